@@ -0,0 +1,13 @@
+-- V002: API keys (personal access tokens)
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    key_id TEXT PRIMARY KEY,
+    token_hash TEXT NOT NULL UNIQUE,
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    principal_json TEXT NOT NULL,
+    scopes TEXT NOT NULL,
+    created_utc TEXT NOT NULL,
+    expiry_epoch INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_api_keys_owner ON api_keys(owner);
@@ -0,0 +1,10 @@
+-- V003: Service accounts (non-human principals managed by administrators)
+
+CREATE TABLE IF NOT EXISTS service_accounts (
+    account_id TEXT PRIMARY KEY,
+    description TEXT,
+    roles_json TEXT NOT NULL,
+    secret_hash TEXT,
+    created_by TEXT NOT NULL,
+    created_utc TEXT NOT NULL
+);
@@ -0,0 +1,11 @@
+-- V004: Local username/password accounts (used by the `local` auth provider)
+
+CREATE TABLE IF NOT EXISTS users (
+    username TEXT PRIMARY KEY,
+    password_hash TEXT NOT NULL,
+    display_name TEXT,
+    email TEXT,
+    groups_json TEXT NOT NULL DEFAULT '[]',
+    is_admin INTEGER NOT NULL DEFAULT 0,
+    created_utc TEXT NOT NULL
+);
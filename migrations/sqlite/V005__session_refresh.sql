@@ -0,0 +1,7 @@
+-- V005: IdP refresh tokens used to silently renew browser sessions
+
+CREATE TABLE IF NOT EXISTS session_refresh (
+    session_id TEXT PRIMARY KEY,
+    refresh_token_enc TEXT NOT NULL,
+    renew_after_epoch INTEGER NOT NULL
+);
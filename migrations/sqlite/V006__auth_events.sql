@@ -0,0 +1,15 @@
+-- V006: Audit trail of authentication events (sign-ins, sign-outs, token issuance)
+
+CREATE TABLE IF NOT EXISTS auth_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp_utc TEXT NOT NULL,
+    timestamp_epoch INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    provider TEXT,
+    subject TEXT,
+    ip TEXT,
+    user_agent TEXT,
+    detail TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_auth_events_timestamp ON auth_events(timestamp_epoch);
+CREATE INDEX IF NOT EXISTS idx_auth_events_subject ON auth_events(subject);
@@ -0,0 +1,4 @@
+-- V007: Request id of the HTTP request that produced each authentication event
+
+ALTER TABLE auth_events ADD COLUMN request_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_auth_events_request_id ON auth_events(request_id);
@@ -0,0 +1,13 @@
+-- V008: Persisted tool call counters, per plugin owner and tool
+
+CREATE TABLE IF NOT EXISTS tool_usage (
+    owner TEXT NOT NULL,
+    plugin_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    calls INTEGER NOT NULL DEFAULT 0,
+    errors INTEGER NOT NULL DEFAULT 0,
+    total_latency_ms REAL NOT NULL DEFAULT 0,
+    last_call_epoch INTEGER,
+    PRIMARY KEY (owner, plugin_id, tool)
+);
+CREATE INDEX IF NOT EXISTS idx_tool_usage_owner ON tool_usage(owner);
@@ -0,0 +1,23 @@
+-- V009: Tool calls awaiting rollup, and their hourly rollups for usage analytics
+
+CREATE TABLE IF NOT EXISTS tool_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp_epoch INTEGER NOT NULL,
+    principal TEXT,
+    plugin_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    latency_ms REAL NOT NULL,
+    is_error INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS tool_usage_rollups (
+    bucket_epoch INTEGER NOT NULL,
+    plugin_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    principal TEXT NOT NULL DEFAULT '',
+    calls INTEGER NOT NULL DEFAULT 0,
+    errors INTEGER NOT NULL DEFAULT 0,
+    total_latency_ms REAL NOT NULL DEFAULT 0,
+    latency_histogram TEXT NOT NULL,
+    PRIMARY KEY (bucket_epoch, plugin_id, tool, principal)
+);
@@ -0,0 +1,8 @@
+-- V010: Leases electing the instance that runs each periodic task when
+-- several instances share the database
+
+CREATE TABLE IF NOT EXISTS task_leases (
+    task TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    expires_epoch INTEGER NOT NULL
+);
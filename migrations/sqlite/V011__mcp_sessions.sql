@@ -0,0 +1,9 @@
+-- V011: Streamable HTTP MCP sessions shared by the instances using the
+-- database, so that any of them can continue a session
+
+CREATE TABLE IF NOT EXISTS mcp_sessions (
+    session_id TEXT PRIMARY KEY,
+    initialize_request TEXT NOT NULL,
+    created_epoch INTEGER NOT NULL,
+    last_seen_epoch INTEGER NOT NULL
+);
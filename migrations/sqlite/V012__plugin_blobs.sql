@@ -0,0 +1,5 @@
+-- V012: Large plugin payloads are kept in a content-addressed directory next
+-- to the database; their rows keep only the SHA-256 digest
+
+ALTER TABLE plugins ADD COLUMN plugin_digest TEXT;
+CREATE INDEX IF NOT EXISTS idx_plugins_digest ON plugins(plugin_digest);
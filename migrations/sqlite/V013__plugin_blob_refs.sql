@@ -0,0 +1,13 @@
+-- V013: Plugin payloads are stored once per SHA-256 digest, inline or in the
+-- blob directory, and counted by the plugins referencing them
+
+CREATE TABLE IF NOT EXISTS plugin_blobs (
+    digest TEXT PRIMARY KEY,
+    data BLOB,
+    ref_count INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO plugin_blobs(digest, data, ref_count)
+    SELECT plugin_digest, NULL, COUNT(*) FROM plugins
+    WHERE plugin_digest IS NOT NULL
+    GROUP BY plugin_digest;
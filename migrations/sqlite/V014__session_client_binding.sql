@@ -0,0 +1,3 @@
+-- V014: Hash of the client attributes (IP, User-Agent) a session is bound to
+
+ALTER TABLE sessions ADD COLUMN client_binding TEXT;
@@ -0,0 +1,6 @@
+-- V015: Global id of the principal owning a session, used to cap the
+-- number of sessions per user. Sessions saved before have none and are not
+-- counted.
+
+ALTER TABLE sessions ADD COLUMN principal_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_sessions_principal_id ON sessions(principal_id);
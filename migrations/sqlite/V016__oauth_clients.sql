@@ -0,0 +1,9 @@
+-- V016: OAuth clients registered through dynamic client registration
+-- (RFC 7591)
+
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    client_id TEXT PRIMARY KEY,
+    secret_hash TEXT,
+    metadata_json TEXT NOT NULL,
+    created_utc TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- V017: Console display preferences, one row per principal global id
+
+CREATE TABLE IF NOT EXISTS user_preferences (
+    principal_id TEXT PRIMARY KEY,
+    preferences_json TEXT NOT NULL,
+    updated_utc TEXT NOT NULL
+);
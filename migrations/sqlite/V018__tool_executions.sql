@@ -0,0 +1,17 @@
+-- V018: History of tool executions, kept as a ring buffer of the latest calls
+
+CREATE TABLE IF NOT EXISTS tool_executions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp_utc TEXT NOT NULL,
+    timestamp_epoch INTEGER NOT NULL,
+    principal TEXT,
+    plugin_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    duration_ms REAL NOT NULL,
+    status TEXT NOT NULL,
+    error_code TEXT,
+    args_hash TEXT NOT NULL,
+    request_id TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_tool_executions_principal ON tool_executions(principal, id);
+CREATE INDEX IF NOT EXISTS idx_tool_executions_timestamp ON tool_executions(timestamp_epoch);
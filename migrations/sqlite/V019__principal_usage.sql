@@ -0,0 +1,11 @@
+-- V019: Monthly usage counters per principal, for quotas and chargeback
+
+CREATE TABLE IF NOT EXISTS principal_usage (
+    principal TEXT NOT NULL,
+    month TEXT NOT NULL,
+    calls INTEGER NOT NULL DEFAULT 0,
+    compute_ms REAL NOT NULL DEFAULT 0,
+    bytes_returned INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (principal, month)
+);
+CREATE INDEX IF NOT EXISTS idx_principal_usage_month ON principal_usage(month);
@@ -0,0 +1,413 @@
+//! Subcommands of the `ark` binary.
+//!
+//! Without a subcommand `ark` runs the server; the subcommands are tools that
+//! run and exit without starting it.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, anyhow, bail};
+use clap::Subcommand;
+use serde_json::json;
+
+use crate::config::{
+    init::{AuthPreset, InitOptions, starter_config},
+    models::McpTransport,
+    plugins::{ArkPlugin, PluginManifest},
+};
+use crate::doctor::{Status, diagnose};
+use crate::plugins::{ToolSet, read_plugin_data, wasm::WasmHandler};
+use crate::server::persist::{Database, PluginRecord};
+use crate::server::reload::ConfigLoader;
+use crate::server::signing::{KeyAlgorithm, generate_key};
+use crate::utility::set_secure_file_permissions;
+
+/// Owner of plugins added without an authenticated caller.
+const ANONYMOUS_OWNER: &str = "*/*/*";
+
+/// Tools run instead of the server.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Configuration file tools
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage the plugins stored in the database, without a running server
+    Plugin {
+        /// Database file (default: ARK_DB_PATH or the platform default)
+        #[arg(
+            long = "database",
+            value_name = "FILE",
+            env = "ARK_DB_PATH",
+            global = true
+        )]
+        database: Option<PathBuf>,
+        #[command(subcommand)]
+        command: PluginCommand,
+    },
+    /// Generate a token signing key, and optionally a self-signed certificate
+    Keygen {
+        /// Key type
+        #[arg(long = "algorithm", value_enum, default_value_t = KeyAlgorithm::Ec)]
+        algorithm: KeyAlgorithm,
+        /// Private key file to write
+        #[arg(long = "key", value_name = "FILE", default_value = "signing.key")]
+        key: PathBuf,
+        /// Also write a self-signed certificate to this file
+        #[arg(long = "cert", value_name = "FILE")]
+        cert: Option<PathBuf>,
+        /// Common name of the certificate subject
+        #[arg(long = "common-name", default_value = "Ark token signing")]
+        common_name: String,
+        /// Days the certificate is valid for
+        #[arg(long = "days", default_value_t = 365)]
+        days: u32,
+        /// Overwrite existing files
+        #[arg(long = "force")]
+        force: bool,
+    },
+    /// Print the completion script for a shell
+    Completions {
+        /// Shell to complete in
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page, in roff format
+    Man,
+    /// Check the configuration and environment, with hints for any problem
+    Doctor {
+        /// Database file (default: ARK_DB_PATH or the platform default)
+        #[arg(long = "database", value_name = "FILE", env = "ARK_DB_PATH")]
+        database: Option<PathBuf>,
+    },
+}
+
+/// `ark config` subcommands.
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Write a commented starter configuration
+    Init {
+        /// Set up authentication with this identity provider
+        #[arg(long = "auth", value_enum)]
+        auth: Option<AuthPreset>,
+        /// Transport the server will be started with
+        #[arg(long = "transport", value_enum, default_value_t = McpTransport::StreamableHTTP)]
+        transport: McpTransport,
+        /// Serve HTTPS with a key and certificate
+        #[arg(long = "tls")]
+        tls: bool,
+        /// File to write; standard output when omitted
+        #[arg(long = "output", short = 'o', value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Overwrite the output file if it exists
+        #[arg(long = "force")]
+        force: bool,
+    },
+}
+
+/// `ark plugin` subcommands.
+///
+/// These work on the plugins registered through the management API, which the
+/// server loads from the database at startup; plugins in the configuration file
+/// are not stored there.
+#[derive(Subcommand, Debug, Clone)]
+pub enum PluginCommand {
+    /// List the stored plugins
+    List,
+    /// Fetch and verify a plugin, then store it
+    Add {
+        /// Plugin name
+        name: String,
+        /// Location: oci://, http(s)://, file:// URL or local path
+        location: String,
+        /// Allow insecure transports (e.g. plain HTTP)
+        #[arg(long = "insecure")]
+        insecure: bool,
+        /// Owner, in the provider:tenant:userid format (default: anonymous)
+        #[arg(long = "owner")]
+        owner: Option<String>,
+        /// Replace a stored plugin with the same name and owner
+        #[arg(long = "force")]
+        force: bool,
+    },
+    /// Remove a stored plugin
+    Remove {
+        /// Plugin name
+        name: String,
+        /// Only remove the plugin of this owner
+        #[arg(long = "owner")]
+        owner: Option<String>,
+    },
+    /// Show the tools of a stored plugin, or of a plugin location without
+    /// storing it
+    Inspect {
+        /// Stored plugin name, or location to fetch and verify
+        plugin: String,
+    },
+}
+
+/// Runs a subcommand; `cli` describes the whole command line, for
+/// completions and the man page, and `loader` loads the configuration as the
+/// server would.
+pub async fn run(
+    command: Command,
+    mut cli: clap::Command,
+    loader: ConfigLoader,
+) -> anyhow::Result<()> {
+    match command {
+        Command::Config {
+            command:
+                ConfigCommand::Init {
+                    auth,
+                    transport,
+                    tls,
+                    output,
+                    force,
+                },
+        } => {
+            let text = starter_config(&InitOptions {
+                auth,
+                transport,
+                tls,
+            });
+            match output {
+                None => print!("{}", text),
+                Some(path) => {
+                    if path.exists() && !force {
+                        anyhow::bail!("{} exists; use --force to overwrite it", path.display());
+                    }
+                    std::fs::write(&path, text)
+                        .with_context(|| format!("Cannot write {}", path.display()))?;
+                    eprintln!("Wrote {}", path.display());
+                }
+            }
+            Ok(())
+        }
+        Command::Plugin { database, command } => run_plugin(database, command).await,
+        Command::Keygen {
+            algorithm,
+            key,
+            cert,
+            common_name,
+            days,
+            force,
+        } => {
+            for path in std::iter::once(&key).chain(cert.as_ref()) {
+                if path.exists() && !force {
+                    bail!("{} exists; use --force to overwrite it", path.display());
+                }
+            }
+            let (key_pem, cert_pem) = generate_key(
+                algorithm,
+                cert.as_ref().map(|_| (common_name.as_str(), days)),
+            )?;
+            std::fs::write(&key, key_pem)
+                .with_context(|| format!("Cannot write {}", key.display()))?;
+            set_secure_file_permissions(&key)?;
+            if let (Some(path), Some(pem)) = (&cert, cert_pem) {
+                std::fs::write(path, pem)
+                    .with_context(|| format!("Cannot write {}", path.display()))?;
+            }
+
+            eprintln!("Wrote {}; add to the configuration:", key.display());
+            let absolute = |p: &PathBuf| std::path::absolute(p).unwrap_or_else(|_| p.clone());
+            println!("token_signing:");
+            println!("  source: local");
+            println!("  key: {}", absolute(&key).display());
+            if let Some(path) = &cert {
+                println!("  cert: {}", absolute(path).display());
+            }
+            Ok(())
+        }
+        Command::Completions { shell } => {
+            let name = cli.get_name().to_string();
+            clap_complete::generate(shell, &mut cli, name, &mut std::io::stdout());
+            Ok(())
+        }
+        Command::Man => {
+            clap_mangen::Man::new(cli).render(&mut std::io::stdout())?;
+            Ok(())
+        }
+        Command::Doctor { database } => {
+            let findings = diagnose(loader().await, database).await;
+            for finding in &findings {
+                let label = match finding.status {
+                    Status::Ok => "[ ok ]",
+                    Status::Warning => "[warn]",
+                    Status::Failed => "[fail]",
+                };
+                println!("{} {}: {}", label, finding.check, finding.message);
+                if let Some(hint) = &finding.hint {
+                    println!("       hint: {}", hint);
+                }
+            }
+            let failed = findings
+                .iter()
+                .filter(|f| f.status == Status::Failed)
+                .count();
+            if failed > 0 {
+                bail!("{} check(s) failed", failed);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn run_plugin(database: Option<PathBuf>, command: PluginCommand) -> anyhow::Result<()> {
+    let db = match database {
+        Some(path) => Database::with_path(path)?,
+        None => Database::new()?,
+    };
+    match command {
+        PluginCommand::List => {
+            let records = db.list_plugins_async().await?;
+            if records.is_empty() {
+                eprintln!("No stored plugins");
+                return Ok(());
+            }
+            println!("{:<24} {:<32} {:<20} SOURCE", "NAME", "OWNER", "ADDED");
+            for record in records {
+                println!(
+                    "{:<24} {:<32} {:<20} {}",
+                    record.plugin_id,
+                    record.owner,
+                    record.date_added_utc.format("%Y-%m-%d %H:%M:%S"),
+                    record.plugin_path.as_deref().unwrap_or("-")
+                );
+            }
+            Ok(())
+        }
+        PluginCommand::Add {
+            name,
+            location,
+            insecure,
+            owner,
+            force,
+        } => {
+            let owner = owner.unwrap_or_else(|| ANONYMOUS_OWNER.to_string());
+            if !force
+                && db
+                    .get_plugin_async(owner.clone(), name.clone())
+                    .await?
+                    .is_some()
+            {
+                bail!(
+                    "Plugin '{}' is already stored; use --force to replace it",
+                    name
+                );
+            }
+            let plugin = plugin_at(&name, &location, insecure)?;
+            let result = read_plugin_data(&plugin)
+                .await
+                .with_context(|| format!("Cannot load plugin from {}", location))?;
+            db.save_plugin_record_async(PluginRecord {
+                owner,
+                plugin_id: name.clone(),
+                plugin_name: Some(name.clone()),
+                plugin_path: plugin.url.as_ref().map(|u| u.to_string()),
+                plugin_data: result.raw_bytes,
+                metadata: json!({
+                    "manifest": plugin.manifest,
+                    "insecure": plugin.insecure,
+                }),
+                date_added_utc: chrono::Utc::now(),
+            })
+            .await?;
+            print_tools(&result.toolset);
+            eprintln!(
+                "Stored plugin '{}'; it is loaded when the server next starts",
+                name
+            );
+            Ok(())
+        }
+        PluginCommand::Remove { name, owner } => {
+            let records: Vec<PluginRecord> = db
+                .list_plugins_async()
+                .await?
+                .into_iter()
+                .filter(|r| r.plugin_id == name && owner.as_ref().is_none_or(|o| *o == r.owner))
+                .collect();
+            if records.is_empty() {
+                bail!("No stored plugin named '{}'", name);
+            }
+            for record in records {
+                db.delete_plugin_async(record.owner.clone(), record.plugin_id)
+                    .await?;
+                eprintln!("Removed plugin '{}' of {}", name, record.owner);
+            }
+            Ok(())
+        }
+        PluginCommand::Inspect { plugin } => {
+            let stored = db
+                .list_plugins_async()
+                .await?
+                .into_iter()
+                .find(|r| r.plugin_id == plugin);
+            let toolset = match stored {
+                Some(record) => {
+                    println!("Name:    {}", record.plugin_id);
+                    println!("Owner:   {}", record.owner);
+                    println!("Source:  {}", record.plugin_path.as_deref().unwrap_or("-"));
+                    println!("Added:   {}", record.date_added_utc.to_rfc3339());
+                    stored_toolset(&record).await?
+                }
+                None => {
+                    let config = plugin_at("inspect", &plugin, false)?;
+                    let result = read_plugin_data(&config)
+                        .await
+                        .with_context(|| format!("Cannot load plugin from {}", plugin))?;
+                    if let Some(bytes) = &result.raw_bytes {
+                        println!("Size:    {} bytes", bytes.len());
+                    }
+                    result.toolset
+                }
+            };
+            print_tools(&toolset);
+            Ok(())
+        }
+    }
+}
+
+/// Plugin configuration for `location`, a URL or local path as in the
+/// configuration file.
+fn plugin_at(name: &str, location: &str, insecure: bool) -> anyhow::Result<ArkPlugin> {
+    serde_json::from_value(json!({
+        "name": name,
+        "url": location,
+        "insecure": insecure,
+    }))
+    .map_err(|e| anyhow!("Invalid plugin location {}: {}", location, e))
+}
+
+/// Tools of a stored plugin, from the stored bytes when there are any.
+async fn stored_toolset(record: &PluginRecord) -> anyhow::Result<ToolSet> {
+    let mut plugin = plugin_at(
+        &record.plugin_id,
+        record.plugin_path.as_deref().unwrap_or("file:///"),
+        record.metadata["insecure"].as_bool().unwrap_or(false),
+    )?;
+    plugin.manifest = serde_json::from_value::<Option<PluginManifest>>(
+        record.metadata.get("manifest").cloned().unwrap_or_default(),
+    )
+    .unwrap_or_default();
+    match &record.plugin_data {
+        Some(bytes) => {
+            println!("Size:    {} bytes (stored)", bytes.len());
+            WasmHandler::new(bytes.clone(), &plugin.name, &plugin.manifest)?
+                .describe(&plugin)
+                .await
+        }
+        None => Ok(read_plugin_data(&plugin).await?.toolset),
+    }
+}
+
+fn print_tools(toolset: &ToolSet) {
+    println!("Tools:");
+    for tool in &toolset.tools {
+        println!(
+            "  {:<24} {}",
+            tool.name,
+            tool.description.as_deref().unwrap_or("")
+        );
+    }
+}
@@ -62,6 +62,13 @@ pub(crate) fn default_readyz_path() -> String {
     "/readyz".to_string()
 }
 
+/// Default startup probe path.
+///
+/// Returns `"/startupz"`.
+pub(crate) fn default_startupz_path() -> String {
+    "/startupz".to_string()
+}
+
 /// Default true value.
 ///
 /// Returns `true`.
@@ -96,6 +103,16 @@ pub(crate) fn default_readyz() -> ManagementPathConfig {
     }
 }
 
+/// Default startup probe configuration.
+///
+/// Returns a `ManagementPathConfig` with `/startupz` path and disabled by default.
+pub(crate) fn default_startupz() -> ManagementPathConfig {
+    ManagementPathConfig {
+        path: Some(default_startupz_path()),
+        enabled: false,
+    }
+}
+
 /// Default management response type.
 ///
 /// Returns `"json"`.
@@ -103,13 +120,108 @@ pub(crate) fn default_mgmt_response_type() -> String {
     "json".to_string()
 }
 
+/// Default admin console directory.
+///
+/// Returns `"www/dist"`, where the console build writes its output.
+pub(crate) fn default_console_path() -> String {
+    "www/dist".to_string()
+}
+
 // ----------------- Auth / Session Defaults -----------------
 pub(crate) fn default_session_timeout() -> u64 {
     3600
 }
+pub(crate) fn default_session_max_lifetime() -> u64 {
+    7 * 24 * 3600
+}
+/// Default time a replaced signing key stays published: the ID token lifetime.
+pub(crate) fn default_signing_key_retain_seconds() -> u64 {
+    3600
+}
 pub(crate) fn default_session_cookie_name() -> String {
     "ark_session".to_string()
 }
 pub(crate) fn default_cookie_same_site() -> String {
     "Lax".to_string()
 }
+pub(crate) fn default_lockout_max_attempts() -> u32 {
+    5
+}
+pub(crate) fn default_lockout_max_attempts_per_ip() -> u32 {
+    20
+}
+pub(crate) fn default_lockout_backoff_base() -> u64 {
+    1
+}
+pub(crate) fn default_lockout_seconds() -> u64 {
+    900
+}
+pub(crate) fn default_audit_retention_days() -> u32 {
+    90
+}
+pub(crate) fn default_owner_label_max_owners() -> usize {
+    50
+}
+pub(crate) fn default_owner_label_max_length() -> usize {
+    12
+}
+pub(crate) fn default_push_gateway_job() -> String {
+    "ark".to_string()
+}
+pub(crate) fn default_statsd_address() -> String {
+    "127.0.0.1:8125".to_string()
+}
+pub(crate) fn default_webhook_failure_threshold() -> u32 {
+    3
+}
+pub(crate) fn default_header_timeout_secs() -> u64 {
+    30
+}
+pub(crate) fn default_watch_interval_secs() -> u64 {
+    10
+}
+pub(crate) fn default_wasm_max_instances() -> u32 {
+    16
+}
+pub(crate) fn default_download_connect_timeout_ms() -> u64 {
+    10_000
+}
+pub(crate) fn default_download_read_timeout_ms() -> u64 {
+    30_000
+}
+pub(crate) fn default_download_retries() -> u32 {
+    2
+}
+pub(crate) fn default_download_retry_backoff_ms() -> u64 {
+    500
+}
+pub(crate) fn default_max_plugin_bytes() -> u64 {
+    128 * 1024 * 1024
+}
+pub(crate) fn default_scratch_path() -> std::path::PathBuf {
+    std::path::PathBuf::from("/tmp")
+}
+pub(crate) fn default_scratch_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+pub(crate) fn default_api_body_limit() -> usize {
+    2 * 1024 * 1024
+}
+pub(crate) fn default_mcp_body_limit() -> usize {
+    4 * 1024 * 1024
+}
+pub(crate) fn default_upload_body_limit() -> usize {
+    64 * 1024 * 1024
+}
+pub(crate) fn default_syslog_identifier() -> String {
+    "ark".to_string()
+}
+pub(crate) fn default_acme_directory() -> String {
+    "https://acme-v02.api.letsencrypt.org/directory".to_string()
+}
+pub(crate) fn default_acme_http_bind_address() -> String {
+    "0.0.0.0:80".to_string()
+}
+pub(crate) fn default_acme_renew_before_days() -> u32 {
+    30
+}
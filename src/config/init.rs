@@ -0,0 +1,242 @@
+//! Starter configuration for `ark config init`.
+//!
+//! The generated file is commented throughout and covers the common
+//! settings; `config.example.yaml` documents every setting.
+
+use clap::ValueEnum;
+
+use super::models::McpTransport;
+use crate::server::constants::{DEFAULT_MCP_BIND_ADDRESS, DEFAULT_MGMT_BIND_ADDRESS};
+
+/// Identity provider the starter configuration is set up for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuthPreset {
+    /// Local username/password accounts stored in the database.
+    Local,
+    /// Microsoft Entra ID.
+    Entra,
+    /// Google.
+    Google,
+    /// Okta.
+    Okta,
+    /// Auth0.
+    Auth0,
+    /// Keycloak.
+    Keycloak,
+    /// Any other OpenID Connect provider.
+    Oidc,
+    /// SAML 2.0 identity provider.
+    Saml,
+    /// LDAP or Active Directory.
+    Ldap,
+}
+
+/// Choices tailoring the starter configuration.
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    /// Identity provider; authentication stays disabled without one.
+    pub auth: Option<AuthPreset>,
+    /// Transport the server will be started with.
+    pub transport: McpTransport,
+    /// Serve HTTPS with a key and certificate.
+    pub tls: bool,
+}
+
+/// Renders the starter configuration.
+pub fn starter_config(options: &InitOptions) -> String {
+    let scheme = if options.tls { "https" } else { "http" };
+    let transport = options
+        .transport
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default();
+    let mut out = format!(
+        r#"# Ark MCP server configuration, generated by `ark config init`.
+# config.example.yaml documents every setting.
+#
+# The MCP transport is chosen when starting the server:
+#   ark --config-file <this file> --transport {transport}
+# (or ARK_TRANSPORT={transport}).
+
+# Management API, admin console and health endpoints.
+management_server:
+  # Address to listen on; use 0.0.0.0 to accept remote connections.
+  bind_address: "{DEFAULT_MGMT_BIND_ADDRESS}"
+  # Origins allowed to call the API from a browser (comma-separated).
+  cors: "{scheme}://localhost:8000"
+  # Kubernetes-style probes.
+  livez:
+    enabled: true
+    path: /livez
+  readyz:
+    enabled: true
+    path: /readyz
+"#
+    );
+
+    out.push('\n');
+    if options.transport == McpTransport::Stdio {
+        out.push_str(&format!(
+            r#"# MCP endpoints, used with --transport streamable-http.
+# mcp_server:
+#   bind_address: "{DEFAULT_MCP_BIND_ADDRESS}"
+#   cors: "{scheme}://localhost:6274"
+"#
+        ));
+    } else {
+        out.push_str(&format!(
+            r#"# MCP endpoints.
+mcp_server:
+  # Address to listen on; use 0.0.0.0 to accept remote connections.
+  bind_address: "{DEFAULT_MCP_BIND_ADDRESS}"
+  # Origins allowed to connect from a browser, e.g. the MCP inspector.
+  cors: "{scheme}://localhost:6274"
+"#
+        ));
+    }
+
+    out.push_str(
+        r#"
+# Plugins loaded at startup, from OCI registries, URLs or local files.
+plugins:
+  - name: time
+    url: oci://ghcr.io/vpopescu/ark-mcp-plugin-time:v0.0.1
+#  - name: hash
+#    url: file:///etc/ark/plugins/hash_plugin.wasm
+"#,
+    );
+
+    if options.tls {
+        out.push_str(
+            r#"
+# HTTPS for both listeners.
+tls:
+  key: /etc/ark/tls/server.key
+  cert: /etc/ark/tls/server.pem
+"#,
+        );
+    }
+
+    out.push('\n');
+    match options.auth {
+        None => out.push_str(
+            r#"# Authentication; see config.example.yaml for the identity providers.
+auth:
+  enabled: false
+"#,
+        ),
+        Some(preset) => {
+            let (name, provider) = provider(preset);
+            out.push_str(&format!(
+                r#"# Authentication.
+auth:
+  enabled: true
+  provider: {name}
+  session:
+    # Session cookies are only sent over HTTPS when true.
+    cookie_secure: {}
+  providers:
+{provider}"#,
+                options.tls
+            ));
+        }
+    }
+    // The templates carry the line endings of the source checkout
+    out.replace("\r\n", "\n")
+}
+
+/// Provider name and its entry under `auth.providers`.
+fn provider(preset: AuthPreset) -> (&'static str, &'static str) {
+    match preset {
+        AuthPreset::Local => (
+            "local",
+            r#"    # Accounts are managed at /api/v1/admin/users. Set
+    # ARK_AUTH_LOCAL_ADMIN_PASSWORD on first start to create the "admin" user.
+    - name: local
+"#,
+        ),
+        AuthPreset::Entra => (
+            "entra",
+            r#"    - name: entra
+      # Application (client) ID of the app registration.
+      client_id: "<application id>"
+      # Secret of the app registration, e.g. a mounted Kubernetes secret.
+      client_secret_file: /run/secrets/ark-client-secret
+      authority: https://login.microsoftonline.com/<tenant id>/v2.0
+      # groups:
+      #   # Object ID of the group whose members are administrators.
+      #   admin: "<group object id>"
+"#,
+        ),
+        AuthPreset::Google => (
+            "google",
+            r#"    - name: google
+      client_id: "<client id>.apps.googleusercontent.com"
+      client_secret_file: /run/secrets/ark-client-secret
+      authority: https://accounts.google.com
+"#,
+        ),
+        AuthPreset::Okta => (
+            "okta",
+            r#"    - name: okta
+      client_id: "<client id>"
+      client_secret_file: /run/secrets/ark-client-secret
+      # Org or custom authorization server.
+      authority: https://<org>.okta.com/oauth2/default
+"#,
+        ),
+        AuthPreset::Auth0 => (
+            "auth0",
+            r#"    - name: auth0
+      client_id: "<client id>"
+      client_secret_file: /run/secrets/ark-client-secret
+      authority: https://<tenant>.auth0.com
+"#,
+        ),
+        AuthPreset::Keycloak => (
+            "keycloak",
+            r#"    - name: keycloak
+      client_id: ark
+      client_secret_file: /run/secrets/ark-client-secret
+      # Must include the realm.
+      authority: https://<keycloak host>/realms/<realm>
+"#,
+        ),
+        AuthPreset::Oidc => (
+            "oidc",
+            r#"    - name: oidc
+      client_id: "<client id>"
+      client_secret_file: /run/secrets/ark-client-secret
+      # Issuer URL; endpoints are found by OIDC discovery.
+      authority: https://<issuer>
+"#,
+        ),
+        AuthPreset::Saml => (
+            "saml",
+            r#"    # Register Ark with the IdP using the metadata at /auth/saml/metadata.
+    - name: saml
+      kind: saml
+      saml:
+        idp_entity_id: https://<idp>/saml
+        idp_sso_url: https://<idp>/saml/sso
+        # PEM file, or the certificate inline.
+        idp_certificate: /etc/ark/idp-signing.crt
+        sp_entity_id: https://<ark host>/saml
+"#,
+        ),
+        AuthPreset::Ldap => (
+            "ldap",
+            r#"    # Users sign in with the password form at /auth/login/local.
+    - name: ldap
+      kind: ldap
+      ldap:
+        url: ldaps://<directory host>
+        bind_dn: "cn=ark,ou=services,dc=example,dc=com"
+        bind_password_file: /run/secrets/ark-ldap-password
+        user_base_dn: "dc=example,dc=com"
+        # Use (sAMAccountName={username}) for Active Directory.
+        user_filter: "(uid={username})"
+"#,
+        ),
+    }
+}
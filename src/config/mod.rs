@@ -1,9 +1,9 @@
 pub use models::McpTransport;
 #[cfg(feature = "schemars")]
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
-use std::{path::Path, path::PathBuf, sync::Arc};
+use std::{collections::BTreeMap, path::Path, path::PathBuf, sync::Arc};
 use thiserror::Error;
 
 use models::{ManagementEndpointConfig, McpEndpointConfig};
@@ -13,8 +13,11 @@ use crate::config::models::TlsConfig;
 use crate::state::ArkState;
 
 pub mod defaults;
+pub mod init;
 pub mod models;
+pub mod overlay;
 pub mod plugins;
+pub mod secrets;
 
 // Root configuration for the Ark server.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -50,6 +53,11 @@ pub struct ArkConfig {
     /// MCP server configuration.
     pub mcp_server: Option<McpEndpointConfig>,
 
+    /// Register the built-in diagnostic tools alongside the loaded plugins,
+    /// not only when none loads (optional)
+    #[serde(default)]
+    pub always_load_builtin: bool,
+
     /// List of plugins to load at startup.
     #[serde(default)]
     pub plugins: Vec<ArkPlugin>,
@@ -64,31 +72,92 @@ pub struct ArkConfig {
     /// Optional token signing configuration (controls local signing/JWKS)
     #[serde(default)]
     pub token_signing: Option<models::TokenSigningConfig>,
+    /// Logging configuration (optional)
+    #[serde(default)]
+    pub logging: Option<models::LoggingConfig>,
+    /// Outbound webhook notifications (optional)
+    #[serde(default)]
+    pub webhooks: Option<models::WebhooksConfig>,
+    /// Request body size limits (optional)
+    #[serde(default)]
+    pub body_limits: Option<models::BodyLimitsConfig>,
+    /// Monthly usage quotas per principal (optional)
+    #[serde(default)]
+    pub quotas: Option<models::QuotasConfig>,
+    /// Vault server for `vault:` secret references (optional)
+    #[serde(default)]
+    pub vault: Option<models::VaultConfig>,
+    /// Listener settings for deploys without downtime (optional)
+    #[serde(default)]
+    pub listeners: Option<models::ListenersConfig>,
+    /// Reload when the configuration or TLS files change (optional)
+    #[serde(default)]
+    pub watch: Option<models::WatchConfig>,
+    /// WASM engine tuning (optional)
+    #[serde(default)]
+    pub wasm_engine: Option<models::WasmEngineConfig>,
+    /// Timeouts, retries and proxy for plugins downloaded over HTTP(S)
+    /// (optional)
+    #[serde(default)]
+    pub plugin_downloads: Option<models::PluginDownloadConfig>,
+    /// Addresses or CIDR ranges of reverse proxies whose `X-Forwarded-For`,
+    /// `X-Forwarded-Proto` and `X-Forwarded-Host` headers are honored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_proxies: Vec<String>,
+    /// Files or directories merged over this file, relative to its directory
+    /// (optional; see [`overlay`])
+    #[serde(default, skip_serializing)]
+    pub include: Vec<String>,
+    /// Named settings merged over the others when selected with `--profile`
+    /// or `ARK_PROFILE`, e.g. `dev`, `staging` and `prod` (optional)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, serde_json::Value>,
+    /// Reject unknown settings instead of ignoring them, as with
+    /// `--strict-config` (optional)
+    #[serde(default)]
+    pub strict: bool,
 }
 
+/// Replacement for secret values in [`ArkConfig::redacted`].
+pub const REDACTED: &str = "********";
+
+/// Fields holding secrets rather than references to them.
+const SECRET_FIELDS: &[&str] = &[
+    "password",
+    "bind_password",
+    "client_secret",
+    "bearer_token",
+    "token",
+    "secret",
+    "encryption_key",
+    "headers",
+];
+
 impl ArkConfig {
     /// Compute the default configuration file path.
+    ///
+    /// In the default directory, the first existing of `config.yaml`,
+    /// `config.toml` and `config.json` is used.
     pub fn default_path() -> PathBuf {
         // Allow override via environment variable
         if let Some(override_path) = std::env::var_os("ARK_CONFIG_PATH") {
             return PathBuf::from(override_path);
         }
-        if cfg!(target_os = "windows") {
-            // %HOME%/ark/config.yaml or %USERPROFILE%/ark/config.yaml
+        let dir = if cfg!(target_os = "windows") {
+            // %HOME%/ark or %USERPROFILE%/ark
             let home = std::env::var_os("HOME")
                 .or_else(|| std::env::var_os("USERPROFILE"))
                 .unwrap_or_default();
-            let mut p = PathBuf::from(home);
-            p.push("ark");
-            p.push("config.yaml");
-            p
+            PathBuf::from(home).join("ark")
         } else {
-            // /etc/ark/config.yaml
-            PathBuf::from("/")
-                .join("etc")
-                .join("ark")
-                .join("config.yaml")
-        }
+            // /etc/ark
+            PathBuf::from("/").join("etc").join("ark")
+        };
+        ["config.yaml", "config.toml", "config.json"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|p| p.exists())
+            .unwrap_or_else(|| dir.join("config.yaml"))
     }
 
     /// Create a default configuration when no file is present.
@@ -106,9 +175,23 @@ impl ArkConfig {
             tls: None,
             management_server: Some(ManagementEndpointConfig::default()),
             mcp_server: Some(McpEndpointConfig::default()),
+            always_load_builtin: false,
             plugins: Vec::new(),
             auth: None,
             token_signing: None,
+            logging: None,
+            webhooks: None,
+            body_limits: None,
+            quotas: None,
+            vault: None,
+            listeners: None,
+            watch: None,
+            wasm_engine: None,
+            plugin_downloads: None,
+            trusted_proxies: Vec::new(),
+            include: Vec::new(),
+            profiles: BTreeMap::new(),
+            strict: false,
         }
     }
 
@@ -125,9 +208,12 @@ impl ArkConfig {
     /// * `use_sigstore_tuf_data` - Whether to use Sigstore TUF data.
     /// * `disable_api` - Optional flag to disable plugin API.
     /// * `management_bind_address` - Optional bind address for management server.
+    /// * `profile` - Optional profile merged over the file's shared settings.
+    /// * `strict` - Reject unknown settings (also enabled by `strict: true` in the file).
     ///
     /// # Returns
     /// The loaded and overridden configuration, or a ConfigError.
+    #[allow(clippy::too_many_arguments)]
     pub fn load_with_overrides(
         config_path: Option<PathBuf>,
         //log_level: Option<LogLevel>,
@@ -137,9 +223,12 @@ impl ArkConfig {
         use_sigstore_tuf_data: bool,
         disable_api: Option<bool>,
         management_bind_address: Option<String>,
+        profile: Option<String>,
+        strict: bool,
     ) -> Result<Self, ConfigError> {
         let path = config_path.unwrap_or_else(Self::default_path);
-        let mut cfg = Self::load_config_from_file_or_defaults(&path)?;
+        let mut cfg = Self::load_config_from_file_or_defaults(&path, strict)?
+            .apply_profile(&path, profile.as_deref())?;
 
         Self::apply_cli_overrides(
             &mut cfg,
@@ -162,15 +251,44 @@ impl ArkConfig {
     ///
     /// # Arguments
     /// * `path` - Path to check for config file
+    /// * `strict` - Reject unknown settings even if the file doesn't ask to
     ///
     /// # Returns
     /// Loaded config or defaults
-    fn load_config_from_file_or_defaults(path: &Path) -> Result<Self, ConfigError> {
+    fn load_config_from_file_or_defaults(path: &Path, strict: bool) -> Result<Self, ConfigError> {
         if path.exists() {
             tracing::debug!("Reading from configuration file {:?}", path);
             let text = std::fs::read_to_string(path)
                 .map_err(|e| ConfigError::Parse(path.to_path_buf(), format!("I/O error: {}", e)))?;
-            let parsed_cfg = Self::parse_yaml_with_path(path, &text)?;
+            let mut parsed_cfg: Self = Self::parse_with_path(path, &text)?;
+            let strict = strict || parsed_cfg.strict;
+            if strict {
+                Self::reject_unknown_settings(path, &text)?;
+            }
+
+            let fragments = overlay::fragments(path, &parsed_cfg.include)?;
+            if !fragments.is_empty() {
+                // Not serialized, so kept aside for the file watcher
+                let include = std::mem::take(&mut parsed_cfg.include);
+                let mut merged = serde_json::to_value(&parsed_cfg)
+                    .map_err(|e| ConfigError::Parse(path.to_path_buf(), e.to_string()))?;
+                for fragment in &fragments {
+                    tracing::debug!("Merging configuration fragment {:?}", fragment);
+                    let text = std::fs::read_to_string(fragment).map_err(|e| {
+                        ConfigError::Parse(fragment.clone(), format!("I/O error: {}", e))
+                    })?;
+                    // Parsed as a configuration first for errors with locations
+                    Self::parse_with_path::<Self>(fragment, &text)?;
+                    if strict {
+                        Self::reject_unknown_settings(fragment, &text)?;
+                    }
+                    overlay::merge(&mut merged, Self::parse_with_path(fragment, &text)?);
+                }
+                parsed_cfg = serde_json::from_value(merged).map_err(|e| {
+                    ConfigError::Parse(path.to_path_buf(), format!("merged fragments: {}", e))
+                })?;
+                parsed_cfg.include = include;
+            }
 
             // Ensure defaults for missing sections
             Ok(Self {
@@ -188,6 +306,40 @@ impl ArkConfig {
         }
     }
 
+    /// Merge the named profile over the shared settings, as for config.d
+    /// fragments, and drop the profile definitions.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the config file (for error messages)
+    /// * `profile` - Profile to apply, if any
+    fn apply_profile(mut self, path: &Path, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let profiles = std::mem::take(&mut self.profiles);
+        let Some(name) = profile else {
+            return Ok(self);
+        };
+        let Some(overrides) = profiles.get(name) else {
+            let defined: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            return Err(ConfigError::Parse(
+                path.to_path_buf(),
+                format!(
+                    "profile '{}' is not defined (defined: {})",
+                    name,
+                    defined.join(", ")
+                ),
+            ));
+        };
+        tracing::debug!("Applying configuration profile '{}'", name);
+
+        let mut merged = serde_json::to_value(&self)
+            .map_err(|e| ConfigError::Parse(path.to_path_buf(), e.to_string()))?;
+        overlay::merge(&mut merged, overrides.clone());
+        let mut cfg: Self = serde_json::from_value(merged).map_err(|e| {
+            ConfigError::Parse(path.to_path_buf(), format!("profile '{}': {}", name, e))
+        })?;
+        cfg.profiles.clear();
+        Ok(cfg)
+    }
+
     /// Apply CLI overrides to config.
     ///
     /// # Arguments
@@ -230,7 +382,7 @@ impl ArkConfig {
     fn apply_auth_env_overrides(
         existing_auth: Option<models::AuthConfig>,
     ) -> Option<models::AuthConfig> {
-        use models::{AuthConfig, IdentityProviderConfig, SessionConfig};
+        use models::{AuthConfig, IdentityProviderConfig, IdentityProviderKind, SessionConfig};
 
         // Check if any auth env vars are set
         let auth_enabled = std::env::var("ARK_AUTH_ENABLED")
@@ -240,9 +392,11 @@ impl ArkConfig {
         let auth_authority = std::env::var("ARK_AUTH_AUTHORITY").ok();
         let client_id = std::env::var("ARK_AUTH_CLIENT_ID").ok();
         let client_secret = std::env::var("ARK_AUTH_CLIENT_SECRET").ok();
+        let client_secret_file = std::env::var("ARK_AUTH_CLIENT_SECRET_FILE").ok();
         let auth_scopes = std::env::var("ARK_AUTH_SCOPES").ok();
         let groups_admin = std::env::var("ARK_AUTH_GROUPS_ADMIN").ok();
         let groups_users = std::env::var("ARK_AUTH_GROUPS_USERS").ok();
+        let groups_claim = std::env::var("ARK_AUTH_GROUPS_CLAIM").ok();
 
         // Entra ID specific vars
         let entra_tenant_id = std::env::var("ARK_AUTH_ENTRA_TENANT_ID").ok();
@@ -253,9 +407,11 @@ impl ArkConfig {
             && auth_authority.is_none()
             && client_id.is_none()
             && client_secret.is_none()
+            && client_secret_file.is_none()
             && auth_scopes.is_none()
             && groups_admin.is_none()
             && groups_users.is_none()
+            && groups_claim.is_none()
             && entra_tenant_id.is_none()
         {
             return existing_auth;
@@ -267,6 +423,7 @@ impl ArkConfig {
             provider: None,
             providers: Vec::new(),
             session: Some(SessionConfig::default()),
+            ..Default::default()
         });
 
         // Apply environment variable overrides
@@ -300,18 +457,25 @@ impl ArkConfig {
                         let scopes = auth_scopes
                             .clone()
                             .unwrap_or_else(|| "openid profile email".to_string());
-                        let groups = if groups_admin.is_some() || groups_users.is_some() {
+                        let groups = if groups_admin.is_some()
+                            || groups_users.is_some()
+                            || groups_claim.is_some()
+                        {
                             Some(models::Groups {
                                 admin: groups_admin.clone(),
                                 users: groups_users.clone(),
+                                claim: groups_claim.clone(),
+                                ..Default::default()
                             })
                         } else {
                             None
                         };
                         providers.push(IdentityProviderConfig {
                             name: "microsoft".to_string(),
+                            kind: None,
                             client_id: cid,
                             client_secret: client_secret.clone(),
+                            client_secret_file: client_secret_file.clone(),
                             authority: auth_url,
                             scopes: Some(scopes),
                             audience: None,
@@ -322,6 +486,8 @@ impl ArkConfig {
                             redirect_uri: None,
                             additional_scopes: None,
                             groups,
+                            saml: None,
+                            ldap: None,
                         });
                     }
                 }
@@ -331,18 +497,23 @@ impl ArkConfig {
                 let scopes = auth_scopes
                     .clone()
                     .unwrap_or_else(|| "openid profile email".to_string());
-                let groups = if groups_admin.is_some() || groups_users.is_some() {
-                    Some(models::Groups {
-                        admin: groups_admin.clone(),
-                        users: groups_users.clone(),
-                    })
-                } else {
-                    None
-                };
+                let groups =
+                    if groups_admin.is_some() || groups_users.is_some() || groups_claim.is_some() {
+                        Some(models::Groups {
+                            admin: groups_admin.clone(),
+                            users: groups_users.clone(),
+                            claim: groups_claim.clone(),
+                            ..Default::default()
+                        })
+                    } else {
+                        None
+                    };
                 providers.push(IdentityProviderConfig {
                     name: "google".to_string(),
+                    kind: None,
                     client_id: cid,
                     client_secret: client_secret.clone(),
+                    client_secret_file: client_secret_file.clone(),
                     authority: "https://accounts.google.com".to_string(),
                     scopes: Some(scopes),
                     audience: None,
@@ -353,6 +524,83 @@ impl ArkConfig {
                     redirect_uri: None,
                     additional_scopes: None,
                     groups,
+                    saml: None,
+                    ldap: None,
+                });
+            } else if matches!(provider.as_str(), "okta" | "auth0" | "keycloak")
+                && let Some(cid) = client_id
+            {
+                // Presets fill in scopes and endpoints; only the issuer is required.
+                if let Some(auth_url) = auth_authority.clone() {
+                    let groups = if groups_admin.is_some()
+                        || groups_users.is_some()
+                        || groups_claim.is_some()
+                    {
+                        Some(models::Groups {
+                            admin: groups_admin.clone(),
+                            users: groups_users.clone(),
+                            claim: groups_claim.clone(),
+                            ..Default::default()
+                        })
+                    } else {
+                        None
+                    };
+                    providers.push(IdentityProviderConfig {
+                        name: provider.clone(),
+                        kind: None,
+                        client_id: cid,
+                        client_secret: client_secret.clone(),
+                        client_secret_file: client_secret_file.clone(),
+                        authority: auth_url,
+                        scopes: auth_scopes.clone(),
+                        audience: None,
+                        discovery: true,
+                        jwks_uri: None,
+                        authorization_endpoint: None,
+                        token_endpoint: None,
+                        redirect_uri: None,
+                        additional_scopes: None,
+                        groups,
+                        saml: None,
+                        ldap: None,
+                    });
+                } else {
+                    tracing::warn!(
+                        "ARK_AUTH_AUTHORITY must be provided for {} provider",
+                        provider
+                    );
+                }
+            } else if provider == "local" {
+                // Local accounts need no IdP settings; only group mapping applies.
+                let groups =
+                    if groups_admin.is_some() || groups_users.is_some() || groups_claim.is_some() {
+                        Some(models::Groups {
+                            admin: groups_admin.clone(),
+                            users: groups_users.clone(),
+                            claim: groups_claim.clone(),
+                            ..Default::default()
+                        })
+                    } else {
+                        None
+                    };
+                providers.push(IdentityProviderConfig {
+                    name: "local".to_string(),
+                    kind: Some(IdentityProviderKind::Local),
+                    client_id: String::new(),
+                    client_secret: None,
+                    client_secret_file: None,
+                    authority: String::new(),
+                    scopes: None,
+                    audience: None,
+                    discovery: false,
+                    jwks_uri: None,
+                    authorization_endpoint: None,
+                    token_endpoint: None,
+                    redirect_uri: None,
+                    additional_scopes: None,
+                    groups,
+                    saml: None,
+                    ldap: None,
                 });
             }
 
@@ -390,6 +638,11 @@ impl ArkConfig {
             key: None,
             cert: None,
             silent_insecure: false,
+            acme: None,
+            client_auth: None,
+            min_version: None,
+            max_version: None,
+            cipher_suites: None,
         });
 
         // Apply environment variable overrides
@@ -406,6 +659,61 @@ impl ArkConfig {
         Some(tls_config)
     }
 
+    /// Parse configuration in the format given by the file extension:
+    /// `.toml`, `.json`, or YAML for anything else.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the configuration file (for format and error messages).
+    /// * `text` - The configuration content as a string.
+    fn parse_with_path<T: DeserializeOwned>(path: &Path, text: &str) -> Result<T, ConfigError> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("toml") => toml::from_str::<T>(text)
+                .map_err(|e| ConfigError::Parse(path.to_path_buf(), format!("toml error: {}", e))),
+            Some("json") => serde_json::from_str::<T>(text)
+                .map_err(|e| ConfigError::Parse(path.to_path_buf(), format!("json error: {}", e))),
+            _ => Self::parse_yaml_with_path(path, text),
+        }
+    }
+
+    /// Fail on settings that the configuration doesn't have, such as
+    /// misspelled section names, which are otherwise ignored. Profiles are
+    /// checked too.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the configuration file (for format and error messages).
+    /// * `text` - The configuration content as a string.
+    fn reject_unknown_settings(path: &Path, text: &str) -> Result<(), ConfigError> {
+        fn collect(value: serde_json::Value, prefix: &str, unknown: &mut Vec<String>) {
+            let _ = serde_ignored::deserialize::<_, _, ArkConfig>(value, |ignored| {
+                unknown.push(format!("{}{}", prefix, setting_name(&ignored)))
+            });
+        }
+
+        let value: serde_json::Value = Self::parse_with_path(path, text)?;
+        let mut unknown = Vec::new();
+        if let Some(profiles) = value.get("profiles").and_then(|p| p.as_object()) {
+            for (name, profile) in profiles {
+                collect(
+                    profile.clone(),
+                    &format!("profiles.{}.", name),
+                    &mut unknown,
+                );
+            }
+        }
+        collect(value, "", &mut unknown);
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        Err(ConfigError::Parse(
+            path.to_path_buf(),
+            format!("unknown settings (strict mode): {}", unknown.join(", ")),
+        ))
+    }
+
     /// Parse YAML configuration with enhanced error reporting.
     ///
     /// Uses serde_yaml_ng to parse the YAML text, and includes line/column information
@@ -417,8 +725,11 @@ impl ArkConfig {
     ///
     /// # Returns
     /// The parsed configuration, or a ConfigError with detailed location info.
-    fn parse_yaml_with_path(path: &Path, text: &str) -> Result<Self, ConfigError> {
-        serde_yaml_ng::from_str::<Self>(text).map_err(|e| {
+    fn parse_yaml_with_path<T: DeserializeOwned>(
+        path: &Path,
+        text: &str,
+    ) -> Result<T, ConfigError> {
+        serde_yaml_ng::from_str::<T>(text).map_err(|e| {
             let msg = if let Some(loc) = e.location() {
                 format!(
                     "yaml error at line {}, column {}: {}",
@@ -433,6 +744,32 @@ impl ArkConfig {
             ConfigError::Parse(path.to_path_buf(), msg)
         })
     }
+
+    /// The configuration as JSON with secrets (passwords, client secrets,
+    /// tokens and keys given inline) replaced by [`REDACTED`]. Paths to key
+    /// files are kept.
+    pub fn redacted(&self) -> serde_json::Value {
+        fn redact(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map.iter_mut() {
+                        if SECRET_FIELDS.contains(&key.as_str()) && !value.is_null() {
+                            *value = serde_json::Value::from(REDACTED);
+                        } else {
+                            redact(value);
+                        }
+                    }
+                }
+                serde_json::Value::Array(items) => items.iter_mut().for_each(redact),
+                _ => {}
+            }
+        }
+
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        redact(&mut value);
+        value
+    }
+
     /// Apply relevant config fields to the shared application state.
     ///
     /// Updates the application state with configuration values that affect runtime behavior,
@@ -453,7 +790,12 @@ impl ArkConfig {
         state.set_disable_console(mgmt_srv.disable_console);
         state.set_disable_plugins_api(mgmt_srv.disable_plugin_api);
         state.set_disable_prometheus_api(mgmt_srv.disable_prometheus_api);
+        crate::metrics::configure_owner_label(mgmt_srv.tool_metrics_owner.clone());
+        crate::plugins::wasm::configure_engine(self.wasm_engine.clone().unwrap_or_default());
+        crate::plugins::url::configure_downloads(self.plugin_downloads.clone().unwrap_or_default());
         state.set_transport(self.transport.unwrap_or_default());
+        state.set_body_limits(self.body_limits.clone().unwrap_or_default());
+        state.set_quotas(self.quotas.clone().unwrap_or_default());
 
         // Log auth summary (do not fail if misconfigured)
         if let Some(auth) = &self.auth {
@@ -479,6 +821,28 @@ impl ArkConfig {
     }
 }
 
+/// Dotted name of a setting, e.g. `auth.providers.0.clinet_id`.
+fn setting_name(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => join_setting(parent, &index.to_string()),
+        Path::Map { parent, key } => join_setting(parent, key),
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => setting_name(parent),
+    }
+}
+
+fn join_setting(parent: &serde_ignored::Path, name: &str) -> String {
+    let parent = setting_name(parent);
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
 // Errors during configuration loading/parsing.
 #[derive(Debug, Error)]
 pub enum ConfigError {
@@ -489,4 +853,11 @@ pub enum ConfigError {
     /// - 1: Error message from the underlying parser
     #[error("Failed to parse {0}: {1}")]
     Parse(PathBuf, String),
+    /// Failed to read a secret from a file or Vault.
+    ///
+    /// Tuple fields:
+    /// - 0: Setting holding the secret (e.g. `auth.providers[entra].client_secret`)
+    /// - 1: Error message
+    #[error("Failed to resolve {0}: {1}")]
+    Secret(String, String),
 }
@@ -10,6 +10,8 @@ use clap::ValueEnum;
 #[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 /// Supported transports for MCP communications.
 #[derive(Debug, Default, Copy, Clone, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
@@ -36,10 +38,116 @@ pub struct TlsConfig {
     /// Whether to suppress insecure connection warnings.
     #[serde(default = "defaults::default_false")]
     pub silent_insecure: bool,
+    /// Obtain and renew the certificate from an ACME CA instead of `key`
+    /// and `cert`.
+    #[serde(default)]
+    pub acme: Option<AcmeConfig>,
+    /// Client certificates required by each listener (mutual TLS).
+    #[serde(default)]
+    pub client_auth: Option<ClientAuthListenersConfig>,
+    /// Lowest protocol version accepted. Default: 1.2.
+    #[serde(default)]
+    pub min_version: Option<TlsVersion>,
+    /// Highest protocol version accepted. Default: 1.3.
+    #[serde(default)]
+    pub max_version: Option<TlsVersion>,
+    /// Cipher suites allowed, by IANA name (e.g. `TLS13_AES_256_GCM_SHA384`),
+    /// in order of preference. Default: all suites rustls supports.
+    #[serde(default)]
+    pub cipher_suites: Option<Vec<String>>,
+}
+
+/// TLS protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+/// Client certificate requirements by listener; a listener without one
+/// does not ask for certificates.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ClientAuthListenersConfig {
+    /// Management API listener.
+    #[serde(default)]
+    pub management: Option<ClientAuthConfig>,
+    /// MCP listener.
+    #[serde(default)]
+    pub mcp: Option<ClientAuthConfig>,
+}
+
+/// Client certificate requirement of a listener.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ClientAuthConfig {
+    /// PEM bundle of the CAs client certificates must chain to (relative to
+    /// configuration directory).
+    pub ca: String,
+    /// Whether clients must present a certificate.
+    #[serde(default)]
+    pub mode: ClientAuthMode,
+}
+
+/// Client certificate mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ClientAuthMode {
+    /// Handshakes without a valid client certificate fail.
+    #[default]
+    Require,
+    /// Certificates are verified when presented, but optional.
+    Verify,
+}
+
+/// Certificates from an ACME CA such as Let's Encrypt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct AcmeConfig {
+    /// Domains the certificate is for; each must resolve to this server.
+    pub domains: Vec<String>,
+    /// Contact address for expiry and account notices.
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Directory URL of the CA. Default: Let's Encrypt.
+    #[serde(default = "defaults::default_acme_directory")]
+    pub directory: String,
+    /// How control of the domains is proven.
+    #[serde(default)]
+    pub challenge: AcmeChallenge,
+    /// Plain HTTP listener answering HTTP-01 challenges; the CA connects to
+    /// port 80.
+    #[serde(default = "defaults::default_acme_http_bind_address")]
+    pub http_bind_address: String,
+    /// Directory keeping the account key and the certificate. Default:
+    /// `acme` next to the database.
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    /// Days before expiry the certificate is renewed.
+    #[serde(default = "defaults::default_acme_renew_before_days")]
+    pub renew_before_days: u32,
+}
+
+/// ACME challenge type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub enum AcmeChallenge {
+    /// Answered by the TLS listeners; one of them must be reachable on
+    /// port 443.
+    #[default]
+    #[serde(rename = "tls-alpn-01")]
+    TlsAlpn01,
+    /// Answered by a plain HTTP listener on `http_bind_address`.
+    #[serde(rename = "http-01")]
+    Http01,
 }
 
 /// Group configuration for role-based access control.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct Groups {
     /// Optional canonical admin group identifier for this provider.
@@ -49,9 +157,24 @@ pub struct Groups {
     /// Optional canonical user group identifier for this provider (non-admin users).
     /// If specified, non-admin users must be in this group to access the system.
     pub users: Option<String>,
+    /// ID token claim carrying group membership (default "groups"). The name is
+    /// matched verbatim, so namespaced claims such as
+    /// "https://example.com/groups" work as-is.
+    #[serde(default)]
+    pub claim: Option<String>,
+    /// Optional JSONPath selecting the group values within the claim, e.g.
+    /// "$.roles" for Keycloak's `realm_access` or "$[*].name" for a list of
+    /// objects. Supports `.key`, `['key']`, `[n]` and `[*]` steps.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Maps group identifiers to role names (built-in "Admin"/"User" or roles
+    /// declared under `auth.roles`). Mapping a group to "Admin" is equivalent
+    /// to listing it as `admin`.
+    #[serde(default)]
+    pub role_mappings: std::collections::BTreeMap<String, Vec<String>>,
 }
 
-/// Configuration for a management endpoint (liveness/readiness).
+/// Configuration for a management endpoint (liveness/readiness/startup).
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct ManagementPathConfig {
@@ -73,6 +196,10 @@ pub struct ManagementEndpointConfig {
     #[serde(default = "defaults::default_readyz")]
     pub readyz: ManagementPathConfig,
 
+    /// Startup probe setup.
+    #[serde(default = "defaults::default_startupz")]
+    pub startupz: ManagementPathConfig,
+
     /// Response body type for management endpoints: "text" (default) or "json".
     #[serde(default = "defaults::default_mgmt_response_type")]
     pub response_type: String,
@@ -85,6 +212,11 @@ pub struct ManagementEndpointConfig {
     #[serde(default = "defaults::default_false")]
     pub disable_console: bool,
 
+    /// Whether the console serves Swagger UI for the management API at
+    /// `/admin/api-docs`.
+    #[serde(default = "defaults::default_false")]
+    pub swagger_ui: bool,
+
     /// Whether to disable the health API.
     #[serde(default = "defaults::default_false")]
     pub disable_health_api: bool,
@@ -101,9 +233,353 @@ pub struct ManagementEndpointConfig {
     #[serde(default = "defaults::default_cors")]
     pub cors: Option<String>,
 
-    /// Optional bind address for the management server.
-    #[serde(default = "defaults::default_mgmt_bind_address_opt")]
+    /// Optional bind address for the management server. A list binds
+    /// several addresses, kept comma-separated.
+    #[serde(
+        default = "defaults::default_mgmt_bind_address_opt",
+        deserialize_with = "deserialize_bind_addresses"
+    )]
     pub bind_address: Option<String>,
+
+    /// Scraper credentials for `/metrics`. When set, they replace the
+    /// session / API key check on that route.
+    #[serde(default)]
+    pub metrics_auth: Option<MetricsAuthConfig>,
+
+    /// Adds a plugin `owner` label to the tool metrics when set.
+    #[serde(default)]
+    pub tool_metrics_owner: Option<OwnerLabelConfig>,
+
+    /// Pushgateway that receives the final metrics on shutdown, for runs
+    /// (typically stdio) that live too briefly to be scraped.
+    #[serde(default)]
+    pub push_gateway: Option<PushGatewayConfig>,
+
+    /// StatsD / DogStatsD agent that receives the metrics (requires the
+    /// `statsd` feature).
+    #[serde(default)]
+    pub statsd: Option<StatsdConfig>,
+
+    /// Bucket upper bounds for the latency histograms, in milliseconds.
+    /// Only the Prometheus exporter uses them; metrics without buckets are
+    /// exposed as summaries.
+    #[serde(default)]
+    pub histogram_buckets: Option<HistogramBucketsConfig>,
+
+    /// Request rate limits per client for classes of management endpoints.
+    #[serde(default)]
+    pub rate_limits: Option<RateLimitsConfig>,
+
+    /// Client addresses allowed to reach the management listener. Checked
+    /// before authentication.
+    #[serde(default)]
+    pub ip_filter: Option<IpFilterConfig>,
+
+    /// TLS of this listener, replacing the top-level `tls` block; an empty
+    /// block serves plain HTTP.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+
+    /// Path prefix the management API, console and auth endpoints are served
+    /// under (e.g. "/ark"), for path-routing ingresses that do not rewrite.
+    #[serde(default)]
+    pub base_path: Option<String>,
+
+    /// Directory holding the built admin console (`index.html`, `assets/`,
+    /// `fonts/`), e.g. an external build of the console.
+    #[serde(default = "defaults::default_console_path")]
+    pub console_path: String,
+}
+
+impl ManagementEndpointConfig {
+    /// `base_path` with a leading and no trailing slash, or `None` when it
+    /// is unset or the root.
+    pub fn normalized_base_path(&self) -> Option<String> {
+        let path = self.base_path.as_deref()?.trim().trim_matches('/');
+        (!path.is_empty()).then(|| format!("/{path}"))
+    }
+}
+
+/// Client IP allow and deny lists, as IPs or CIDR ranges (e.g. "10.0.0.0/8",
+/// "::1"). A client is refused when it matches a deny entry, or when an
+/// allow list is set and it matches none of its entries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct IpFilterConfig {
+    /// Addresses allowed on every route; all when empty.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Addresses refused on every route.
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Additional lists for routes under a path prefix.
+    #[serde(default)]
+    pub routes: Vec<RouteIpFilterConfig>,
+}
+
+/// Client IP lists applied, on top of the listener's, to a path prefix.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct RouteIpFilterConfig {
+    /// Path prefix, e.g. "/api/admin". API prefixes also cover the
+    /// versioned paths ("/api/v1/admin").
+    pub prefix: String,
+    /// Addresses allowed under the prefix; all when empty.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Addresses refused under the prefix.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// Rate limits for classes of management endpoints. Classes without a limit
+/// are not limited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct RateLimitsConfig {
+    /// Sign-in, OAuth token and device flow endpoints.
+    #[serde(default)]
+    pub auth: Option<RateLimitConfig>,
+    /// Creating, deleting and importing plugins.
+    #[serde(default)]
+    pub plugin_mutations: Option<RateLimitConfig>,
+    /// Executing tools through the management API.
+    #[serde(default)]
+    pub tool_execution: Option<RateLimitConfig>,
+}
+
+/// Request budget of one client for a class of endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct RateLimitConfig {
+    /// Sustained requests per minute.
+    pub per_minute: u32,
+    /// Requests that may be sent at once before the sustained rate applies.
+    /// Defaults to `per_minute`.
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+/// Latency histogram buckets, in milliseconds, per metric.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct HistogramBucketsConfig {
+    /// Buckets for `ark_tool_latency_ms`.
+    #[serde(default)]
+    pub tool: Option<Vec<f64>>,
+    /// Buckets for `ark_api_latency_ms`.
+    #[serde(default)]
+    pub api: Option<Vec<f64>>,
+    /// Buckets for `ark_mcp_latency_ms`.
+    #[serde(default)]
+    pub mcp: Option<Vec<f64>>,
+}
+
+/// StatsD / DogStatsD exporter settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct StatsdConfig {
+    /// Agent address as "host:port".
+    #[serde(default = "defaults::default_statsd_address")]
+    pub address: String,
+    /// Prefix prepended to every metric name (e.g. "ark.").
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Send metric labels as DogStatsD tags. Plain StatsD has no tags, so
+    /// labels are dropped when this is off.
+    #[serde(default = "defaults::default_true")]
+    pub dogstatsd: bool,
+    /// Tags added to every metric in DogStatsD mode (e.g. "env:prod").
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Default for StatsdConfig {
+    fn default() -> Self {
+        Self {
+            address: defaults::default_statsd_address(),
+            prefix: None,
+            dogstatsd: defaults::default_true(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// Prometheus Pushgateway target.
+///
+/// Metrics are pushed with `PUT`, replacing whatever the same job/instance
+/// group pushed before.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct PushGatewayConfig {
+    /// Pushgateway base URL (e.g. "http://pushgateway:9091").
+    pub url: String,
+    /// `job` label of the pushed group.
+    #[serde(default = "defaults::default_push_gateway_job")]
+    pub job: String,
+    /// Optional `instance` label of the pushed group.
+    #[serde(default)]
+    pub instance: Option<String>,
+    /// HTTP basic auth credentials for the Pushgateway.
+    #[serde(default)]
+    pub basic: Option<BasicAuthConfig>,
+}
+
+/// Format of the log lines written by Ark.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Compact, human-readable lines.
+    #[default]
+    Compact,
+    /// One JSON object per line with `timestamp`, `level`, `target`,
+    /// `message`, `request_id` and the event's own fields.
+    Json,
+}
+
+/// Logging configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct LoggingConfig {
+    /// Output format (compact or json).
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Write logs to this file instead of stdout.
+    #[serde(default)]
+    pub file: Option<LogFileConfig>,
+    /// Log level per target (module path), e.g. `ark::plugins::oci: debug`.
+    /// `RUST_LOG` directives take precedence for the same target.
+    #[serde(default)]
+    pub filters: std::collections::BTreeMap<String, String>,
+    /// Send logs to the systemd journal instead of stdout (requires the
+    /// `journald` feature). Cannot be combined with `file`.
+    #[serde(default)]
+    pub journald: Option<JournaldConfig>,
+}
+
+/// systemd journal output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct JournaldConfig {
+    /// `SYSLOG_IDENTIFIER` of the entries, for `journalctl -t`.
+    #[serde(default = "defaults::default_syslog_identifier")]
+    pub syslog_identifier: String,
+}
+
+impl Default for JournaldConfig {
+    fn default() -> Self {
+        Self {
+            syslog_identifier: defaults::default_syslog_identifier(),
+        }
+    }
+}
+
+/// How often the log file is rotated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// At the start of every hour (UTC).
+    Hourly,
+    /// At midnight UTC.
+    #[default]
+    Daily,
+    /// Only when `max_size_bytes` is reached.
+    Never,
+}
+
+/// Log file output.
+///
+/// Rotated files are kept next to `path` as `<file name>.<UTC timestamp>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct LogFileConfig {
+    /// Path of the active log file; missing directories are created.
+    pub path: PathBuf,
+    /// Time-based rotation (hourly, daily or never).
+    #[serde(default)]
+    pub rotation: LogRotation,
+    /// Also rotate when the file would grow past this many bytes.
+    #[serde(default)]
+    pub max_size_bytes: Option<u64>,
+    /// Number of rotated files to keep; older ones are deleted. Keeps all if unset.
+    #[serde(default)]
+    pub max_files: Option<usize>,
+}
+
+/// How plugin owners appear in the `owner` label of the tool metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum OwnerLabelMode {
+    /// Leading hex digits of the SHA-256 of the owner's global id.
+    #[default]
+    Hash,
+    /// The user part of the owner's global id, cut to `max_length` characters.
+    Truncate,
+}
+
+/// Plugin owner label on `ark_tool_calls_total` / `ark_tool_latency_ms`.
+///
+/// Each owner is a new time series, so only the first `max_owners` distinct
+/// owners get their own label value; later ones are reported as "other".
+/// Public plugins are labelled "public".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct OwnerLabelConfig {
+    #[serde(default)]
+    pub mode: OwnerLabelMode,
+    /// Distinct owners labelled before the rest are grouped as "other".
+    #[serde(default = "defaults::default_owner_label_max_owners")]
+    pub max_owners: usize,
+    /// Label length for `truncate` (and hex digits kept for `hash`).
+    #[serde(default = "defaults::default_owner_label_max_length")]
+    pub max_length: usize,
+}
+
+impl Default for OwnerLabelConfig {
+    fn default() -> Self {
+        Self {
+            mode: OwnerLabelMode::default(),
+            max_owners: defaults::default_owner_label_max_owners(),
+            max_length: defaults::default_owner_label_max_length(),
+        }
+    }
+}
+
+/// Authentication for Prometheus scrapes of `/metrics`.
+///
+/// The IP allowlist, when set, always applies. If a bearer token or basic
+/// credentials are configured the scrape must present one of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct MetricsAuthConfig {
+    /// Static token sent as `Authorization: Bearer <token>`.
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    /// File holding the bearer token; takes precedence over `bearer_token`.
+    #[serde(default)]
+    pub bearer_token_file: Option<String>,
+    /// HTTP basic auth credentials.
+    #[serde(default)]
+    pub basic: Option<BasicAuthConfig>,
+    /// Client addresses allowed to scrape, as IPs or CIDR ranges
+    /// (e.g. "10.0.0.0/8", "::1").
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+}
+
+/// Username and password for HTTP basic auth.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct BasicAuthConfig {
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    /// File holding the password; takes precedence over `password`.
+    #[serde(default)]
+    pub password_file: Option<String>,
 }
 
 impl Default for ManagementEndpointConfig {
@@ -111,14 +587,26 @@ impl Default for ManagementEndpointConfig {
         Self {
             livez: defaults::default_livez(),
             readyz: defaults::default_readyz(),
+            startupz: defaults::default_startupz(),
             response_type: defaults::default_mgmt_response_type(),
             disable_plugin_api: defaults::default_false(),
             disable_console: defaults::default_false(),
+            swagger_ui: defaults::default_false(),
             disable_health_api: defaults::default_false(),
             disable_prometheus_api: defaults::default_false(),
             disable_emit_otel: defaults::default_true(),
             cors: defaults::default_cors(),
             bind_address: defaults::default_mgmt_bind_address_opt(),
+            metrics_auth: None,
+            tool_metrics_owner: None,
+            push_gateway: None,
+            statsd: None,
+            histogram_buckets: None,
+            rate_limits: None,
+            ip_filter: None,
+            tls: None,
+            base_path: None,
+            console_path: defaults::default_console_path(),
         }
     }
 }
@@ -131,9 +619,23 @@ pub struct McpEndpointConfig {
     #[serde(default = "defaults::default_cors")]
     pub cors: Option<String>,
 
-    /// Optional bind address for the MCP server.
-    #[serde(default = "defaults::default_mcp_bind_address_opt")]
+    /// Optional bind address for the MCP server. A list binds several
+    /// addresses, kept comma-separated.
+    #[serde(
+        default = "defaults::default_mcp_bind_address_opt",
+        deserialize_with = "deserialize_bind_addresses"
+    )]
     pub bind_address: Option<String>,
+
+    /// TLS of this listener, replacing the top-level `tls` block; an empty
+    /// block serves plain HTTP.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+
+    /// Store streamable HTTP sessions in the database, so that any instance
+    /// sharing it can continue them (see [`crate::server::mcp_sessions`]).
+    #[serde(default)]
+    pub shared_sessions: bool,
 }
 
 impl Default for McpEndpointConfig {
@@ -141,19 +643,48 @@ impl Default for McpEndpointConfig {
         Self {
             cors: defaults::default_cors(),
             bind_address: defaults::default_mcp_bind_address_opt(),
+            tls: None,
+            shared_sessions: false,
         }
     }
 }
 
-/// Authentication options for pulling artifacts from OCI registries.
+/// Authentication options for pulling plugins from OCI registries and
+/// HTTP(S) URLs.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum OciAuthentication {
-    /// Basic auth with username and password.
-    Basic { username: String, password: String },
-    /// Bearer token auth.
-    Bearer { token: String },
+    /// Basic auth with username and password, the latter optionally read
+    /// from `password_file` or the environment variable `password_env`.
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: String,
+        #[serde(default)]
+        password_file: Option<String>,
+        #[serde(default)]
+        password_env: Option<String>,
+    },
+    /// Bearer token auth, the token optionally read from `token_file` or the
+    /// environment variable `token_env`.
+    Bearer {
+        #[serde(default)]
+        token: String,
+        #[serde(default)]
+        token_file: Option<String>,
+        #[serde(default)]
+        token_env: Option<String>,
+    },
+    /// Custom request headers, for HTTP(S) URLs only. `headers_env` maps
+    /// header names to environment variables holding their values, and wins
+    /// over `headers` for the same header.
+    Headers {
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(default)]
+        headers_env: BTreeMap<String, String>,
+    },
     /// Anonymous auth (no credentials).
     #[default]
     Anonymous,
@@ -166,28 +697,65 @@ impl From<OciAuthentication> for RegistryAuth {
     fn from(a: OciAuthentication) -> Self {
         match a {
             OciAuthentication::Anonymous => RegistryAuth::Anonymous,
-            OciAuthentication::Basic { username, password } => {
-                RegistryAuth::Basic(username, password)
-            }
-            OciAuthentication::Bearer { token } => RegistryAuth::Bearer(token),
+            OciAuthentication::Basic {
+                username, password, ..
+            } => RegistryAuth::Basic(username, password),
+            OciAuthentication::Bearer { token, .. } => RegistryAuth::Bearer(token),
+            OciAuthentication::Headers { .. } => RegistryAuth::Anonymous,
         }
     }
 }
 
 // ----------------- Authentication (External Identity) -----------------
 
-/// Configuration for a single external identity provider (Microsoft / Google).
+/// Kind of an identity provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum IdentityProviderKind {
+    /// OpenID Connect; presets (Okta, Auth0, ...) are picked by name prefix.
+    Oidc,
+    /// Local username/password accounts stored in the database.
+    Local,
+    /// SAML 2.0 identity provider, configured under `saml`.
+    Saml,
+    /// LDAP / Active Directory, configured under `ldap`.
+    Ldap,
+}
+
+impl IdentityProviderKind {
+    /// The kind as written in the configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdentityProviderKind::Oidc => "oidc",
+            IdentityProviderKind::Local => "local",
+            IdentityProviderKind::Saml => "saml",
+            IdentityProviderKind::Ldap => "ldap",
+        }
+    }
+}
+
+/// Configuration for a single identity provider (Microsoft / Google / OIDC presets / local).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct IdentityProviderConfig {
     /// Logical name referenced by `auth.provider` (e.g. "microsoft", "google").
     pub name: String,
-    /// OAuth / OIDC client id.
+    /// Provider kind. Defaults to `local` for a provider named "local" and
+    /// to `oidc` otherwise.
+    #[serde(default)]
+    pub kind: Option<IdentityProviderKind>,
+    /// OAuth / OIDC client id (not used by the `local` provider).
+    #[serde(default)]
     pub client_id: String,
     /// Optional client secret (only for confidential flows; not required for pure bearer validation).
     #[serde(default)]
     pub client_secret: Option<String>,
-    /// Issuer / authority base URL.
+    /// File holding the client secret; takes precedence over `client_secret`.
+    #[serde(default)]
+    pub client_secret_file: Option<String>,
+    /// Issuer / authority base URL (not used by the `local` provider).
+    #[serde(default)]
     pub authority: String,
     /// Optional space-separated scopes (defaults to "openid profile email").
     #[serde(default)]
@@ -216,14 +784,24 @@ pub struct IdentityProviderConfig {
     /// Optional group configuration for role-based access control.
     #[serde(default)]
     pub groups: Option<Groups>,
+    /// SAML 2.0 settings, required by providers of kind `saml` and ignored
+    /// otherwise.
+    #[serde(default)]
+    pub saml: Option<SamlConfig>,
+    /// Directory settings, required by providers of kind `ldap` and ignored
+    /// otherwise.
+    #[serde(default)]
+    pub ldap: Option<LdapConfig>,
 }
 
 impl Default for IdentityProviderConfig {
     fn default() -> Self {
         IdentityProviderConfig {
             name: String::new(),
+            kind: None,
             client_id: String::new(),
             client_secret: None,
+            client_secret_file: None,
             authority: String::new(),
             scopes: None,
             audience: None,
@@ -234,10 +812,103 @@ impl Default for IdentityProviderConfig {
             redirect_uri: None,
             additional_scopes: None,
             groups: None,
+            saml: None,
+            ldap: None,
         }
     }
 }
 
+/// LDAP / Active Directory settings for an `ldap` identity provider.
+///
+/// Users sign in with the built-in username/password form. The user's entry is
+/// located either by `user_dn_template` (direct bind) or by searching
+/// `user_base_dn` with `user_filter`, optionally bound as a service account,
+/// and the password is verified by binding as that entry. Group DNs from the
+/// entry's `memberOf` and from the optional group search become the user's
+/// groups, so `groups.admin` and `groups.role_mappings` take group DNs.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct LdapConfig {
+    /// Directory URL (`ldap://` or `ldaps://`).
+    pub url: String,
+    /// Upgrade `ldap://` connections with StartTLS.
+    #[serde(default)]
+    pub starttls: bool,
+    /// Skip TLS certificate verification (testing only).
+    #[serde(default)]
+    pub insecure: bool,
+    /// DN of the service account used to search for users and groups.
+    #[serde(default)]
+    pub bind_dn: Option<String>,
+    /// Password of the service account.
+    #[serde(default)]
+    pub bind_password: Option<String>,
+    /// File holding the service account password; takes precedence over
+    /// `bind_password`.
+    #[serde(default)]
+    pub bind_password_file: Option<String>,
+    /// DN pattern for binding users directly, e.g.
+    /// "uid={username},ou=people,dc=example,dc=com". Disables the user search.
+    #[serde(default)]
+    pub user_dn_template: Option<String>,
+    /// Base DN of the user search.
+    #[serde(default)]
+    pub user_base_dn: Option<String>,
+    /// User search filter; `{username}` is replaced with the escaped username
+    /// (default "(uid={username})", use "(sAMAccountName={username})" for AD).
+    #[serde(default)]
+    pub user_filter: Option<String>,
+    /// Base DN of the optional group search.
+    #[serde(default)]
+    pub group_base_dn: Option<String>,
+    /// Group search filter; `{dn}` is the user's DN and `{username}` the
+    /// username (default "(member={dn})").
+    #[serde(default)]
+    pub group_filter: Option<String>,
+    /// Attribute carrying the email address (default "mail").
+    #[serde(default)]
+    pub email_attribute: Option<String>,
+    /// Attribute carrying the display name (default "displayName").
+    #[serde(default)]
+    pub name_attribute: Option<String>,
+    /// Connection and operation timeout in seconds (default 10).
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// SAML 2.0 service provider settings for a `saml` identity provider.
+///
+/// Ark acts as the service provider: logins are sent to the IdP with the
+/// HTTP-Redirect binding and responses come back to `/auth/saml/acs` with the
+/// HTTP-POST binding. SP metadata is published at `/auth/saml/metadata`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct SamlConfig {
+    /// Entity ID of the IdP; assertions must be issued by it.
+    pub idp_entity_id: String,
+    /// Single sign-on endpoint of the IdP (HTTP-Redirect binding).
+    pub idp_sso_url: String,
+    /// IdP signing certificate, either a path to a PEM file or the PEM /
+    /// base64 DER certificate inline (as found in the IdP metadata).
+    pub idp_certificate: String,
+    /// Entity ID of this service provider, used as the expected audience.
+    pub sp_entity_id: String,
+    /// Assertion consumer service URL registered with the IdP. Defaults to
+    /// `<scheme>://<host>/auth/saml/acs` derived from the request.
+    #[serde(default)]
+    pub acs_url: Option<String>,
+    /// Attribute carrying the user's email address (default: the first of
+    /// "email", "mail" and the standard claims URI that is present).
+    #[serde(default)]
+    pub email_attribute: Option<String>,
+    /// Attribute carrying the display name (default: "displayName" / "name").
+    #[serde(default)]
+    pub name_attribute: Option<String>,
+    /// Allowed clock skew, in seconds, when checking assertion validity (default 120).
+    #[serde(default)]
+    pub clock_skew_secs: Option<u64>,
+}
+
 /// Session cookie configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
@@ -255,6 +926,34 @@ pub struct SessionConfig {
     /// Optional cookie domain (e.g., "localhost" or ".example.com")
     #[serde(default)]
     pub cookie_domain: Option<String>,
+    /// Silently renew sessions with the IdP refresh token when one is issued
+    /// (most IdPs only issue one for the `offline_access` scope).
+    #[serde(default = "defaults::default_true")]
+    pub refresh: bool,
+    /// Absolute lifetime of a renewable session, in seconds (default 7 days).
+    #[serde(default = "defaults::default_session_max_lifetime")]
+    pub max_lifetime_seconds: u64,
+    /// Base64-encoded 32-byte key used to encrypt stored refresh tokens and
+    /// session principals (falls back to `ARK_SESSION_ENCRYPTION_KEY`).
+    /// Without one session principals are stored unencrypted, and a random key
+    /// is generated at startup so sessions cannot be renewed after a restart.
+    #[serde(default)]
+    pub encryption_key: Option<String>,
+    /// File holding the encryption key; takes precedence over `encryption_key`.
+    #[serde(default)]
+    pub encryption_key_file: Option<String>,
+    /// Reject a session cookie sent from another client IP than the one the
+    /// session was created from.
+    #[serde(default)]
+    pub bind_client_ip: bool,
+    /// Reject a session cookie sent with another `User-Agent` than the one
+    /// the session was created with.
+    #[serde(default)]
+    pub bind_user_agent: bool,
+    /// Maximum number of active sessions per user; signing in once more ends
+    /// the oldest one. Unlimited when unset.
+    #[serde(default)]
+    pub max_sessions_per_user: Option<usize>,
 }
 
 impl Default for SessionConfig {
@@ -266,6 +965,13 @@ impl Default for SessionConfig {
             cookie_http_only: defaults::default_true(),
             same_site: defaults::default_cookie_same_site(),
             cookie_domain: None,
+            refresh: defaults::default_true(),
+            max_lifetime_seconds: defaults::default_session_max_lifetime(),
+            encryption_key: None,
+            encryption_key_file: None,
+            bind_client_ip: false,
+            bind_user_agent: false,
+            max_sessions_per_user: None,
         }
     }
 }
@@ -276,7 +982,8 @@ impl Default for SessionConfig {
 pub struct AuthConfig {
     #[serde(default = "defaults::default_false")]
     pub enabled: bool,
-    /// Active provider name.
+    /// Active provider name, or several comma-separated names to let users
+    /// choose at sign-in (e.g. "google,microsoft"); the first is the default.
     #[serde(default)]
     pub provider: Option<String>,
     /// Declared provider configurations.
@@ -285,13 +992,143 @@ pub struct AuthConfig {
     /// Optional session configuration (enables cookie-based auth for browser clients).
     #[serde(default)]
     pub session: Option<SessionConfig>,
+    /// Custom role definitions. `User` may be redefined here to change what
+    /// regular users are allowed to do; `Admin` always holds every permission.
+    #[serde(default)]
+    pub roles: Vec<RoleConfig>,
+    /// Throttling of failed sign-in attempts.
+    #[serde(default)]
+    pub lockout: LockoutConfig,
+    /// Ark's own OAuth authorization server (`/authorize`, `/token`).
+    #[serde(default)]
+    pub oauth: OAuthServerConfig,
+    /// Audit trail of authentication events.
+    #[serde(default)]
+    pub audit: AuthAuditConfig,
 }
 
-/// Token signing configuration for ID token issuance.
+/// Audit trail of authentication events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct AuthAuditConfig {
+    /// Days authentication events are kept before the periodic cleanup
+    /// deletes them. 0 keeps them forever.
+    #[serde(default = "defaults::default_audit_retention_days")]
+    pub retention_days: u32,
+}
+
+impl Default for AuthAuditConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: defaults::default_audit_retention_days(),
+        }
+    }
+}
+
+/// Ark's OAuth authorization server, used by MCP clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct OAuthServerConfig {
+    /// Let clients register themselves at `/register` (RFC 7591 dynamic
+    /// client registration), as MCP clients do instead of using a
+    /// pre-provisioned client id.
+    #[serde(default = "defaults::default_true")]
+    pub dynamic_registration: bool,
+    /// Require PKCE (RFC 7636, `S256`) on every authorization request.
+    /// Public clients always need it.
+    #[serde(default)]
+    pub require_pkce: bool,
+    /// Accept public clients, which hold no secret
+    /// (`token_endpoint_auth_method: none`), as desktop MCP agents do.
+    #[serde(default = "defaults::default_true")]
+    pub public_clients: bool,
+}
+
+impl Default for OAuthServerConfig {
+    fn default() -> Self {
+        Self {
+            dynamic_registration: defaults::default_true(),
+            require_pkce: false,
+            public_clients: defaults::default_true(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Names of the active providers listed in `provider`, in order.
+    pub fn active_provider_names(&self) -> Vec<&str> {
+        self.provider
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+}
+
+/// Backoff and lockout applied to failed sign-in attempts.
+///
+/// Failures are counted per username and per client IP. Each failed password
+/// for a username doubles the wait before the next attempt; a username or IP
+/// reaching its limit is locked out for `lockout_seconds`. Counters are
+/// forgotten once `lockout_seconds` pass without a failure.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct LockoutConfig {
+    #[serde(default = "defaults::default_true")]
+    pub enabled: bool,
+    /// Failed attempts for one username before it is locked out.
+    #[serde(default = "defaults::default_lockout_max_attempts")]
+    pub max_attempts: u32,
+    /// Failed attempts from one IP (any username, OAuth/SAML callbacks
+    /// included) before it is locked out.
+    #[serde(default = "defaults::default_lockout_max_attempts_per_ip")]
+    pub max_attempts_per_ip: u32,
+    /// Wait after the first failure for a username, in seconds; doubles with
+    /// each further failure. 0 disables backoff.
+    #[serde(default = "defaults::default_lockout_backoff_base")]
+    pub backoff_base_seconds: u64,
+    /// Lockout duration, in seconds (default 15 minutes).
+    #[serde(default = "defaults::default_lockout_seconds")]
+    pub lockout_seconds: u64,
+    /// Take the client IP from the first `X-Forwarded-For` entry. Only enable
+    /// behind a reverse proxy that sets the header.
+    #[serde(default = "defaults::default_false")]
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: defaults::default_true(),
+            max_attempts: defaults::default_lockout_max_attempts(),
+            max_attempts_per_ip: defaults::default_lockout_max_attempts_per_ip(),
+            backoff_base_seconds: defaults::default_lockout_backoff_base(),
+            lockout_seconds: defaults::default_lockout_seconds(),
+            trust_forwarded_for: defaults::default_false(),
+        }
+    }
+}
+
+/// A named role and the permissions it grants.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct RoleConfig {
+    /// Role name, as assigned to principals (e.g. "operator").
+    pub name: String,
+    /// Granted permissions: "plugin.create", "plugin.delete.any",
+    /// "tool.execute", "metrics.read", "console.access".
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+/// Token signing configuration for ID token issuance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct TokenSigningConfig {
-    /// Source of the signing key: "local" | "azure" | "aws"
+    /// Source of the signing key: "local" | "kms" (AWS KMS) | "keyvault"
+    /// (Azure Key Vault)
     #[serde(default)]
     pub source: Option<String>,
     /// Path to local PEM private key (used when source == "local").
@@ -300,4 +1137,441 @@ pub struct TokenSigningConfig {
     /// Optional path to public cert (PEM) for JWKS construction.
     #[serde(default)]
     pub cert: Option<String>,
+    /// Further local keys, each taking over signing from `key` at its
+    /// `activate_at`. They are published in the JWKS ahead of time.
+    #[serde(default)]
+    pub rotation: Vec<SigningKeyConfig>,
+    /// Seconds a replaced key stays in the JWKS so the tokens it signed can
+    /// still be verified (default: the ID token lifetime, 1 hour).
+    #[serde(default = "defaults::default_signing_key_retain_seconds")]
+    pub retain_seconds: u64,
+    /// AWS KMS key signing tokens (used when source == "kms").
+    #[serde(default)]
+    pub kms: Option<KmsSigningConfig>,
+    /// Azure Key Vault key signing tokens (used when source == "keyvault").
+    #[serde(default)]
+    pub keyvault: Option<KeyVaultSigningConfig>,
+}
+
+impl Default for TokenSigningConfig {
+    fn default() -> Self {
+        Self {
+            source: None,
+            key: None,
+            cert: None,
+            rotation: Vec::new(),
+            retain_seconds: defaults::default_signing_key_retain_seconds(),
+            kms: None,
+            keyvault: None,
+        }
+    }
+}
+
+/// AWS KMS asymmetric key (RSA or ECC_NIST_P256, usage SIGN_VERIFY).
+///
+/// Credentials come from `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` /
+/// `AWS_SESSION_TOKEN`, or from a web identity token (`AWS_ROLE_ARN` and
+/// `AWS_WEB_IDENTITY_TOKEN_FILE`, as set for EKS service accounts).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct KmsSigningConfig {
+    /// Key id, ARN or alias (e.g. "alias/ark-token-signing").
+    pub key_id: String,
+    /// AWS region. Default: `AWS_REGION` or `AWS_DEFAULT_REGION`.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// KMS endpoint, e.g. a VPC endpoint. Default:
+    /// `https://kms.<region>.amazonaws.com`.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+/// Azure Key Vault key (RSA or EC P-256).
+///
+/// Authenticates with a client secret when one is set, otherwise with
+/// workload identity (`AZURE_FEDERATED_TOKEN_FILE`) or the managed identity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct KeyVaultSigningConfig {
+    /// Vault URL, e.g. "https://my-vault.vault.azure.net".
+    pub vault_url: String,
+    /// Name of the key.
+    pub key_name: String,
+    /// Version of the key. Default: the current version at startup.
+    #[serde(default)]
+    pub key_version: Option<String>,
+    /// Directory (tenant) id. Default: `AZURE_TENANT_ID`.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// Application (client) id. Default: `AZURE_CLIENT_ID`.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Client secret of the application.
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// File holding the client secret; takes precedence over `client_secret`.
+    #[serde(default)]
+    pub client_secret_file: Option<String>,
+    /// Microsoft Entra endpoint. Default: `AZURE_AUTHORITY_HOST` or
+    /// `https://login.microsoftonline.com`.
+    #[serde(default)]
+    pub authority_host: Option<String>,
+}
+
+/// Signing key scheduled to take over from the current one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct SigningKeyConfig {
+    /// Path to the PEM private key.
+    pub key: String,
+    /// Optional path to its certificate (PEM).
+    #[serde(default)]
+    pub cert: Option<String>,
+    /// RFC 3339 time from which the key signs tokens, e.g.
+    /// "2026-01-01T00:00:00Z".
+    pub activate_at: String,
+}
+
+/// Outbound webhooks notified of plugin changes and failing tools.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct WebhooksConfig {
+    /// Endpoints each event is posted to.
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpointConfig>,
+    /// Consecutive failed calls of a tool after which `tool.failing` is sent.
+    #[serde(default = "defaults::default_webhook_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            failure_threshold: defaults::default_webhook_failure_threshold(),
+        }
+    }
+}
+
+/// Listener settings for deploys without downtime: a new process binds the
+/// same addresses while the old one drains its connections, the timeouts
+/// closing connections of slow or stalled clients, and the limits shedding
+/// load past capacity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ListenersConfig {
+    /// Bind with SO_REUSEPORT (Unix), so that another process started with
+    /// it can bind the same addresses while this one runs.
+    #[serde(default)]
+    pub reuse_port: bool,
+    /// Seconds the connections open at shutdown get to finish once the
+    /// listeners are closed; 0 closes them at once.
+    #[serde(default)]
+    pub drain_timeout_secs: u64,
+    /// Seconds a client gets to send the headers of a request (HTTP/1) and
+    /// to complete the TLS handshake; 0 waits forever.
+    #[serde(default = "defaults::default_header_timeout_secs")]
+    pub header_timeout_secs: u64,
+    /// Seconds a read may stall while a request is in flight; 0 waits
+    /// forever.
+    #[serde(default)]
+    pub read_timeout_secs: u64,
+    /// Seconds a write to the client may stall; 0 waits forever.
+    #[serde(default)]
+    pub write_timeout_secs: u64,
+    /// Seconds a connection is kept open without a request in flight; 0
+    /// keeps it open until the client closes it.
+    #[serde(default)]
+    pub idle_timeout_secs: u64,
+    /// Connections each listener keeps open at once; further ones are
+    /// closed as soon as accepted. 0 is unlimited.
+    #[serde(default)]
+    pub max_connections: usize,
+    /// Requests each listener handles at once; further ones are answered
+    /// with 503 Service Unavailable. 0 is unlimited.
+    #[serde(default)]
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for ListenersConfig {
+    fn default() -> Self {
+        Self {
+            reuse_port: false,
+            drain_timeout_secs: 0,
+            header_timeout_secs: defaults::default_header_timeout_secs(),
+            read_timeout_secs: 0,
+            write_timeout_secs: 0,
+            idle_timeout_secs: 0,
+            max_connections: 0,
+            max_concurrent_requests: 0,
+        }
+    }
+}
+
+/// Watching the configuration and TLS files for changes, e.g. ConfigMap and
+/// Secret volumes mounted in a Kubernetes pod, so that updates apply without
+/// a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct WatchConfig {
+    /// Whether to watch the files.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds between checks for changes.
+    #[serde(default = "defaults::default_watch_interval_secs")]
+    pub interval_secs: u64,
+    /// Further files or directories whose changes reload the configuration,
+    /// e.g. those named by `*_file` settings.
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: defaults::default_watch_interval_secs(),
+            paths: Vec::new(),
+        }
+    }
+}
+
+/// How the WASM runtime allocates plugin instances.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WasmAllocator {
+    /// Instances and their memories are allocated as they are created.
+    #[default]
+    OnDemand,
+    /// Instances come from pools reserved up front, trading memory (and
+    /// address space) for faster instantiation.
+    Pooling,
+}
+
+/// Tuning of the WASM engine running the plugins. Applies to plugins loaded
+/// after startup too; each plugin has its own engine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct WasmEngineConfig {
+    /// Instance allocation strategy. Default: `on_demand`.
+    #[serde(default)]
+    pub allocator: WasmAllocator,
+    /// Instances, and linear memories, each plugin's pool holds with the
+    /// pooling allocator; a plugin uses two. Default: 16.
+    #[serde(default = "defaults::default_wasm_max_instances")]
+    pub max_instances: u32,
+    /// Bytes of address space reserved for each linear memory with the
+    /// pooling allocator, which is also the most a memory can grow to.
+    /// Default: 4 GiB.
+    #[serde(default)]
+    pub memory_reservation: Option<u64>,
+    /// Records `ark_wasm_memory_peak_bytes` with the `on_demand` allocator.
+    /// The plugins' memories are then plain heap allocations without guard
+    /// pages instead of wasmtime's own, and every access is bounds-checked.
+    /// Default: false.
+    #[serde(default)]
+    pub track_memory: bool,
+}
+
+impl Default for WasmEngineConfig {
+    fn default() -> Self {
+        Self {
+            allocator: WasmAllocator::default(),
+            max_instances: defaults::default_wasm_max_instances(),
+            memory_reservation: None,
+            track_memory: false,
+        }
+    }
+}
+
+/// How plugins are downloaded from HTTP(S) URLs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct PluginDownloadConfig {
+    /// Time allowed to connect to the host. Default: 10000.
+    #[serde(default = "defaults::default_download_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Time allowed without receiving any data, so a host that stops
+    /// answering fails the download instead of stalling it. Default: 30000.
+    #[serde(default = "defaults::default_download_read_timeout_ms")]
+    pub read_timeout_ms: u64,
+    /// Limit on the whole download, however slowly data arrives (optional).
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Further attempts after a connection error, timeout, 429 or 5xx
+    /// response. Default: 2.
+    #[serde(default = "defaults::default_download_retries")]
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each further one.
+    /// Default: 500.
+    #[serde(default = "defaults::default_download_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Proxy for all downloads, e.g. `http://proxy.example.com:3128`.
+    /// Without it, `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` are honored.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Comma-separated hosts, domains and CIDR ranges reached without the
+    /// proxy, as in `NO_PROXY`.
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// Keep downloaded modules that come with an `ETag` or `Last-Modified`
+    /// header, and download them again only if the server reports a change.
+    /// Default: true.
+    #[serde(default = "defaults::default_true")]
+    pub cache: bool,
+    /// Directory of the cached modules. Default: `plugin-cache` next to the
+    /// database.
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    /// Largest plugin accepted, in bytes, from any source: files, HTTP(S)
+    /// downloads, OCI layers (as stored and extracted) and bundle imports.
+    /// Default: 128 MiB.
+    #[serde(default = "defaults::default_max_plugin_bytes")]
+    pub max_plugin_bytes: u64,
+}
+
+impl Default for PluginDownloadConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: defaults::default_download_connect_timeout_ms(),
+            read_timeout_ms: defaults::default_download_read_timeout_ms(),
+            timeout_ms: None,
+            retries: defaults::default_download_retries(),
+            retry_backoff_ms: defaults::default_download_retry_backoff_ms(),
+            proxy: None,
+            no_proxy: None,
+            cache: true,
+            cache_dir: None,
+            max_plugin_bytes: defaults::default_max_plugin_bytes(),
+        }
+    }
+}
+
+/// Largest request bodies accepted, in bytes, per class of endpoint.
+/// Larger requests are rejected with 413 Payload Too Large.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct BodyLimitsConfig {
+    /// Management API, console and auth endpoints.
+    #[serde(default = "defaults::default_api_body_limit")]
+    pub api: usize,
+    /// MCP endpoints (`/mcp`, `/message`).
+    #[serde(default = "defaults::default_mcp_body_limit")]
+    pub mcp: usize,
+    /// Plugin bundle uploads (`/api/v1/plugins/import`). WASM modules are
+    /// base64-encoded in bundles, so modules can be about 3/4 of this.
+    #[serde(default = "defaults::default_upload_body_limit")]
+    pub upload: usize,
+}
+
+impl Default for BodyLimitsConfig {
+    fn default() -> Self {
+        Self {
+            api: defaults::default_api_body_limit(),
+            mcp: defaults::default_mcp_body_limit(),
+            upload: defaults::default_upload_body_limit(),
+        }
+    }
+}
+
+/// Monthly usage quotas per principal.
+///
+/// Usage is counted per calendar month (UTC). Once a principal has used up
+/// one of its quotas, its tool calls are refused until the next month.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct QuotasConfig {
+    /// Quotas of principals without their own entry in `principals`.
+    #[serde(default)]
+    pub default: QuotaLimits,
+    /// Quotas of specific principals, by global id (e.g. `oidc/*/alice`).
+    /// An entry replaces `default` entirely.
+    #[serde(default)]
+    pub principals: BTreeMap<String, QuotaLimits>,
+}
+
+impl QuotasConfig {
+    /// Returns the quotas of `principal`.
+    pub fn limits_for(&self, principal: &str) -> QuotaLimits {
+        self.principals
+            .get(principal)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Monthly quotas of one principal; 0 is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct QuotaLimits {
+    /// Tool calls.
+    #[serde(default)]
+    pub calls: u64,
+    /// Sum of the tool call durations, in milliseconds.
+    #[serde(default)]
+    pub compute_ms: u64,
+    /// Sum of the result sizes returned, in bytes.
+    #[serde(default)]
+    pub bytes_returned: u64,
+}
+
+/// A webhook receiver.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct WebhookEndpointConfig {
+    /// URL the events are posted to.
+    pub url: String,
+    /// Shared secret; when set, each request carries
+    /// `X-Ark-Signature: sha256=<hex HMAC-SHA256 of the body>`.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// File holding the shared secret; takes precedence over `secret`.
+    #[serde(default)]
+    pub secret_file: Option<String>,
+    /// Events to send ("plugin.created", "plugin.updated", "plugin.deleted",
+    /// "tool.failing"); all when empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// HashiCorp Vault server used to resolve `vault:<path>#<key>` references in
+/// secret fields.
+///
+/// `<path>` is the API path below `/v1/` (e.g. `secret/data/ark` for the KV
+/// version 2 engine mounted at `secret`) and `<key>` the field of the secret.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct VaultConfig {
+    /// Vault base URL (falls back to `VAULT_ADDR`).
+    #[serde(default)]
+    pub address: Option<String>,
+    /// Vault token (falls back to `VAULT_TOKEN`).
+    #[serde(default)]
+    pub token: Option<String>,
+    /// File holding the Vault token; takes precedence over `token`.
+    #[serde(default)]
+    pub token_file: Option<String>,
+    /// Enterprise namespace (falls back to `VAULT_NAMESPACE`).
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+/// Reads a bind address, or a list of them joined with commas.
+fn deserialize_bind_addresses<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Addresses {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<Addresses>::deserialize(deserializer)? {
+        Some(Addresses::One(address)) => Some(address),
+        Some(Addresses::Many(addresses)) => Some(addresses.join(",")),
+        None => None,
+    })
 }
@@ -0,0 +1,85 @@
+//! Configuration fragments merged over the main configuration file.
+//!
+//! The files named by `include:` (relative to the main file's directory; a
+//! directory stands for the configuration files in it) and then the files in
+//! the `config.d/` directory next to the main file are merged in order, so
+//! for example plugin definitions owned by different teams can live in
+//! separate files. Maps are merged key by key, lists are appended to and
+//! other values are replaced.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::ConfigError;
+
+/// Directory of fragments next to the main configuration file.
+pub const CONF_DIR: &str = "config.d";
+
+/// Extensions of the files picked up from fragment directories.
+const EXTENSIONS: &[&str] = &["yaml", "yml", "toml", "json"];
+
+/// Fragments to merge over the configuration file at `path`, in order.
+///
+/// # Errors
+/// Returns an error if an included file or directory does not exist or a
+/// directory cannot be listed.
+pub fn fragments(path: &Path, include: &[String]) -> Result<Vec<PathBuf>, ConfigError> {
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut fragments = Vec::new();
+    for entry in include {
+        let entry = base.join(entry);
+        if entry.is_dir() {
+            fragments.extend(directory(&entry)?);
+        } else if entry.is_file() {
+            fragments.push(entry);
+        } else {
+            return Err(ConfigError::Parse(
+                path.to_path_buf(),
+                format!("included file {} does not exist", entry.display()),
+            ));
+        }
+    }
+    let conf_dir = base.join(CONF_DIR);
+    if conf_dir.is_dir() {
+        fragments.extend(directory(&conf_dir)?);
+    }
+    Ok(fragments)
+}
+
+/// Configuration files in `dir`, sorted by name.
+fn directory(dir: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| ConfigError::Parse(dir.to_path_buf(), format!("I/O error: {}", e)))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Deep-merges `overlay` into `base`: maps key by key, lists appended, other
+/// values replaced. Empty (null) overlay values leave `base` unchanged.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (_, Value::Null) => {}
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) => base.extend(overlay),
+        (base, overlay) => *base = overlay,
+    }
+}
@@ -11,6 +11,7 @@ use crate::state::ToolExecFn;
 use super::defaults;
 use super::models::OciAuthentication;
 use rmcp::ErrorData;
+use rmcp::model::ToolAnnotations;
 #[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -52,6 +53,16 @@ pub struct ArkPlugin {
     /// Principal registered or owns the plugin.
     #[serde(default)]
     pub owner: Option<String>,
+    /// Names of the only tools of the plugin to expose; all when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools_allow: Option<Vec<String>>,
+    /// Names of tools of the plugin not to expose, even when allowed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools_deny: Option<Vec<String>>,
+    /// Expected hex-encoded SHA-256 digest of the WASM module of a file or
+    /// HTTP(S) plugin, checked before the module is instantiated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 impl ArkPlugin {
@@ -74,6 +85,9 @@ impl ArkPlugin {
             insecure: false,
             manifest,
             owner: None,
+            tools_allow: None,
+            tools_deny: None,
+            sha256: None,
         }
     }
 }
@@ -128,6 +142,13 @@ impl ToolProvider for (ArkPlugin, ToolSet, HashMap<String, ToolExecFn>) {
 ///     "BASE64_WASM_BYTES=="
 ///   ],
 ///   "memory": { "max_pages": 1024 },
+///   "fuel_limit": 1000000000,
+///   "scratch": { "path": "/tmp", "max_bytes": 67108864 },
+///   "warmup": true,
+///   "tools": {
+///     "sha256": { "name": "hash_sha256", "description": "Hashes text with SHA-256." },
+///     "debug_dump": { "hidden": true }
+///   },
 ///   "config": {
 ///     "SERVICE_BASE_URL": "https://api.example.com",
 ///     "FEATURE_FLAG_X": "true",
@@ -164,6 +185,21 @@ pub struct PluginManifest {
     /// Filesystem access mapping for WASI-enabled plugins.
     /// Maps host paths to plugin-accessible paths with read/write permissions.
     pub allowed_paths: Option<BTreeMap<String, PathBuf>>,
+    /// Fuel (roughly, WASM instructions) a single tool call may consume
+    /// before it is aborted. Setting it also enables the fuel metric.
+    pub fuel_limit: Option<u64>,
+    /// Private writable directory for intermediate files, emptied after
+    /// every call.
+    #[serde(default)]
+    pub scratch: Option<ScratchConfig>,
+    /// Call the plugin's `warmup` export, if it has one, once it is loaded,
+    /// so the first tool call does not pay for cold caches.
+    #[serde(default)]
+    pub warmup: bool,
+    /// Changes to how the plugin's tools are exposed, by the name the plugin
+    /// gives the tool.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tools: BTreeMap<String, ToolOverride>,
 }
 
 /// Memory allocation limits for the plugin.
@@ -178,6 +214,49 @@ pub struct MemoryLimits {
     pub max_pages: Option<u32>,
 }
 
+/// Scratch directory of a plugin.
+///
+/// Each loaded plugin gets its own directory on the host, outside any path
+/// it could otherwise reach. It is emptied after every call and removed when
+/// the plugin is unloaded, and a call whose files grow past `max_bytes` is
+/// aborted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ScratchConfig {
+    /// Path the plugin sees the directory at. Default: `/tmp`.
+    #[serde(default = "defaults::default_scratch_path")]
+    pub path: PathBuf,
+    /// Bytes the files in it may take up. Default: 64 MiB.
+    #[serde(default = "defaults::default_scratch_max_bytes")]
+    pub max_bytes: u64,
+}
+
+/// Changes to how one tool of a plugin is exposed.
+///
+/// Applied when the plugin is registered, so clients, scoped tokens and the
+/// management API only see the result; calls still reach the plugin under
+/// the tool's own name.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ToolOverride {
+    /// Name the tool is listed and called as.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Replaces the tool's title.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Replaces the tool's description.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Replaces the tool's annotations (e.g. `readOnlyHint`).
+    #[serde(default)]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<Value>"))]
+    pub annotations: Option<ToolAnnotations>,
+    /// Leaves the tool out, so it cannot be listed or called.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
 /// Deserializes a string that could be either a URL or a file path.
 ///
 /// This custom deserializer provides flexible input handling for plugin locations,
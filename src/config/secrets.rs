@@ -0,0 +1,299 @@
+//! Secrets kept outside the configuration file.
+//!
+//! Sensitive settings have a `*_file` variant naming a file that holds the
+//! value, e.g. a mounted Kubernetes secret; the file wins over the inline
+//! value and surrounding whitespace is trimmed. Plugin credentials can also
+//! name an environment variable holding the value (`*_env`), which wins over
+//! the inline value but not over the file. Values of the form
+//! `vault:<path>#<key>`, inline or read from a file, are then looked up in
+//! HashiCorp Vault (see [`VaultConfig`]).
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use super::models::{BasicAuthConfig, OciAuthentication, VaultConfig};
+use super::{ArkConfig, ConfigError};
+
+/// Prefix of values looked up in Vault.
+pub const VAULT_PREFIX: &str = "vault:";
+
+impl ArkConfig {
+    /// Replaces secret settings with the contents of their `*_file`
+    /// variants, and Vault references with the values they point to.
+    ///
+    /// # Errors
+    /// Returns an error naming the setting if a file cannot be read or a
+    /// Vault lookup fails.
+    pub async fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        let resolver = Resolver::new(self.vault.as_ref())?;
+
+        if let Some(auth) = self.auth.as_mut() {
+            for provider in auth.providers.iter_mut() {
+                let name = format!("auth.providers[{}]", provider.name);
+                resolver
+                    .resolve(
+                        &format!("{name}.client_secret"),
+                        &mut provider.client_secret,
+                        provider.client_secret_file.as_deref(),
+                    )
+                    .await?;
+                if let Some(ldap) = provider.ldap.as_mut() {
+                    resolver
+                        .resolve(
+                            &format!("{name}.ldap.bind_password"),
+                            &mut ldap.bind_password,
+                            ldap.bind_password_file.as_deref(),
+                        )
+                        .await?;
+                }
+            }
+            if let Some(session) = auth.session.as_mut() {
+                resolver
+                    .resolve(
+                        "auth.session.encryption_key",
+                        &mut session.encryption_key,
+                        session.encryption_key_file.as_deref(),
+                    )
+                    .await?;
+            }
+        }
+
+        if let Some(mgmt) = self.management_server.as_mut() {
+            if let Some(metrics_auth) = mgmt.metrics_auth.as_mut() {
+                resolver
+                    .resolve(
+                        "management_server.metrics_auth.bearer_token",
+                        &mut metrics_auth.bearer_token,
+                        metrics_auth.bearer_token_file.as_deref(),
+                    )
+                    .await?;
+                if let Some(basic) = metrics_auth.basic.as_mut() {
+                    resolver
+                        .resolve_basic("management_server.metrics_auth.basic", basic)
+                        .await?;
+                }
+            }
+            if let Some(basic) = mgmt.push_gateway.as_mut().and_then(|p| p.basic.as_mut()) {
+                resolver
+                    .resolve_basic("management_server.push_gateway.basic", basic)
+                    .await?;
+            }
+        }
+
+        if let Some(keyvault) = self
+            .token_signing
+            .as_mut()
+            .and_then(|s| s.keyvault.as_mut())
+        {
+            resolver
+                .resolve(
+                    "token_signing.keyvault.client_secret",
+                    &mut keyvault.client_secret,
+                    keyvault.client_secret_file.as_deref(),
+                )
+                .await?;
+        }
+
+        if let Some(webhooks) = self.webhooks.as_mut() {
+            for (i, endpoint) in webhooks.endpoints.iter_mut().enumerate() {
+                resolver
+                    .resolve(
+                        &format!("webhooks.endpoints[{i}].secret"),
+                        &mut endpoint.secret,
+                        endpoint.secret_file.as_deref(),
+                    )
+                    .await?;
+            }
+        }
+
+        for plugin in self.plugins.iter_mut() {
+            let name = format!("plugins[{}].config", plugin.name);
+            match plugin.auth.as_mut() {
+                Some(OciAuthentication::Basic {
+                    password,
+                    password_file,
+                    password_env,
+                    ..
+                }) => {
+                    if let Some(var) = password_env.as_deref() {
+                        *password = read_env(&format!("{name}.password"), var)?;
+                    }
+                    resolver
+                        .resolve_string(
+                            &format!("{name}.password"),
+                            password,
+                            password_file.as_deref(),
+                        )
+                        .await?
+                }
+                Some(OciAuthentication::Bearer {
+                    token,
+                    token_file,
+                    token_env,
+                }) => {
+                    if let Some(var) = token_env.as_deref() {
+                        *token = read_env(&format!("{name}.token"), var)?;
+                    }
+                    resolver
+                        .resolve_string(&format!("{name}.token"), token, token_file.as_deref())
+                        .await?
+                }
+                Some(OciAuthentication::Headers {
+                    headers,
+                    headers_env,
+                }) => {
+                    for (header, var) in headers_env.iter() {
+                        let value = read_env(&format!("{name}.headers.{header}"), var)?;
+                        headers.insert(header.clone(), value);
+                    }
+                    for (header, value) in headers.iter_mut() {
+                        resolver
+                            .resolve_string(&format!("{name}.headers.{header}"), value, None)
+                            .await?;
+                    }
+                }
+                Some(OciAuthentication::Anonymous) | None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Vault server and credentials.
+#[derive(Debug)]
+struct Vault {
+    address: String,
+    token: Option<String>,
+    namespace: Option<String>,
+}
+
+struct Resolver {
+    vault: Option<Vault>,
+    client: reqwest::Client,
+}
+
+impl Resolver {
+    fn new(config: Option<&VaultConfig>) -> Result<Self, ConfigError> {
+        let config = config.cloned().unwrap_or_default();
+        let mut token = config.token.or_else(|| std::env::var("VAULT_TOKEN").ok());
+        if let Some(path) = config.token_file.as_deref() {
+            token = Some(read_file("vault.token", path)?);
+        }
+        let vault = config
+            .address
+            .or_else(|| std::env::var("VAULT_ADDR").ok())
+            .map(|address| Vault {
+                address,
+                token,
+                namespace: config
+                    .namespace
+                    .or_else(|| std::env::var("VAULT_NAMESPACE").ok()),
+            });
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| ConfigError::Secret("vault".to_string(), e.to_string()))?;
+        Ok(Self { vault, client })
+    }
+
+    /// Resolves one secret setting, `name` being used in errors.
+    async fn resolve(
+        &self,
+        name: &str,
+        value: &mut Option<String>,
+        file: Option<&str>,
+    ) -> Result<(), ConfigError> {
+        if let Some(path) = file {
+            *value = Some(read_file(name, path)?);
+        }
+        if let Some(reference) = value.as_deref().and_then(|v| v.strip_prefix(VAULT_PREFIX)) {
+            let secret = self
+                .read_vault(reference)
+                .await
+                .map_err(|e| ConfigError::Secret(name.to_string(), e))?;
+            *value = Some(secret);
+        }
+        Ok(())
+    }
+
+    async fn resolve_string(
+        &self,
+        name: &str,
+        value: &mut String,
+        file: Option<&str>,
+    ) -> Result<(), ConfigError> {
+        let mut resolved = Some(std::mem::take(value));
+        self.resolve(name, &mut resolved, file).await?;
+        *value = resolved.unwrap_or_default();
+        Ok(())
+    }
+
+    async fn resolve_basic(
+        &self,
+        name: &str,
+        basic: &mut BasicAuthConfig,
+    ) -> Result<(), ConfigError> {
+        self.resolve_string(
+            &format!("{name}.password"),
+            &mut basic.password,
+            basic.password_file.as_deref(),
+        )
+        .await
+    }
+
+    /// Reads `<path>#<key>` from Vault. Secrets of the KV version 2 engine
+    /// (nested under `data.data`) and of other engines (under `data`) are
+    /// both supported.
+    async fn read_vault(&self, reference: &str) -> Result<String, String> {
+        let Some(vault) = &self.vault else {
+            return Err("no Vault server configured (vault.address or VAULT_ADDR)".to_string());
+        };
+        let Some(token) = &vault.token else {
+            return Err("no Vault token configured (vault.token or VAULT_TOKEN)".to_string());
+        };
+        let Some((path, key)) = reference.rsplit_once('#') else {
+            return Err(format!(
+                "expected {VAULT_PREFIX}<path>#<key>, got {VAULT_PREFIX}{reference}"
+            ));
+        };
+        let path = path.trim_start_matches('/');
+        let url = format!("{}/v1/{}", vault.address.trim_end_matches('/'), path);
+
+        let mut request = self.client.get(&url).header("X-Vault-Token", token);
+        if let Some(namespace) = &vault.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Vault request failed: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("Vault returned {} for {path}", response.status()));
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("invalid Vault response: {e}"))?;
+        let data = match body["data"].get("data") {
+            Some(nested @ Value::Object(_)) => nested,
+            _ => &body["data"],
+        };
+        data.get(key)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| format!("no key '{key}' in Vault secret {path}"))
+    }
+}
+
+fn read_env(name: &str, var: &str) -> Result<String, ConfigError> {
+    std::env::var(var)
+        .map(|text| text.trim().to_string())
+        .map_err(|e| ConfigError::Secret(name.to_string(), format!("cannot read ${var}: {e}")))
+}
+
+fn read_file(name: &str, path: &str) -> Result<String, ConfigError> {
+    std::fs::read_to_string(path)
+        .map(|text| text.trim().to_string())
+        .map_err(|e| ConfigError::Secret(name.to_string(), format!("cannot read {path}: {e}")))
+}
@@ -0,0 +1,165 @@
+//! Running in the background for non-systemd init scripts (Unix).
+//!
+//! `ark --daemon` detaches with the classic double fork: the original process
+//! waits until the daemon reports that it started and exits with 0, or with 1
+//! if the daemon exits first, so init scripts see startup failures. Until then
+//! the daemon keeps the terminal's standard streams, so errors are printed
+//! there; afterwards they go to `/dev/null` and logs should go to a file or the
+//! journal (see `logging`). The working directory is kept so relative paths in
+//! the configuration keep working.
+//!
+//! `--pid-file` records the daemon's process ID. It is refused while the
+//! recorded process is still running, and removed on shutdown.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// Detaches from the terminal and the session.
+///
+/// Must be called before any threads are started, in particular before the
+/// tokio runtime. Returns only in the daemon process.
+///
+/// # Errors
+/// Returns an error if a pipe or fork cannot be created or `setsid` fails.
+pub fn daemonize() -> Result<Detached> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors pipe(2) writes
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("creating the startup pipe");
+    }
+    // SAFETY: both descriptors were just created and are owned here only
+    let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+    match fork()? {
+        Fork::Child => drop(read),
+        Fork::Parent => {
+            drop(write);
+            let mut byte = [0u8; 1];
+            let status = match File::from(read).read(&mut byte) {
+                Ok(1) => 0,
+                _ => {
+                    eprintln!("ark: the daemon exited during startup");
+                    1
+                }
+            };
+            // SAFETY: exits without running the parent's destructors, which
+            // the daemon owns now
+            unsafe { libc::_exit(status) }
+        }
+    }
+
+    // New session without a controlling terminal; the second fork makes sure
+    // one cannot be acquired again
+    // SAFETY: plain system call
+    if unsafe { libc::setsid() } == -1 {
+        return Err(std::io::Error::last_os_error()).context("setsid");
+    }
+    if let Fork::Parent = fork()? {
+        // SAFETY: as above
+        unsafe { libc::_exit(0) }
+    }
+    Ok(Detached { startup: write })
+}
+
+enum Fork {
+    Parent,
+    Child,
+}
+
+fn fork() -> Result<Fork> {
+    // SAFETY: called before other threads exist (see `daemonize`)
+    match unsafe { libc::fork() } {
+        -1 => Err(std::io::Error::last_os_error()).context("fork"),
+        0 => Ok(Fork::Child),
+        _ => Ok(Fork::Parent),
+    }
+}
+
+/// Daemon side of the startup handshake.
+#[derive(Debug)]
+pub struct Detached {
+    startup: OwnedFd,
+}
+
+impl Detached {
+    /// Reports a successful start, letting the original process exit, and
+    /// detaches the standard streams.
+    ///
+    /// # Errors
+    /// Returns an error if `/dev/null` cannot be opened.
+    pub fn ready(self) -> Result<()> {
+        let null = File::options()
+            .read(true)
+            .write(true)
+            .open("/dev/null")
+            .context("opening /dev/null")?;
+        for fd in 0..=2 {
+            // SAFETY: both descriptors are open
+            unsafe { libc::dup2(null.as_raw_fd(), fd) };
+        }
+        // The original process may be gone already; nothing to report then
+        let _ = File::from(self.startup).write_all(b"1");
+        Ok(())
+    }
+}
+
+/// PID file of the running server, removed when dropped.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Fails if `path` names a process that is still running.
+    ///
+    /// # Errors
+    /// Returns an error naming the running process.
+    pub fn check(path: &Path) -> Result<()> {
+        let Some(pid) = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| s.trim().parse::<libc::pid_t>().ok())
+        else {
+            return Ok(());
+        };
+        // SAFETY: signal 0 only checks that the process exists
+        let alive = unsafe { libc::kill(pid, 0) } == 0
+            || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+        if alive && pid != std::process::id() as libc::pid_t {
+            bail!(
+                "ark is already running with PID {} (from {})",
+                pid,
+                path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Writes the current process ID to `path`, replacing a stale file.
+    ///
+    /// # Errors
+    /// Returns an error if the recorded process is running or the file cannot
+    /// be written.
+    pub fn create(path: &Path) -> Result<Self> {
+        Self::check(path)?;
+        std::fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Cannot write PID file {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Leave a file rewritten by another instance alone
+        let ours = std::fs::read_to_string(&self.path)
+            .is_ok_and(|s| s.trim() == std::process::id().to_string());
+        if ours && let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("Cannot remove PID file {}: {}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,594 @@
+//! Environment checks for `ark doctor`.
+//!
+//! Each check looks at one thing the server needs at startup or when serving
+//! requests (configuration, database, TLS material, token signing key, identity
+//! providers, plugin sources, signature verification settings) and reports a
+//! [`Finding`] with a remediation hint when something is off. Nothing is
+//! created or changed.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use url::Url;
+
+use crate::config::models::{AcmeConfig, TlsConfig};
+use crate::config::{ArkConfig, ConfigError};
+use crate::server::persist;
+
+/// Timeout of each network check.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Certificates expiring sooner than this are reported.
+const CERT_EXPIRY_WARNING_DAYS: i64 = 30;
+
+/// Sigstore TUF repository, fetched when `use_sigstore_tuf_data` is set.
+const SIGSTORE_TUF_URL: &str = "https://tuf-repo-cdn.sigstore.dev/";
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+/// Result of one check.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    /// What was checked, e.g. `database` or `plugin time`.
+    pub check: String,
+    pub status: Status,
+    pub message: String,
+    /// What to do about a warning or failure.
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            check: check.into(),
+            status: Status::Ok,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warning(check: impl Into<String>, message: impl Into<String>, hint: &str) -> Self {
+        Self {
+            check: check.into(),
+            status: Status::Warning,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    fn failed(check: impl Into<String>, message: impl Into<String>, hint: &str) -> Self {
+        Self {
+            check: check.into(),
+            status: Status::Failed,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+/// Runs every check. `config` is the result of loading the configuration as
+/// the server would; `database` overrides the database file location.
+pub async fn diagnose(
+    config: Result<ArkConfig, ConfigError>,
+    database: Option<PathBuf>,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let config = match config {
+        Ok(config) => {
+            findings.push(Finding::ok("configuration", "loaded"));
+            Some(config)
+        }
+        Err(e) => {
+            findings.push(Finding::failed(
+                "configuration",
+                e.to_string(),
+                "Fix the file named in the error, or point --config-file / ARK_CONFIG_PATH at the right one",
+            ));
+            None
+        }
+    };
+
+    findings.extend(check_database(database));
+
+    let Some(config) = config else {
+        return findings;
+    };
+    findings.extend(check_tls(&config).await);
+    findings.extend(check_client_auth(&config));
+    findings.extend(check_token_signing(&config));
+
+    let client = reqwest::Client::builder()
+        .timeout(NETWORK_TIMEOUT)
+        .build()
+        .expect("HTTP client");
+    findings.extend(check_identity_providers(&config, &client).await);
+    findings.extend(check_plugins(&config, &client).await);
+    findings.extend(check_signatures(&config, &client).await);
+    findings
+}
+
+fn check_database(database: Option<PathBuf>) -> Vec<Finding> {
+    let path = match database.map(Ok).unwrap_or_else(persist::resolve_db_path) {
+        Ok(path) => path,
+        Err(e) => {
+            return vec![Finding::failed(
+                "database",
+                format!("cannot determine the database path: {e}"),
+                "Set ARK_DB_PATH to the database file",
+            )];
+        }
+    };
+
+    let mut findings = Vec::new();
+    match writable_dir(&path) {
+        Ok(dir) => findings.push(Finding::ok(
+            "database",
+            format!(
+                "{} (directory {} is writable)",
+                path.display(),
+                dir.display()
+            ),
+        )),
+        Err(e) => {
+            findings.push(Finding::failed(
+                "database",
+                format!("{}: {e:#}", path.display()),
+                "Create the directory and make it writable by the server's user, or set ARK_DB_PATH",
+            ));
+            return findings;
+        }
+    }
+
+    let auto_apply = std::env::var("ARK_AUTO_APPLY_MIGRATIONS")
+        .map(|v| !v.eq_ignore_ascii_case("false"))
+        .unwrap_or(true);
+    findings.push(match persist::migration_status(&path) {
+        Ok(status) if status.pending.is_empty() => Finding::ok(
+            "migrations",
+            format!("{} applied, none pending", status.applied.len()),
+        ),
+        Ok(status) if auto_apply => Finding::ok(
+            "migrations",
+            format!(
+                "{} applied; {} pending, applied at the next start",
+                status.applied.len(),
+                status.pending.len()
+            ),
+        ),
+        Ok(status) => Finding::failed(
+            "migrations",
+            format!(
+                "pending: {} (ARK_AUTO_APPLY_MIGRATIONS=false)",
+                status.pending.join(", ")
+            ),
+            "Apply the migrations, or unset ARK_AUTO_APPLY_MIGRATIONS for one start",
+        ),
+        Err(e) => Finding::failed(
+            "migrations",
+            format!("{e:#}"),
+            "Check that the file is an Ark database and readable; restore it from a backup if it is corrupt",
+        ),
+    });
+    findings
+}
+
+/// The database file's directory, or the nearest existing ancestor when the
+/// directory does not exist yet, if a file can be created there.
+fn writable_dir(db_path: &Path) -> anyhow::Result<PathBuf> {
+    let mut dir = db_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    while !dir.as_os_str().is_empty() && !dir.exists() {
+        dir = dir.parent().map(Path::to_path_buf).unwrap_or_default();
+    }
+    if dir.as_os_str().is_empty() {
+        dir = PathBuf::from(".");
+    }
+    let probe = dir.join(format!(".ark-doctor-{}", std::process::id()));
+    std::fs::write(&probe, b"")
+        .with_context(|| format!("directory {} is not writable", dir.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(dir)
+}
+
+/// TLS block of each listener: its own, or the top-level one.
+fn listener_tls(config: &ArkConfig) -> [(&'static str, &'static str, TlsConfig); 2] {
+    let global = config.tls.clone().unwrap_or_default();
+    let management = config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.tls.clone());
+    let mcp = config.mcp_server.as_ref().and_then(|m| m.tls.clone());
+    [
+        match management {
+            Some(tls) => ("management", "management_server.tls", tls),
+            None => ("management", "tls", global.clone()),
+        },
+        match mcp {
+            Some(tls) => ("mcp", "mcp_server.tls", tls),
+            None => ("mcp", "tls", global),
+        },
+    ]
+}
+
+async fn check_tls(config: &ArkConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut checked = Vec::new();
+    for (_, block, tls) in listener_tls(config) {
+        if !checked.contains(&block) {
+            checked.push(block);
+            findings.extend(check_tls_block(block, &tls).await);
+        }
+    }
+    findings
+}
+
+async fn check_tls_block(block: &str, tls: &TlsConfig) -> Vec<Finding> {
+    let builder = match crate::server::service::tls_server_config_builder(tls) {
+        Ok(builder) => builder.with_no_client_auth(),
+        Err(e) => {
+            return vec![Finding::failed(
+                block,
+                format!("{e:#}"),
+                &format!(
+                    "Check {block}.min_version, {block}.max_version and {block}.cipher_suites; the server does not start with these"
+                ),
+            )];
+        }
+    };
+    if let Some(acme) = &tls.acme {
+        return check_acme(block, acme);
+    }
+    let (Some(key), Some(cert)) = (tls.key.clone().filter(|k| !k.is_empty()), tls.cert.clone())
+    else {
+        return vec![Finding::ok(block, "not configured (plain HTTP)")];
+    };
+    if let Err(e) = crate::server::service::build_tls_acceptor(tls, builder).await {
+        return vec![Finding::failed(
+            block,
+            format!("{e:#}"),
+            &format!(
+                "Check {block}.key and {block}.cert: both PEM files must exist and the certificate must be for the key"
+            ),
+        )];
+    }
+
+    let mut findings = vec![Finding::ok(block, format!("{cert} matches {key}"))];
+    if let Some(finding) = certificate_expiry(&format!("{block} certificate"), &cert) {
+        findings.push(finding);
+    }
+    findings
+}
+
+fn check_acme(block: &str, acme: &AcmeConfig) -> Vec<Finding> {
+    let manager = match crate::server::acme::AcmeManager::new(acme) {
+        Ok(manager) => manager,
+        Err(e) => {
+            return vec![Finding::failed(
+                block,
+                format!("ACME: {e:#}"),
+                &format!(
+                    "Set {block}.acme.domains and make {block}.acme.cache_dir writable by the server's user"
+                ),
+            )];
+        }
+    };
+    let cache = manager.cache_dir().display();
+    if !manager.has_certificate() {
+        return vec![Finding::warning(
+            block,
+            format!(
+                "ACME: no certificate for {} in {cache} yet",
+                acme.domains.join(", ")
+            ),
+            &format!(
+                "One is ordered from {block}.acme.directory at startup; the domains must resolve to this host"
+            ),
+        )];
+    }
+    let mut findings = vec![Finding::ok(
+        block,
+        format!(
+            "ACME certificate for {} in {cache}",
+            acme.domains.join(", ")
+        ),
+    )];
+    let cert = manager.certificate_path();
+    if let Some(finding) =
+        certificate_expiry(&format!("{block} certificate"), &cert.to_string_lossy())
+    {
+        findings.push(finding);
+    }
+    findings
+}
+
+fn check_client_auth(config: &ArkConfig) -> Vec<Finding> {
+    listener_tls(config)
+        .into_iter()
+        .filter_map(|(listener, block, tls)| {
+            let client_auth = tls.client_auth.clone().unwrap_or_default();
+            let client_auth = match listener {
+                "management" => client_auth.management,
+                _ => client_auth.mcp,
+            }?;
+            let check = format!("tls client auth ({listener})");
+            let tls_configured = tls.acme.is_some() || tls.key.is_some_and(|k| !k.is_empty());
+            if !tls_configured {
+                return Some(Finding::failed(
+                    check,
+                    "client certificates need TLS",
+                    &format!("Set {block}.key and {block}.cert, or {block}.acme"),
+                ));
+            }
+            Some(
+                match crate::server::service::client_cert_verifier(Some(&client_auth)) {
+                    Ok(_) => Finding::ok(
+                        check,
+                        format!(
+                            "{:?} certificates issued by {}",
+                            client_auth.mode, client_auth.ca
+                        ),
+                    ),
+                    Err(e) => Finding::failed(
+                        check,
+                        format!("{e:#}"),
+                        "Point ca at a PEM bundle of the CA certificates clients chain to",
+                    ),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Warns about an expired or soon expiring certificate in the PEM file `path`.
+fn certificate_expiry(check: &str, path: &str) -> Option<Finding> {
+    let pem = std::fs::read_to_string(path).ok()?;
+    let pem = pem::parse(pem).ok()?;
+    let (_, cert) = x509_parser::parse_x509_certificate(&pem.contents).ok()?;
+    let not_after = cert.validity().not_after.timestamp();
+    let days = (not_after - chrono::Utc::now().timestamp()) / 86_400;
+    let until = chrono::DateTime::from_timestamp(not_after, 0)?.to_rfc3339();
+    Some(if days < 0 {
+        Finding::failed(
+            check,
+            format!("expired on {until}"),
+            "Renew the certificate",
+        )
+    } else if days < CERT_EXPIRY_WARNING_DAYS {
+        Finding::warning(
+            check,
+            format!("expires on {until}, in {days} days"),
+            "Renew the certificate soon",
+        )
+    } else {
+        Finding::ok(check, format!("valid until {until}"))
+    })
+}
+
+fn check_token_signing(config: &ArkConfig) -> Vec<Finding> {
+    let Some(signing) = config
+        .token_signing
+        .as_ref()
+        .filter(|s| s.source.as_deref() == Some("local"))
+    else {
+        return Vec::new();
+    };
+    let key = std::env::var("ARK_TOKEN_SIGNING_KEY")
+        .ok()
+        .or_else(|| signing.key.clone());
+    let cert = std::env::var("ARK_TOKEN_SIGNING_CERT")
+        .ok()
+        .or_else(|| signing.cert.clone());
+    let Some(key) = key else {
+        return vec![Finding::failed(
+            "token signing",
+            "source is local but no key is configured",
+            "Set token_signing.key, e.g. to a key generated with `ark keygen`",
+        )];
+    };
+    match crate::server::signing::load_signer(signing, &key, cert.as_deref()) {
+        Ok(_) => {
+            let mut findings = vec![Finding::ok("token signing", format!("key {key} loads"))];
+            if let Some(finding) = cert
+                .as_deref()
+                .and_then(|c| certificate_expiry("token signing certificate", c))
+            {
+                findings.push(finding);
+            }
+            findings
+        }
+        Err(e) => vec![Finding::failed(
+            "token signing",
+            format!("{e:#}"),
+            "Use an RSA, EC P-256 or Ed25519 PEM key (see `ark keygen`) and the certificate issued for it",
+        )],
+    }
+}
+
+async fn check_identity_providers(config: &ArkConfig, client: &reqwest::Client) -> Vec<Finding> {
+    let Some(auth) = config.auth.as_ref().filter(|a| a.enabled) else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    for provider in &auth.providers {
+        let check = format!("auth provider {}", provider.name);
+        if let Some(ldap) = &provider.ldap {
+            findings.push(check_ldap(&check, &ldap.url).await);
+        } else if let Some(saml) = &provider.saml {
+            findings.push(
+                check_reachable(
+                    client,
+                    &check,
+                    &saml.idp_sso_url,
+                    "Check saml.idp_sso_url and that this host can reach the IdP (proxy, firewall, DNS)",
+                )
+                .await,
+            );
+        } else if !provider.authority.is_empty() && provider.discovery {
+            let url = format!(
+                "{}/.well-known/openid-configuration",
+                provider.authority.trim_end_matches('/')
+            );
+            findings.push(check_discovery(client, &check, &url).await);
+        }
+    }
+    findings
+}
+
+async fn check_discovery(client: &reqwest::Client, check: &str, url: &str) -> Finding {
+    const HINT: &str = "Check the provider's authority (issuer URL, including any tenant or realm) and that this host can reach it";
+    let response = match client.get(url).send().await {
+        Ok(response) => response,
+        Err(e) => return Finding::failed(check, format!("{url}: {e}"), HINT),
+    };
+    if !response.status().is_success() {
+        return Finding::failed(check, format!("{url} returned {}", response.status()), HINT);
+    }
+    match response.json::<serde_json::Value>().await {
+        Ok(document) if document.get("issuer").is_some() => {
+            Finding::ok(check, format!("discovery document at {url}"))
+        }
+        _ => Finding::failed(
+            check,
+            format!("{url} is not an OpenID Connect discovery document"),
+            HINT,
+        ),
+    }
+}
+
+async fn check_ldap(check: &str, url: &str) -> Finding {
+    const HINT: &str = "Check ldap.url and that this host can reach the directory (firewall, DNS)";
+    let Some((host, port)) = Url::parse(url).ok().and_then(|u| {
+        let port = u
+            .port()
+            .unwrap_or(if u.scheme() == "ldaps" { 636 } else { 389 });
+        u.host_str().map(|h| (h.to_string(), port))
+    }) else {
+        return Finding::failed(check, format!("invalid directory URL {url}"), HINT);
+    };
+    match tokio::time::timeout(
+        NETWORK_TIMEOUT,
+        tokio::net::TcpStream::connect((host.as_str(), port)),
+    )
+    .await
+    {
+        Ok(Ok(_)) => Finding::ok(check, format!("{host}:{port} accepts connections")),
+        Ok(Err(e)) => Finding::failed(check, format!("{host}:{port}: {e}"), HINT),
+        Err(_) => Finding::failed(check, format!("{host}:{port}: timed out"), HINT),
+    }
+}
+
+/// Any HTTP response counts: only reachability is checked.
+async fn check_reachable(client: &reqwest::Client, check: &str, url: &str, hint: &str) -> Finding {
+    match client.head(url).send().await {
+        Ok(response) => Finding::ok(check, format!("{url} answers ({})", response.status())),
+        Err(e) => Finding::failed(check, format!("{url}: {e}"), hint),
+    }
+}
+
+async fn check_plugins(config: &ArkConfig, client: &reqwest::Client) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for plugin in &config.plugins {
+        let check = format!("plugin {}", plugin.name);
+        let Some(url) = &plugin.url else {
+            findings.push(Finding::failed(
+                check,
+                "no url",
+                "Set the plugin's url to an oci://, http(s):// or file:// location",
+            ));
+            continue;
+        };
+        findings.push(match url.scheme() {
+            "file" => match url.to_file_path() {
+                Ok(path) if path.is_file() => Finding::ok(check, format!("{} exists", path.display())),
+                _ => Finding::failed(
+                    check,
+                    format!("{url} does not exist"),
+                    "Fix the path, or copy the plugin there",
+                ),
+            },
+            "http" if !plugin.insecure => Finding::failed(
+                check,
+                format!("{url} uses plain HTTP"),
+                "Serve the plugin over HTTPS, or set insecure: true on the plugin",
+            ),
+            "http" | "https" => {
+                check_reachable(
+                    client,
+                    &check,
+                    url.as_str(),
+                    "Check the URL and that this host can reach it (proxy, firewall, DNS)",
+                )
+                .await
+            }
+            "oci" => {
+                // Registries answer /v2/ with 200 or 401 (authentication required)
+                let registry = format!(
+                    "https://{}{}/v2/",
+                    url.host_str().unwrap_or_default(),
+                    url.port().map(|p| format!(":{p}")).unwrap_or_default()
+                );
+                check_reachable(
+                    client,
+                    &check,
+                    &registry,
+                    "Check the registry host and that this host can reach it; private registries need credentials under the plugin's config",
+                )
+                .await
+            }
+            scheme => Finding::failed(
+                check,
+                format!("unsupported scheme {scheme}"),
+                "Use an oci://, http(s):// or file:// location",
+            ),
+        });
+    }
+    findings
+}
+
+async fn check_signatures(config: &ArkConfig, client: &reqwest::Client) -> Vec<Finding> {
+    if !config
+        .plugins
+        .iter()
+        .any(|p| p.url.as_ref().is_some_and(|u| u.scheme() == "oci"))
+    {
+        return Vec::new();
+    }
+    if config.insecure_skip_signature {
+        return vec![Finding::warning(
+            "signatures",
+            "OCI signature verification is disabled",
+            "Remove insecure_skip_signature / --insecure-skip-signature outside development",
+        )];
+    }
+    let mut findings = Vec::new();
+    if config.cert_issuer.is_none() || (config.cert_email.is_none() && config.cert_url.is_none()) {
+        findings.push(Finding::warning(
+            "signatures",
+            "no expected signer identity",
+            "Set cert_issuer and cert_email or cert_url to the identity the plugins are signed with",
+        ));
+    } else {
+        findings.push(Finding::ok(
+            "signatures",
+            "expected signer identity configured",
+        ));
+    }
+    if config.use_sigstore_tuf_data {
+        findings.push(
+            check_reachable(
+                client,
+                "sigstore tuf",
+                SIGSTORE_TUF_URL,
+                "Allow access to the Sigstore TUF repository, or disable use_sigstore_tuf_data and provide the trust root locally",
+            )
+            .await,
+        );
+    }
+    findings
+}
@@ -1,16 +1,224 @@
+//! Error types and the machine-readable codes reported to clients.
+//!
+//! Every management API error body carries a [`ErrorCode`] in its `code`
+//! field, and MCP errors carry it in `data.code` (or in `_meta.code` of a
+//! failed tool result), so clients can branch on the kind of failure without
+//! parsing messages. The codes are part of the API: add new ones, but do not
+//! rename or reuse them.
+
+use rmcp::ErrorData;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 use thiserror::Error;
 
+use crate::config::ConfigError;
+
+/// Stable identifier of the kind of an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request is malformed or has invalid parameters.
+    InvalidRequest,
+    /// The request needs an authenticated caller.
+    Unauthenticated,
+    /// A password, API key or token was not accepted.
+    InvalidCredentials,
+    /// The caller lacks the permission or role the operation needs.
+    PermissionDenied,
+    /// The plugin belongs to another principal.
+    OwnershipDenied,
+    /// The token or API key scope does not cover the operation.
+    ScopeDenied,
+    /// A state-changing request lacked a valid CSRF token.
+    CsrfInvalid,
+    /// The requested resource does not exist.
+    NotFound,
+    /// No plugin is registered under the requested name.
+    PluginNotFound,
+    /// No tool is registered under the requested name.
+    ToolNotFound,
+    /// A resource with the same name already exists.
+    AlreadyExists,
+    /// The request body is larger than allowed.
+    PayloadTooLarge,
+    /// The caller sent too many requests.
+    RateLimited,
+    /// The caller has used up one of its monthly usage quotas.
+    QuotaExceeded,
+    /// The server is at its connection or request limit.
+    Overloaded,
+    /// The operation needs a database, and none is configured.
+    DatabaseRequired,
+    /// The operation is not available on this server.
+    Unavailable,
+    /// A plugin does not match its pinned digest or signature.
+    SignatureInvalid,
+    /// A WASM plugin trapped or failed while running a tool.
+    WasmTrap,
+    /// A tool did not finish in time.
+    ToolTimeout,
+    /// A tool reported a failure or returned an invalid result.
+    ToolFailed,
+    /// The configuration is invalid.
+    ConfigInvalid,
+    /// Token signing is misconfigured.
+    SigningMisconfigured,
+    /// A certificate does not match its private key.
+    KeyCertMismatch,
+    /// An unexpected server-side failure.
+    Internal,
+}
+
+impl ErrorCode {
+    /// Every code, in declaration order.
+    pub const ALL: [ErrorCode; 25] = [
+        ErrorCode::InvalidRequest,
+        ErrorCode::Unauthenticated,
+        ErrorCode::InvalidCredentials,
+        ErrorCode::PermissionDenied,
+        ErrorCode::OwnershipDenied,
+        ErrorCode::ScopeDenied,
+        ErrorCode::CsrfInvalid,
+        ErrorCode::NotFound,
+        ErrorCode::PluginNotFound,
+        ErrorCode::ToolNotFound,
+        ErrorCode::AlreadyExists,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::RateLimited,
+        ErrorCode::QuotaExceeded,
+        ErrorCode::Overloaded,
+        ErrorCode::DatabaseRequired,
+        ErrorCode::Unavailable,
+        ErrorCode::SignatureInvalid,
+        ErrorCode::WasmTrap,
+        ErrorCode::ToolTimeout,
+        ErrorCode::ToolFailed,
+        ErrorCode::ConfigInvalid,
+        ErrorCode::SigningMisconfigured,
+        ErrorCode::KeyCertMismatch,
+        ErrorCode::Internal,
+    ];
+
+    /// Returns the code as sent to clients.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "INVALID_REQUEST",
+            ErrorCode::Unauthenticated => "UNAUTHENTICATED",
+            ErrorCode::InvalidCredentials => "INVALID_CREDENTIALS",
+            ErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            ErrorCode::OwnershipDenied => "OWNERSHIP_DENIED",
+            ErrorCode::ScopeDenied => "SCOPE_DENIED",
+            ErrorCode::CsrfInvalid => "CSRF_INVALID",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::PluginNotFound => "PLUGIN_NOT_FOUND",
+            ErrorCode::ToolNotFound => "TOOL_NOT_FOUND",
+            ErrorCode::AlreadyExists => "ALREADY_EXISTS",
+            ErrorCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::Overloaded => "OVERLOADED",
+            ErrorCode::DatabaseRequired => "DATABASE_REQUIRED",
+            ErrorCode::Unavailable => "UNAVAILABLE",
+            ErrorCode::SignatureInvalid => "SIGNATURE_INVALID",
+            ErrorCode::WasmTrap => "WASM_TRAP",
+            ErrorCode::ToolTimeout => "TOOL_TIMEOUT",
+            ErrorCode::ToolFailed => "TOOL_FAILED",
+            ErrorCode::ConfigInvalid => "CONFIG_INVALID",
+            ErrorCode::SigningMisconfigured => "SIGNING_MISCONFIGURED",
+            ErrorCode::KeyCertMismatch => "KEY_CERT_MISMATCH",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+
+    /// Parses a code as sent to clients.
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == code)
+    }
+
+    /// The `data` of an MCP error carrying this code.
+    pub fn data(self) -> Option<Value> {
+        Some(json!({ "code": self }))
+    }
+
+    /// Process exit code when the server fails to start with this error.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCode::ConfigInvalid => 2,
+            ErrorCode::SigningMisconfigured => 3,
+            ErrorCode::KeyCertMismatch => 4,
+            _ => 1,
+        }
+    }
+
+    /// Classifies an error by the first typed cause in its chain.
+    ///
+    /// MCP errors without a code in their `data` are classified by their
+    /// JSON-RPC code; anything unrecognised is [`ErrorCode::Internal`].
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<StartupError>() {
+                return e.code();
+            }
+            if cause.downcast_ref::<ConfigError>().is_some() {
+                return ErrorCode::ConfigInvalid;
+            }
+            if cause.downcast_ref::<SignatureError>().is_some() {
+                return ErrorCode::SignatureInvalid;
+            }
+            if let Some(e) = cause.downcast_ref::<ErrorData>() {
+                return Self::of_mcp(e);
+            }
+        }
+        ErrorCode::Internal
+    }
+
+    /// Classifies an MCP error.
+    pub fn of_mcp(error: &ErrorData) -> Self {
+        if let Some(code) = error
+            .data
+            .as_ref()
+            .and_then(|data| data.get("code"))
+            .and_then(Value::as_str)
+            .and_then(Self::parse)
+        {
+            return code;
+        }
+        match error.code {
+            rmcp::model::ErrorCode::INVALID_REQUEST | rmcp::model::ErrorCode::INVALID_PARAMS => {
+                ErrorCode::InvalidRequest
+            }
+            rmcp::model::ErrorCode::METHOD_NOT_FOUND => ErrorCode::ToolNotFound,
+            _ => ErrorCode::ToolFailed,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A plugin does not match the digest it is pinned to or its signature.
 #[derive(Debug, Error)]
-pub enum StartupError {
-    #[error("Configuration error: {0}")]
-    ConfigError(String),
+#[error("{0}")]
+pub struct SignatureError(pub String);
 
+#[derive(Debug, Error)]
+pub enum StartupError {
     #[error("Token signing misconfigured: {0}")]
     SigningMisconfigured(String),
 
     #[error("Key/Cert mismatch: {0}")]
     KeyCertMismatch(String),
+}
 
-    #[error("Other startup error: {0}")]
-    Other(String),
+impl StartupError {
+    /// The code reported for this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            StartupError::SigningMisconfigured(_) => ErrorCode::SigningMisconfigured,
+            StartupError::KeyCertMismatch(_) => ErrorCode::KeyCertMismatch,
+        }
+    }
 }
@@ -1,7 +1,12 @@
 pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod doctor;
 pub mod errors;
+pub mod logging;
 pub mod metrics;
 pub mod plugins;
 pub mod server;
 pub mod state;
+pub mod telemetry;
 pub mod utility;
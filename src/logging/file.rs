@@ -0,0 +1,174 @@
+//! Log file with size- and time-based rotation.
+//!
+//! The active file keeps its configured name. On rotation it is closed and
+//! renamed to `<file name>.<UTC timestamp>` before a new one is opened, which
+//! also works on Windows where open files cannot be renamed.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::config::models::{LogFileConfig, LogRotation};
+
+/// Writer appending to a log file and rotating it as configured.
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    max_size: Option<u64>,
+    max_files: Option<usize>,
+    file: Option<File>,
+    size: u64,
+    /// Rotation period the active file belongs to.
+    period: i64,
+}
+
+impl RotatingFile {
+    /// Opens (or creates) the log file described by `config`.
+    ///
+    /// An existing file from an earlier period is rotated first.
+    ///
+    /// # Errors
+    /// Returns an error if the directory or file cannot be created.
+    pub fn new(config: &LogFileConfig) -> io::Result<Self> {
+        if config.path.file_name().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Log file path '{}' has no file name", config.path.display()),
+            ));
+        }
+        if let Some(dir) = config.path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = Self {
+            path: config.path.clone(),
+            rotation: config.rotation,
+            max_size: config.max_size_bytes,
+            max_files: config.max_files,
+            file: None,
+            size: 0,
+            period: 0,
+        };
+        writer.open()?;
+        if writer.size > 0
+            && let Ok(modified) = fs::metadata(&writer.path).and_then(|m| m.modified())
+            && writer.period_of(modified.into()) != writer.period
+        {
+            writer.rotate()?;
+        }
+        Ok(writer)
+    }
+
+    fn period_of(&self, time: DateTime<Utc>) -> i64 {
+        match self.rotation {
+            LogRotation::Hourly => time.timestamp().div_euclid(3600),
+            LogRotation::Daily => time.timestamp().div_euclid(86400),
+            LogRotation::Never => 0,
+        }
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.period = self.period_of(Utc::now());
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Archives the active file, prunes old archives and opens a new file.
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        let archive = self.archive_path();
+        fs::rename(&self.path, &archive)?;
+        if let Some(max_files) = self.max_files {
+            self.prune(max_files)?;
+        }
+        self.open()
+    }
+
+    /// Unused `<file name>.<timestamp>[.<n>]` path for the file being rotated.
+    fn archive_path(&self) -> PathBuf {
+        let stamp = Utc::now().format("%Y-%m-%dT%H-%M-%S");
+        let base = format!("{}.{}", self.file_name(), stamp);
+        let mut candidate = self.dir().join(&base);
+        let mut n = 1;
+        while candidate.exists() {
+            candidate = self.dir().join(format!("{}.{}", base, n));
+            n += 1;
+        }
+        candidate
+    }
+
+    /// Deletes the oldest archives so that at most `keep` remain.
+    fn prune(&self, keep: usize) -> io::Result<()> {
+        let prefix = format!("{}.", self.file_name());
+        let mut archives: Vec<PathBuf> = fs::read_dir(self.dir())?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .map(|entry| entry.path())
+            .collect();
+        if archives.len() <= keep {
+            return Ok(());
+        }
+        // Timestamps sort chronologically, and `.<n>` suffixes after their base
+        archives.sort();
+        for old in &archives[..archives.len() - keep] {
+            // Not `tracing`: this runs on the log writer thread
+            if let Err(e) = fs::remove_file(old) {
+                eprintln!("Failed to remove old log file {}: {}", old.display(), e);
+            }
+        }
+        Ok(())
+    }
+
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn dir(&self) -> &Path {
+        match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        }
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let too_big = self
+            .max_size
+            .is_some_and(|max| self.size > 0 && self.size + buf.len() as u64 > max);
+        if too_big || self.period != self.period_of(Utc::now()) {
+            self.rotate()?;
+        }
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => {
+                self.open()?;
+                self.file.as_mut().expect("log file just opened")
+            }
+        };
+        file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
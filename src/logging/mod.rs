@@ -0,0 +1,340 @@
+//! # Logging Module
+//!
+//! Installs the global `tracing` subscriber: log lines in the configured
+//! format (compact or JSON) on stdout or in a rotating file, or entries in the
+//! systemd journal with the `journald` feature, filtered by `RUST_LOG`, plus
+//! span export when the `otel-traces` feature is enabled.
+//!
+//! Every HTTP request runs with a request id (see [`with_request_id`]), which
+//! JSON lines carry as `request_id` so a request's log lines can be grouped.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_appender::non_blocking::{NonBlockingBuilder, WorkerGuard};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::{FmtContext, MakeWriter};
+use tracing_subscriber::layer::{Filter, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry, reload};
+
+#[cfg(feature = "journald")]
+use crate::config::models::JournaldConfig;
+use crate::config::models::{LogFormat, LoggingConfig};
+
+pub mod file;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Handle to the filter of the installed subscriber, set by [`init`].
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Filter to restore when verbose logging is toggled off.
+static FILTER_BEFORE_VERBOSE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Filter applied by [`toggle_verbose`]: everything from Ark, and what other
+/// crates log at info and above.
+pub const VERBOSE_FILTER: &str = "info,ark=trace";
+
+/// Id of an HTTP request, kept in its extensions for handlers that run
+/// outside the request's task, such as MCP tool calls.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Runs `future` with `request_id` attached to everything it logs.
+pub async fn with_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// Request id of the request being handled by the current task, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Current filter directives, or `None` before [`init`].
+pub fn current_filter() -> Option<String> {
+    FILTER.get()?.with_current(|filter| filter.to_string()).ok()
+}
+
+/// Replaces the filter directives (`RUST_LOG` syntax) at runtime.
+///
+/// # Errors
+/// Returns an error if the directives are invalid or [`init`] has not
+/// installed a subscriber.
+pub fn set_filter(directives: &str) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(directives)?;
+    let handle = FILTER
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Logging has not been initialized"))?;
+    handle.reload(filter)?;
+    // An explicit change ends verbose mode
+    *FILTER_BEFORE_VERBOSE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Rebuilds the filter from the configured per-target levels and `RUST_LOG`,
+/// as at startup, e.g. after a configuration reload.
+///
+/// # Errors
+/// Returns an error if a configured target or level is invalid or [`init`]
+/// has not installed a subscriber.
+pub fn reset_filter(config: Option<&LoggingConfig>) -> anyhow::Result<()> {
+    let filter = env_filter(config)?;
+    let handle = FILTER
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Logging has not been initialized"))?;
+    handle.reload(filter)?;
+    *FILTER_BEFORE_VERBOSE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Switches to [`VERBOSE_FILTER`], or back to the previous filter if verbose
+/// logging is already on. Returns the filter now in effect.
+///
+/// # Errors
+/// Returns an error if [`init`] has not installed a subscriber.
+pub fn toggle_verbose() -> anyhow::Result<String> {
+    let previous = FILTER_BEFORE_VERBOSE.lock().unwrap().take();
+    let (directives, saved) = match previous {
+        Some(previous) => (previous, None),
+        None => (
+            VERBOSE_FILTER.to_string(),
+            Some(current_filter().unwrap_or_default()),
+        ),
+    };
+    set_filter(&directives)?;
+    *FILTER_BEFORE_VERBOSE.lock().unwrap() = saved;
+    Ok(directives)
+}
+
+/// Toggles verbose logging (see [`toggle_verbose`]) on every SIGUSR1.
+///
+/// Must be called from within a tokio runtime.
+///
+/// # Errors
+/// Returns an error if the signal handler cannot be registered.
+#[cfg(unix)]
+pub fn spawn_signal_handler() -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut usr1 = signal(SignalKind::user_defined1())?;
+    tokio::spawn(async move {
+        while usr1.recv().await.is_some() {
+            match toggle_verbose() {
+                Ok(directives) => tracing::info!("SIGUSR1: log filter set to '{}'", directives),
+                Err(e) => tracing::warn!("SIGUSR1: failed to change log filter: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Filter from the configured per-target levels and `RUST_LOG`, with the
+/// `log` crate bridge limited to warnings.
+///
+/// `RUST_LOG` directives are applied last, so they win for the same target.
+/// Without `RUST_LOG`, targets not configured log errors only.
+///
+/// # Errors
+/// Returns an error if a configured target or level is invalid.
+pub fn env_filter(config: Option<&LoggingConfig>) -> anyhow::Result<EnvFilter> {
+    let env = std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_default();
+    let base = if env.trim().is_empty() { "error" } else { "" };
+    let mut filter = EnvFilter::builder().parse(base)?;
+    for (target, level) in config.map(|c| &c.filters).into_iter().flatten() {
+        let directive = format!("{}={}", target, level).parse().map_err(|e| {
+            anyhow::anyhow!("Invalid logging filter '{}: {}': {}", target, level, e)
+        })?;
+        filter = filter.add_directive(directive);
+    }
+    for directive in env.split(',').filter(|d| !d.trim().is_empty()) {
+        match directive.parse() {
+            Ok(directive) => filter = filter.add_directive(directive),
+            // Invalid RUST_LOG directives have always been skipped
+            Err(e) => eprintln!("ignoring `{}`: {}", directive, e),
+        }
+    }
+    Ok(filter.add_directive("log=warn".parse()?))
+}
+
+/// Builds the log output layer for `format`, writing to `writer`.
+///
+/// `RUST_LOG` only filters this layer, so exported spans don't depend on it.
+pub fn output_layer<S, W>(
+    format: LogFormat,
+    writer: W,
+) -> anyhow::Result<Box<dyn Layer<S> + Send + Sync>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    Ok(filtered_layer(format, writer, env_filter(None)?))
+}
+
+fn filtered_layer<S, W, F>(
+    format: LogFormat,
+    writer: W,
+    filter: F,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+    F: Filter<S> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Compact => layer
+            .with_target(false)
+            .compact()
+            .with_filter(filter)
+            .boxed(),
+        LogFormat::Json => layer.event_format(JsonFormat).with_filter(filter).boxed(),
+    }
+}
+
+/// Installs the global subscriber.
+///
+/// When logging to a file, lines are written by a background thread; keep the
+/// returned guard alive until exit so buffered lines are flushed.
+///
+/// The filter can be changed afterwards with [`set_filter`].
+///
+/// # Errors
+/// Returns an error if both a log file and journald are configured, the log
+/// file or journal cannot be opened, the filter directives are invalid or a
+/// global subscriber is already set.
+pub fn init(config: Option<&LoggingConfig>) -> anyhow::Result<Option<WorkerGuard>> {
+    let format = config.map(|c| c.format).unwrap_or_default();
+    let file_config = config.and_then(|c| c.file.as_ref());
+    let journald_config = config.and_then(|c| c.journald.as_ref());
+    if file_config.is_some() && journald_config.is_some() {
+        anyhow::bail!("logging.file and logging.journald cannot both be set");
+    }
+    let (filter, handle) = reload::Layer::new(env_filter(config)?);
+    let (layer, guard) = match (file_config, journald_config) {
+        #[cfg(feature = "journald")]
+        (_, Some(journald_config)) => (journald_layer(journald_config, filter)?, None),
+        (Some(file_config), _) => {
+            let file = file::RotatingFile::new(file_config).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to open log file '{}': {}",
+                    file_config.path.display(),
+                    e
+                )
+            })?;
+            // Block rather than drop lines when the writer falls behind
+            let (writer, guard) = NonBlockingBuilder::default().lossy(false).finish(file);
+            (filtered_layer(format, writer, filter), Some(guard))
+        }
+        _ => (filtered_layer(format, std::io::stdout, filter), None),
+    };
+    let registry = tracing_subscriber::registry().with(layer);
+    #[cfg(feature = "otel-traces")]
+    let registry = registry.with(crate::telemetry::otel_layer());
+    registry.try_init()?;
+    let _ = FILTER.set(handle);
+
+    #[cfg(not(feature = "journald"))]
+    if journald_config.is_some() {
+        tracing::warn!("logging.journald is configured but the journald feature is not enabled");
+    }
+    Ok(guard)
+}
+
+/// Builds the journal output layer. Entries carry the event and span fields
+/// (such as `request_id`) as journal fields; the log format does not apply.
+#[cfg(feature = "journald")]
+fn journald_layer<F>(
+    config: &JournaldConfig,
+    filter: F,
+) -> anyhow::Result<Box<dyn Layer<Registry> + Send + Sync>>
+where
+    F: Filter<Registry> + Send + Sync + 'static,
+{
+    let layer = tracing_journald::layer()
+        .map_err(|e| anyhow::anyhow!("Failed to connect to journald: {}", e))?
+        .with_syslog_identifier(config.syslog_identifier.clone());
+    Ok(layer.with_filter(filter).boxed())
+}
+
+/// Formats events as single-line JSON objects.
+///
+/// Standard fields come first: `timestamp` (RFC 3339, UTC), `level`,
+/// `target`, `message` and, inside a request, `request_id`. The event's other
+/// fields follow under their own names.
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".into(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        line.insert("level".into(), metadata.level().as_str().into());
+        line.insert("target".into(), metadata.target().into());
+
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        if let Some(message) = fields.remove("message") {
+            line.insert("message".into(), message);
+        }
+        if let Some(request_id) = current_request_id() {
+            line.insert("request_id".into(), request_id.into());
+        }
+        for (name, value) in fields {
+            line.entry(name).or_insert(value);
+        }
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+/// Collects event fields as JSON values.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{:?}", value).into());
+    }
+}
@@ -12,6 +12,7 @@
 //! - Apply configuration to runtime state
 //! - Load and register plugins
 //! - Start MCP and management servers
+//! - Run tool subcommands such as `ark config init` instead (see [`cli`])
 //!
 //! # Application Lifecycle
 //!
@@ -23,11 +24,18 @@
 //! 5. **Ready** → Server is fully operational
 //! 6. **Terminating** → Server is shutting down
 
+mod cli;
 mod config;
+#[cfg(unix)]
+mod daemon;
+mod doctor;
+mod errors;
+mod logging;
 mod metrics;
 mod plugins;
 mod server;
 mod state;
+mod telemetry;
 mod utility;
 
 use crate::{
@@ -35,10 +43,11 @@ use crate::{
     state::{ApplicationState, ArkState},
 };
 use clap::{CommandFactory, FromArgMatches, Parser};
-use config::{ArkConfig, models::McpTransport};
-use tracing_subscriber::fmt;
+use config::{
+    ArkConfig,
+    models::{LogFormat, McpTransport},
+};
 use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
 
 /// CLI arguments definition for the Ark MCP server.
 ///
@@ -106,6 +115,36 @@ struct Args {
         required = false
     )]
     disable_api: Option<bool>,
+
+    /// Configuration profile merged over the shared settings (optional)
+    #[arg(
+        long = "profile",
+        value_name = "PROFILE",
+        env = "ARK_PROFILE",
+        required = false
+    )]
+    profile: Option<String>,
+
+    /// Fail on unknown configuration settings instead of ignoring them
+    #[arg(
+        long = "strict-config",
+        env = "ARK_STRICT_CONFIG",
+        default_value = "false"
+    )]
+    strict_config: bool,
+
+    /// Run in the background, detached from the terminal (Unix)
+    #[cfg(unix)]
+    #[arg(long = "daemon", env = "ARK_DAEMON", default_value = "false")]
+    daemon: bool,
+
+    /// Write the process ID to this file, removed on shutdown (Unix)
+    #[cfg(unix)]
+    #[arg(long = "pid-file", value_name = "FILE", env = "ARK_PID_FILE")]
+    pid_file: Option<std::path::PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 /// Main entry point for the Ark MCP server.
@@ -121,37 +160,137 @@ struct Args {
 ///
 /// # Panics
 /// This function may panic if critical initialization steps fail (e.g., argument parsing).
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     // Parse command-line arguments
     let matches = Args::command().get_matches();
     let args = Args::from_arg_matches(&matches).expect("invalid args");
 
+    // Forking is only safe while the process has a single thread, so detach
+    // before the runtime starts
+    let startup = if args.command.is_none() {
+        Startup::new(&args)?
+    } else {
+        Startup::default()
+    };
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(args, startup))
+}
+
+/// `--daemon` and `--pid-file` state, kept for the server's lifetime.
+#[derive(Default)]
+struct Startup {
+    #[cfg(unix)]
+    detached: Option<daemon::Detached>,
+    /// Removes the file when dropped
+    #[cfg(unix)]
+    _pid_file: Option<daemon::PidFile>,
+}
+
+impl Startup {
+    #[cfg(unix)]
+    fn new(args: &Args) -> anyhow::Result<Self> {
+        if args.daemon && args.transport == McpTransport::Stdio {
+            anyhow::bail!("--daemon needs a network transport, e.g. --transport streamable-http");
+        }
+        if let Some(path) = &args.pid_file {
+            // Reported on the terminal rather than by the detached process
+            daemon::PidFile::check(path)?;
+        }
+        let detached = args.daemon.then(daemon::daemonize).transpose()?;
+        let pid_file = args
+            .pid_file
+            .as_deref()
+            .map(daemon::PidFile::create)
+            .transpose()?;
+        Ok(Self {
+            detached,
+            _pid_file: pid_file,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new(_args: &Args) -> anyhow::Result<Self> {
+        Ok(Self::default())
+    }
+
+    /// Lets the process that started the daemon exit, once the configuration
+    /// is loaded and logging is set up.
+    fn ready(&mut self) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        if let Some(detached) = self.detached.take() {
+            detached.ready()?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a subcommand, or the server until it shuts down.
+async fn run(args: Args, mut startup: Startup) -> anyhow::Result<()> {
+    // Configuration comes from the file, environment, and CLI overrides.
+    // Startup, reloads and `ark doctor` all read it through this loader.
+    let loader: server::reload::ConfigLoader = {
+        let args = args.clone();
+        std::sync::Arc::new(move || {
+            let args = args.clone();
+            Box::pin(async move {
+                let mut config = ArkConfig::load_with_overrides(
+                    args.config_file,
+                    args.transport,
+                    args.mcp_bind_address,
+                    args.insecure_skip_signature,
+                    args.use_sigstore_tuf_data,
+                    args.disable_api,
+                    args.management_bind_address,
+                    args.profile,
+                    args.strict_config,
+                )?;
+                config.resolve_secrets().await?;
+                Ok(config)
+            })
+        })
+    };
+    if let Some(command) = args.command.clone() {
+        return cli::run(command, Args::command(), loader).await;
+    }
+
     // Initialize application state with default values
     let app_state = std::sync::Arc::new(ArkState::default());
 
-    let fmt_layer = fmt::layer().with_target(false).compact();
-    tracing_subscriber::registry()
-        .with(fmt_layer)
-        .with(tracing_subscriber::EnvFilter::from_default_env().add_directive("log=warn".parse()?))
-        .init();
+    // The log format comes from the configuration, so log compactly until it is loaded
+    let bootstrap_logging = tracing::subscriber::set_default(
+        tracing_subscriber::registry()
+            .with(logging::output_layer(LogFormat::Compact, std::io::stdout)?),
+    );
 
     // Transition to initializing state
     app_state.set_state(ApplicationState::Initializing);
 
-    // Load configuration from file, environment, and CLI overrides
-    let config = ArkConfig::load_with_overrides(
-        args.config_file.clone(),
-        args.transport,
-        args.mcp_bind_address.clone(),
-        args.insecure_skip_signature,
-        args.use_sigstore_tuf_data,
-        args.disable_api,
-        args.management_bind_address,
-    )?;
+    // Load configuration
+    let config = loader().await?;
+
+    drop(bootstrap_logging);
+    let _log_guard = logging::init(config.logging.as_ref())?;
+    #[cfg(unix)]
+    logging::spawn_signal_handler()?;
+    startup.ready()?;
 
     // Apply configuration-derived settings to application state
     config.apply_to_state(app_state.clone()).await;
+    app_state.set_config_reloader(std::sync::Arc::new(server::reload::ConfigReloader::new(
+        loader,
+        config.clone(),
+    )));
+    #[cfg(unix)]
+    server::reload::spawn_signal_handler(app_state.clone())?;
+    if let Some(watch) = config.watch.as_ref().filter(|w| w.enabled) {
+        let path = args
+            .config_file
+            .clone()
+            .unwrap_or_else(ArkConfig::default_path);
+        server::watch::spawn(app_state.clone(), path, watch);
+    }
 
     // Startup-time validation: if token_signing is configured to use local keys,
     // ensure the key file is present and readable. Fail fast if misconfigured.
@@ -168,24 +307,37 @@ async fn main() -> anyhow::Result<()> {
                 Ok(_) => tracing::info!("Token signing configured with local key: {}", k),
                 Err(e) => {
                     tracing::error!("Token signing key '{}' not readable: {}", k, e);
-                    return Err(anyhow::anyhow!(
-                        "Token signing misconfigured: key '{}' not readable",
+                    return Err(errors::StartupError::SigningMisconfigured(format!(
+                        "key '{}' not readable",
                         k
-                    ));
+                    ))
+                    .into());
                 }
             }
         } else {
             tracing::error!(
                 "Token signing configured for 'local' but no key path provided (config.token_signing.key or ARK_TOKEN_SIGNING_KEY)"
             );
-            return Err(anyhow::anyhow!(
-                "Token signing misconfigured: missing key path"
-            ));
+            return Err(
+                errors::StartupError::SigningMisconfigured("missing key path".to_string()).into(),
+            );
         }
     }
     // Initialize database for persistent storage
+    let session_cipher = crate::server::crypto::Cipher::from_session_key(
+        config
+            .auth
+            .as_ref()
+            .and_then(|auth| auth.session.as_ref())
+            .and_then(|session| session.encryption_key.as_deref()),
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid auth.session.encryption_key: {e}"))?;
     match crate::server::persist::Database::new() {
         Ok(database) => {
+            let database = match session_cipher {
+                Some(cipher) => database.with_session_cipher(cipher),
+                None => database,
+            };
             app_state.set_database(database);
             tracing::info!("Database initialized successfully");
         }
@@ -196,7 +348,7 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Initialize metrics collection if enabled
-    crate::metrics::init();
+    crate::metrics::init(config.management_server.as_ref());
 
     // Transition to plugin loading phase
     app_state.set_state(ApplicationState::LoadingPlugins);
@@ -218,27 +370,16 @@ async fn main() -> anyhow::Result<()> {
     match start(&config, app_state).await {
         Ok(_) => {
             tracing::debug!("Server has exited");
+            crate::telemetry::shutdown();
             Ok(())
         }
         Err(e) => {
             tracing::error!("Server execution failed: {:?}", e);
-            // Use string-based classification to avoid type import issues at the binary boundary.
-            let msg = format!("{:?}", e);
-            let code = if msg.contains("Failed to parse") || msg.contains("Configuration") {
-                2
-            } else if msg.contains("Token signing misconfigured")
-                || msg.contains("Failed to initialize PEM signer")
-            {
-                3
-            } else if msg.contains("KeyCertMismatch")
-                || msg.contains("Certificate public key does not match")
-            {
-                4
-            } else {
-                1
-            };
+            let code = errors::ErrorCode::of(&e).exit_code();
 
-            // Flush logs then exit with code
+            // Flush logs, remove the PID file, then exit with code
+            crate::telemetry::shutdown();
+            drop(startup);
             std::process::exit(code);
         }
     }
@@ -91,3 +91,53 @@ pub fn make_metrics_response() -> Response<Full<Bytes>> {
             .expect("Failed to build not found response")
     }
 }
+
+/// Pushes the current metrics to a Prometheus Pushgateway.
+///
+/// The metrics replace the previous push of the same job/instance group
+/// (`PUT /metrics/job/{job}[/instance/{instance}]`).
+///
+/// # Arguments
+/// * `config` - Pushgateway URL, grouping labels and optional credentials
+///
+/// # Errors
+/// Returns an error if the Prometheus recorder is not initialized (or not
+/// compiled in), or the Pushgateway cannot be reached or rejects the push.
+pub async fn push_to_gateway(
+    config: &crate::config::models::PushGatewayConfig,
+) -> anyhow::Result<()> {
+    #[cfg(feature = "prometheus")]
+    {
+        let handle = PROM_HANDLE
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("prometheus recorder not initialized"))?;
+        let mut url = format!(
+            "{}/metrics/job/{}",
+            config.url.trim_end_matches('/'),
+            urlencoding::encode(&config.job)
+        );
+        if let Some(instance) = &config.instance {
+            url.push_str(&format!("/instance/{}", urlencoding::encode(instance)));
+        }
+
+        let mut request = reqwest::Client::new()
+            .put(&url)
+            .timeout(std::time::Duration::from_secs(10))
+            .header(reqwest::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(handle.render());
+        if let Some(basic) = &config.basic {
+            request = request.basic_auth(&basic.username, Some(&basic.password));
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Pushgateway returned {}", response.status());
+        }
+        tracing::debug!("Pushed metrics to {}", url);
+        Ok(())
+    }
+    #[cfg(not(feature = "prometheus"))]
+    {
+        let _ = config;
+        anyhow::bail!("metrics disabled")
+    }
+}
@@ -1,132 +1,239 @@
 //! # Metrics Collection Module
 
 pub mod handler;
+#[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+pub mod process;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::models::{
+    HistogramBucketsConfig, ManagementEndpointConfig, OwnerLabelConfig, OwnerLabelMode,
+};
+
+/// Owner label settings and the label values handed out so far.
+struct OwnerLabels {
+    config: OwnerLabelConfig,
+    seen: HashSet<String>,
+}
+
+/// Set by [`configure_owner_label`]; `None` leaves tool metrics unlabelled.
+static OWNER_LABELS: Mutex<Option<OwnerLabels>> = Mutex::new(None);
 
 /// Initializes metrics exporters based on enabled features.
 ///
 /// This function sets up the global metrics recorder depending on which feature flags
-/// are enabled. It supports three modes:
-/// - Both Prometheus and OpenTelemetry (fanout mode)
-/// - Prometheus only
-/// - OpenTelemetry only
+/// are enabled. Every enabled exporter is added to a fanout, so metrics are sent to
+/// all of them simultaneously:
+/// - Prometheus (scraped through `/metrics`)
+/// - OpenTelemetry
+/// - StatsD / DogStatsD, when `statsd` is configured
+///
+/// Latency histograms use the configured `histogram_buckets` in Prometheus and
+/// are exposed as summaries otherwise.
 ///
-/// When both features are enabled, metrics are sent to both exporters simultaneously.
 /// The Prometheus recorder also spawns a background task for periodic upkeep of
-/// histograms and summaries.
+/// histograms and summaries, and process/runtime metrics are sampled by another
+/// background task (see [`process`]).
+///
+/// # Arguments
+/// * `config` - Management endpoint configuration (StatsD agent, histogram buckets)
 ///
 /// # Feature Requirements
-/// Requires either `prometheus` or `otel` feature to be enabled.
-/// When neither feature is enabled, this function is a no-op.
-pub fn init() {
-    // If both features are enabled, install a fanout so both receive metrics.
-    #[cfg(all(feature = "prometheus", feature = "otel"))]
-    {
-        use metrics::set_global_recorder;
-        use metrics_exporter_opentelemetry::Recorder as OtelRecorder;
-        use metrics_exporter_prometheus::PrometheusBuilder;
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn init(config: Option<&ManagementEndpointConfig>) {
+    #[cfg(not(feature = "statsd"))]
+    if config.is_some_and(|c| c.statsd.is_some()) {
+        tracing::warn!("statsd is configured but the statsd feature is not enabled");
+    }
+
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
         use metrics_util::layers::FanoutBuilder;
-        use opentelemetry::global;
+        #[cfg(any(feature = "prometheus", feature = "otel"))]
         use tracing::debug;
 
-        debug!("Prometheus + otel metrics (fanout) is enabled");
+        let fanout = FanoutBuilder::default();
 
-        // Build Prometheus recorder (not installed globally) and keep handle for scrape.
-        let prom_recorder = PrometheusBuilder::new().build_recorder();
-        let prom_handle = prom_recorder.handle();
-        crate::metrics::handler::set_prom_handle(prom_handle);
-        // Spawn periodic upkeep for Prometheus histograms/summaries.
-        {
-            use std::time::Duration;
-            let handle_for_task = prom_recorder.handle();
-            tokio::spawn(async move {
-                let mut tick = tokio::time::interval(Duration::from_secs(30));
-                loop {
-                    tick.tick().await;
-                    handle_for_task.run_upkeep();
+        #[cfg(feature = "prometheus")]
+        let fanout = {
+            use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+            debug!("Prometheus metrics endpoint is enabled");
+
+            let mut builder = PrometheusBuilder::new();
+            if let Some(buckets) = config.and_then(|c| c.histogram_buckets.as_ref()) {
+                for (metric, values) in latency_buckets(buckets) {
+                    builder = builder
+                        .set_buckets_for_metric(Matcher::Full(metric.to_string()), &values)
+                        .expect("latency_buckets only returns non-empty buckets");
                 }
-            });
-        }
+            }
+
+            // Build Prometheus recorder (not installed globally) and keep handle for scrape.
+            let prom_recorder = builder.build_recorder();
+            crate::metrics::handler::set_prom_handle(prom_recorder.handle());
+            // Spawn periodic upkeep for Prometheus histograms/summaries.
+            {
+                use std::time::Duration;
+                let handle_for_task = prom_recorder.handle();
+                tokio::spawn(async move {
+                    let mut tick = tokio::time::interval(Duration::from_secs(30));
+                    loop {
+                        tick.tick().await;
+                        handle_for_task.run_upkeep();
+                    }
+                });
+            }
+            fanout.add_recorder(prom_recorder)
+        };
+
+        #[cfg(feature = "otel")]
+        let fanout = {
+            use metrics_exporter_opentelemetry::Recorder as OtelRecorder;
+            use opentelemetry::global;
+            debug!("Enabling otel metrics reporting");
 
-        // Build OTEL recorder and also set the global OTEL meter provider.
-        // install_global() both builds and sets the metrics global recorder, but we
-        // need the Recorder instance without setting it globally, so we use build().
-        let (meter_provider, otel_recorder) = OtelRecorder::builder(env!("CARGO_PKG_NAME")).build();
-        // Ensure OpenTelemetry SDK is set globally so OTLP exporter (if configured elsewhere)
-        // can export the metrics produced by the recorder.
-        global::set_meter_provider(meter_provider);
+            // Build OTEL recorder and also set the global OTEL meter provider.
+            // install_global() both builds and sets the metrics global recorder, but we
+            // need the Recorder instance without setting it globally, so we use build().
+            let (meter_provider, otel_recorder) =
+                OtelRecorder::builder(env!("CARGO_PKG_NAME")).build();
+            // Ensure OpenTelemetry SDK is set globally so OTLP exporter (if configured elsewhere)
+            // can export the metrics produced by the recorder.
+            global::set_meter_provider(meter_provider);
+            fanout.add_recorder(otel_recorder)
+        };
 
-        // Fan out to both recorders.
-        let fanout = FanoutBuilder::default()
-            .add_recorder(prom_recorder)
-            .add_recorder(otel_recorder)
-            .build();
-        let _ = set_global_recorder(fanout);
+        #[cfg(feature = "statsd")]
+        let fanout = match config
+            .and_then(|c| c.statsd.as_ref())
+            .map(statsd::StatsdRecorder::new)
+        {
+            Some(Ok(recorder)) => {
+                tracing::info!("Sending metrics to StatsD agent at {}", recorder.address());
+                fanout.add_recorder(recorder)
+            }
+            Some(Err(e)) => {
+                tracing::warn!("StatsD exporter disabled: {:#}", e);
+                fanout
+            }
+            None => fanout,
+        };
+
+        let _ = metrics::set_global_recorder(fanout.build());
+        process::spawn_collector(process::COLLECT_INTERVAL);
     }
+}
 
-    // If only Prometheus is enabled, keep existing behavior.
-    #[cfg(all(feature = "prometheus", not(feature = "otel")))]
-    {
-        use metrics_exporter_prometheus::PrometheusBuilder;
-        use tracing::debug;
-        debug!("Prometheus metrics endpoint is enabled");
-        if let Ok(handle) = PrometheusBuilder::new().install_recorder() {
-            crate::metrics::handler::set_prom_handle(handle.clone());
-            // Spawn periodic upkeep when using install_recorder() as well.
-            use std::time::Duration;
-            tokio::spawn(async move {
-                let mut tick = tokio::time::interval(Duration::from_secs(30));
-                loop {
-                    tick.tick().await;
-                    handle.run_upkeep();
-                }
-            });
+/// Returns the latency histograms with configured buckets, paired with their
+/// bucket bounds sorted in ascending order.
+///
+/// Bucket lists that are empty or contain non-finite values are skipped with
+/// a warning.
+pub fn latency_buckets(config: &HistogramBucketsConfig) -> Vec<(&'static str, Vec<f64>)> {
+    [
+        ("ark_tool_latency_ms", &config.tool),
+        ("ark_api_latency_ms", &config.api),
+        ("ark_mcp_latency_ms", &config.mcp),
+    ]
+    .into_iter()
+    .filter_map(|(metric, values)| {
+        let mut values = values.clone()?;
+        if values.is_empty() || values.iter().any(|v| !v.is_finite()) {
+            tracing::warn!("Ignoring invalid histogram buckets for {}", metric);
+            return None;
         }
-    }
+        values.sort_by(f64::total_cmp);
+        values.dedup();
+        Some((metric, values))
+    })
+    .collect()
+}
 
-    // If only OTEL is enabled, keep existing behavior.
-    #[cfg(all(feature = "otel", not(feature = "prometheus")))]
-    {
-        use metrics_exporter_opentelemetry::Recorder;
-        use tracing::debug;
-        debug!("Enabling otel metrics reporting");
-        let _ = Recorder::builder(env!("CARGO_PKG_NAME")).install_global();
+/// Enables the `owner` label on tool metrics, or disables it with `None`.
+///
+/// Reconfiguring forgets the owners counted towards `max_owners`.
+pub fn configure_owner_label(config: Option<OwnerLabelConfig>) {
+    let mut labels = OWNER_LABELS.lock().unwrap_or_else(|e| e.into_inner());
+    *labels = config.map(|config| OwnerLabels {
+        config,
+        seen: HashSet::new(),
+    });
+}
+
+/// Returns the `owner` label value for a plugin owner (a global id), or
+/// `None` when owner labels are disabled.
+///
+/// Public plugins map to "public"; owners beyond the `max_owners` cap map
+/// to "other".
+pub fn owner_label(owner: Option<&str>) -> Option<String> {
+    let mut guard = OWNER_LABELS.lock().unwrap_or_else(|e| e.into_inner());
+    let labels = guard.as_mut()?;
+    let Some(owner) = owner.filter(|o| *o != "*/*/*") else {
+        return Some("public".to_string());
+    };
+    let max_length = labels.config.max_length.max(1);
+    let value: String = match labels.config.mode {
+        OwnerLabelMode::Hash => hex::encode(Sha256::digest(owner.as_bytes()))
+            .chars()
+            .take(max_length)
+            .collect(),
+        // Global ids are "provider/tenant/user"; the user part tells owners apart
+        OwnerLabelMode::Truncate => owner
+            .rsplit('/')
+            .next()
+            .unwrap_or(owner)
+            .chars()
+            .take(max_length)
+            .collect(),
+    };
+    if !labels.seen.contains(&value) {
+        if labels.seen.len() >= labels.config.max_owners {
+            return Some("other".to_string());
+        }
+        labels.seen.insert(value.clone());
     }
+    Some(value)
 }
 
 /// Records tool execution metrics.
 ///
-/// Tracks tool call count and execution latency by plugin and tool name.
+/// Tracks tool call count and execution latency by plugin and tool name,
+/// and by plugin owner when enabled through [`configure_owner_label`].
 /// This provides insights into tool usage and performance characteristics.
 ///
 /// # Arguments
 /// * `plugin` - Name of the plugin executing the tool
 /// * `tool` - Name of the tool being executed
+/// * `owner` - Global id of the plugin owner, `None` for public plugins
 /// * `latency_ms` - Tool execution time in milliseconds
 ///
 /// # Feature Requirements
-/// Requires either `prometheus` or `otel` feature to be enabled.
-/// When neither feature is enabled, this function is a no-op.
-pub fn record_tool_metrics(plugin: &str, tool: &str, latency_ms: f64) {
-    #[cfg(any(feature = "prometheus", feature = "otel"))]
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_tool_metrics(plugin: &str, tool: &str, owner: Option<&str>, latency_ms: f64) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
     {
-        use metrics::{counter, histogram};
-        counter!(
-            "ark_tool_calls_total",
-            "plugin" => plugin.to_string(),
-            "tool" => tool.to_string()
-        )
-        .increment(1);
-        histogram!(
-            "ark_tool_latency_ms",
-            "plugin" => plugin.to_string(),
-            "tool" => tool.to_string()
-        )
-        .record(latency_ms);
+        use metrics::{Label, counter, histogram};
+        let mut labels = vec![
+            Label::new("plugin", plugin.to_string()),
+            Label::new("tool", tool.to_string()),
+        ];
+        if let Some(owner) = owner_label(owner) {
+            labels.push(Label::new("owner", owner));
+        }
+        counter!("ark_tool_calls_total", labels.clone()).increment(1);
+        histogram!("ark_tool_latency_ms", labels).record(latency_ms);
     }
-    #[cfg(not(any(feature = "prometheus", feature = "otel")))]
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
     {
         // No-op when metrics are disabled
-        let _ = (plugin, tool, latency_ms);
+        let _ = (plugin, tool, owner, latency_ms);
     }
 }
 
@@ -142,10 +249,10 @@ pub fn record_tool_metrics(plugin: &str, tool: &str, latency_ms: f64) {
 /// * `latency_ms` - Request processing time in milliseconds
 ///
 /// # Feature Requirements
-/// Requires either `prometheus` or `otel` feature to be enabled.
-/// When neither feature is enabled, this function is a no-op.
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
 pub fn record_api_http(path: &str, method: &str, status: u16, latency_ms: f64) {
-    #[cfg(any(feature = "prometheus", feature = "otel"))]
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
     {
         use metrics::{counter, histogram};
         let status_s = status.to_string();
@@ -164,7 +271,7 @@ pub fn record_api_http(path: &str, method: &str, status: u16, latency_ms: f64) {
         )
         .record(latency_ms);
     }
-    #[cfg(not(any(feature = "prometheus", feature = "otel")))]
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
     {
         // No-op when metrics are disabled
         let _ = (path, method, status, latency_ms);
@@ -181,10 +288,10 @@ pub fn record_api_http(path: &str, method: &str, status: u16, latency_ms: f64) {
 /// * `latency_ms` - Protocol operation time in milliseconds
 ///
 /// # Feature Requirements
-/// Requires either `prometheus` or `otel` feature to be enabled.
-/// When neither feature is enabled, this function is a no-op.
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
 pub fn record_mcp_call(transport: &str, latency_ms: f64) {
-    #[cfg(any(feature = "prometheus", feature = "otel"))]
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
     {
         use metrics::{counter, histogram};
         counter!(
@@ -198,9 +305,200 @@ pub fn record_mcp_call(transport: &str, latency_ms: f64) {
         )
         .record(latency_ms);
     }
-    #[cfg(not(any(feature = "prometheus", feature = "otel")))]
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
     {
         // No-op when metrics are disabled
         let _ = (transport, latency_ms);
     }
 }
+
+/// Records a failed sign-in attempt.
+///
+/// # Arguments
+/// * `endpoint` - Where the attempt was made ("password", "oauth_callback", "saml_acs")
+///
+/// # Feature Requirements
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_auth_failure(endpoint: &str) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
+        use metrics::counter;
+        counter!(
+            "ark_auth_failures_total",
+            "endpoint" => endpoint.to_string()
+        )
+        .increment(1);
+    }
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
+    {
+        // No-op when metrics are disabled
+        let _ = endpoint;
+    }
+}
+
+/// Records a sign-in attempt refused because of backoff or lockout.
+///
+/// # Arguments
+/// * `endpoint` - Where the attempt was made ("password", "oauth_callback", "saml_acs")
+///
+/// # Feature Requirements
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_auth_throttled(endpoint: &str) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
+        use metrics::counter;
+        counter!(
+            "ark_auth_throttled_total",
+            "endpoint" => endpoint.to_string()
+        )
+        .increment(1);
+    }
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
+    {
+        // No-op when metrics are disabled
+        let _ = endpoint;
+    }
+}
+
+/// Records a request or connection turned away because a listener was at
+/// capacity.
+///
+/// # Arguments
+/// * `listener` - Listener name ("management", "mcp")
+/// * `kind` - What was turned away ("request", "connection")
+///
+/// # Feature Requirements
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_overloaded(listener: &str, kind: &str) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
+        use metrics::counter;
+        counter!(
+            "ark_overloaded_total",
+            "listener" => listener.to_string(),
+            "kind" => kind.to_string()
+        )
+        .increment(1);
+    }
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
+    {
+        // No-op when metrics are disabled
+        let _ = (listener, kind);
+    }
+}
+
+/// Records a management API request refused by the rate limiter.
+///
+/// # Arguments
+/// * `class` - Class of the endpoint ("auth", "plugin_mutation", "tool_execution")
+///
+/// # Feature Requirements
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_rate_limited(class: &str) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
+        use metrics::counter;
+        counter!("ark_rate_limited_total", "class" => class.to_string()).increment(1);
+    }
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
+    {
+        // No-op when metrics are disabled
+        let _ = class;
+    }
+}
+
+/// Records a username or client IP being locked out after repeated failures.
+///
+/// # Arguments
+/// * `kind` - What was locked out ("user" or "ip")
+///
+/// # Feature Requirements
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_auth_lockout(kind: &str) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
+        use metrics::counter;
+        counter!(
+            "ark_auth_lockouts_total",
+            "kind" => kind.to_string()
+        )
+        .increment(1);
+    }
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
+    {
+        // No-op when metrics are disabled
+        let _ = kind;
+    }
+}
+
+/// Records the time taken to compile and link a WASM plugin when it is loaded.
+///
+/// # Arguments
+/// * `plugin` - Plugin name
+/// * `duration_ms` - Time spent, in milliseconds
+///
+/// # Feature Requirements
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_wasm_instantiation(plugin: &str, duration_ms: f64) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
+        use metrics::histogram;
+        histogram!(
+            "ark_wasm_instantiation_ms",
+            "plugin" => plugin.to_string()
+        )
+        .record(duration_ms);
+    }
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
+    {
+        // No-op when metrics are disabled
+        let _ = (plugin, duration_ms);
+    }
+}
+
+/// Records the resources used by one WASM tool call.
+///
+/// # Arguments
+/// * `plugin` - Plugin name
+/// * `memory_peak_bytes` - High-water mark of the plugin's linear memory during the call,
+///   when `wasm_engine.track_memory` is set
+/// * `fuel_consumed` - Fuel used, when the plugin has a `fuel_limit`
+///
+/// # Feature Requirements
+/// Requires one of the `prometheus`, `otel` or `statsd` features to be enabled.
+/// When none is enabled, this function is a no-op.
+pub fn record_wasm_call_resources(
+    plugin: &str,
+    memory_peak_bytes: Option<usize>,
+    fuel_consumed: Option<u64>,
+) {
+    #[cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+    {
+        use metrics::histogram;
+        if let Some(bytes) = memory_peak_bytes {
+            histogram!(
+                "ark_wasm_memory_peak_bytes",
+                "plugin" => plugin.to_string()
+            )
+            .record(bytes as f64);
+        }
+        if let Some(fuel) = fuel_consumed {
+            histogram!(
+                "ark_wasm_fuel_consumed",
+                "plugin" => plugin.to_string()
+            )
+            .record(fuel as f64);
+        }
+    }
+    #[cfg(not(any(feature = "prometheus", feature = "otel", feature = "statsd")))]
+    {
+        // No-op when metrics are disabled
+        let _ = (plugin, memory_peak_bytes, fuel_consumed);
+    }
+}
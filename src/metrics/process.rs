@@ -0,0 +1,119 @@
+//! # Process and Runtime Metrics
+//!
+//! Samples the process (CPU time, memory, file descriptors, threads) and the
+//! tokio runtime (workers, alive tasks, global queue depth, worker busy time)
+//! and reports them as `ark_process_*` / `ark_runtime_*` metrics, so they are
+//! attributed to Ark rather than to the whole node.
+//!
+//! Process figures are read from `/proc` and are only collected on Linux.
+
+use std::time::Duration;
+
+use metrics::{counter, gauge};
+
+/// How often [`spawn_collector`] samples.
+pub const COLLECT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Spawns a task sampling process and runtime metrics every `interval`.
+///
+/// Must be called from within a tokio runtime; the runtime sampled is the
+/// one the task runs on.
+pub fn spawn_collector(interval: Duration) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(interval);
+        loop {
+            tick.tick().await;
+            collect();
+        }
+    });
+}
+
+/// Takes one sample of process and runtime metrics.
+///
+/// Runtime metrics are skipped when called outside a tokio runtime.
+pub fn collect() {
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        collect_runtime(&handle.metrics());
+    }
+    #[cfg(target_os = "linux")]
+    collect_process();
+}
+
+fn collect_runtime(metrics: &tokio::runtime::RuntimeMetrics) {
+    let workers = metrics.num_workers();
+    gauge!("ark_runtime_workers").set(workers as f64);
+    gauge!("ark_runtime_alive_tasks").set(metrics.num_alive_tasks() as f64);
+    gauge!("ark_runtime_global_queue_depth").set(metrics.global_queue_depth() as f64);
+    let busy: Duration = (0..workers)
+        .map(|worker| metrics.worker_total_busy_duration(worker))
+        .sum();
+    counter!("ark_runtime_worker_busy_ms_total").absolute(busy.as_millis() as u64);
+}
+
+#[cfg(target_os = "linux")]
+fn collect_process() {
+    if let Ok(stat) = std::fs::read_to_string("/proc/self/stat")
+        && let Some(cpu) = parse_cpu_time(&stat)
+    {
+        counter!("ark_process_cpu_ms_total").absolute(cpu.as_millis() as u64);
+    }
+    if let Ok(status) = std::fs::read_to_string("/proc/self/status") {
+        if let Some(rss) = parse_status_value(&status, "VmRSS:") {
+            gauge!("ark_process_resident_memory_bytes").set((rss * 1024) as f64);
+        }
+        if let Some(virt) = parse_status_value(&status, "VmSize:") {
+            gauge!("ark_process_virtual_memory_bytes").set((virt * 1024) as f64);
+        }
+        if let Some(threads) = parse_status_value(&status, "Threads:") {
+            gauge!("ark_process_threads").set(threads as f64);
+        }
+    }
+    if let Ok(fds) = std::fs::read_dir("/proc/self/fd") {
+        gauge!("ark_process_open_fds").set(fds.count() as f64);
+    }
+    if let Ok(limits) = std::fs::read_to_string("/proc/self/limits")
+        && let Some(max) = parse_max_open_files(&limits)
+    {
+        gauge!("ark_process_max_fds").set(max as f64);
+    }
+}
+
+/// Clock ticks per second used by `/proc/<pid>/stat` (USER_HZ); it is 100
+/// on every Linux ABI.
+#[cfg(target_os = "linux")]
+const USER_HZ: u64 = 100;
+
+/// User plus system CPU time from `/proc/self/stat`.
+#[cfg(target_os = "linux")]
+fn parse_cpu_time(stat: &str) -> Option<Duration> {
+    // The command name may contain spaces, so count fields after its ')'.
+    // utime and stime are fields 14 and 15; the state (field 3) comes first.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(Duration::from_millis((utime + stime) * 1000 / USER_HZ))
+}
+
+/// Numeric value of a `/proc/self/status` line, e.g. 1234 for "VmRSS:  1234 kB".
+#[cfg(target_os = "linux")]
+fn parse_status_value(status: &str, key: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(key))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Soft "Max open files" limit from `/proc/self/limits`; `None` if unlimited.
+#[cfg(target_os = "linux")]
+fn parse_max_open_files(limits: &str) -> Option<u64> {
+    limits
+        .lines()
+        .find_map(|line| line.strip_prefix("Max open files"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
@@ -0,0 +1,165 @@
+//! # StatsD / DogStatsD Exporter
+//!
+//! Sends every metric update as a UDP datagram to a StatsD agent, which does
+//! the aggregation. In DogStatsD mode metric labels become tags; plain StatsD
+//! has no tags, so labels are dropped there.
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+
+use anyhow::Context;
+use metrics::{
+    Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+    SharedString, Unit,
+};
+
+use crate::config::models::StatsdConfig;
+
+/// Connected socket plus the settings applied to every line.
+struct Sink {
+    socket: UdpSocket,
+    prefix: String,
+    dogstatsd: bool,
+    tags: Vec<String>,
+}
+
+impl Sink {
+    /// Formats one StatsD line and sends it.
+    ///
+    /// Send errors are ignored: a missing agent must not affect request handling.
+    fn send(&self, key: &Key, value: &str, kind: &str) {
+        let mut line = format!("{}{}:{}|{}", self.prefix, key.name(), value, kind);
+        if self.dogstatsd {
+            let tags: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}:{}", label.key(), sanitize_tag(label.value())))
+                .chain(self.tags.iter().cloned())
+                .collect();
+            if !tags.is_empty() {
+                line.push_str("|#");
+                line.push_str(&tags.join(","));
+            }
+        }
+        let _ = self.socket.send(line.as_bytes());
+    }
+}
+
+/// Replaces characters that delimit DogStatsD fields.
+fn sanitize_tag(value: &str) -> String {
+    value.replace([',', '|', '#', '\n'], "_")
+}
+
+/// Handle returned for every registered metric.
+struct StatsdMetric {
+    sink: Arc<Sink>,
+    key: Key,
+}
+
+impl CounterFn for StatsdMetric {
+    fn increment(&self, value: u64) {
+        self.sink.send(&self.key, &value.to_string(), "c");
+    }
+
+    fn absolute(&self, value: u64) {
+        // StatsD counters are deltas; an absolute value is reported as a gauge
+        self.sink.send(&self.key, &value.to_string(), "g");
+    }
+}
+
+impl GaugeFn for StatsdMetric {
+    fn increment(&self, value: f64) {
+        self.sink.send(&self.key, &format!("+{}", value), "g");
+    }
+
+    fn decrement(&self, value: f64) {
+        self.sink.send(&self.key, &format!("-{}", value), "g");
+    }
+
+    fn set(&self, value: f64) {
+        // A leading sign means a delta, so negative values need a reset first
+        if value < 0.0 {
+            self.sink.send(&self.key, "0", "g");
+        }
+        self.sink.send(&self.key, &value.to_string(), "g");
+    }
+}
+
+impl HistogramFn for StatsdMetric {
+    fn record(&self, value: f64) {
+        let kind = if self.sink.dogstatsd { "h" } else { "ms" };
+        self.sink.send(&self.key, &value.to_string(), kind);
+    }
+}
+
+/// Recorder sending metrics to a StatsD / DogStatsD agent.
+pub struct StatsdRecorder {
+    sink: Arc<Sink>,
+    address: SocketAddr,
+}
+
+impl StatsdRecorder {
+    /// Resolves the agent address and opens a non-blocking UDP socket to it.
+    ///
+    /// # Errors
+    /// Returns an error if the address does not resolve or the socket cannot
+    /// be opened.
+    pub fn new(config: &StatsdConfig) -> anyhow::Result<Self> {
+        let address = config
+            .address
+            .to_socket_addrs()
+            .with_context(|| format!("Invalid StatsD address '{}'", config.address))?
+            .next()
+            .with_context(|| format!("StatsD address '{}' did not resolve", config.address))?;
+        let local: SocketAddr = if address.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local).context("Failed to open StatsD socket")?;
+        socket
+            .connect(address)
+            .with_context(|| format!("Failed to connect StatsD socket to {}", address))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            sink: Arc::new(Sink {
+                socket,
+                prefix: config.prefix.clone().unwrap_or_default(),
+                dogstatsd: config.dogstatsd,
+                tags: config.tags.clone(),
+            }),
+            address,
+        })
+    }
+
+    /// Address of the agent the metrics are sent to.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    fn metric(&self, key: &Key) -> Arc<StatsdMetric> {
+        Arc::new(StatsdMetric {
+            sink: self.sink.clone(),
+            key: key.clone(),
+        })
+    }
+}
+
+impl Recorder for StatsdRecorder {
+    // StatsD has no metadata
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.metric(key))
+    }
+
+    fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.metric(key))
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.metric(key))
+    }
+}
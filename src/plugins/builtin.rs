@@ -1,32 +1,51 @@
 //! Built-in plugin implementation for the Ark MCP server.
 //!
-//! This module provides the built-in "echo" tool that simply returns the input message
-//! as output. It serves as an example plugin and basic functionality test for the
-//! MCP server infrastructure.
+//! This module provides diagnostic tools that are registered when no other plugin
+//! is loaded, so a fresh install can be used to check that clients connect and
+//! call tools. It also serves as an example plugin for the MCP server infrastructure.
 //!
 //! # Built-in Tools
 //!
 //! - **echo**: Returns the input message unchanged, useful for testing and basic functionality.
+//! - **server_info**: Reports the server name, version, uptime and MCP transport.
+//! - **list_capabilities**: Lists the registered plugins and tools, and which
+//!   management APIs, authentication and TLS are enabled.
+//! - **time**: Returns the server's current time, to check for clock skew.
+//! - **ping_url**: Requests a URL from the server and reports the status and
+//!   latency. Administrators only, as it makes the server reach arbitrary hosts.
 
 use crate::config::plugins::{ArkPlugin, PluginManifest};
 use crate::plugins::registry::ToolProvider;
 use crate::plugins::{ToolContentBlock, ToolResult, ToolSet};
+use crate::server::authz;
+use crate::server::constants::MCP_SERVER_INFO_NAME;
+use crate::state::{ArkState, TlsStatus};
 use rmcp::ErrorData;
 use rmcp::model::Tool;
 use rmcp::serde_json::Value;
-use serde_json::Map;
+use serde_json::{Map, json};
 use std::collections::BTreeMap;
-use std::{borrow::Cow, sync::Arc};
+use std::time::{Duration, Instant};
+use std::{
+    borrow::Cow,
+    sync::{Arc, Weak},
+};
 
 /// Identifier for the built-in plugin in the plugin registry.
 pub const BUILTIN_PLUGIN_ID: &str = "__BUILTIN__";
 
-/// Built-in plugin implementation providing basic echo functionality.
+/// How long `ping_url` waits for a response.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Built-in plugin implementation providing diagnostic tools.
 ///
 /// This plugin serves as the default tool provider for the Ark MCP server,
-/// offering a simple echo tool for testing and basic functionality verification.
+/// offering tools for testing and basic functionality verification. It holds
+/// the server state weakly, as the state's registry holds the plugin.
 #[derive(Debug, Clone)]
-pub struct BuiltinPlugin;
+pub struct BuiltinPlugin {
+    state: Weak<ArkState>,
+}
 
 #[async_trait::async_trait]
 impl ToolProvider for BuiltinPlugin {
@@ -37,8 +56,8 @@ impl ToolProvider for BuiltinPlugin {
 
     /// Executes a tool with the given input parameters.
     ///
-    /// For the built-in echo tool, this extracts the "message" field from the input
-    /// and returns it as both structured content and text content.
+    /// The tool is named by the input's "tool" field, defaulting to echo,
+    /// and receives the whole input as its arguments.
     ///
     /// # Arguments
     /// * `input` - JSON value containing tool arguments
@@ -46,70 +65,121 @@ impl ToolProvider for BuiltinPlugin {
     /// # Returns
     /// A result containing the tool execution output or an error.
     async fn call(&self, input: &Value) -> Result<Value, ErrorData> {
-        // Extract the "message" field from input, defaulting to empty string
-        let message = input
-            .get("message")
-            .and_then(Value::as_str)
-            .unwrap_or_default();
-
-        let block = ToolContentBlock {
-            kind: "text",
-            text: message,
-        };
-
-        let result = ToolResult {
-            content: vec![block],
-            structured_content: message,
-            is_error: false,
-        };
-
-        rmcp::serde_json::to_value(&result)
-            .map_err(|_| ErrorData::invalid_params("failed to serialize tool result", None))
+        let tool = input.get("tool").and_then(Value::as_str).unwrap_or("echo");
+        self.call_tool(tool, input).await
     }
 }
 
 impl BuiltinPlugin {
+    /// Creates the built-in plugin reporting on `state`.
+    pub fn new(state: &Arc<ArkState>) -> Self {
+        Self {
+            state: Arc::downgrade(state),
+        }
+    }
+
     /// Creates the tool set definition for the built-in plugin.
     ///
-    /// This method defines the "echo" tool with its input schema, description,
-    /// and metadata. The tool accepts a single "message" parameter of type string.
+    /// This method defines each tool with its input schema, description,
+    /// and metadata. Only echo (a "message" string) and ping_url (a "url"
+    /// string) take parameters.
     ///
     /// # Returns
-    /// A `ToolSet` containing the echo tool definition.
+    /// A `ToolSet` containing the built-in tool definitions.
     pub fn toolset(&self) -> ToolSet {
-        // Define the input schema for the message parameter
-        let mut properties = Map::new();
-        properties.insert(
-            "message".to_string(),
-            Value::Object({
-                let mut obj = Map::new();
-                obj.insert("type".to_string(), Value::String("string".to_string()));
-                obj
-            }),
-        );
-
-        // Define the complete input schema as an object
-        let mut input_schema = Map::new();
-        input_schema.insert("type".to_string(), Value::String("object".to_string()));
-        input_schema.insert("properties".to_string(), Value::Object(properties));
-
-        // Create the echo tool definition
-        let tool = Tool {
-            name: Cow::Borrowed("echo"),
-            title: Some("Echo Tool".to_string()),
-            description: Some(Cow::Borrowed("Returns the input message as output.")),
-            input_schema: Arc::new(input_schema),
-            output_schema: None,
-            annotations: None,
-            icons: None,
-        };
+        let tools = vec![
+            tool(
+                "echo",
+                "Echo Tool",
+                "Returns the input message as output.",
+                json!({ "message": { "type": "string" } }),
+            ),
+            tool(
+                "server_info",
+                "Server Info",
+                "Returns the server name, version, uptime and MCP transport.",
+                json!({}),
+            ),
+            tool(
+                "list_capabilities",
+                "List Capabilities",
+                "Lists the registered plugins and tools, and the enabled management APIs, authentication and TLS.",
+                json!({}),
+            ),
+            tool(
+                "time",
+                "Server Time",
+                "Returns the server's current time in UTC.",
+                json!({}),
+            ),
+            tool(
+                "ping_url",
+                "Ping URL",
+                "Requests a URL from the server and returns the HTTP status and latency. Administrators only.",
+                json!({ "url": { "type": "string", "format": "uri" } }),
+            ),
+        ];
 
         ToolSet {
-            name: tool.name.clone().into_owned(),
-            tools: vec![tool],
+            name: "builtin".to_string(),
+            tools,
+        }
+    }
+
+    /// Executes the built-in tool named `tool`.
+    ///
+    /// # Errors
+    /// Returns an error for an unknown tool, invalid arguments, or a
+    /// `ping_url` call by a caller who is not an administrator.
+    pub async fn call_tool(&self, tool: &str, input: &Value) -> Result<Value, ErrorData> {
+        match tool {
+            "echo" => echo(input),
+            "server_info" => {
+                let state = self.state()?;
+                let uptime = state.created_at.elapsed().as_secs();
+                Ok(json_result(json!({
+                    "name": MCP_SERVER_INFO_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "uptime_secs": uptime,
+                    "transport": state.get_transport(),
+                })))
+            }
+            "list_capabilities" => {
+                let state = self.state()?;
+                Ok(json_result(capabilities(&state)))
+            }
+            "time" => {
+                let now = chrono::Utc::now();
+                Ok(json_result(json!({
+                    "utc": now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                    "unix_ms": now.timestamp_millis(),
+                })))
+            }
+            "ping_url" => {
+                if !authz::caller_is_admin() {
+                    return Err(ErrorData::invalid_request(
+                        "Permission denied: ping_url is restricted to administrators",
+                        None,
+                    ));
+                }
+                let url = input
+                    .get("url")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| ErrorData::invalid_params("url is required", None))?;
+                ping(url).await
+            }
+            _ => Err(ErrorData::method_not_found::<
+                rmcp::model::CallToolRequestMethod,
+            >()),
         }
     }
 
+    fn state(&self) -> Result<Arc<ArkState>, ErrorData> {
+        self.state
+            .upgrade()
+            .ok_or_else(|| ErrorData::internal_error("server is shutting down", None))
+    }
+
     /// Converts a tool provider into a plugin configuration tuple.
     ///
     /// This utility method creates a plugin configuration and toolset pair
@@ -137,9 +207,119 @@ impl BuiltinPlugin {
             ])),
             allowed_hosts: None,
             allowed_paths: None,
+            fuel_limit: None,
+            scratch: None,
+            warmup: false,
+            tools: BTreeMap::new(),
         };
 
         let config = ArkPlugin::new(plugin_id.to_string(), Some(manifest));
         (config, toolset)
     }
 }
+
+/// Defines a tool whose input is an object with the given `properties`.
+fn tool(name: &'static str, title: &str, description: &'static str, properties: Value) -> Tool {
+    let mut input_schema = Map::new();
+    input_schema.insert("type".to_string(), Value::String("object".to_string()));
+    input_schema.insert("properties".to_string(), properties);
+    Tool {
+        name: Cow::Borrowed(name),
+        title: Some(title.to_string()),
+        description: Some(Cow::Borrowed(description)),
+        input_schema: Arc::new(input_schema),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+/// Returns the "message" field of the input as both structured content and
+/// text content.
+fn echo(input: &Value) -> Result<Value, ErrorData> {
+    // Extract the "message" field from input, defaulting to empty string
+    let message = input
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    let block = ToolContentBlock {
+        kind: "text",
+        text: message,
+    };
+
+    let result = ToolResult {
+        content: vec![block],
+        structured_content: message,
+        is_error: false,
+    };
+
+    rmcp::serde_json::to_value(&result)
+        .map_err(|_| ErrorData::invalid_params("failed to serialize tool result", None))
+}
+
+/// Wraps a JSON object as a tool result, with its text as the text content.
+fn json_result(structured: Value) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": structured.to_string() }],
+        "structuredContent": structured,
+        "isError": false,
+    })
+}
+
+fn capabilities(state: &ArkState) -> Value {
+    let catalog = state.plugin_registry.catalog.load();
+    let mut plugins: Vec<&String> = catalog.plugin_to_config.keys().collect();
+    plugins.sort();
+    let mut tools: Vec<&String> = catalog.tool_to_def.keys().collect();
+    tools.sort();
+    let auth = state
+        .auth_state
+        .read()
+        .ok()
+        .and_then(|a| a.as_ref().map(|a| a.enabled))
+        .unwrap_or(false);
+    json!({
+        "plugins": plugins,
+        "tools": tools,
+        "apis": {
+            "plugins": state.is_plugin_api_enabled(),
+            "health": state.is_health_api_enabled(),
+            "metrics": state.is_prometheus_api_enabled(),
+            "console": state.is_console_enabled(),
+        },
+        "auth": auth,
+        "tls": state.tls_status() == TlsStatus::Loaded,
+    })
+}
+
+/// Requests `url` without following redirects. An unreachable URL is
+/// reported as a failed tool result rather than an error.
+async fn ping(url: &str) -> Result<Value, ErrorData> {
+    let parsed = url::Url::parse(url)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .ok_or_else(|| ErrorData::invalid_params("url must be an http or https URL", None))?;
+    let client = reqwest::Client::builder()
+        .timeout(PING_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+    let started = Instant::now();
+    let response = client.get(parsed).send().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    Ok(match response {
+        Ok(response) => json_result(json!({
+            "url": url,
+            "status": response.status().as_u16(),
+            "latency_ms": latency_ms,
+        })),
+        Err(e) => {
+            let message = format!("Request to {} failed: {}", url, e);
+            json!({
+                "content": [{ "type": "text", "text": message }],
+                "isError": true,
+            })
+        }
+    })
+}
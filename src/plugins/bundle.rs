@@ -0,0 +1,171 @@
+//! Self-contained plugin bundles.
+//!
+//! A bundle carries a plugin's WASM module together with its manifest and the
+//! metadata identifying the build it was taken from (source URL, digest and
+//! the date it was first added), so a plugin can be moved to another server
+//! that has no access to the original source. Bundles are JSON documents with
+//! the module base64-encoded.
+
+use anyhow::{anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use super::PluginLoadResult;
+use super::wasm::WasmHandler;
+use crate::config::plugins::{ArkPlugin, PluginManifest};
+use crate::errors::SignatureError;
+
+/// Format identifier written to, and required in, every bundle.
+pub const BUNDLE_FORMAT: &str = "ark-plugin-bundle/1";
+
+/// Key under which imported plugins keep the bundle's version information in
+/// their persisted metadata.
+pub const BUNDLE_METADATA_KEY: &str = "bundle";
+
+/// A plugin exported for import into another server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginBundle {
+    /// Always [`BUNDLE_FORMAT`].
+    pub format: String,
+    /// Plugin name.
+    pub name: String,
+    /// Where the plugin was originally loaded from, without credentials or
+    /// query string. OCI tags and versioned paths are kept.
+    pub source_url: String,
+    /// Hex-encoded SHA-256 digest of the WASM module.
+    pub sha256: String,
+    /// When the plugin was first added, if known.
+    pub date_added_utc: Option<DateTime<Utc>>,
+    /// When the bundle was created.
+    pub exported_utc: DateTime<Utc>,
+    /// Whether the plugin was allowed insecure transports.
+    pub insecure: bool,
+    /// Runtime manifest of the plugin.
+    pub manifest: Option<PluginManifest>,
+    /// Names of the only tools of the plugin to expose, if restricted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools_allow: Option<Vec<String>>,
+    /// Names of tools of the plugin not to expose.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools_deny: Option<Vec<String>>,
+    /// Base64-encoded WASM module.
+    pub wasm: String,
+}
+
+impl PluginBundle {
+    /// Bundles `wasm` with the configuration of `plugin`.
+    ///
+    /// `date_added_utc` is the date recorded for the plugin, if any. Plugins
+    /// that were themselves imported keep the version information of their
+    /// original bundle, found in `metadata`.
+    ///
+    /// # Errors
+    /// Returns an error if the plugin has no source URL.
+    pub fn new(
+        plugin: &ArkPlugin,
+        wasm: &[u8],
+        metadata: Option<&Value>,
+        date_added_utc: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Self> {
+        let url = plugin
+            .url
+            .as_ref()
+            .ok_or_else(|| anyhow!("Plugin '{}' has no source URL", plugin.name))?;
+        let original = metadata.and_then(|m| m.get(BUNDLE_METADATA_KEY));
+        let date_added_utc = original
+            .and_then(|b| b.get("date_added_utc"))
+            .and_then(|d| serde_json::from_value(d.clone()).ok())
+            .or(date_added_utc);
+        Ok(Self {
+            format: BUNDLE_FORMAT.to_string(),
+            name: plugin.name.clone(),
+            source_url: super::sanitized_url(url),
+            sha256: hex::encode(Sha256::digest(wasm)),
+            date_added_utc,
+            exported_utc: Utc::now(),
+            insecure: plugin.insecure,
+            manifest: plugin.manifest.clone(),
+            tools_allow: plugin.tools_allow.clone(),
+            tools_deny: plugin.tools_deny.clone(),
+            wasm: STANDARD.encode(wasm),
+        })
+    }
+
+    /// Checks the bundle and returns the plugin configuration and WASM module
+    /// it carries. The plugin keeps the bundle's source URL.
+    ///
+    /// # Errors
+    /// Returns an error for an unknown format, an invalid name or source URL,
+    /// or a module that does not match its digest.
+    pub fn unpack(&self) -> anyhow::Result<(ArkPlugin, Vec<u8>)> {
+        if self.format != BUNDLE_FORMAT {
+            bail!("Unsupported bundle format '{}'", self.format);
+        }
+        if self.name.is_empty() || self.name.contains('/') {
+            bail!("Invalid plugin name '{}'", self.name);
+        }
+        let url = url::Url::parse(&self.source_url)
+            .map_err(|e| anyhow!("Invalid source URL '{}': {e}", self.source_url))?;
+        let wasm = STANDARD
+            .decode(&self.wasm)
+            .map_err(|e| anyhow!("Invalid WASM encoding: {e}"))?;
+        if !hex::encode(Sha256::digest(&wasm)).eq_ignore_ascii_case(&self.sha256) {
+            return Err(
+                SignatureError("WASM module does not match its sha256 digest".to_string()).into(),
+            );
+        }
+        let plugin = ArkPlugin {
+            name: self.name.clone(),
+            url: Some(url),
+            auth: None,
+            insecure: self.insecure,
+            manifest: self.manifest.clone(),
+            owner: None,
+            tools_allow: self.tools_allow.clone(),
+            tools_deny: self.tools_deny.clone(),
+            sha256: None,
+        };
+        Ok((plugin, wasm))
+    }
+
+    /// Size of the WASM module in bytes, computed from its encoding without
+    /// decoding it.
+    pub fn wasm_size(&self) -> u64 {
+        let padding = self.wasm.bytes().rev().take_while(|&b| b == b'=').count();
+        (self.wasm.len() / 4 * 3).saturating_sub(padding) as u64
+    }
+
+    /// Version information persisted with an imported plugin, under
+    /// [`BUNDLE_METADATA_KEY`].
+    pub fn version_metadata(&self) -> Value {
+        json!({
+            "source_url": self.source_url,
+            "sha256": self.sha256.to_ascii_lowercase(),
+            "date_added_utc": self.date_added_utc,
+        })
+    }
+}
+
+/// Instantiates a plugin from the WASM module unpacked from a bundle.
+///
+/// # Errors
+/// Returns an error if the module cannot be instantiated or described.
+pub async fn load_wasm(plugin: &ArkPlugin, wasm: Vec<u8>) -> anyhow::Result<PluginLoadResult> {
+    let handler = WasmHandler::new(wasm.clone(), &plugin.name, &plugin.manifest)?;
+    let toolset = handler.describe(plugin).await?;
+    handler.warm_up().await;
+    let executors = toolset
+        .tools
+        .iter()
+        .map(|t| (t.name.to_string(), handler.build_executor(t.name.as_ref())))
+        .collect();
+    Ok(PluginLoadResult {
+        toolset,
+        executors,
+        raw_bytes: Some(wasm),
+        source_url: plugin.url.as_ref().map(|u| u.to_string()),
+    })
+}
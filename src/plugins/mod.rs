@@ -22,12 +22,16 @@
 //! 5. If no plugins are loaded, built-in diagnostic tools are registered
 
 pub mod builtin;
+pub mod bundle;
 pub mod oci;
 pub mod registry;
+mod scratch;
 pub mod url;
 pub mod wasm;
+mod wasm_memory;
 
 // (JSON schema helper types were removed from the server; the frontend owns schema handling)
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use super::config::ArkConfig;
@@ -42,6 +46,7 @@ use rmcp::model::Tool;
 use serde::de::Error as _;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use tracing::Instrument;
 use url::UrlHandler;
 
 /// Result of loading a plugin from a URI source.
@@ -109,7 +114,8 @@ pub struct ToolResult<'a> {
 ///
 /// Creates a mapping of tool names to execution functions that delegate
 /// to the provided plugin handler. Each tool gets its own closure that
-/// captures the necessary context for execution.
+/// captures the necessary context for execution, and names the tool in the
+/// input's "tool" field, as `ToolProvider::call` expects.
 ///
 /// # Arguments
 /// * `toolset` - The tool set containing tool definitions
@@ -127,9 +133,13 @@ pub async fn build_executors(
         .map(|tool| {
             let name = tool.name.to_string();
             let handler = Arc::clone(&handler);
+            let tool_name = name.clone();
 
-            let exec_fn: ToolExecFn = Arc::new(move |input: Value| {
+            let exec_fn: ToolExecFn = Arc::new(move |mut input: Value| {
                 let handler = Arc::clone(&handler);
+                if let Value::Object(args) = &mut input {
+                    args.insert("tool".to_string(), Value::String(tool_name.clone()));
+                }
                 handler(input)
             });
 
@@ -138,6 +148,65 @@ pub async fn build_executors(
         .collect()
 }
 
+/// Applies the `tools_allow` and `tools_deny` lists and the `manifest.tools`
+/// overrides of `plugin` to its tools.
+///
+/// Tools left out by the lists or hidden are dropped along with their
+/// executors, and renamed tools are registered under their new name;
+/// executors keep calling the plugin with the tool's own name, which they
+/// captured when built. The lists and overrides name tools as the plugin
+/// does; names the plugin does not have are logged and ignored.
+pub fn apply_tool_overrides(
+    plugin: &ArkPlugin,
+    toolset: &mut ToolSet,
+    executors: &mut Vec<(String, ToolExecFn)>,
+) {
+    let no_overrides = BTreeMap::new();
+    let overrides = plugin.manifest.as_ref().map_or(&no_overrides, |m| &m.tools);
+    let allow = plugin.tools_allow.as_deref();
+    let deny = plugin.tools_deny.as_deref().unwrap_or_default();
+    let named = overrides
+        .keys()
+        .chain(allow.unwrap_or_default())
+        .chain(deny);
+    for name in named {
+        if !toolset.tools.iter().any(|t| t.name == name.as_str()) {
+            tracing::warn!("Plugin '{}' has no tool '{}'", plugin.name, name);
+        }
+    }
+
+    let hidden = |name: &str| {
+        overrides.get(name).is_some_and(|o| o.hidden)
+            || deny.iter().any(|n| n == name)
+            || allow.is_some_and(|allow| !allow.iter().any(|n| n == name))
+    };
+    toolset.tools.retain(|tool| !hidden(&tool.name));
+    executors.retain(|(name, _)| !hidden(name));
+
+    for tool in &mut toolset.tools {
+        let Some(overrides) = overrides.get(tool.name.as_ref()) else {
+            continue;
+        };
+        if let Some(title) = &overrides.title {
+            tool.title = Some(title.clone());
+        }
+        if let Some(description) = &overrides.description {
+            tool.description = Some(description.clone().into());
+        }
+        if let Some(annotations) = &overrides.annotations {
+            tool.annotations = Some(annotations.clone());
+        }
+        if let Some(name) = &overrides.name {
+            tool.name = name.clone().into();
+        }
+    }
+    for (name, _) in executors.iter_mut() {
+        if let Some(renamed) = overrides.get(name.as_str()).and_then(|o| o.name.clone()) {
+            *name = renamed;
+        }
+    }
+}
+
 /// Loads all configured plugins and registers them with the application state.
 ///
 /// This function iterates through the plugin configuration, loads each plugin
@@ -154,7 +223,8 @@ pub async fn build_executors(
 ///
 /// # Behavior
 /// - Loads plugins in the order they appear in configuration
-/// - Falls back to built-in echo tool if no external plugins are loaded
+/// - Falls back to the built-in diagnostic tools if no external plugins are
+///   loaded, or registers them anyway with `always_load_builtin`
 /// - Logs progress and any failures during loading
 pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::Result<()> {
     tracing::debug!("Searching for configured plugins");
@@ -182,8 +252,7 @@ pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::R
                     if state
                         .plugin_registry
                         .catalog
-                        .read()
-                        .await
+                        .load()
                         .plugin_to_config
                         .contains_key(&rec.plugin_id)
                     {
@@ -216,10 +285,19 @@ pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::R
                             )
                             .ok(),
                             owner: Some(rec.owner.clone()),
+                            tools_allow: persisted_tool_list(&rec.metadata, "tools_allow"),
+                            tools_deny: persisted_tool_list(&rec.metadata, "tools_deny"),
+                            sha256: rec
+                                .metadata
+                                .get("sha256")
+                                .and_then(Value::as_str)
+                                .map(str::to_string),
                         };
-                        match wasm::WasmHandler::new(bytes, &plugin_cfg.manifest) {
+                        match wasm::WasmHandler::new(bytes, &plugin_cfg.name, &plugin_cfg.manifest)
+                        {
                             Ok(wasm) => match wasm.describe(&plugin_cfg).await {
                                 Ok(toolset) => {
+                                    wasm.warm_up().await;
                                     let executors = toolset
                                         .tools
                                         .iter()
@@ -245,19 +323,26 @@ pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::R
                                             rec.plugin_id,
                                             e
                                         );
+                                        state.record_plugin_failure(&rec.plugin_id, e.message);
                                     }
                                 }
-                                Err(e) => tracing::warn!(
-                                    "Failed to describe persisted plugin '{}' from DB: {:?}",
+                                Err(e) => {
+                                    tracing::warn!(
+                                        "Failed to describe persisted plugin '{}' from DB: {:?}",
+                                        rec.plugin_id,
+                                        e
+                                    );
+                                    state.record_plugin_failure(&rec.plugin_id, e);
+                                }
+                            },
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Failed to initialize Wasm for persisted plugin '{}' from DB: {:?}",
                                     rec.plugin_id,
                                     e
-                                ),
-                            },
-                            Err(e) => tracing::warn!(
-                                "Failed to initialize Wasm for persisted plugin '{}' from DB: {:?}",
-                                rec.plugin_id,
-                                e
-                            ),
+                                );
+                                state.record_plugin_failure(&rec.plugin_id, e);
+                            }
                         }
                         continue;
                     }
@@ -284,6 +369,13 @@ pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::R
                             )
                             .ok(),
                             owner: Some(rec.owner.clone()),
+                            tools_allow: persisted_tool_list(&rec.metadata, "tools_allow"),
+                            tools_deny: persisted_tool_list(&rec.metadata, "tools_deny"),
+                            sha256: rec
+                                .metadata
+                                .get("sha256")
+                                .and_then(Value::as_str)
+                                .map(str::to_string),
                         };
                         match read_plugin_data(&reconstructed).await {
                             Ok(result) => {
@@ -300,13 +392,17 @@ pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::R
                                         rec.plugin_id,
                                         e
                                     );
+                                    state.record_plugin_failure(&rec.plugin_id, e.message);
                                 }
                             }
-                            Err(e) => tracing::warn!(
-                                "Failed to reload persisted plugin '{}' from path: {:?}",
-                                rec.plugin_id,
-                                e
-                            ),
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Failed to reload persisted plugin '{}' from path: {:?}",
+                                    rec.plugin_id,
+                                    e
+                                );
+                                state.record_plugin_failure(&rec.plugin_id, e);
+                            }
                         }
                     }
                 }
@@ -315,11 +411,26 @@ pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::R
         }
     }
 
-    if state.plugin_registry.tools(None).await?.is_empty() {
-        tracing::warn!("No plugins loaded, registering builtin diagnostic plugins");
+    let registered = state.plugin_registry.tools(None).await?;
+    if registered.is_empty() || config.always_load_builtin {
+        if registered.is_empty() {
+            tracing::warn!("No plugins loaded, registering builtin diagnostic plugins");
+        }
 
-        let plugin = Arc::new(BuiltinPlugin);
-        let (plugin_config, toolset) = BuiltinPlugin::as_plugin_config(BUILTIN_PLUGIN_ID, &*plugin);
+        let plugin = Arc::new(BuiltinPlugin::new(&state));
+        let (plugin_config, mut toolset) =
+            BuiltinPlugin::as_plugin_config(BUILTIN_PLUGIN_ID, &*plugin);
+        // Tools of loaded plugins win over builtin tools of the same name
+        toolset.tools.retain(|tool| {
+            let taken = registered.iter().any(|t| t.name == tool.name);
+            if taken {
+                tracing::warn!(
+                    "Builtin tool '{}' not registered, a plugin provides a tool of that name",
+                    tool.name
+                );
+            }
+            !taken
+        });
 
         let plugin_clone = Arc::clone(&plugin);
         let handler: PluginHandler = Arc::new(move |input: Value| {
@@ -333,12 +444,17 @@ pub async fn load_plugins(config: &ArkConfig, state: Arc<ArkState>) -> anyhow::R
             .register_plugin_with_executors(plugin_config, toolset, executors)
             .await?;
 
-        tracing::debug!("Builtin EchoPlugin registered");
+        tracing::debug!("Builtin diagnostic plugin registered");
     }
 
     Ok(())
 }
 
+/// Reads a tool allow or deny list from a persisted plugin's metadata.
+fn persisted_tool_list(metadata: &Value, key: &str) -> Option<Vec<String>> {
+    serde_json::from_value(metadata.get(key)?.clone()).ok()
+}
+
 /// Reads and loads plugin data from the configured source.
 ///
 /// This function determines the appropriate handler based on the plugin's URL scheme
@@ -368,14 +484,19 @@ pub async fn read_plugin_data(plugin: &ArkPlugin) -> anyhow::Result<PluginLoadRe
         .ok_or_else(|| anyhow!("Missing plugin path"))?;
 
     let scheme = url.scheme();
+    let span = tracing::info_span!(
+        "plugin.fetch",
+        plugin = %plugin.name,
+        url = %sanitized_url(&url)
+    );
     let result = match scheme {
         "http" | "https" | "file" => {
             let h = UrlHandler;
-            h.get(plugin).await
+            h.get(plugin).instrument(span).await
         }
         "oci" => {
             let h = OciHandler;
-            h.get(plugin).await
+            h.get(plugin).instrument(span).await
         }
         _ => {
             tracing::warn!("Scheme {} for path {} is not supported", scheme, url);
@@ -19,7 +19,9 @@ use tokio::io::AsyncWrite;
 use zstd::stream::read::Decoder as ZstdDecoder;
 
 use super::PluginLoadResult;
+use super::url::max_plugin_bytes;
 use crate::config::{models::OciAuthentication, plugins::ArkPlugin};
+use crate::errors::SignatureError;
 use crate::plugins::wasm::WasmHandler;
 use oci_client::{
     Reference,
@@ -47,8 +49,8 @@ const TAR_ZSTD_MEDIA_TYPES: &[&str] = &[
     "application/vnd.oci.image.layer.nondistributable.v1.tar+zstd",
 ];
 
-const MAX_BLOB_SIZE: u64 = 128 * 1024 * 1024; // 128 MiB, compressed as stored
-const MAX_WASM_SIZE: u64 = 128 * 1024 * 1024; // 128 MiB, after extraction
+// Layers, as stored and after extraction, are limited to
+// `plugin_downloads.max_plugin_bytes` (see `max_plugin_bytes`).
 
 /// A bounded `AsyncWrite` sink that caps the number of bytes accepted to prevent unbounded growth.
 struct LimitedAsyncWriter {
@@ -115,7 +117,7 @@ impl AsyncWrite for LimitedAsyncWriter {
 fn build_auth(config: &ArkPlugin) -> anyhow::Result<RegistryAuth> {
     match config.auth.clone().unwrap_or(OciAuthentication::Anonymous) {
         OciAuthentication::Anonymous => Ok(RegistryAuth::Anonymous),
-        OciAuthentication::Bearer { token } => {
+        OciAuthentication::Bearer { token, .. } => {
             if token.is_empty() {
                 warn!(
                     repo = LOCAL_LOG_PREFIX,
@@ -126,7 +128,9 @@ fn build_auth(config: &ArkPlugin) -> anyhow::Result<RegistryAuth> {
                 Ok(RegistryAuth::Bearer(token))
             }
         }
-        OciAuthentication::Basic { username, password } => {
+        OciAuthentication::Basic {
+            username, password, ..
+        } => {
             if username.is_empty() || password.is_empty() {
                 warn!(
                     repo = LOCAL_LOG_PREFIX,
@@ -137,6 +141,13 @@ fn build_auth(config: &ArkPlugin) -> anyhow::Result<RegistryAuth> {
                 Ok(RegistryAuth::Basic(username, password))
             }
         }
+        OciAuthentication::Headers { .. } => {
+            warn!(
+                repo = LOCAL_LOG_PREFIX,
+                "Custom headers are not supported for OCI registries; proceeding with anonymous access"
+            );
+            Ok(RegistryAuth::Anonymous)
+        }
     }
 }
 
@@ -272,11 +283,12 @@ async fn fetch_blob_checked(
         bail!("{LOCAL_LOG_PREFIX} Negative layer size in descriptor");
     }
     let expected_size = desc.size as u64;
-    if expected_size > MAX_BLOB_SIZE {
+    let max = max_plugin_bytes();
+    if expected_size > max {
         bail!(
-            "{LOCAL_LOG_PREFIX} Layer size {} exceeds maximum {} bytes",
+            "{LOCAL_LOG_PREFIX} Layer size {} exceeds maximum {} bytes (plugin_downloads.max_plugin_bytes)",
             expected_size,
-            MAX_BLOB_SIZE
+            max
         );
     }
 
@@ -307,11 +319,11 @@ async fn fetch_blob_checked(
         Some(("sha256", expected_hex)) => {
             let computed_hex = hex::encode(sha2::Sha256::digest(&buf));
             if computed_hex != expected_hex {
-                bail!(
+                return Err(SignatureError(format!(
                     "{LOCAL_LOG_PREFIX} Layer digest mismatch: expected sha256:{}, got sha256:{}",
-                    expected_hex,
-                    computed_hex
-                );
+                    expected_hex, computed_hex
+                ))
+                .into());
             }
         }
         Some((algo, _)) => bail!("{LOCAL_LOG_PREFIX} Unsupported digest algorithm: {}", algo),
@@ -326,11 +338,12 @@ async fn fetch_blob_checked(
 
 fn decode_wasm_from_layer(desc: &manifest::OciDescriptor, blob: &[u8]) -> anyhow::Result<Vec<u8>> {
     let mt = desc.media_type.as_str();
+    let max = max_plugin_bytes();
     if mt == RAW_WASM_MEDIA_TYPE {
-        if blob.len() as u64 > MAX_WASM_SIZE {
+        if blob.len() as u64 > max {
             bail!(
-                "{LOCAL_LOG_PREFIX} WASM layer exceeds maximum {} bytes",
-                MAX_WASM_SIZE
+                "{LOCAL_LOG_PREFIX} WASM layer exceeds maximum {} bytes (plugin_downloads.max_plugin_bytes)",
+                max
             );
         }
         return Ok(blob.to_vec());
@@ -351,10 +364,10 @@ fn decode_wasm_from_layer(desc: &manifest::OciDescriptor, blob: &[u8]) -> anyhow
             Err(_) => {
                 // Fallback: check if blob is raw WASM (handles mislabeled layers)
                 if blob.len() >= 8 && &blob[0..4] == b"\0asm" {
-                    if blob.len() as u64 > MAX_WASM_SIZE {
+                    if blob.len() as u64 > max {
                         bail!(
-                            "{LOCAL_LOG_PREFIX} WASM layer exceeds maximum {} bytes",
-                            MAX_WASM_SIZE
+                            "{LOCAL_LOG_PREFIX} WASM layer exceeds maximum {} bytes (plugin_downloads.max_plugin_bytes)",
+                            max
                         );
                     }
                     return Ok(blob.to_vec());
@@ -410,11 +423,15 @@ fn extract_wasm_from_tar<R: Read>(reader: R) -> anyhow::Result<Vec<u8>> {
                         repo = LOCAL_LOG_PREFIX,
                         "Found WASM file in tar: {}", path_str
                     );
+                    let max = max_plugin_bytes();
                     let mut buf = Vec::new();
-                    let mut limited = e.take(MAX_WASM_SIZE + 1);
+                    let mut limited = e.take(max + 1);
                     let n = limited.read_to_end(&mut buf)?;
-                    if n as u64 > MAX_WASM_SIZE {
-                        bail!("{LOCAL_LOG_PREFIX} wasm file exceeds maximum allowed size");
+                    if n as u64 > max {
+                        bail!(
+                            "{LOCAL_LOG_PREFIX} wasm file exceeds maximum {} bytes (plugin_downloads.max_plugin_bytes)",
+                            max
+                        );
                     }
                     return Ok(buf);
                 } else {
@@ -455,10 +472,21 @@ impl UriHandler for OciHandler {
         }
         let url = plugin_config.url.clone().unwrap();
         let start = Instant::now(); // Measure load + init time for diagnostics
+        if plugin_config.sha256.is_some() {
+            warn!(
+                repo = LOCAL_LOG_PREFIX,
+                "Ignoring sha256 of plugin '{}'; OCI plugins are verified by digest and signature",
+                plugin_config.name
+            );
+        }
         let wasm_bytes = download_and_verify_image(plugin_config).await?;
 
         // Initialize WASM plugin
-        let wasm = WasmHandler::new(wasm_bytes.clone(), &plugin_config.manifest)?; // Validates module and required exports
+        let wasm = WasmHandler::new(
+            wasm_bytes.clone(),
+            &plugin_config.name,
+            &plugin_config.manifest,
+        )?; // Validates module and required exports
 
         // Execute plugin
         let exec_start = Instant::now();
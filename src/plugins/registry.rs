@@ -1,6 +1,14 @@
+use arc_swap::ArcSwap;
 use futures::future::BoxFuture;
 use rmcp::{ErrorData, model::Tool, serde_json::Value};
-use std::{collections::HashMap, fmt, sync::Arc};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+use tracing::Instrument;
 
 use crate::config::plugins::ArkPlugin;
 use crate::plugins::ToolSet;
@@ -11,7 +19,7 @@ pub type PluginHandler =
     Arc<dyn Fn(Value) -> BoxFuture<'static, Result<Value, ErrorData>> + Send + Sync + 'static>;
 
 /// Application inner store holding plugin metadata and handlers (moved inside PluginRegistry).
-#[derive(Default)]
+#[derive(Clone)]
 pub struct PluginStore {
     /// Plugin definitions mapped by plugin ID.
     pub plugin_to_config: HashMap<String, ArkPlugin>,
@@ -24,6 +32,25 @@ pub struct PluginStore {
 
     /// Tool name to tool definition mapping.
     pub tool_to_def: HashMap<String, Tool>,
+
+    /// Incremented whenever a plugin is registered or unregistered.
+    pub generation: u64,
+
+    /// Random id of this store, so entity tags differ across restarts.
+    instance: u64,
+}
+
+impl Default for PluginStore {
+    fn default() -> Self {
+        Self {
+            plugin_to_config: HashMap::new(),
+            tool_to_plugin: HashMap::new(),
+            tool_to_handler: HashMap::new(),
+            tool_to_def: HashMap::new(),
+            generation: 0,
+            instance: rand::random(),
+        }
+    }
 }
 
 impl PluginStore {
@@ -31,6 +58,19 @@ impl PluginStore {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Returns a weak entity tag for a view of the catalog at its current
+    /// generation.
+    ///
+    /// `view` names what a response contains, e.g. the route and the caller
+    /// it was filtered for, so different views never share a tag.
+    pub fn etag(&self, view: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.instance.to_be_bytes());
+        hasher.update(self.generation.to_be_bytes());
+        hasher.update(view.as_bytes());
+        format!("W/\"{}\"", hex::encode(&hasher.finalize()[..16]))
+    }
 }
 
 /// A tool-provider trait for a plugin.
@@ -57,34 +97,103 @@ impl fmt::Debug for PluginStore {
     }
 }
 
-/// Registry providing CRUD and invocation. It owns the PluginStore behind a Tokio RwLock.
+/// The plugin store as immutable snapshots.
+///
+/// Readers take the current snapshot with [`Catalog::load`], a lock-free
+/// `Arc` clone, so tools/list and tool calls never wait for plugins being
+/// registered. Writers update a copy with [`Catalog::write`], one at a time,
+/// and the copy replaces the snapshot when they are done.
+pub struct Catalog {
+    current: ArcSwap<PluginStore>,
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl Catalog {
+    pub fn new(store: PluginStore) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(store),
+            writer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Returns the current snapshot.
+    pub fn load(&self) -> Arc<PluginStore> {
+        self.current.load_full()
+    }
+
+    /// Starts an update of the store, waiting for the one in progress.
+    /// Changes are published when the returned guard is dropped.
+    pub async fn write(&self) -> CatalogWriter<'_> {
+        let guard = self.writer.lock().await;
+        CatalogWriter {
+            catalog: self,
+            store: Some(PluginStore::clone(&self.load())),
+            changed: false,
+            _guard: guard,
+        }
+    }
+}
+
+impl fmt::Debug for Catalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.load().fmt(f)
+    }
+}
+
+/// Copy of the store being updated, published on drop if modified.
+pub struct CatalogWriter<'a> {
+    catalog: &'a Catalog,
+    store: Option<PluginStore>,
+    changed: bool,
+    _guard: tokio::sync::MutexGuard<'a, ()>,
+}
+
+impl Deref for CatalogWriter<'_> {
+    type Target = PluginStore;
+
+    fn deref(&self) -> &PluginStore {
+        self.store.as_ref().expect("store taken before drop")
+    }
+}
+
+impl DerefMut for CatalogWriter<'_> {
+    fn deref_mut(&mut self) -> &mut PluginStore {
+        self.changed = true;
+        self.store.as_mut().expect("store taken before drop")
+    }
+}
+
+impl Drop for CatalogWriter<'_> {
+    fn drop(&mut self) {
+        if let Some(store) = self.store.take().filter(|_| self.changed) {
+            self.catalog.current.store(Arc::new(store));
+        }
+    }
+}
+
+/// Registry providing CRUD and invocation over the [`Catalog`] snapshots.
 #[derive(Clone, Debug)]
 pub struct PluginRegistry {
-    /// The underlying plugin store protected by a read-write lock.
-    pub catalog: Arc<tokio::sync::RwLock<PluginStore>>,
+    /// The underlying plugin store, read as immutable snapshots.
+    pub catalog: Arc<Catalog>,
 }
 
 impl PluginRegistry {
     /// Checks if a handler is registered for the given tool name.
     pub fn has_handler(&self, name: &str) -> bool {
-        tokio::task::block_in_place(|| {
-            self.catalog
-                .blocking_read()
-                .tool_to_handler
-                .contains_key(name)
-        })
+        self.catalog.load().tool_to_handler.contains_key(name)
     }
 
     /// Create a registry backed by a fresh store (not installed globally).
     pub fn new_local() -> Self {
         Self {
-            catalog: Arc::new(tokio::sync::RwLock::new(PluginStore::new())),
+            catalog: Arc::new(Catalog::new(PluginStore::new())),
         }
     }
 
     // list all tools (across all plugins, or on a given plugin)
     pub async fn tools(&self, plugin_id: Option<&str>) -> anyhow::Result<Vec<Tool>> {
-        let guard = self.catalog.read().await;
+        let guard = self.catalog.load();
 
         let tools: Vec<Tool> = match plugin_id {
             None => guard.tool_to_def.values().cloned().collect(),
@@ -100,19 +209,36 @@ impl PluginRegistry {
     }
 
     /// Calls a registered plugin handler with the given input.
-    /// Clones the handler while holding the lock and invokes it outside to avoid blocking.
+    /// Clones the handler from the current snapshot, so reloads during the call do not affect it.
+    /// The call runs in a `tool.call` span carrying the plugin and tool names.
     pub async fn call(&self, id: &str, input: &Value) -> anyhow::Result<Value> {
-        let handler = {
-            let guard = self.catalog.read().await;
-            guard.tool_to_handler.get(id).cloned()
+        let (handler, plugin) = {
+            let guard = self.catalog.load();
+            (
+                guard.tool_to_handler.get(id).cloned(),
+                guard.tool_to_plugin.get(id).cloned(),
+            )
         };
 
+        let span = tracing::info_span!(
+            "tool.call",
+            plugin = plugin.as_deref().unwrap_or_default(),
+            tool = id
+        );
         match handler {
-            Some(h) => match h(input.clone()).await {
+            // Handlers create their spans up front, so build the future inside ours
+            Some(h) => match span.in_scope(|| h(input.clone())).instrument(span).await {
                 Ok(result) => Ok(result),
-                Err(err) => Err(anyhow::anyhow!("Plugin handler error: {:?}", err)),
+                // Keep the MCP error in the chain so callers can read its code
+                Err(err) => {
+                    let message = format!("Plugin handler error: {:?}", err);
+                    Err(anyhow::Error::new(err).context(message))
+                }
             },
-            None => Err(anyhow::anyhow!("No handler registered for plugin '{}'", id)),
+            None => Err(anyhow::Error::new(ErrorData::method_not_found::<
+                rmcp::model::CallToolRequestMethod,
+            >())
+            .context(format!("No handler registered for plugin '{}'", id))),
         }
     }
 }
@@ -0,0 +1,150 @@
+//! Scratch directories of WASM plugins.
+//!
+//! A plugin with `scratch` set gets a fresh directory below the system
+//! temporary directory, preopened through WASI at the configured path. WASI
+//! has no quotas, so while a call runs its size is checked every
+//! [`CHECK_INTERVAL`] and the call is cancelled once it exceeds the limit;
+//! the directory is checked once more and emptied when the call returns.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use extism::CancelHandle;
+
+use crate::config::plugins::ScratchConfig;
+use crate::utility::set_secure_dir_permissions;
+
+/// How often the directory is measured during a call.
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Scratch directory of one plugin instance, removed when dropped.
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl ScratchDir {
+    /// Creates an empty directory for `plugin_name`, readable only by this
+    /// user.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created.
+    pub fn create(plugin_name: &str, config: &ScratchConfig) -> anyhow::Result<Self> {
+        let name: String = plugin_name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!(
+            "ark-scratch-{}-{}",
+            name,
+            hex::encode(rand::random::<[u8; 8]>())
+        ));
+        std::fs::create_dir(&path)
+            .with_context(|| format!("Cannot create scratch directory {}", path.display()))?;
+        let dir = Self {
+            path,
+            max_bytes: config.max_bytes,
+        };
+        set_secure_dir_permissions(&dir.path)?;
+        Ok(dir)
+    }
+
+    /// Host path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Runs a plugin call, cancelling it through `cancel` if the directory
+    /// grows past its limit, and empties the directory afterwards.
+    ///
+    /// # Errors
+    /// Returns the call's error, or an error if the limit was exceeded.
+    pub fn run<T>(
+        &self,
+        cancel: CancelHandle,
+        call: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let exceeded = AtomicBool::new(false);
+        let result = std::thread::scope(|scope| {
+            // Dropped when the call returns, waking the watchdog at once
+            let (finished, wait) = mpsc::channel::<()>();
+            let exceeded = &exceeded;
+            scope.spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = wait.recv_timeout(CHECK_INTERVAL) {
+                    if size(&self.path) > self.max_bytes {
+                        exceeded.store(true, Ordering::Release);
+                        let _ = cancel.cancel();
+                        break;
+                    }
+                }
+            });
+            let result = call();
+            drop(finished);
+            result
+        });
+        let exceeded = exceeded.load(Ordering::Acquire) || size(&self.path) > self.max_bytes;
+        self.clear();
+        if exceeded {
+            bail!(
+                "WASM plugin wrote more than its scratch limit of {} bytes",
+                self.max_bytes
+            );
+        }
+        result
+    }
+
+    /// Removes everything in the directory, keeping the directory itself,
+    /// which the plugin holds open.
+    fn clear(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.path) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let removed = match entry.file_type() {
+                Ok(kind) if kind.is_dir() => std::fs::remove_dir_all(&path),
+                _ => std::fs::remove_file(&path),
+            };
+            if let Err(e) = removed {
+                tracing::warn!("Failed to clean up {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            tracing::warn!(
+                "Failed to remove scratch directory {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Bytes taken up by the files below `path`, not following symlinks.
+fn size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
@@ -3,21 +3,42 @@
 //! Loads a WASM plugin from a local file or over HTTP(S), initializes it via
 //! `WasmHandler`, and returns the plugin-described `ToolSet`.
 //!
-//! Local files must be provided as file:/// URLs.
+//! Local files must be provided as file:/// URLs. HTTP(S) requests carry the
+//! plugin's basic, bearer or custom header authentication, if any, and
+//! follow the timeouts, retries and proxy of `plugin_downloads`. A module
+//! whose digest differs from the plugin's `sha256` is rejected before it is
+//! instantiated.
+//!
+//! Downloaded modules served with an `ETag` or `Last-Modified` header are
+//! cached on disk by URL. Later downloads of the same URL, after a restart or
+//! a reload, are conditional requests, and a `304 Not Modified` answer is
+//! served from the cache.
+//!
+//! Modules larger than `plugin_downloads.max_plugin_bytes` are rejected
+//! before being read in full.
 
 use super::sanitized_url;
 use super::wasm::WasmHandler;
 use super::{PluginLoadResult, UriHandler};
-use crate::config::plugins::ArkPlugin;
+use crate::config::{
+    models::{OciAuthentication, PluginDownloadConfig},
+    plugins::ArkPlugin,
+};
+use crate::errors::SignatureError;
 use crate::server;
 use anyhow::{Context, anyhow, bail};
-use reqwest::Client;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, NoProxy, Proxy, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
-    sync::OnceLock,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use tokio::fs;
-use tracing::debug;
+use tracing::{debug, warn};
 
 /// Handles loading WASM plugins from file://, http://, and https:// URLs.
 pub struct UrlHandler;
@@ -39,6 +60,11 @@ impl UriHandler for UrlHandler {
                     .to_file_path()
                     .map_err(|_| anyhow!("{LOCAL_LOG_PREFIX} Unsupported file URL '{}'", url))?;
 
+                let size = fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+                let max = max_plugin_bytes();
+                if size > max {
+                    return Err(too_large(&sanitized_url(&url), size, max));
+                }
                 let bytes = fs::read(&path).await.with_context(|| {
                     format!(
                         "{LOCAL_LOG_PREFIX} Failed to read file '{}'",
@@ -46,7 +72,12 @@ impl UriHandler for UrlHandler {
                     )
                 })?;
                 let bytes_vec = bytes;
-                let wasm = WasmHandler::new(bytes_vec.clone(), &plugin_config.manifest)?;
+                verify_sha256(plugin_config, &bytes_vec)?;
+                let wasm = WasmHandler::new(
+                    bytes_vec.clone(),
+                    &plugin_config.name,
+                    &plugin_config.manifest,
+                )?;
 
                 debug!(
                     repo = LOCAL_LOG_PREFIX,
@@ -64,27 +95,29 @@ impl UriHandler for UrlHandler {
                         safe
                     );
                 }
+                if url.scheme() == "http"
+                    && !matches!(
+                        plugin_config.auth,
+                        None | Some(OciAuthentication::Anonymous)
+                    )
+                {
+                    warn!(
+                        repo = LOCAL_LOG_PREFIX,
+                        "Using insecure HTTP with credentials; this may leak secrets"
+                    );
+                }
                 debug!(
                     repo = LOCAL_LOG_PREFIX,
                     "Retrieving plugin from URL: {}", safe
                 );
 
-                let resp = http_client()
-                    .get(url.as_str())
-                    .send()
-                    .await
-                    .with_context(|| format!("{LOCAL_LOG_PREFIX} Failed to fetch '{}'", safe))?
-                    .error_for_status()
-                    .with_context(|| format!("{LOCAL_LOG_PREFIX} HTTP error for '{}'", safe))?;
-
-                let bytes = resp.bytes().await.with_context(|| {
-                    format!(
-                        "{LOCAL_LOG_PREFIX} Failed to read response body for '{}'",
-                        safe
-                    )
-                })?;
-                let bytes_vec = bytes.to_vec();
-                let wasm = WasmHandler::new(bytes_vec.clone(), &plugin_config.manifest)?;
+                let bytes_vec = download(&url, plugin_config, &safe).await?;
+                verify_sha256(plugin_config, &bytes_vec)?;
+                let wasm = WasmHandler::new(
+                    bytes_vec.clone(),
+                    &plugin_config.name,
+                    &plugin_config.manifest,
+                )?;
 
                 debug!(
                     repo = LOCAL_LOG_PREFIX,
@@ -106,6 +139,7 @@ impl UriHandler for UrlHandler {
         // Execute plugin
         let exec_start = Instant::now();
         let result = wasm.describe(plugin_config).await?;
+        wasm.warm_up().await;
         debug!(
             repo = LOCAL_LOG_PREFIX,
             "Plugin [{}] execution completed in {:.2?}",
@@ -128,15 +162,333 @@ impl UriHandler for UrlHandler {
     }
 }
 
-/// Returns a reused HTTP client with a 30-second timeout and user agent.
-/// The client is lazily initialized and reused across requests.
-fn http_client() -> &'static Client {
-    static CLIENT: OnceLock<Client> = OnceLock::new();
-    CLIENT.get_or_init(|| {
-        Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent(server::constants::REQUEST_USER_AGENT)
-            .build()
-            .expect("reqwest client")
+/// Checks `bytes` against the digest the plugin is pinned to, if any.
+fn verify_sha256(plugin: &ArkPlugin, bytes: &[u8]) -> anyhow::Result<()> {
+    let Some(expected) = plugin.sha256.as_deref() else {
+        return Ok(());
+    };
+    let actual = hex::encode(Sha256::digest(bytes));
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(SignatureError(format!(
+            "{LOCAL_LOG_PREFIX} Plugin '{}' does not match its pinned sha256: expected {}, got {}",
+            plugin.name,
+            expected.trim(),
+            actual
+        ))
+        .into());
+    }
+    debug!(
+        repo = LOCAL_LOG_PREFIX,
+        "Plugin [{}] matches its pinned sha256", plugin.name
+    );
+    Ok(())
+}
+
+/// Adds the plugin's authentication to `request`. Custom header values are
+/// marked sensitive so they are left out of debug output.
+fn with_auth(
+    request: RequestBuilder,
+    auth: Option<&OciAuthentication>,
+) -> anyhow::Result<RequestBuilder> {
+    Ok(match auth {
+        None | Some(OciAuthentication::Anonymous) => request,
+        Some(OciAuthentication::Basic {
+            username, password, ..
+        }) => request.basic_auth(username, Some(password)),
+        Some(OciAuthentication::Bearer { token, .. }) => request.bearer_auth(token),
+        Some(OciAuthentication::Headers { headers, .. }) => {
+            let mut map = HeaderMap::new();
+            for (name, value) in headers {
+                let name = HeaderName::try_from(name.as_str())
+                    .with_context(|| format!("{LOCAL_LOG_PREFIX} Invalid header name '{name}'"))?;
+                let mut value = HeaderValue::try_from(value.as_str()).with_context(|| {
+                    format!("{LOCAL_LOG_PREFIX} Invalid value for header '{name}'")
+                })?;
+                value.set_sensitive(true);
+                map.insert(name, value);
+            }
+            request.headers(map)
+        }
     })
 }
+
+/// Download settings, and the client built from them, of the plugins loaded
+/// from now on.
+static DOWNLOADS: Mutex<Option<Arc<Downloads>>> = Mutex::new(None);
+
+struct Downloads {
+    settings: PluginDownloadConfig,
+    /// The client, or why the settings don't make one (e.g. a bad proxy URL),
+    /// reported when a plugin is downloaded.
+    client: Result<Client, String>,
+    /// Directory of cached modules, unless caching is disabled.
+    cache_dir: Option<PathBuf>,
+}
+
+impl Downloads {
+    fn new(settings: PluginDownloadConfig) -> Self {
+        let client = build_client(&settings);
+        let cache_dir = if settings.cache {
+            settings.cache_dir.clone().or_else(|| {
+                crate::server::persist::resolve_db_path()
+                    .ok()
+                    .map(|db| db.parent().unwrap_or(Path::new(".")).join("plugin-cache"))
+            })
+        } else {
+            None
+        };
+        Self {
+            settings,
+            client,
+            cache_dir,
+        }
+    }
+}
+
+/// Sets the timeouts, retries and proxy of the plugins downloaded from now on.
+pub fn configure_downloads(settings: PluginDownloadConfig) {
+    let downloads = Downloads::new(settings);
+    if let Err(e) = &downloads.client {
+        warn!(repo = LOCAL_LOG_PREFIX, "{}", e);
+    }
+    *DOWNLOADS.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(downloads));
+}
+
+/// Largest plugin accepted, in bytes, from any source.
+pub fn max_plugin_bytes() -> u64 {
+    downloads().settings.max_plugin_bytes
+}
+
+/// Error for a plugin of `size` bytes, more than `max`.
+fn too_large(safe: &str, size: u64, max: u64) -> anyhow::Error {
+    anyhow!(
+        "{LOCAL_LOG_PREFIX} Plugin '{safe}' is {size} bytes, more than the maximum of {max} (plugin_downloads.max_plugin_bytes)"
+    )
+}
+
+fn downloads() -> Arc<Downloads> {
+    DOWNLOADS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(|| Arc::new(Downloads::new(PluginDownloadConfig::default())))
+        .clone()
+}
+
+fn build_client(settings: &PluginDownloadConfig) -> Result<Client, String> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_millis(settings.connect_timeout_ms))
+        .read_timeout(Duration::from_millis(settings.read_timeout_ms))
+        .user_agent(server::constants::REQUEST_USER_AGENT);
+    if let Some(timeout) = settings.timeout_ms {
+        builder = builder.timeout(Duration::from_millis(timeout));
+    }
+    if let Some(proxy) = settings.proxy.as_deref() {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| format!("Invalid plugin_downloads.proxy: {e}"))?
+            .no_proxy(settings.no_proxy.as_deref().and_then(NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Cannot create the plugin download client: {e}"))
+}
+
+/// Downloads the plugin at `url`, retrying connection errors, timeouts and
+/// 429 or 5xx responses with exponential backoff. A cached copy is used when
+/// the server reports it unchanged.
+async fn download(url: &url::Url, plugin: &ArkPlugin, safe: &str) -> anyhow::Result<Vec<u8>> {
+    let downloads = downloads();
+    let client = downloads
+        .client
+        .as_ref()
+        .map_err(|e| anyhow!("{LOCAL_LOG_PREFIX} {e}"))?;
+    let settings = &downloads.settings;
+    let cache_dir = downloads.cache_dir.as_deref();
+    let max = settings.max_plugin_bytes;
+    // A copy cached under a higher limit is downloaded again, and rejected.
+    let cached = match cache_dir {
+        Some(dir) => read_cache(dir, url).await,
+        None => None,
+    }
+    .filter(|(_, bytes)| bytes.len() as u64 <= max);
+    let mut attempt = 0;
+    loop {
+        let validators = cached.as_ref().map(|(entry, _)| entry);
+        match fetch(client, url, plugin, safe, validators, max).await {
+            Ok(Fetched::NotModified) => {
+                debug!(
+                    repo = LOCAL_LOG_PREFIX,
+                    "Plugin [{}] is unchanged; using the cached copy", safe
+                );
+                return Ok(cached.map(|(_, bytes)| bytes).unwrap_or_default());
+            }
+            Ok(Fetched::Modified(bytes, entry)) => {
+                if let Some(dir) = cache_dir
+                    && let Err(e) = write_cache(dir, url, &entry, &bytes).await
+                {
+                    warn!(
+                        repo = LOCAL_LOG_PREFIX,
+                        "Cannot cache plugin [{}] in {}: {:#}",
+                        safe,
+                        dir.display(),
+                        e
+                    );
+                }
+                return Ok(bytes);
+            }
+            Err((error, true)) if attempt < settings.retries => {
+                let delay = Duration::from_millis(
+                    settings
+                        .retry_backoff_ms
+                        .saturating_mul(1 << attempt.min(16)),
+                );
+                warn!(
+                    repo = LOCAL_LOG_PREFIX,
+                    "Attempt {} to download '{}' failed: {:#}; retrying in {:.2?}",
+                    attempt + 1,
+                    safe,
+                    error,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err((error, _)) => return Err(error),
+        }
+    }
+}
+
+/// Outcome of a download attempt.
+enum Fetched {
+    /// The server reports the cached copy is current.
+    NotModified,
+    /// The module, and the validators to revalidate it with later.
+    Modified(Vec<u8>, CacheEntry),
+}
+
+/// Makes one download attempt, conditional if `cached` has validators.
+/// Bodies over `max` bytes are rejected without being read in full. Errors
+/// come with whether retrying may help.
+async fn fetch(
+    client: &Client,
+    url: &url::Url,
+    plugin: &ArkPlugin,
+    safe: &str,
+    cached: Option<&CacheEntry>,
+    max: u64,
+) -> Result<Fetched, (anyhow::Error, bool)> {
+    let mut request = client
+        .get(url.as_str())
+        .headers(crate::telemetry::trace_headers());
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let request = with_auth(request, plugin.auth.as_ref()).map_err(|e| (e, false))?;
+    let mut resp = request.send().await.map_err(|e| {
+        let transient = is_transient(&e);
+        let error =
+            anyhow::Error::new(e).context(format!("{LOCAL_LOG_PREFIX} Failed to fetch '{safe}'"));
+        (error, transient)
+    })?;
+    let status = resp.status();
+    if status == StatusCode::NOT_MODIFIED && cached.is_some() {
+        return Ok(Fetched::NotModified);
+    }
+    if !status.is_success() {
+        let transient = status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS;
+        return Err((
+            anyhow!("{LOCAL_LOG_PREFIX} HTTP error for '{safe}': {status}"),
+            transient,
+        ));
+    }
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v: &HeaderValue| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let entry = CacheEntry {
+        url: safe.to_string(),
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    if let Some(size) = resp.content_length().filter(|size| *size > max) {
+        return Err((too_large(safe, size, max), false));
+    }
+    let mut bytes = Vec::with_capacity(resp.content_length().unwrap_or(0) as usize);
+    loop {
+        let chunk = resp.chunk().await.map_err(|e| {
+            let transient = is_transient(&e);
+            let error = anyhow::Error::new(e).context(format!(
+                "{LOCAL_LOG_PREFIX} Failed to read response body for '{safe}'"
+            ));
+            (error, transient)
+        })?;
+        let Some(chunk) = chunk else { break };
+        let size = (bytes.len() + chunk.len()) as u64;
+        if size > max {
+            return Err((too_large(safe, size, max), false));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Fetched::Modified(bytes, entry))
+}
+
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request() || error.is_body()
+}
+
+/// Validators of a cached module, stored next to it.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Where the module was downloaded from, without credentials, for people
+    /// looking at the cache directory.
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Paths of the module and of its validators cached for `url`.
+fn cache_paths(dir: &Path, url: &url::Url) -> (PathBuf, PathBuf) {
+    let key = hex::encode(Sha256::digest(url.as_str().as_bytes()));
+    (
+        dir.join(format!("{key}.wasm")),
+        dir.join(format!("{key}.json")),
+    )
+}
+
+async fn read_cache(dir: &Path, url: &url::Url) -> Option<(CacheEntry, Vec<u8>)> {
+    let (module, validators) = cache_paths(dir, url);
+    let entry = serde_json::from_slice(&fs::read(&validators).await.ok()?).ok()?;
+    let bytes = fs::read(&module).await.ok()?;
+    Some((entry, bytes))
+}
+
+/// Caches `bytes` if the server gave validators for them, and drops the
+/// previous copy otherwise. The validators are removed first and written
+/// last, so an interrupted write leaves no entry rather than a module with
+/// the validators of another version.
+async fn write_cache(
+    dir: &Path,
+    url: &url::Url,
+    entry: &CacheEntry,
+    bytes: &[u8],
+) -> anyhow::Result<()> {
+    let (module, validators) = cache_paths(dir, url);
+    match fs::remove_file(&validators).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    if entry.etag.is_none() && entry.last_modified.is_none() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir).await?;
+    fs::write(&module, bytes).await?;
+    fs::write(&validators, serde_json::to_vec(entry)?).await?;
+    Ok(())
+}
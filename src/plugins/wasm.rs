@@ -9,21 +9,54 @@
 /// - Merging plugin manifests with Extism configurations
 /// - Describing available tools via the plugin's `describe` export
 /// - Executing tool calls via the plugin's `call` export
+/// - Recording load time, memory high-water mark and fuel use per plugin
+/// - Giving plugins a size-capped scratch directory
+/// - Allocating instances on demand or from pools (see [`configure_engine`])
+/// - Warming plugins up through their optional `warmup` export
+use super::scratch::ScratchDir;
+use super::wasm_memory::{MemoryUsage, tracked_config};
+use crate::config::models::{WasmAllocator, WasmEngineConfig};
 use crate::config::plugins::PluginManifest;
+use crate::errors::ErrorCode;
 use crate::state::{DynExecFuture, ToolExecFn};
 use crate::{config::plugins::ArkPlugin, plugins::ToolSet};
 use anyhow::anyhow;
-use extism::{Manifest, Plugin, Wasm};
+use extism::{Manifest, Plugin, PluginBuilder, Wasm};
 use rmcp::ErrorData;
 use serde_json::{Value, json};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tracing::debug;
+use std::time::{Duration, Instant};
+use tracing::{Instrument, debug};
 
 /// Timeouts for WASM plugin operations (in seconds)
 const DESCRIBE_TIMEOUT_SECS: u64 = 30;
 const CALL_TIMEOUT_SECS: u64 = 120;
 
+/// Engine settings of the plugins loaded from now on.
+static ENGINE: Mutex<Option<WasmEngineConfig>> = Mutex::new(None);
+
+/// Sets the engine settings of the plugins loaded from now on; plugins
+/// already loaded keep theirs.
+pub fn configure_engine(config: WasmEngineConfig) {
+    *ENGINE.lock().unwrap_or_else(|e| e.into_inner()) = Some(config);
+}
+
+/// Wasmtime configuration drawing instances from a pool sized by `engine`.
+fn pooling_config(engine: &WasmEngineConfig) -> wasmtime::Config {
+    let mut pooling = wasmtime::PoolingAllocationConfig::default();
+    pooling
+        .total_core_instances(engine.max_instances)
+        .total_memories(engine.max_instances)
+        .total_tables(engine.max_instances);
+    let mut config = wasmtime::Config::new();
+    if let Some(reservation) = engine.memory_reservation {
+        pooling.max_memory_size(usize::try_from(reservation).unwrap_or(usize::MAX));
+        config.memory_reservation(reservation);
+    }
+    config.allocation_strategy(wasmtime::InstanceAllocationStrategy::Pooling(pooling));
+    config
+}
+
 use super::UriHandler;
 
 /// Handler for loading and executing WASM plugins using Extism.
@@ -35,6 +68,14 @@ use super::UriHandler;
 pub struct WasmHandler {
     /// Shared plugin instance protected by a mutex for thread safety.
     plugin: Arc<Mutex<Plugin>>,
+    /// Plugin name, used to label metrics.
+    plugin_name: String,
+    /// Size of the plugin's linear memories, when tracked.
+    memory: Option<Arc<MemoryUsage>>,
+    /// Scratch directory, when the manifest asks for one.
+    scratch: Option<Arc<ScratchDir>>,
+    /// Whether [`WasmHandler::warm_up`] calls the `warmup` export.
+    warmup: bool,
 }
 
 impl WasmHandler {
@@ -68,6 +109,7 @@ impl WasmHandler {
     ///
     /// # Arguments
     /// * `bytes` - The raw WASM module data
+    /// * `plugin_name` - Name of the plugin, used to label metrics
     /// * `plugin_cfg` - Optional plugin manifest for configuration
     ///
     /// # Returns
@@ -76,21 +118,92 @@ impl WasmHandler {
     /// # Details
     /// This method creates an Extism `Manifest` from the WASM data and merges
     /// any provided plugin configuration (memory limits, allowed hosts/paths, etc.).
-    /// The plugin is instantiated with the merged manifest.
-    pub fn new(bytes: Vec<u8>, plugin_cfg: &Option<PluginManifest>) -> anyhow::Result<Self> {
+    /// The plugin is instantiated with the merged manifest, with fuel metering
+    /// when `fuel_limit` is set and a scratch directory when `scratch` is.
+    pub fn new(
+        bytes: Vec<u8>,
+        plugin_name: &str,
+        plugin_cfg: &Option<PluginManifest>,
+    ) -> anyhow::Result<Self> {
         let wasm = Wasm::data(bytes);
         let manifest = Manifest::new([wasm]);
-        let merged = Self::merge_manifest(manifest, plugin_cfg);
+        let mut merged = Self::merge_manifest(manifest, plugin_cfg);
+
+        let scratch = match plugin_cfg.as_ref().and_then(|cfg| cfg.scratch.as_ref()) {
+            Some(config) => {
+                let dir = ScratchDir::create(plugin_name, config)?;
+                merged.allowed_paths.get_or_insert_default().insert(
+                    dir.path().to_string_lossy().into_owned(),
+                    config.path.clone(),
+                );
+                Some(Arc::new(dir))
+            }
+            None => None,
+        };
 
-        let plugin = Plugin::new(&merged, [], true)
+        let engine = ENGINE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_default();
+        // Only memories we provide ourselves can be tracked, so pooled ones
+        // never are
+        let (config, memory) = match engine.allocator {
+            WasmAllocator::OnDemand if engine.track_memory => {
+                let memory = Arc::new(MemoryUsage::default());
+                (tracked_config(Arc::clone(&memory)), Some(memory))
+            }
+            WasmAllocator::OnDemand => (wasmtime::Config::new(), None),
+            WasmAllocator::Pooling => (pooling_config(&engine), None),
+        };
+        let mut builder = PluginBuilder::new(&merged)
+            .with_wasi(true)
+            .with_wasmtime_config(config);
+        if let Some(fuel) = plugin_cfg.as_ref().and_then(|cfg| cfg.fuel_limit) {
+            builder = builder.with_fuel_limit(fuel);
+        }
+
+        let started = Instant::now();
+        let plugin = builder
+            .build()
             .map_err(|e| anyhow!("Failed to load WASM plugin: {e}"))?;
+        crate::metrics::record_wasm_instantiation(
+            plugin_name,
+            started.elapsed().as_secs_f64() * 1000.0,
+        );
 
         Ok(Self {
             plugin: Arc::new(Mutex::new(plugin)),
+            plugin_name: plugin_name.to_string(),
+            memory,
+            scratch,
+            warmup: plugin_cfg.as_ref().is_some_and(|cfg| cfg.warmup),
         })
     }
 }
 
+/// Calls the plugin's `function` export, within the limit of its scratch
+/// directory if it has one.
+fn call_plugin(
+    plugin: &mut Plugin,
+    scratch: Option<&ScratchDir>,
+    function: &str,
+    input: &str,
+) -> anyhow::Result<String> {
+    let call = |plugin: &mut Plugin| {
+        plugin
+            .call::<&str, String>(function, input)
+            .map_err(|e| anyhow!("WASM plugin {function}() failed: {e}"))
+    };
+    match scratch {
+        Some(dir) => {
+            let cancel = plugin.cancel_handle();
+            dir.run(cancel, || call(plugin))
+        }
+        None => call(plugin),
+    }
+}
+
 impl WasmHandler {
     /// Describes the tools available in the WASM plugin.
     ///
@@ -109,6 +222,7 @@ impl WasmHandler {
     /// logged for debugging purposes.
     pub async fn describe(&self, config: &ArkPlugin) -> anyhow::Result<ToolSet> {
         let plugin = Arc::clone(&self.plugin);
+        let scratch = self.scratch.clone();
         let uri_owned = config
             .url
             .as_ref()
@@ -119,9 +233,7 @@ impl WasmHandler {
             let mut plugin = plugin
                 .lock()
                 .map_err(|e| anyhow!("Failed to lock plugin: {e}"))?;
-            plugin
-                .call::<&str, String>("describe", "")
-                .map_err(|e| anyhow!("WASM plugin describe() failed: {e}"))
+            call_plugin(&mut plugin, scratch.as_deref(), "describe", "")
         });
 
         let joined = tokio::time::timeout(Duration::from_secs(DESCRIBE_TIMEOUT_SECS), handle)
@@ -153,6 +265,52 @@ impl WasmHandler {
         Ok(deserialized)
     }
 
+    /// Calls the plugin's `warmup` export when the manifest sets `warmup`.
+    ///
+    /// The plugin is already instantiated by `describe`; this lets it fill
+    /// its caches too before the first tool call. Plugins without the export
+    /// are skipped, and a failing or slow warm-up is logged rather than
+    /// failing the load, since the plugin still works, only colder.
+    pub async fn warm_up(&self) {
+        if !self.warmup {
+            return;
+        }
+        let plugin = Arc::clone(&self.plugin);
+        let scratch = self.scratch.clone();
+        let started = Instant::now();
+        let handle = tokio::task::spawn_blocking(move || -> anyhow::Result<bool> {
+            let mut plugin = plugin
+                .lock()
+                .map_err(|e| anyhow!("Failed to lock plugin: {e}"))?;
+            if !plugin.function_exists("warmup") {
+                return Ok(false);
+            }
+            call_plugin(&mut plugin, scratch.as_deref(), "warmup", "")?;
+            Ok(true)
+        });
+        match tokio::time::timeout(Duration::from_secs(DESCRIBE_TIMEOUT_SECS), handle).await {
+            Ok(Ok(Ok(true))) => debug!(
+                "WASM plugin [{}] warmed up in {:.2?}",
+                self.plugin_name,
+                started.elapsed()
+            ),
+            Ok(Ok(Ok(false))) => debug!("WASM plugin [{}] has no warmup export", self.plugin_name),
+            Ok(Ok(Err(e))) => {
+                tracing::warn!("WASM plugin [{}] not warmed up: {}", self.plugin_name, e)
+            }
+            Ok(Err(e)) => tracing::warn!(
+                "WASM plugin [{}] warmup() join error: {}",
+                self.plugin_name,
+                e
+            ),
+            Err(_) => tracing::warn!(
+                "WASM plugin [{}] warmup() timed out after {}s",
+                self.plugin_name,
+                DESCRIBE_TIMEOUT_SECS
+            ),
+        }
+    }
+
     /// Builds an executor function for a specific tool.
     ///
     /// Returns a closure that can be used to invoke the specified tool on the plugin.
@@ -172,42 +330,72 @@ impl WasmHandler {
     /// Execution is performed in a blocking task with a 120-second timeout to prevent
     /// hanging on long-running plugin operations. The `spawn_blocking` is used because
     /// WASM execution is CPU-bound and should not block the async runtime.
+    ///
+    /// Each call records the plugin's memory high-water mark and, when metered,
+    /// the fuel it consumed.
     pub fn build_executor(&self, tool_name: &str) -> ToolExecFn {
         let plugin = Arc::clone(&self.plugin);
+        let plugin_name = self.plugin_name.clone();
+        let memory = self.memory.clone();
+        let scratch = self.scratch.clone();
         let tool_name = tool_name.to_string();
         Arc::new(move |args: Value| -> DynExecFuture {
             let plugin = Arc::clone(&plugin);
+            let plugin_name = plugin_name.clone();
+            let memory = memory.clone();
+            let scratch = scratch.clone();
             let tool_name = tool_name.clone();
-            Box::pin(async move {
-                // Wrap args in "params" to match MCP CallToolRequest structure
-                let input = json!({"params": {"name": tool_name, "arguments": args}});
-                let input_str = serde_json::to_string(&input)
-                    .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
-                tracing::debug!("Sending to WASM plugin: {}", input_str);
-                let handle = tokio::task::spawn_blocking(move || -> anyhow::Result<String> {
-                    let mut plugin = plugin
-                        .lock()
-                        .map_err(|e| anyhow!("Failed to lock plugin: {e}"))?;
-                    plugin
-                        .call::<&str, String>("call", &input_str)
-                        .map_err(|e| anyhow!("WASM plugin call() failed: {e}"))
-                });
-                let joined = tokio::time::timeout(Duration::from_secs(CALL_TIMEOUT_SECS), handle)
-                    .await
-                    .map_err(|_| {
-                        ErrorData::new(
-                            rmcp::model::ErrorCode::INTERNAL_ERROR,
-                            format!("WASM plugin call() timed out after {}s", CALL_TIMEOUT_SECS),
-                            None,
-                        )
+            let tool_span_name = tool_name.clone();
+            Box::pin(
+                async move {
+                    // Wrap args in "params" to match MCP CallToolRequest structure
+                    let input = json!({"params": {"name": tool_name, "arguments": args}});
+                    let input_str = serde_json::to_string(&input)
+                        .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
+                    tracing::debug!("Sending to WASM plugin: {}", input_str);
+                    let span = tracing::Span::current();
+                    let handle = tokio::task::spawn_blocking(move || -> anyhow::Result<String> {
+                        let _entered = span.enter();
+                        let mut plugin = plugin
+                            .lock()
+                            .map_err(|e| anyhow!("Failed to lock plugin: {e}"))?;
+                        // Calls are serialized by the lock, so the peak belongs to this one
+                        if let Some(memory) = &memory {
+                            memory.reset_peak();
+                        }
+                        let result =
+                            call_plugin(&mut plugin, scratch.as_deref(), "call", &input_str);
+                        crate::metrics::record_wasm_call_resources(
+                            &plugin_name,
+                            memory.as_ref().map(|m| m.peak()),
+                            plugin.fuel_consumed(),
+                        );
+                        result
+                    });
+                    let joined =
+                        tokio::time::timeout(Duration::from_secs(CALL_TIMEOUT_SECS), handle)
+                            .await
+                            .map_err(|_| {
+                                ErrorData::new(
+                                    rmcp::model::ErrorCode::INTERNAL_ERROR,
+                                    format!(
+                                        "WASM plugin call() timed out after {}s",
+                                        CALL_TIMEOUT_SECS
+                                    ),
+                                    ErrorCode::ToolTimeout.data(),
+                                )
+                            })?;
+                    let join_ok =
+                        joined.map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                    let json_text = join_ok.map_err(|e| {
+                        ErrorData::internal_error(e.to_string(), ErrorCode::WasmTrap.data())
                     })?;
-                let join_ok = joined.map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
-                let json_text =
-                    join_ok.map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
-                let value: Value = serde_json::from_str(&json_text)
-                    .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
-                Ok(value)
-            })
+                    let value: Value = serde_json::from_str(&json_text)
+                        .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
+                    Ok(value)
+                }
+                .instrument(tracing::info_span!("wasm.execute", tool = %tool_span_name)),
+            )
         })
     }
 }
@@ -226,9 +414,10 @@ impl UriHandler for WasmHandler {
     ///
     /// # Details
     /// This method is called during plugin loading to discover and prepare
-    /// all tools provided by the WASM plugin.
+    /// all tools provided by the WASM plugin, warming it up if configured.
     async fn get(&self, config: &ArkPlugin) -> anyhow::Result<super::PluginLoadResult> {
         let toolset = self.describe(config).await?;
+        self.warm_up().await;
         let mut execs = Vec::new();
         for t in &toolset.tools {
             let name = t.name.as_ref();
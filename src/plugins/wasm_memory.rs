@@ -0,0 +1,167 @@
+//! Linear memories that report their size, for per-plugin memory metrics.
+//!
+//! Extism does not expose the wasmtime store, so the only way to observe how
+//! much memory a plugin uses is to provide its memories ourselves. They are
+//! plain heap allocations without guard pages; wasmtime bounds-checks every
+//! access instead. Only used when `wasm_engine.track_memory` opts in.
+
+use std::alloc::{Layout, alloc_zeroed, dealloc};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::bail;
+use wasmtime::{LinearMemory, MemoryCreator, MemoryType};
+
+/// WASM page size; allocations are aligned to it.
+const WASM_PAGE_SIZE: usize = 64 * 1024;
+
+/// Bytes held by one plugin's memories, and the peak since the last reset.
+#[derive(Debug, Default)]
+pub struct MemoryUsage {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl MemoryUsage {
+    fn grow(&self, bytes: usize) {
+        let current = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn release(&self, bytes: usize) {
+        self.current.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    /// Starts a new measurement window at the current size.
+    pub fn reset_peak(&self) {
+        self.peak
+            .store(self.current.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    /// Largest total size, in bytes, since the last [`reset_peak`](Self::reset_peak).
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+/// Builds the wasmtime configuration for a plugin whose memories are tracked
+/// in `usage`.
+pub fn tracked_config(usage: Arc<MemoryUsage>) -> wasmtime::Config {
+    let mut config = wasmtime::Config::new();
+    config
+        .with_host_memory(Arc::new(TrackingMemoryCreator(usage)))
+        // Heap allocations: nothing reserved up front, no guard pages,
+        // growth may move the memory and initial data is copied in rather
+        // than mapped copy-on-write
+        .memory_reservation(0)
+        .memory_reservation_for_growth(0)
+        .memory_guard_size(0)
+        .memory_may_move(true)
+        .memory_init_cow(false);
+    config
+}
+
+/// Creates [`HeapMemory`] instances charged to one plugin.
+struct TrackingMemoryCreator(Arc<MemoryUsage>);
+
+unsafe impl MemoryCreator for TrackingMemoryCreator {
+    fn new_memory(
+        &self,
+        _ty: MemoryType,
+        minimum: usize,
+        maximum: Option<usize>,
+        _reserved_size_in_bytes: Option<usize>,
+        _guard_size_in_bytes: usize,
+    ) -> Result<Box<dyn LinearMemory>, String> {
+        let mut memory = HeapMemory {
+            base: ptr::null_mut(),
+            size: 0,
+            capacity: 0,
+            maximum,
+            usage: Arc::clone(&self.0),
+        };
+        memory.grow_to(minimum).map_err(|e| e.to_string())?;
+        Ok(Box::new(memory))
+    }
+}
+
+/// Zero-initialized, page-aligned linear memory.
+struct HeapMemory {
+    base: *mut u8,
+    size: usize,
+    capacity: usize,
+    maximum: Option<usize>,
+    usage: Arc<MemoryUsage>,
+}
+
+// The allocation is owned by the memory and only accessed through wasmtime
+unsafe impl Send for HeapMemory {}
+unsafe impl Sync for HeapMemory {}
+
+impl HeapMemory {
+    fn layout(capacity: usize) -> anyhow::Result<Layout> {
+        Ok(Layout::from_size_align(capacity, WASM_PAGE_SIZE)?)
+    }
+}
+
+unsafe impl LinearMemory for HeapMemory {
+    fn byte_size(&self) -> usize {
+        self.size
+    }
+
+    fn byte_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn grow_to(&mut self, new_size: usize) -> anyhow::Result<()> {
+        if self.maximum.is_some_and(|maximum| new_size > maximum) {
+            bail!("WASM memory cannot grow beyond {:?} bytes", self.maximum);
+        }
+        if self.base.is_null() || new_size > self.capacity {
+            // Double the capacity so page-by-page growth doesn't copy every time
+            let mut capacity = new_size
+                .max(self.capacity.saturating_mul(2))
+                .max(WASM_PAGE_SIZE)
+                .next_multiple_of(WASM_PAGE_SIZE);
+            if let Some(maximum) = self.maximum {
+                capacity = capacity.min(maximum.max(WASM_PAGE_SIZE));
+            }
+            let layout = Self::layout(capacity)?;
+            // SAFETY: the layout has a non-zero size
+            let base = unsafe { alloc_zeroed(layout) };
+            if base.is_null() {
+                bail!("Failed to allocate {} bytes of WASM memory", capacity);
+            }
+            if !self.base.is_null() {
+                // SAFETY: both allocations hold at least `size` bytes and are distinct
+                unsafe {
+                    ptr::copy_nonoverlapping(self.base, base, self.size);
+                    dealloc(self.base, Self::layout(self.capacity)?);
+                }
+            }
+            self.base = base;
+            self.capacity = capacity;
+        }
+        // Bytes past `size` are still zero: memories never shrink
+        self.usage.grow(new_size - self.size);
+        self.size = new_size;
+        Ok(())
+    }
+
+    fn as_ptr(&self) -> *mut u8 {
+        self.base
+    }
+}
+
+impl Drop for HeapMemory {
+    fn drop(&mut self) {
+        if let Ok(layout) = Self::layout(self.capacity)
+            && !self.base.is_null()
+        {
+            // SAFETY: `base` was allocated with this layout
+            unsafe { dealloc(self.base, layout) };
+        }
+        self.usage.release(self.size);
+    }
+}
@@ -0,0 +1,651 @@
+//! Certificates from an ACME CA such as Let's Encrypt (RFC 8555).
+//!
+//! With `tls.acme` configured both listeners serve a certificate for the
+//! configured domains. It is ordered when none is cached, or when the cached
+//! one covers other domains or expires within `renew_before_days`, and checked
+//! again twice a day. Until the first certificate is issued TLS handshakes
+//! fail.
+//!
+//! Control of the domains is proven with TLS-ALPN-01 (RFC 8737), answered by
+//! the listeners themselves, so one of them must be reachable on port 443; or
+//! with HTTP-01, answered by a plain HTTP listener on `http_bind_address`,
+//! which the CA reaches on port 80.
+//!
+//! The account key, the certificate and its key are kept in the cache
+//! directory, readable by the server's user only.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use axum::{
+    Router,
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    routing::get,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey};
+use rustls::server::{ClientHello, ResolvesServerCert, WantsServerCert};
+use rustls::sign::CertifiedKey;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::models::{AcmeChallenge, AcmeConfig};
+use crate::state::{ArkState, TlsStatus};
+use crate::utility::{set_secure_dir_permissions, set_secure_file_permissions};
+
+/// ALPN protocol of TLS-ALPN-01 validation handshakes.
+const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+/// Interval between certificate checks.
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Interval between attempts after a failed order.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Interval between polls of a pending authorization or order.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls before a pending authorization or order is given up on.
+const POLL_ATTEMPTS: u32 = 60;
+
+const ACCOUNT_KEY_FILE: &str = "account.key";
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+
+/// Obtains and renews the listeners' certificate.
+#[derive(Debug)]
+pub struct AcmeManager {
+    config: AcmeConfig,
+    cache_dir: PathBuf,
+    client: reqwest::Client,
+    resolver: Arc<AcmeResolver>,
+    /// HTTP-01 key authorizations, by token.
+    http_challenges: RwLock<HashMap<String, String>>,
+}
+
+/// Certificate resolver of the listeners: the issued certificate, or the
+/// challenge certificate for TLS-ALPN-01 validation handshakes.
+#[derive(Debug, Default)]
+pub struct AcmeResolver {
+    certificate: RwLock<Option<Arc<CertifiedKey>>>,
+    /// TLS-ALPN-01 challenge certificates, by domain.
+    challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl ResolvesServerCert for AcmeResolver {
+    fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let validation = hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|p| p == ACME_TLS_ALPN));
+        if validation {
+            let domain = hello.server_name()?;
+            return read(&self.challenges).get(domain).cloned();
+        }
+        read(&self.certificate).clone()
+    }
+}
+
+impl AcmeManager {
+    /// Creates the manager and installs the cached certificate, if any.
+    ///
+    /// # Errors
+    /// Returns an error if no domains are configured or the cache directory
+    /// cannot be created.
+    pub fn new(config: &AcmeConfig) -> Result<Self> {
+        if config.domains.is_empty() {
+            bail!("tls.acme.domains is empty");
+        }
+        let cache_dir = match &config.cache_dir {
+            Some(dir) => dir.clone(),
+            None => crate::server::persist::resolve_db_path()?
+                .parent()
+                .unwrap_or(Path::new("."))
+                .join("acme"),
+        };
+        std::fs::create_dir_all(&cache_dir)
+            .with_context(|| format!("Cannot create {}", cache_dir.display()))?;
+        set_secure_dir_permissions(&cache_dir)?;
+
+        let manager = Self {
+            config: config.clone(),
+            cache_dir,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()?,
+            resolver: Arc::new(AcmeResolver::default()),
+            http_challenges: RwLock::new(HashMap::new()),
+        };
+        match manager.cached() {
+            Ok(Some((key, _))) => *write(&manager.resolver.certificate) = Some(key),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Ignoring the cached ACME certificate: {:#}", e),
+        }
+        Ok(manager)
+    }
+
+    /// TLS acceptor serving the managed certificate, finishing `builder`.
+    pub fn tls_acceptor(
+        &self,
+        builder: rustls::ConfigBuilder<rustls::ServerConfig, WantsServerCert>,
+    ) -> Arc<TlsAcceptor> {
+        let mut config = builder.with_cert_resolver(self.resolver.clone());
+        config.alpn_protocols = crate::server::service::ALPN_PROTOCOLS
+            .iter()
+            .chain([&ACME_TLS_ALPN])
+            .map(|p| p.to_vec())
+            .collect();
+        Arc::new(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    /// Directory holding the account key and the certificate.
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// PEM file of the issued certificate chain.
+    pub fn certificate_path(&self) -> PathBuf {
+        self.cache_dir.join(CERT_FILE)
+    }
+
+    /// Whether a certificate is installed.
+    pub fn has_certificate(&self) -> bool {
+        read(&self.resolver.certificate).is_some()
+    }
+
+    /// Starts the HTTP-01 listener when needed, and keeps the certificate
+    /// current in the background, reporting it in the TLS status.
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP-01 listener cannot be bound.
+    pub async fn spawn(self: Arc<Self>, state: Arc<ArkState>) -> Result<()> {
+        if self.config.challenge == AcmeChallenge::Http01 {
+            let listener = tokio::net::TcpListener::bind(&self.config.http_bind_address)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Cannot bind the HTTP-01 listener to {}",
+                        self.config.http_bind_address
+                    )
+                })?;
+            let router = self.clone().http_challenge_router();
+            tokio::spawn(async move {
+                if let Err(e) = axum::serve(listener, router).await {
+                    tracing::error!("ACME HTTP-01 listener error: {:?}", e);
+                }
+            });
+        }
+
+        tokio::spawn(async move {
+            loop {
+                let wait = match self.ensure_certificate().await {
+                    Ok(()) => {
+                        state.set_tls_status(TlsStatus::Loaded);
+                        CHECK_INTERVAL
+                    }
+                    Err(e) => {
+                        tracing::warn!("ACME certificate not obtained: {:#}", e);
+                        if !self.has_certificate() {
+                            state.set_tls_status(TlsStatus::Failed(format!("ACME: {:#}", e)));
+                        }
+                        RETRY_INTERVAL
+                    }
+                };
+                tokio::time::sleep(wait).await;
+            }
+        });
+        Ok(())
+    }
+
+    /// Answers HTTP-01 challenges at `/.well-known/acme-challenge/{token}`.
+    pub fn http_challenge_router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route(
+                "/.well-known/acme-challenge/{token}",
+                get(
+                    |State(manager): State<Arc<Self>>, UrlPath(token): UrlPath<String>| async move {
+                        read(&manager.http_challenges)
+                            .get(&token)
+                            .cloned()
+                            .ok_or(StatusCode::NOT_FOUND)
+                    },
+                ),
+            )
+            .with_state(self)
+    }
+
+    /// Orders a certificate unless the cached one is current.
+    ///
+    /// # Errors
+    /// Returns an error if the order fails or the certificate cannot be
+    /// stored.
+    pub async fn ensure_certificate(&self) -> Result<()> {
+        if let Some((key, expires)) = self.cached()? {
+            let renew_at = expires - chrono::Duration::days(self.config.renew_before_days.into());
+            *write(&self.resolver.certificate) = Some(key);
+            if chrono::Utc::now() < renew_at {
+                return Ok(());
+            }
+            tracing::info!("ACME certificate expires on {}; renewing", expires);
+        }
+
+        let (cert_pem, key_pem) = self.order().await?;
+        let key = certified_key(cert_pem.as_bytes(), key_pem.as_bytes())?;
+        let key_path = self.cache_dir.join(KEY_FILE);
+        std::fs::write(&key_path, &key_pem)
+            .with_context(|| format!("Cannot write {}", key_path.display()))?;
+        set_secure_file_permissions(&key_path)?;
+        std::fs::write(self.cache_dir.join(CERT_FILE), &cert_pem)
+            .with_context(|| format!("Cannot write {}", CERT_FILE))?;
+        *write(&self.resolver.certificate) = Some(Arc::new(key));
+        tracing::info!(
+            "ACME certificate issued for {}",
+            self.config.domains.join(", ")
+        );
+        Ok(())
+    }
+
+    /// Cached certificate and its expiry, if it covers the configured domains.
+    fn cached(&self) -> Result<Option<(Arc<CertifiedKey>, chrono::DateTime<chrono::Utc>)>> {
+        let (Ok(cert_pem), Ok(key_pem)) = (
+            std::fs::read(self.cache_dir.join(CERT_FILE)),
+            std::fs::read(self.cache_dir.join(KEY_FILE)),
+        ) else {
+            return Ok(None);
+        };
+        let pem = pem::parse(&cert_pem).context("Invalid cached certificate")?;
+        let (_, cert) = x509_parser::parse_x509_certificate(&pem.contents)
+            .map_err(|e| anyhow!("Invalid cached certificate: {}", e))?;
+        let names: Vec<String> = cert
+            .subject_alternative_name()
+            .ok()
+            .flatten()
+            .map(|san| {
+                san.value
+                    .general_names
+                    .iter()
+                    .filter_map(|name| match name {
+                        x509_parser::extensions::GeneralName::DNSName(dns) => Some(dns.to_string()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !self.config.domains.iter().all(|d| names.contains(d)) {
+            tracing::info!(
+                "Cached ACME certificate is for {:?}; ordering a new one",
+                names
+            );
+            return Ok(None);
+        }
+        let expires = chrono::DateTime::from_timestamp(cert.validity().not_after.timestamp(), 0)
+            .context("Invalid certificate expiry")?;
+        Ok(Some((
+            Arc::new(certified_key(&cert_pem, &key_pem)?),
+            expires,
+        )))
+    }
+
+    /// Runs an order to completion, returning the certificate chain and its
+    /// key, in PEM.
+    async fn order(&self) -> Result<(String, String)> {
+        let mut account = Account::login(self).await?;
+        let (order_url, order) = account
+            .post_json::<Order>(
+                &account.directory.new_order.clone(),
+                Some(json!({
+                    "identifiers": self.config.domains.iter()
+                        .map(|d| json!({ "type": "dns", "value": d }))
+                        .collect::<Vec<_>>(),
+                })),
+            )
+            .await?;
+        let order_url = order_url.context("Order without a location")?;
+
+        for url in &order.authorizations {
+            self.authorize(&mut account, url).await?;
+        }
+
+        let key = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+        let csr =
+            rcgen::CertificateParams::new(self.config.domains.clone())?.serialize_request(&key)?;
+        account
+            .post_json::<Order>(
+                &order.finalize,
+                Some(json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) })),
+            )
+            .await?;
+        let order: Order = account
+            .poll(&order_url, |o: &Order| {
+                o.status != "processing" && o.status != "ready"
+            })
+            .await?;
+        let certificate = match (order.status.as_str(), order.certificate) {
+            ("valid", Some(url)) => url,
+            _ => bail!("Order {}: {}", order.status, problem(&order.error)),
+        };
+        let chain = account.post(&certificate, None).await?.text().await?;
+        Ok((chain, key.serialize_pem()))
+    }
+
+    /// Answers the challenge of one authorization and waits for the CA to
+    /// validate it.
+    async fn authorize(&self, account: &mut Account<'_>, url: &str) -> Result<()> {
+        let (_, authorization) = account.post_json::<Authorization>(url, None).await?;
+        if authorization.status == "valid" {
+            return Ok(());
+        }
+        let domain = authorization.identifier.value.clone();
+        let kind = match self.config.challenge {
+            AcmeChallenge::TlsAlpn01 => "tls-alpn-01",
+            AcmeChallenge::Http01 => "http-01",
+        };
+        let challenge = authorization
+            .challenges
+            .iter()
+            .find(|c| c.kind == kind)
+            .with_context(|| format!("The CA offers no {} challenge for {}", kind, domain))?;
+        let key_authorization = format!("{}.{}", challenge.token, account.thumbprint);
+        match self.config.challenge {
+            AcmeChallenge::TlsAlpn01 => {
+                let digest = Sha256::digest(key_authorization.as_bytes());
+                let key = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+                let mut params = rcgen::CertificateParams::new(vec![domain.clone()])?;
+                params
+                    .custom_extensions
+                    .push(rcgen::CustomExtension::new_acme_identifier(&digest));
+                let cert = params.self_signed(&key)?;
+                let certified =
+                    certified_key(cert.pem().as_bytes(), key.serialize_pem().as_bytes())?;
+                write(&self.resolver.challenges).insert(domain.clone(), Arc::new(certified));
+            }
+            AcmeChallenge::Http01 => {
+                write(&self.http_challenges)
+                    .insert(challenge.token.clone(), key_authorization.clone());
+            }
+        }
+
+        let result = async {
+            account.post(&challenge.url, Some(json!({}))).await?;
+            account
+                .poll(url, |a: &Authorization| a.status != "pending")
+                .await
+        }
+        .await;
+        write(&self.resolver.challenges).remove(&domain);
+        write(&self.http_challenges).remove(&challenge.token);
+
+        let authorization = result?;
+        if authorization.status != "valid" {
+            let error = authorization
+                .challenges
+                .iter()
+                .find(|c| c.kind == kind)
+                .and_then(|c| c.error.clone());
+            bail!(
+                "{} challenge for {} is {}: {}",
+                kind,
+                domain,
+                authorization.status,
+                problem(&error)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// ACME account session: signs requests and tracks the replay nonce.
+struct Account<'a> {
+    client: &'a reqwest::Client,
+    directory: Directory,
+    key: EncodingKey,
+    jwk: Value,
+    /// Base64url SHA-256 thumbprint of the account key (RFC 7638).
+    thumbprint: String,
+    /// Account URL, once registered.
+    kid: Option<String>,
+    nonce: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    status: String,
+    #[serde(default)]
+    authorizations: Vec<String>,
+    #[serde(default)]
+    finalize: String,
+    certificate: Option<String>,
+    error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+    status: String,
+    identifier: Identifier,
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize)]
+struct Identifier {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    token: String,
+    error: Option<Value>,
+}
+
+impl<'a> Account<'a> {
+    /// Loads or creates the account key and registers it with the CA (an
+    /// existing account is returned for a known key).
+    async fn login(manager: &'a AcmeManager) -> Result<Account<'a>> {
+        let directory: Directory = manager
+            .client
+            .get(&manager.config.directory)
+            .send()
+            .await?
+            .error_for_status()
+            .context("ACME directory")?
+            .json()
+            .await
+            .context("ACME directory")?;
+
+        let key_path = manager.cache_dir.join(ACCOUNT_KEY_FILE);
+        let key_pem = match std::fs::read_to_string(&key_path) {
+            Ok(pem) => pem,
+            Err(_) => {
+                let pem =
+                    rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)?.serialize_pem();
+                std::fs::write(&key_path, &pem)
+                    .with_context(|| format!("Cannot write {}", key_path.display()))?;
+                set_secure_file_permissions(&key_path)?;
+                pem
+            }
+        };
+        let pair = rcgen::KeyPair::from_pem(&key_pem).context("Invalid ACME account key")?;
+        let raw = pair.public_key_raw();
+        // Uncompressed point: 0x04 || x || y
+        let (x, y) = raw
+            .get(1..)
+            .filter(|xy| raw[0] == 4 && xy.len() == 64)
+            .map(|xy| xy.split_at(32))
+            .context("ACME account key is not a P-256 key")?;
+        let (x, y) = (URL_SAFE_NO_PAD.encode(x), URL_SAFE_NO_PAD.encode(y));
+        // Members in lexicographic order, without whitespace (RFC 7638)
+        let thumbprint = URL_SAFE_NO_PAD.encode(Sha256::digest(format!(
+            r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+            x, y
+        )));
+
+        let mut account = Account {
+            client: &manager.client,
+            directory,
+            key: EncodingKey::from_ec_pem(key_pem.as_bytes())?,
+            jwk: json!({ "crv": "P-256", "kty": "EC", "x": x, "y": y }),
+            thumbprint,
+            kid: None,
+            nonce: None,
+        };
+        let mut registration = json!({ "termsOfServiceAgreed": true });
+        if let Some(email) = &manager.config.email {
+            registration["contact"] = json!([format!("mailto:{}", email)]);
+        }
+        let new_account = account.directory.new_account.clone();
+        let (kid, _) = account
+            .post_json::<Value>(&new_account, Some(registration))
+            .await?;
+        account.kid = Some(kid.context("Account without a location")?);
+        Ok(account)
+    }
+
+    /// Signed POST, or POST-as-GET without a payload. A rejected nonce is
+    /// retried once with a fresh one.
+    async fn post(&mut self, url: &str, payload: Option<Value>) -> Result<reqwest::Response> {
+        let mut retried = false;
+        loop {
+            let nonce = match self.nonce.take() {
+                Some(nonce) => nonce,
+                None => self.fresh_nonce().await?,
+            };
+            let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+            match &self.kid {
+                Some(kid) => protected["kid"] = json!(kid),
+                None => protected["jwk"] = self.jwk.clone(),
+            }
+            let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+            let payload = payload
+                .as_ref()
+                .map(|p| URL_SAFE_NO_PAD.encode(p.to_string()))
+                .unwrap_or_default();
+            let signature = jsonwebtoken::crypto::sign(
+                format!("{}.{}", protected, payload).as_bytes(),
+                &self.key,
+                Algorithm::ES256,
+            )?;
+            let response = self
+                .client
+                .post(url)
+                .header("content-type", "application/jose+json")
+                .body(
+                    json!({ "protected": protected, "payload": payload, "signature": signature })
+                        .to_string(),
+                )
+                .send()
+                .await?;
+            self.nonce = response
+                .headers()
+                .get("replay-nonce")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            if response.status().is_success() {
+                return Ok(response);
+            }
+            let status = response.status();
+            let error: Value = response.json().await.unwrap_or_default();
+            if !retried && error["type"] == "urn:ietf:params:acme:error:badNonce" {
+                retried = true;
+                continue;
+            }
+            bail!("{} returned {}: {}", url, status, problem(&Some(error)));
+        }
+    }
+
+    /// Signed request returning the `Location` header and the JSON body.
+    async fn post_json<T: serde::de::DeserializeOwned>(
+        &mut self,
+        url: &str,
+        payload: Option<Value>,
+    ) -> Result<(Option<String>, T)> {
+        let response = self.post(url, payload).await?;
+        let location = response
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = response
+            .json()
+            .await
+            .with_context(|| format!("Unexpected response from {}", url))?;
+        Ok((location, body))
+    }
+
+    /// Fetches `url` until `done` holds.
+    async fn poll<T: serde::de::DeserializeOwned>(
+        &mut self,
+        url: &str,
+        done: impl Fn(&T) -> bool,
+    ) -> Result<T> {
+        for _ in 0..POLL_ATTEMPTS {
+            let (_, value) = self.post_json::<T>(url, None).await?;
+            if done(&value) {
+                return Ok(value);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        bail!("{} did not complete in time", url)
+    }
+
+    async fn fresh_nonce(&self) -> Result<String> {
+        let response = self
+            .client
+            .head(&self.directory.new_nonce)
+            .send()
+            .await?
+            .error_for_status()?;
+        response
+            .headers()
+            .get("replay-nonce")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .context("No replay nonce from the CA")
+    }
+}
+
+/// Describes an ACME problem document (RFC 7807).
+fn problem(error: &Option<Value>) -> String {
+    match error {
+        Some(error) => error["detail"]
+            .as_str()
+            .or_else(|| error["type"].as_str())
+            .unwrap_or("unknown error")
+            .to_string(),
+        None => "no details".to_string(),
+    }
+}
+
+fn certified_key(cert_pem: &[u8], key_pem: &[u8]) -> Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut &cert_pem[..])
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to parse certificates")?;
+    let key = rustls_pemfile::private_key(&mut &key_pem[..])
+        .context("Failed to parse private key")?
+        .context("No private key found")?;
+    let key = rustls::crypto::aws_lc_rs::sign::any_supported_type(&key)
+        .context("Unsupported private key")?;
+    Ok(CertifiedKey::new(certs, key))
+}
+
+fn read<T>(lock: &RwLock<T>) -> std::sync::RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write<T>(lock: &RwLock<T>) -> std::sync::RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,218 @@
+//! Audit trail of authentication events.
+//!
+//! Sign-ins, sign-outs, token issuance and failed attempts are stored in the
+//! `auth_events` table with the provider, subject, client IP, user agent and
+//! request id, and listed by administrators at
+//! `GET /api/admin/audit/auth-events`. Each event is also logged under the
+//! `audit` target and published to live event subscribers. Recording is best
+//! effort: a storage failure is logged and never fails the request being
+//! audited. Events older than `auth.audit.retention_days` are deleted by the
+//! periodic auth cleanup, and rejected API keys are coalesced per client by
+//! [`FailureThrottle`].
+
+use axum::http::{HeaderMap, header};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::server::auth::{Principal, ProviderKind};
+use crate::server::events::ServerEvent;
+use crate::server::persist::AuthEventRecord;
+use crate::state::ArkState;
+
+/// Longest subject, user agent or detail stored; all may be client-supplied.
+const MAX_FIELD_LEN: usize = 256;
+
+/// Period over which repeated failures from one client are coalesced into a
+/// single event.
+pub const FAILURE_AUDIT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Kinds of authentication events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthEventKind {
+    /// A user signed in and a session was created.
+    Login,
+    /// A sign-in attempt or presented credential was rejected.
+    LoginFailed,
+    /// A user signed out.
+    Logout,
+    /// An access token or API key was issued.
+    TokenIssued,
+    /// The token endpoint refused a grant.
+    TokenFailed,
+}
+
+impl AuthEventKind {
+    /// Name stored in the `event` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthEventKind::Login => "login",
+            AuthEventKind::LoginFailed => "login_failed",
+            AuthEventKind::Logout => "logout",
+            AuthEventKind::TokenIssued => "token_issued",
+            AuthEventKind::TokenFailed => "token_failed",
+        }
+    }
+}
+
+/// Where a request came from, as recorded with its events.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    /// Client IP, resolved like the login limiter does.
+    pub ip: Option<IpAddr>,
+    /// `User-Agent` header, truncated.
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    /// Builds the client info from the request headers and a resolved IP.
+    pub fn new(ip: Option<IpAddr>, headers: &HeaderMap) -> Self {
+        Self {
+            ip,
+            user_agent: headers
+                .get(header::USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(clip),
+        }
+    }
+}
+
+/// An authentication event about to be recorded.
+#[derive(Debug, Clone)]
+pub struct AuthEvent {
+    kind: AuthEventKind,
+    provider: Option<String>,
+    subject: Option<String>,
+    client: ClientInfo,
+    detail: Option<String>,
+}
+
+impl AuthEvent {
+    pub fn new(kind: AuthEventKind, client: &ClientInfo) -> Self {
+        Self {
+            kind,
+            provider: None,
+            subject: None,
+            client: client.clone(),
+            detail: None,
+        }
+    }
+
+    /// Attributes the event to an authenticated principal.
+    pub fn principal(mut self, principal: &Principal) -> Self {
+        self.provider = Some(provider_name(&principal.provider_kind));
+        self.subject = Some(principal.global_id());
+        self
+    }
+
+    /// Sets the provider kind, for events without a principal.
+    pub fn provider(mut self, kind: &ProviderKind) -> Self {
+        self.provider = Some(provider_name(kind));
+        self
+    }
+
+    /// Sets the subject, e.g. the username given in a failed attempt.
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = Some(clip(subject));
+        self
+    }
+
+    pub fn detail(mut self, detail: impl AsRef<str>) -> Self {
+        self.detail = Some(clip(detail.as_ref()));
+        self
+    }
+
+    /// Logs and publishes the event, and appends it to the audit trail when a
+    /// database is configured.
+    pub async fn record(self, state: &ArkState) {
+        tracing::info!(
+            target: "audit",
+            event = self.kind.as_str(),
+            provider = self.provider.as_deref().unwrap_or("-"),
+            subject = self.subject.as_deref().unwrap_or("-"),
+            ip = ?self.client.ip,
+            detail = self.detail.as_deref().unwrap_or(""),
+            "Authentication event"
+        );
+        state.events.publish(ServerEvent::Auth {
+            event: self.kind.as_str().to_string(),
+            provider: self.provider.clone(),
+            subject: self.subject.clone(),
+        });
+        let request_id = crate::logging::current_request_id();
+        let Some(database) = state.database.read().ok().and_then(|g| g.clone()) else {
+            return;
+        };
+        let record = AuthEventRecord {
+            id: 0,
+            timestamp_utc: chrono::Utc::now(),
+            event: self.kind.as_str().to_string(),
+            provider: self.provider,
+            subject: self.subject,
+            ip: self.client.ip.map(|ip| ip.to_string()),
+            user_agent: self.client.user_agent,
+            detail: self.detail,
+            request_id,
+        };
+        if let Err(e) = database.save_auth_event_async(record).await {
+            tracing::warn!("Failed to record authentication event: {}", e);
+        }
+    }
+}
+
+/// Coalesces repeated failures from one client, so a client retrying a bad
+/// credential writes one event per interval rather than one per request.
+///
+/// Clients are told apart by IP; those without one share an entry.
+#[derive(Debug)]
+pub struct FailureThrottle {
+    interval: Duration,
+    entries: Mutex<HashMap<Option<IpAddr>, (Instant, u32)>>,
+}
+
+impl FailureThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a failure from `client`. Returns whether it should be recorded,
+    /// with the number of failures skipped since the client's last recorded
+    /// one, or `None` while the interval since that one has not passed.
+    pub fn admit(&self, client: &ClientInfo) -> Option<u32> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get_mut(&client.ip) {
+            Some((recorded, skipped)) if now.duration_since(*recorded) < self.interval => {
+                *skipped += 1;
+                None
+            }
+            entry => {
+                let skipped = entry.map_or(0, |(_, skipped)| *skipped);
+                entries.insert(client.ip, (now, 0));
+                Some(skipped)
+            }
+        }
+    }
+
+    /// Forgets the clients whose interval has passed.
+    pub fn purge(&self) {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, (recorded, _)| now.duration_since(*recorded) < self.interval);
+    }
+}
+
+/// Lower-case provider kind as stored in the `provider` column.
+pub fn provider_name(kind: &ProviderKind) -> String {
+    format!("{:?}", kind).to_lowercase()
+}
+
+fn clip(value: &str) -> String {
+    value.chars().take(MAX_FIELD_LEN).collect()
+}
@@ -1,13 +1,20 @@
 //! Core authentication logic and data structures.
 
-use crate::config::models::{AuthConfig, IdentityProviderConfig};
+use crate::config::models::{
+    AuthConfig, IdentityProviderConfig, IdentityProviderKind, SessionConfig,
+};
+use crate::errors::ErrorCode;
+use crate::server::audit::{AuthEvent, AuthEventKind, ClientInfo};
+use crate::server::authz::{self, Authorizer, ToolScope};
+use crate::server::csrf;
 use crate::server::roles::Role;
+use crate::server::service::StandardizedResponse;
 use crate::server::signing::{DynSigner, load_pem_signer_from_paths};
 use anyhow::{Context, Result, anyhow};
 use axum::{
     Extension,
     extract::Request,
-    http::{StatusCode, header},
+    http::{Extensions, HeaderMap, StatusCode, header},
     middleware::Next,
     response::{IntoResponse, Response},
 };
@@ -22,16 +29,34 @@ use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
+/// Prefix identifying API keys (personal access tokens) in bearer headers.
+pub const API_KEY_PREFIX: &str = "ark_";
+/// API key scope granting access to the management API.
+pub const API_KEY_SCOPE_API: &str = "api";
+/// API key scope granting access to the MCP endpoint.
+pub const API_KEY_SCOPE_MCP: &str = "mcp";
+/// Provider name recorded on service account principals.
+pub const SERVICE_ACCOUNT_PROVIDER: &str = "ark";
+/// Authority recorded on principals authenticated by the `local` provider.
+pub const LOCAL_PROVIDER_AUTHORITY: &str = "local";
+/// Minimum accepted length for local account passwords.
+pub const MIN_PASSWORD_LEN: usize = 8;
+/// Access token lifetime assumed when the IdP omits `expires_in`.
+const DEFAULT_ACCESS_TOKEN_LIFETIME_SECS: u64 = 3600;
+
 /// Represents the response from a token exchange request.
 #[derive(Debug, Deserialize)]
 pub struct TokenResponse {
+    /// ID token; refresh responses may omit it, in which case it is empty.
+    #[serde(default)]
     pub id_token: String,
-    // The following fields are part of the standard token response but are not
-    // currently used in this application. They are kept for completeness and
-    // potential future use-cases like API access with the access_token.
     pub access_token: String,
-    // pub refresh_token: Option<String>,
-    // pub expires_in: u64,
+    /// Refresh token, when the IdP issues one (usually for `offline_access`).
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Lifetime of the access token in seconds.
+    #[serde(default)]
+    pub expires_in: Option<u64>,
     // pub scope: String,
 }
 
@@ -48,8 +73,23 @@ pub struct IdTokenClaims {
     pub picture: Option<String>,
     pub tid: Option<String>,
     pub oid: Option<String>,
-    /// Groups the user belongs to (from the 'groups' claim).
+    /// Groups the user belongs to (from the 'groups' claim). Values that are
+    /// not a list of strings are ignored here; see `groups.claim`/`groups.path`.
+    #[serde(default, deserialize_with = "lenient_string_list")]
     pub groups: Option<Vec<String>>,
+    /// The full claim set, used for configurable group extraction.
+    #[serde(skip)]
+    pub raw: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Deserializes an optional list of strings, yielding `None` for other shapes
+/// (e.g. a list of objects) instead of failing the whole token.
+fn lenient_string_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|v| serde_json::from_value(v).ok()))
 }
 
 /// Represents an authenticated user principal.
@@ -70,6 +110,10 @@ pub struct Principal {
     pub provider: String,
     /// The canonical provider kind (required, no fallback).
     pub provider_kind: ProviderKind,
+    /// Name of the configured provider the user signed in with; `None` for
+    /// service accounts and sessions created before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_name: Option<String>,
     /// Optional tenant identifier for multi-tenant providers.
     pub tenant_id: Option<String>,
     /// Optional object identifier for directory services.
@@ -90,25 +134,22 @@ impl Principal {
     ///
     /// # Returns
     ///
-    /// A string in the format "provider/tenant/userid".
+    /// A string in the format "provider/tenant/userid", where "provider" is
+    /// qualified by the configured provider name (e.g. "okta:okta-eu") unless
+    /// the provider is named after its kind; see [`ProviderKind::id_prefix`].
     pub fn global_id(&self) -> String {
-        // build a global id based on provider. Unwrap should be safe for
-        // oid and tenant_id since those fields should've previously been asserted
-        match self.provider_kind {
-            ProviderKind::Microsoft => {
-                format!(
-                    "aad/{}/{}",
-                    self.tenant_id.clone().unwrap(),
-                    self.oid.clone().unwrap()
-                )
-            }
-            ProviderKind::Google => {
-                format!("gip/*/{}", self.subject)
-            }
-            ProviderKind::Oidc => {
-                format!("oidc/*/{}", self.subject)
-            }
-        }
+        // Unwrap should be safe for oid and tenant_id since those fields
+        // should've previously been asserted
+        let (tenant, id) = match self.provider_kind {
+            ProviderKind::Microsoft => (self.tenant_id.clone().unwrap(), self.oid.clone().unwrap()),
+            _ => ("*".to_string(), self.subject.clone()),
+        };
+        format!(
+            "{}/{}/{}",
+            self.provider_kind.id_prefix(self.provider_name.as_deref()),
+            tenant,
+            id
+        )
     }
 }
 
@@ -120,6 +161,54 @@ pub enum ProviderKind {
     Microsoft,
     Google,
     Oidc,
+    Okta,
+    Auth0,
+    Keycloak,
+    /// Admin-managed service account (not backed by an external provider).
+    Service,
+    /// Local username/password account stored in the database.
+    Local,
+    /// SAML 2.0 identity provider.
+    Saml,
+    /// LDAP / Active Directory account.
+    Ldap,
+}
+
+impl ProviderKind {
+    /// First segment of the global ids of users signed in with the provider
+    /// named `name`.
+    ///
+    /// That is the kind (e.g. "okta"), qualified by the name ("okta:okta-eu")
+    /// so that providers of the same kind don't share ids. Providers named
+    /// after their kind, service accounts and local accounts (one users table
+    /// whatever the provider is called) are not qualified.
+    pub fn id_prefix(&self, name: Option<&str>) -> String {
+        let kind = match self {
+            ProviderKind::Microsoft => "aad",
+            ProviderKind::Google => "gip",
+            ProviderKind::Oidc => "oidc",
+            ProviderKind::Okta => "okta",
+            ProviderKind::Auth0 => "auth0",
+            ProviderKind::Keycloak => "keycloak",
+            ProviderKind::Service => "svc",
+            ProviderKind::Local => "local",
+            ProviderKind::Saml => "saml",
+            ProviderKind::Ldap => "ldap",
+        };
+        let named_after_kind = |name: &str| {
+            name == crate::server::audit::provider_name(self)
+                || (*self == ProviderKind::Microsoft && name == "entra")
+        };
+        match name {
+            Some(name)
+                if !named_after_kind(name)
+                    && !matches!(self, ProviderKind::Service | ProviderKind::Local) =>
+            {
+                format!("{kind}:{name}")
+            }
+            _ => kind.to_string(),
+        }
+    }
 }
 
 /// Resolved identity provider configuration.
@@ -133,7 +222,6 @@ pub struct ResolvedProvider {
     /// The client ID registered with the provider.
     pub client_id: String,
     /// The client secret for confidential clients.
-    #[allow(dead_code)]
     pub client_secret: Option<String>,
     /// List of OAuth scopes to request.
     pub scopes: Vec<String>,
@@ -145,10 +233,18 @@ pub struct ResolvedProvider {
     pub authorization_endpoint: Option<String>,
     /// Optional pre-configured token endpoint.
     pub token_endpoint: Option<String>,
+    /// Optional end-session (logout) endpoint, from a preset or discovery.
+    pub end_session_endpoint: Option<String>,
+    /// Optional API audience requested at authorization time (Auth0).
+    pub audience: Option<String>,
     /// The canonical provider kind (set during resolution).
     pub provider_kind: ProviderKind,
     /// Optional group configuration for role-based access control.
     pub groups: Option<crate::config::models::Groups>,
+    /// SAML service provider settings (SAML providers only).
+    pub saml: Option<Arc<crate::server::saml::ServiceProvider>>,
+    /// Directory settings (LDAP providers only).
+    pub ldap: Option<Arc<crate::server::ldap::Directory>>,
 }
 
 /// Supported identity provider types.
@@ -164,6 +260,18 @@ pub enum IdentityProvider {
     Google(IdentityProviderConfig),
     /// Generic OIDC provider.
     Oidc(IdentityProviderConfig),
+    /// Okta (org or custom authorization server) preset.
+    Okta(IdentityProviderConfig),
+    /// Auth0 tenant preset.
+    Auth0(IdentityProviderConfig),
+    /// Keycloak realm preset.
+    Keycloak(IdentityProviderConfig),
+    /// Local username/password accounts (no external IdP).
+    Local(IdentityProviderConfig),
+    /// SAML 2.0 identity provider.
+    Saml(IdentityProviderConfig),
+    /// LDAP / Active Directory (username/password form, no external IdP).
+    Ldap(IdentityProviderConfig),
 }
 
 impl IdentityProvider {
@@ -209,8 +317,12 @@ impl IdentityProvider {
                     jwks_uri: config.jwks_uri.clone(),
                     authorization_endpoint: config.authorization_endpoint.clone(),
                     token_endpoint: config.token_endpoint.clone(),
+                    end_session_endpoint: None,
+                    audience: None,
                     provider_kind: ProviderKind::Microsoft,
                     groups: config.groups.clone(),
+                    saml: None,
+                    ldap: None,
                 })
             }
             IdentityProvider::Google(config) => {
@@ -234,8 +346,12 @@ impl IdentityProvider {
                     jwks_uri: config.jwks_uri.clone(),
                     authorization_endpoint: config.authorization_endpoint.clone(),
                     token_endpoint: config.token_endpoint.clone(),
+                    end_session_endpoint: None,
+                    audience: None,
                     provider_kind: ProviderKind::Google,
                     groups: config.groups.clone(),
+                    saml: None,
+                    ldap: None,
                 })
             }
             IdentityProvider::Oidc(config) => {
@@ -271,12 +387,265 @@ impl IdentityProvider {
                     jwks_uri: config.jwks_uri.clone(),
                     authorization_endpoint: config.authorization_endpoint.clone(),
                     token_endpoint: config.token_endpoint.clone(),
+                    end_session_endpoint: None,
+                    audience: None,
                     provider_kind: ProviderKind::Oidc,
                     groups: config.groups.clone(),
+                    saml: None,
+                    ldap: None,
+                })
+            }
+            IdentityProvider::Okta(config) => {
+                let authority = preset_authority("Okta", config, false)?;
+                // The org authorization server serves OIDC under /oauth2/v1, custom
+                // authorization servers (/oauth2/{id}) directly under /v1.
+                let logout = if authority.contains("/oauth2/") {
+                    format!("{}/v1/logout", authority)
+                } else {
+                    format!("{}/oauth2/v1/logout", authority)
+                };
+                resolve_preset(
+                    config,
+                    authority,
+                    "openid profile email groups",
+                    logout,
+                    ProviderKind::Okta,
+                )
+            }
+            IdentityProvider::Auth0(config) => {
+                // Auth0 issues tokens with a trailing slash on the issuer.
+                let authority = preset_authority("Auth0", config, true)?;
+                let logout = format!("{}v2/logout", authority);
+                resolve_preset(
+                    config,
+                    authority,
+                    "openid profile email",
+                    logout,
+                    ProviderKind::Auth0,
+                )
+            }
+            IdentityProvider::Keycloak(config) => {
+                let authority = preset_authority("Keycloak", config, false)?;
+                if !authority.contains("/realms/") {
+                    return Err(anyhow!(
+                        "Keycloak provider authority must include the realm (https://host/realms/<realm>): {}",
+                        authority
+                    ));
+                }
+                let logout = format!("{}/protocol/openid-connect/logout", authority);
+                resolve_preset(
+                    config,
+                    authority,
+                    "openid profile email",
+                    logout,
+                    ProviderKind::Keycloak,
+                )
+            }
+            IdentityProvider::Local(config) => {
+                // Credentials are checked against the users table; no endpoints,
+                // client registration or discovery are involved.
+                Ok(ResolvedProvider {
+                    authority: LOCAL_PROVIDER_AUTHORITY.to_string(),
+                    client_id: config.client_id.clone(),
+                    client_secret: None,
+                    scopes: Vec::new(),
+                    discovery: false,
+                    jwks_uri: None,
+                    authorization_endpoint: None,
+                    token_endpoint: None,
+                    end_session_endpoint: None,
+                    audience: None,
+                    provider_kind: ProviderKind::Local,
+                    groups: config.groups.clone(),
+                    saml: None,
+                    ldap: None,
+                })
+            }
+            IdentityProvider::Saml(config) => {
+                let saml = config
+                    .saml
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("SAML provider requires a 'saml' section"))?;
+                let sp = crate::server::saml::ServiceProvider::from_config(saml)?;
+                Ok(ResolvedProvider {
+                    authority: sp.idp_entity_id.clone(),
+                    client_id: sp.sp_entity_id.clone(),
+                    client_secret: None,
+                    scopes: Vec::new(),
+                    discovery: false,
+                    jwks_uri: None,
+                    authorization_endpoint: Some(sp.idp_sso_url.clone()),
+                    token_endpoint: None,
+                    end_session_endpoint: None,
+                    audience: None,
+                    provider_kind: ProviderKind::Saml,
+                    groups: config.groups.clone(),
+                    saml: Some(Arc::new(sp)),
+                    ldap: None,
+                })
+            }
+            IdentityProvider::Ldap(config) => {
+                let ldap = config
+                    .ldap
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("LDAP provider requires an 'ldap' section"))?;
+                let directory = crate::server::ldap::Directory::from_config(ldap)?;
+                Ok(ResolvedProvider {
+                    authority: directory.url().to_string(),
+                    client_id: config.client_id.clone(),
+                    client_secret: None,
+                    scopes: Vec::new(),
+                    discovery: false,
+                    jwks_uri: None,
+                    authorization_endpoint: None,
+                    token_endpoint: None,
+                    end_session_endpoint: None,
+                    audience: None,
+                    provider_kind: ProviderKind::Ldap,
+                    groups: config.groups.clone(),
+                    saml: None,
+                    ldap: Some(Arc::new(directory)),
                 })
             }
         }
     }
+
+    /// Selects the provider type from a configured provider.
+    ///
+    /// The type follows `kind`, which defaults to local for a provider named
+    /// "local" and to OIDC otherwise. OIDC presets are matched by name prefix
+    /// so several instances can coexist (e.g. "okta-staging"); anything
+    /// unrecognized is generic OIDC. A provider named after a kind (e.g.
+    /// "local") must be of that kind.
+    pub fn from_config(config: &IdentityProviderConfig) -> Result<Self> {
+        let name = config.name.as_str();
+        let named = match name {
+            "oidc" => Some(IdentityProviderKind::Oidc),
+            "local" => Some(IdentityProviderKind::Local),
+            "saml" => Some(IdentityProviderKind::Saml),
+            "ldap" => Some(IdentityProviderKind::Ldap),
+            _ => None,
+        };
+        let kind = config
+            .kind
+            .or(named.filter(|k| *k == IdentityProviderKind::Local))
+            .unwrap_or(IdentityProviderKind::Oidc);
+        if let Some(named) = named
+            && named != kind
+        {
+            return Err(anyhow!(
+                "Provider '{}' is named after kind '{}' but has kind '{}'",
+                name,
+                named.as_str(),
+                kind.as_str()
+            ));
+        }
+        Ok(match kind {
+            IdentityProviderKind::Local => IdentityProvider::Local(config.clone()),
+            IdentityProviderKind::Saml => IdentityProvider::Saml(config.clone()),
+            IdentityProviderKind::Ldap => IdentityProvider::Ldap(config.clone()),
+            IdentityProviderKind::Oidc => {
+                if name.starts_with("microsoft") || name == "entra" {
+                    IdentityProvider::Microsoft(config.clone())
+                } else if name.starts_with("google") {
+                    IdentityProvider::Google(config.clone())
+                } else if name.starts_with("okta") {
+                    IdentityProvider::Okta(config.clone())
+                } else if name.starts_with("auth0") {
+                    IdentityProvider::Auth0(config.clone())
+                } else if name.starts_with("keycloak") {
+                    IdentityProvider::Keycloak(config.clone())
+                } else {
+                    IdentityProvider::Oidc(config.clone())
+                }
+            }
+        })
+    }
+}
+
+/// Validates and normalizes the authority of a preset provider.
+///
+/// A bare host (e.g. "dev-123.okta.com") is accepted and promoted to HTTPS.
+/// Trailing slashes are removed, or enforced when `trailing_slash` is set.
+fn preset_authority(
+    label: &str,
+    config: &IdentityProviderConfig,
+    trailing_slash: bool,
+) -> Result<String> {
+    if config.client_id.trim().is_empty() {
+        return Err(anyhow!("{} provider client_id cannot be empty", label));
+    }
+    let authority = config.authority.trim();
+    if authority.is_empty() {
+        return Err(anyhow!("{} provider authority cannot be empty", label));
+    }
+    let authority = if authority.contains("://") {
+        if !authority.starts_with("http://") && !authority.starts_with("https://") {
+            return Err(anyhow!(
+                "{} provider authority must be a valid HTTP/HTTPS URL: {}",
+                label,
+                authority
+            ));
+        }
+        authority.to_string()
+    } else {
+        format!("https://{}", authority)
+    };
+    let trimmed = authority.trim_end_matches('/');
+    Ok(if trailing_slash {
+        format!("{}/", trimmed)
+    } else {
+        trimmed.to_string()
+    })
+}
+
+/// Builds a `ResolvedProvider` for a preset, applying preset defaults where
+/// the configuration leaves values unset.
+fn resolve_preset(
+    config: &IdentityProviderConfig,
+    authority: String,
+    default_scopes: &str,
+    end_session_endpoint: String,
+    provider_kind: ProviderKind,
+) -> Result<ResolvedProvider> {
+    let scopes = config
+        .scopes
+        .clone()
+        .unwrap_or_else(|| default_scopes.to_string());
+    let scopes_vec: Vec<String> = scopes.split_whitespace().map(|s| s.to_string()).collect();
+    Ok(ResolvedProvider {
+        authority,
+        client_id: config.client_id.clone(),
+        client_secret: config.client_secret.clone(),
+        scopes: scopes_vec,
+        discovery: config.discovery,
+        jwks_uri: config.jwks_uri.clone(),
+        authorization_endpoint: config.authorization_endpoint.clone(),
+        token_endpoint: config.token_endpoint.clone(),
+        end_session_endpoint: Some(end_session_endpoint),
+        // Only Auth0 needs the API audience on the authorize request; elsewhere
+        // the audience is configured on the authorization server itself.
+        audience: if provider_kind == ProviderKind::Auth0 {
+            config.audience.clone()
+        } else {
+            None
+        },
+        provider_kind,
+        groups: config.groups.clone(),
+        saml: None,
+        ldap: None,
+    })
+}
+
+/// A provider listed in `auth.provider`, selectable at sign-in.
+#[derive(Clone, Debug)]
+pub struct ActiveProvider {
+    /// Name from the providers list, as given to `/auth/login?provider=`.
+    pub name: String,
+    /// The provider kind.
+    pub kind: ProviderKind,
+    /// Resolved settings, completed in place by OIDC discovery.
+    pub resolved: Arc<RwLock<Option<ResolvedProvider>>>,
 }
 
 /// Pending authentication state for OAuth flows.
@@ -285,7 +654,8 @@ impl IdentityProvider {
 /// including the PKCE verifier and creation timestamp.
 #[derive(Clone, Debug)]
 pub struct PendingAuth {
-    /// The PKCE code verifier for this authentication attempt.
+    /// The PKCE code verifier for this authentication attempt. For SAML logins
+    /// this holds the ID of the AuthnRequest the response must answer.
     pub code_verifier: String,
     /// When this pending auth was created.
     #[allow(dead_code)]
@@ -295,6 +665,38 @@ pub struct PendingAuth {
     pub redirect_to: Option<String>,
     /// Original OAuth request query string for resuming after login.
     pub oauth_query: Option<String>,
+    /// Name of the provider the login was started with; `None` for the
+    /// default provider.
+    pub provider: Option<String>,
+}
+
+/// A device authorization request (RFC 8628 device code flow), keyed by its
+/// device code until the client redeems or abandons it.
+#[derive(Clone, Debug)]
+pub struct DeviceAuthorization {
+    /// Client that started the flow; only it may redeem the device code.
+    pub client_id: String,
+    /// Short code the user enters on the verification page.
+    pub user_code: String,
+    /// Scope requested for the access token.
+    pub scope: String,
+    /// When the device code expires.
+    pub expires_at: SystemTime,
+    /// Minimum time between two polls of the token endpoint.
+    pub interval: Duration,
+    /// Earliest time the client may poll again.
+    pub next_poll: SystemTime,
+    /// The user's decision, once made on the verification page.
+    pub decision: Option<DeviceDecision>,
+}
+
+/// Outcome of a device authorization on the verification page.
+#[derive(Clone, Debug)]
+pub enum DeviceDecision {
+    /// Approved by the signed-in user, whose identity the token carries.
+    Approved(Box<Principal>),
+    /// Denied by the user.
+    Denied,
 }
 
 /// Core authentication state and session management.
@@ -317,14 +719,84 @@ type AuthCodeEntry = (
     Option<String>,
 );
 
+/// Builds the session and CSRF cookies with the `auth.session` attributes.
+#[derive(Clone, Debug)]
+pub struct SessionCookies {
+    /// Lifetime of sessions that cannot be renewed.
+    pub ttl: Duration,
+    secure: bool,
+    http_only: bool,
+    same_site: String,
+    domain: Option<String>,
+}
+
+impl SessionCookies {
+    pub fn from_config(config: &SessionConfig) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.timeout_seconds),
+            secure: config.cookie_secure,
+            http_only: config.cookie_http_only,
+            same_site: config.same_site.clone(),
+            domain: config.cookie_domain.clone(),
+        }
+    }
+
+    /// Returns the `Set-Cookie` value of the session cookie.
+    pub fn session(&self, session_id: &str, max_age: Duration) -> String {
+        self.cookie("ark_session", session_id, self.http_only, max_age)
+    }
+
+    /// Returns the `Set-Cookie` value exposing the session's CSRF token to
+    /// scripts.
+    pub fn csrf(&self, session_id: &str, max_age: Duration) -> String {
+        self.cookie(
+            csrf::CSRF_COOKIE,
+            &csrf::token_for_session(session_id),
+            false,
+            max_age,
+        )
+    }
+
+    /// Returns the `Set-Cookie` values expiring the session and CSRF cookies.
+    pub fn cleared(&self) -> [String; 2] {
+        [
+            self.cookie("ark_session", "deleted", self.http_only, Duration::ZERO),
+            self.cookie(csrf::CSRF_COOKIE, "deleted", false, Duration::ZERO),
+        ]
+    }
+
+    fn cookie(&self, name: &str, value: &str, http_only: bool, max_age: Duration) -> String {
+        let mut cookie = format!("{}={}; Path=/", name, value);
+        if http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        cookie.push_str(&format!(
+            "; SameSite={}; Max-Age={}",
+            self.same_site,
+            max_age.as_secs()
+        ));
+        if let Some(domain) = &self.domain {
+            cookie.push_str(&format!("; Domain={}", domain));
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        cookie
+    }
+}
+
 #[derive(Clone)]
 pub struct AuthState {
     /// Whether authentication is enabled.
     pub enabled: bool,
     /// HTTP client for making external requests (OIDC discovery, token exchange).
     pub http: Client,
-    /// Active resolved provider configuration.
+    /// Active resolved provider configuration; the default provider when
+    /// several are active.
     pub active: Arc<RwLock<Option<ResolvedProvider>>>,
+    /// Every active provider in configuration order. The first one shares
+    /// its settings with `active`.
+    pub providers: Vec<ActiveProvider>,
     /// Reference to the application state for database access.
     pub app_state: Arc<crate::state::ArkState>,
     /// Pending OAuth authentications (state -> PendingAuth).
@@ -333,8 +805,36 @@ pub struct AuthState {
     pub auth_codes: Arc<RwLock<HashMap<String, AuthCodeEntry>>>,
     /// OAuth access tokens (token -> (principal, expires_at, scope)).
     pub access_tokens: Arc<RwLock<HashMap<String, AccessTokenEntry>>>,
+    /// Pending device authorizations (device code -> DeviceAuthorization).
+    pub device_codes: Arc<RwLock<HashMap<String, DeviceAuthorization>>>,
     /// Optional signer for issuing ID tokens (JWKS endpoint)
     pub signer: Option<DynSigner>,
+    /// Role definitions used to resolve permissions.
+    pub authz: Arc<Authorizer>,
+    /// Whether sessions are renewed with the IdP refresh token.
+    pub session_refresh: bool,
+    /// Absolute lifetime of a renewable session.
+    pub session_max_lifetime: Duration,
+    /// Whether sessions are bound to the client IP they were created from.
+    pub session_bind_ip: bool,
+    /// Whether sessions are bound to the `User-Agent` they were created with.
+    pub session_bind_user_agent: bool,
+    /// Maximum number of active sessions per principal, if limited.
+    pub session_max_per_user: Option<usize>,
+    /// Cipher sealing the refresh tokens stored with sessions.
+    pub cipher: crate::server::crypto::Cipher,
+    /// Backoff and lockout for failed sign-in attempts.
+    pub login_limiter: Arc<crate::server::lockout::LoginLimiter>,
+    /// Serializes session renewals so a rotated refresh token is only redeemed once.
+    renewals: Arc<tokio::sync::Mutex<()>>,
+    /// Settings of the OAuth authorization server.
+    pub oauth: crate::config::models::OAuthServerConfig,
+    /// Attributes of the session cookies.
+    pub session_cookies: SessionCookies,
+    /// How long authentication events are kept, if not forever.
+    pub audit_retention: Option<Duration>,
+    /// Coalesces the audit events of rejected API keys per client.
+    failed_key_audits: Arc<crate::server::audit::FailureThrottle>,
 }
 
 impl std::fmt::Debug for AuthState {
@@ -343,10 +843,22 @@ impl std::fmt::Debug for AuthState {
             .field("enabled", &self.enabled)
             .field("http", &"reqwest::Client")
             .field("active", &self.active)
+            .field("providers", &self.providers)
             .field("app_state", &self.app_state)
             .field("pending", &self.pending)
             .field("auth_codes", &self.auth_codes)
             .field("access_tokens", &self.access_tokens)
+            .field("device_codes", &self.device_codes)
+            .field("login_limiter", &self.login_limiter)
+            .field("authz", &self.authz)
+            .field("session_refresh", &self.session_refresh)
+            .field("session_max_lifetime", &self.session_max_lifetime)
+            .field("session_bind_ip", &self.session_bind_ip)
+            .field("session_bind_user_agent", &self.session_bind_user_agent)
+            .field("session_max_per_user", &self.session_max_per_user)
+            .field("oauth", &self.oauth)
+            .field("session_cookies", &self.session_cookies)
+            .field("audit_retention", &self.audit_retention)
             .finish()
     }
 }
@@ -363,43 +875,146 @@ impl AuthState {
             .build()
             .context("Failed to create HTTP client")?;
 
-        let active = if let Some(auth_config) = config {
-            if let Some(provider_name) = &auth_config.provider {
-                // Find the provider config by name
-                let provider_config = auth_config
-                    .providers
+        let mut resolved: Vec<(String, ResolvedProvider)> = Vec::new();
+        let names = config
+            .as_ref()
+            .map(|c| c.active_provider_names())
+            .unwrap_or_default();
+        for provider_name in names {
+            // Find the provider config by name
+            let provider_config = config
+                .iter()
+                .flat_map(|c| c.providers.iter())
+                .find(|p| p.name == provider_name)
+                .ok_or_else(|| {
+                    anyhow!("Provider '{}' not found in providers list", provider_name)
+                })?;
+
+            // Create IdentityProvider enum from config
+            let provider = IdentityProvider::from_config(provider_config)?.resolve()?;
+
+            if resolved.iter().any(|(name, _)| name == provider_name) {
+                return Err(anyhow!("Provider '{}' is listed twice", provider_name));
+            }
+            // The name qualifies global ids, whose segments are separated by '/'
+            if provider_name.contains('/') {
+                return Err(anyhow!(
+                    "Provider name '{}' cannot contain '/'",
+                    provider_name
+                ));
+            }
+            resolved.push((provider_name.to_string(), provider));
+        }
+
+        let authz = Authorizer::from_config(
+            config
+                .as_ref()
+                .map(|c| c.roles.as_slice())
+                .unwrap_or_default(),
+        )
+        .context("Invalid auth.roles configuration")?;
+
+        // Group claim paths and role mappings must refer to valid syntax and known roles
+        for groups in resolved.iter().filter_map(|(_, p)| p.groups.as_ref()) {
+            if let Some(path) = &groups.path {
+                crate::server::roles::validate_groups_path(path)
+                    .map_err(|e| anyhow!("Invalid groups.path: {}", e))?;
+            }
+            for (group, roles) in &groups.role_mappings {
+                if let Some(role) = roles
                     .iter()
-                    .find(|p| p.name == *provider_name)
-                    .ok_or_else(|| {
-                        anyhow!("Provider '{}' not found in providers list", provider_name)
-                    })?;
-
-                // Create IdentityProvider enum from config
-                let identity_provider =
-                    if provider_name.starts_with("microsoft") || provider_name == "entra" {
-                        IdentityProvider::Microsoft(provider_config.clone())
-                    } else if provider_name.starts_with("google") {
-                        IdentityProvider::Google(provider_config.clone())
-                    } else {
-                        IdentityProvider::Oidc(provider_config.clone())
-                    };
+                    .map(|r| Role::from(r.as_str()))
+                    .find(|r| !authz.is_defined(r))
+                {
+                    return Err(anyhow!(
+                        "groups.role_mappings maps '{}' to undefined role '{}'",
+                        group,
+                        role
+                    ));
+                }
+            }
+        }
 
-                Some(identity_provider.resolve()?)
-            } else {
-                None
+        let session = config
+            .as_ref()
+            .and_then(|c| c.session.clone())
+            .unwrap_or_default();
+        let cipher = match crate::server::crypto::Cipher::from_session_key(
+            session.encryption_key.as_deref(),
+        )
+        .context("Invalid auth.session.encryption_key")?
+        {
+            Some(cipher) => cipher,
+            None => {
+                if session.refresh && !resolved.is_empty() {
+                    tracing::info!(
+                        "No session encryption key configured; sessions cannot be renewed after a restart"
+                    );
+                }
+                crate::server::crypto::Cipher::ephemeral()
             }
-        } else {
-            None
         };
 
+        let providers: Vec<ActiveProvider> = resolved
+            .into_iter()
+            .map(|(name, provider)| ActiveProvider {
+                name,
+                kind: provider.provider_kind.clone(),
+                resolved: Arc::new(RwLock::new(Some(provider))),
+            })
+            .collect();
+        let active = match providers.first() {
+            Some(default) => default.resolved.clone(),
+            None => Arc::new(RwLock::new(None)),
+        };
+
+        // Global ids of providers not named after their kind are qualified by
+        // the name. Rows from before then are moved to the provider when it is
+        // the only active one of its kind, so they can only be its users'.
+        let database = app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|g| g.as_ref().cloned());
+        for provider in &providers {
+            let legacy = provider.kind.id_prefix(None);
+            let qualified = provider.kind.id_prefix(Some(&provider.name));
+            let same_kind = providers.iter().filter(|p| p.kind == provider.kind).count();
+            let Some(database) = database
+                .as_ref()
+                .filter(|_| qualified != legacy && same_kind == 1)
+            else {
+                continue;
+            };
+            match database
+                .qualify_principal_ids_async(legacy, qualified.clone(), provider.name.clone())
+                .await
+            {
+                Ok(0) => {}
+                Ok(n) => tracing::info!(
+                    "Moved {} rows of provider '{}' to global ids starting with '{}'",
+                    n,
+                    provider.name,
+                    qualified
+                ),
+                Err(e) => tracing::warn!(
+                    "Failed to move rows of provider '{}' to its global ids: {}",
+                    provider.name,
+                    e
+                ),
+            }
+        }
+
         Ok(Self {
             enabled: config.as_ref().map(|c| c.enabled).unwrap_or(false),
             http,
-            active: Arc::new(RwLock::new(active)),
+            active,
+            providers,
             app_state,
             pending: Arc::new(RwLock::new(HashMap::new())),
             auth_codes: Arc::new(RwLock::new(HashMap::new())),
             access_tokens: Arc::new(RwLock::new(HashMap::new())),
+            device_codes: Arc::new(RwLock::new(HashMap::new())),
             signer: signer_override.or_else(|| {
                 // If override not provided, try env/config as before (best-effort)
                 let key_path = std::env::var("ARK_TOKEN_SIGNING_KEY").ok();
@@ -416,6 +1031,33 @@ impl AuthState {
                     None
                 }
             }),
+            authz: Arc::new(authz),
+            session_refresh: session.refresh,
+            session_max_lifetime: Duration::from_secs(session.max_lifetime_seconds),
+            session_bind_ip: session.bind_client_ip,
+            session_bind_user_agent: session.bind_user_agent,
+            session_max_per_user: session.max_sessions_per_user,
+            session_cookies: SessionCookies::from_config(&session),
+            cipher,
+            login_limiter: Arc::new(crate::server::lockout::LoginLimiter::new(
+                config
+                    .as_ref()
+                    .map(|c| c.lockout.clone())
+                    .unwrap_or_default(),
+            )),
+            renewals: Arc::new(tokio::sync::Mutex::new(())),
+            oauth: config.as_ref().map(|c| c.oauth.clone()).unwrap_or_default(),
+            audit_retention: Some(
+                config
+                    .as_ref()
+                    .map(|c| c.audit.retention_days)
+                    .unwrap_or_else(crate::config::defaults::default_audit_retention_days),
+            )
+            .filter(|days| *days > 0)
+            .map(|days| Duration::from_secs(u64::from(days) * 86_400)),
+            failed_key_audits: Arc::new(crate::server::audit::FailureThrottle::new(
+                crate::server::audit::FAILURE_AUDIT_INTERVAL,
+            )),
         })
     }
 
@@ -424,6 +1066,37 @@ impl AuthState {
         self.signer.as_ref().map(|s| s.jwks())
     }
 
+    /// Returns the active provider named `name`, or the default provider
+    /// when `name` is `None`.
+    pub fn active_provider(&self, name: Option<&str>) -> Option<&ActiveProvider> {
+        match name {
+            Some(name) => self.providers.iter().find(|p| p.name == name),
+            None => self.providers.first(),
+        }
+    }
+
+    /// Returns the active provider of `kind` named `name`, or the first one
+    /// of `kind` when `name` is `None`.
+    fn active_of_kind(&self, kind: &ProviderKind, name: Option<&str>) -> Option<&ActiveProvider> {
+        self.providers
+            .iter()
+            .find(|p| p.kind == *kind && name.is_none_or(|name| p.name == name))
+    }
+
+    /// Returns the settings of the active provider of `kind` named `name`,
+    /// or of the first one of `kind` when `name` is `None`.
+    pub async fn provider_of_kind(
+        &self,
+        kind: &ProviderKind,
+        name: Option<&str>,
+    ) -> Option<ResolvedProvider> {
+        self.active_of_kind(kind, name)?
+            .resolved
+            .read()
+            .await
+            .clone()
+    }
+
     /// Retrieves a user session by session ID.
     ///
     /// Looks up the session and checks if it hasn't expired.
@@ -431,11 +1104,19 @@ impl AuthState {
     /// # Arguments
     ///
     /// * `session_id` - The session identifier.
+    /// * `client` - The client sending the session cookie.
     ///
     /// # Returns
     ///
-    /// `Some(Principal)` if the session is valid, `None` otherwise.
-    pub async fn get_session(&self, session_id: &str) -> Option<Principal> {
+    /// `Some(Principal)` if the session is valid, `None` otherwise. When
+    /// sessions are bound to their client (`session.bind_client_ip`,
+    /// `session.bind_user_agent`), also `None` if `client` is not the one the
+    /// session was created for.
+    pub async fn get_client_session(
+        &self,
+        session_id: &str,
+        client: &ClientInfo,
+    ) -> Option<Principal> {
         // Get database reference without holding the guard across await
         let database = {
             if let Ok(database_guard) = self.app_state.database.read() {
@@ -451,10 +1132,21 @@ impl AuthState {
                 .await
             {
                 Ok(Some(session_record)) => {
+                    if let Some(binding) = self.client_binding(client)
+                        && session_record.client_binding.as_deref() != Some(binding.as_str())
+                    {
+                        tracing::warn!(
+                            "Rejected session of {}: sent from another client than it is bound to",
+                            session_record.principal.global_id()
+                        );
+                        return None;
+                    }
                     // Check if session is still valid using chrono UTC timestamp
                     if chrono::Utc::now() < session_record.expiry_utc {
                         tracing::debug!("Session found in database: {}", session_id);
-                        return Some(session_record.principal);
+                        return self
+                            .renew_session_if_due(&database, session_id, session_record.principal)
+                            .await;
                     } else {
                         // Session expired, remove it immediately
                         tracing::info!("Session {} expired, removing from database", session_id);
@@ -484,17 +1176,54 @@ impl AuthState {
     /// Creates a new user session.
     ///
     /// Generates a unique session ID, stores the principal with the given TTL,
-    /// and returns the session ID.
+    /// and returns the session ID. When sessions per user are limited, the
+    /// oldest sessions of the principal beyond the limit are ended.
     ///
     /// # Arguments
     ///
     /// * `principal` - The authenticated user principal.
     /// * `ttl` - Time-to-live for the session.
+    /// * `client` - The client signing in; the session is bound to it when
+    ///   sessions are bound to their client.
     ///
     /// # Returns
     ///
     /// The generated session ID.
-    pub async fn put_session(&self, principal: Principal, ttl: Duration) -> String {
+    pub async fn put_client_session(
+        &self,
+        principal: Principal,
+        ttl: Duration,
+        client: &ClientInfo,
+    ) -> String {
+        self.store_session(principal, ttl, self.client_binding(client))
+            .await
+    }
+
+    /// Hash of the attributes of `client` sessions are bound to, or `None`
+    /// when sessions are not bound.
+    fn client_binding(&self, client: &ClientInfo) -> Option<String> {
+        use sha2::{Digest, Sha256};
+        if !self.session_bind_ip && !self.session_bind_user_agent {
+            return None;
+        }
+        let mut hasher = Sha256::new();
+        if self.session_bind_ip {
+            let ip = client.ip.map(|ip| ip.to_string()).unwrap_or_default();
+            hasher.update(format!("ip={ip}\n"));
+        }
+        if self.session_bind_user_agent {
+            let user_agent = client.user_agent.as_deref().unwrap_or_default();
+            hasher.update(format!("user-agent={user_agent}\n"));
+        }
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    async fn store_session(
+        &self,
+        principal: Principal,
+        ttl: Duration,
+        client_binding: Option<String>,
+    ) -> String {
         let session_id = random_urlsafe(32);
 
         // Get database reference without holding the guard across await
@@ -523,17 +1252,228 @@ impl AuthState {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: principal.is_admin,
+                client_binding,
             };
 
             match database.save_session_record_async(session_record).await {
                 Ok(()) => tracing::debug!("Session saved to database: {}", session_id),
                 Err(e) => tracing::warn!("Failed to save session to database: {}", e),
             }
+
+            // Enforce the session limit by ending the oldest sessions
+            if let Some(max) = self.session_max_per_user {
+                let principal_id = principal.global_id();
+                match database
+                    .evict_oldest_sessions_async(&principal_id, max.max(1))
+                    .await
+                {
+                    Ok(evicted) if !evicted.is_empty() => tracing::info!(
+                        "Ended {} oldest session(s) of {}: session limit of {} reached",
+                        evicted.len(),
+                        principal_id,
+                        max
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Failed to enforce the session limit: {}", e),
+                }
+            }
         }
 
         session_id
     }
 
+    /// Creates a session for a user who signed in at an OIDC provider.
+    ///
+    /// When the IdP issued a refresh token and renewal is enabled, the session
+    /// lasts up to `session.max_lifetime_seconds` and is renewed at the IdP
+    /// each time the access token expires; otherwise it lasts
+    /// `session.timeout_seconds`.
+    ///
+    /// # Returns
+    ///
+    /// The session ID and the session lifetime (for the cookie `Max-Age`).
+    pub async fn put_session_with_tokens(
+        &self,
+        principal: Principal,
+        tokens: &TokenResponse,
+        client: &ClientInfo,
+    ) -> (String, Duration) {
+        let refresh_token = match tokens.refresh_token.as_deref() {
+            Some(token) if self.session_refresh && !token.is_empty() => token,
+            _ => {
+                let ttl = self.session_cookies.ttl;
+                return (self.put_client_session(principal, ttl, client).await, ttl);
+            }
+        };
+        let ttl = self.session_max_lifetime;
+        let session_id = self
+            .put_client_session(principal.clone(), ttl, client)
+            .await;
+        if let Err(e) = self
+            .save_refresh_token(&session_id, refresh_token, tokens.expires_in)
+            .await
+        {
+            // Without the refresh token the session must not outlive the access token
+            tracing::warn!(
+                "Failed to store refresh token, session is not renewable: {}",
+                e
+            );
+            self.delete_session(&session_id).await;
+            let ttl = self.session_cookies.ttl;
+            return (self.put_client_session(principal, ttl, client).await, ttl);
+        }
+        (session_id, ttl)
+    }
+
+    /// Seals and stores a session's refresh token, due for renewal when the
+    /// access token issued with it expires.
+    async fn save_refresh_token(
+        &self,
+        session_id: &str,
+        refresh_token: &str,
+        expires_in: Option<u64>,
+    ) -> Result<()> {
+        let database = self
+            .app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|guard| guard.as_ref().cloned())
+            .ok_or_else(|| anyhow!("No database configured"))?;
+        let record = crate::server::persist::SessionRefreshRecord {
+            session_id: session_id.to_string(),
+            refresh_token_enc: self.cipher.seal(refresh_token.as_bytes())?,
+            renew_after_epoch: chrono::Utc::now().timestamp()
+                + expires_in.unwrap_or(DEFAULT_ACCESS_TOKEN_LIFETIME_SECS) as i64,
+        };
+        database.save_session_refresh_async(record).await
+    }
+
+    /// Renews a session at the IdP when its access token has expired.
+    ///
+    /// Sessions without a refresh token, or not yet due, are returned as is.
+    /// When renewal fails (the refresh token was revoked, the user disabled at
+    /// the IdP, or the provider changed) the session is deleted and the user
+    /// has to sign in again.
+    async fn renew_session_if_due(
+        &self,
+        database: &crate::server::persist::Database,
+        session_id: &str,
+        principal: Principal,
+    ) -> Option<Principal> {
+        let now = chrono::Utc::now().timestamp();
+        match database
+            .get_session_refresh_async(session_id.to_string())
+            .await
+        {
+            Ok(Some(record)) if record.renew_after_epoch <= now => {}
+            Ok(_) => return Some(principal),
+            Err(e) => {
+                tracing::warn!("Failed to load refresh token for session: {}", e);
+                return Some(principal);
+            }
+        }
+
+        // Re-check under the lock: a concurrent request may have renewed the
+        // session already, and rotated refresh tokens can only be redeemed once.
+        let _guard = self.renewals.lock().await;
+        let record = match database
+            .get_session_refresh_async(session_id.to_string())
+            .await
+        {
+            Ok(Some(record)) if record.renew_after_epoch <= now => record,
+            Ok(Some(_)) => return Some(principal),
+            _ => return None,
+        };
+
+        match self.refresh_session(&record, &principal).await {
+            Ok(tokens) => {
+                let refresh_token = match tokens.refresh_token.as_deref() {
+                    Some(rotated) if !rotated.is_empty() => rotated.to_string(),
+                    _ => match self.cipher.open(&record.refresh_token_enc) {
+                        Ok(token) => String::from_utf8_lossy(&token).into_owned(),
+                        Err(_) => return None,
+                    },
+                };
+                if let Err(e) = self
+                    .save_refresh_token(session_id, &refresh_token, tokens.expires_in)
+                    .await
+                {
+                    tracing::warn!("Failed to store renewed refresh token: {}", e);
+                }
+                tracing::debug!("Renewed session for {}", principal.global_id());
+                Some(principal)
+            }
+            Err(e) => {
+                tracing::info!(
+                    "Session renewal failed for {}, ending session: {}",
+                    principal.global_id(),
+                    e
+                );
+                if let Err(e) = database.delete_session_async(session_id.to_string()).await {
+                    tracing::warn!("Failed to delete session: {}", e);
+                }
+                None
+            }
+        }
+    }
+
+    /// Redeems a session's refresh token at the active provider. A returned ID
+    /// token is validated and must identify the same user.
+    async fn refresh_session(
+        &self,
+        record: &crate::server::persist::SessionRefreshRecord,
+        principal: &Principal,
+    ) -> Result<TokenResponse> {
+        if !self.session_refresh {
+            return Err(anyhow!("session renewal is disabled"));
+        }
+        let refresh_token = self
+            .cipher
+            .open(&record.refresh_token_enc)
+            .context("refresh token cannot be decrypted (encryption key changed?)")?;
+        let refresh_token = String::from_utf8(refresh_token)?;
+        let provider = self
+            .provider_of_kind(&principal.provider_kind, principal.provider_name.as_deref())
+            .await
+            .filter(|p| p.authority == principal.provider)
+            .ok_or_else(|| anyhow!("the session's provider is no longer active"))?;
+        let token_url = provider
+            .token_endpoint
+            .as_deref()
+            .ok_or_else(|| anyhow!("provider has no token endpoint"))?;
+
+        let tokens = self
+            .refresh_tokens(
+                &refresh_token,
+                &provider.client_id,
+                provider.client_secret.as_deref(),
+                token_url,
+            )
+            .await?;
+
+        if !tokens.id_token.is_empty() {
+            let jwks_uri = provider
+                .jwks_uri
+                .as_deref()
+                .ok_or_else(|| anyhow!("provider has no JWKS endpoint"))?;
+            let jwks: jsonwebtoken::jwk::JwkSet =
+                self.http.get(jwks_uri).send().await?.json().await?;
+            let claims = self
+                .validate_id_token(
+                    &tokens.id_token,
+                    &provider.client_id,
+                    &provider.authority,
+                    &jwks,
+                )
+                .await?;
+            if claims.sub != principal.subject {
+                return Err(anyhow!("renewed ID token is for a different subject"));
+            }
+        }
+        Ok(tokens)
+    }
+
     /// Cleans up expired sessions and pending authentications.
     ///
     /// Should be called periodically to maintain the state size.
@@ -547,8 +1487,12 @@ impl AuthState {
             }
         };
 
-        // Clean up database sessions
-        if let Some(database) = database {
+        // Clean up database sessions, unless another instance does
+        if let Some(database) = database
+            && crate::server::lease::SESSION_CLEANUP
+                .acquire(&database)
+                .await
+        {
             match database.cleanup_expired_sessions_async().await {
                 Ok(count) => {
                     if count > 0 {
@@ -559,6 +1503,13 @@ impl AuthState {
                     tracing::warn!("Failed to cleanup expired sessions: {}", e);
                 }
             }
+            if let Some(retention) = self.audit_retention {
+                match database.purge_auth_events_async(retention).await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::debug!("Purged {} old authentication events", count),
+                    Err(e) => tracing::warn!("Failed to purge authentication events: {}", e),
+                }
+            }
         }
 
         // Clean up in-memory state
@@ -574,6 +1525,14 @@ impl AuthState {
 
         let mut access_tokens = self.access_tokens.write().await;
         access_tokens.retain(|_, (_, expires_at, _)| *expires_at > system_now);
+        drop(access_tokens);
+
+        let mut device_codes = self.device_codes.write().await;
+        device_codes.retain(|_, device| device.expires_at > system_now);
+        drop(device_codes);
+
+        self.login_limiter.purge();
+        self.failed_key_audits.purge();
     }
 
     /// Removes a user session.
@@ -604,6 +1563,301 @@ impl AuthState {
         false
     }
 
+    /// Returns the principal of a stored session without renewing it.
+    pub async fn session_owner(&self, session_id: &str) -> Option<Principal> {
+        let database = self
+            .app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|g| g.as_ref().cloned())?;
+        match database
+            .get_session_record_async(session_id.to_string())
+            .await
+        {
+            Ok(record) => record.map(|r| r.principal),
+            Err(e) => {
+                tracing::warn!("Database error retrieving session {}: {}", session_id, e);
+                None
+            }
+        }
+    }
+
+    /// Resolves the client IP and user agent recorded with audit events.
+    pub fn client_info(&self, headers: &HeaderMap, extensions: &Extensions) -> ClientInfo {
+        ClientInfo::new(self.login_limiter.client_ip(headers, extensions), headers)
+    }
+
+    /// Records an authentication event in the audit trail.
+    pub async fn audit(&self, event: AuthEvent) {
+        event.record(&self.app_state).await;
+    }
+
+    /// Looks up an API key by its plaintext token.
+    ///
+    /// Returns `None` if no database is configured, the key is unknown or expired.
+    /// Keys issued to service accounts carry the account's current roles and are
+    /// rejected once the account has been deleted.
+    pub async fn get_api_key(&self, token: &str) -> Option<crate::server::persist::ApiKeyRecord> {
+        let database = self
+            .app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|g| g.as_ref().cloned())?;
+
+        match database
+            .get_api_key_by_hash_async(hash_api_key(token))
+            .await
+        {
+            Ok(Some(mut record)) if !record.is_expired() => {
+                if record.principal.provider_kind == ProviderKind::Service {
+                    let account = database
+                        .get_service_account_async(record.principal.subject.clone())
+                        .await
+                        .ok()
+                        .flatten()?;
+                    record.principal = account.principal();
+                }
+                Some(record)
+            }
+            Ok(Some(record)) => {
+                tracing::debug!("API key {} has expired", record.key_id);
+                None
+            }
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!("Database error retrieving API key: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Authenticates a service account using OAuth client credentials.
+    ///
+    /// Returns the account's principal if the account exists, has client
+    /// credentials enabled and the secret matches; `None` otherwise.
+    pub async fn authenticate_client_credentials(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Option<Principal> {
+        let database = self
+            .app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|g| g.as_ref().cloned())?;
+
+        let account = match database
+            .get_service_account_async(client_id.to_string())
+            .await
+        {
+            Ok(account) => account?,
+            Err(e) => {
+                tracing::warn!("Database error retrieving service account: {}", e);
+                return None;
+            }
+        };
+        let expected = account.secret_hash.as_deref()?;
+        client_secret_matches(expected, client_secret).then(|| account.principal())
+    }
+
+    /// Looks up an OAuth client registered through dynamic client registration.
+    ///
+    /// Returns `None` if no database is configured or the client is unknown.
+    pub async fn registered_client(
+        &self,
+        client_id: &str,
+    ) -> Option<crate::server::persist::OAuthClientRecord> {
+        let database = self
+            .app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|g| g.as_ref().cloned())?;
+
+        match database.get_oauth_client_async(client_id.to_string()).await {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::warn!("Database error retrieving OAuth client: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Authenticates a local account by username and password.
+    ///
+    /// Returns the user's principal when the user exists, the password
+    /// matches and the user passes the `groups.users` restriction of
+    /// `provider`. Unknown users are verified against a dummy hash so response
+    /// timing does not reveal which usernames exist.
+    async fn authenticate_local(
+        &self,
+        provider: &ResolvedProvider,
+        username: &str,
+        password: &str,
+    ) -> Option<Principal> {
+        let database = self
+            .app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|g| g.as_ref().cloned())?;
+
+        let user = match database.get_user_async(username.to_string()).await {
+            Ok(user) => user,
+            Err(e) => {
+                tracing::warn!("Database error retrieving user: {}", e);
+                return None;
+            }
+        };
+        let hash = user
+            .as_ref()
+            .map(|u| u.password_hash.clone())
+            .unwrap_or_else(|| dummy_password_hash().to_string());
+        let password = password.to_string();
+        let valid = tokio::task::spawn_blocking(move || verify_password(&password, &hash))
+            .await
+            .unwrap_or(false);
+        let user = user.filter(|_| valid)?;
+
+        let mut principal = Principal {
+            subject: user.username.clone(),
+            email: user.email.clone(),
+            name: user.display_name.clone(),
+            picture: None,
+            provider: LOCAL_PROVIDER_AUTHORITY.to_string(),
+            provider_kind: ProviderKind::Local,
+            provider_name: None,
+            tenant_id: None,
+            oid: None,
+            groups: user.groups.clone(),
+            roles: vec![Role::User],
+            is_admin: false,
+        };
+        let admin_group = provider.groups.as_ref().and_then(|g| g.admin.as_ref());
+        if user.is_admin || admin_group.is_some_and(|g| principal.groups.contains(g)) {
+            principal.roles.push(Role::Admin);
+            principal.is_admin = true;
+        }
+        if let Some(groups) = provider.groups.as_ref() {
+            crate::server::roles::apply_role_mappings(&mut principal, groups);
+        }
+        if !principal.is_admin
+            && let Some(users_group) = provider.groups.as_ref().and_then(|g| g.users.as_ref())
+            && !principal.groups.contains(users_group)
+        {
+            tracing::warn!(
+                "Access denied: local user {} not in required users group '{}'",
+                principal.subject,
+                users_group
+            );
+            return None;
+        }
+        Some(principal)
+    }
+
+    /// Authenticates a directory user by username and password.
+    ///
+    /// Returns the user's principal when the directory of `provider` accepts
+    /// the credentials and the user passes its `groups.users` restriction.
+    /// Usernames are case-insensitive, as in most directories, and normalized
+    /// to lowercase for the subject.
+    async fn authenticate_ldap(
+        &self,
+        provider: &ResolvedProvider,
+        username: &str,
+        password: &str,
+    ) -> Option<Principal> {
+        let directory = provider.ldap.clone()?;
+        let username = username.trim().to_lowercase();
+        let user = match directory.authenticate(&username, password).await {
+            Ok(user) => user?,
+            Err(e) => {
+                tracing::warn!("LDAP authentication error: {:#}", e);
+                return None;
+            }
+        };
+
+        let mut principal = Principal {
+            subject: username,
+            email: user.email,
+            name: user.name,
+            picture: None,
+            provider: directory.url().to_string(),
+            provider_kind: ProviderKind::Ldap,
+            provider_name: None,
+            tenant_id: None,
+            oid: Some(user.dn),
+            groups: user.groups,
+            roles: vec![Role::User],
+            is_admin: false,
+        };
+        crate::server::roles::assign_group_roles(&mut principal, provider.groups.as_ref())
+            .then_some(principal)
+    }
+
+    /// Authenticates username/password credentials with the active provider
+    /// of `kind` (`local` or `ldap`) named `name`, or the first one of `kind`
+    /// when `name` is `None`.
+    pub async fn authenticate_password(
+        &self,
+        kind: &ProviderKind,
+        name: Option<&str>,
+        username: &str,
+        password: &str,
+    ) -> Option<Principal> {
+        let active = self.active_of_kind(kind, name)?;
+        let provider = active.resolved.read().await.clone()?;
+        let mut principal = match kind {
+            ProviderKind::Local => self.authenticate_local(&provider, username, password).await,
+            ProviderKind::Ldap => self.authenticate_ldap(&provider, username, password).await,
+            _ => None,
+        }?;
+        principal.provider_name = Some(active.name.clone());
+        Some(principal)
+    }
+
+    /// Creates the initial local administrator if the users table is empty.
+    ///
+    /// Does nothing unless the `local` provider is active and a database is
+    /// configured, so it is safe to call unconditionally at startup.
+    pub async fn bootstrap_local_admin(&self, username: &str, password: &str) -> Result<()> {
+        let is_local = self.providers.iter().any(|p| p.kind == ProviderKind::Local);
+        let database = self
+            .app_state
+            .database
+            .read()
+            .ok()
+            .and_then(|g| g.as_ref().cloned());
+        let Some(database) = database.filter(|_| is_local) else {
+            return Ok(());
+        };
+        if !database.list_users_async().await?.is_empty() {
+            return Ok(());
+        }
+        if password.len() < MIN_PASSWORD_LEN {
+            return Err(anyhow!(
+                "Initial local admin password must be at least {} characters",
+                MIN_PASSWORD_LEN
+            ));
+        }
+        database
+            .save_user_async(crate::server::persist::UserRecord {
+                username: username.to_string(),
+                password_hash: hash_password(password)?,
+                display_name: None,
+                email: None,
+                groups: Vec::new(),
+                is_admin: true,
+                created_utc: chrono::Utc::now(),
+            })
+            .await?;
+        tracing::info!("Created initial local admin user '{}'", username);
+        Ok(())
+    }
+
     /// Exchanges an authorization code for an ID token and access token.
     pub async fn exchange_code_for_tokens(
         &self,
@@ -640,6 +1894,33 @@ impl AuthState {
         Ok(token_response)
     }
 
+    /// Exchanges a refresh token for new tokens (`grant_type=refresh_token`).
+    pub async fn refresh_tokens(
+        &self,
+        refresh_token: &str,
+        client_id: &str,
+        client_secret: Option<&str>,
+        token_url: &str,
+    ) -> Result<TokenResponse, anyhow::Error> {
+        let mut params = HashMap::new();
+        params.insert("grant_type", "refresh_token");
+        params.insert("refresh_token", refresh_token);
+        params.insert("client_id", client_id);
+        if let Some(secret) = client_secret {
+            params.insert("client_secret", secret);
+        }
+
+        let response = self.http.post(token_url).form(&params).send().await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Failed to refresh token: {}", error_text));
+        }
+
+        let token_response: TokenResponse = response.json().await?;
+        Ok(token_response)
+    }
+
     /// Validates the ID token's signature and claims.
     pub async fn validate_id_token(
         &self,
@@ -665,7 +1946,14 @@ impl AuthState {
         validation.set_audience(&[client_id]);
         validation.set_issuer(&[issuer_url]);
 
-        let token_data = decode::<IdTokenClaims>(id_token, &decoding_key, &validation)?;
+        let token_data = decode::<serde_json::Map<String, serde_json::Value>>(
+            id_token,
+            &decoding_key,
+            &validation,
+        )?;
+        let mut claims: IdTokenClaims =
+            serde_json::from_value(serde_json::Value::Object(token_data.claims.clone()))?;
+        claims.raw = token_data.claims;
 
         // Additional security: check token is not too old (max 1 hour)
         let now = std::time::SystemTime::now()
@@ -673,17 +1961,17 @@ impl AuthState {
             .unwrap_or_default()
             .as_secs();
 
-        if now > token_data.claims.exp {
+        if now > claims.exp {
             return Err(anyhow::anyhow!("ID token has expired"));
         }
 
-        if now < token_data.claims.iat || (now - token_data.claims.iat) > 3600 {
+        if now < claims.iat || (now - claims.iat) > 3600 {
             return Err(anyhow::anyhow!(
                 "ID token issued time is invalid or too old"
             ));
         }
 
-        Ok(token_data.claims)
+        Ok(claims)
     }
 
     /// Fetches a user's profile photo from Microsoft Graph API.
@@ -750,7 +2038,7 @@ impl AuthState {
 ///
 /// The response from the next middleware or an authentication error.
 pub async fn check_auth(
-    req: Request,
+    mut req: Request,
     next: Next,
     Extension(auth): Extension<Arc<AuthState>>,
 ) -> Response {
@@ -758,6 +2046,10 @@ pub async fn check_auth(
         return next.run(req).await;
     }
 
+    // Handlers that record audit events read the client from here
+    let client = auth.client_info(req.headers(), req.extensions());
+    req.extensions_mut().insert(client.clone());
+
     let path = req.uri().path();
 
     // Check if this path requires authentication
@@ -768,32 +2060,118 @@ pub async fn check_auth(
     // Try session cookie first
     if let Some(cookie_header) = req.headers().get(header::COOKIE)
         && let Ok(cookie_str) = cookie_header.to_str()
-        && let Some(principal) = extract_session_user_from_cookie(&auth, cookie_str).await
+        && let Some(principal) = extract_client_session_user(&auth, cookie_str, &client).await
     {
         tracing::debug!("Authenticated user via session: {}", principal.global_id());
 
-        // Check if this path requires admin privileges
-        if path_requires_admin(path) && !principal.is_admin {
+        // Check if this path requires admin privileges or a permission
+        if let Some(denied) = deny_path(&auth, &principal, path) {
+            return denied;
+        }
+
+        // Browsers attach the cookie to cross-site requests as well, so
+        // state-changing API calls must echo the session's CSRF token
+        if path.starts_with("/api")
+            && csrf::is_state_changing(req.method())
+            && !csrf::session_id(req.headers())
+                .is_some_and(|sid| csrf::verify_header(sid, req.headers()))
+        {
             tracing::warn!(
-                "Access denied: user {} is not an admin",
+                "Rejected {} {} from {}: missing or invalid CSRF token",
+                req.method(),
+                path,
                 principal.global_id()
             );
-            return (StatusCode::FORBIDDEN, "Admin privileges required").into_response();
+            return reject(
+                StatusCode::FORBIDDEN,
+                ErrorCode::CsrfInvalid,
+                "Missing or invalid CSRF token",
+            );
         }
 
-        let mut req = req;
         // Make principal available to downstream handlers
         req.extensions_mut().insert(principal);
         return next.run(req).await;
     }
 
-    // Try OAuth bearer token for /mcp endpoints
-    if path.starts_with("/mcp")
-        && let Some(auth_header) = req.headers().get(header::AUTHORIZATION)
+    // Try API key (personal access token) for the management API and /mcp
+    let api_key = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .filter(|t| t.starts_with(API_KEY_PREFIX))
+        .map(|t| t.to_string());
+    if let Some(token) = api_key {
+        let required_scope = if path.starts_with("/mcp") {
+            API_KEY_SCOPE_MCP
+        } else {
+            API_KEY_SCOPE_API
+        };
+        let Some(record) = auth.get_api_key(&token).await else {
+            tracing::debug!("Invalid or expired API key for path: {}", path);
+            // A client retrying a bad key is recorded once per interval
+            if let Some(skipped) = auth.failed_key_audits.admit(&client) {
+                let detail = match skipped {
+                    0 => "invalid or expired API key".to_string(),
+                    n => format!("invalid or expired API key ({n} more not recorded)"),
+                };
+                auth.audit(AuthEvent::new(AuthEventKind::LoginFailed, &client).detail(detail))
+                    .await;
+            }
+            return reject(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::InvalidCredentials,
+                "Invalid or expired API key",
+            );
+        };
+        if !record.scopes.iter().any(|s| s == required_scope) {
+            tracing::warn!(
+                "API key {} lacks scope '{}' for path {}",
+                record.key_id,
+                required_scope,
+                path
+            );
+            return reject(
+                StatusCode::FORBIDDEN,
+                ErrorCode::ScopeDenied,
+                "API key scope does not permit this endpoint",
+            );
+        }
+        let principal = record.principal;
+        tracing::debug!(
+            "Authenticated user via API key {}: {}",
+            record.key_id,
+            principal.global_id()
+        );
+
+        if let Some(denied) = deny_path(&auth, &principal, path) {
+            return denied;
+        }
+        if let Some(denied) = restrict_tools(&mut req, record.scopes.iter().map(String::as_str)) {
+            return denied;
+        }
+
+        req.extensions_mut().insert(principal);
+        return next.run(req).await;
+    }
+
+    // Try OAuth bearer token: tokens granted the "mcp" scope may call /mcp, and
+    // those granted the "api" scope (e.g. service account client credentials)
+    // the management API.
+    let required_scope = if path.starts_with("/mcp") {
+        API_KEY_SCOPE_MCP
+    } else {
+        API_KEY_SCOPE_API
+    };
+    if let Some(auth_header) = req.headers().get(header::AUTHORIZATION)
         && let Ok(auth_str) = auth_header.to_str()
         && let Some(token) = auth_str.strip_prefix("Bearer ")
-        && let Some((principal, expires_at, _scope)) =
+        && let Some((principal, expires_at, scope)) =
             auth.access_tokens.read().await.get(token).cloned()
+        && scope
+            .as_deref()
+            .is_some_and(|s| s.split_whitespace().any(|s| s == required_scope))
     {
         // Check if token is still valid
         if SystemTime::now() <= expires_at {
@@ -802,16 +2180,15 @@ pub async fn check_auth(
                 principal.global_id()
             );
 
-            // Check if this path requires admin privileges
-            if path_requires_admin(path) && !principal.is_admin {
-                tracing::warn!(
-                    "Access denied: user {} is not an admin (OAuth token)",
-                    principal.global_id()
-                );
-                return (StatusCode::FORBIDDEN, "Admin privileges required").into_response();
+            // Check if this path requires admin privileges or a permission
+            if let Some(denied) = deny_path(&auth, &principal, path) {
+                return denied;
+            }
+            let scopes = scope.as_deref().unwrap_or_default().split_whitespace();
+            if let Some(denied) = restrict_tools(&mut req, scopes) {
+                return denied;
             }
 
-            let mut req = req;
             req.extensions_mut().insert(principal);
             return next.run(req).await;
         } else {
@@ -828,7 +2205,16 @@ pub async fn check_auth(
 
     // Authentication required but not provided
     tracing::debug!("Authentication required for path: {}", path);
-    (StatusCode::UNAUTHORIZED, "Authentication required").into_response()
+    reject(
+        StatusCode::UNAUTHORIZED,
+        ErrorCode::Unauthenticated,
+        "Authentication required",
+    )
+}
+
+/// Rejects a request with an API error body carrying `code`.
+fn reject(status: StatusCode, code: ErrorCode, message: &str) -> Response {
+    (status, StandardizedResponse::as_error(code, message, None)).into_response()
 }
 
 /// Determines if a request path requires authentication.
@@ -847,6 +2233,8 @@ pub fn path_requires_auth(path: &str) -> bool {
     let public_paths = [
         "/",
         "/authorize",
+        "/device",
+        "/register",
         "/.well-known/openid-configuration",
         "/.well-known/jwks.json",
         "/.well-known/oauth-authorization-server",
@@ -856,11 +2244,15 @@ pub fn path_requires_auth(path: &str) -> bool {
         "/auth/login",
         "/auth/logout",
         "/auth/callback",
+        "/auth/saml",
         "/health",
         "/livez",
         "/readyz",
+        "/startupz",
         "/admin",
+        "/api/openapi.json",
     ];
+    let path = &*crate::server::service::unversioned_api_path(path);
 
     // Check exact match for root path
     if path == "/" {
@@ -885,7 +2277,10 @@ pub fn path_requires_auth(path: &str) -> bool {
 
 /// Determines if a request path requires admin privileges.
 ///
-/// Checks the path against a list of admin-only routes.
+/// Checks the path against a list of admin-only routes. `/metrics` is listed
+/// because only admins hold `metrics.read` under the built-in roles; the auth
+/// middleware evaluates it by permission so custom roles can be granted access.
+/// `/api/v1` paths are checked as their unversioned equivalents.
 ///
 /// # Arguments
 ///
@@ -898,8 +2293,77 @@ pub fn path_requires_admin(path: &str) -> bool {
     // Admin-only paths
     let admin_paths = ["/metrics"];
 
-    // Check exact match
-    admin_paths.contains(&path)
+    // Admin-only path prefixes
+    let admin_prefixes = ["/api/admin/"];
+
+    // Check exact match, then prefixes
+    let path = &*crate::server::service::unversioned_api_path(path);
+    admin_paths.contains(&path) || admin_prefixes.iter().any(|p| path.starts_with(p))
+}
+
+/// Returns a 403 response when the principal may not access `path`.
+///
+/// Paths with a dedicated permission (see [`crate::server::authz::path_permission`])
+/// are checked against the principal's roles; remaining admin paths require
+/// the admin flag.
+fn deny_path(auth: &AuthState, principal: &Principal, path: &str) -> Option<Response> {
+    if let Some(permission) = crate::server::authz::path_permission(path) {
+        if auth.authz.has_permission(principal, permission) {
+            return None;
+        }
+        tracing::warn!(
+            "Access denied: user {} lacks permission '{}'",
+            principal.global_id(),
+            permission
+        );
+        return Some(reject(
+            StatusCode::FORBIDDEN,
+            ErrorCode::PermissionDenied,
+            "Permission denied",
+        ));
+    }
+    if path_requires_admin(path) && !principal.is_admin {
+        tracing::warn!(
+            "Access denied: user {} is not an admin",
+            principal.global_id()
+        );
+        return Some(reject(
+            StatusCode::FORBIDDEN,
+            ErrorCode::PermissionDenied,
+            "Admin privileges required",
+        ));
+    }
+    None
+}
+
+/// Applies the `tool:`/`plugin:` restrictions of an API key or access token.
+///
+/// Scoped credentials get a [`ToolScope`] extension, checked where tools are
+/// executed, and may not make state-changing API calls other than tool
+/// execution (which would let them e.g. mint an unrestricted API key).
+fn restrict_tools<'a>(
+    req: &mut Request,
+    scopes: impl IntoIterator<Item = &'a str>,
+) -> Option<Response> {
+    let scope = ToolScope::from_scopes(scopes)?;
+    let path = req.uri().path();
+    if path.starts_with("/api")
+        && csrf::is_state_changing(req.method())
+        && !authz::is_tool_execution(req.method(), path)
+    {
+        tracing::warn!(
+            "Rejected {} {}: credential is restricted to tool execution",
+            req.method(),
+            path
+        );
+        return Some(reject(
+            StatusCode::FORBIDDEN,
+            ErrorCode::ScopeDenied,
+            "Token scope only permits tool execution",
+        ));
+    }
+    req.extensions_mut().insert(scope);
+    None
 }
 
 // ------------------------- Helper Functions -------------------------
@@ -924,25 +2388,81 @@ fn random_urlsafe(bytes: usize) -> String {
     URL_SAFE_NO_PAD.encode(buf)
 }
 
-/// Extracts session user from cookie string.
-///
-/// Parses the cookie header and looks up the session ID.
-///
-/// # Arguments
-///
-/// * `state` - Authentication state.
-/// * `cookie_str` - Cookie header string.
+/// Generates a new API key token (`ark_` followed by 256 random bits).
+pub fn generate_api_key() -> String {
+    format!("{}{}", API_KEY_PREFIX, random_urlsafe(32))
+}
+
+/// Generates a new service account client secret (256 random bits).
+pub fn generate_client_secret() -> String {
+    random_urlsafe(32)
+}
+
+/// Hashes a local account password with argon2 (PHC string format).
+pub fn hash_password(password: &str) -> Result<String> {
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    let mut salt = [0u8; 16];
+    OsRng
+        .try_fill_bytes(&mut salt)
+        .expect("OsRng failed to produce random bytes");
+    let salt = SaltString::encode_b64(&salt).map_err(|e| anyhow!("encoding salt: {}", e))?;
+    argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| anyhow!("hashing password: {}", e))
+}
+
+/// Verifies a password against an argon2 PHC string; malformed hashes never match.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+    PasswordHash::new(password_hash).is_ok_and(|parsed| {
+        argon2::Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok()
+    })
+}
+
+/// Hash checked for unknown usernames so failed logins take constant time.
+fn dummy_password_hash() -> &'static str {
+    static DUMMY: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    DUMMY.get_or_init(|| hash_password(&random_urlsafe(16)).unwrap_or_default())
+}
+
+/// Checks a client secret against its stored hash in constant time.
+pub fn client_secret_matches(secret_hash: &str, client_secret: &str) -> bool {
+    let presented = hash_api_key(client_secret);
+    secret_hash.len() == presented.len()
+        && secret_hash
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Hashes an API key token for storage and lookup (hex-encoded SHA-256).
 ///
-/// # Returns
+/// Also used for service account client secrets, which are equally high-entropy.
+pub fn hash_api_key(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Extracts session user from cookie string.
 ///
-/// `Some(Principal)` if a valid session is found, `None` otherwise.
-pub async fn extract_session_user_from_cookie(
+/// Extracts the user principal from a session cookie sent by `client`,
+/// rejecting sessions bound to another client.
+pub async fn extract_client_session_user(
     state: &AuthState,
     cookie_str: &str,
+    client: &ClientInfo,
 ) -> Option<Principal> {
-    let id = cookie_str
+    state
+        .get_client_session(session_cookie(cookie_str)?, client)
+        .await
+}
+
+fn session_cookie(cookie_str: &str) -> Option<&str> {
+    cookie_str
         .split(';')
         .find_map(|p| p.trim().strip_prefix("ark_session="))
-        .map(|s| s.to_string())?;
-    state.get_session(&id).await
 }
@@ -0,0 +1,314 @@
+//! Permission model and role-based authorization.
+//!
+//! Roles (see [`Role`]) map to a set of explicit [`Permission`]s. The built-in
+//! `Admin` role holds every permission; `User` defaults to creating plugins,
+//! executing tools and using the console. Further roles, or a narrower `User`,
+//! are declared under `auth.roles`:
+//!
+//! ```yaml
+//! auth:
+//!   roles:
+//!     - name: operator
+//!       permissions: [tool.execute, metrics.read, console.access]
+//! ```
+//!
+//! Handlers ask [`allows`] whether the caller holds a permission; ownership
+//! rules (a user only sees their own and public plugins) still apply on top.
+//!
+//! API keys and client-credentials tokens may further be restricted to a set
+//! of tools with `tool:<name>` and `plugin:<id>` scopes (see [`ToolScope`]).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::models::RoleConfig;
+use crate::server::{
+    auth::{API_KEY_SCOPE_API, API_KEY_SCOPE_MCP, Principal},
+    roles::Role,
+};
+use crate::state::ArkState;
+
+/// An individual action a principal may be allowed to perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    /// Register new plugins (owned by the caller).
+    #[serde(rename = "plugin.create")]
+    PluginCreate,
+    /// Unregister any plugin, including public and other users' plugins.
+    #[serde(rename = "plugin.delete.any")]
+    PluginDeleteAny,
+    /// Execute tools, via the management API or MCP.
+    #[serde(rename = "tool.execute")]
+    ToolExecute,
+    /// Read the Prometheus `/metrics` endpoint.
+    #[serde(rename = "metrics.read")]
+    MetricsRead,
+    /// Use the admin console.
+    #[serde(rename = "console.access")]
+    ConsoleAccess,
+}
+
+impl Permission {
+    /// Every permission, in declaration order.
+    pub const ALL: [Permission; 5] = [
+        Permission::PluginCreate,
+        Permission::PluginDeleteAny,
+        Permission::ToolExecute,
+        Permission::MetricsRead,
+        Permission::ConsoleAccess,
+    ];
+
+    /// Returns the permission name as used in configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::PluginCreate => "plugin.create",
+            Permission::PluginDeleteAny => "plugin.delete.any",
+            Permission::ToolExecute => "tool.execute",
+            Permission::MetricsRead => "metrics.read",
+            Permission::ConsoleAccess => "console.access",
+        }
+    }
+
+    /// Parses a configuration permission name.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == name)
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Resolves principals' roles to permissions.
+#[derive(Debug, Clone)]
+pub struct Authorizer {
+    roles: HashMap<Role, HashSet<Permission>>,
+}
+
+impl Default for Authorizer {
+    /// Built-in roles only: `Admin` with every permission, and `User` with
+    /// `plugin.create`, `tool.execute` and `console.access`.
+    fn default() -> Self {
+        let mut roles = HashMap::new();
+        roles.insert(Role::Admin, Permission::ALL.into_iter().collect());
+        roles.insert(
+            Role::User,
+            [
+                Permission::PluginCreate,
+                Permission::ToolExecute,
+                Permission::ConsoleAccess,
+            ]
+            .into_iter()
+            .collect(),
+        );
+        Self { roles }
+    }
+}
+
+impl Authorizer {
+    /// Builds an authorizer from `auth.roles`, on top of the built-in roles.
+    ///
+    /// Fails on unknown permission names, duplicate definitions, or an
+    /// attempt to redefine `Admin`.
+    pub fn from_config(roles: &[RoleConfig]) -> Result<Self> {
+        let mut authz = Self::default();
+        let mut seen = HashSet::new();
+        for def in roles {
+            let role = Role::from(def.name.trim());
+            if role.as_str().is_empty() {
+                bail!("Role names must not be empty");
+            }
+            if role == Role::Admin {
+                bail!("The built-in Admin role cannot be redefined");
+            }
+            if !seen.insert(role.clone()) {
+                bail!("Role '{}' is defined more than once", role);
+            }
+            let permissions = def
+                .permissions
+                .iter()
+                .map(|name| {
+                    Permission::parse(name).ok_or_else(|| {
+                        anyhow::anyhow!("Unknown permission '{}' in role '{}'", name, role)
+                    })
+                })
+                .collect::<Result<HashSet<_>>>()?;
+            authz.roles.insert(role, permissions);
+        }
+        Ok(authz)
+    }
+
+    /// Returns whether `role` is built in or declared in the configuration.
+    pub fn is_defined(&self, role: &Role) -> bool {
+        self.roles.contains_key(role)
+    }
+
+    /// Returns the union of permissions granted by the principal's roles.
+    ///
+    /// Admins hold every permission regardless of the roles they carry.
+    /// Roles that are not defined (e.g. removed from the configuration)
+    /// grant nothing.
+    pub fn permissions(&self, principal: &Principal) -> HashSet<Permission> {
+        if principal.is_admin {
+            return Permission::ALL.into_iter().collect();
+        }
+        principal
+            .roles
+            .iter()
+            .filter_map(|r| self.roles.get(r))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Returns whether the principal holds `permission`.
+    pub fn has_permission(&self, principal: &Principal, permission: Permission) -> bool {
+        principal.is_admin
+            || principal
+                .roles
+                .iter()
+                .any(|r| self.roles.get(r).is_some_and(|p| p.contains(&permission)))
+    }
+}
+
+/// Returns the authorizer configured on the application's auth state, or the
+/// built-in roles when no auth state is attached.
+pub fn authorizer(state: &ArkState) -> Arc<Authorizer> {
+    state
+        .auth_state
+        .read()
+        .ok()
+        .and_then(|g| g.as_ref().map(|a| a.authz.clone()))
+        .unwrap_or_default()
+}
+
+/// Checks whether the caller may perform `permission`.
+///
+/// Requests without a principal are only possible when authentication is
+/// disabled (the auth middleware rejects them otherwise), so they are allowed.
+pub fn allows(state: &ArkState, principal: Option<&Principal>, permission: Permission) -> bool {
+    match principal {
+        Some(p) => {
+            let allowed = authorizer(state).has_permission(p, permission);
+            if !allowed {
+                tracing::debug!(
+                    "Permission '{}' denied for {} (roles: {:?})",
+                    permission,
+                    p.global_id(),
+                    p.roles
+                );
+            }
+            allowed
+        }
+        None => true,
+    }
+}
+
+/// Scope prefix restricting a credential to one tool, e.g. `tool:echo`.
+pub const TOOL_SCOPE_PREFIX: &str = "tool:";
+/// Scope prefix restricting a credential to every tool of one plugin.
+pub const PLUGIN_SCOPE_PREFIX: &str = "plugin:";
+
+/// Tools a scoped API key or access token may execute.
+///
+/// Inserted as a request extension by the auth middleware when the
+/// credential carries `tool:` or `plugin:` scopes; absent for unrestricted
+/// credentials. Tool execution via the API and MCP checks it, and such
+/// credentials may not make any other state-changing API call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolScope {
+    tools: HashSet<String>,
+    plugins: HashSet<String>,
+}
+
+impl ToolScope {
+    /// Returns whether `scope` is a well-formed `tool:` or `plugin:` entry.
+    pub fn is_scope_entry(scope: &str) -> bool {
+        scope
+            .strip_prefix(TOOL_SCOPE_PREFIX)
+            .or_else(|| scope.strip_prefix(PLUGIN_SCOPE_PREFIX))
+            .is_some_and(|name| !name.is_empty())
+    }
+
+    /// Collects the tool restrictions from a credential's scopes, or `None`
+    /// when it has none and may execute every tool.
+    pub fn from_scopes<'a>(scopes: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut scope = Self::default();
+        for s in scopes {
+            if let Some(tool) = s.strip_prefix(TOOL_SCOPE_PREFIX) {
+                scope.tools.insert(tool.to_string());
+            } else if let Some(plugin) = s.strip_prefix(PLUGIN_SCOPE_PREFIX) {
+                scope.plugins.insert(plugin.to_string());
+            }
+        }
+        (!scope.tools.is_empty() || !scope.plugins.is_empty()).then_some(scope)
+    }
+
+    /// Returns whether `tool`, provided by `plugin_id`, may be executed.
+    pub fn allows(&self, plugin_id: Option<&str>, tool: &str) -> bool {
+        self.tools.contains(tool) || plugin_id.is_some_and(|p| self.plugins.contains(p))
+    }
+}
+
+/// Returns whether `scopes` is a valid scope set for an API key or a
+/// client-credentials token: at least one of `api` and `mcp`, optionally
+/// narrowed by `tool:`/`plugin:` entries.
+pub fn valid_credential_scopes<'a>(scopes: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut has_endpoint = false;
+    for s in scopes {
+        if s == API_KEY_SCOPE_API || s == API_KEY_SCOPE_MCP {
+            has_endpoint = true;
+        } else if !ToolScope::is_scope_entry(s) {
+            return false;
+        }
+    }
+    has_endpoint
+}
+
+/// Returns whether the request executes a tool through the management API
+/// (`POST /api/plugins/{id}/tools/{tool_id}`, also under `/api/v1`).
+pub fn is_tool_execution(method: &axum::http::Method, path: &str) -> bool {
+    method == axum::http::Method::POST
+        && crate::server::service::unversioned_api_path(path)
+            .strip_prefix("/api/plugins/")
+            .and_then(|rest| rest.split_once("/tools/"))
+            .is_some_and(|(id, tool)| {
+                !id.is_empty() && !id.contains('/') && !tool.is_empty() && !tool.contains('/')
+            })
+}
+
+/// Returns the permission required for a request path, if any.
+///
+/// Checked by the auth middleware in addition to the admin-only prefixes.
+pub fn path_permission(path: &str) -> Option<Permission> {
+    match path {
+        "/metrics" => Some(Permission::MetricsRead),
+        _ => None,
+    }
+}
+
+tokio::task_local! {
+    /// Caller of the tool being executed by the current task.
+    static CALLER: Option<Principal>;
+}
+
+/// Runs the tool call `future` on behalf of `principal`, so tools can check
+/// who called them with [`caller_is_admin`].
+pub async fn with_caller<F: Future>(principal: Option<Principal>, future: F) -> F::Output {
+    CALLER.scope(principal, future).await
+}
+
+/// Whether the current tool call comes from an administrator. Calls without
+/// a principal, only possible with authentication disabled, count as an
+/// administrator's, as they do for the management API; code not running a
+/// tool call is not trusted.
+pub fn caller_is_admin() -> bool {
+    CALLER
+        .try_with(|caller| caller.as_ref().is_none_or(|p| p.is_admin))
+        .unwrap_or(false)
+}
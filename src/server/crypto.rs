@@ -0,0 +1,96 @@
+//! Symmetric encryption for secrets persisted in the database.
+//!
+//! Values are sealed with AES-256-GCM under a random 96-bit nonce and stored
+//! as base64 `nonce || ciphertext`.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Result, anyhow, bail};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use rand::TryRngCore;
+use rand::rngs::OsRng;
+
+const NONCE_LEN: usize = 12;
+
+/// Environment variable holding the base64-encoded 32-byte key.
+pub const ENCRYPTION_KEY_ENV: &str = "ARK_SESSION_ENCRYPTION_KEY";
+
+/// AES-256-GCM cipher used to seal and open stored secrets.
+#[derive(Clone)]
+pub struct Cipher {
+    cipher: Aes256Gcm,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cipher")
+    }
+}
+
+impl Cipher {
+    /// Creates a cipher from a base64-encoded 32-byte key.
+    pub fn from_base64(key: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(key.trim())
+            .map_err(|e| anyhow!("Encryption key is not valid base64: {}", e))?;
+        if bytes.len() != 32 {
+            bail!("Encryption key must be 32 bytes, got {}", bytes.len());
+        }
+        Ok(Self {
+            cipher: Aes256Gcm::new_from_slice(&bytes).map_err(|_| anyhow!("Invalid key length"))?,
+        })
+    }
+
+    /// Creates a cipher from the configured session key, or from
+    /// [`ENCRYPTION_KEY_ENV`] when none is configured. `None` when neither is set.
+    pub fn from_session_key(key: Option<&str>) -> Result<Option<Self>> {
+        match key {
+            Some(key) => Self::from_base64(key).map(Some),
+            None => std::env::var(ENCRYPTION_KEY_ENV)
+                .ok()
+                .map(|key| Self::from_base64(&key))
+                .transpose(),
+        }
+    }
+
+    /// Creates a cipher with a random key that only lives as long as the process.
+    pub fn ephemeral() -> Self {
+        let mut key = [0u8; 32];
+        OsRng
+            .try_fill_bytes(&mut key)
+            .expect("OsRng failed to produce random bytes");
+        Self {
+            cipher: Aes256Gcm::new(&key.into()),
+        }
+    }
+
+    /// Encrypts a value, returning base64 `nonce || ciphertext`.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng
+            .try_fill_bytes(&mut nonce)
+            .map_err(|e| anyhow!("OsRng failed to produce random bytes: {}", e))?;
+        let ciphertext = self
+            .cipher
+            .encrypt(&Nonce::from(nonce), plaintext)
+            .map_err(|_| anyhow!("Encryption failed"))?;
+        Ok(STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+    }
+
+    /// Decrypts a value produced by [`Cipher::seal`]. Fails when the value was
+    /// sealed under another key or has been tampered with.
+    pub fn open(&self, sealed: &str) -> Result<Vec<u8>> {
+        let bytes = STANDARD
+            .decode(sealed)
+            .map_err(|e| anyhow!("Sealed value is not valid base64: {}", e))?;
+        if bytes.len() <= NONCE_LEN {
+            bail!("Sealed value is too short");
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into()?;
+        self.cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| anyhow!("Decryption failed"))
+    }
+}
@@ -0,0 +1,60 @@
+//! CSRF protection for cookie-authenticated requests.
+//!
+//! The token is derived from the session id, so it needs no server-side state
+//! and cannot be forged without the (HttpOnly) session cookie. It is handed to
+//! the console in a script-readable `ark_csrf` cookie and must be echoed in the
+//! `X-CSRF-Token` header (or a `csrf_token` form field) on state-changing
+//! requests.
+
+use axum::http::{HeaderMap, Method, header};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use sha2::{Digest, Sha256};
+
+/// Cookie carrying the CSRF token for the console.
+pub const CSRF_COOKIE: &str = "ark_csrf";
+/// Header the console echoes the token in.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Returns the CSRF token bound to a session.
+pub fn token_for_session(session_id: &str) -> String {
+    let digest = Sha256::digest(format!("ark-csrf:{}", session_id).as_bytes());
+    URL_SAFE_NO_PAD.encode(digest)
+}
+
+/// Whether requests with this method change state and need a token.
+pub fn is_state_changing(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Returns the session id from the `ark_session` cookie, if any.
+pub fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|p| p.trim().strip_prefix("ark_session="))
+}
+
+/// Checks a presented token against the one bound to the session.
+pub fn verify(session_id: &str, presented: Option<&str>) -> bool {
+    let Some(presented) = presented else {
+        return false;
+    };
+    let expected = token_for_session(session_id);
+    // Constant-time comparison
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Checks the `X-CSRF-Token` header of a cookie-authenticated request.
+pub fn verify_header(session_id: &str, headers: &HeaderMap) -> bool {
+    verify(
+        session_id,
+        headers.get(CSRF_HEADER).and_then(|v| v.to_str().ok()),
+    )
+}
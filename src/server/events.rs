@@ -0,0 +1,114 @@
+//! Live server events for the admin console.
+//!
+//! Plugin registrations, tool executions, authentication events and lifecycle
+//! state changes are published on a broadcast channel held by [`ArkState`],
+//! and streamed to clients at `GET /api/events` as server-sent events. Events
+//! are not persisted: a client only sees what happens while it is connected,
+//! and one that falls behind by more than [`EVENT_CAPACITY`] events skips the
+//! ones it missed.
+//!
+//! [`ArkState`]: crate::state::ArkState
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::server::auth::Principal;
+
+/// Events buffered for each subscriber before the slowest start skipping.
+pub const EVENT_CAPACITY: usize = 256;
+
+/// An event published to connected console clients.
+///
+/// Serialized with a `type` tag, which is also the SSE event name.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// A plugin was registered or replaced.
+    PluginRegistered {
+        plugin: String,
+        owner: String,
+        tools: Vec<String>,
+    },
+    /// A plugin was unregistered.
+    PluginUnregistered { plugin: String, owner: String },
+    /// A tool call completed, over MCP or the plugin API.
+    ToolExecuted {
+        plugin: String,
+        tool: String,
+        owner: String,
+        principal: Option<String>,
+        is_error: bool,
+        latency_ms: f64,
+    },
+    /// An authentication event was recorded in the audit trail.
+    Auth {
+        event: String,
+        provider: Option<String>,
+        subject: Option<String>,
+    },
+    /// The application lifecycle state changed.
+    StateChanged { state: String },
+}
+
+impl ServerEvent {
+    /// SSE event name, matching the `type` tag.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServerEvent::PluginRegistered { .. } => "plugin_registered",
+            ServerEvent::PluginUnregistered { .. } => "plugin_unregistered",
+            ServerEvent::ToolExecuted { .. } => "tool_executed",
+            ServerEvent::Auth { .. } => "auth",
+            ServerEvent::StateChanged { .. } => "state_changed",
+        }
+    }
+
+    /// Whether `principal` may see this event.
+    ///
+    /// Without authentication everything is visible. Administrators see every
+    /// event; other users see state changes, events for shared plugins and
+    /// plugins they own, their own tool calls and their own authentication
+    /// events.
+    pub fn is_visible_to(&self, principal: Option<&Principal>) -> bool {
+        let Some(principal) = principal else {
+            return true;
+        };
+        if principal.is_admin {
+            return true;
+        }
+        let gid = principal.global_id();
+        match self {
+            ServerEvent::PluginRegistered { owner, .. }
+            | ServerEvent::PluginUnregistered { owner, .. } => owner == "*/*/*" || *owner == gid,
+            ServerEvent::ToolExecuted {
+                owner, principal, ..
+            } => owner == "*/*/*" || *owner == gid || principal.as_deref() == Some(&gid),
+            ServerEvent::Auth { subject, .. } => subject.as_deref() == Some(&gid),
+            ServerEvent::StateChanged { .. } => true,
+        }
+    }
+}
+
+/// Broadcast channel of [`ServerEvent`]s.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ServerEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl EventBus {
+    /// Sends an event to current subscribers; a no-op when there are none.
+    pub fn publish(&self, event: ServerEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Receives events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.sender.subscribe()
+    }
+}
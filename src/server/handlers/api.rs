@@ -8,23 +8,34 @@
 ///
 /// - `GET /api/plugins` - Get a list of all plugins
 /// - `GET /api/plugins/:id` - Get a specific plugin by ID
+/// - `GET /api/tools?q=` - Search tools across the visible plugins
 /// - `POST /api/plugins` - Register a new plugin
 /// - `DELETE /api/plugins/:id` - Unregister a plugin by ID
+/// - `GET /api/plugins/:id/export` - Export a plugin as a self-contained bundle
+/// - `POST /api/plugins/import` - Register a plugin from an exported bundle
 /// - `POST /api/plugins/:id/tools/:tool_id` - Execute a tool on a plugin
 use axum::{
     Extension, Json,
-    extract::{Path, State},
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, header},
+    response::{IntoResponse, Response},
 };
 use hyper::StatusCode;
+use serde::Deserialize;
 use serde_json::{Value, json};
 
 use std::sync::Arc;
 use std::time::Instant;
 
 use crate::{
-    config::plugins::ArkPlugin, plugins::builtin::BUILTIN_PLUGIN_ID,
-    server::service::StandardizedResponse, state::ArkState,
+    config::plugins::ArkPlugin,
+    errors::ErrorCode,
+    plugins::builtin::BUILTIN_PLUGIN_ID,
+    plugins::bundle::{self, BUNDLE_METADATA_KEY, PluginBundle},
+    server::authz::{self, Permission, ToolScope},
+    server::service::StandardizedResponse,
+    server::usage::{ToolCall, check_quota},
+    state::ArkState,
 };
 
 /// Returns the authenticated caller's global id, if present.
@@ -33,6 +44,16 @@ fn principal_gid(principal: &Option<Extension<crate::server::auth::Principal>>)
     principal.as_ref().map(|p| p.0.global_id())
 }
 
+/// Returns whether the caller holds `permission` (always true without auth).
+#[inline]
+fn caller_allows(
+    state: &ArkState,
+    principal: &Option<Extension<crate::server::auth::Principal>>,
+    permission: Permission,
+) -> bool {
+    authz::allows(state, principal.as_ref().map(|p| &p.0), permission)
+}
+
 /// Determines whether a plugin is accessible to the caller.
 ///
 /// Behavior controlled by `allow_public`:
@@ -50,8 +71,39 @@ fn is_accessible(owner: Option<&str>, caller_gid: Option<&str>, allow_public: bo
     }
 }
 
+/// Returns whether the request's `If-None-Match` header matches `etag`.
+///
+/// Uses the weak comparison of RFC 9110, as required for `If-None-Match`.
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// Returns 304 Not Modified when the client already holds `etag`.
+fn not_modified(headers: &HeaderMap, etag: &str) -> Option<Response> {
+    if_none_match(headers, etag).then(|| with_etag(StatusCode::NOT_MODIFIED.into_response(), etag))
+}
+
+/// Sets the `ETag` of a response, and asks clients to revalidate it on reuse.
+fn with_etag(mut response: Response, etag: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        let headers = response.headers_mut();
+        headers.insert(header::ETAG, value);
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    }
+    response
+}
+
 /// Retrieves a list of all registered plugins.
 ///
+/// The response carries an `ETag` derived from the catalog generation and the
+/// caller; a matching `If-None-Match` gets 304 Not Modified without a body.
+///
 /// # Endpoint
 /// `GET /api/plugins`
 ///
@@ -60,17 +112,30 @@ fn is_accessible(owner: Option<&str>, caller_gid: Option<&str>, allow_public: bo
 pub async fn get_plugins(
     State(state): State<Arc<ArkState>>,
     principal: Option<Extension<crate::server::auth::Principal>>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let start = Instant::now();
     tracing::debug!("API: GET /api/plugins");
 
-    let catalog = state.plugin_registry.catalog.read().await;
-    // Build plugin response with tools
-    let mut plugins_object = json!({});
+    let catalog = state.plugin_registry.catalog.load();
 
     // Determine caller global id if authenticated
     let caller_gid = principal_gid(&principal);
 
+    let etag = catalog.etag(&format!(
+        "plugins {}",
+        caller_gid.as_deref().unwrap_or_default()
+    ));
+    if let Some(response) = not_modified(&headers, &etag) {
+        drop(catalog);
+        let latency_ms = start.elapsed().as_millis() as f64;
+        crate::metrics::record_api_http("/api/plugins", "GET", 304, latency_ms);
+        return response;
+    }
+
+    // Build plugin response with tools
+    let mut plugins_object = json!({});
+
     for plugin in catalog.plugin_to_config.values() {
         // Enforce ownership filtering: include only public or owned by caller
         if !is_accessible(plugin.owner.as_deref(), caller_gid.as_deref(), true) {
@@ -119,7 +184,12 @@ pub async fn get_plugins(
         }
     }
 
-    let response = (StatusCode::OK, Json(plugins_object)).into_response();
+    drop(catalog);
+
+    let response = with_etag(
+        (StatusCode::OK, Json(plugins_object)).into_response(),
+        &etag,
+    );
     let status = response.status().as_u16();
     let latency_ms = start.elapsed().as_millis() as f64;
     crate::metrics::record_api_http("/api/plugins", "GET", status, latency_ms);
@@ -135,57 +205,72 @@ pub async fn get_plugins(
 /// - `plugin_id`: The ID of the plugin to retrieve
 ///
 /// # Returns
-/// The plugin's tool set as JSON, or an error if not found.
+/// The plugin's tool set as JSON, or an error if not found. Like
+/// `GET /api/plugins`, the response carries an `ETag` and honors
+/// `If-None-Match`.
 pub async fn get_plugin_by_id(
     State(state): State<Arc<ArkState>>,
     principal: Option<Extension<crate::server::auth::Principal>>,
+    headers: HeaderMap,
     Path(plugin_id): Path<String>,
 ) -> impl IntoResponse {
     let start = Instant::now();
     tracing::debug!("API: GET /api/plugin/{}", plugin_id);
     // Ownership check: ensure plugin exists and caller is allowed
-    let catalog = state.plugin_registry.catalog.read().await;
+    let catalog = state.plugin_registry.catalog.load();
     let plugin_cfg = catalog.plugin_to_config.get(&plugin_id).cloned();
+    let etag = catalog.etag(&format!("plugin {}", plugin_id));
     drop(catalog);
-    let response = if let Some(plugin_cfg) = plugin_cfg {
-        if !is_accessible(
-            plugin_cfg.owner.as_deref(),
-            principal_gid(&principal).as_deref(),
-            true,
-        ) {
-            (
-                StatusCode::NOT_FOUND,
-                StandardizedResponse::as_error("Plugin not found", None),
-            )
-        } else {
-            match state.plugin_registry.tools(Some(&plugin_id)).await {
-                Ok(toolset) => match serde_json::to_value(toolset) {
-                    Ok(val) => (StatusCode::OK, Json(val)),
-                    Err(e) => {
-                        tracing::error!("Failed to retrieve responses: {:?}", e);
+    let response = match plugin_cfg {
+        Some(cfg)
+            if is_accessible(
+                cfg.owner.as_deref(),
+                principal_gid(&principal).as_deref(),
+                true,
+            ) =>
+        {
+            if let Some(response) = not_modified(&headers, &etag) {
+                response
+            } else {
+                match state.plugin_registry.tools(Some(&plugin_id)).await {
+                    Ok(toolset) => match serde_json::to_value(toolset) {
+                        Ok(val) => with_etag((StatusCode::OK, Json(val)).into_response(), &etag),
+                        Err(e) => {
+                            tracing::error!("Failed to retrieve responses: {:?}", e);
+                            (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                StandardizedResponse::as_error(
+                                    ErrorCode::Internal,
+                                    "Failed to retrieve plugin",
+                                    None,
+                                ),
+                            )
+                                .into_response()
+                        }
+                    },
+                    Err(_e) => {
+                        tracing::debug!("Plugin '{}' not found: {:?}", plugin_id, _e);
                         (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            StandardizedResponse::as_error("Failed to retrieve plugin", None),
+                            StatusCode::NOT_FOUND,
+                            StandardizedResponse::as_error(
+                                ErrorCode::PluginNotFound,
+                                "Plugin not found",
+                                None,
+                            ),
                         )
+                            .into_response()
                     }
-                },
-                Err(_e) => {
-                    tracing::debug!("Plugin '{}' not found: {:?}", plugin_id, _e);
-                    (
-                        StatusCode::NOT_FOUND,
-                        StandardizedResponse::as_error("Plugin not found", None),
-                    )
                 }
             }
         }
-    } else {
-        (
+        _ => (
             StatusCode::NOT_FOUND,
-            StandardizedResponse::as_error("Plugin not found", None),
+            StandardizedResponse::as_error(ErrorCode::PluginNotFound, "Plugin not found", None),
         )
+            .into_response(),
     };
 
-    let status = response.0.as_u16();
+    let status = response.status().as_u16();
     let latency_ms = start.elapsed().as_millis() as f64;
     crate::metrics::record_api_http(
         &format!("/api/plugins/{}", plugin_id),
@@ -193,7 +278,89 @@ pub async fn get_plugin_by_id(
         status,
         latency_ms,
     );
-    response.into_response()
+    response
+}
+
+/// Query parameters for `GET /api/tools`.
+#[derive(Debug, Deserialize)]
+pub struct ToolSearchParams {
+    /// Case-insensitive text matched against tool names and descriptions;
+    /// every visible tool is returned when absent or empty.
+    pub q: Option<String>,
+}
+
+/// Searches the tools of every plugin visible to the caller.
+///
+/// Tools whose name matches come first, followed by those matching only in
+/// their description; ties are ordered by plugin and tool name. Credentials
+/// restricted to specific tools only find the tools they may execute.
+///
+/// # Endpoint
+/// `GET /api/tools?q=`
+///
+/// # Returns
+/// A JSON array of `{"plugin", "tool"}` entries, where `tool` has the same
+/// shape as in `GET /api/plugins`.
+pub async fn search_tools(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<crate::server::auth::Principal>>,
+    tool_scope: Option<Extension<ToolScope>>,
+    Query(params): Query<ToolSearchParams>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/tools q={:?}", params.q);
+
+    let query = params.q.unwrap_or_default().trim().to_lowercase();
+    let caller_gid = principal_gid(&principal);
+    let catalog = state.plugin_registry.catalog.load();
+
+    let mut matches = Vec::new();
+    for (tool_name, plugin_id) in &catalog.tool_to_plugin {
+        let Some(plugin) = catalog.plugin_to_config.get(plugin_id) else {
+            continue;
+        };
+        if !is_accessible(plugin.owner.as_deref(), caller_gid.as_deref(), true) {
+            continue;
+        }
+        if tool_scope
+            .as_ref()
+            .is_some_and(|s| !s.0.allows(Some(plugin_id), tool_name))
+        {
+            continue;
+        }
+        let Some(tool) = catalog.tool_to_def.get(tool_name) else {
+            continue;
+        };
+        let name_match = tool.name.to_lowercase().contains(&query);
+        let description_match = tool
+            .description
+            .as_ref()
+            .is_some_and(|d| d.to_lowercase().contains(&query));
+        if name_match || description_match {
+            matches.push((!name_match, plugin_id, tool));
+        }
+    }
+    matches.sort_by(|a, b| (a.0, a.1, &a.2.name).cmp(&(b.0, b.1, &b.2.name)));
+
+    let results = matches
+        .into_iter()
+        .map(|(_, plugin_id, tool)| {
+            json!({
+                "plugin": plugin_id,
+                "tool": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "inputSchema": tool.input_schema
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(catalog);
+
+    let response = (StatusCode::OK, Json(json!(results))).into_response();
+    let latency_ms = start.elapsed().as_millis() as f64;
+    crate::metrics::record_api_http("/api/tools", "GET", response.status().as_u16(), latency_ms);
+    response
 }
 
 /// Registers a new plugin.
@@ -206,6 +373,7 @@ pub async fn get_plugin_by_id(
 ///
 /// # Returns
 /// - 201 Created on success with a success message
+/// - 403 Forbidden if the caller lacks the `plugin.create` permission
 /// - 500 Internal Server Error on failure
 pub async fn create_plugin(
     State(state): State<Arc<ArkState>>,
@@ -215,6 +383,20 @@ pub async fn create_plugin(
     let start = Instant::now();
     tracing::debug!("API: POST /api/plugins BODY={:?}", payload);
 
+    if !caller_allows(&state, &principal, Permission::PluginCreate) {
+        let response = (
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::PermissionDenied,
+                "Forbidden",
+                Some("Missing permission plugin.create"),
+            ),
+        );
+        let latency_ms = start.elapsed().as_millis() as f64;
+        crate::metrics::record_api_http("/api/plugins", "POST", 403, latency_ms);
+        return response.into_response();
+    }
+
     // If authenticated, set owner to caller's global id
     if let Some(p) = principal.as_ref() {
         payload.owner = Some(p.0.global_id());
@@ -239,6 +421,9 @@ pub async fn create_plugin(
                         let metadata = json!({
                             "manifest": persist_payload.manifest,
                             "insecure": persist_payload.insecure,
+                            "tools_allow": persist_payload.tools_allow,
+                            "tools_deny": persist_payload.tools_deny,
+                            "sha256": persist_payload.sha256,
                         });
                         let owner = persist_payload
                             .owner
@@ -275,7 +460,11 @@ pub async fn create_plugin(
                     tracing::error!("Failed to register plugin: {:?}", e);
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        StandardizedResponse::as_error("Failed to register plugin", None),
+                        StandardizedResponse::as_error(
+                            ErrorCode::Internal,
+                            "Failed to register plugin",
+                            None,
+                        ),
                     )
                 }
             }
@@ -284,7 +473,11 @@ pub async fn create_plugin(
             tracing::error!("Failed to read plugin data: {:?}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                StandardizedResponse::as_error("Failed to read plugin data", None),
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to read plugin data",
+                    None,
+                ),
             )
         }
     };
@@ -295,6 +488,261 @@ pub async fn create_plugin(
     response.into_response()
 }
 
+/// Exports a plugin as a self-contained bundle.
+///
+/// The WASM module is taken from the database when the plugin was persisted
+/// there, and otherwise read again from the plugin's source.
+///
+/// # Endpoint
+/// `GET /api/plugins/:id/export`
+///
+/// # Returns
+/// - 200 OK with the [`PluginBundle`] as an attachment
+/// - 400 Bad Request for the built-in plugin
+/// - 404 Not Found if the plugin doesn't exist or is not visible to the caller
+/// - 409 Conflict if no WASM module is available for the plugin
+/// - 500 Internal Server Error if the module could not be read
+pub async fn export_plugin(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<crate::server::auth::Principal>>,
+    Path(plugin_id): Path<String>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/plugins/{}/export", plugin_id);
+
+    let plugin_cfg = state
+        .plugin_registry
+        .catalog
+        .load()
+        .plugin_to_config
+        .get(&plugin_id)
+        .cloned()
+        .filter(|cfg| {
+            is_accessible(
+                cfg.owner.as_deref(),
+                principal_gid(&principal).as_deref(),
+                true,
+            )
+        });
+
+    let response = match plugin_cfg {
+        None => (
+            StatusCode::NOT_FOUND,
+            StandardizedResponse::as_error(ErrorCode::PluginNotFound, "Plugin not found", None),
+        )
+            .into_response(),
+        Some(_) if plugin_id == BUILTIN_PLUGIN_ID => (
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Cannot export built-in plugin",
+                None,
+            ),
+        )
+            .into_response(),
+        Some(cfg) => match export_bundle(&state, &cfg).await {
+            Ok(Some(bundle)) => {
+                let disposition = format!("attachment; filename=\"{}.arkplugin.json\"", cfg.name);
+                (
+                    StatusCode::OK,
+                    [(header::CONTENT_DISPOSITION, disposition)],
+                    Json(bundle),
+                )
+                    .into_response()
+            }
+            Ok(None) => (
+                StatusCode::CONFLICT,
+                StandardizedResponse::as_error(
+                    ErrorCode::Unavailable,
+                    "Plugin cannot be exported",
+                    Some("No WASM module is available for this plugin"),
+                ),
+            )
+                .into_response(),
+            Err(e) => {
+                tracing::error!("Failed to export plugin '{}': {:?}", plugin_id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(
+                        ErrorCode::Internal,
+                        "Failed to export plugin",
+                        None,
+                    ),
+                )
+                    .into_response()
+            }
+        },
+    };
+
+    let status = response.status().as_u16();
+    let latency_ms = start.elapsed().as_millis() as f64;
+    crate::metrics::record_api_http(
+        &format!("/api/plugins/{}/export", plugin_id),
+        "GET",
+        status,
+        latency_ms,
+    );
+    response
+}
+
+/// Builds the bundle of a registered plugin, or `None` without a WASM module.
+async fn export_bundle(state: &ArkState, cfg: &ArkPlugin) -> anyhow::Result<Option<PluginBundle>> {
+    let owner = cfg.owner.clone().unwrap_or_else(|| "*/*/*".to_string());
+    let record = match state.database.read().ok().and_then(|g| g.clone()) {
+        Some(db) => db.get_plugin_async(owner, cfg.name.clone()).await?,
+        None => None,
+    };
+    if let Some(record) = record
+        && let Some(wasm) = record.plugin_data.as_deref()
+    {
+        return PluginBundle::new(
+            cfg,
+            wasm,
+            Some(&record.metadata),
+            Some(record.date_added_utc),
+        )
+        .map(Some);
+    }
+    match crate::plugins::read_plugin_data(cfg).await?.raw_bytes {
+        Some(wasm) => PluginBundle::new(cfg, &wasm, None, None).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Registers a plugin from a bundle produced by `GET /api/plugins/:id/export`.
+///
+/// The plugin is owned by the caller and keeps the bundle's source URL. The
+/// bundle's version information (source URL, digest and original date) is
+/// persisted with it, so exporting it again carries the same information.
+///
+/// # Endpoint
+/// `POST /api/plugins/import`
+///
+/// # Returns
+/// - 201 Created on success
+/// - 400 Bad Request if the bundle is malformed or its digest does not match
+/// - 403 Forbidden if the caller lacks the `plugin.create` permission
+/// - 413 Payload Too Large for bundles over the `body_limits.upload` limit,
+///   or modules over `plugin_downloads.max_plugin_bytes`
+/// - 500 Internal Server Error if the plugin cannot be loaded or registered
+pub async fn import_plugin(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<crate::server::auth::Principal>>,
+    Json(bundle): Json<PluginBundle>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: POST /api/plugins/import name={}", bundle.name);
+
+    let response = if !caller_allows(&state, &principal, Permission::PluginCreate) {
+        (
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::PermissionDenied,
+                "Forbidden",
+                Some("Missing permission plugin.create"),
+            ),
+        )
+    } else if bundle.wasm_size() > crate::plugins::url::max_plugin_bytes() {
+        let message = format!(
+            "WASM module is {} bytes, more than the maximum of {} (plugin_downloads.max_plugin_bytes)",
+            bundle.wasm_size(),
+            crate::plugins::url::max_plugin_bytes()
+        );
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            StandardizedResponse::as_error(
+                ErrorCode::PayloadTooLarge,
+                "Plugin too large",
+                Some(&message),
+            ),
+        )
+    } else {
+        match bundle.unpack() {
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                StandardizedResponse::as_error(
+                    match ErrorCode::of(&e) {
+                        ErrorCode::SignatureInvalid => ErrorCode::SignatureInvalid,
+                        _ => ErrorCode::InvalidRequest,
+                    },
+                    "Invalid plugin bundle",
+                    Some(&e.to_string()),
+                ),
+            ),
+            Ok((mut plugin, wasm)) => {
+                plugin.owner = principal.as_ref().map(|p| p.0.global_id());
+                import_bundle(&state, &bundle, plugin, wasm).await
+            }
+        }
+    };
+
+    let status = response.0.as_u16();
+    let latency_ms = start.elapsed().as_millis() as f64;
+    crate::metrics::record_api_http("/api/plugins/import", "POST", status, latency_ms);
+    response.into_response()
+}
+
+/// Loads, registers and persists an unpacked bundle.
+async fn import_bundle(
+    state: &ArkState,
+    bundle: &PluginBundle,
+    plugin: ArkPlugin,
+    wasm: Vec<u8>,
+) -> (StatusCode, Json<Value>) {
+    let result = match bundle::load_wasm(&plugin, wasm).await {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::error!("Failed to load plugin bundle '{}': {:?}", plugin.name, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to load plugin bundle",
+                    None,
+                ),
+            );
+        }
+    };
+
+    let record = crate::server::persist::PluginRecord {
+        owner: plugin.owner.clone().unwrap_or_else(|| "*/*/*".to_string()),
+        plugin_id: plugin.name.clone(),
+        plugin_name: Some(plugin.name.clone()),
+        plugin_path: Some(bundle.source_url.clone()),
+        plugin_data: result.raw_bytes.clone(),
+        metadata: json!({
+            "manifest": plugin.manifest,
+            "insecure": plugin.insecure,
+            "tools_allow": plugin.tools_allow,
+            "tools_deny": plugin.tools_deny,
+            BUNDLE_METADATA_KEY: bundle.version_metadata(),
+        }),
+        date_added_utc: chrono::Utc::now(),
+    };
+    let name = plugin.name.clone();
+    if let Err(e) = state
+        .register_plugin_with_executors(plugin, result.toolset, result.executors)
+        .await
+    {
+        tracing::error!("Failed to register imported plugin '{}': {:?}", name, e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StandardizedResponse::as_error(ErrorCode::Internal, "Failed to register plugin", None),
+        );
+    }
+
+    // Persist like plugins registered through the API, without failing the import
+    if let Some(db) = state.database.read().ok().and_then(|g| g.clone())
+        && let Err(e) = db.save_plugin_record_async(record).await
+    {
+        tracing::warn!("Failed to persist imported plugin to database: {:?}", e);
+    }
+    (
+        StatusCode::CREATED,
+        Json(json!({"message": "Plugin imported successfully", "name": name})),
+    )
+}
+
 /// Unregisters a plugin by its ID.
 ///
 /// # Endpoint
@@ -306,6 +754,7 @@ pub async fn create_plugin(
 /// # Returns
 /// - 204 No Content on success
 /// - 400 Bad Request if trying to delete built-in plugin
+/// - 403 Forbidden if the caller neither owns the plugin nor holds `plugin.delete.any`
 /// - 404 Not Found if plugin doesn't exist
 /// - 500 Internal Server Error on failure
 ///
@@ -323,7 +772,11 @@ pub async fn delete_plugin(
         tracing::debug!("Attempted to delete built-in plugin '{}'", plugin_id);
         let response = (
             StatusCode::BAD_REQUEST,
-            Json(json!({ "error": "Cannot delete built-in plugin" })),
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Cannot delete built-in plugin",
+                None,
+            ),
         )
             .into_response();
         let status = response.status().as_u16();
@@ -337,8 +790,10 @@ pub async fn delete_plugin(
         return response;
     }
 
-    // Ownership check: only owner can delete; public and others' plugins are forbidden
-    let catalog = state.plugin_registry.catalog.read().await;
+    // Ownership check: only the owner can delete, unless the caller holds
+    // plugin.delete.any (which also covers public plugins)
+    let delete_any = caller_allows(&state, &principal, Permission::PluginDeleteAny);
+    let catalog = state.plugin_registry.catalog.load();
     // Capture the configured owner for this plugin so we can remove DB record
     // after successful unregister. Use Option<String> to own the value.
     let owner_for_db: Option<String> = catalog
@@ -347,14 +802,18 @@ pub async fn delete_plugin(
         .and_then(|c| c.owner.clone());
 
     if let Some(cfg) = catalog.plugin_to_config.get(&plugin_id)
+        && !delete_any
         && !is_accessible(
             cfg.owner.as_deref(),
             principal_gid(&principal).as_deref(),
             false,
         )
     {
-        let response =
-            (StatusCode::FORBIDDEN, Json(json!({ "error": "Forbidden" }))).into_response();
+        let response = (
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(ErrorCode::OwnershipDenied, "Forbidden", None),
+        )
+            .into_response();
         let status = response.status().as_u16();
         let latency_ms = start.elapsed().as_millis() as f64;
         crate::metrics::record_api_http(
@@ -401,9 +860,7 @@ pub async fn delete_plugin(
             tracing::debug!("Plugin '{}' was not found", plugin_id);
             (
                 StatusCode::NOT_FOUND,
-                Json(json!({
-                    "error": "Plugin not found"
-                })),
+                StandardizedResponse::as_error(ErrorCode::PluginNotFound, "Plugin not found", None),
             )
                 .into_response()
         }
@@ -411,9 +868,11 @@ pub async fn delete_plugin(
             tracing::error!("Failed to delete plugin '{}': {:?}", plugin_id, e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Failed to delete plugin"
-                })),
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to delete plugin",
+                    None,
+                ),
             )
                 .into_response()
         }
@@ -442,24 +901,48 @@ pub async fn delete_plugin(
 ///
 /// # Returns
 /// - 200 OK with the tool execution result
+/// - 403 Forbidden if the caller lacks `tool.execute`, may not access the plugin,
+///   or presented a token whose scope does not include the tool
 /// - 404 Not Found if plugin or tool doesn't exist, or tool doesn't belong to plugin
+/// - 429 Too Many Requests if the caller has used up a monthly quota
 /// - 500 Internal Server Error on execution failure
 pub async fn execute_plugin_tool(
     State(state): State<Arc<ArkState>>,
     principal: Option<Extension<crate::server::auth::Principal>>,
+    tool_scope: Option<Extension<ToolScope>>,
     Path((plugin_id, tool_id)): Path<(String, String)>,
     Json(payload): Json<Value>,
 ) -> impl IntoResponse {
     let start = Instant::now();
     tracing::debug!("API: POST /api/plugins/{}/tool/{}", plugin_id, tool_id);
 
+    if !caller_allows(&state, &principal, Permission::ToolExecute) {
+        let response = (
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::PermissionDenied,
+                "Forbidden",
+                Some("Missing permission tool.execute"),
+            ),
+        )
+            .into_response();
+        let latency_ms = start.elapsed().as_millis() as f64;
+        crate::metrics::record_api_http(
+            &format!("/api/plugins/{}/tools/{}", plugin_id, tool_id),
+            "POST",
+            403,
+            latency_ms,
+        );
+        return response;
+    }
+
     // Check if plugin exists
-    let catalog = state.plugin_registry.catalog.read().await;
+    let catalog = state.plugin_registry.catalog.load();
     if !catalog.plugin_to_config.contains_key(&plugin_id) {
         tracing::debug!("Plugin '{}' not found", plugin_id);
         let response = (
             StatusCode::NOT_FOUND,
-            StandardizedResponse::as_error("Plugin not found", None),
+            StandardizedResponse::as_error(ErrorCode::PluginNotFound, "Plugin not found", None),
         )
             .into_response();
         let status = response.status().as_u16();
@@ -483,7 +966,7 @@ pub async fn execute_plugin_tool(
     {
         let response = (
             StatusCode::FORBIDDEN,
-            StandardizedResponse::as_error("Forbidden", None),
+            StandardizedResponse::as_error(ErrorCode::OwnershipDenied, "Forbidden", None),
         )
             .into_response();
         let status = response.status().as_u16();
@@ -508,7 +991,11 @@ pub async fn execute_plugin_tool(
             );
             let response = (
                 StatusCode::NOT_FOUND,
-                StandardizedResponse::as_error("Tool not found for this plugin", None),
+                StandardizedResponse::as_error(
+                    ErrorCode::ToolNotFound,
+                    "Tool not found for this plugin",
+                    None,
+                ),
             )
                 .into_response();
             let status = response.status().as_u16();
@@ -525,7 +1012,7 @@ pub async fn execute_plugin_tool(
         tracing::debug!("Tool '{}' not found", tool_id);
         let response = (
             StatusCode::NOT_FOUND,
-            StandardizedResponse::as_error("Tool not found", None),
+            StandardizedResponse::as_error(ErrorCode::ToolNotFound, "Tool not found", None),
         )
             .into_response();
         let status = response.status().as_u16();
@@ -538,21 +1025,84 @@ pub async fn execute_plugin_tool(
         );
         return response;
     }
-    drop(catalog); // Release the lock
+    let owner = catalog
+        .plugin_to_config
+        .get(&plugin_id)
+        .and_then(|cfg| cfg.owner.clone());
+    drop(catalog); // Release the snapshot
+
+    // Scoped tokens may only execute the tools they were issued for
+    if let Some(Extension(scope)) = &tool_scope
+        && !scope.allows(Some(&plugin_id), &tool_id)
+    {
+        tracing::debug!("Token scope does not include tool '{}'", tool_id);
+        let response = (
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::ScopeDenied,
+                "Forbidden",
+                Some("Token scope does not include this tool"),
+            ),
+        )
+            .into_response();
+        let latency_ms = start.elapsed().as_millis() as f64;
+        crate::metrics::record_api_http(
+            &format!("/api/plugins/{}/tools/{}", plugin_id, tool_id),
+            "POST",
+            403,
+            latency_ms,
+        );
+        return response;
+    }
+
+    // Callers that used up a monthly quota wait for the next month
+    let caller_gid = principal_gid(&principal);
+    if let Err(exceeded) = check_quota(&state, caller_gid.as_deref()).await {
+        tracing::debug!("Refusing tool '{}': {}", tool_id, exceeded);
+        let response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            StandardizedResponse::as_error(
+                ErrorCode::QuotaExceeded,
+                "Quota exceeded",
+                Some(&exceeded.to_string()),
+            ),
+        )
+            .into_response();
+        let latency_ms = start.elapsed().as_millis() as f64;
+        crate::metrics::record_api_http(
+            &format!("/api/plugins/{}/tools/{}", plugin_id, tool_id),
+            "POST",
+            429,
+            latency_ms,
+        );
+        return response;
+    }
 
     // Execute the tool
-    let response = match state.plugin_registry.call(&tool_id, &payload).await {
+    let caller = principal.as_ref().map(|p| p.0.clone());
+    let call = state.plugin_registry.call(&tool_id, &payload);
+    let (response, error, bytes_returned) = match authz::with_caller(caller, call).await {
         Ok(result) => {
             tracing::debug!("Tool '{}' executed successfully", tool_id);
-            (StatusCode::OK, Json(result)).into_response()
+            let bytes_returned = result.to_string().len() as u64;
+            (
+                (StatusCode::OK, Json(result)).into_response(),
+                None,
+                bytes_returned,
+            )
         }
         Err(e) => {
             tracing::error!("Failed to execute tool '{}': {:?}", tool_id, e);
+            let code = ErrorCode::of(&e);
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                StandardizedResponse::as_error("Tool execution failed", None),
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(code, "Tool execution failed", None),
+                )
+                    .into_response(),
+                Some(code),
+                0,
             )
-                .into_response()
         }
     };
 
@@ -564,6 +1114,20 @@ pub async fn execute_plugin_tool(
         status,
         latency_ms,
     );
-    crate::metrics::record_tool_metrics(&plugin_id, &tool_id, latency_ms);
+    crate::metrics::record_tool_metrics(&plugin_id, &tool_id, owner.as_deref(), latency_ms);
+    crate::server::usage::record_tool_call(
+        &state,
+        ToolCall {
+            plugin_id: &plugin_id,
+            tool: &tool_id,
+            owner: owner.as_deref(),
+            principal: caller_gid.as_deref(),
+            args: &payload,
+            error,
+            latency_ms,
+            bytes_returned,
+        },
+    )
+    .await;
     response
 }
@@ -0,0 +1,164 @@
+/// Implementation of the audit trail endpoints.
+///
+/// Authentication events (sign-ins, sign-outs, token issuance and failed
+/// attempts) are recorded by [`crate::server::audit`]; administrators list
+/// them here, e.g. to export evidence for a compliance review.
+///
+/// # Endpoints
+///
+/// - `GET /api/admin/audit/auth-events` - List authentication events, newest first
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    response::IntoResponse,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        auth::Principal,
+        handlers::service_accounts::{admin_context, finish},
+        persist::{AuthEventQuery, AuthEventRecord},
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+/// Number of events returned when the request does not specify a limit.
+const DEFAULT_EVENT_LIMIT: u32 = 100;
+/// Maximum number of events returned by one request.
+const MAX_EVENT_LIMIT: u32 = 1000;
+
+/// Query parameters for `GET /api/admin/audit/auth-events`.
+#[derive(Debug, Deserialize)]
+pub struct AuthEventsParams {
+    /// Only events of this type ("login", "login_failed", "logout",
+    /// "token_issued", "token_failed").
+    pub event: Option<String>,
+    /// Only events for this subject (principal global id or username).
+    pub subject: Option<String>,
+    /// Only events from this provider kind (e.g. "local", "oidc").
+    pub provider: Option<String>,
+    /// Only events produced by this request (its `x-request-id`).
+    pub request_id: Option<String>,
+    /// Only events at or after this RFC 3339 timestamp.
+    pub since: Option<String>,
+    /// Only events before this RFC 3339 timestamp.
+    pub until: Option<String>,
+    /// Maximum number of events; defaults to 100, capped at 1000.
+    pub limit: Option<u32>,
+}
+
+/// Serializes an authentication event for API responses.
+fn event_to_json(record: &AuthEventRecord) -> Value {
+    json!({
+        "id": record.id,
+        "timestamp": record.timestamp_utc.to_rfc3339(),
+        "event": record.event,
+        "provider": record.provider,
+        "subject": record.subject,
+        "ip": record.ip,
+        "user_agent": record.user_agent,
+        "detail": record.detail,
+        "request_id": record.request_id,
+    })
+}
+
+/// Parses an optional RFC 3339 timestamp parameter.
+pub(crate) fn parse_time(
+    name: &str,
+    value: Option<&str>,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, (StatusCode, Json<Value>)> {
+    value
+        .map(|v| {
+            chrono::DateTime::parse_from_rfc3339(v)
+                .map(|t| t.with_timezone(&chrono::Utc))
+                .map_err(|_| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        StandardizedResponse::as_error(
+                            ErrorCode::InvalidRequest,
+                            &format!("Invalid {}", name),
+                            Some("Expected an RFC 3339 timestamp"),
+                        ),
+                    )
+                })
+        })
+        .transpose()
+}
+
+/// Builds the database query from the request parameters.
+fn event_query(params: AuthEventsParams) -> Result<AuthEventQuery, (StatusCode, Json<Value>)> {
+    let limit = params.limit.unwrap_or(DEFAULT_EVENT_LIMIT);
+    if limit == 0 || limit > MAX_EVENT_LIMIT {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Invalid limit",
+                Some("Must be between 1 and 1000"),
+            ),
+        ));
+    }
+    Ok(AuthEventQuery {
+        since: parse_time("since", params.since.as_deref())?,
+        until: parse_time("until", params.until.as_deref())?,
+        event: params.event.filter(|s| !s.is_empty()),
+        subject: params.subject.filter(|s| !s.is_empty()),
+        provider: params.provider.filter(|s| !s.is_empty()),
+        request_id: params.request_id.filter(|s| !s.is_empty()),
+        limit,
+    })
+}
+
+/// Lists authentication events, newest first.
+///
+/// # Endpoint
+/// `GET /api/admin/audit/auth-events?event=&subject=&provider=&request_id=&since=&until=&limit=`
+///
+/// # Returns
+/// - 200 OK with a JSON array of events
+/// - 400 Bad Request for an invalid timestamp or limit
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if no database is configured
+pub async fn list_auth_events(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Query(params): Query<AuthEventsParams>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/audit/auth-events");
+
+    let response = match admin_context(&state, principal).and_then(|(_, db)| {
+        let query = event_query(params)?;
+        Ok((db, query))
+    }) {
+        Err(e) => e.into_response(),
+        Ok((db, query)) => match db.list_auth_events_async(query).await {
+            Ok(events) => (
+                StatusCode::OK,
+                Json(Value::Array(events.iter().map(event_to_json).collect())),
+            )
+                .into_response(),
+            Err(e) => {
+                tracing::error!("Failed to list authentication events: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(
+                        ErrorCode::Internal,
+                        "Failed to list authentication events",
+                        None,
+                    ),
+                )
+                    .into_response()
+            }
+        },
+    };
+    finish(response, "/api/admin/audit/auth-events", "GET", start)
+}
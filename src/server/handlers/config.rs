@@ -0,0 +1,111 @@
+/// Implementation of the configuration endpoints.
+///
+/// Administrators can see the configuration in effect, and have the server
+/// read its configuration file again and apply the settings that are safe to
+/// change at runtime, as on SIGHUP. See [`crate::server::reload`] for what is
+/// reloaded.
+///
+/// # Endpoints
+///
+/// - `GET /api/admin/config` - Effective configuration with secrets masked
+/// - `POST /api/admin/reload` - Reload the configuration
+use axum::{
+    Extension, Json,
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use hyper::StatusCode;
+
+use std::{sync::Arc, time::Instant};
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        auth::Principal,
+        handlers::service_accounts::{finish, require_admin},
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+fn unavailable() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        StandardizedResponse::as_error(
+            ErrorCode::Unavailable,
+            "The server was not started from a configuration file",
+            None,
+        ),
+    )
+        .into_response()
+}
+
+/// Returns the configuration in effect, after environment and command line
+/// overrides and reloads, with secrets masked.
+///
+/// # Endpoint
+/// `GET /api/admin/config`
+///
+/// # Returns
+/// - 200 OK with the configuration
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if the server was not started from a configuration file
+pub async fn get_config(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/config");
+
+    let response: Response = match require_admin(principal) {
+        Err(e) => e.into_response(),
+        Ok(_) => match state.config_reloader() {
+            None => unavailable(),
+            Some(reloader) => {
+                (StatusCode::OK, Json(reloader.current().await.redacted())).into_response()
+            }
+        },
+    };
+    finish(response, "/api/admin/config", "GET", start)
+}
+
+/// Reloads the configuration file and applies the runtime-safe changes.
+///
+/// # Endpoint
+/// `POST /api/admin/reload`
+///
+/// # Returns
+/// - 200 OK with `{"applied": [...], "restart_required": [...], "errors": [...]}`
+/// - 400 Bad Request if the configuration cannot be read or parsed
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if the server was not started from a configuration file
+pub async fn reload_config(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: POST /api/admin/reload");
+
+    let response: Response = match require_admin(principal) {
+        Err(e) => e.into_response(),
+        Ok(admin) => match state.config_reloader() {
+            None => unavailable(),
+            Some(reloader) => {
+                tracing::info!("Configuration reload requested by {}", admin.global_id());
+                match reloader.reload(&state).await {
+                    Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+                    Err(e) => (
+                        StatusCode::BAD_REQUEST,
+                        StandardizedResponse::as_error(
+                            ErrorCode::InvalidRequest,
+                            "Invalid configuration",
+                            Some(&e.to_string()),
+                        ),
+                    )
+                        .into_response(),
+                }
+            }
+        },
+    };
+    finish(response, "/api/admin/reload", "POST", start)
+}
@@ -0,0 +1,97 @@
+/// Implementation of the live event stream.
+///
+/// Streams [`crate::server::events::ServerEvent`]s as server-sent events so the console can update
+/// without polling. Each SSE message is named after the event `type` and
+/// carries the event as JSON. A client that falls too far behind receives a
+/// `lagged` message with the number of skipped events, after which it should
+/// reload whatever it displays.
+///
+/// # Endpoints
+///
+/// - `GET /api/events` - Stream of events visible to the caller
+use axum::{
+    Extension,
+    extract::State,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use hyper::StatusCode;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        auth::Principal,
+        authz::{self, Permission},
+        handlers::service_accounts::finish,
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+/// Streams server events to the caller.
+///
+/// # Endpoint
+/// `GET /api/events`
+///
+/// # Returns
+/// - 200 OK with a `text/event-stream` of the events the caller may see (see
+///   [`crate::server::events::ServerEvent::is_visible_to`])
+/// - 403 if the caller lacks `console.access`
+pub async fn stream_events(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/events");
+
+    let principal = principal.map(|Extension(p)| p);
+    if !authz::allows(&state, principal.as_ref(), Permission::ConsoleAccess) {
+        return finish(
+            (
+                StatusCode::FORBIDDEN,
+                StandardizedResponse::as_error(
+                    ErrorCode::PermissionDenied,
+                    "Forbidden",
+                    Some("Missing permission console.access"),
+                ),
+            )
+                .into_response(),
+            "/api/events",
+            "GET",
+            start,
+        );
+    }
+
+    let receiver = state.events.subscribe();
+    let stream = futures::stream::unfold(
+        (receiver, principal),
+        |(mut receiver, principal)| async move {
+            loop {
+                let message = match receiver.recv().await {
+                    Ok(event) if !event.is_visible_to(principal.as_ref()) => continue,
+                    Ok(event) => Event::default().event(event.name()).json_data(&event),
+                    Err(RecvError::Lagged(skipped)) => Event::default()
+                        .event("lagged")
+                        .json_data(json!({ "skipped": skipped })),
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((message, (receiver, principal)));
+            }
+        },
+    );
+    finish(
+        Sse::new(stream)
+            .keep_alive(KeepAlive::default())
+            .into_response(),
+        "/api/events",
+        "GET",
+        start,
+    )
+}
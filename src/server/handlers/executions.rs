@@ -0,0 +1,200 @@
+/// Implementation of the tool execution history endpoint.
+///
+/// Every tool call made over MCP or the plugin API is recorded by
+/// [`crate::server::usage`] with its caller, duration, outcome and a hash of
+/// its arguments. Users list their own calls here to see what ran on their
+/// behalf; administrators can list everyone's.
+///
+/// # Endpoints
+///
+/// - `GET /api/executions` - List tool executions, newest first
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    response::IntoResponse,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        auth::Principal,
+        handlers::{audit::parse_time, service_accounts::finish, tokens::database},
+        persist::{ToolExecutionQuery, ToolExecutionRecord},
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+/// Number of executions returned when the request does not specify a limit.
+const DEFAULT_EXECUTION_LIMIT: u32 = 100;
+/// Maximum number of executions returned by one request.
+const MAX_EXECUTION_LIMIT: u32 = 1000;
+
+/// Query parameters for `GET /api/executions`.
+#[derive(Debug, Deserialize)]
+pub struct ExecutionsParams {
+    /// Only calls made by this principal global id; non-administrators may
+    /// only name themselves.
+    pub principal: Option<String>,
+    /// Only calls to tools of this plugin.
+    pub plugin: Option<String>,
+    /// Only calls to this tool.
+    pub tool: Option<String>,
+    /// Only successful ("ok") or failed ("error") calls.
+    pub status: Option<String>,
+    /// Only calls at or after this RFC 3339 timestamp.
+    pub since: Option<String>,
+    /// Only calls before this RFC 3339 timestamp.
+    pub until: Option<String>,
+    /// Only calls with a lower id, to page through older history.
+    pub before_id: Option<i64>,
+    /// Maximum number of calls; defaults to 100, capped at 1000.
+    pub limit: Option<u32>,
+}
+
+/// Serializes a tool execution for API responses.
+fn execution_to_json(record: &ToolExecutionRecord) -> Value {
+    json!({
+        "id": record.id,
+        "timestamp": record.timestamp_utc.to_rfc3339(),
+        "principal": record.principal,
+        "plugin": record.plugin_id,
+        "tool": record.tool,
+        "duration_ms": record.duration_ms,
+        "status": record.status(),
+        "error_code": record.error_code,
+        "args_hash": record.args_hash,
+        "request_id": record.request_id,
+    })
+}
+
+fn bad_request(message: &str, detail: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        StandardizedResponse::as_error(ErrorCode::InvalidRequest, message, Some(detail)),
+    )
+}
+
+/// Builds the database query from the request parameters, restricting
+/// non-administrators to their own calls.
+fn execution_query(
+    principal: &Principal,
+    params: ExecutionsParams,
+) -> Result<ToolExecutionQuery, (StatusCode, Json<Value>)> {
+    let limit = params.limit.unwrap_or(DEFAULT_EXECUTION_LIMIT);
+    if limit == 0 || limit > MAX_EXECUTION_LIMIT {
+        return Err(bad_request("Invalid limit", "Must be between 1 and 1000"));
+    }
+    let is_error = match params.status.as_deref() {
+        None | Some("") => None,
+        Some("ok") => Some(false),
+        Some("error") => Some(true),
+        Some(_) => {
+            return Err(bad_request(
+                "Invalid status",
+                "Expected \"ok\" or \"error\"",
+            ));
+        }
+    };
+    let requested = params.principal.filter(|s| !s.is_empty());
+    let principal = if principal.is_admin {
+        requested
+    } else {
+        let own = principal.global_id();
+        if requested.as_ref().is_some_and(|p| *p != own) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                StandardizedResponse::as_error(
+                    ErrorCode::PermissionDenied,
+                    "Forbidden",
+                    Some("Only administrators can list other principals' executions"),
+                ),
+            ));
+        }
+        Some(own)
+    };
+    Ok(ToolExecutionQuery {
+        principal,
+        plugin_id: params.plugin.filter(|s| !s.is_empty()),
+        tool: params.tool.filter(|s| !s.is_empty()),
+        is_error,
+        since: parse_time("since", params.since.as_deref())?,
+        until: parse_time("until", params.until.as_deref())?,
+        before_id: params.before_id,
+        limit,
+    })
+}
+
+/// Lists tool executions, newest first.
+///
+/// Administrators see every caller's executions; other users only their own.
+///
+/// # Endpoint
+/// `GET /api/executions?principal=&plugin=&tool=&status=&since=&until=&before_id=&limit=`
+///
+/// # Returns
+/// - 200 OK with a JSON array of executions
+/// - 400 Bad Request for an invalid status, timestamp or limit
+/// - 401 Unauthorized if the caller is not authenticated
+/// - 403 Forbidden if a non-administrator asks for another principal
+/// - 503 Service Unavailable if no database is configured
+pub async fn list_executions(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Query(params): Query<ExecutionsParams>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/executions");
+
+    let response = match (principal, database(&state)) {
+        (None, _) => (
+            StatusCode::UNAUTHORIZED,
+            StandardizedResponse::as_error(
+                ErrorCode::Unauthenticated,
+                "Authentication required",
+                None,
+            ),
+        )
+            .into_response(),
+        (_, None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "Execution history requires a database",
+                None,
+            ),
+        )
+            .into_response(),
+        (Some(Extension(principal)), Some(db)) => match execution_query(&principal, params) {
+            Err(e) => e.into_response(),
+            Ok(query) => match db.list_tool_executions_async(query).await {
+                Ok(executions) => (
+                    StatusCode::OK,
+                    Json(Value::Array(
+                        executions.iter().map(execution_to_json).collect(),
+                    )),
+                )
+                    .into_response(),
+                Err(e) => {
+                    tracing::error!("Failed to list tool executions: {:?}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        StandardizedResponse::as_error(
+                            ErrorCode::Internal,
+                            "Failed to list tool executions",
+                            None,
+                        ),
+                    )
+                        .into_response()
+                }
+            },
+        },
+    };
+    finish(response, "/api/executions", "GET", start)
+}
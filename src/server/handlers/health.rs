@@ -6,12 +6,16 @@
 //!
 //! - `GET /livez` - Returns 200/OK if the server is alive (basic liveness check)
 //! - `GET /readyz` - Returns 200/OK if the server is ready to serve requests
+//! - `GET /readyz?verbose` - Same status code, with a JSON body listing the
+//!   status of each component
+//! - `GET /startupz` - Returns 200/OK once startup has completed
 //!
 //! # Response Format
 //!
-//! Both endpoints support content negotiation:
-//! - `Accept: application/json` returns `{"status": "live|ready|not live|not ready"}`
-//! - Default returns plain text `"live"`, `"ready"`, `"not live"`, or `"not ready"`
+//! All endpoints support content negotiation:
+//! - `Accept: application/json` returns `{"status": "live|ready|started|not live|not ready|not started"}`
+//! - Default returns plain text `"live"`, `"ready"`, `"started"`, `"not live"`, `"not ready"`,
+//!   or `"not started"`
 //!
 //! # Notes
 //!
@@ -20,11 +24,22 @@
 
 use std::sync::Arc;
 
-use axum::{extract::State, response::Response};
+use axum::{
+    extract::{Query, State},
+    response::Response,
+};
 use hyper::{HeaderMap, StatusCode};
-use serde_json::json;
+use serde::Deserialize;
+use serde_json::{Value, json};
 
-use crate::state::ArkState;
+use crate::state::{ApplicationState, ArkState, TlsStatus};
+
+/// Query parameters of the readiness check.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReadyzParams {
+    /// Present (`?verbose`, `?verbose=true`) to list component status.
+    pub verbose: Option<String>,
+}
 
 /// Liveness check handler.
 ///
@@ -83,42 +98,123 @@ pub async fn livez(State(state): State<Arc<ArkState>>, headers: HeaderMap) -> Re
 /// # Endpoint
 /// `GET /readyz`
 ///
+/// The server is ready once startup completed, the token signer (if required)
+/// is loaded and the database (if configured) can be read. In verbose mode the
+/// JSON body also reports plugins that failed to load, identity provider
+/// discovery and TLS; those do not affect readiness.
+///
 /// # Parameters
 /// - `state`: Application state containing readiness information
+/// - `params`: `verbose` selects the per-component JSON body
 /// - `headers`: HTTP headers, used for content negotiation via Accept header
 ///
 /// # Returns
 /// - 200 OK with "ready" if the server is ready
 /// - 503 Service Unavailable with "not ready" if the server is not ready
-pub async fn readyz(State(state): State<Arc<ArkState>>, headers: HeaderMap) -> Response {
+pub async fn readyz(
+    State(state): State<Arc<ArkState>>,
+    Query(params): Query<ReadyzParams>,
+    headers: HeaderMap,
+) -> Response {
     tracing::debug!("readyz_handler invoked");
 
     let accept = headers
         .get("accept")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
+    let verbose = params
+        .verbose
+        .is_some_and(|v| !matches!(v.as_str(), "false" | "0"));
 
-    // Consider both application readiness and signer readiness
+    // Consider application, signer and database readiness
     let app_ready = state.is_ready();
     let signer_ready = state.is_signer_ready();
+    let database = database_status(&state).await;
+    let database_ready = database["status"] != "error";
 
-    let (status, text) = if app_ready && signer_ready {
+    let (status, text) = if app_ready && signer_ready && database_ready {
         (StatusCode::OK, "ready")
     } else {
         tracing::debug!(
-            "Server not ready: app_ready={}, signer_ready={}",
+            "Server not ready: app_ready={}, signer_ready={}, database_ready={}",
             app_ready,
-            signer_ready
+            signer_ready,
+            database_ready
         );
         (StatusCode::SERVICE_UNAVAILABLE, "not ready")
     };
 
-    let body = if accept.contains("application/json") {
+    let json_body = verbose || accept.contains("application/json");
+    let body = if verbose {
+        json!({
+            "status": text,
+            "components": {
+                "lifecycle": lifecycle_status(&state),
+                "signer": { "status": if signer_ready { "ok" } else { "error" } },
+                "database": database,
+                "plugins": plugins_status(&state).await,
+                "auth": auth_status(&state).await,
+                "tls": tls_status(&state),
+            }
+        })
+        .to_string()
+    } else if json_body {
         json!({ "status": text}).to_string()
     } else {
         text.to_string()
     };
 
+    Response::builder()
+        .status(status)
+        .header(
+            "Content-Type",
+            if json_body {
+                "application/json"
+            } else {
+                "text/plain"
+            },
+        )
+        .body(body.into())
+        .unwrap()
+}
+
+/// Startup check handler.
+///
+/// This endpoint indicates whether the server has completed startup: configuration
+/// loaded, plugins loaded and the network servers started. Meant for a Kubernetes
+/// startup probe, which holds off the liveness probe while plugins are pulled.
+///
+/// # Endpoint
+/// `GET /startupz`
+///
+/// # Parameters
+/// - `state`: Application state containing lifecycle information
+/// - `headers`: HTTP headers, used for content negotiation via Accept header
+///
+/// # Returns
+/// - 200 OK with "started" once the server has reached the ready state
+/// - 503 Service Unavailable with "not started" before that
+pub async fn startupz(State(state): State<Arc<ArkState>>, headers: HeaderMap) -> Response {
+    tracing::debug!("startupz_handler invoked");
+
+    let accept = headers
+        .get("accept")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    let (status, text) = if state.is_started() {
+        (StatusCode::OK, "started")
+    } else {
+        tracing::debug!("Server not started: state={:?}", state.get_state());
+        (StatusCode::SERVICE_UNAVAILABLE, "not started")
+    };
+
+    let body = if accept.contains("application/json") {
+        json!({ "status": text }).to_string()
+    } else {
+        text.to_string()
+    };
+
     Response::builder()
         .status(status)
         .header(
@@ -132,3 +228,67 @@ pub async fn readyz(State(state): State<Arc<ArkState>>, headers: HeaderMap) -> R
         .body(body.into())
         .unwrap()
 }
+
+/// Lifecycle stage; `ok` once startup completed.
+fn lifecycle_status(state: &ArkState) -> Value {
+    let stage = state.get_state();
+    let status = match stage {
+        ApplicationState::Ready => "ok",
+        ApplicationState::Terminating => "terminating",
+        _ => "starting",
+    };
+    json!({ "status": status, "state": format!("{:?}", stage) })
+}
+
+/// Whether the database can be read; `disabled` without a database.
+async fn database_status(state: &ArkState) -> Value {
+    let Some(db) = state.database.read().ok().and_then(|g| g.clone()) else {
+        return json!({ "status": "disabled" });
+    };
+    match db.ping_async().await {
+        Ok(()) => json!({ "status": "ok" }),
+        Err(e) => json!({ "status": "error", "error": format!("{:#}", e) }),
+    }
+}
+
+/// Loaded plugins and those that failed to load; `degraded` on failures.
+async fn plugins_status(state: &ArkState) -> Value {
+    let loaded = state.plugin_registry.catalog.load().plugin_to_config.len();
+    let failed = state.plugin_failures();
+    let status = if failed.is_empty() { "ok" } else { "degraded" };
+    json!({ "status": status, "loaded": loaded, "failed": failed })
+}
+
+/// Identity providers; `pending` until OIDC discovery has completed.
+async fn auth_status(state: &ArkState) -> Value {
+    let auth = state.auth_state.read().ok().and_then(|g| g.clone());
+    let Some(auth) = auth.filter(|a| a.enabled) else {
+        return json!({ "status": "disabled" });
+    };
+    let mut providers = Vec::new();
+    for provider in &auth.providers {
+        let discovered = provider.resolved.read().await.as_ref().is_some_and(|p| {
+            !p.discovery || (p.authorization_endpoint.is_some() && p.token_endpoint.is_some())
+        });
+        providers.push(json!({
+            "name": provider.name,
+            "kind": provider.kind,
+            "status": if discovered { "ok" } else { "pending" },
+        }));
+    }
+    let status = if providers.iter().all(|p| p["status"] == "ok") {
+        "ok"
+    } else {
+        "pending"
+    };
+    json!({ "status": status, "providers": providers })
+}
+
+/// TLS material; `degraded` if configured but not loaded.
+fn tls_status(state: &ArkState) -> Value {
+    match state.tls_status() {
+        TlsStatus::Disabled => json!({ "status": "disabled" }),
+        TlsStatus::Loaded => json!({ "status": "ok" }),
+        TlsStatus::Failed(error) => json!({ "status": "degraded", "error": error }),
+    }
+}
@@ -0,0 +1,107 @@
+/// Implementation of the runtime log level endpoints.
+///
+/// Administrators can change the tracing filter of the running server, e.g.
+/// to turn on trace logging for one module while a problem reproduces, without
+/// a restart. The change lasts until the next restart; `RUST_LOG` still sets
+/// the filter at startup.
+///
+/// # Endpoints
+///
+/// - `GET /api/admin/loglevel` - Current filter directives
+/// - `PUT /api/admin/loglevel` - Replace the filter directives
+use axum::{Extension, Json, response::IntoResponse};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+
+use std::time::Instant;
+
+use crate::errors::ErrorCode;
+use crate::server::{
+    auth::Principal,
+    handlers::service_accounts::{finish, require_admin},
+    service::StandardizedResponse,
+};
+
+/// Request body for `PUT /api/admin/loglevel`.
+#[derive(Debug, Deserialize)]
+pub struct SetLogLevelRequest {
+    /// Filter directives in `RUST_LOG` syntax, e.g. `info,ark::plugins::oci=trace`.
+    pub filter: String,
+}
+
+/// Response when the subscriber was not installed through [`crate::logging::init`].
+fn not_reloadable() -> axum::response::Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        StandardizedResponse::as_error(
+            ErrorCode::Unavailable,
+            "The log level cannot be changed at runtime",
+            None,
+        ),
+    )
+        .into_response()
+}
+
+/// Returns the current filter directives.
+///
+/// # Endpoint
+/// `GET /api/admin/loglevel`
+///
+/// # Returns
+/// - 200 OK with `{"filter": "..."}`
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if the filter cannot be changed at runtime
+pub async fn get_log_level(principal: Option<Extension<Principal>>) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/loglevel");
+
+    let response = match require_admin(principal) {
+        Err(e) => e.into_response(),
+        Ok(_) => match crate::logging::current_filter() {
+            Some(filter) => (StatusCode::OK, Json(json!({ "filter": filter }))).into_response(),
+            None => not_reloadable(),
+        },
+    };
+    finish(response, "/api/admin/loglevel", "GET", start)
+}
+
+/// Replaces the filter directives of the running server.
+///
+/// # Endpoint
+/// `PUT /api/admin/loglevel`
+///
+/// # Returns
+/// - 200 OK with the filter now in effect, `{"filter": "..."}`
+/// - 400 Bad Request if the directives are invalid
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if the filter cannot be changed at runtime
+pub async fn set_log_level(
+    principal: Option<Extension<Principal>>,
+    Json(payload): Json<SetLogLevelRequest>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: PUT /api/admin/loglevel");
+
+    let response = match require_admin(principal) {
+        Err(e) => e.into_response(),
+        Ok(_) if crate::logging::current_filter().is_none() => not_reloadable(),
+        Ok(admin) => match crate::logging::set_filter(&payload.filter) {
+            Ok(()) => {
+                let filter = crate::logging::current_filter().unwrap_or_default();
+                tracing::info!("Log filter set to '{}' (by {})", filter, admin.global_id());
+                (StatusCode::OK, Json(json!({ "filter": filter }))).into_response()
+            }
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                StandardizedResponse::as_error(
+                    ErrorCode::InvalidRequest,
+                    "Invalid log filter",
+                    Some(&e.to_string()),
+                ),
+            )
+                .into_response(),
+        },
+    };
+    finish(response, "/api/admin/loglevel", "PUT", start)
+}
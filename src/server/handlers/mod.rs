@@ -1,4 +1,14 @@
 pub mod api;
+pub mod audit;
+pub mod config;
+pub mod events;
+pub mod executions;
 pub mod health;
+pub mod logging;
 pub mod oauth;
+pub mod profile;
+pub mod service_accounts;
 pub mod session;
+pub mod stats;
+pub mod tokens;
+pub mod users;
@@ -2,15 +2,28 @@
 //!
 //! Provides standard OAuth 2.0 endpoints for authorization and token exchange
 //! that integrate with the configured identity providers (Google/Microsoft).
+//! The token endpoint also supports the client-credentials grant for service
+//! accounts and the device authorization grant (RFC 8628) for CLI and agent
+//! clients on headless machines: the client obtains a device code from
+//! `/device_authorization`, the user signs in on another device and approves
+//! the request at `/device`, and the client polls `/token` for its token.
+//!
+//! MCP clients discover these endpoints through the authorization server
+//! metadata (RFC 8414) and may register themselves at `/register` (RFC 7591
+//! dynamic client registration) instead of using a pre-provisioned client id.
 
 use axum::{
-    Json, Router,
+    Form, Json, Router,
+    body::Bytes,
     extract::{Extension, OriginalUri, Query},
-    http::{HeaderMap, StatusCode},
-    response::{IntoResponse, Redirect, Response},
+    http::{Extensions, HeaderMap, StatusCode, header},
+    response::{Html, IntoResponse, Redirect, Response},
     routing::{get, post},
 };
-use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use base64::{
+    Engine as _,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
 use openidconnect::{
     AccessToken, AuthorizationCode, CsrfToken, IssuerUrl, PkceCodeChallenge, PkceCodeVerifier,
 };
@@ -20,7 +33,32 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use urlencoding;
 
-use crate::server::auth::{AuthState, Principal, extract_session_user_from_cookie};
+use crate::server::audit::{AuthEvent, AuthEventKind, ClientInfo};
+use crate::server::auth::{
+    API_KEY_SCOPE_API, API_KEY_SCOPE_MCP, AuthState, DeviceAuthorization, DeviceDecision,
+    Principal, ProviderKind, client_secret_matches, extract_client_session_user,
+    generate_client_secret, hash_api_key,
+};
+use crate::server::authz;
+use crate::server::csrf;
+use crate::server::handlers::session::{html_escape, jwks_handler, request_base_url};
+use crate::server::handlers::tokens::{SCOPES_HINT, database};
+use crate::server::persist::{OAuthClientMetadata, OAuthClientRecord};
+use crate::server::service::BasePath;
+
+/// Grant type of the device authorization grant (RFC 8628).
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// How long a device code stays valid.
+const DEVICE_CODE_LIFETIME: Duration = Duration::from_secs(600);
+/// Minimum polling interval; each `slow_down` adds this much again.
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Characters used in user codes: consonants only, so codes are easy to type
+/// and never spell words.
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+/// Scope of authorization code flow tokens, which grant MCP access.
+const AUTHORIZATION_CODE_SCOPE: &str = "openid profile email mcp";
+/// Default scope of device flow tokens (MCP access, like the authorization code flow).
+const DEVICE_DEFAULT_SCOPE: &str = AUTHORIZATION_CODE_SCOPE;
 
 #[derive(Debug, Deserialize)]
 pub struct AuthorizeParams {
@@ -40,9 +78,47 @@ pub struct TokenParams {
     pub grant_type: String,
     pub code: Option<String>,
     pub redirect_uri: Option<String>,
+    /// May instead be sent in an `Authorization: Basic` header.
+    #[serde(default)]
     pub client_id: String,
     pub client_secret: Option<String>,
     pub code_verifier: Option<String>,
+    pub scope: Option<String>,
+    /// Device code, for the device authorization grant.
+    pub device_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceAuthorizationParams {
+    pub client_id: String,
+    pub scope: Option<String>,
+}
+
+/// Device authorization response (RFC 8628 section 3.2).
+#[derive(Debug, Serialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DevicePageParams {
+    pub user_code: Option<String>,
+}
+
+/// Form posted from the device verification page.
+#[derive(Debug, Deserialize)]
+pub struct DeviceDecisionForm {
+    pub user_code: String,
+    /// "approve" or "deny".
+    pub action: String,
+    /// CSRF token rendered into the confirmation form.
+    #[serde(default)]
+    pub csrf_token: Option<String>,
 }
 
 /// OAuth token response using openidconnect types
@@ -56,6 +132,20 @@ pub struct OAuthTokenResponse {
     pub id_token: Option<String>,
 }
 
+/// Client registration response (RFC 7591 section 3.2.1).
+#[derive(Debug, Serialize)]
+pub struct ClientRegistrationResponse {
+    pub client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub client_id_issued_at: i64,
+    /// Always 0 (the secret does not expire) when a secret was issued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<u64>,
+    #[serde(flatten)]
+    pub metadata: OAuthClientMetadata,
+}
+
 /// OAuth error response
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
@@ -64,21 +154,262 @@ pub struct ErrorResponse {
     pub error_uri: Option<String>,
 }
 
-/// Creates the OAuth router with /authorize and /token endpoints
+/// Creates the OAuth router with the /authorize, /token and device flow
+/// endpoints, the authorization server metadata, the `/.well-known/jwks.json`
+/// key set for verifying issued tokens and, unless disabled, `/register`
 pub fn router(auth_state: Arc<AuthState>) -> Router {
-    Router::new()
+    let mut router = Router::new()
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .route(
+            "/.well-known/oauth-authorization-server",
+            get(authorization_server_metadata_handler),
+        )
+        .route(
+            "/.well-known/oauth-authorization-server/mcp",
+            get(authorization_server_metadata_handler),
+        )
         .route("/authorize", get(authorize_handler))
         .route("/token", post(token_handler))
-        .layer(Extension(auth_state))
+        .route("/device_authorization", post(device_authorization_handler))
+        .route(
+            "/device",
+            get(device_page_handler).post(device_decision_handler),
+        );
+    if auth_state.oauth.dynamic_registration {
+        router = router.route("/register", post(register_handler));
+    }
+    router.layer(Extension(auth_state))
+}
+
+/// GET /.well-known/oauth-authorization-server - authorization server
+/// metadata (RFC 8414)
+async fn authorization_server_metadata_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    OriginalUri(uri): OriginalUri,
+) -> Response {
+    let Some(issuer) = request_base_url(&uri, &headers, &extensions) else {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "Missing host");
+    };
+
+    let mut metadata = serde_json::json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+        "device_authorization_endpoint": format!("{issuer}/device_authorization"),
+        "jwks_uri": format!("{issuer}/.well-known/jwks.json"),
+        "response_types_supported": ["code"],
+        "grant_types_supported": [
+            "authorization_code",
+            "client_credentials",
+            DEVICE_CODE_GRANT_TYPE
+        ],
+        "code_challenge_methods_supported": ["S256"],
+        "token_endpoint_auth_methods_supported": CLIENT_AUTH_METHODS
+            .iter()
+            .filter(|method| auth.oauth.public_clients || **method != "none")
+            .collect::<Vec<_>>(),
+        "scopes_supported": ["openid", "profile", "email", API_KEY_SCOPE_API, API_KEY_SCOPE_MCP],
+    });
+    if auth.oauth.dynamic_registration {
+        metadata["registration_endpoint"] = format!("{issuer}/register").into();
+    }
+    Json(metadata).into_response()
+}
+
+/// Client authentication methods accepted at the token endpoint; `none` is
+/// for public clients, which hold no secret.
+const CLIENT_AUTH_METHODS: [&str; 3] = ["client_secret_basic", "client_secret_post", "none"];
+/// Grant types a client may register.
+const REGISTRABLE_GRANT_TYPES: [&str; 3] = [
+    "authorization_code",
+    "refresh_token",
+    DEVICE_CODE_GRANT_TYPE,
+];
+
+/// POST /register - dynamic client registration (RFC 7591)
+///
+/// Open to anyone, like the registration endpoint MCP clients expect: a
+/// registered client gains nothing but a client id whose redirect URIs are
+/// fixed, since users still sign in and approve at `/authorize`.
+async fn register_handler(Extension(auth): Extension<Arc<AuthState>>, body: Bytes) -> Response {
+    let mut metadata: OAuthClientMetadata = match serde_json::from_slice(&body) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return oauth_error(
+                StatusCode::BAD_REQUEST,
+                "invalid_client_metadata",
+                &format!("Invalid client metadata: {}", e),
+            );
+        }
+    };
+    if let Err((error, description)) = normalize_client_metadata(&mut metadata) {
+        return oauth_error(StatusCode::BAD_REQUEST, error, &description);
+    }
+    let public_client = metadata.token_endpoint_auth_method.as_deref() == Some("none");
+    if public_client && !auth.oauth.public_clients {
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_client_metadata",
+            "Public clients are not accepted; register with a client secret",
+        );
+    }
+    let Some(db) = database(&auth.app_state) else {
+        return oauth_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "temporarily_unavailable",
+            "Client registration requires a database",
+        );
+    };
+
+    let secret = (!public_client).then(generate_client_secret);
+    let record = OAuthClientRecord {
+        client_id: generate_secure_code(),
+        secret_hash: secret.as_deref().map(hash_api_key),
+        metadata,
+        created_utc: chrono::Utc::now(),
+    };
+    if let Err(e) = db.save_oauth_client_async(record.clone()).await {
+        tracing::error!("Failed to save OAuth client: {}", e);
+        return oauth_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "server_error",
+            "Failed to register client",
+        );
+    }
+    tracing::info!(
+        "Registered OAuth client {} ({})",
+        record.client_id,
+        record.metadata.client_name.as_deref().unwrap_or("unnamed")
+    );
+
+    let response = ClientRegistrationResponse {
+        client_id: record.client_id,
+        client_secret_expires_at: secret.as_ref().map(|_| 0),
+        client_secret: secret,
+        client_id_issued_at: record.created_utc.timestamp(),
+        metadata: record.metadata,
+    };
+    (StatusCode::CREATED, Json(response)).into_response()
+}
+
+/// Validates registered client metadata and fills in the defaults of RFC 7591.
+///
+/// Returns the OAuth error code and description of the first problem found.
+fn normalize_client_metadata(
+    metadata: &mut OAuthClientMetadata,
+) -> Result<(), (&'static str, String)> {
+    let method = metadata
+        .token_endpoint_auth_method
+        .get_or_insert_with(|| "client_secret_basic".to_string());
+    if !CLIENT_AUTH_METHODS.contains(&method.as_str()) {
+        return Err((
+            "invalid_client_metadata",
+            format!("Unsupported token_endpoint_auth_method '{}'", method),
+        ));
+    }
+
+    if metadata.grant_types.is_empty() {
+        metadata.grant_types.push("authorization_code".to_string());
+    }
+    if let Some(grant) = metadata
+        .grant_types
+        .iter()
+        .find(|g| !REGISTRABLE_GRANT_TYPES.contains(&g.as_str()))
+    {
+        return Err((
+            "invalid_client_metadata",
+            format!("Unsupported grant type '{}'", grant),
+        ));
+    }
+
+    if metadata.response_types.is_empty() {
+        metadata.response_types.push("code".to_string());
+    }
+    if metadata.response_types.iter().any(|t| t != "code") {
+        return Err((
+            "invalid_client_metadata",
+            "Only the 'code' response type is supported".to_string(),
+        ));
+    }
+
+    let uses_redirects = metadata
+        .grant_types
+        .iter()
+        .any(|g| g == "authorization_code");
+    if uses_redirects && metadata.redirect_uris.is_empty() {
+        return Err((
+            "invalid_redirect_uri",
+            "redirect_uris is required for the authorization_code grant".to_string(),
+        ));
+    }
+    if let Some(uri) = metadata
+        .redirect_uris
+        .iter()
+        .find(|uri| !valid_redirect_uri(uri))
+    {
+        return Err((
+            "invalid_redirect_uri",
+            format!("Invalid redirect URI '{}'", uri),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks a redirect URI a client wants to register.
+///
+/// Web clients must use HTTPS; plain HTTP is only accepted for loopback
+/// addresses, and native apps may use private-use schemes (RFC 8252).
+/// Fragments and script or local-content schemes are refused.
+fn valid_redirect_uri(uri: &str) -> bool {
+    let Ok(url) = url::Url::parse(uri) else {
+        return false;
+    };
+    if url.fragment().is_some() {
+        return false;
+    }
+    match url.scheme() {
+        "https" => url.host().is_some(),
+        "http" => match url.host() {
+            Some(url::Host::Domain(domain)) => domain == "localhost",
+            Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+            None => false,
+        },
+        "javascript" | "data" | "file" | "vbscript" | "blob" | "about" => false,
+        _ => true,
+    }
 }
 
 /// GET /authorize - OAuth authorization endpoint
 async fn authorize_handler(
     Extension(auth): Extension<Arc<AuthState>>,
     headers: HeaderMap,
+    extensions: Extensions,
     OriginalUri(uri): OriginalUri,
     Query(params): Query<AuthorizeParams>,
 ) -> impl IntoResponse {
+    // A registered client may only be sent to its registered redirect URIs;
+    // errors are not redirected anywhere else.
+    let registered = auth.registered_client(&params.client_id).await;
+    if let Some(registered) = &registered
+        && !registered
+            .metadata
+            .redirect_uris
+            .contains(&params.redirect_uri)
+    {
+        tracing::warn!(
+            "Refused unregistered redirect URI for OAuth client {}",
+            params.client_id
+        );
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "redirect_uri is not registered for this client",
+        );
+    }
+
     // Validate request
     if params.response_type != "code" {
         return error_redirect(
@@ -98,11 +429,42 @@ async fn authorize_handler(
         );
     }
 
-    // Check if user is authenticated via session cookie
-    let principal = if let Some(cookie_header) = headers.get("cookie")
-        && let Ok(cookie_str) = cookie_header.to_str()
-        && let Some(user) = extract_session_user_from_cookie(&auth, cookie_str).await
+    // Public clients cannot keep a secret, so the code is bound to the client
+    // with PKCE instead
+    let public_client = registered
+        .as_ref()
+        .is_some_and(|client| client.secret_hash.is_none());
+    if public_client && !auth.oauth.public_clients {
+        return error_redirect(
+            &params.redirect_uri,
+            "unauthorized_client",
+            Some("Public clients are not accepted"),
+            params.state.as_deref(),
+        );
+    }
+    if params
+        .code_challenge_method
+        .as_deref()
+        .is_some_and(|method| method != "S256")
     {
+        return error_redirect(
+            &params.redirect_uri,
+            "invalid_request",
+            Some("Only the 'S256' code challenge method is supported"),
+            params.state.as_deref(),
+        );
+    }
+    if params.code_challenge.is_none() && (public_client || auth.oauth.require_pkce) {
+        return error_redirect(
+            &params.redirect_uri,
+            "invalid_request",
+            Some("PKCE code_challenge is required"),
+            params.state.as_deref(),
+        );
+    }
+
+    // Check if user is authenticated via session cookie
+    let principal = if let Some(user) = session_principal(&auth, &headers, &extensions).await {
         user
     } else {
         // User not authenticated - redirect to auth login with original OAuth params
@@ -111,7 +473,7 @@ async fn authorize_handler(
             "/auth/login?mode=redirect&oauth_params={}",
             urlencoding::encode(oauth_params)
         );
-        return Redirect::to(&login_url).into_response();
+        return Redirect::to(&BasePath::url(&extensions, &login_url)).into_response();
     };
 
     // Generate authorization code using openidconnect types
@@ -127,7 +489,6 @@ async fn authorize_handler(
     // to avoid unused-field warnings while keeping behavior unchanged.
     tracing::trace!(
         scope = params.scope.as_deref().unwrap_or(""),
-        code_challenge_method = ?params.code_challenge_method,
         nonce = ?params.nonce,
         prompt = ?params.prompt,
         "authorize request optional params"
@@ -157,20 +518,34 @@ async fn authorize_handler(
 /// POST /token - OAuth token endpoint
 async fn token_handler(
     Extension(auth): Extension<Arc<AuthState>>,
-    axum::extract::Form(params): axum::extract::Form<TokenParams>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    axum::extract::Form(mut params): axum::extract::Form<TokenParams>,
 ) -> impl IntoResponse {
+    let client = auth.client_info(&headers, &extensions);
+    if let Some((client_id, client_secret)) = basic_client_credentials(&headers) {
+        params.client_id = client_id;
+        params.client_secret = Some(client_secret);
+    }
+    if params.grant_type == "client_credentials" {
+        return client_credentials_grant(&auth, &client, params).await;
+    }
+    if params.grant_type == DEVICE_CODE_GRANT_TYPE {
+        return device_code_grant(&auth, &client, params).await;
+    }
     if params.grant_type != "authorization_code" {
         return Json(ErrorResponse {
             error: "unsupported_grant_type".to_string(),
             error_description: Some(
-                "Only 'authorization_code' grant type is supported".to_string(),
+                "Only 'authorization_code', 'client_credentials' and device code grant types are supported"
+                    .to_string(),
             ),
             error_uri: None,
         })
         .into_response();
     }
 
-    let code = match params.code {
+    let code = match params.code.clone() {
         Some(c) => c,
         None => {
             return Json(ErrorResponse {
@@ -188,6 +563,9 @@ async fn token_handler(
         match codes.remove(&code) {
             Some(data) => data,
             None => {
+                drop(codes);
+                audit_refused_grant(&auth, &client, &params, None, "invalid authorization code")
+                    .await;
                 return Json(ErrorResponse {
                     error: "invalid_grant".to_string(),
                     error_description: Some("Invalid or expired authorization code".to_string()),
@@ -200,6 +578,14 @@ async fn token_handler(
 
     // Check expiration
     if SystemTime::now() > expires_at {
+        audit_refused_grant(
+            &auth,
+            &client,
+            &params,
+            Some(&principal),
+            "expired authorization code",
+        )
+        .await;
         return Json(ErrorResponse {
             error: "invalid_grant".to_string(),
             error_description: Some("Authorization code has expired".to_string()),
@@ -210,6 +596,14 @@ async fn token_handler(
 
     // Validate client_id matches
     if client_id != params.client_id {
+        audit_refused_grant(
+            &auth,
+            &client,
+            &params,
+            Some(&principal),
+            "client id mismatch",
+        )
+        .await;
         return Json(ErrorResponse {
             error: "invalid_client".to_string(),
             error_description: Some("Client ID mismatch".to_string()),
@@ -218,16 +612,48 @@ async fn token_handler(
         .into_response();
     }
 
-    // Mark client_secret as observed (do not log or echo it) so callers
-    // that provide it (confidential clients) don't trigger an unused-field
-    // warning. Proper client-secret validation can be added later if needed.
-    let _ = params.client_secret.as_ref();
+    // Registered confidential clients must authenticate with their secret
+    if let Some(registered) = auth.registered_client(&client_id).await
+        && let Some(secret_hash) = registered.secret_hash.as_deref()
+        && !params
+            .client_secret
+            .as_deref()
+            .is_some_and(|secret| client_secret_matches(secret_hash, secret))
+    {
+        audit_refused_grant(
+            &auth,
+            &client,
+            &params,
+            Some(&principal),
+            "invalid client secret",
+        )
+        .await;
+        return oauth_error(
+            StatusCode::UNAUTHORIZED,
+            "invalid_client",
+            "Invalid client credentials",
+        );
+    }
 
-    // Validate PKCE if present using openidconnect types
-    if let (Some(challenge), Some(verifier)) = (code_challenge, params.code_verifier) {
-        let pkce_verifier = PkceCodeVerifier::new(verifier);
-        let computed_challenge = PkceCodeChallenge::from_code_verifier_sha256(&pkce_verifier);
-        if challenge != computed_challenge.as_str() {
+    // A code issued with a PKCE challenge is only exchanged with its verifier
+    if let Some(challenge) = code_challenge {
+        let verified = params
+            .code_verifier
+            .as_deref()
+            .filter(|verifier| valid_code_verifier(verifier))
+            .is_some_and(|verifier| {
+                let pkce_verifier = PkceCodeVerifier::new(verifier.to_string());
+                PkceCodeChallenge::from_code_verifier_sha256(&pkce_verifier).as_str() == challenge
+            });
+        if !verified {
+            audit_refused_grant(
+                &auth,
+                &client,
+                &params,
+                Some(&principal),
+                "PKCE verification failed",
+            )
+            .await;
             return Json(ErrorResponse {
                 error: "invalid_grant".to_string(),
                 error_description: Some("PKCE verification failed".to_string()),
@@ -243,6 +669,14 @@ async fn token_handler(
         params.redirect_uri.as_deref(),
     ) {
         (Some(stored), Some(provided)) if stored != provided => {
+            audit_refused_grant(
+                &auth,
+                &client,
+                &params,
+                Some(&principal),
+                "redirect URI mismatch",
+            )
+            .await;
             return Json(ErrorResponse {
                 error: "invalid_grant".to_string(),
                 error_description: Some("Redirect URI mismatch".to_string()),
@@ -251,6 +685,14 @@ async fn token_handler(
             .into_response();
         }
         (Some(_), None) => {
+            audit_refused_grant(
+                &auth,
+                &client,
+                &params,
+                Some(&principal),
+                "redirect URI missing",
+            )
+            .await;
             return Json(ErrorResponse {
                 error: "invalid_grant".to_string(),
                 error_description: Some("Redirect URI missing".to_string()),
@@ -272,25 +714,577 @@ async fn token_handler(
         (
             principal.clone(),
             token_expires_at,
-            Some("openid profile email".to_string()),
+            Some(AUTHORIZATION_CODE_SCOPE.to_string()),
         ),
     );
 
     // Create ID token with actual user data (signed if signer configured)
     let id_token = create_id_token(&auth, &params.client_id, &principal).await;
+    audit_issued_token(&auth, &client, &params, &principal).await;
 
     let response = OAuthTokenResponse {
         access_token: access_token.secret().clone(),
         token_type: "Bearer".to_string(),
         expires_in,
         refresh_token: Some(generate_secure_token()),
-        scope: Some("openid profile email".to_string()),
+        scope: Some(AUTHORIZATION_CODE_SCOPE.to_string()),
         id_token: Some(id_token),
     };
 
     (StatusCode::OK, Json(response)).into_response()
 }
 
+/// Handles the client-credentials grant for service accounts.
+///
+/// The client id is the service account id. Issued access tokens carry the
+/// requested scopes ("api" and/or "mcp", default both, optionally restricted to
+/// tools with `tool:`/`plugin:` entries); no ID or refresh token is returned.
+async fn client_credentials_grant(
+    auth: &AuthState,
+    client: &ClientInfo,
+    params: TokenParams,
+) -> Response {
+    let Some(principal) = (match params.client_secret.as_deref() {
+        Some(secret) => {
+            auth.authenticate_client_credentials(&params.client_id, secret)
+                .await
+        }
+        None => None,
+    }) else {
+        tracing::warn!(
+            "Client credentials rejected for client_id={}",
+            params.client_id
+        );
+        auth.audit(
+            AuthEvent::new(AuthEventKind::TokenFailed, client)
+                .provider(&ProviderKind::Service)
+                .subject(&params.client_id)
+                .detail("client_credentials grant: invalid client credentials"),
+        )
+        .await;
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "invalid_client".to_string(),
+                error_description: Some("Invalid client credentials".to_string()),
+                error_uri: None,
+            }),
+        )
+            .into_response();
+    };
+
+    let scope = params
+        .scope
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("api mcp")
+        .to_string();
+    if !authz::valid_credential_scopes(scope.split_whitespace()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_scope".to_string(),
+                error_description: Some(SCOPES_HINT.to_string()),
+                error_uri: None,
+            }),
+        )
+            .into_response();
+    }
+
+    let access_token = AccessToken::new(generate_secure_token());
+    let expires_in = 3600; // 1 hour
+    let token_expires_at = SystemTime::now() + Duration::from_secs(expires_in);
+    tracing::info!(
+        "Issued client-credentials token for {}",
+        principal.global_id()
+    );
+    audit_issued_token(auth, client, &params, &principal).await;
+    auth.access_tokens.write().await.insert(
+        access_token.secret().clone(),
+        (principal, token_expires_at, Some(scope.clone())),
+    );
+
+    let response = OAuthTokenResponse {
+        access_token: access_token.secret().clone(),
+        token_type: "Bearer".to_string(),
+        expires_in,
+        refresh_token: None,
+        scope: Some(scope),
+        id_token: None,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// POST /device_authorization - starts a device authorization (RFC 8628).
+///
+/// Returns a device code for the client to poll `/token` with, and a user
+/// code the user enters at the verification URI after signing in.
+async fn device_authorization_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Form(params): Form<DeviceAuthorizationParams>,
+) -> Response {
+    if params.client_id.trim().is_empty() {
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "Missing client_id",
+        );
+    }
+    let scope = params
+        .scope
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEVICE_DEFAULT_SCOPE)
+        .to_string();
+    if !scope.split_whitespace().all(|s| {
+        matches!(
+            s,
+            "openid" | "profile" | "email" | API_KEY_SCOPE_API | API_KEY_SCOPE_MCP
+        )
+    }) {
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_scope",
+            "Allowed scopes: openid, profile, email, api, mcp",
+        );
+    }
+    let Some(base_url) = request_base_url(&uri, &headers, &extensions) else {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "Missing host");
+    };
+
+    let device_code = generate_secure_token();
+    let user_code = generate_user_code();
+    let now = SystemTime::now();
+    auth.device_codes.write().await.insert(
+        device_code.clone(),
+        DeviceAuthorization {
+            client_id: params.client_id.clone(),
+            user_code: user_code.clone(),
+            scope,
+            expires_at: now + DEVICE_CODE_LIFETIME,
+            interval: DEVICE_POLL_INTERVAL,
+            next_poll: now,
+            decision: None,
+        },
+    );
+    tracing::debug!(
+        "Device authorization started for client_id={}",
+        params.client_id
+    );
+
+    let verification_uri = format!("{}/device", base_url);
+    let response = DeviceAuthorizationResponse {
+        verification_uri_complete: format!(
+            "{}?user_code={}",
+            verification_uri,
+            urlencoding::encode(&user_code)
+        ),
+        verification_uri,
+        device_code,
+        user_code,
+        expires_in: DEVICE_CODE_LIFETIME.as_secs(),
+        interval: DEVICE_POLL_INTERVAL.as_secs(),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// GET /device - the device verification page.
+///
+/// Sends signed-out users through the login flow and back. Without a user
+/// code (or with an unknown one) it asks for the code; otherwise it shows the
+/// requesting client and scope for the user to approve or deny.
+async fn device_page_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Query(params): Query<DevicePageParams>,
+) -> Response {
+    let user_code = params.user_code.as_deref().unwrap_or("");
+    let Some(principal) = session_principal(&auth, &headers, &extensions).await else {
+        let return_to = if user_code.is_empty() {
+            "/device".to_string()
+        } else {
+            format!("/device?user_code={}", urlencoding::encode(user_code))
+        };
+        let login_url = format!(
+            "/auth/login?mode=redirect&return_to={}",
+            urlencoding::encode(&return_to)
+        );
+        return Redirect::to(&BasePath::url(&extensions, &login_url)).into_response();
+    };
+    if user_code.is_empty() {
+        return Html(device_code_page(&extensions, None)).into_response();
+    }
+
+    let now = SystemTime::now();
+    let pending = auth
+        .device_codes
+        .read()
+        .await
+        .values()
+        .find(|d| {
+            d.decision.is_none() && d.expires_at > now && user_codes_match(&d.user_code, user_code)
+        })
+        .cloned();
+    match pending {
+        Some(device) => {
+            let csrf_token =
+                csrf::token_for_session(csrf::session_id(&headers).unwrap_or_default());
+            Html(device_confirm_page(
+                &extensions,
+                &device,
+                &principal,
+                &csrf_token,
+            ))
+            .into_response()
+        }
+        None => (
+            StatusCode::BAD_REQUEST,
+            Html(device_code_page(
+                &extensions,
+                Some("Unknown or expired code"),
+            )),
+        )
+            .into_response(),
+    }
+}
+
+/// POST /device - records the signed-in user's decision for a user code.
+async fn device_decision_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Form(form): Form<DeviceDecisionForm>,
+) -> Response {
+    let Some(principal) = session_principal(&auth, &headers, &extensions).await else {
+        return (StatusCode::UNAUTHORIZED, "Authentication required").into_response();
+    };
+    if !csrf::session_id(&headers).is_some_and(|sid| csrf::verify(sid, form.csrf_token.as_deref()))
+    {
+        tracing::warn!(
+            "Rejected device decision from {}: missing or invalid CSRF token",
+            principal.global_id()
+        );
+        return (StatusCode::FORBIDDEN, "Missing or invalid CSRF token").into_response();
+    }
+    let approve = match form.action.as_str() {
+        "approve" => true,
+        "deny" => false,
+        _ => return (StatusCode::BAD_REQUEST, "Invalid action").into_response(),
+    };
+
+    let now = SystemTime::now();
+    let mut device_codes = auth.device_codes.write().await;
+    let Some(device) = device_codes.values_mut().find(|d| {
+        d.decision.is_none()
+            && d.expires_at > now
+            && user_codes_match(&d.user_code, &form.user_code)
+    }) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Html(device_code_page(
+                &extensions,
+                Some("Unknown or expired code"),
+            )),
+        )
+            .into_response();
+    };
+
+    tracing::info!(
+        "Device authorization for client_id={} {} by {}",
+        device.client_id,
+        if approve { "approved" } else { "denied" },
+        principal.global_id()
+    );
+    let message = if approve {
+        device.decision = Some(DeviceDecision::Approved(Box::new(principal)));
+        "Device approved. You can close this window and return to your device."
+    } else {
+        device.decision = Some(DeviceDecision::Denied);
+        "Request denied. You can close this window."
+    };
+    Html(format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Ark device sign in</title></head>\n<body><h1>{}</h1></body></html>",
+        message
+    ))
+    .into_response()
+}
+
+/// Handles the device code grant: the client polls until the user decides.
+///
+/// Answers `authorization_pending` until then, `slow_down` (and a longer
+/// interval) when polled too fast, and issues an access token for the
+/// approving user.
+async fn device_code_grant(auth: &AuthState, client: &ClientInfo, params: TokenParams) -> Response {
+    let Some(device_code) = params.device_code.as_deref() else {
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "Missing device_code",
+        );
+    };
+
+    let now = SystemTime::now();
+    let (principal, scope) = {
+        let mut device_codes = auth.device_codes.write().await;
+        let Some(device) = device_codes
+            .get_mut(device_code)
+            .filter(|d| d.client_id == params.client_id)
+        else {
+            drop(device_codes);
+            audit_refused_grant(auth, client, &params, None, "invalid device code").await;
+            return oauth_error(
+                StatusCode::BAD_REQUEST,
+                "invalid_grant",
+                "Invalid device code",
+            );
+        };
+        if device.expires_at <= now {
+            device_codes.remove(device_code);
+            return oauth_error(
+                StatusCode::BAD_REQUEST,
+                "expired_token",
+                "The device code has expired",
+            );
+        }
+        match device.decision.clone() {
+            None => {
+                if now < device.next_poll {
+                    device.interval += DEVICE_POLL_INTERVAL;
+                    device.next_poll = now + device.interval;
+                    return oauth_error(
+                        StatusCode::BAD_REQUEST,
+                        "slow_down",
+                        "Polling too frequently",
+                    );
+                }
+                device.next_poll = now + device.interval;
+                return oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "authorization_pending",
+                    "The user has not yet approved the request",
+                );
+            }
+            Some(DeviceDecision::Denied) => {
+                device_codes.remove(device_code);
+                drop(device_codes);
+                audit_refused_grant(auth, client, &params, None, "denied by the user").await;
+                return oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "access_denied",
+                    "The user denied the request",
+                );
+            }
+            Some(DeviceDecision::Approved(principal)) => {
+                let scope = device.scope.clone();
+                device_codes.remove(device_code);
+                (*principal, scope)
+            }
+        }
+    };
+
+    let access_token = AccessToken::new(generate_secure_token());
+    let expires_in = 3600; // 1 hour
+    let token_expires_at = SystemTime::now() + Duration::from_secs(expires_in);
+    tracing::info!(
+        "Issued device flow token for {} (client_id={})",
+        principal.global_id(),
+        params.client_id
+    );
+    audit_issued_token(auth, client, &params, &principal).await;
+    auth.access_tokens.write().await.insert(
+        access_token.secret().clone(),
+        (principal.clone(), token_expires_at, Some(scope.clone())),
+    );
+    let id_token = if scope.split_whitespace().any(|s| s == "openid") {
+        Some(create_id_token(auth, &params.client_id, &principal).await)
+    } else {
+        None
+    };
+
+    let response = OAuthTokenResponse {
+        access_token: access_token.secret().clone(),
+        token_type: "Bearer".to_string(),
+        expires_in,
+        refresh_token: None,
+        scope: Some(scope),
+        id_token,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Returns the user signed in through the session cookie, if any and if it
+/// was sent by the client the session is bound to.
+async fn session_principal(
+    auth: &AuthState,
+    headers: &HeaderMap,
+    extensions: &Extensions,
+) -> Option<Principal> {
+    let cookie_str = headers.get("cookie")?.to_str().ok()?;
+    let client = auth.client_info(headers, extensions);
+    extract_client_session_user(auth, cookie_str, &client).await
+}
+
+/// Compares user codes ignoring case, spaces and dashes.
+fn user_codes_match(expected: &str, entered: &str) -> bool {
+    let normalize = |code: &str| -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    };
+    let entered = normalize(entered);
+    !entered.is_empty() && normalize(expected) == entered
+}
+
+/// Renders the page asking for a user code.
+fn device_code_page(extensions: &Extensions, error: Option<&str>) -> String {
+    let error_html = error
+        .map(|e| format!(r#"<p class="error">{}</p>"#, html_escape(e)))
+        .unwrap_or_default();
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Ark device sign in</title></head>
+<body>
+<h1>Connect a device</h1>
+{}
+<form method="get" action="{}">
+<label>Code shown on your device <input name="user_code" autocomplete="off" required autofocus></label>
+<button type="submit">Continue</button>
+</form>
+</body></html>"#,
+        error_html,
+        html_escape(&BasePath::url(extensions, "/device"))
+    )
+}
+
+/// Renders the approve/deny page for a pending device authorization.
+fn device_confirm_page(
+    extensions: &Extensions,
+    device: &DeviceAuthorization,
+    principal: &Principal,
+    csrf_token: &str,
+) -> String {
+    let user = principal
+        .email
+        .as_deref()
+        .or(principal.name.as_deref())
+        .unwrap_or(&principal.subject);
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Ark device sign in</title></head>
+<body>
+<h1>Connect a device</h1>
+<p>Client <strong>{}</strong> is requesting access as <strong>{}</strong> (scope: {}).</p>
+<p>Only approve if you started this sign-in and your device shows the code <strong>{}</strong>.</p>
+<form method="post" action="{}">
+<input type="hidden" name="user_code" value="{}">
+<input type="hidden" name="csrf_token" value="{}">
+<button type="submit" name="action" value="approve">Approve</button>
+<button type="submit" name="action" value="deny">Deny</button>
+</form>
+</body></html>"#,
+        html_escape(&device.client_id),
+        html_escape(user),
+        html_escape(&device.scope),
+        html_escape(&device.user_code),
+        html_escape(&BasePath::url(extensions, "/device")),
+        html_escape(&device.user_code),
+        html_escape(csrf_token)
+    )
+}
+
+/// Builds an OAuth error response.
+/// Records a token issued at the token endpoint in the audit trail.
+async fn audit_issued_token(
+    auth: &AuthState,
+    client: &ClientInfo,
+    params: &TokenParams,
+    principal: &Principal,
+) {
+    auth.audit(
+        AuthEvent::new(AuthEventKind::TokenIssued, client)
+            .principal(principal)
+            .detail(format!(
+                "{} grant for client {}",
+                grant_name(&params.grant_type),
+                params.client_id
+            )),
+    )
+    .await;
+}
+
+/// Records a grant refused at the token endpoint in the audit trail.
+async fn audit_refused_grant(
+    auth: &AuthState,
+    client: &ClientInfo,
+    params: &TokenParams,
+    principal: Option<&Principal>,
+    reason: &str,
+) {
+    let mut event = AuthEvent::new(AuthEventKind::TokenFailed, client).detail(format!(
+        "{} grant for client {}: {}",
+        grant_name(&params.grant_type),
+        params.client_id,
+        reason
+    ));
+    if let Some(principal) = principal {
+        event = event.principal(principal);
+    }
+    auth.audit(event).await;
+}
+
+/// Short grant type name for audit details.
+fn grant_name(grant_type: &str) -> &str {
+    if grant_type == DEVICE_CODE_GRANT_TYPE {
+        "device_code"
+    } else {
+        grant_type
+    }
+}
+
+/// Checks the form of a PKCE code verifier: 43 to 128 unreserved characters
+/// (RFC 7636 section 4.1).
+fn valid_code_verifier(verifier: &str) -> bool {
+    (43..=128).contains(&verifier.len())
+        && verifier
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~'))
+}
+
+/// Client credentials from an `Authorization: Basic` header (RFC 6749
+/// section 2.3.1), where both parts are form-urlencoded.
+fn basic_client_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some((
+        urlencoding::decode(client_id).ok()?.into_owned(),
+        urlencoding::decode(client_secret).ok()?.into_owned(),
+    ))
+}
+
+fn oauth_error(status: StatusCode, error: &str, description: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            error_description: Some(description.to_string()),
+            error_uri: None,
+        }),
+    )
+        .into_response()
+}
+
 fn error_redirect(
     redirect_uri: &str,
     error: &str,
@@ -313,6 +1307,19 @@ fn generate_secure_code() -> String {
     URL_SAFE_NO_PAD.encode(bytes)
 }
 
+/// Generates a user code such as `BDFG-HJKL` (about 34 bits of entropy).
+fn generate_user_code() -> String {
+    let mut rng = rand::rng();
+    let chars: Vec<char> = (0..8)
+        .map(|_| USER_CODE_ALPHABET[rng.random_range(0..USER_CODE_ALPHABET.len())] as char)
+        .collect();
+    format!(
+        "{}-{}",
+        chars[..4].iter().collect::<String>(),
+        chars[4..].iter().collect::<String>()
+    )
+}
+
 fn generate_secure_token() -> String {
     let mut rng = rand::rng();
     let bytes: [u8; 48] = rng.random();
@@ -342,8 +1349,8 @@ async fn create_id_token(auth: &AuthState, client_id: &str, principal: &Principa
 
     // If signer configured, use it. Otherwise fallback to legacy unsigned token.
     if let Some(signer) = &auth.signer {
-        let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
-        match signer.sign(header, &claims) {
+        // The signer sets the algorithm of its key
+        match signer.sign(jsonwebtoken::Header::default(), &claims).await {
             Ok(tok) => tok,
             Err(e) => {
                 tracing::warn!("Signer failed, falling back to unsigned token: {}", e);
@@ -0,0 +1,343 @@
+/// Implementation of the caller's profile endpoints.
+///
+/// The console shows who is signed in from `GET /api/me` and keeps its
+/// display preferences with `PATCH /api/me`. Preferences are stored per
+/// principal global id, so they follow the user across sessions and devices.
+///
+/// # Endpoints
+///
+/// - `GET /api/me` - The caller's identity, permissions and preferences
+/// - `PATCH /api/me` - Update the caller's display preferences
+/// - `GET /api/me/usage` - The caller's monthly usage and quotas
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+};
+use hyper::StatusCode;
+use serde_json::{Map, Value, json};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        auth::Principal,
+        authz::{self, Permission},
+        handlers::{
+            service_accounts::finish,
+            stats::{UsageParams, parse_month, principal_usage_to_json},
+            tokens::database,
+        },
+        persist::{Database, UserPreferences},
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+/// Accepted values of the `theme` preference.
+const THEMES: [&str; 3] = ["light", "dark", "system"];
+/// Largest accepted `page_size` preference.
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Returns the caller's profile.
+///
+/// # Endpoint
+/// `GET /api/me`
+///
+/// # Returns
+/// - 200 OK with the caller's identity, roles, groups, permissions, the
+///   number of plugins they own and their preferences (defaults when no
+///   database is configured)
+/// - 401 Unauthorized if the caller is not authenticated
+pub async fn get_me(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/me");
+
+    let response = match principal {
+        None => unauthorized(),
+        Some(Extension(principal)) => {
+            let preferences = match database(&state) {
+                Some(db) => db.get_user_preferences_async(principal.global_id()).await,
+                None => Ok(UserPreferences::default()),
+            };
+            match preferences {
+                Ok(preferences) => (
+                    StatusCode::OK,
+                    Json(profile(&state, &principal, &preferences)),
+                )
+                    .into_response(),
+                Err(e) => {
+                    tracing::error!("Failed to load preferences: {:?}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        StandardizedResponse::as_error(
+                            ErrorCode::Internal,
+                            "Failed to load preferences",
+                            None,
+                        ),
+                    )
+                        .into_response()
+                }
+            }
+        }
+    };
+    finish(response, "/api/me", "GET", start)
+}
+
+/// Updates the caller's display preferences.
+///
+/// The body is a JSON merge patch of the preferences: listed fields are
+/// replaced and fields set to `null` are reset to their default.
+///
+/// # Endpoint
+/// `PATCH /api/me`
+///
+/// # Returns
+/// - 200 OK with the caller's profile, including the updated preferences
+/// - 400 Bad Request for an unknown preference or an invalid value
+/// - 401 Unauthorized if the caller is not authenticated
+/// - 503 Service Unavailable if no database is configured
+pub async fn update_me(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Json(patch): Json<Map<String, Value>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: PATCH /api/me");
+
+    let response = match (principal, database(&state)) {
+        (None, _) => unauthorized(),
+        (_, None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "Preferences require a database",
+                None,
+            ),
+        )
+            .into_response(),
+        (Some(Extension(principal)), Some(db)) => {
+            update_me_inner(&state, &db, &principal, patch).await
+        }
+    };
+    finish(response, "/api/me", "PATCH", start)
+}
+
+async fn update_me_inner(
+    state: &ArkState,
+    db: &Database,
+    principal: &Principal,
+    patch: Map<String, Value>,
+) -> Response {
+    let global_id = principal.global_id();
+    let current = match db.get_user_preferences_async(global_id.clone()).await {
+        Ok(current) => current,
+        Err(e) => {
+            tracing::error!("Failed to load preferences: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to load preferences",
+                    None,
+                ),
+            )
+                .into_response();
+        }
+    };
+    let preferences = match apply_patch(&current, patch) {
+        Ok(preferences) => preferences,
+        Err(detail) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                StandardizedResponse::as_error(
+                    ErrorCode::InvalidRequest,
+                    "Invalid preferences",
+                    Some(&detail),
+                ),
+            )
+                .into_response();
+        }
+    };
+    match db
+        .save_user_preferences_async(global_id, preferences.clone())
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(profile(state, principal, &preferences)),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to save preferences: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to save preferences",
+                    None,
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Returns the caller's usage in a month, against their quotas.
+///
+/// # Endpoint
+/// `GET /api/me/usage?month=YYYY-MM`
+///
+/// # Returns
+/// - 200 OK with the tool calls, compute time and bytes returned in the month
+///   (the current one by default), each with its quota and what remains
+/// - 400 Bad Request for an invalid month
+/// - 401 Unauthorized if the caller is not authenticated
+/// - 503 Service Unavailable if no database is configured
+pub async fn get_my_usage(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Query(params): Query<UsageParams>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/me/usage");
+
+    let response = match (principal, database(&state)) {
+        (None, _) => unauthorized(),
+        (_, None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "Usage accounting requires a database",
+                None,
+            ),
+        )
+            .into_response(),
+        (Some(Extension(principal)), Some(db)) => match parse_month(params.month.as_deref()) {
+            Err(e) => e.into_response(),
+            Ok(month) => {
+                let global_id = principal.global_id();
+                match db.get_principal_usage_async(global_id.clone(), month).await {
+                    Ok(usage) => (
+                        StatusCode::OK,
+                        Json(principal_usage_to_json(
+                            &usage,
+                            state.quota_limits(&global_id),
+                        )),
+                    )
+                        .into_response(),
+                    Err(e) => {
+                        tracing::error!("Failed to load usage: {:?}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            StandardizedResponse::as_error(
+                                ErrorCode::Internal,
+                                "Failed to load usage",
+                                None,
+                            ),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+        },
+    };
+    finish(response, "/api/me/usage", "GET", start)
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        StandardizedResponse::as_error(ErrorCode::Unauthenticated, "Authentication required", None),
+    )
+        .into_response()
+}
+
+/// Serializes the caller's profile for API responses.
+fn profile(state: &ArkState, principal: &Principal, preferences: &UserPreferences) -> Value {
+    let global_id = principal.global_id();
+    let granted = authz::authorizer(state).permissions(principal);
+    let permissions: Vec<Permission> = Permission::ALL
+        .into_iter()
+        .filter(|p| granted.contains(p))
+        .collect();
+    let owned_plugins = state
+        .plugin_registry
+        .catalog
+        .load()
+        .plugin_to_config
+        .values()
+        .filter(|plugin| plugin.owner.as_deref() == Some(global_id.as_str()))
+        .count();
+    json!({
+        "global_id": global_id,
+        "subject": principal.subject,
+        "name": principal.name,
+        "email": principal.email,
+        "picture": principal.picture,
+        "provider": principal.provider,
+        "provider_kind": principal.provider_kind,
+        "groups": principal.groups,
+        "roles": principal.roles,
+        "is_admin": principal.is_admin,
+        "permissions": permissions,
+        "owned_plugins": owned_plugins,
+        "preferences": preferences,
+    })
+}
+
+/// Applies a JSON merge patch to the preferences and validates the result.
+fn apply_patch(
+    current: &UserPreferences,
+    patch: Map<String, Value>,
+) -> Result<UserPreferences, String> {
+    let mut merged = match serde_json::to_value(current) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            merged.remove(&key);
+        } else {
+            merged.insert(key, value);
+        }
+    }
+    let preferences: UserPreferences =
+        serde_json::from_value(Value::Object(merged)).map_err(|e| e.to_string())?;
+
+    if let Some(theme) = &preferences.theme
+        && !THEMES.contains(&theme.as_str())
+    {
+        return Err(format!("theme must be one of {}", THEMES.join(", ")));
+    }
+    if let Some(locale) = &preferences.locale
+        && !is_valid_tag(locale, 35, &['-'])
+    {
+        return Err("locale must be a language tag such as en-US".to_string());
+    }
+    if let Some(time_zone) = &preferences.time_zone
+        && !is_valid_tag(time_zone, 64, &['/', '_', '-', '+'])
+    {
+        return Err("time_zone must be an IANA time zone such as Europe/Bucharest".to_string());
+    }
+    if let Some(page_size) = preferences.page_size
+        && !(1..=MAX_PAGE_SIZE).contains(&page_size)
+    {
+        return Err(format!("page_size must be between 1 and {}", MAX_PAGE_SIZE));
+    }
+    Ok(preferences)
+}
+
+/// Returns true if `value` has 1 to `max_len` ASCII letters, digits or
+/// `extra` characters.
+fn is_valid_tag(value: &str, max_len: usize, extra: &[char]) -> bool {
+    (1..=max_len).contains(&value.len())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || extra.contains(&c))
+}
@@ -0,0 +1,640 @@
+/// Implementation of the service account administration endpoints.
+///
+/// Service accounts are non-human principals (e.g. CI pipelines) with their own
+/// global id (`svc/*/<id>`) and role set. They authenticate either with the
+/// OAuth client-credentials grant at `/token` (client id = account id) or with
+/// API keys issued here, and own the plugins they publish. All endpoints require
+/// an administrator.
+///
+/// # Endpoints
+///
+/// - `GET /api/admin/service-accounts` - List service accounts
+/// - `POST /api/admin/service-accounts` - Create a service account
+/// - `DELETE /api/admin/service-accounts/:id` - Delete an account and revoke its keys
+/// - `POST /api/admin/service-accounts/:id/secret` - Issue or rotate the client secret
+/// - `GET /api/admin/service-accounts/:id/tokens` - List the account's API keys
+/// - `POST /api/admin/service-accounts/:id/tokens` - Issue an API key for the account
+/// - `DELETE /api/admin/service-accounts/:id/tokens/:key_id` - Revoke an account API key
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        audit::ClientInfo,
+        auth::{Principal, generate_client_secret, hash_api_key},
+        authz::Authorizer,
+        handlers::tokens::{CreateTokenRequest, database, issue_api_key, token_to_json},
+        persist::{Database, ServiceAccountRecord},
+        roles::Role,
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+/// Request body for `POST /api/admin/service-accounts`.
+#[derive(Debug, Deserialize)]
+pub struct CreateServiceAccountRequest {
+    /// Account id: 1-64 lowercase letters, digits, '-', '_' or '.'.
+    pub id: String,
+    /// Optional description shown in listings.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Roles to grant; defaults to `["User"]`.
+    #[serde(default)]
+    pub roles: Option<Vec<Role>>,
+    /// Whether to generate a client secret for the client-credentials grant (default true).
+    #[serde(default)]
+    pub client_credentials: Option<bool>,
+}
+
+/// Serializes a service account for API responses, omitting the secret hash.
+fn account_to_json(record: &ServiceAccountRecord) -> Value {
+    json!({
+        "id": record.account_id,
+        "global_id": record.principal().global_id(),
+        "description": record.description,
+        "roles": record.roles,
+        "client_credentials": record.secret_hash.is_some(),
+        "created_by": record.created_by,
+        "created": record.created_utc.to_rfc3339(),
+    })
+}
+
+/// Returns true if `id` is a valid service account id.
+fn is_valid_account_id(id: &str) -> bool {
+    (1..=64).contains(&id.len())
+        && id
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && id.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_' || c == '.'
+        })
+}
+
+/// Resolves the calling administrator and the database, or an error response.
+pub(crate) fn admin_context(
+    state: &ArkState,
+    principal: Option<Extension<Principal>>,
+) -> Result<(Principal, Database), (StatusCode, Json<Value>)> {
+    let principal = require_admin(principal)?;
+    let Some(db) = database(state) else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "This endpoint requires a database",
+                None,
+            ),
+        ));
+    };
+    Ok((principal, db))
+}
+
+/// Requires an authenticated administrator.
+pub(crate) fn require_admin(
+    principal: Option<Extension<Principal>>,
+) -> Result<Principal, (StatusCode, Json<Value>)> {
+    let Some(Extension(principal)) = principal else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            StandardizedResponse::as_error(
+                ErrorCode::Unauthenticated,
+                "Authentication required",
+                None,
+            ),
+        ));
+    };
+    if !principal.is_admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::PermissionDenied,
+                "Admin privileges required",
+                None,
+            ),
+        ));
+    }
+    Ok(principal)
+}
+
+/// Loads a service account, mapping a missing account to 404.
+async fn load_account(
+    db: &Database,
+    account_id: &str,
+) -> Result<ServiceAccountRecord, (StatusCode, Json<Value>)> {
+    match db.get_service_account_async(account_id.to_string()).await {
+        Ok(Some(account)) => Ok(account),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            StandardizedResponse::as_error(ErrorCode::NotFound, "Service account not found", None),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to load service account: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to load service account",
+                    None,
+                ),
+            ))
+        }
+    }
+}
+
+/// Records API metrics for a completed request and converts it into a response.
+pub(crate) fn finish(response: Response, route: &str, method: &str, start: Instant) -> Response {
+    let status = response.status().as_u16();
+    let latency_ms = start.elapsed().as_millis() as f64;
+    crate::metrics::record_api_http(route, method, status, latency_ms);
+    response
+}
+
+/// Lists all service accounts.
+///
+/// # Endpoint
+/// `GET /api/admin/service-accounts`
+///
+/// # Returns
+/// - 200 OK with a JSON array of accounts (secrets are never returned)
+/// - 401/403 if the caller is not an authenticated administrator
+pub async fn list_service_accounts(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/service-accounts");
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((_, db)) => match db.list_service_accounts_async().await {
+            Ok(accounts) => (
+                StatusCode::OK,
+                Json(Value::Array(accounts.iter().map(account_to_json).collect())),
+            )
+                .into_response(),
+            Err(e) => {
+                tracing::error!("Failed to list service accounts: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(
+                        ErrorCode::Internal,
+                        "Failed to list service accounts",
+                        None,
+                    ),
+                )
+                    .into_response()
+            }
+        },
+    };
+    finish(response, "/api/admin/service-accounts", "GET", start)
+}
+
+/// Creates a service account.
+///
+/// # Endpoint
+/// `POST /api/admin/service-accounts`
+///
+/// # Returns
+/// - 201 Created with the account and, when client credentials are enabled,
+///   the plaintext `client_secret` (shown only once)
+/// - 400 Bad Request for an invalid id, an empty role set or an undefined role
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 409 Conflict if the id is already taken
+pub async fn create_service_account(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Json(payload): Json<CreateServiceAccountRequest>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: POST /api/admin/service-accounts id={}", payload.id);
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((admin, db)) => {
+            let authz = crate::server::authz::authorizer(&state);
+            create_service_account_inner(&db, &authz, admin, payload).await
+        }
+    };
+    finish(response, "/api/admin/service-accounts", "POST", start)
+}
+
+async fn create_service_account_inner(
+    db: &Database,
+    authz: &Authorizer,
+    admin: Principal,
+    payload: CreateServiceAccountRequest,
+) -> Response {
+    if !is_valid_account_id(&payload.id) {
+        return (
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Invalid service account id",
+                Some("Use 1-64 lowercase letters, digits, '-', '_' or '.'"),
+            ),
+        )
+            .into_response();
+    }
+    let mut roles = payload.roles.unwrap_or_else(|| vec![Role::User]);
+    let mut seen = HashSet::new();
+    roles.retain(|role| seen.insert(role.clone()));
+    if roles.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "At least one role is required",
+                None,
+            ),
+        )
+            .into_response();
+    }
+    if let Some(unknown) = roles.iter().find(|r| !authz.is_defined(r)) {
+        return (
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Unknown role",
+                Some(&format!("Role '{}' is not defined in auth.roles", unknown)),
+            ),
+        )
+            .into_response();
+    }
+
+    match db.get_service_account_async(payload.id.clone()).await {
+        Ok(Some(_)) => {
+            return (
+                StatusCode::CONFLICT,
+                StandardizedResponse::as_error(
+                    ErrorCode::AlreadyExists,
+                    "Service account already exists",
+                    None,
+                ),
+            )
+                .into_response();
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to check service account: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to create service account",
+                    None,
+                ),
+            )
+                .into_response();
+        }
+    }
+
+    let client_secret = payload
+        .client_credentials
+        .unwrap_or(true)
+        .then(generate_client_secret);
+    let record = ServiceAccountRecord {
+        account_id: payload.id,
+        description: payload
+            .description
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty()),
+        roles,
+        secret_hash: client_secret.as_deref().map(hash_api_key),
+        created_by: admin.global_id(),
+        created_utc: chrono::Utc::now(),
+    };
+
+    match db.save_service_account_async(record.clone()).await {
+        Ok(()) => {
+            tracing::info!(
+                "Created service account {} (by {})",
+                record.account_id,
+                record.created_by
+            );
+            let mut body = account_to_json(&record);
+            if let (Some(secret), Some(obj)) = (client_secret, body.as_object_mut()) {
+                obj.insert("client_secret".to_string(), json!(secret));
+            }
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to persist service account: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to create service account",
+                    None,
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Deletes a service account and revokes all of its API keys.
+///
+/// Plugins owned by the account are left in place.
+///
+/// # Endpoint
+/// `DELETE /api/admin/service-accounts/:id`
+///
+/// # Returns
+/// - 204 No Content on success
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 404 Not Found if the account does not exist
+pub async fn delete_service_account(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Path(account_id): Path<String>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: DELETE /api/admin/service-accounts/{}", account_id);
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((admin, db)) => match load_account(&db, &account_id).await {
+            Err(e) => e.into_response(),
+            Ok(account) => {
+                let owner = account.principal().global_id();
+                match db
+                    .delete_service_account_async(account_id.clone(), owner)
+                    .await
+                {
+                    Ok(_) => {
+                        tracing::info!(
+                            "Deleted service account {} (by {})",
+                            account_id,
+                            admin.global_id()
+                        );
+                        StatusCode::NO_CONTENT.into_response()
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to delete service account: {:?}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            StandardizedResponse::as_error(
+                                ErrorCode::Internal,
+                                "Failed to delete service account",
+                                None,
+                            ),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+        },
+    };
+    finish(
+        response,
+        "/api/admin/service-accounts/{id}",
+        "DELETE",
+        start,
+    )
+}
+
+/// Issues a new client secret for a service account, replacing any previous one.
+///
+/// Access tokens already issued with the old secret remain valid until they expire.
+///
+/// # Endpoint
+/// `POST /api/admin/service-accounts/:id/secret`
+///
+/// # Returns
+/// - 200 OK with `client_id` and the plaintext `client_secret` (shown only once)
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 404 Not Found if the account does not exist
+pub async fn rotate_service_account_secret(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Path(account_id): Path<String>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!(
+        "API: POST /api/admin/service-accounts/{}/secret",
+        account_id
+    );
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((admin, db)) => match load_account(&db, &account_id).await {
+            Err(e) => e.into_response(),
+            Ok(mut account) => {
+                let secret = generate_client_secret();
+                account.secret_hash = Some(hash_api_key(&secret));
+                match db.save_service_account_async(account).await {
+                    Ok(()) => {
+                        tracing::info!(
+                            "Rotated client secret for service account {} (by {})",
+                            account_id,
+                            admin.global_id()
+                        );
+                        (
+                            StatusCode::OK,
+                            Json(json!({
+                                "client_id": account_id,
+                                "client_secret": secret,
+                            })),
+                        )
+                            .into_response()
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to rotate service account secret: {:?}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            StandardizedResponse::as_error(
+                                ErrorCode::Internal,
+                                "Failed to rotate secret",
+                                None,
+                            ),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+        },
+    };
+    finish(
+        response,
+        "/api/admin/service-accounts/{id}/secret",
+        "POST",
+        start,
+    )
+}
+
+/// Lists the API keys issued to a service account.
+///
+/// # Endpoint
+/// `GET /api/admin/service-accounts/:id/tokens`
+pub async fn list_service_account_tokens(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Path(account_id): Path<String>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/service-accounts/{}/tokens", account_id);
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((_, db)) => match load_account(&db, &account_id).await {
+            Err(e) => e.into_response(),
+            Ok(account) => {
+                match db
+                    .list_api_keys_by_owner_async(account.principal().global_id())
+                    .await
+                {
+                    Ok(records) => (
+                        StatusCode::OK,
+                        Json(Value::Array(records.iter().map(token_to_json).collect())),
+                    )
+                        .into_response(),
+                    Err(e) => {
+                        tracing::error!("Failed to list API keys: {:?}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            StandardizedResponse::as_error(
+                                ErrorCode::Internal,
+                                "Failed to list API keys",
+                                None,
+                            ),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+        },
+    };
+    finish(
+        response,
+        "/api/admin/service-accounts/{id}/tokens",
+        "GET",
+        start,
+    )
+}
+
+/// Issues an API key for a service account.
+///
+/// Accepts the same body as `POST /api/me/tokens`.
+///
+/// # Endpoint
+/// `POST /api/admin/service-accounts/:id/tokens`
+///
+/// # Returns
+/// - 201 Created with the key metadata and plaintext `token` (shown only once)
+/// - 400 Bad Request for an invalid name, scopes or lifetime
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 404 Not Found if the account does not exist
+pub async fn create_service_account_token(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    client: Option<Extension<ClientInfo>>,
+    Path(account_id): Path<String>,
+    Json(payload): Json<CreateTokenRequest>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!(
+        "API: POST /api/admin/service-accounts/{}/tokens",
+        account_id
+    );
+
+    let client = client.map(|Extension(c)| c).unwrap_or_default();
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((admin, db)) => match load_account(&db, &account_id).await {
+            Err(e) => e.into_response(),
+            Ok(account) => issue_api_key(
+                &state,
+                &db,
+                account.principal(),
+                payload,
+                &client,
+                &admin.global_id(),
+            )
+            .await
+            .into_response(),
+        },
+    };
+    finish(
+        response,
+        "/api/admin/service-accounts/{id}/tokens",
+        "POST",
+        start,
+    )
+}
+
+/// Revokes an API key issued to a service account.
+///
+/// # Endpoint
+/// `DELETE /api/admin/service-accounts/:id/tokens/:key_id`
+///
+/// # Returns
+/// - 204 No Content on success
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 404 Not Found if the account or key does not exist
+pub async fn revoke_service_account_token(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Path((account_id, key_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!(
+        "API: DELETE /api/admin/service-accounts/{}/tokens/{}",
+        account_id,
+        key_id
+    );
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((_, db)) => match load_account(&db, &account_id).await {
+            Err(e) => e.into_response(),
+            Ok(account) => {
+                match db
+                    .delete_api_key_async(account.principal().global_id(), key_id)
+                    .await
+                {
+                    Ok(true) => StatusCode::NO_CONTENT.into_response(),
+                    Ok(false) => (
+                        StatusCode::NOT_FOUND,
+                        StandardizedResponse::as_error(
+                            ErrorCode::NotFound,
+                            "API key not found",
+                            None,
+                        ),
+                    )
+                        .into_response(),
+                    Err(e) => {
+                        tracing::error!("Failed to revoke API key: {:?}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            StandardizedResponse::as_error(
+                                ErrorCode::Internal,
+                                "Failed to revoke API key",
+                                None,
+                            ),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+        },
+    };
+    finish(
+        response,
+        "/api/admin/service-accounts/{id}/tokens/{key_id}",
+        "DELETE",
+        start,
+    )
+}
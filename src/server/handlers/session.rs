@@ -4,13 +4,13 @@
 //! endpoints including login, logout, callback processing, and status checking.
 
 use anyhow::Result;
-use axum::http::HeaderMap;
+use axum::http::{Extensions, HeaderMap};
 use axum::{
     Json, Router,
     extract::{Extension, OriginalUri, Query},
     http::{Request, StatusCode, header},
     response::{Html, IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
 };
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 // OsRng is used locally in the helper; no top-level import needed here.
@@ -22,8 +22,15 @@ use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use urlencoding;
 
-use crate::server::auth::{AuthState, PendingAuth, Principal, ProviderKind, ResolvedProvider};
+use crate::server::audit::{AuthEvent, AuthEventKind, ClientInfo};
+use crate::server::auth::{
+    ActiveProvider, AuthState, PendingAuth, Principal, ProviderKind, ResolvedProvider,
+};
+use crate::server::authz::Permission;
+use crate::server::proxy::Forwarded;
 use crate::server::roles::Role;
+use crate::server::saml::ServiceProvider;
+use crate::server::service::BasePath;
 use jsonwebtoken::jwk::JwkSet;
 
 /// Extract OAuth parameters from the query string
@@ -39,6 +46,49 @@ fn extract_oauth_params_from_query(query: &str) -> Option<String> {
     None
 }
 
+/// Reads the `return_to` query parameter: a same-origin path to land on after
+/// login (e.g. the device verification page). Anything else is ignored.
+fn return_to_from_query(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| *key == "return_to")
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|path| path.into_owned())
+        .filter(|path| is_local_path(path))
+}
+
+/// Reads the `provider` query parameter, which selects one of several active
+/// providers.
+fn provider_from_query(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| *key == "provider")
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|name| name.into_owned())
+}
+
+/// Whether `path` is a same-origin absolute path (not `//host` or `/\host`).
+fn is_local_path(path: &str) -> bool {
+    path.starts_with('/') && !path.starts_with("//") && !path.contains('\\')
+}
+
+/// Where to send the browser once a pending login completes: back to
+/// `/authorize` for OAuth flows, to the requested `return_to` path, or to
+/// the console.
+fn post_login_location(pending: &PendingAuth, extensions: &Extensions) -> String {
+    let path = match pending.redirect_to.as_deref() {
+        Some("oauth") => match &pending.oauth_query {
+            Some(q) => format!("/authorize?{}", q),
+            None => "/authorize".to_string(),
+        },
+        Some(path) if is_local_path(path) => path.to_string(),
+        _ => "/".to_string(),
+    };
+    BasePath::url(extensions, &path)
+}
+
 /// Response structure for authentication status endpoint.
 #[derive(serde::Serialize)]
 struct StatusResponse {
@@ -52,12 +102,17 @@ struct StatusResponse {
     /// Whether authentication is disabled.
     #[serde(skip_serializing_if = "Option::is_none")]
     auth_disabled: Option<bool>,
+    /// Permissions held by the authenticated user (used by the console to
+    /// gate access and hide unavailable features).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    permissions: Option<Vec<Permission>>,
 }
 
 /// Creates the authentication router with all auth-related endpoints.
 ///
-/// Sets up routes for `/status`, `/logout`, `/login`, `/callback` endpoints,
-/// all protected by the provided authentication state.
+/// Sets up routes for `/status`, `/logout`, `/login`, `/login/local`, `/callback`
+/// and the SAML `/saml/metadata` and `/saml/acs` endpoints, all protected by the
+/// provided authentication state.
 ///
 /// # Arguments
 ///
@@ -72,13 +127,28 @@ pub fn router(state: Arc<AuthState>) -> Router {
         .route("/.well-known/jwks.json", get(jwks_handler))
         .route("/logout", get(logout_handler))
         .route("/login", get(login_handler))
+        .route(
+            "/login/local",
+            get(local_login_form_handler).post(local_login_handler),
+        )
         .route("/callback", get(callback_handler))
+        .route("/saml/metadata", get(saml_metadata_handler))
+        .route("/saml/acs", post(saml_acs_handler))
         .layer(Extension(state))
 }
 
-async fn jwks_handler(Extension(auth): Extension<Arc<AuthState>>) -> impl IntoResponse {
+/// Handler for GET /.well-known/jwks.json (also served under `/auth`).
+///
+/// Publishes the public key of the local token signer so downstream services
+/// and plugins can verify ark-issued tokens. 404 when no signer is configured.
+pub(crate) async fn jwks_handler(Extension(auth): Extension<Arc<AuthState>>) -> impl IntoResponse {
     if let Some(j) = auth.jwks() {
-        (StatusCode::OK, Json(j)).into_response()
+        (
+            StatusCode::OK,
+            [(header::CACHE_CONTROL, "public, max-age=3600")],
+            Json(j),
+        )
+            .into_response()
     } else {
         (
             StatusCode::NOT_FOUND,
@@ -102,6 +172,7 @@ async fn jwks_handler(Extension(auth): Extension<Arc<AuthState>>) -> impl IntoRe
 async fn status_handler(
     Extension(auth): Extension<Arc<AuthState>>,
     headers: HeaderMap,
+    extensions: Extensions,
 ) -> impl IntoResponse {
     if !auth.enabled {
         // When auth is disabled, return a dummy admin user so frontend doesn't show login
@@ -112,6 +183,7 @@ async fn status_handler(
             picture: None,
             provider: "disabled".to_string(),
             provider_kind: ProviderKind::Oidc,
+            provider_name: None,
             tenant_id: None,
             oid: None,
             groups: vec![],
@@ -125,8 +197,10 @@ async fn status_handler(
                 user: Some(dummy_principal),
                 auth: None,
                 auth_disabled: Some(true),
+                permissions: Some(Permission::ALL.to_vec()),
             }),
-        );
+        )
+            .into_response();
     }
 
     // Check for session cookie
@@ -136,17 +210,32 @@ async fn status_handler(
         for cookie_pair in cookie_str.split(';') {
             let cookie_pair = cookie_pair.trim();
             if let Some(session_id) = cookie_pair.strip_prefix("ark_session=")
-                && let Some(principal) = auth.get_session(session_id).await
+                && let Some(principal) = auth
+                    .get_client_session(session_id, &auth.client_info(&headers, &extensions))
+                    .await
             {
+                let granted = auth.authz.permissions(&principal);
+                let permissions = Permission::ALL
+                    .into_iter()
+                    .filter(|p| granted.contains(p))
+                    .collect();
+                // (Re)issue the CSRF cookie, e.g. for sessions created before it existed
                 return (
                     StatusCode::OK,
+                    [(
+                        header::SET_COOKIE,
+                        auth.session_cookies
+                            .csrf(session_id, auth.session_max_lifetime),
+                    )],
                     Json(StatusResponse {
                         status: "ok",
                         user: Some(principal.clone()),
                         auth: None,
                         auth_disabled: Some(false),
+                        permissions: Some(permissions),
                     }),
-                );
+                )
+                    .into_response();
             }
         }
     }
@@ -158,8 +247,10 @@ async fn status_handler(
             user: None,
             auth: Some("required"),
             auth_disabled: Some(false),
+            permissions: None,
         }),
     )
+        .into_response()
 }
 
 /// Handler for GET /auth/logout - logs out the current user.
@@ -190,16 +281,26 @@ async fn logout_handler(
 
     // Extract session ID from cookie and remove it
     let mut session_removed = false;
+    let mut signed_out = None;
     if let Some(cookie_header) = headers.get(header::COOKIE)
         && let Ok(cookie_str) = cookie_header.to_str()
     {
         for cookie_pair in cookie_str.split(';') {
             let cookie_pair = cookie_pair.trim();
             if let Some(session_id) = cookie_pair.strip_prefix("ark_session=") {
+                let owner = auth.session_owner(session_id).await;
                 // Remove session from database
                 session_removed = auth.delete_session(session_id).await;
                 if session_removed {
                     tracing::debug!("Logout: removed session {}", session_id);
+                    if let Some(principal) = owner {
+                        let client = auth.client_info(&headers, req.extensions());
+                        auth.audit(
+                            AuthEvent::new(AuthEventKind::Logout, &client).principal(&principal),
+                        )
+                        .await;
+                        signed_out = Some((principal.provider_kind, principal.provider_name));
+                    }
                 } else {
                     tracing::warn!("Logout: session {} not found in store", session_id);
                 }
@@ -212,43 +313,29 @@ async fn logout_handler(
         tracing::debug!("Logout: no session found to remove");
     }
 
-    // Get provider for logout URL construction
-    let provider_guard = auth.active.read().await;
-    let provider = match provider_guard.as_ref() {
-        Some(p) => p.clone(),
+    // End the session at the provider the user signed in with
+    let provider = match signed_out {
+        Some((kind, name)) => auth.provider_of_kind(&kind, name.as_deref()).await,
+        None => auth.active.read().await.clone(),
+    };
+    let provider = match provider {
+        Some(p) => p,
         None => {
             // No provider configured, just clear cookie and return
             let mut response = Json(serde_json::json!({"status":"ok"})).into_response();
-            response.headers_mut().insert(
-                header::SET_COOKIE,
-                "ark_session=deleted; Path=/; Max-Age=0; HttpOnly; Secure"
-                    .parse()
-                    .unwrap(),
-            );
+            clear_session_cookies(&auth, &mut response);
             return response;
         }
     };
 
     // Build post-logout redirect URI
-    let scheme = uri.scheme().map(|s| s.as_str()).unwrap_or("http");
-    let authority = if let Some(a) = uri.authority().map(|a| a.as_str()) {
-        a
-    } else if let Some(h) = req.headers().get("host").and_then(|h| h.to_str().ok()) {
-        h
-    } else {
+    let Some(base_url) = request_base_url(&uri, &headers, req.extensions()) else {
         // Fallback to simple cookie clearing if we can't build redirect
         let mut response = Json(serde_json::json!({"status":"ok"})).into_response();
-        response.headers_mut().insert(
-            header::SET_COOKIE,
-            "ark_session=deleted; Path=/; Max-Age=0; HttpOnly; Secure"
-                .parse()
-                .unwrap(),
-        );
+        clear_session_cookies(&auth, &mut response);
         return response;
     };
-
-    let effective_scheme = get_effective_scheme_from_headers(&headers, scheme);
-    let post_logout_redirect = format!("{}://{}/admin", effective_scheme, authority);
+    let post_logout_redirect = format!("{}/admin", base_url);
 
     // Build IDP logout URL
     let logout_url = match provider.provider_kind {
@@ -267,12 +354,41 @@ async fn logout_handler(
         }
         ProviderKind::Oidc => {
             // Generic OIDC - try end_session_endpoint from discovery or fallback
+            let endpoint = provider.end_session_endpoint.clone().unwrap_or_else(|| {
+                format!(
+                    "{}/connect/endsession",
+                    provider.authority.trim_end_matches('/')
+                )
+            });
             format!(
-                "{}/connect/endsession?post_logout_redirect_uri={}",
-                provider.authority.trim_end_matches('/'),
+                "{}?post_logout_redirect_uri={}",
+                endpoint,
+                urlencoding::encode(&post_logout_redirect)
+            )
+        }
+        ProviderKind::Okta | ProviderKind::Keycloak => {
+            // Without an id_token_hint both require client_id to identify the
+            // post-logout redirect registration.
+            format!(
+                "{}?client_id={}&post_logout_redirect_uri={}",
+                provider.end_session_endpoint.clone().unwrap_or_default(),
+                urlencoding::encode(&provider.client_id),
+                urlencoding::encode(&post_logout_redirect)
+            )
+        }
+        ProviderKind::Auth0 => {
+            format!(
+                "{}?client_id={}&returnTo={}",
+                provider.end_session_endpoint.clone().unwrap_or_default(),
+                urlencoding::encode(&provider.client_id),
                 urlencoding::encode(&post_logout_redirect)
             )
         }
+        // Local, directory and service accounts have no external session to end
+        // SAML single logout is not implemented; only the local session ends
+        ProviderKind::Service | ProviderKind::Local | ProviderKind::Ldap | ProviderKind::Saml => {
+            post_logout_redirect.clone()
+        }
     };
 
     // Create redirect response with cookie deletion
@@ -283,16 +399,20 @@ async fn logout_handler(
         .unwrap()
         .into_response();
 
-    response.headers_mut().insert(
-        header::SET_COOKIE,
-        "ark_session=deleted; Path=/; Max-Age=0; HttpOnly; Secure"
-            .parse()
-            .unwrap(),
-    );
+    clear_session_cookies(&auth, &mut response);
 
     response
 }
 
+/// Expires the session and CSRF cookies.
+fn clear_session_cookies(auth: &AuthState, response: &mut Response) {
+    let headers = response.headers_mut();
+    headers.remove(header::SET_COOKIE);
+    for cookie in auth.session_cookies.cleared() {
+        headers.append(header::SET_COOKIE, cookie.parse().unwrap());
+    }
+}
+
 /// Handler for GET /auth/login - initiates OAuth authorization flow.
 ///
 /// Generates PKCE parameters, stores the state for callback validation,
@@ -321,24 +441,87 @@ async fn login_handler(
     if !auth.enabled {
         return (StatusCode::BAD_REQUEST, "auth disabled").into_response();
     }
-    // Read current provider
-    let provider_guard = auth.active.read().await;
+    // With several providers the user picks one, unless the request names it
+    let requested = req.uri().query().and_then(provider_from_query);
+    if requested.is_none() && auth.providers.len() > 1 {
+        return provider_chooser(&auth, req.uri().query().unwrap_or(""), req.extensions());
+    }
+    let Some(active) = auth.active_provider(requested.as_deref()) else {
+        let reason = if requested.is_some() {
+            "unknown provider"
+        } else {
+            "no provider"
+        };
+        return (StatusCode::BAD_REQUEST, reason).into_response();
+    };
+    let provider_guard = active.resolved.read().await;
     let provider = match provider_guard.as_ref() {
         Some(p) => p.clone(),
         None => return (StatusCode::BAD_REQUEST, "no provider").into_response(),
     };
+    // Local and directory accounts sign in through the built-in form instead of an IdP
+    if uses_password_form(&provider.provider_kind) {
+        let mut form_url = BasePath::url(req.extensions(), "/auth/login/local");
+        let mut separator = '?';
+        if let Some(name) = &requested {
+            form_url.push_str("?provider=");
+            form_url.push_str(&urlencoding::encode(name));
+            separator = '&';
+        }
+        let query = req.uri().query().unwrap_or("");
+        if query.contains("mode=redirect") {
+            if let Some(return_to) = return_to_from_query(query) {
+                form_url.push(separator);
+                form_url.push_str("return_to=");
+                form_url.push_str(&urlencoding::encode(&return_to));
+            } else if let Some(oauth_params) = extract_oauth_params_from_query(query) {
+                form_url.push(separator);
+                form_url.push_str("oauth_params=");
+                form_url.push_str(&oauth_params);
+            }
+            return Response::builder()
+                .status(StatusCode::FOUND)
+                .header(header::LOCATION, form_url)
+                .body(axum::body::Body::empty())
+                .unwrap()
+                .into_response();
+        }
+        return (
+            StatusCode::OK,
+            Json(serde_json::json!({ "redirect": form_url })),
+        )
+            .into_response();
+    }
+    // SAML providers redirect to the IdP with an AuthnRequest instead
+    if let Some(sp) = provider.saml.clone() {
+        drop(provider_guard);
+        return saml_login(
+            &auth,
+            &sp,
+            Some(active.name.clone()),
+            &uri,
+            req.headers(),
+            req.extensions(),
+            req.uri().query(),
+        )
+        .await;
+    }
     // Ensure authorization endpoint (perform inline discovery if needed to avoid race with startup async discovery)
     let authz = if let Some(u) = &provider.authorization_endpoint {
         u.clone()
     } else if provider.discovery {
         drop(provider_guard); // release before discovery mutation
-        if let Err(e) =
-            perform_discovery(auth.http.clone(), auth.active.clone(), &provider.authority).await
+        if let Err(e) = perform_discovery(
+            auth.http.clone(),
+            active.resolved.clone(),
+            &provider.authority,
+        )
+        .await
         {
             tracing::warn!(error=%e, "inline OIDC discovery failed in /auth/login");
         }
         // Re-acquire and check again
-        let pg2 = auth.active.read().await;
+        let pg2 = active.resolved.read().await;
         match pg2.as_ref().and_then(|p| p.authorization_endpoint.clone()) {
             Some(u2) => u2,
             None => {
@@ -382,8 +565,10 @@ async fn login_handler(
         .map(|q| q.contains("mode=redirect"))
         .unwrap_or(false);
 
+    let return_to = req.uri().query().and_then(return_to_from_query);
+
     // Extract OAuth parameters if present
-    let oauth_query = if direct_redirect {
+    let oauth_query = if direct_redirect && return_to.is_none() {
         if let Some(query) = req.uri().query() {
             if let Some(oauth_params_encoded) = extract_oauth_params_from_query(query) {
                 // URL decode the OAuth parameters
@@ -407,33 +592,24 @@ async fn login_handler(
         PendingAuth {
             code_verifier,
             created_at: Instant::now(),
-            redirect_to: if direct_redirect {
+            redirect_to: if return_to.is_some() {
+                return_to
+            } else if direct_redirect {
                 Some("oauth".to_string())
             } else {
                 None
             },
             oauth_query,
+            provider: requested,
         },
     );
     let scopes = provider.scopes.join(" ");
-    let scheme = uri.scheme().map(|s| s.as_str()).unwrap_or("http");
-    let authority = if let Some(a) = uri.authority().map(|a| a.as_str()) {
-        a
-    } else if let Some(h) = req.headers().get("host").and_then(|h| h.to_str().ok()) {
-        h
-    } else {
+    let Some(base_url) = request_base_url(&uri, req.headers(), req.extensions()) else {
         return (StatusCode::BAD_REQUEST, "Invalid request URI: missing host").into_response();
     };
-    let effective_scheme = get_effective_scheme_from_headers(req.headers(), scheme);
-    let redirect_uri = format!("{}://{}{}", effective_scheme, authority, "/auth/callback");
+    let redirect_uri = format!("{}{}", base_url, "/auth/callback");
 
-    tracing::debug!(
-        "OAuth login: original_scheme={}, effective_scheme={}, authority={}, redirect_uri={}",
-        scheme,
-        effective_scheme,
-        authority,
-        redirect_uri
-    );
+    tracing::debug!("OAuth login: redirect_uri={}", redirect_uri);
 
     let mut url = format!(
         "{}?response_type=code&client_id={}&scope={}&state={}&code_challenge={}&code_challenge_method=S256&prompt=login",
@@ -445,6 +621,10 @@ async fn login_handler(
     );
     url.push_str("&redirect_uri=");
     url.push_str(&urlencoding::encode(&redirect_uri));
+    if let Some(audience) = &provider.audience {
+        url.push_str("&audience=");
+        url.push_str(&urlencoding::encode(audience));
+    }
     // Support optional mode=redirect for direct 302 flow
     let direct_redirect = req
         .uri()
@@ -462,6 +642,579 @@ async fn login_handler(
     (StatusCode::OK, Json(serde_json::json!({ "redirect": url }))).into_response()
 }
 
+/// Lets the user choose among several active providers.
+///
+/// Browsers (`mode=redirect`) get a page linking to `/auth/login?provider=`
+/// for each provider, keeping the rest of the query (`return_to`,
+/// `oauth_params`). JSON clients get the provider list and a `redirect` to
+/// that page.
+fn provider_chooser(auth: &AuthState, query: &str, extensions: &Extensions) -> Response {
+    let kept: String = query
+        .split('&')
+        .filter(|param| {
+            let key = param.split('=').next().unwrap_or_default();
+            !param.is_empty() && key != "provider" && key != "mode"
+        })
+        .map(|param| format!("&{}", param))
+        .collect();
+    let login_url = |name: &str| {
+        let path = format!(
+            "/auth/login?provider={}&mode=redirect{}",
+            urlencoding::encode(name),
+            kept
+        );
+        BasePath::url(extensions, &path)
+    };
+
+    if !query.contains("mode=redirect") {
+        let providers: Vec<_> = auth
+            .providers
+            .iter()
+            .map(|p| {
+                serde_json::json!({
+                    "name": p.name,
+                    "kind": crate::server::audit::provider_name(&p.kind),
+                    "login": login_url(&p.name),
+                })
+            })
+            .collect();
+        return (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "redirect": BasePath::url(extensions, &format!("/auth/login?mode=redirect{}", kept)),
+                "providers": providers,
+            })),
+        )
+            .into_response();
+    }
+
+    let links: String = auth
+        .providers
+        .iter()
+        .map(|p| {
+            format!(
+                r#"<li><a href="{}">{}</a></li>"#,
+                html_escape(&login_url(&p.name)),
+                html_escape(&p.name)
+            )
+        })
+        .collect();
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Ark sign in</title></head>
+<body>
+<h1>Sign in with</h1>
+<ul>{}</ul>
+</body></html>"#,
+        links
+    ))
+    .into_response()
+}
+
+/// Returns whether the provider signs users in with the built-in
+/// username/password form (`/auth/login/local`).
+fn uses_password_form(kind: &ProviderKind) -> bool {
+    matches!(kind, ProviderKind::Local | ProviderKind::Ldap)
+}
+
+/// Returns the active password-form provider named `name`, or the first one
+/// when no name is given.
+fn password_provider<'a>(auth: &'a AuthState, name: Option<&str>) -> Option<&'a ActiveProvider> {
+    auth.providers
+        .iter()
+        .filter(|p| uses_password_form(&p.kind))
+        .find(|p| name.is_none_or(|name| p.name == name))
+}
+
+/// Form fields posted to `/auth/login/local`.
+#[derive(Debug, serde::Deserialize)]
+struct LocalLoginForm {
+    username: String,
+    password: String,
+    /// Original OAuth authorize query to resume after login.
+    #[serde(default)]
+    oauth_params: Option<String>,
+    /// Same-origin path to land on after login.
+    #[serde(default)]
+    return_to: Option<String>,
+    /// Provider to check the credentials with, when several are active.
+    #[serde(default)]
+    provider: Option<String>,
+}
+
+/// Escapes text for inclusion in HTML content or attribute values.
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Renders the local login form.
+fn local_login_page(
+    extensions: &Extensions,
+    oauth_params: Option<&str>,
+    return_to: Option<&str>,
+    provider: Option<&str>,
+    error: Option<&str>,
+) -> String {
+    let error_html = error
+        .map(|e| format!(r#"<p class="error">{}</p>"#, html_escape(e)))
+        .unwrap_or_default();
+    let hidden = [
+        ("oauth_params", oauth_params),
+        ("return_to", return_to),
+        ("provider", provider),
+    ];
+    let oauth_html: String = hidden
+        .iter()
+        .filter_map(|(name, value)| value.map(|v| (name, v)))
+        .map(|(name, value)| {
+            format!(
+                r#"<input type="hidden" name="{}" value="{}">"#,
+                name,
+                html_escape(value)
+            )
+        })
+        .collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Ark sign in</title></head>
+<body>
+<h1>Sign in</h1>
+{}
+<form method="post" action="{}">
+<label>Username <input name="username" autocomplete="username" required autofocus></label>
+<label>Password <input name="password" type="password" autocomplete="current-password" required></label>
+{}
+<button type="submit">Sign in</button>
+</form>
+</body></html>"#,
+        error_html,
+        html_escape(&BasePath::url(extensions, "/auth/login/local")),
+        oauth_html
+    )
+}
+
+/// Handler for GET /auth/login/local - serves the username/password form used
+/// by the local and LDAP providers.
+async fn local_login_form_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    extensions: Extensions,
+    query: Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let provider = query.get("provider").map(|s| s.as_str());
+    if !auth.enabled || password_provider(&auth, provider).is_none() {
+        return (StatusCode::NOT_FOUND, "local login not enabled").into_response();
+    }
+    Html(local_login_page(
+        &extensions,
+        query.get("oauth_params").map(|s| s.as_str()),
+        query
+            .get("return_to")
+            .map(|s| s.as_str())
+            .filter(|p| is_local_path(p)),
+        provider,
+        None,
+    ))
+    .into_response()
+}
+
+/// Handler for POST /auth/login/local - verifies local or LDAP credentials.
+///
+/// On success creates a session, sets the session cookie and redirects to the
+/// console (or back to `/authorize` when the login was part of an OAuth flow).
+/// On failure re-renders the form with a generic error.
+async fn local_login_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    axum::extract::Form(form): axum::extract::Form<LocalLoginForm>,
+) -> impl IntoResponse {
+    let provider = form.provider.as_deref().filter(|p| !p.is_empty());
+    let active = password_provider(&auth, provider).map(|p| (p.kind.clone(), p.name.clone()));
+    let Some((provider_kind, provider_name)) = active.filter(|_| auth.enabled) else {
+        return (StatusCode::NOT_FOUND, "local login not enabled").into_response();
+    };
+
+    let oauth_params = form.oauth_params.as_deref().filter(|q| !q.is_empty());
+    let return_to = form.return_to.as_deref().filter(|p| is_local_path(p));
+    let client = auth.client_info(&headers, &extensions);
+    if let Some(wait) = auth
+        .login_limiter
+        .check("password", client.ip, Some(&form.username))
+    {
+        tracing::warn!("Password login throttled for user '{}'", form.username);
+        auth.audit(
+            AuthEvent::new(AuthEventKind::LoginFailed, &client)
+                .provider(&provider_kind)
+                .subject(&form.username)
+                .detail("throttled"),
+        )
+        .await;
+        return too_many_attempts(
+            wait,
+            local_login_page(
+                &extensions,
+                oauth_params,
+                return_to,
+                provider,
+                Some("Too many failed attempts, please try again later"),
+            ),
+        );
+    }
+    let Some(principal) = auth
+        .authenticate_password(
+            &provider_kind,
+            Some(&provider_name),
+            &form.username,
+            &form.password,
+        )
+        .await
+    else {
+        tracing::warn!("Password login failed for user '{}'", form.username);
+        sign_in_failed(
+            &auth,
+            &client,
+            "password",
+            Some(&provider_kind),
+            Some(&form.username),
+            "invalid username or password",
+        )
+        .await;
+        return (
+            StatusCode::UNAUTHORIZED,
+            Html(local_login_page(
+                &extensions,
+                oauth_params,
+                return_to,
+                provider,
+                Some("Invalid username or password"),
+            )),
+        )
+            .into_response();
+    };
+
+    tracing::info!("Password login succeeded for {}", principal.global_id());
+    auth.login_limiter.record_success(&form.username);
+    auth.audit(AuthEvent::new(AuthEventKind::Login, &client).principal(&principal))
+        .await;
+    let ttl = auth.session_cookies.ttl;
+    let session_id = auth.put_client_session(principal, ttl, &client).await;
+    let location = match (oauth_params, return_to) {
+        (Some(q), _) => format!("/authorize?{}", q),
+        (None, Some(path)) => path.to_string(),
+        (None, None) => "/".to_string(),
+    };
+    let location = BasePath::url(&extensions, &location);
+    Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header(header::LOCATION, location)
+        .header(
+            header::SET_COOKIE,
+            auth.session_cookies.session(&session_id, ttl),
+        )
+        .header(
+            header::SET_COOKIE,
+            auth.session_cookies.csrf(&session_id, ttl),
+        )
+        .body(axum::body::Body::empty())
+        .unwrap()
+        .into_response()
+}
+
+/// Response for a sign-in attempt refused while its IP or username is backing
+/// off or locked out.
+fn too_many_attempts(wait: Duration, page: String) -> Response {
+    let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Html(page),
+    )
+        .into_response()
+}
+
+/// Counts a failed sign-in against the login limiter and records it in the
+/// audit trail.
+async fn sign_in_failed(
+    auth: &AuthState,
+    client: &ClientInfo,
+    endpoint: &str,
+    provider_kind: Option<&ProviderKind>,
+    username: Option<&str>,
+    reason: &str,
+) {
+    auth.login_limiter
+        .record_failure(endpoint, client.ip, username);
+    let mut event = AuthEvent::new(AuthEventKind::LoginFailed, client).detail(reason);
+    if let Some(kind) = provider_kind {
+        event = event.provider(kind);
+    }
+    if let Some(username) = username {
+        event = event.subject(username);
+    }
+    auth.audit(event).await;
+}
+
+/// Page shown when a callback is refused by the login limiter.
+const TOO_MANY_ATTEMPTS_PAGE: &str =
+    "<h1>Too Many Attempts</h1><p>Too many failed sign-in attempts, please try again later.</p>";
+
+/// Returns the ACS URL: the configured one, or derived from the request host.
+fn saml_acs_url(
+    sp: &ServiceProvider,
+    uri: &axum::http::Uri,
+    headers: &HeaderMap,
+    extensions: &Extensions,
+) -> Option<String> {
+    if let Some(url) = &sp.acs_url {
+        return Some(url.clone());
+    }
+    Some(format!(
+        "{}{}",
+        request_base_url(uri, headers, extensions)?,
+        crate::server::saml::ACS_PATH
+    ))
+}
+
+/// Returns the externally visible base URL of a request: `scheme://host`
+/// followed by the base path, if any.
+///
+/// The scheme and host reported by a trusted proxy take precedence.
+pub(crate) fn request_base_url(
+    uri: &axum::http::Uri,
+    headers: &HeaderMap,
+    extensions: &Extensions,
+) -> Option<String> {
+    let forwarded = extensions.get::<Forwarded>();
+    let authority = forwarded
+        .and_then(|f| f.host.as_deref())
+        .or_else(|| uri.authority().map(|a| a.as_str()))
+        .or_else(|| headers.get(header::HOST).and_then(|h| h.to_str().ok()))?;
+    let scheme = match forwarded.and_then(|f| f.proto.clone()) {
+        Some(proto) => proto,
+        None => get_effective_scheme_from_headers(
+            headers,
+            uri.scheme().map(|s| s.as_str()).unwrap_or("http"),
+        ),
+    };
+    Some(format!(
+        "{}://{}{}",
+        scheme,
+        authority,
+        BasePath::url(extensions, "")
+    ))
+}
+
+/// Returns the active SAML service provider named `name` (the first one
+/// without a name) with its resolved provider, if authentication is enabled
+/// and such a SAML provider is active.
+async fn active_saml_provider(
+    auth: &AuthState,
+    name: Option<&str>,
+) -> Option<(ResolvedProvider, Arc<ServiceProvider>)> {
+    if !auth.enabled {
+        return None;
+    }
+    let provider = auth.provider_of_kind(&ProviderKind::Saml, name).await?;
+    let sp = provider.saml.clone()?;
+    Some((provider, sp))
+}
+
+/// Starts a SAML login: records the pending AuthnRequest under a random
+/// relay state and redirects (or returns the URL) to the IdP.
+async fn saml_login(
+    auth: &AuthState,
+    sp: &ServiceProvider,
+    provider: Option<String>,
+    uri: &axum::http::Uri,
+    headers: &HeaderMap,
+    extensions: &Extensions,
+    query: Option<&str>,
+) -> Response {
+    let Some(acs_url) = saml_acs_url(sp, uri, headers, extensions) else {
+        return (StatusCode::BAD_REQUEST, "Invalid request URI: missing host").into_response();
+    };
+    let query = query.unwrap_or("");
+    let direct_redirect = query.contains("mode=redirect");
+    let return_to = return_to_from_query(query);
+    let oauth_query = if direct_redirect && return_to.is_none() {
+        extract_oauth_params_from_query(query)
+            .and_then(|q| urlencoding::decode(&q).ok().map(|d| d.into_owned()))
+    } else {
+        None
+    };
+
+    let relay_state = random_urlsafe(32);
+    let request_id = format!("_{}", random_urlsafe(16));
+    let url = match sp.authn_request_url(&request_id, &acs_url, &relay_state) {
+        Ok(url) => url,
+        Err(e) => {
+            tracing::error!("Failed to build SAML AuthnRequest: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "SAML request failed").into_response();
+        }
+    };
+    auth.pending.write().await.insert(
+        relay_state,
+        PendingAuth {
+            code_verifier: request_id,
+            created_at: Instant::now(),
+            redirect_to: return_to.or_else(|| direct_redirect.then(|| "oauth".to_string())),
+            oauth_query,
+            provider,
+        },
+    );
+
+    if direct_redirect {
+        return Response::builder()
+            .status(StatusCode::FOUND)
+            .header(header::LOCATION, url)
+            .body(axum::body::Body::empty())
+            .unwrap()
+            .into_response();
+    }
+    (StatusCode::OK, Json(serde_json::json!({ "redirect": url }))).into_response()
+}
+
+/// Handler for GET /auth/saml/metadata - serves the SP metadata document of
+/// the SAML provider named by `?provider=` (default: the first one).
+async fn saml_metadata_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    extensions: Extensions,
+) -> impl IntoResponse {
+    let name = uri.query().and_then(provider_from_query);
+    let Some((_, sp)) = active_saml_provider(&auth, name.as_deref()).await else {
+        return (StatusCode::NOT_FOUND, "SAML not enabled").into_response();
+    };
+    let Some(acs_url) = saml_acs_url(&sp, &uri, &headers, &extensions) else {
+        return (StatusCode::BAD_REQUEST, "Invalid request URI: missing host").into_response();
+    };
+    (
+        [(header::CONTENT_TYPE, "application/samlmetadata+xml")],
+        sp.metadata(&acs_url),
+    )
+        .into_response()
+}
+
+/// Form fields posted by the IdP to `/auth/saml/acs` (HTTP-POST binding).
+#[derive(Debug, serde::Deserialize)]
+struct SamlAcsForm {
+    #[serde(rename = "SAMLResponse")]
+    saml_response: String,
+    #[serde(rename = "RelayState", default)]
+    relay_state: Option<String>,
+}
+
+/// Handler for POST /auth/saml/acs - the SAML assertion consumer service.
+///
+/// Matches the response to a pending login through its relay state, validates
+/// the signed assertion, creates a session and redirects to the console (or
+/// back to `/authorize` when the login was part of an OAuth flow).
+async fn saml_acs_handler(
+    Extension(auth): Extension<Arc<AuthState>>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    extensions: Extensions,
+    axum::extract::Form(form): axum::extract::Form<SamlAcsForm>,
+) -> impl IntoResponse {
+    if active_saml_provider(&auth, None).await.is_none() {
+        return (StatusCode::NOT_FOUND, "SAML not enabled").into_response();
+    }
+    let client = auth.client_info(&headers, &extensions);
+    if let Some(wait) = auth.login_limiter.check("saml_acs", client.ip, None) {
+        return too_many_attempts(wait, TOO_MANY_ATTEMPTS_PAGE.to_string());
+    }
+    // Unsolicited (IdP-initiated) responses carry no known relay state
+    let pending = match form.relay_state.as_deref() {
+        Some(state) => auth.pending.write().await.remove(state),
+        None => None,
+    };
+    let Some(pending) = pending else {
+        tracing::warn!("SAML response without a pending login request");
+        sign_in_failed(
+            &auth,
+            &client,
+            "saml_acs",
+            Some(&ProviderKind::Saml),
+            None,
+            "no pending login request",
+        )
+        .await;
+        return (
+            StatusCode::BAD_REQUEST,
+            Html("<h1>Invalid or expired authentication state</h1>"),
+        )
+            .into_response();
+    };
+    // Validate with the provider the login was started with
+    let Some((provider, sp)) = active_saml_provider(&auth, pending.provider.as_deref()).await
+    else {
+        return (StatusCode::NOT_FOUND, "SAML not enabled").into_response();
+    };
+    let Some(acs_url) = saml_acs_url(&sp, &uri, &headers, &extensions) else {
+        return (StatusCode::BAD_REQUEST, "Invalid request URI: missing host").into_response();
+    };
+
+    let assertion =
+        match sp.validate_response(&form.saml_response, &acs_url, &pending.code_verifier) {
+            Ok(a) => a,
+            Err(e) => {
+                tracing::warn!("Rejected SAML response: {:#}", e);
+                sign_in_failed(
+                    &auth,
+                    &client,
+                    "saml_acs",
+                    Some(&provider.provider_kind),
+                    None,
+                    "invalid SAML response",
+                )
+                .await;
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Html("<h1>Authentication Error</h1><p>The SAML response was rejected.</p>"),
+                )
+                    .into_response();
+            }
+        };
+
+    let mut principal = sp.principal(&assertion, provider.groups.as_ref());
+    principal.provider_name = pending.provider.clone();
+    if !crate::server::roles::assign_group_roles(&mut principal, provider.groups.as_ref()) {
+        auth.audit(
+            AuthEvent::new(AuthEventKind::LoginFailed, &client)
+                .principal(&principal)
+                .detail("not in required group"),
+        )
+        .await;
+        return (StatusCode::FORBIDDEN, Html(ACCESS_DENIED_PAGE)).into_response();
+    }
+
+    tracing::info!("SAML login succeeded for {}", principal.global_id());
+    auth.audit(AuthEvent::new(AuthEventKind::Login, &client).principal(&principal))
+        .await;
+    let ttl = auth.session_cookies.ttl;
+    let session_id = auth.put_client_session(principal, ttl, &client).await;
+    let location = post_login_location(&pending, &extensions);
+    Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header(header::LOCATION, location)
+        .header(
+            header::SET_COOKIE,
+            auth.session_cookies.session(&session_id, ttl),
+        )
+        .header(
+            header::SET_COOKIE,
+            auth.session_cookies.csrf(&session_id, ttl),
+        )
+        .body(axum::body::Body::empty())
+        .unwrap()
+        .into_response()
+}
+
 /// Handler for GET /auth/callback - processes OAuth authorization code.
 ///
 /// Validates the callback parameters, exchanges the code for tokens (TODO),
@@ -480,6 +1233,7 @@ async fn callback_handler(
     Extension(auth): Extension<Arc<AuthState>>,
     OriginalUri(uri): OriginalUri,
     headers: axum::http::HeaderMap,
+    extensions: Extensions,
     query: Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
     if !auth.enabled {
@@ -503,10 +1257,22 @@ async fn callback_handler(
 
     if let Some(err) = error {
         tracing::warn!("OAuth callback error: {}", err);
+        auth.audit(
+            AuthEvent::new(
+                AuthEventKind::LoginFailed,
+                &auth.client_info(&headers, &extensions),
+            )
+            .detail(format!("identity provider error: {}", err)),
+        )
+        .await;
         return Html(format!(r#"<h1>Authentication Error</h1><p>{}</p>"#, err)).into_response();
     }
 
     if let (Some(code), Some(state)) = (code, state) {
+        let client = auth.client_info(&headers, &extensions);
+        if let Some(wait) = auth.login_limiter.check("oauth_callback", client.ip, None) {
+            return too_many_attempts(wait, TOO_MANY_ATTEMPTS_PAGE.to_string());
+        }
         // Validate state parameter format for security
         if state.len() > 256
             || !state
@@ -517,19 +1283,30 @@ async fn callback_handler(
                 "Invalid state parameter format in OAuth callback: '{}'",
                 state
             );
+            sign_in_failed(
+                &auth,
+                &client,
+                "oauth_callback",
+                None,
+                None,
+                "invalid state format",
+            )
+            .await;
             return Html("<h1>Invalid authentication state format</h1>").into_response();
         }
         let pending_auth = auth.pending.write().await.remove(state);
 
         if let Some(pending) = pending_auth {
-            let provider = match auth.active.read().await.clone() {
-                Some(p) => p,
-                None => {
-                    return Html("<h1>No active authentication provider configured.</h1>")
-                        .into_response();
-                }
+            // Finish the login with the provider it was started with
+            let active = auth.active_provider(pending.provider.as_deref());
+            let provider = match active {
+                Some(active) => active.resolved.read().await.clone(),
+                None => None,
+            };
+            let Some(provider) = provider else {
+                return Html("<h1>No active authentication provider configured.</h1>")
+                    .into_response();
             };
-
             let (token_url, jwks_uri) = match (
                 provider.token_endpoint.as_ref(),
                 provider.jwks_uri.as_ref(),
@@ -540,17 +1317,10 @@ async fn callback_handler(
                 }
             };
 
-            let scheme = uri.scheme_str().unwrap_or("http");
-            let authority = if let Some(auth) = uri.authority() {
-                auth.as_str()
-            } else if let Some(host) = headers.get(header::HOST).and_then(|h| h.to_str().ok()) {
-                host
-            } else {
+            let Some(base_url) = request_base_url(&uri, &headers, &extensions) else {
                 return Html("<h1>Missing host header</h1>").into_response();
             };
-
-            let effective_scheme = get_effective_scheme_from_headers(&headers, scheme);
-            let redirect_uri = format!("{}://{}{}", effective_scheme, authority, "/auth/callback");
+            let redirect_uri = format!("{}{}", base_url, "/auth/callback");
 
             // Exchange code for tokens
             let token_response = match auth
@@ -567,6 +1337,15 @@ async fn callback_handler(
                 Ok(res) => res,
                 Err(e) => {
                     tracing::error!("Failed to exchange code for tokens: {}", e);
+                    sign_in_failed(
+                        &auth,
+                        &client,
+                        "oauth_callback",
+                        Some(&provider.provider_kind),
+                        None,
+                        "token exchange failed",
+                    )
+                    .await;
                     return Html(format!(
                         "<h1>Token Exchange Failed</h1><p>Could not get tokens: {}</p>",
                         e
@@ -604,6 +1383,15 @@ async fn callback_handler(
                 Ok(c) => c,
                 Err(e) => {
                     tracing::error!("ID token validation failed: {}", e);
+                    sign_in_failed(
+                        &auth,
+                        &client,
+                        "oauth_callback",
+                        Some(&provider.provider_kind),
+                        None,
+                        "invalid ID token",
+                    )
+                    .await;
                     return Html(format!("<h1>Invalid Token</h1><p>{}</p>", e)).into_response();
                 }
             };
@@ -617,12 +1405,25 @@ async fn callback_handler(
                     claims.tid,
                     claims.oid
                 );
+                sign_in_failed(
+                    &auth,
+                    &client,
+                    "oauth_callback",
+                    Some(&provider.provider_kind),
+                    None,
+                    "missing AAD claims",
+                )
+                .await;
                 return Html(
                     "<h1>Invalid Token</h1><p>Missing required AAD claims (tid or oid)</p>",
                 )
                 .into_response();
             }
 
+            // Group membership comes from the configured claim (default "groups")
+            let groups =
+                crate::server::roles::groups_from_claims(&claims, provider.groups.as_ref());
+
             // Create principal from claims (carry provider_kind into the session)
             let mut principal = Principal {
                 subject: claims.sub,
@@ -631,13 +1432,23 @@ async fn callback_handler(
                 picture: claims.picture,
                 provider: provider.authority.clone(),
                 provider_kind: provider.provider_kind.clone(),
+                provider_name: active.map(|active| active.name.clone()),
                 tenant_id: claims.tid,
                 oid: claims.oid,
                 roles: vec![Role::User], // Default role
                 is_admin: false,         // Will be updated below
-                groups: claims.groups.unwrap_or_default(),
+                groups,
             };
 
+            // Keycloak's group membership mapper emits full paths ("/admins") by default
+            if provider.provider_kind == ProviderKind::Keycloak {
+                for group in principal.groups.iter_mut() {
+                    if let Some(stripped) = group.strip_prefix('/') {
+                        *group = stripped.to_string();
+                    }
+                }
+            }
+
             // Fetch profile picture for Microsoft users
             if provider.provider_kind == ProviderKind::Microsoft {
                 if let Some(profile_photo) = auth
@@ -658,66 +1469,26 @@ async fn callback_handler(
             }
 
             // Assign roles based on group membership
-            if let Some(groups) = &provider.groups {
-                if let Some(admin_group) = &groups.admin {
-                    tracing::debug!(
-                        "Role assignment: user groups = {:?}, admin_group = {}, provider = {}",
-                        principal.groups,
-                        admin_group,
-                        provider.authority
-                    );
-                    if principal.groups.iter().any(|g| g == admin_group) {
-                        principal.roles.push(Role::Admin);
-                        principal.is_admin = true;
-                        tracing::debug!("User assigned Admin role");
-                    } else {
-                        tracing::debug!("User not in admin group, keeping User role only");
-                    }
-                } else {
-                    tracing::debug!("No admin group configured, user gets User role only");
-                }
-
-                // Check user group restriction for non-admin users
-                if !principal.is_admin
-                    && let Some(users_group) = &groups.users
-                    && !principal.groups.iter().any(|g| g == users_group)
-                {
-                    tracing::warn!(
-                        "Access denied: user not in required users group '{}', user groups = {:?}",
-                        users_group,
-                        principal.groups
-                    );
-                    return Html(
-                        "<h1>Access Denied</h1><p>You are not authorized to access this system. Please contact your administrator.</p>"
-                            .to_string(),
-                    )
-                    .into_response();
-                }
-            } else {
-                tracing::debug!("No groups configured, user gets User role only");
+            if !crate::server::roles::assign_group_roles(&mut principal, provider.groups.as_ref()) {
+                auth.audit(
+                    AuthEvent::new(AuthEventKind::LoginFailed, &client)
+                        .principal(&principal)
+                        .detail("not in required group"),
+                )
+                .await;
+                return Html(ACCESS_DENIED_PAGE).into_response();
             }
+            auth.audit(AuthEvent::new(AuthEventKind::Login, &client).principal(&principal))
+                .await;
 
-            // Create session
-            let session_id = auth.put_session(principal, Duration::from_secs(3600)).await;
-            let cookie_value = format!(
-                "ark_session={}; Path=/; HttpOnly; SameSite=Lax; Max-Age=3600; Secure",
-                session_id
-            );
+            // Create session (renewable when the IdP issued a refresh token)
+            let (session_id, ttl) = auth
+                .put_session_with_tokens(principal, &token_response, &client)
+                .await;
 
-            // Check if this specific auth was initiated from OAuth flow
-            let redirect_location = if pending.redirect_to.as_deref() == Some("oauth") {
-                if let Some(query) = &pending.oauth_query {
-                    let oauth_path = format!("/authorize?{}", query);
-                    tracing::debug!("Redirecting to OAuth path: {}", oauth_path);
-                    oauth_path
-                } else {
-                    tracing::debug!("Redirecting to OAuth authorize endpoint");
-                    "/authorize".to_string()
-                }
-            } else {
-                tracing::debug!("Redirecting to root - no OAuth flow found");
-                "/".to_string()
-            };
+            // Resume the OAuth flow or requested page that started this login
+            let redirect_location = post_login_location(&pending, &extensions);
+            tracing::debug!("Redirecting after login to {}", redirect_location);
 
             // Redirect with session cookie
             let mut response = Response::builder()
@@ -727,25 +1498,27 @@ async fn callback_handler(
                 .unwrap()
                 .into_response();
 
-            response.headers_mut().insert(
-                header::SET_COOKIE,
-                cookie_value
-                    .parse()
-                    .map_err(|e| {
-                        tracing::error!("Failed to parse cookie value: {}", e);
-                        e
-                    })
-                    .unwrap_or_else(|_| {
-                        // Fallback to a basic cookie if parsing fails
-                        format!("ark_session={}; Path=/; HttpOnly", session_id)
-                            .parse()
-                            .expect("Basic cookie format should always parse")
-                    }),
-            );
+            for cookie in [
+                auth.session_cookies.session(&session_id, ttl),
+                auth.session_cookies.csrf(&session_id, ttl),
+            ] {
+                response
+                    .headers_mut()
+                    .append(header::SET_COOKIE, cookie.parse().unwrap());
+            }
 
             return response;
         } else {
             tracing::warn!("Invalid or expired state in OAuth callback: '{}'", state);
+            sign_in_failed(
+                &auth,
+                &client,
+                "oauth_callback",
+                None,
+                None,
+                "invalid or expired state",
+            )
+            .await;
             return Html("<h1>Invalid or expired authentication state</h1>").into_response();
         }
     }
@@ -758,6 +1531,9 @@ async fn callback_handler(
     .into_response()
 }
 
+/// Page shown when a user authenticates but is not in the required users group.
+const ACCESS_DENIED_PAGE: &str = "<h1>Access Denied</h1><p>You are not authorized to access this system. Please contact your administrator.</p>";
+
 // ------------------------- PKCE Helpers -------------------------
 
 /// Determines the effective scheme for redirect URIs.
@@ -868,6 +1644,10 @@ async fn perform_discovery(
         .get("token_endpoint")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
+    let end_session_endpoint = doc
+        .get("end_session_endpoint")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
     {
         let mut guard = active.write().await;
         if let Some(ref mut p) = *guard
@@ -882,6 +1662,10 @@ async fn perform_discovery(
             if let Some(tok_ep) = token_endpoint {
                 p.token_endpoint = Some(tok_ep);
             }
+            // Preset logout endpoints take precedence over discovery
+            if p.end_session_endpoint.is_none() {
+                p.end_session_endpoint = end_session_endpoint;
+            }
         }
     }
     Ok(())
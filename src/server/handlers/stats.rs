@@ -0,0 +1,476 @@
+/// Implementation of the usage statistics endpoint.
+///
+/// Aggregates the persisted tool call counters (see [`crate::server::usage`]),
+/// the unexpired sessions and the loaded plugins by owner, so the console can
+/// show who uses what, including per-user plugins.
+///
+/// # Endpoints
+///
+/// - `GET /api/admin/stats` - Usage statistics per owner and in total
+/// - `GET /api/admin/stats/top` - Busiest and slowest tools and most active
+///   principals over a recent window
+/// - `GET /api/admin/usage` - Monthly usage and quotas of every principal,
+///   for chargeback
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::models::QuotaLimits,
+    errors::ErrorCode,
+    server::{
+        auth::Principal,
+        handlers::service_accounts::{admin_context, finish},
+        persist::{
+            Database, PrincipalUsageRecord, ToolUsageRecord, ToolUsageRollup,
+            models::ROLLUP_LATENCY_BOUNDS_MS,
+        },
+        service::StandardizedResponse,
+        usage::ROLLUP_RETENTION,
+    },
+    state::ArkState,
+};
+
+/// Window reported when the request does not specify one.
+const DEFAULT_WINDOW: &str = "24h";
+/// Number of entries per list when the request does not specify a limit.
+const DEFAULT_TOP_LIMIT: u32 = 10;
+/// Maximum number of entries per list.
+const MAX_TOP_LIMIT: u32 = 100;
+
+/// Query parameters for `GET /api/admin/stats/top`.
+#[derive(Debug, Deserialize)]
+pub struct TopUsageParams {
+    /// Hours or days to report on (e.g. "24h", "7d"); defaults to 24h, at
+    /// most 30 days.
+    pub window: Option<String>,
+    /// Entries per list; defaults to 10, capped at 100.
+    pub limit: Option<u32>,
+}
+
+/// Query parameters of the monthly usage endpoints.
+#[derive(Debug, Deserialize)]
+pub struct UsageParams {
+    /// Month to report on, as `YYYY-MM`; defaults to the current month (UTC).
+    pub month: Option<String>,
+}
+
+/// Parses the `month` parameter, defaulting to the current month.
+pub(crate) fn parse_month(value: Option<&str>) -> Result<String, (StatusCode, Json<Value>)> {
+    let Some(value) = value.filter(|v| !v.is_empty()) else {
+        return Ok(PrincipalUsageRecord::month_of(chrono::Utc::now()));
+    };
+    match chrono::NaiveDate::parse_from_str(&format!("{value}-01"), "%Y-%m-%d") {
+        Ok(_) if value.len() == 7 => Ok(value.to_string()),
+        _ => Err((
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Invalid month",
+                Some("Expected YYYY-MM, e.g. 2026-01"),
+            ),
+        )),
+    }
+}
+
+/// Serializes one usage counter with its quota; `quota` and `remaining` are
+/// null when unlimited.
+fn counter_to_json(used: Value, limit: u64) -> Value {
+    if limit == 0 {
+        return json!({ "used": used, "quota": null, "remaining": null });
+    }
+    let remaining = (limit as f64 - used.as_f64().unwrap_or_default()).max(0.0);
+    json!({ "used": used, "quota": limit, "remaining": remaining.floor() as u64 })
+}
+
+/// Serializes a principal's monthly usage against its quotas.
+pub(crate) fn principal_usage_to_json(record: &PrincipalUsageRecord, limits: QuotaLimits) -> Value {
+    json!({
+        "principal": record.principal,
+        "month": record.month,
+        "calls": counter_to_json(json!(record.calls), limits.calls),
+        "compute_ms": counter_to_json(json!(record.compute_ms), limits.compute_ms),
+        "bytes_returned": counter_to_json(json!(record.bytes_returned), limits.bytes_returned),
+    })
+}
+
+/// Statistics accumulated for one owner, or for all of them.
+#[derive(Debug, Default)]
+struct OwnerStats {
+    plugins: u64,
+    tools: u64,
+    calls: u64,
+    errors: u64,
+    latency_ms: f64,
+    active_sessions: u64,
+    usage: Vec<Value>,
+}
+
+impl OwnerStats {
+    fn add_usage(&mut self, record: &ToolUsageRecord) {
+        self.calls += record.calls;
+        self.errors += record.errors;
+        self.latency_ms += record.total_latency_ms;
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "plugins": self.plugins,
+            "tools": self.tools,
+            "calls": self.calls,
+            "errors": self.errors,
+            "error_rate": ratio(self.errors as f64, self.calls),
+            "avg_latency_ms": ratio(self.latency_ms, self.calls),
+            "active_sessions": self.active_sessions,
+        })
+    }
+}
+
+/// `value / count`, or `null` without calls.
+fn ratio(value: f64, count: u64) -> Value {
+    if count == 0 {
+        Value::Null
+    } else {
+        json!(value / count as f64)
+    }
+}
+
+/// Serializes the counters of one tool.
+fn usage_to_json(record: &ToolUsageRecord) -> Value {
+    json!({
+        "plugin": record.plugin_id,
+        "tool": record.tool,
+        "calls": record.calls,
+        "errors": record.errors,
+        "avg_latency_ms": ratio(record.total_latency_ms, record.calls),
+        "last_call": record.last_call_utc.map(|t| t.to_rfc3339()),
+    })
+}
+
+/// Parses a window such as "6h" or "7d", up to the rollup retention.
+fn parse_window(value: &str) -> Option<Duration> {
+    let (count, unit_secs) = if let Some(count) = value.strip_suffix('h') {
+        (count, 3600)
+    } else if let Some(count) = value.strip_suffix('d') {
+        (count, 86400)
+    } else {
+        return None;
+    };
+    let count: u64 = count.parse().ok().filter(|n| *n > 0)?;
+    Some(Duration::from_secs(count.checked_mul(unit_secs)?)).filter(|w| *w <= ROLLUP_RETENTION)
+}
+
+/// Upper bound of the latency bucket holding the 95th percentile call.
+fn p95_ms(histogram: &[u64]) -> f64 {
+    let total: u64 = histogram.iter().sum();
+    let rank = (total * 95).div_ceil(100);
+    let last = ROLLUP_LATENCY_BOUNDS_MS[ROLLUP_LATENCY_BOUNDS_MS.len() - 1];
+    let mut seen = 0;
+    for (bucket, count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return ROLLUP_LATENCY_BOUNDS_MS
+                .get(bucket)
+                .copied()
+                .unwrap_or(last);
+        }
+    }
+    last
+}
+
+/// Calls accumulated over the rollups of a tool or principal.
+#[derive(Debug, Default)]
+struct Totals {
+    calls: u64,
+    errors: u64,
+    histogram: Vec<u64>,
+}
+
+impl Totals {
+    fn add(&mut self, rollup: &ToolUsageRollup) {
+        self.calls += rollup.calls;
+        self.errors += rollup.errors;
+        if self.histogram.len() < rollup.latency_histogram.len() {
+            self.histogram.resize(rollup.latency_histogram.len(), 0);
+        }
+        for (total, count) in self.histogram.iter_mut().zip(&rollup.latency_histogram) {
+            *total += count;
+        }
+    }
+}
+
+/// Ranks tools by calls and p95 latency, and principals by calls.
+///
+/// Unauthenticated calls count for the tools but not for any principal.
+fn top_usage(rollups: &[ToolUsageRollup], limit: usize) -> Value {
+    let mut tools: BTreeMap<(&str, &str), Totals> = BTreeMap::new();
+    let mut principals: BTreeMap<&str, Totals> = BTreeMap::new();
+    for rollup in rollups {
+        tools
+            .entry((rollup.plugin_id.as_str(), rollup.tool.as_str()))
+            .or_default()
+            .add(rollup);
+        if let Some(principal) = rollup.principal.as_deref() {
+            principals.entry(principal).or_default().add(rollup);
+        }
+    }
+
+    let mut busiest: Vec<_> = tools.iter().collect();
+    busiest.sort_by_key(|(_, totals)| std::cmp::Reverse(totals.calls));
+    let mut slowest: Vec<_> = tools
+        .iter()
+        .map(|(key, totals)| (key, totals, p95_ms(&totals.histogram)))
+        .collect();
+    slowest.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.calls.cmp(&a.1.calls)));
+    let mut active: Vec<_> = principals.iter().collect();
+    active.sort_by_key(|(_, totals)| std::cmp::Reverse(totals.calls));
+
+    json!({
+        "top_tools": busiest.iter().take(limit).map(|((plugin, tool), totals)| json!({
+            "plugin": plugin,
+            "tool": tool,
+            "calls": totals.calls,
+            "errors": totals.errors,
+        })).collect::<Vec<_>>(),
+        "slowest_tools": slowest.iter().take(limit).map(|((plugin, tool), totals, p95)| json!({
+            "plugin": plugin,
+            "tool": tool,
+            "p95_latency_ms": p95,
+            "calls": totals.calls,
+        })).collect::<Vec<_>>(),
+        "top_principals": active.iter().take(limit).map(|(principal, totals)| json!({
+            "principal": principal,
+            "calls": totals.calls,
+            "errors": totals.errors,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Collects the statistics of every owner with plugins, calls or sessions.
+async fn collect(state: &ArkState, db: &Database) -> anyhow::Result<Value> {
+    let mut owners: BTreeMap<String, OwnerStats> = BTreeMap::new();
+    {
+        let catalog = state.plugin_registry.catalog.load();
+        for (name, cfg) in &catalog.plugin_to_config {
+            let stats = owners
+                .entry(cfg.owner.clone().unwrap_or_else(|| "*/*/*".to_string()))
+                .or_default();
+            stats.plugins += 1;
+            stats.tools += catalog
+                .tool_to_plugin
+                .values()
+                .filter(|plugin| *plugin == name)
+                .count() as u64;
+        }
+    }
+    for record in db.list_tool_usage_async().await? {
+        let stats = owners.entry(record.owner.clone()).or_default();
+        stats.add_usage(&record);
+        stats.usage.push(usage_to_json(&record));
+    }
+    for (owner, count) in db.count_active_sessions_async().await? {
+        owners.entry(owner).or_default().active_sessions += count;
+    }
+
+    let mut totals = OwnerStats::default();
+    let mut by_owner = Vec::with_capacity(owners.len());
+    for (owner, stats) in &owners {
+        totals.plugins += stats.plugins;
+        totals.tools += stats.tools;
+        totals.calls += stats.calls;
+        totals.errors += stats.errors;
+        totals.latency_ms += stats.latency_ms;
+        totals.active_sessions += stats.active_sessions;
+
+        let mut entry = stats.to_json();
+        entry["owner"] = json!(owner);
+        entry["usage"] = json!(stats.usage);
+        by_owner.push(entry);
+    }
+    Ok(json!({ "owners": by_owner, "totals": totals.to_json() }))
+}
+
+/// Reports tool calls, error rates, active sessions and plugin counts by owner.
+///
+/// Shared plugins are listed under the owner `*/*/*`. Call counters are
+/// persisted and cover every call since the database was created.
+///
+/// # Endpoint
+/// `GET /api/admin/stats`
+///
+/// # Returns
+/// - 200 OK with `{"owners": [...], "totals": {...}}`
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if no database is configured
+pub async fn get_stats(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/stats");
+
+    let response: Response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((_, db)) => match collect(&state, &db).await {
+            Ok(stats) => (StatusCode::OK, Json(stats)).into_response(),
+            Err(e) => {
+                tracing::error!("Failed to collect usage statistics: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(
+                        ErrorCode::Internal,
+                        "Failed to collect usage statistics",
+                        None,
+                    ),
+                )
+                    .into_response()
+            }
+        },
+    };
+    finish(response, "/api/admin/stats", "GET", start)
+}
+
+/// Reports the most called tools, the slowest tools by p95 latency and the
+/// most active principals over a recent window.
+///
+/// Computed from hourly rollups, so the window is extended to the start of
+/// its first hour. The p95 latency is the upper bound of the histogram bucket
+/// it falls in.
+///
+/// # Endpoint
+/// `GET /api/admin/stats/top?window=24h&limit=10`
+///
+/// # Returns
+/// - 200 OK with `{"window", "since", "top_tools", "slowest_tools", "top_principals"}`
+/// - 400 Bad Request for an invalid window or limit
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if no database is configured
+pub async fn get_top_usage(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Query(params): Query<TopUsageParams>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/stats/top");
+
+    let window = params.window.unwrap_or_else(|| DEFAULT_WINDOW.to_string());
+    let limit = params.limit.unwrap_or(DEFAULT_TOP_LIMIT);
+    let response: Response = match admin_context(&state, principal).and_then(|(_, db)| {
+        let Some(duration) = parse_window(&window) else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                StandardizedResponse::as_error(
+                    ErrorCode::InvalidRequest,
+                    "Invalid window",
+                    Some("Expected hours or days up to 30d, e.g. 24h or 7d"),
+                ),
+            ));
+        };
+        if limit == 0 || limit > MAX_TOP_LIMIT {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                StandardizedResponse::as_error(
+                    ErrorCode::InvalidRequest,
+                    "Invalid limit",
+                    Some("Must be between 1 and 100"),
+                ),
+            ));
+        }
+        Ok((db, duration))
+    }) {
+        Err(e) => e.into_response(),
+        Ok((db, duration)) => {
+            let from = chrono::Utc::now().timestamp() - duration.as_secs() as i64;
+            let since = chrono::DateTime::from_timestamp(from - from.rem_euclid(3600), 0)
+                .unwrap_or_default();
+            // Include the calls made since the last background rollup
+            let rollups = match db.rollup_tool_calls_async(ROLLUP_RETENTION).await {
+                Ok(_) => db.list_tool_usage_rollups_async(since).await,
+                Err(e) => Err(e),
+            };
+            match rollups {
+                Ok(rollups) => {
+                    let mut body = top_usage(&rollups, limit as usize);
+                    body["window"] = json!(window);
+                    body["since"] = json!(since.to_rfc3339());
+                    (StatusCode::OK, Json(body)).into_response()
+                }
+                Err(e) => {
+                    tracing::error!("Failed to collect usage analytics: {:?}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        StandardizedResponse::as_error(
+                            ErrorCode::Internal,
+                            "Failed to collect usage analytics",
+                            None,
+                        ),
+                    )
+                        .into_response()
+                }
+            }
+        }
+    };
+    finish(response, "/api/admin/stats/top", "GET", start)
+}
+
+/// Reports the usage of every principal that made calls in a month, with
+/// their quotas, for chargeback.
+///
+/// # Endpoint
+/// `GET /api/admin/usage?month=YYYY-MM`
+///
+/// # Returns
+/// - 200 OK with `{"month", "principals"}`, heaviest compute users first
+/// - 400 Bad Request for an invalid month
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 503 Service Unavailable if no database is configured
+pub async fn get_usage_report(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Query(params): Query<UsageParams>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/usage");
+
+    let response: Response = match admin_context(&state, principal).and_then(|(_, db)| {
+        let month = parse_month(params.month.as_deref())?;
+        Ok((db, month))
+    }) {
+        Err(e) => e.into_response(),
+        Ok((db, month)) => match db.list_principal_usage_async(month.clone()).await {
+            Ok(records) => {
+                let principals: Vec<Value> = records
+                    .iter()
+                    .map(|r| principal_usage_to_json(r, state.quota_limits(&r.principal)))
+                    .collect();
+                (
+                    StatusCode::OK,
+                    Json(json!({ "month": month, "principals": principals })),
+                )
+                    .into_response()
+            }
+            Err(e) => {
+                tracing::error!("Failed to list principal usage: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(
+                        ErrorCode::Internal,
+                        "Failed to list principal usage",
+                        None,
+                    ),
+                )
+                    .into_response()
+            }
+        },
+    };
+    finish(response, "/api/admin/usage", "GET", start)
+}
@@ -0,0 +1,404 @@
+/// Implementation of the API key (personal access token) endpoints.
+///
+/// API keys let scripts call the management API and the MCP endpoint without an
+/// interactive login. They are presented as `Authorization: Bearer ark_...` and
+/// validated by `check_auth`. Only a hash of each key is stored.
+///
+/// # Endpoints
+///
+/// - `GET /api/me/tokens` - List the caller's API keys (without secrets)
+/// - `POST /api/me/tokens` - Create an API key; the token is returned once
+/// - `DELETE /api/me/tokens/:id` - Revoke one of the caller's API keys
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::{HeaderMap, header},
+    response::IntoResponse,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        audit::{AuthEvent, AuthEventKind, ClientInfo},
+        auth::{
+            API_KEY_PREFIX, API_KEY_SCOPE_API, API_KEY_SCOPE_MCP, Principal, ProviderKind,
+            generate_api_key, hash_api_key,
+        },
+        authz,
+        persist::{ApiKeyRecord, Database},
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+/// Explains the accepted scope values in validation errors.
+pub(crate) const SCOPES_HINT: &str =
+    "Allowed scopes: api, mcp (at least one), tool:<name>, plugin:<id>";
+/// Default API key lifetime when the request does not specify one (90 days).
+const DEFAULT_API_KEY_TTL_SECS: u64 = 90 * 24 * 3600;
+/// Maximum API key lifetime (365 days).
+const MAX_API_KEY_TTL_SECS: u64 = 365 * 24 * 3600;
+
+/// Request body for `POST /api/me/tokens`.
+#[derive(Debug, Deserialize)]
+pub struct CreateTokenRequest {
+    /// Friendly name to identify the key.
+    pub name: String,
+    /// Scopes to grant ("api", "mcp"); defaults to both. Adding `tool:<name>`
+    /// or `plugin:<id>` entries restricts the key to executing those tools.
+    #[serde(default)]
+    pub scopes: Option<Vec<String>>,
+    /// Lifetime in seconds; defaults to 90 days, capped at 365 days.
+    #[serde(default)]
+    pub expires_in_seconds: Option<u64>,
+}
+
+/// Serializes an API key record for API responses, omitting the token hash.
+pub(crate) fn token_to_json(record: &ApiKeyRecord) -> Value {
+    json!({
+        "id": record.key_id,
+        "name": record.name,
+        "scopes": record.scopes,
+        "created": record.created_utc.to_rfc3339(),
+        "expires": record.expiry_utc.map(|e| e.to_rfc3339()),
+        "expired": record.is_expired(),
+    })
+}
+
+/// Returns the configured database, if any, without holding the lock.
+pub(crate) fn database(state: &ArkState) -> Option<Database> {
+    state.database.read().ok().and_then(|g| g.clone())
+}
+
+/// Returns true when the request itself was authenticated with an API key.
+fn is_api_key_request(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .is_some_and(|t| t.starts_with(API_KEY_PREFIX))
+}
+
+/// Lists the caller's API keys.
+///
+/// # Endpoint
+/// `GET /api/me/tokens`
+///
+/// # Returns
+/// - 200 OK with a JSON array of keys (secrets are never returned)
+/// - 401 Unauthorized if the caller is not authenticated
+/// - 503 Service Unavailable if no database is configured
+pub async fn list_tokens(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/me/tokens");
+
+    let response = match (principal, database(&state)) {
+        (None, _) => (
+            StatusCode::UNAUTHORIZED,
+            StandardizedResponse::as_error(
+                ErrorCode::Unauthenticated,
+                "Authentication required",
+                None,
+            ),
+        ),
+        (_, None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "API keys require a database",
+                None,
+            ),
+        ),
+        (Some(Extension(p)), Some(db)) => {
+            match db.list_api_keys_by_owner_async(p.global_id()).await {
+                Ok(records) => (
+                    StatusCode::OK,
+                    Json(Value::Array(records.iter().map(token_to_json).collect())),
+                ),
+                Err(e) => {
+                    tracing::error!("Failed to list API keys: {:?}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        StandardizedResponse::as_error(
+                            ErrorCode::Internal,
+                            "Failed to list API keys",
+                            None,
+                        ),
+                    )
+                }
+            }
+        }
+    };
+
+    let status = response.0.as_u16();
+    let latency_ms = start.elapsed().as_millis() as f64;
+    crate::metrics::record_api_http("/api/me/tokens", "GET", status, latency_ms);
+    response.into_response()
+}
+
+/// Creates an API key for the caller.
+///
+/// # Endpoint
+/// `POST /api/me/tokens`
+///
+/// # Returns
+/// - 201 Created with the key metadata and the plaintext `token` (shown only once)
+/// - 400 Bad Request for an empty name, unknown scopes or an invalid lifetime
+/// - 401 Unauthorized if the caller is not authenticated
+/// - 403 Forbidden if the request was itself authenticated with an API key
+/// - 503 Service Unavailable if no database is configured
+pub async fn create_token(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    client: Option<Extension<ClientInfo>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateTokenRequest>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: POST /api/me/tokens name={}", payload.name);
+
+    let client = client.map(|Extension(c)| c).unwrap_or_default();
+    let response = create_token_inner(&state, principal, &client, &headers, payload).await;
+
+    let status = response.0.as_u16();
+    let latency_ms = start.elapsed().as_millis() as f64;
+    crate::metrics::record_api_http("/api/me/tokens", "POST", status, latency_ms);
+    response.into_response()
+}
+
+async fn create_token_inner(
+    state: &ArkState,
+    principal: Option<Extension<Principal>>,
+    client: &ClientInfo,
+    headers: &HeaderMap,
+    payload: CreateTokenRequest,
+) -> (StatusCode, Json<Value>) {
+    let Some(Extension(principal)) = principal else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            StandardizedResponse::as_error(
+                ErrorCode::Unauthenticated,
+                "Authentication required",
+                None,
+            ),
+        );
+    };
+    // Keys may not mint further keys; otherwise a leaked key could outlive its expiry
+    if is_api_key_request(headers) {
+        return (
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::PermissionDenied,
+                "API keys cannot create API keys",
+                None,
+            ),
+        );
+    }
+    // Service account keys are issued by administrators only
+    if principal.provider_kind == ProviderKind::Service {
+        return (
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::PermissionDenied,
+                "Service accounts cannot create API keys",
+                None,
+            ),
+        );
+    }
+    let Some(db) = database(state) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "API keys require a database",
+                None,
+            ),
+        );
+    };
+
+    let issued_by = principal.global_id();
+    issue_api_key(state, &db, principal, payload, client, &issued_by).await
+}
+
+/// Validates a token request and persists a new API key for `principal`.
+///
+/// `issued_by` is the global id of the caller, recorded in the audit trail.
+/// Returns 201 with the key metadata and plaintext `token`, or 400 for an
+/// invalid name, scopes or lifetime.
+pub(crate) async fn issue_api_key(
+    state: &ArkState,
+    db: &Database,
+    principal: Principal,
+    payload: CreateTokenRequest,
+    client: &ClientInfo,
+    issued_by: &str,
+) -> (StatusCode, Json<Value>) {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.len() > 128 {
+        return (
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Token name must be 1-128 characters",
+                None,
+            ),
+        );
+    }
+
+    let scopes = payload
+        .scopes
+        .unwrap_or_else(|| vec![API_KEY_SCOPE_API.to_string(), API_KEY_SCOPE_MCP.to_string()]);
+    if !authz::valid_credential_scopes(scopes.iter().map(String::as_str)) {
+        return (
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Invalid scopes",
+                Some(SCOPES_HINT),
+            ),
+        );
+    }
+
+    let ttl = payload
+        .expires_in_seconds
+        .unwrap_or(DEFAULT_API_KEY_TTL_SECS);
+    if ttl == 0 || ttl > MAX_API_KEY_TTL_SECS {
+        return (
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Invalid expires_in_seconds",
+                Some("Must be between 1 and 31536000"),
+            ),
+        );
+    }
+
+    let token = generate_api_key();
+    let now = chrono::Utc::now();
+    let record = ApiKeyRecord {
+        key_id: derive_key_id(&token),
+        token_hash: hash_api_key(&token),
+        owner: principal.global_id(),
+        name,
+        principal,
+        scopes,
+        created_utc: now,
+        expiry_utc: Some(now + chrono::Duration::seconds(ttl as i64)),
+    };
+
+    match db.save_api_key_record_async(record.clone()).await {
+        Ok(()) => {
+            tracing::info!("Created API key {} for {}", record.key_id, record.owner);
+            AuthEvent::new(AuthEventKind::TokenIssued, client)
+                .principal(&record.principal)
+                .detail(format!(
+                    "API key {} ({}) created by {}",
+                    record.key_id, record.name, issued_by
+                ))
+                .record(state)
+                .await;
+            let mut body = token_to_json(&record);
+            if let Some(obj) = body.as_object_mut() {
+                obj.insert("token".to_string(), json!(token));
+            }
+            (StatusCode::CREATED, Json(body))
+        }
+        Err(e) => {
+            tracing::error!("Failed to persist API key: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to create API key",
+                    None,
+                ),
+            )
+        }
+    }
+}
+
+/// Revokes one of the caller's API keys.
+///
+/// # Endpoint
+/// `DELETE /api/me/tokens/:id`
+///
+/// # Returns
+/// - 204 No Content on success
+/// - 401 Unauthorized if the caller is not authenticated
+/// - 404 Not Found if the caller has no key with this id
+/// - 503 Service Unavailable if no database is configured
+pub async fn revoke_token(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Path(key_id): Path<String>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: DELETE /api/me/tokens/{}", key_id);
+
+    let response = match (principal, database(&state)) {
+        (None, _) => (
+            StatusCode::UNAUTHORIZED,
+            StandardizedResponse::as_error(
+                ErrorCode::Unauthenticated,
+                "Authentication required",
+                None,
+            ),
+        )
+            .into_response(),
+        (_, None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "API keys require a database",
+                None,
+            ),
+        )
+            .into_response(),
+        (Some(Extension(p)), Some(db)) => {
+            match db.delete_api_key_async(p.global_id(), key_id.clone()).await {
+                Ok(true) => {
+                    tracing::info!("Revoked API key {} for {}", key_id, p.global_id());
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Ok(false) => (
+                    StatusCode::NOT_FOUND,
+                    StandardizedResponse::as_error(ErrorCode::NotFound, "API key not found", None),
+                )
+                    .into_response(),
+                Err(e) => {
+                    tracing::error!("Failed to revoke API key: {:?}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        StandardizedResponse::as_error(
+                            ErrorCode::Internal,
+                            "Failed to revoke API key",
+                            None,
+                        ),
+                    )
+                        .into_response()
+                }
+            }
+        }
+    };
+
+    let status = response.status().as_u16();
+    let latency_ms = start.elapsed().as_millis() as f64;
+    crate::metrics::record_api_http("/api/me/tokens/{id}", "DELETE", status, latency_ms);
+    response
+}
+
+/// Derives the public key id from the token hash (first 16 hex characters).
+///
+/// The id is safe to display and log; it cannot be used to recover the token.
+fn derive_key_id(token: &str) -> String {
+    hash_api_key(token)[..16].to_string()
+}
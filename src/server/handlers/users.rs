@@ -0,0 +1,487 @@
+/// Implementation of the local user account endpoints.
+///
+/// Local accounts back the `local` auth provider for deployments without an
+/// enterprise IdP. Passwords are hashed with argon2 before they are stored.
+/// Administration endpoints require an administrator; `/api/me/password`
+/// lets a signed-in local user change their own password.
+///
+/// # Endpoints
+///
+/// - `GET /api/admin/users` - List local users
+/// - `POST /api/admin/users` - Create a local user
+/// - `DELETE /api/admin/users/:username` - Delete a local user
+/// - `PUT /api/admin/users/:username/password` - Reset a user's password
+/// - `POST /api/me/password` - Change the caller's own password
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{
+    errors::ErrorCode,
+    server::{
+        auth::{MIN_PASSWORD_LEN, Principal, ProviderKind, hash_password, verify_password},
+        handlers::{
+            service_accounts::{admin_context, finish},
+            tokens::database,
+        },
+        persist::{Database, UserRecord},
+        service::StandardizedResponse,
+    },
+    state::ArkState,
+};
+
+/// Maximum accepted password length (bounds hashing cost).
+const MAX_PASSWORD_LEN: usize = 1024;
+
+/// Request body for `POST /api/admin/users`.
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    /// Login name: 1-64 letters, digits, '-', '_', '.' or '@'.
+    pub username: String,
+    /// Initial password.
+    pub password: String,
+    /// Optional display name.
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Optional email address.
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Local group memberships.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Whether the user is an administrator.
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+/// Request body for `PUT /api/admin/users/:username/password`.
+#[derive(Debug, Deserialize)]
+pub struct SetPasswordRequest {
+    /// The new password.
+    pub password: String,
+}
+
+/// Request body for `POST /api/me/password`.
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    /// The caller's current password.
+    pub current_password: String,
+    /// The new password.
+    pub new_password: String,
+}
+
+/// Serializes a user for API responses, omitting the password hash.
+fn user_to_json(record: &UserRecord) -> Value {
+    json!({
+        "username": record.username,
+        "global_id": format!("local/*/{}", record.username),
+        "display_name": record.display_name,
+        "email": record.email,
+        "groups": record.groups,
+        "is_admin": record.is_admin,
+        "created": record.created_utc.to_rfc3339(),
+    })
+}
+
+/// Returns true if `username` is a valid local account name.
+fn is_valid_username(username: &str) -> bool {
+    (1..=64).contains(&username.len())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+}
+
+/// Validates a new password, returning an error response if it is unacceptable.
+fn check_password_policy(password: &str) -> Result<(), (StatusCode, Json<Value>)> {
+    if password.len() < MIN_PASSWORD_LEN || password.len() > MAX_PASSWORD_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Invalid password",
+                Some(&format!(
+                    "Password must be between {} and {} characters",
+                    MIN_PASSWORD_LEN, MAX_PASSWORD_LEN
+                )),
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Hashes a password off the async runtime.
+async fn hash_password_blocking(password: String) -> Result<String, (StatusCode, Json<Value>)> {
+    match tokio::task::spawn_blocking(move || hash_password(&password)).await {
+        Ok(Ok(hash)) => Ok(hash),
+        other => {
+            tracing::error!("Failed to hash password: {:?}", other.map(|r| r.err()));
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(
+                    ErrorCode::Internal,
+                    "Failed to hash password",
+                    None,
+                ),
+            ))
+        }
+    }
+}
+
+/// Loads a user, mapping a missing user to 404.
+async fn load_user(db: &Database, username: &str) -> Result<UserRecord, (StatusCode, Json<Value>)> {
+    match db.get_user_async(username.to_string()).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            StandardizedResponse::as_error(ErrorCode::NotFound, "User not found", None),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to load user: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(ErrorCode::Internal, "Failed to load user", None),
+            ))
+        }
+    }
+}
+
+/// Persists a user record, mapping failures to 500.
+async fn store_user(db: &Database, record: UserRecord) -> Result<(), (StatusCode, Json<Value>)> {
+    db.save_user_async(record).await.map_err(|e| {
+        tracing::error!("Failed to persist user: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StandardizedResponse::as_error(ErrorCode::Internal, "Failed to save user", None),
+        )
+    })
+}
+
+/// Lists all local users.
+///
+/// # Endpoint
+/// `GET /api/admin/users`
+///
+/// # Returns
+/// - 200 OK with a JSON array of users (password hashes are never returned)
+/// - 401/403 if the caller is not an authenticated administrator
+pub async fn list_users(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: GET /api/admin/users");
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((_, db)) => match db.list_users_async().await {
+            Ok(users) => (
+                StatusCode::OK,
+                Json(Value::Array(users.iter().map(user_to_json).collect())),
+            )
+                .into_response(),
+            Err(e) => {
+                tracing::error!("Failed to list users: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(
+                        ErrorCode::Internal,
+                        "Failed to list users",
+                        None,
+                    ),
+                )
+                    .into_response()
+            }
+        },
+    };
+    finish(response, "/api/admin/users", "GET", start)
+}
+
+/// Creates a local user.
+///
+/// # Endpoint
+/// `POST /api/admin/users`
+///
+/// # Returns
+/// - 201 Created with the user
+/// - 400 Bad Request for an invalid username or password
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 409 Conflict if the username is already taken
+pub async fn create_user(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Json(payload): Json<CreateUserRequest>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: POST /api/admin/users username={}", payload.username);
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((admin, db)) => match create_user_inner(&db, payload).await {
+            Ok(record) => {
+                tracing::info!(
+                    "Created local user {} (by {})",
+                    record.username,
+                    admin.global_id()
+                );
+                (StatusCode::CREATED, Json(user_to_json(&record))).into_response()
+            }
+            Err(e) => e.into_response(),
+        },
+    };
+    finish(response, "/api/admin/users", "POST", start)
+}
+
+async fn create_user_inner(
+    db: &Database,
+    payload: CreateUserRequest,
+) -> Result<UserRecord, (StatusCode, Json<Value>)> {
+    if !is_valid_username(&payload.username) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Invalid username",
+                Some("Use 1-64 letters, digits, '-', '_', '.' or '@'"),
+            ),
+        ));
+    }
+    check_password_policy(&payload.password)?;
+    match db.get_user_async(payload.username.clone()).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return Err((
+                StatusCode::CONFLICT,
+                StandardizedResponse::as_error(
+                    ErrorCode::AlreadyExists,
+                    "User already exists",
+                    None,
+                ),
+            ));
+        }
+        Err(e) => {
+            tracing::error!("Failed to check user: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StandardizedResponse::as_error(ErrorCode::Internal, "Failed to create user", None),
+            ));
+        }
+    }
+
+    let record = UserRecord {
+        username: payload.username,
+        password_hash: hash_password_blocking(payload.password).await?,
+        display_name: payload.display_name.filter(|s| !s.trim().is_empty()),
+        email: payload.email.filter(|s| !s.trim().is_empty()),
+        groups: payload.groups,
+        is_admin: payload.is_admin,
+        created_utc: chrono::Utc::now(),
+    };
+    store_user(db, record.clone()).await?;
+    Ok(record)
+}
+
+/// Deletes a local user.
+///
+/// Existing sessions of the user remain valid until they expire.
+///
+/// # Endpoint
+/// `DELETE /api/admin/users/:username`
+///
+/// # Returns
+/// - 204 No Content on success
+/// - 400 Bad Request when an administrator tries to delete their own account
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 404 Not Found if the user does not exist
+pub async fn delete_user(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Path(username): Path<String>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: DELETE /api/admin/users/{}", username);
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((admin, _))
+            if admin.provider_kind == ProviderKind::Local && admin.subject == username =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                StandardizedResponse::as_error(
+                    ErrorCode::InvalidRequest,
+                    "Cannot delete your own account",
+                    None,
+                ),
+            )
+                .into_response()
+        }
+        Ok((admin, db)) => match db.delete_user_async(username.clone()).await {
+            Ok(true) => {
+                tracing::info!("Deleted local user {} (by {})", username, admin.global_id());
+                StatusCode::NO_CONTENT.into_response()
+            }
+            Ok(false) => (
+                StatusCode::NOT_FOUND,
+                StandardizedResponse::as_error(ErrorCode::NotFound, "User not found", None),
+            )
+                .into_response(),
+            Err(e) => {
+                tracing::error!("Failed to delete user: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    StandardizedResponse::as_error(
+                        ErrorCode::Internal,
+                        "Failed to delete user",
+                        None,
+                    ),
+                )
+                    .into_response()
+            }
+        },
+    };
+    finish(response, "/api/admin/users/{username}", "DELETE", start)
+}
+
+/// Resets a local user's password.
+///
+/// # Endpoint
+/// `PUT /api/admin/users/:username/password`
+///
+/// # Returns
+/// - 204 No Content on success
+/// - 400 Bad Request if the password does not meet the policy
+/// - 401/403 if the caller is not an authenticated administrator
+/// - 404 Not Found if the user does not exist
+pub async fn set_user_password(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Path(username): Path<String>,
+    Json(payload): Json<SetPasswordRequest>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: PUT /api/admin/users/{}/password", username);
+
+    let response = match admin_context(&state, principal) {
+        Err(e) => e.into_response(),
+        Ok((admin, db)) => match set_password_inner(&db, &username, payload.password).await {
+            Ok(()) => {
+                tracing::info!(
+                    "Reset password for local user {} (by {})",
+                    username,
+                    admin.global_id()
+                );
+                StatusCode::NO_CONTENT.into_response()
+            }
+            Err(e) => e.into_response(),
+        },
+    };
+    finish(
+        response,
+        "/api/admin/users/{username}/password",
+        "PUT",
+        start,
+    )
+}
+
+async fn set_password_inner(
+    db: &Database,
+    username: &str,
+    password: String,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    check_password_policy(&password)?;
+    let mut user = load_user(db, username).await?;
+    user.password_hash = hash_password_blocking(password).await?;
+    store_user(db, user).await
+}
+
+/// Changes the caller's own local account password.
+///
+/// # Endpoint
+/// `POST /api/me/password`
+///
+/// # Returns
+/// - 204 No Content on success
+/// - 400 Bad Request if the caller is not a local account or the new password is invalid
+/// - 401 Unauthorized if the caller is not authenticated
+/// - 403 Forbidden if the current password is wrong
+pub async fn change_own_password(
+    State(state): State<Arc<ArkState>>,
+    principal: Option<Extension<Principal>>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    tracing::debug!("API: POST /api/me/password");
+
+    let response = change_own_password_inner(&state, principal, payload)
+        .await
+        .map_or_else(
+            |e| e.into_response(),
+            |()| StatusCode::NO_CONTENT.into_response(),
+        );
+    finish(response, "/api/me/password", "POST", start)
+}
+
+async fn change_own_password_inner(
+    state: &ArkState,
+    principal: Option<Extension<Principal>>,
+    payload: ChangePasswordRequest,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    let Some(Extension(principal)) = principal else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            StandardizedResponse::as_error(
+                ErrorCode::Unauthenticated,
+                "Authentication required",
+                None,
+            ),
+        ));
+    };
+    if principal.provider_kind != ProviderKind::Local {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidRequest,
+                "Only local accounts have a password",
+                None,
+            ),
+        ));
+    }
+    let Some(db) = database(state) else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            StandardizedResponse::as_error(
+                ErrorCode::DatabaseRequired,
+                "This endpoint requires a database",
+                None,
+            ),
+        ));
+    };
+
+    let user = load_user(&db, &principal.subject).await?;
+    let hash = user.password_hash.clone();
+    let current = payload.current_password;
+    let valid = tokio::task::spawn_blocking(move || verify_password(&current, &hash))
+        .await
+        .unwrap_or(false);
+    if !valid {
+        return Err((
+            StatusCode::FORBIDDEN,
+            StandardizedResponse::as_error(
+                ErrorCode::InvalidCredentials,
+                "Current password is incorrect",
+                None,
+            ),
+        ));
+    }
+    set_password_inner(&db, &principal.subject, payload.new_password).await?;
+    tracing::info!("Local user {} changed their password", principal.subject);
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Client IP allow and deny lists for the management listener.
+//!
+//! The lists in `management_server.ip_filter` apply to every route, and each
+//! entry of `routes` adds lists for a path prefix (e.g. only internal ranges
+//! may reach `/api/admin`). They are checked before authentication, so
+//! requests from refused addresses are dropped without touching sessions or
+//! the database.
+
+use std::net::IpAddr;
+
+use anyhow::Context;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::config::models::IpFilterConfig;
+use crate::server::metrics_auth::IpRange;
+use crate::server::service::unversioned_api_path;
+
+/// Allow and deny ranges of one rule.
+#[derive(Debug, Clone, Default)]
+struct Rule {
+    allow: Vec<IpRange>,
+    deny: Vec<IpRange>,
+}
+
+impl Rule {
+    fn parse(allow: &[String], deny: &[String], name: &str) -> anyhow::Result<Self> {
+        let parse = |list: &[String], field: &str| {
+            list.iter()
+                .map(|s| IpRange::parse(s).with_context(|| format!("Invalid {name}.{field} entry")))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        Ok(Self {
+            allow: parse(allow, "allow")?,
+            deny: parse(deny, "deny")?,
+        })
+    }
+
+    fn allows(&self, ip: Option<IpAddr>) -> bool {
+        let matches =
+            |ranges: &[IpRange]| ip.is_some_and(|ip| ranges.iter().any(|r| r.contains(ip)));
+        !matches(&self.deny) && (self.allow.is_empty() || matches(&self.allow))
+    }
+}
+
+/// Validated form of [`IpFilterConfig`].
+#[derive(Debug, Clone)]
+pub struct IpFilter {
+    listener: Rule,
+    routes: Vec<(String, Rule)>,
+}
+
+impl IpFilter {
+    /// Builds the filter, rejecting malformed ranges and prefixes.
+    pub fn from_config(config: &IpFilterConfig) -> anyhow::Result<Self> {
+        let listener = Rule::parse(&config.allow, &config.deny, "ip_filter")?;
+        let routes = config
+            .routes
+            .iter()
+            .map(|route| {
+                if !route.prefix.starts_with('/') {
+                    anyhow::bail!(
+                        "ip_filter.routes prefix '{}' must start with '/'",
+                        route.prefix
+                    );
+                }
+                let prefix = route.prefix.trim_end_matches('/');
+                let rule = Rule::parse(&route.allow, &route.deny, "ip_filter.routes")?;
+                Ok((unversioned_api_path(prefix).into_owned(), rule))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { listener, routes })
+    }
+
+    /// Whether a client at `ip` may request `path`. Clients whose address is
+    /// unknown only pass when no allow list applies to the path.
+    pub fn allows(&self, ip: Option<IpAddr>, path: &str) -> bool {
+        let path = unversioned_api_path(path);
+        self.listener.allows(ip)
+            && self
+                .routes
+                .iter()
+                .filter(|(prefix, _)| {
+                    path.strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                })
+                .all(|(_, rule)| rule.allows(ip))
+    }
+
+    /// Returns a 403 response when `ip` may not request `path`.
+    pub fn deny(&self, ip: Option<IpAddr>, path: &str) -> Option<Response> {
+        if self.allows(ip, path) {
+            return None;
+        }
+        tracing::debug!(
+            "Rejected request for {} from {:?}: address not allowed",
+            path,
+            ip
+        );
+        Some((StatusCode::FORBIDDEN, "Address not allowed").into_response())
+    }
+}
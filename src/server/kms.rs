@@ -0,0 +1,784 @@
+//! Token signers whose private keys stay in a cloud KMS.
+//!
+//! [`KmsSigner`] signs with an AWS KMS key and [`KeyVaultSigner`] with an
+//! Azure Key Vault key: only the SHA-256 digest of each token is sent to the
+//! service, and the public key is fetched once at startup for the JWKS.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine as _;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{Algorithm, Header};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use simple_asn1::{ASN1Block, from_der};
+use tokio::sync::Mutex;
+use x509_parser::prelude::FromDer;
+use x509_parser::public_key::PublicKey;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+use crate::config::models::{KeyVaultSigningConfig, KmsSigningConfig, TokenSigningConfig};
+use crate::server::signing::{DynSigner, Signer};
+
+/// Credentials and access tokens are renewed this many seconds before they
+/// expire.
+const RENEW_MARGIN_SECS: i64 = 300;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const AMZ_JSON: &str = "application/x-amz-json-1.1";
+
+const KEYVAULT_API_VERSION: &str = "7.4";
+const KEYVAULT_SCOPE: &str = "https://vault.azure.net/.default";
+const KEYVAULT_RESOURCE: &str = "https://vault.azure.net";
+const DEFAULT_AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+/// Creates the signer of `token_signing.source` "kms" or "keyvault", or
+/// `None` for other sources.
+///
+/// # Errors
+/// Returns an error when the key settings are missing or the public key
+/// cannot be fetched.
+pub async fn load_kms_signer(config: &TokenSigningConfig) -> Result<Option<DynSigner>> {
+    match config.source.as_deref() {
+        Some("kms") => {
+            let kms = config.kms.as_ref().ok_or_else(|| {
+                anyhow!("token_signing.source is kms but token_signing.kms is not set")
+            })?;
+            Ok(Some(Arc::new(KmsSigner::new(kms).await?)))
+        }
+        Some("keyvault") => {
+            let keyvault = config.keyvault.as_ref().ok_or_else(|| {
+                anyhow!("token_signing.source is keyvault but token_signing.keyvault is not set")
+            })?;
+            Ok(Some(Arc::new(KeyVaultSigner::new(keyvault).await?)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Public half of a KMS key, as published in the JWKS.
+struct RemoteKey {
+    algorithm: Algorithm,
+    kid: String,
+    jwk: Value,
+}
+
+impl RemoteKey {
+    /// RSA key, signing with RS256. Like local keys without a certificate,
+    /// the kid is the SHA-256 of n || e.
+    fn rsa(n: &[u8], e: &[u8]) -> Self {
+        let (n, e) = (unsigned(n), unsigned(e));
+        let kid = hex::encode(Sha256::digest([n, e].concat()));
+        let jwk = json!({
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "n": URL_SAFE_NO_PAD.encode(n),
+            "e": URL_SAFE_NO_PAD.encode(e),
+            "kid": kid,
+        });
+        Self {
+            algorithm: Algorithm::RS256,
+            kid,
+            jwk,
+        }
+    }
+
+    /// EC P-256 key, signing with ES256; the kid is the SHA-256 of the
+    /// uncompressed point.
+    fn p256(x: &[u8], y: &[u8]) -> Result<Self> {
+        if x.len() != 32 || y.len() != 32 {
+            bail!("unexpected EC public key encoding");
+        }
+        let kid = hex::encode(Sha256::digest([&[4u8][..], x, y].concat()));
+        let jwk = json!({
+            "kty": "EC",
+            "use": "sig",
+            "alg": "ES256",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(x),
+            "y": URL_SAFE_NO_PAD.encode(y),
+            "kid": kid,
+        });
+        Ok(Self {
+            algorithm: Algorithm::ES256,
+            kid,
+            jwk,
+        })
+    }
+
+    /// Key from a DER SubjectPublicKeyInfo.
+    fn from_spki(der: &[u8]) -> Result<Self> {
+        let (_, spki) = SubjectPublicKeyInfo::from_der(der)
+            .map_err(|e| anyhow!("failed to parse public key: {}", e))?;
+        match spki
+            .parsed()
+            .map_err(|e| anyhow!("failed to parse public key: {}", e))?
+        {
+            PublicKey::RSA(rsa) => Ok(Self::rsa(rsa.modulus, rsa.exponent)),
+            PublicKey::EC(point) => match point.data() {
+                [4, xy @ ..] if xy.len() == 64 => Self::p256(&xy[..32], &xy[32..]),
+                _ => bail!("unsupported EC key, use a P-256 key"),
+            },
+            _ => bail!("unsupported key type, use an RSA or EC P-256 key"),
+        }
+    }
+
+    /// Key from a JWK as returned by Key Vault.
+    fn from_jwk(jwk: &Value) -> Result<Self> {
+        let field = |name: &str| -> Result<Vec<u8>> {
+            let value = jwk[name]
+                .as_str()
+                .ok_or_else(|| anyhow!("public key has no {}", name))?;
+            URL_SAFE_NO_PAD
+                .decode(value.trim_end_matches('='))
+                .with_context(|| format!("invalid {} in public key", name))
+        };
+        match (jwk["kty"].as_str(), jwk["crv"].as_str()) {
+            (Some("RSA" | "RSA-HSM"), _) => Ok(Self::rsa(&field("n")?, &field("e")?)),
+            (Some("EC" | "EC-HSM"), Some("P-256")) => Self::p256(&field("x")?, &field("y")?),
+            (kty, crv) => bail!(
+                "unsupported key type {} {}, use an RSA or EC P-256 key",
+                kty.unwrap_or("?"),
+                crv.unwrap_or("")
+            ),
+        }
+    }
+
+    /// Sets the key's `alg` and `kid` on `header`, and returns the JWS
+    /// signing input and its SHA-256 digest.
+    fn signing_input(&self, mut header: Header, claims: &Value) -> Result<(String, Vec<u8>)> {
+        header.alg = self.algorithm;
+        header.kid = Some(self.kid.clone());
+        let input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?)
+        );
+        let digest = Sha256::digest(input.as_bytes()).to_vec();
+        Ok((input, digest))
+    }
+
+    fn jwks(&self) -> Value {
+        json!({ "keys": [self.jwk.clone()] })
+    }
+}
+
+/// Strips the leading zero bytes of a big-endian integer.
+fn unsigned(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| *b != 0)
+        .unwrap_or(bytes.len().saturating_sub(1));
+    &bytes[start..]
+}
+
+/// Converts a DER ECDSA signature to the JWS form, r || s.
+fn ecdsa_der_to_raw(der: &[u8]) -> Result<Vec<u8>> {
+    let blocks = from_der(der).context("failed to parse ECDSA signature")?;
+    let [ASN1Block::Sequence(_, seq)] = blocks.as_slice() else {
+        bail!("unexpected ECDSA signature encoding");
+    };
+    let [ASN1Block::Integer(_, r), ASN1Block::Integer(_, s)] = seq.as_slice() else {
+        bail!("unexpected ECDSA signature encoding");
+    };
+    let mut raw = Vec::with_capacity(64);
+    for n in [r, s] {
+        let (_, bytes) = n.to_bytes_be();
+        if bytes.len() > 32 {
+            bail!("unexpected ECDSA signature encoding");
+        }
+        raw.extend(std::iter::repeat_n(0u8, 32 - bytes.len()));
+        raw.extend(bytes);
+    }
+    Ok(raw)
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .context("failed to build HTTP client")
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Reads a JSON response, failing with its body on an error status.
+async fn json_response(response: reqwest::Response, what: &str) -> Result<Value> {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        bail!("{} failed with {}: {}", what, status, body);
+    }
+    serde_json::from_str(&body).with_context(|| format!("{} returned invalid JSON", what))
+}
+
+/// Seconds in an `expires_in` field, sent as a number or a string.
+fn seconds(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// AWS access key, with the time it expires for temporary credentials.
+#[derive(Clone)]
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    expires: Option<DateTime<Utc>>,
+}
+
+/// Where AWS credentials come from.
+enum AwsCredentialSource {
+    /// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+    Static(AwsCredentials),
+    /// A web identity token exchanged with STS for a role's credentials.
+    WebIdentity {
+        role_arn: String,
+        token_file: String,
+        sts_endpoint: String,
+        cached: Mutex<Option<AwsCredentials>>,
+    },
+}
+
+impl AwsCredentialSource {
+    fn from_env(region: &str) -> Result<Self> {
+        if let (Some(access_key_id), Some(secret_access_key)) =
+            (env("AWS_ACCESS_KEY_ID"), env("AWS_SECRET_ACCESS_KEY"))
+        {
+            return Ok(Self::Static(AwsCredentials {
+                access_key_id,
+                secret_access_key,
+                session_token: env("AWS_SESSION_TOKEN"),
+                expires: None,
+            }));
+        }
+        match (env("AWS_ROLE_ARN"), env("AWS_WEB_IDENTITY_TOKEN_FILE")) {
+            (Some(role_arn), Some(token_file)) => Ok(Self::WebIdentity {
+                role_arn,
+                token_file,
+                sts_endpoint: env("AWS_ENDPOINT_URL_STS")
+                    .unwrap_or_else(|| format!("https://sts.{}.amazonaws.com", region)),
+                cached: Mutex::new(None),
+            }),
+            _ => bail!(
+                "no AWS credentials: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or AWS_ROLE_ARN and AWS_WEB_IDENTITY_TOKEN_FILE"
+            ),
+        }
+    }
+
+    async fn credentials(&self, http: &reqwest::Client) -> Result<AwsCredentials> {
+        let (role_arn, token_file, sts_endpoint, cached) = match self {
+            Self::Static(credentials) => return Ok(credentials.clone()),
+            Self::WebIdentity {
+                role_arn,
+                token_file,
+                sts_endpoint,
+                cached,
+            } => (role_arn, token_file, sts_endpoint, cached),
+        };
+        let mut cached = cached.lock().await;
+        let renew_after = Utc::now() + chrono::Duration::seconds(RENEW_MARGIN_SECS);
+        if let Some(credentials) = cached.as_ref()
+            && credentials.expires.is_none_or(|e| renew_after < e)
+        {
+            return Ok(credentials.clone());
+        }
+
+        let token = tokio::fs::read_to_string(token_file)
+            .await
+            .with_context(|| format!("failed to read web identity token {}", token_file))?;
+        let response = http
+            .get(sts_endpoint)
+            .query(&[
+                ("Action", "AssumeRoleWithWebIdentity"),
+                ("Version", "2011-06-15"),
+                ("RoleArn", role_arn.as_str()),
+                ("RoleSessionName", "ark-token-signing"),
+                ("WebIdentityToken", token.trim()),
+            ])
+            .header(ACCEPT, "application/json")
+            .send()
+            .await
+            .context("STS request failed")?;
+        let body = json_response(response, "STS AssumeRoleWithWebIdentity").await?;
+        let c = &body["AssumeRoleWithWebIdentityResponse"]["AssumeRoleWithWebIdentityResult"]["Credentials"];
+        let field = |name: &str| {
+            c[name]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("STS response has no {}", name))
+        };
+        let expires = match &c["Expiration"] {
+            Value::Number(n) => n
+                .as_f64()
+                .and_then(|secs| DateTime::from_timestamp(secs as i64, 0)),
+            Value::String(s) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|d| d.with_timezone(&Utc)),
+            _ => None,
+        };
+        let credentials = AwsCredentials {
+            access_key_id: field("AccessKeyId")?,
+            secret_access_key: field("SecretAccessKey")?,
+            session_token: Some(field("SessionToken")?),
+            expires,
+        };
+        *cached = Some(credentials.clone());
+        Ok(credentials)
+    }
+}
+
+/// Signature Version 4 headers of a POST to the KMS JSON API, `host` aside.
+fn sigv4_headers(
+    credentials: &AwsCredentials,
+    region: &str,
+    host: &str,
+    path: &str,
+    target: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    type HmacSha256 = Hmac<Sha256>;
+    let hmac = |key: &[u8], data: &str| {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    };
+
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = &amz_date[..8];
+    // Sorted by name, as the canonical request requires
+    let mut headers = vec![
+        ("content-type", AMZ_JSON.to_string()),
+        ("host", host.to_string()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    headers.push(("x-amz-target", target.to_string()));
+
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "POST\n{}\n\n{}\n{}\n{}",
+        path,
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(body))
+    );
+    let scope = format!("{}/{}/kms/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signing_key = [date, region, "kms", "aws4_request"].iter().fold(
+        format!("AWS4{}", credentials.secret_access_key).into_bytes(),
+        |key, part| hmac(&key, part),
+    );
+    let signature = hex::encode(hmac(&signing_key, &string_to_sign));
+
+    // The HTTP client sets Host itself
+    headers.retain(|(name, _)| *name != "host");
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+/// Calls to the AWS KMS JSON API.
+struct KmsClient {
+    http: reqwest::Client,
+    endpoint: url::Url,
+    region: String,
+    credentials: AwsCredentialSource,
+}
+
+impl KmsClient {
+    async fn call(&self, action: &str, body: Value) -> Result<Value> {
+        let target = format!("TrentService.{}", action);
+        let body = serde_json::to_vec(&body)?;
+        let host = match (self.endpoint.host_str(), self.endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => bail!("KMS endpoint {} has no host", self.endpoint),
+        };
+        let credentials = self.credentials.credentials(&self.http).await?;
+        let headers = sigv4_headers(
+            &credentials,
+            &self.region,
+            &host,
+            self.endpoint.path(),
+            &target,
+            &body,
+            Utc::now(),
+        );
+        let mut request = self.http.post(self.endpoint.clone());
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("KMS {} request failed", action))?;
+        json_response(response, &format!("KMS {}", action)).await
+    }
+}
+
+/// Signs tokens with an AWS KMS asymmetric key.
+pub struct KmsSigner {
+    client: KmsClient,
+    key_id: String,
+    key: RemoteKey,
+}
+
+impl KmsSigner {
+    /// Connects to KMS and fetches the public key of the configured key.
+    pub async fn new(config: &KmsSigningConfig) -> Result<Self> {
+        let region = config
+            .region
+            .clone()
+            .or_else(|| env("AWS_REGION"))
+            .or_else(|| env("AWS_DEFAULT_REGION"))
+            .ok_or_else(|| {
+                anyhow!("token_signing.kms.region is not set and AWS_REGION is not set")
+            })?;
+        let endpoint = config
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://kms.{}.amazonaws.com", region));
+        let client = KmsClient {
+            http: http_client()?,
+            endpoint: url::Url::parse(&endpoint)
+                .with_context(|| format!("invalid KMS endpoint {}", endpoint))?,
+            credentials: AwsCredentialSource::from_env(&region)?,
+            region,
+        };
+
+        let response = client
+            .call("GetPublicKey", json!({ "KeyId": config.key_id }))
+            .await?;
+        let spec = response["KeySpec"].as_str().unwrap_or_default();
+        if spec.starts_with("ECC_") && spec != "ECC_NIST_P256" {
+            bail!(
+                "unsupported KMS key spec {}, use RSA or ECC_NIST_P256",
+                spec
+            );
+        }
+        let der = STANDARD
+            .decode(response["PublicKey"].as_str().unwrap_or_default())
+            .context("KMS returned an invalid public key")?;
+        let key = RemoteKey::from_spki(&der)?;
+        tracing::info!(
+            "Signing tokens with KMS key {} ({:?}, kid {})",
+            config.key_id,
+            key.algorithm,
+            key.kid
+        );
+        Ok(Self {
+            client,
+            key_id: config.key_id.clone(),
+            key,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for KmsSigner {
+    fn kid(&self) -> &str {
+        &self.key.kid
+    }
+
+    async fn sign(&self, header: Header, claims: &Value) -> Result<String> {
+        let (input, digest) = self.key.signing_input(header, claims)?;
+        let algorithm = match self.key.algorithm {
+            Algorithm::ES256 => "ECDSA_SHA_256",
+            _ => "RSASSA_PKCS1_V1_5_SHA_256",
+        };
+        let response = self
+            .client
+            .call(
+                "Sign",
+                json!({
+                    "KeyId": self.key_id,
+                    "Message": STANDARD.encode(digest),
+                    "MessageType": "DIGEST",
+                    "SigningAlgorithm": algorithm,
+                }),
+            )
+            .await?;
+        let signature = STANDARD
+            .decode(response["Signature"].as_str().unwrap_or_default())
+            .context("KMS returned an invalid signature")?;
+        let signature = match self.key.algorithm {
+            Algorithm::ES256 => ecdsa_der_to_raw(&signature)?,
+            _ => signature,
+        };
+        Ok(format!("{}.{}", input, URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    fn jwks(&self) -> Value {
+        self.key.jwks()
+    }
+}
+
+/// How Key Vault access tokens are obtained.
+enum AzureCredential {
+    /// Client credentials of an application with a secret.
+    ClientSecret {
+        token_url: String,
+        client_id: String,
+        secret: String,
+    },
+    /// Client credentials with a federated token (AKS workload identity).
+    WorkloadIdentity {
+        token_url: String,
+        client_id: String,
+        token_file: String,
+    },
+    /// The managed identity of the VM or node, from the instance metadata
+    /// service.
+    ManagedIdentity { client_id: Option<String> },
+}
+
+/// Calls to Key Vault with a cached access token.
+struct KeyVaultClient {
+    http: reqwest::Client,
+    credential: AzureCredential,
+    access_token: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+/// Signs tokens with an Azure Key Vault key.
+pub struct KeyVaultSigner {
+    client: KeyVaultClient,
+    /// URL of the key version signing tokens.
+    key_url: String,
+    key: RemoteKey,
+}
+
+impl KeyVaultSigner {
+    /// Authenticates to Key Vault and fetches the public key of the
+    /// configured key.
+    pub async fn new(config: &KeyVaultSigningConfig) -> Result<Self> {
+        let tenant_id = config.tenant_id.clone().or_else(|| env("AZURE_TENANT_ID"));
+        let client_id = config.client_id.clone().or_else(|| env("AZURE_CLIENT_ID"));
+        let authority = config
+            .authority_host
+            .clone()
+            .or_else(|| env("AZURE_AUTHORITY_HOST"))
+            .unwrap_or_else(|| DEFAULT_AUTHORITY_HOST.to_string());
+        let token_url = || -> Result<String> {
+            let tenant_id = tenant_id.as_deref().ok_or_else(|| {
+                anyhow!(
+                    "token_signing.keyvault.tenant_id is not set and AZURE_TENANT_ID is not set"
+                )
+            })?;
+            Ok(format!(
+                "{}/{}/oauth2/v2.0/token",
+                authority.trim_end_matches('/'),
+                tenant_id
+            ))
+        };
+        let required_client_id = || {
+            client_id.clone().ok_or_else(|| {
+                anyhow!(
+                    "token_signing.keyvault.client_id is not set and AZURE_CLIENT_ID is not set"
+                )
+            })
+        };
+        let credential = match (&config.client_secret, env("AZURE_FEDERATED_TOKEN_FILE")) {
+            (Some(secret), _) => AzureCredential::ClientSecret {
+                token_url: token_url()?,
+                client_id: required_client_id()?,
+                secret: secret.clone(),
+            },
+            (None, Some(token_file)) => AzureCredential::WorkloadIdentity {
+                token_url: token_url()?,
+                client_id: required_client_id()?,
+                token_file,
+            },
+            (None, None) => AzureCredential::ManagedIdentity {
+                client_id: client_id.clone(),
+            },
+        };
+
+        let client = KeyVaultClient {
+            http: http_client()?,
+            credential,
+            access_token: Mutex::new(None),
+        };
+        let mut key_url = format!(
+            "{}/keys/{}",
+            config.vault_url.trim_end_matches('/'),
+            urlencoding::encode(&config.key_name)
+        );
+        if let Some(version) = &config.key_version {
+            key_url = format!("{}/{}", key_url, urlencoding::encode(version));
+        }
+
+        let token = client.token().await?;
+        let response = client
+            .http
+            .get(&key_url)
+            .query(&[("api-version", KEYVAULT_API_VERSION)])
+            .bearer_auth(token)
+            .send()
+            .await
+            .context("Key Vault request failed")?;
+        let body = json_response(response, "Key Vault get key").await?;
+        let key = RemoteKey::from_jwk(&body["key"])?;
+        // Pin the version read, so the published key is the one signing
+        if let Some(kid) = body["key"]["kid"].as_str() {
+            key_url = kid.to_string();
+        }
+        tracing::info!(
+            "Signing tokens with Key Vault key {} ({:?}, kid {})",
+            key_url,
+            key.algorithm,
+            key.kid
+        );
+        Ok(Self {
+            client,
+            key_url,
+            key,
+        })
+    }
+}
+
+impl KeyVaultClient {
+    /// Returns a Key Vault access token, renewed shortly before it expires.
+    async fn token(&self) -> Result<String> {
+        let mut cached = self.access_token.lock().await;
+        if let Some((token, expires)) = cached.as_ref()
+            && Utc::now() + chrono::Duration::seconds(RENEW_MARGIN_SECS) < *expires
+        {
+            return Ok(token.clone());
+        }
+
+        let request = match &self.credential {
+            AzureCredential::ClientSecret {
+                token_url,
+                client_id,
+                secret,
+            } => self.http.post(token_url).form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", secret.as_str()),
+                ("scope", KEYVAULT_SCOPE),
+            ]),
+            AzureCredential::WorkloadIdentity {
+                token_url,
+                client_id,
+                token_file,
+            } => {
+                let assertion = tokio::fs::read_to_string(token_file)
+                    .await
+                    .with_context(|| format!("failed to read federated token {}", token_file))?;
+                self.http.post(token_url).form(&[
+                    ("grant_type", "client_credentials"),
+                    ("client_id", client_id.as_str()),
+                    (
+                        "client_assertion_type",
+                        "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+                    ),
+                    ("client_assertion", assertion.trim()),
+                    ("scope", KEYVAULT_SCOPE),
+                ])
+            }
+            AzureCredential::ManagedIdentity { client_id } => {
+                let mut query = vec![
+                    ("api-version", "2018-02-01"),
+                    ("resource", KEYVAULT_RESOURCE),
+                ];
+                if let Some(client_id) = client_id {
+                    query.push(("client_id", client_id.as_str()));
+                }
+                self.http
+                    .get(IMDS_TOKEN_URL)
+                    .query(&query)
+                    .header("Metadata", "true")
+            }
+        };
+        let response = request
+            .send()
+            .await
+            .context("Microsoft Entra token request failed")?;
+        let body = json_response(response, "Microsoft Entra token request").await?;
+        let token = body["access_token"]
+            .as_str()
+            .ok_or_else(|| anyhow!("token response has no access_token"))?
+            .to_string();
+        let expires_in = seconds(&body["expires_in"]).unwrap_or(3600);
+        *cached = Some((
+            token.clone(),
+            Utc::now() + chrono::Duration::seconds(expires_in),
+        ));
+        Ok(token)
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for KeyVaultSigner {
+    fn kid(&self) -> &str {
+        &self.key.kid
+    }
+
+    async fn sign(&self, header: Header, claims: &Value) -> Result<String> {
+        let (input, digest) = self.key.signing_input(header, claims)?;
+        let alg = match self.key.algorithm {
+            Algorithm::ES256 => "ES256",
+            _ => "RS256",
+        };
+        let token = self.client.token().await?;
+        let response = self
+            .client
+            .http
+            .post(format!("{}/sign", self.key_url))
+            .query(&[("api-version", KEYVAULT_API_VERSION)])
+            .header(CONTENT_TYPE, "application/json")
+            .bearer_auth(token)
+            .json(&json!({ "alg": alg, "value": URL_SAFE_NO_PAD.encode(digest) }))
+            .send()
+            .await
+            .context("Key Vault sign request failed")?;
+        let body = json_response(response, "Key Vault sign").await?;
+        // Key Vault returns ECDSA signatures as r || s already
+        let signature = URL_SAFE_NO_PAD
+            .decode(
+                body["value"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .trim_end_matches('='),
+            )
+            .context("Key Vault returned an invalid signature")?;
+        Ok(format!("{}.{}", input, URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    fn jwks(&self) -> Value {
+        self.key.jwks()
+    }
+}
@@ -0,0 +1,214 @@
+//! LDAP / Active Directory authentication.
+//!
+//! Verifies username/password credentials by binding to the directory as the
+//! user's entry and collects the user's attributes and group DNs. See
+//! [`crate::config::models::LdapConfig`] for how entries are located.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use ldap3::{LdapConnAsync, LdapConnSettings, Scope, SearchEntry, dn_escape, ldap_escape};
+
+use crate::config::models::LdapConfig;
+
+const DEFAULT_USER_FILTER: &str = "(uid={username})";
+const DEFAULT_GROUP_FILTER: &str = "(member={dn})";
+const DEFAULT_EMAIL_ATTRIBUTE: &str = "mail";
+const DEFAULT_NAME_ATTRIBUTE: &str = "displayName";
+const MEMBER_OF_ATTRIBUTE: &str = "memberOf";
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// A directory user whose password has been verified.
+#[derive(Clone, Debug)]
+pub struct DirectoryUser {
+    /// DN of the user's entry.
+    pub dn: String,
+    /// Email address, if the entry has one.
+    pub email: Option<String>,
+    /// Display name, if the entry has one.
+    pub name: Option<String>,
+    /// DNs of the groups the user belongs to.
+    pub groups: Vec<String>,
+}
+
+/// Resolved LDAP provider settings.
+#[derive(Clone, Debug)]
+pub struct Directory {
+    config: LdapConfig,
+    timeout: Duration,
+}
+
+impl Directory {
+    /// Validates the configuration.
+    pub fn from_config(config: &LdapConfig) -> Result<Self> {
+        if !config.url.starts_with("ldap://") && !config.url.starts_with("ldaps://") {
+            bail!(
+                "LDAP provider url must be an ldap:// or ldaps:// URL: {}",
+                config.url
+            );
+        }
+        match (&config.user_dn_template, &config.user_base_dn) {
+            (Some(template), _) if !template.contains("{username}") => {
+                bail!("LDAP user_dn_template must contain {{username}}")
+            }
+            (None, None) => bail!("LDAP provider requires user_dn_template or user_base_dn"),
+            _ => {}
+        }
+        if config.bind_dn.is_some() != config.bind_password.is_some() {
+            bail!("LDAP bind_dn and bind_password must be set together");
+        }
+        Ok(Self {
+            config: config.clone(),
+            timeout: Duration::from_secs(config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS)),
+        })
+    }
+
+    /// The directory URL, used as the principal's provider.
+    pub fn url(&self) -> &str {
+        &self.config.url
+    }
+
+    /// Verifies the credentials against the directory.
+    ///
+    /// Returns `Ok(None)` when the user does not exist, is ambiguous or the
+    /// password is wrong, and an error when the directory cannot be reached
+    /// or the service account bind fails.
+    pub async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<DirectoryUser>> {
+        // An empty password would be an unauthenticated bind, which most
+        // directories accept for any DN.
+        if username.is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.timeout)
+            .set_starttls(self.config.starttls)
+            .set_no_tls_verify(self.config.insecure);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.config.url)
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.config.url))?;
+        ldap3::drive!(conn);
+
+        let email_attribute = self
+            .config
+            .email_attribute
+            .as_deref()
+            .unwrap_or(DEFAULT_EMAIL_ATTRIBUTE);
+        let name_attribute = self
+            .config
+            .name_attribute
+            .as_deref()
+            .unwrap_or(DEFAULT_NAME_ATTRIBUTE);
+        let attributes = [email_attribute, name_attribute, MEMBER_OF_ATTRIBUTE];
+
+        let entry = if let Some(template) = &self.config.user_dn_template {
+            let dn = template.replace("{username}", &dn_escape(username));
+            let bound = ldap
+                .with_timeout(self.timeout)
+                .simple_bind(&dn, password)
+                .await?;
+            if bound.rc != 0 {
+                tracing::debug!("LDAP bind failed for {}: rc={}", dn, bound.rc);
+                return Ok(None);
+            }
+            let (entries, _) = ldap
+                .with_timeout(self.timeout)
+                .search(&dn, Scope::Base, "(objectClass=*)", attributes.to_vec())
+                .await?
+                .success()?;
+            entries.into_iter().next().map(SearchEntry::construct)
+        } else {
+            if let (Some(bind_dn), Some(bind_password)) =
+                (&self.config.bind_dn, &self.config.bind_password)
+            {
+                ldap.with_timeout(self.timeout)
+                    .simple_bind(bind_dn, bind_password)
+                    .await?
+                    .success()
+                    .context("LDAP service account bind failed")?;
+            }
+            let base = self.config.user_base_dn.as_deref().unwrap_or_default();
+            let filter = self
+                .config
+                .user_filter
+                .as_deref()
+                .unwrap_or(DEFAULT_USER_FILTER)
+                .replace("{username}", &ldap_escape(username));
+            let (entries, _) = ldap
+                .with_timeout(self.timeout)
+                .search(base, Scope::Subtree, &filter, attributes.to_vec())
+                .await?
+                .success()?;
+            if entries.len() != 1 {
+                tracing::debug!(
+                    "LDAP user search '{}' returned {} entries",
+                    filter,
+                    entries.len()
+                );
+                return Ok(None);
+            }
+            let entry = entries.into_iter().next().map(SearchEntry::construct);
+            let Some(dn) = entry.as_ref().map(|e| e.dn.clone()) else {
+                return Ok(None);
+            };
+            let bound = ldap
+                .with_timeout(self.timeout)
+                .simple_bind(&dn, password)
+                .await?;
+            if bound.rc != 0 {
+                tracing::debug!("LDAP bind failed for {}: rc={}", dn, bound.rc);
+                return Ok(None);
+            }
+            entry
+        };
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+
+        let mut groups = attribute_values(&entry.attrs, MEMBER_OF_ATTRIBUTE);
+        if let Some(base) = &self.config.group_base_dn {
+            let filter = self
+                .config
+                .group_filter
+                .as_deref()
+                .unwrap_or(DEFAULT_GROUP_FILTER)
+                .replace("{dn}", &ldap_escape(entry.dn.as_str()))
+                .replace("{username}", &ldap_escape(username));
+            let (entries, _) = ldap
+                .with_timeout(self.timeout)
+                .search(base, Scope::Subtree, &filter, vec!["1.1"])
+                .await?
+                .success()
+                .map_err(|e| anyhow!("LDAP group search failed: {}", e))?;
+            groups.extend(entries.into_iter().map(|e| SearchEntry::construct(e).dn));
+        }
+        groups.sort();
+        groups.dedup();
+        let _ = ldap.unbind().await;
+
+        Ok(Some(DirectoryUser {
+            email: attribute_values(&entry.attrs, email_attribute)
+                .into_iter()
+                .next(),
+            name: attribute_values(&entry.attrs, name_attribute)
+                .into_iter()
+                .next(),
+            dn: entry.dn,
+            groups,
+        }))
+    }
+}
+
+/// Returns the values of an attribute; names are matched case-insensitively
+/// as directories do.
+fn attribute_values(attrs: &HashMap<String, Vec<String>>, name: &str) -> Vec<String> {
+    attrs
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.clone())
+        .unwrap_or_default()
+}
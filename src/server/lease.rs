@@ -0,0 +1,68 @@
+//! Leases electing one instance to run each periodic task.
+//!
+//! Instances sharing a database would otherwise all run jobs such as session
+//! cleanup and usage rollups. Each tick, a job first takes or renews its
+//! lease in `task_leases`; only the holder runs it, and another instance
+//! takes over once the holder stops renewing and the lease expires. Expiry
+//! is compared with each instance's clock, so clocks should be kept in sync.
+//! Without a database every instance runs its jobs.
+
+use std::sync::{
+    LazyLock,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::Duration;
+
+use crate::server::persist::Database;
+
+/// Holder id of this process.
+static INSTANCE: LazyLock<String> = LazyLock::new(|| hex::encode(rand::random::<[u8; 8]>()));
+
+/// Lease on expired session cleanup, run every minute.
+pub static SESSION_CLEANUP: Lease = Lease::new("session_cleanup", Duration::from_secs(150));
+
+/// Lease on tool usage rollups (see [`crate::server::usage`]).
+pub static USAGE_ROLLUP: Lease = Lease::new("usage_rollup", Duration::from_secs(150));
+
+/// Lease on one periodic task. Its time to live should span a few runs, so
+/// the holder renews it before it expires.
+#[derive(Debug)]
+pub struct Lease {
+    task: &'static str,
+    ttl: Duration,
+    /// Whether this instance held the lease when last checked
+    held: AtomicBool,
+}
+
+impl Lease {
+    pub const fn new(task: &'static str, ttl: Duration) -> Self {
+        Self {
+            task,
+            ttl,
+            held: AtomicBool::new(false),
+        }
+    }
+
+    /// Takes or renews the lease, returning whether this instance should
+    /// run the task. A storage failure counts as not holding it.
+    pub async fn acquire(&self, database: &Database) -> bool {
+        let held = match database
+            .acquire_lease_async(self.task, &INSTANCE, self.ttl)
+            .await
+        {
+            Ok(held) => held,
+            Err(e) => {
+                tracing::warn!("Failed to renew the {} lease: {}", self.task, e);
+                false
+            }
+        };
+        if self.held.swap(held, Ordering::AcqRel) != held {
+            if held {
+                tracing::info!("This instance now runs {}", self.task);
+            } else {
+                tracing::info!("Another instance now runs {}", self.task);
+            }
+        }
+        held
+    }
+}
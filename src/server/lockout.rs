@@ -0,0 +1,162 @@
+//! Throttling of failed sign-in attempts.
+//!
+//! Failures are counted per username and per client IP. Each failure for a
+//! username doubles the wait before its next attempt, and a username or IP
+//! that reaches its limit is locked out for a fixed period. Counters are
+//! forgotten once that period passes without a further failure.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::extract::ConnectInfo;
+use axum::http::{Extensions, HeaderMap};
+
+use crate::config::models::LockoutConfig;
+use crate::server::proxy::Forwarded;
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    blocked_until: Option<Instant>,
+}
+
+/// Tracks failed sign-in attempts and decides when to refuse new ones.
+#[derive(Debug)]
+pub struct LoginLimiter {
+    config: LockoutConfig,
+    entries: Mutex<HashMap<String, Failures>>,
+}
+
+impl LoginLimiter {
+    pub fn new(config: LockoutConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolves the client IP as reported by a trusted proxy, from the
+    /// first `X-Forwarded-For` entry when `trust_forwarded_for` is set, or
+    /// from the connection.
+    pub fn client_ip(&self, headers: &HeaderMap, extensions: &Extensions) -> Option<IpAddr> {
+        if let Some(forwarded) = extensions.get::<Forwarded>() {
+            return Some(forwarded.client);
+        }
+        if self.config.trust_forwarded_for
+            && let Some(ip) = headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .and_then(|v| v.trim().parse().ok())
+        {
+            return Some(ip);
+        }
+        extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+
+    /// Returns how long the caller has to wait when the IP or username is
+    /// currently blocked, counting the refusal against `endpoint`.
+    pub fn check(
+        &self,
+        endpoint: &str,
+        ip: Option<IpAddr>,
+        username: Option<&str>,
+    ) -> Option<Duration> {
+        if !self.config.enabled {
+            return None;
+        }
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let wait = limit_keys(ip, username)
+            .iter()
+            .filter_map(|key| entries.get(key)?.blocked_until)
+            .filter_map(|until| until.checked_duration_since(now))
+            .filter(|wait| !wait.is_zero())
+            .max();
+        if wait.is_some() {
+            crate::metrics::record_auth_throttled(endpoint);
+        }
+        wait
+    }
+
+    /// Records a failed attempt at `endpoint` and blocks the IP or username
+    /// when it has to back off or reached its limit.
+    pub fn record_failure(&self, endpoint: &str, ip: Option<IpAddr>, username: Option<&str>) {
+        crate::metrics::record_auth_failure(endpoint);
+        if !self.config.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let lockout = Duration::from_secs(self.config.lockout_seconds);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        for key in limit_keys(ip, username) {
+            let is_user = key.starts_with("user:");
+            let limit = if is_user {
+                self.config.max_attempts
+            } else {
+                self.config.max_attempts_per_ip
+            };
+            let failures = entries.entry(key.clone()).or_insert(Failures {
+                count: 0,
+                last_failure: now,
+                blocked_until: None,
+            });
+            if now.duration_since(failures.last_failure) >= lockout {
+                failures.count = 0;
+            }
+            failures.count += 1;
+            failures.last_failure = now;
+
+            if failures.count >= limit.max(1) {
+                tracing::warn!(
+                    "Locking out {} for {}s after {} failed sign-in attempts",
+                    key,
+                    lockout.as_secs(),
+                    failures.count
+                );
+                crate::metrics::record_auth_lockout(if is_user { "user" } else { "ip" });
+                failures.blocked_until = Some(now + lockout);
+            } else if is_user && self.config.backoff_base_seconds > 0 {
+                let delay = self
+                    .config
+                    .backoff_base_seconds
+                    .saturating_mul(1u64 << (failures.count - 1).min(32));
+                failures.blocked_until = Some(now + Duration::from_secs(delay).min(lockout));
+            }
+        }
+    }
+
+    /// Forgets the failures for a username after it signed in successfully.
+    pub fn record_success(&self, username: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(&user_key(username));
+    }
+
+    /// Drops counters that are no longer blocking and have been quiet for the
+    /// lockout period.
+    pub fn purge(&self) {
+        let now = Instant::now();
+        let lockout = Duration::from_secs(self.config.lockout_seconds);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, f| {
+            now.duration_since(f.last_failure) < lockout
+                || f.blocked_until.is_some_and(|until| until > now)
+        });
+    }
+}
+
+fn user_key(username: &str) -> String {
+    format!("user:{}", username.trim().to_lowercase())
+}
+
+fn limit_keys(ip: Option<IpAddr>, username: Option<&str>) -> Vec<String> {
+    ip.map(|ip| format!("ip:{}", ip))
+        .into_iter()
+        .chain(username.map(user_key))
+        .collect()
+}
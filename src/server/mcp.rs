@@ -2,9 +2,14 @@
 use std::sync::Arc;
 use std::time::Instant;
 
+use crate::errors::ErrorCode;
+use crate::logging::RequestId;
+use crate::server::auth::Principal;
+use crate::server::authz::{self, Permission, ToolScope};
 use crate::server::constants::{
     MCP_SERVER_INFO_NAME, MCP_SERVER_INFO_TITLE, MCP_SERVER_INFO_URL, MCP_SERVER_INFO_VERSION,
 };
+use crate::server::usage::{ToolCall, check_quota};
 use crate::state::ArkState;
 use rmcp::handler::server::ServerHandler;
 use rmcp::model::{Implementation, ServerCapabilities};
@@ -14,6 +19,7 @@ use rmcp::{ErrorData, RoleServer};
 use serde_json::{Map, Value};
 use tokio::runtime::Handle;
 use tokio::task::block_in_place;
+use tracing::Instrument;
 
 /// Handler for MCP (Model Context Protocol) server operations.
 ///
@@ -25,6 +31,14 @@ pub struct McpHandler {
     pub state: Arc<ArkState>,
 }
 
+/// Continues the caller's distributed trace when the MCP request came over
+/// HTTP with a `traceparent` header.
+fn continue_trace(span: &tracing::Span, context: &RequestContext<RoleServer>) {
+    if let Some(parts) = context.extensions.get::<axum::http::request::Parts>() {
+        crate::telemetry::set_remote_parent(span, &parts.headers);
+    }
+}
+
 // Implement ServerHandler interface
 impl ServerHandler for McpHandler {
     /// Handle MCP initialization request.
@@ -33,10 +47,12 @@ impl ServerHandler for McpHandler {
     fn initialize(
         &self,
         _request: rmcp::model::InitializeRequestParam,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> impl Future<Output = Result<rmcp::model::InitializeResult, ErrorData>> + Send + '_ {
         let start = Instant::now();
         tracing::debug!("McpHandler: initialize");
+        let span = tracing::info_span!("mcp.request", "mcp.method" = "initialize");
+        continue_trace(&span, &context);
         async move {
             // Return the same server info as get_info
             let server_info = self.get_info();
@@ -50,6 +66,7 @@ impl ServerHandler for McpHandler {
             crate::metrics::record_mcp_call("initialize", latency_ms);
             result
         }
+        .instrument(span)
     }
 
     /// Implementation of the ServerHandler trait for MCP operations.
@@ -112,27 +129,40 @@ impl ServerHandler for McpHandler {
     /// Returns a list of tools available on the server.
     ///
     /// Constructs a flat list where each registered plugin that has a handler
-    /// is exposed as a tool (name == plugin id).
+    /// is exposed as a tool (name == plugin id). Callers with a scoped token
+    /// only see the tools the token may call.
     ///
     /// # Arguments
     /// * `_request` - Optional pagination parameters (currently unused)
-    /// * `_context` - Request context (currently unused)
+    /// * `context` - Request context; over HTTP it carries the token's tool scope
     ///
     /// # Returns
     /// A future resolving to `ListToolsResult` or an `ErrorData`.
     fn list_tools(
         &self,
         _request: Option<PaginatedRequestParam>,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> impl Future<Output = Result<ListToolsResult, ErrorData>> + Send + '_ {
         let start = Instant::now();
         tracing::debug!("McpHandler: list_tools");
+        let span = tracing::info_span!("mcp.request", "mcp.method" = "tools/list");
+        continue_trace(&span, &context);
         async move {
-            let catalog = self.state.plugin_registry.catalog.read().await;
+            let scope = context
+                .extensions
+                .get::<axum::http::request::Parts>()
+                .and_then(|parts| parts.extensions.get::<ToolScope>());
+            let catalog = self.state.plugin_registry.catalog.load();
             let mut tools = Vec::new();
 
             for (tool_name, tool_def) in catalog.tool_to_def.iter() {
-                if self.state.plugin_registry.has_handler(tool_name) {
+                let in_scope = scope.is_none_or(|s| {
+                    s.allows(
+                        catalog.tool_to_plugin.get(tool_name).map(String::as_str),
+                        tool_name,
+                    )
+                });
+                if in_scope && self.state.plugin_registry.has_handler(tool_name) {
                     tools.push(tool_def.clone());
                 }
             }
@@ -145,6 +175,7 @@ impl ServerHandler for McpHandler {
             crate::metrics::record_mcp_call("list_tools", latency_ms);
             result
         }
+        .instrument(span)
     }
 
     /// Dispatches a tool call to the plugin registry.
@@ -156,7 +187,8 @@ impl ServerHandler for McpHandler {
     ///
     /// # Arguments
     /// * `request` - The tool call request parameters
-    /// * `_context` - Request context (currently unused)
+    /// * `context` - Request context; over HTTP it carries the request parts,
+    ///   including the principal set by the auth middleware
     ///
     /// # Returns
     /// A future resolving to `CallToolResult` or an `ErrorData`.
@@ -168,14 +200,61 @@ impl ServerHandler for McpHandler {
     fn call_tool(
         &self,
         request: rmcp::model::CallToolRequestParam,
-        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+        context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
     ) -> impl std::future::Future<Output = Result<rmcp::model::CallToolResult, rmcp::ErrorData>>
     + Send
     + '_ {
         let start = Instant::now();
         tracing::debug!("McpHandler: call_tool");
+        // Tool calls run in the session's task, so take the id from the HTTP request
+        let request_id = context
+            .extensions
+            .get::<axum::http::request::Parts>()
+            .and_then(|parts| parts.extensions.get::<RequestId>())
+            .map(|id| id.0.clone());
+        let span = tracing::info_span!(
+            "mcp.request",
+            "mcp.method" = "tools/call",
+            tool = %request.name,
+            request_id = request_id.as_deref()
+        );
+        continue_trace(&span, &context);
+
+        let call = async move {
+            // Authenticated callers need the tool.execute permission
+            let parts = context.extensions.get::<axum::http::request::Parts>();
+            let principal = parts.and_then(|parts| parts.extensions.get::<Principal>());
+            if !authz::allows(&self.state, principal, Permission::ToolExecute) {
+                return Err(rmcp::ErrorData::invalid_request(
+                    "Permission denied: tool.execute is required",
+                    ErrorCode::PermissionDenied.data(),
+                ));
+            }
+
+            // Scoped tokens may only call the tools they were issued for
+            if let Some(scope) = parts.and_then(|parts| parts.extensions.get::<ToolScope>()) {
+                let catalog = self.state.plugin_registry.catalog.load();
+                let owner = catalog.tool_to_plugin.get(request.name.as_ref());
+                if !scope.allows(owner.map(String::as_str), &request.name) {
+                    return Err(rmcp::ErrorData::invalid_request(
+                        format!(
+                            "Permission denied: token scope does not include tool '{}'",
+                            request.name
+                        ),
+                        ErrorCode::ScopeDenied.data(),
+                    ));
+                }
+            }
+
+            // Callers that used up a monthly quota wait for the next month
+            let caller = principal.map(Principal::global_id);
+            if let Err(exceeded) = check_quota(&self.state, caller.as_deref()).await {
+                return Err(rmcp::ErrorData::invalid_request(
+                    exceeded.to_string(),
+                    ErrorCode::QuotaExceeded.data(),
+                ));
+            }
 
-        async move {
             // Convert optional arguments (JsonObject) to a serde_json::Value
             let args_value = match request.arguments {
                 Some(map) => rmcp::serde_json::Value::Object(map),
@@ -186,19 +265,26 @@ impl ServerHandler for McpHandler {
             let registry = &self.state.plugin_registry;
             let plugin_id = request.name.as_ref();
 
-            let result = match registry.call(plugin_id, &args_value).await {
+            let call = registry.call(plugin_id, &args_value);
+            let result = match authz::with_caller(principal.cloned(), call).await {
                 Ok(val) => {
                     // The plugin returns a CallToolResult as JSON Value, deserialize it
                     match serde_json::from_value::<rmcp::model::CallToolResult>(val) {
                         Ok(result) => Ok(result),
                         Err(e) => Err(rmcp::ErrorData::invalid_params(
                             format!("Plugin returned invalid CallToolResult: {}", e),
-                            None,
+                            ErrorCode::ToolFailed.data(),
                         )),
                     }
                 }
                 Err(err) => {
-                    // Return a proper MCP error CallToolResult
+                    // Return a proper MCP error CallToolResult, with the
+                    // error code in its _meta
+                    let mut meta = rmcp::model::Meta::new();
+                    meta.insert(
+                        "code".to_string(),
+                        Value::from(ErrorCode::of(&err).as_str()),
+                    );
                     Ok(rmcp::model::CallToolResult {
                         content: vec![rmcp::model::Content {
                             raw: rmcp::model::RawContent::Text(rmcp::model::RawTextContent {
@@ -208,7 +294,7 @@ impl ServerHandler for McpHandler {
                             annotations: None,
                         }],
                         is_error: Some(true),
-                        meta: None,
+                        meta: Some(meta),
                         structured_content: None,
                     })
                 }
@@ -222,11 +308,75 @@ impl ServerHandler for McpHandler {
                 && call_result.is_error != Some(true)
             {
                 // Only record successful tool calls
-                crate::metrics::record_tool_metrics(plugin_id, plugin_id, latency_ms);
+                let catalog = registry.catalog.load();
+                let owner = catalog
+                    .tool_to_plugin
+                    .get(plugin_id)
+                    .and_then(|p| catalog.plugin_to_config.get(p))
+                    .and_then(|cfg| cfg.owner.as_deref());
+                crate::metrics::record_tool_metrics(plugin_id, plugin_id, owner, latency_ms);
+            }
+
+            // The persisted usage counters also count failed calls
+            let plugin = {
+                let catalog = registry.catalog.load();
+                catalog
+                    .tool_to_plugin
+                    .get(plugin_id)
+                    .and_then(|p| catalog.plugin_to_config.get(p))
+                    .map(|cfg| (cfg.name.clone(), cfg.owner.clone()))
+            };
+            if let Some((plugin_name, owner)) = plugin {
+                let error = match &result {
+                    Ok(r) if r.is_error != Some(true) => None,
+                    Ok(r) => Some(
+                        r.meta
+                            .as_ref()
+                            .and_then(|meta| meta.get("code"))
+                            .and_then(Value::as_str)
+                            .and_then(ErrorCode::parse)
+                            .unwrap_or(ErrorCode::ToolFailed),
+                    ),
+                    Err(e) => Some(ErrorCode::of_mcp(e)),
+                };
+                let bytes_returned = match &result {
+                    Ok(r) => serde_json::to_string(r).map_or(0, |s| s.len() as u64),
+                    Err(_) => 0,
+                };
+                crate::server::usage::record_tool_call(
+                    &self.state,
+                    ToolCall {
+                        plugin_id: &plugin_name,
+                        tool: plugin_id,
+                        owner: owner.as_deref(),
+                        principal: caller.as_deref(),
+                        args: &args_value,
+                        error,
+                        latency_ms,
+                        bytes_returned,
+                    },
+                )
+                .await;
             }
 
             result
+        };
+
+        async move {
+            let Some(request_id) = request_id else {
+                return call.await;
+            };
+            crate::logging::with_request_id(request_id.clone(), call)
+                .await
+                .map_err(|mut e| {
+                    // Let clients quote the id when reporting the failure
+                    if e.data.is_none() {
+                        e.data = Some(serde_json::json!({ "request_id": request_id }));
+                    }
+                    e
+                })
         }
+        .instrument(span)
     }
 }
 
@@ -256,7 +406,7 @@ impl McpHandler {
         }
 
         // Look up which plugin owns this tool
-        let store_guard = self.state.plugin_registry.catalog.read().await;
+        let store_guard = self.state.plugin_registry.catalog.load();
         let plugin_id = match store_guard.tool_to_plugin.get(tool_id) {
             Some(id) => id,
             None => return false, // Tool doesn't exist
@@ -0,0 +1,239 @@
+//! Streamable HTTP MCP sessions shared by the instances using one database.
+//!
+//! rmcp keeps each session in the memory of the instance that opened it, so
+//! behind a load balancer a continuation request reaching another replica
+//! fails with "Session not found". With `mcp_server.shared_sessions`, the
+//! initialize request of every session is stored in `mcp_sessions`; a
+//! replica receiving a request for a session it does not know restores it
+//! by replaying the initialize handshake to a local session with the same
+//! id. Requests are handled by whichever replica receives them, so only
+//! server-to-client messages of a stream, which stay with the replica that
+//! opened it, depend on sticky routing.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::Stream;
+use rmcp::{
+    model::{ClientJsonRpcMessage, ServerJsonRpcMessage},
+    service::serve_server,
+    transport::{
+        WorkerTransport,
+        common::server_side_http::ServerSseMessage,
+        streamable_http_server::session::{
+            SessionId, SessionManager,
+            local::{LocalSessionManager, LocalSessionManagerError, create_local_session},
+        },
+    },
+};
+
+use crate::server::{mcp::McpHandler, persist::Database};
+use crate::state::ArkState;
+
+/// How long a shared session may go unused before it is forgotten.
+pub const SESSION_IDLE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// How often a session in use is marked as seen in the database.
+const TOUCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Errors of [`SharedSessionManager`].
+#[derive(Debug, thiserror::Error)]
+pub enum SharedSessionError {
+    #[error(transparent)]
+    Local(#[from] LocalSessionManagerError),
+    #[error("Invalid stored session: {0}")]
+    Stored(#[from] serde_json::Error),
+    #[error("Session store failed: {0:#}")]
+    Store(anyhow::Error),
+}
+
+/// Session manager storing sessions in the database so that any instance
+/// can continue them. Without a database it behaves as
+/// [`LocalSessionManager`].
+pub struct SharedSessionManager {
+    local: Arc<LocalSessionManager>,
+    state: Arc<ArkState>,
+    /// When each local session was last marked as seen
+    touched: Mutex<HashMap<SessionId, Instant>>,
+    /// Held while restoring, so concurrent requests restore a session once
+    restoring: tokio::sync::Mutex<()>,
+}
+
+impl SharedSessionManager {
+    pub fn new(state: Arc<ArkState>) -> Self {
+        Self {
+            local: Arc::new(LocalSessionManager::default()),
+            state,
+            touched: Mutex::new(HashMap::new()),
+            restoring: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn database(&self) -> Option<Database> {
+        self.state.database.read().ok().and_then(|g| g.clone())
+    }
+
+    fn touched(&self) -> std::sync::MutexGuard<'_, HashMap<SessionId, Instant>> {
+        self.touched.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Marks a shared session as seen, at most every [`TOUCH_INTERVAL`], so
+    /// it is not forgotten while in use. Returns false once another instance
+    /// closed it or it expired.
+    async fn touch(&self, id: &SessionId, database: &Database) -> bool {
+        match self.touched().get(id) {
+            // Not shared, as storing it failed
+            None => return true,
+            Some(at) if at.elapsed() < TOUCH_INTERVAL => return true,
+            Some(_) => {}
+        }
+        self.touched().insert(id.clone(), Instant::now());
+        match database.touch_mcp_session_async(id, SESSION_IDLE_TTL).await {
+            Ok(stored) => stored.is_some(),
+            Err(e) => {
+                tracing::warn!("Failed to mark MCP session {} as seen: {}", id, e);
+                true
+            }
+        }
+    }
+
+    /// Restores a session opened by another instance, returning whether it
+    /// exists.
+    async fn restore(
+        &self,
+        id: &SessionId,
+        database: &Database,
+    ) -> Result<bool, SharedSessionError> {
+        let _restoring = self.restoring.lock().await;
+        if self.local.has_session(id).await? {
+            return Ok(true);
+        }
+        let Some(stored) = database
+            .touch_mcp_session_async(id, SESSION_IDLE_TTL)
+            .await
+            .map_err(SharedSessionError::Store)?
+        else {
+            return Ok(false);
+        };
+        let initialize: ClientJsonRpcMessage = serde_json::from_str(&stored)?;
+        let initialized: ClientJsonRpcMessage = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized",
+        }))?;
+
+        let (handle, worker) = create_local_session(id.clone(), self.local.session_config.clone());
+        self.local.sessions.write().await.insert(id.clone(), handle);
+        // Served as rmcp serves the sessions it opens, until the worker ends
+        let service = McpHandler {
+            state: self.state.clone(),
+        };
+        let local = self.local.clone();
+        let session_id = id.clone();
+        tokio::spawn(async move {
+            match serve_server(service, WorkerTransport::spawn(worker)).await {
+                Ok(running) => {
+                    let _ = running.waiting().await;
+                }
+                Err(e) => tracing::error!("Failed to restore MCP session {}: {}", session_id, e),
+            }
+            let _ = local.close_session(&session_id).await;
+        });
+        self.local.initialize_session(id, initialize).await?;
+        self.local.accept_message(id, initialized).await?;
+        self.touched().insert(id.clone(), Instant::now());
+        tracing::debug!("Restored MCP session {}", id);
+        Ok(true)
+    }
+}
+
+impl SessionManager for SharedSessionManager {
+    type Error = SharedSessionError;
+    type Transport = <LocalSessionManager as SessionManager>::Transport;
+
+    async fn create_session(&self) -> Result<(SessionId, Self::Transport), Self::Error> {
+        Ok(self.local.create_session().await?)
+    }
+
+    async fn initialize_session(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<ServerJsonRpcMessage, Self::Error> {
+        let stored = serde_json::to_string(&message)?;
+        let response = self.local.initialize_session(id, message).await?;
+        // The session still works here if it cannot be shared
+        if let Some(database) = self.database() {
+            match database.save_mcp_session_async(id, &stored).await {
+                Ok(()) => {
+                    self.touched().insert(id.clone(), Instant::now());
+                }
+                Err(e) => tracing::warn!("Failed to share MCP session {}: {}", id, e),
+            }
+        }
+        Ok(response)
+    }
+
+    async fn has_session(&self, id: &SessionId) -> Result<bool, Self::Error> {
+        let database = self.database();
+        if self.local.has_session(id).await? {
+            if let Some(database) = &database
+                && !self.touch(id, database).await
+            {
+                self.local.close_session(id).await?;
+                self.touched().remove(id);
+                return Ok(false);
+            }
+            return Ok(true);
+        }
+        match database {
+            Some(database) => self.restore(id, &database).await,
+            None => Ok(false),
+        }
+    }
+
+    async fn close_session(&self, id: &SessionId) -> Result<(), Self::Error> {
+        self.local.close_session(id).await?;
+        self.touched().remove(id);
+        if let Some(database) = self.database() {
+            database
+                .delete_mcp_session_async(id)
+                .await
+                .map_err(SharedSessionError::Store)?;
+        }
+        Ok(())
+    }
+
+    async fn create_stream(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        Ok(self.local.create_stream(id, message).await?)
+    }
+
+    async fn accept_message(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<(), Self::Error> {
+        Ok(self.local.accept_message(id, message).await?)
+    }
+
+    async fn create_standalone_stream(
+        &self,
+        id: &SessionId,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        Ok(self.local.create_standalone_stream(id).await?)
+    }
+
+    async fn resume(
+        &self,
+        id: &SessionId,
+        last_event_id: String,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        Ok(self.local.resume(id, last_event_id).await?)
+    }
+}
@@ -0,0 +1,167 @@
+//! Scrape-friendly protection for the Prometheus `/metrics` endpoint.
+//!
+//! Prometheus cannot follow the console's cookie sign-in, so when
+//! `management_server.metrics_auth` is configured `/metrics` is checked here
+//! instead of by [`crate::server::auth::check_auth`]: against a static bearer
+//! token, HTTP basic credentials and/or a client IP allowlist.
+
+use std::net::IpAddr;
+
+use anyhow::{Context, anyhow};
+use axum::{
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+use crate::config::models::MetricsAuthConfig;
+
+/// An IP address range in CIDR notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    /// Parses "10.0.0.0/8" or a bare address (a single-host range).
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let network: IpAddr = addr
+            .parse()
+            .with_context(|| format!("Invalid IP address '{}'", s))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| anyhow!("Invalid prefix length in '{}'", s))?,
+            None => max,
+        };
+        Ok(Self { network, prefix })
+    }
+
+    /// Whether `ip` falls inside the range. IPv4-mapped IPv6 addresses
+    /// match IPv4 ranges.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Validated form of [`MetricsAuthConfig`].
+#[derive(Debug, Clone)]
+pub struct MetricsAuth {
+    bearer_token: Option<String>,
+    basic: Option<String>,
+    allowed_ips: Vec<IpRange>,
+}
+
+impl MetricsAuth {
+    /// Builds the guard, rejecting malformed IP ranges and empty secrets.
+    pub fn from_config(config: &MetricsAuthConfig) -> anyhow::Result<Self> {
+        if config.bearer_token.as_deref().is_some_and(str::is_empty) {
+            anyhow::bail!("metrics_auth.bearer_token must not be empty");
+        }
+        if config
+            .basic
+            .as_ref()
+            .is_some_and(|b| b.username.is_empty() || b.password.is_empty())
+        {
+            anyhow::bail!("metrics_auth.basic requires a username and password");
+        }
+        let allowed_ips = config
+            .allowed_ips
+            .iter()
+            .map(|s| IpRange::parse(s).context("Invalid metrics_auth.allowed_ips entry"))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if config.bearer_token.is_none() && config.basic.is_none() && allowed_ips.is_empty() {
+            anyhow::bail!(
+                "metrics_auth must set at least one of bearer_token, basic or allowed_ips"
+            );
+        }
+        Ok(Self {
+            bearer_token: config.bearer_token.clone(),
+            basic: config
+                .basic
+                .as_ref()
+                .map(|b| STANDARD.encode(format!("{}:{}", b.username, b.password))),
+            allowed_ips,
+        })
+    }
+
+    /// Returns a 403/401 response when a scrape from `ip` is not allowed.
+    pub fn deny(&self, headers: &HeaderMap, ip: Option<IpAddr>) -> Option<Response> {
+        if !self.allowed_ips.is_empty()
+            && !ip.is_some_and(|ip| self.allowed_ips.iter().any(|r| r.contains(ip)))
+        {
+            tracing::warn!(
+                "Rejected /metrics scrape from {:?}: address not allowed",
+                ip
+            );
+            return Some((StatusCode::FORBIDDEN, "Address not allowed").into_response());
+        }
+        if self.bearer_token.is_none() && self.basic.is_none() {
+            return None;
+        }
+
+        let authorization = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok());
+        let presented_bearer = authorization.and_then(|s| s.strip_prefix("Bearer "));
+        let presented_basic = authorization.and_then(|s| s.strip_prefix("Basic "));
+        let accepted = matches!(
+            (&self.bearer_token, presented_bearer),
+            (Some(expected), Some(presented)) if constant_time_eq(expected, presented.trim())
+        ) || matches!(
+            (&self.basic, presented_basic),
+            (Some(expected), Some(presented)) if constant_time_eq(expected, presented.trim())
+        );
+        if accepted {
+            return None;
+        }
+
+        tracing::warn!(
+            "Rejected /metrics scrape from {:?}: invalid credentials",
+            ip
+        );
+        let challenge = if self.basic.is_some() {
+            "Basic realm=\"ark metrics\""
+        } else {
+            "Bearer realm=\"ark metrics\""
+        };
+        let mut response = (StatusCode::UNAUTHORIZED, "Authentication required").into_response();
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static(challenge),
+        );
+        Some(response)
+    }
+}
+
+fn constant_time_eq(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
@@ -1,8 +1,31 @@
+pub mod acme;
+pub mod audit;
 pub mod auth;
+pub mod authz;
 pub mod constants;
+pub mod crypto;
+pub mod csrf;
+pub mod events;
 pub mod handlers;
+pub mod ip_filter;
+pub mod kms;
+pub mod ldap;
+pub mod lease;
+pub mod lockout;
 pub mod mcp;
+pub mod mcp_sessions;
+pub mod metrics_auth;
+pub mod openapi;
 pub mod persist;
+pub mod proxy;
+pub mod rate_limit;
+pub mod reload;
 pub mod roles;
+pub mod saml;
 pub mod service;
 pub mod signing;
+pub mod systemd;
+pub mod timeouts;
+pub mod usage;
+pub mod watch;
+pub mod webhooks;
@@ -0,0 +1,1240 @@
+//! OpenAPI description of the management API.
+//!
+//! The document is written by hand next to the handlers it describes and
+//! served at `GET /api/v1/openapi.json`, so clients of the plugin API can
+//! generate bindings instead of reading the JSON shapes from source. When
+//! `management_server.swagger_ui` is enabled the console also serves Swagger
+//! UI at `/admin/api-docs`. Keep both in step with the routes registered in
+//! [`crate::server::service::create_api_router`]; `tests/openapi.rs` fails for
+//! documented operations that are not routed.
+
+use axum::{
+    Json,
+    http::Extensions,
+    response::{Html, IntoResponse},
+};
+use serde_json::{Value, json};
+
+use std::time::Instant;
+
+use crate::errors::ErrorCode;
+use crate::server::handlers::service_accounts::finish;
+use crate::server::service::{API_V1_PREFIX, BasePath};
+
+/// Path of the document, relative to the management server root.
+pub const OPENAPI_PATH: &str = "/api/v1/openapi.json";
+
+/// Swagger UI release loaded by the API docs page.
+const SWAGGER_UI_VERSION: &str = "5.17.14";
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn array_of(name: &str) -> Value {
+    json!({ "type": "array", "items": schema_ref(name) })
+}
+
+fn json_body(schema: Value) -> Value {
+    json!({ "required": true, "content": { "application/json": { "schema": schema } } })
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({ "description": description, "content": { "application/json": { "schema": schema } } })
+}
+
+fn error(description: &str) -> Value {
+    json_response(description, schema_ref("Error"))
+}
+
+fn shared(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/responses/{}", name) })
+}
+
+fn path_param(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+fn query_param(name: &str, description: &str, schema: Value) -> Value {
+    json!({ "name": name, "in": "query", "description": description, "schema": schema })
+}
+
+/// `If-None-Match` request header of conditional GETs.
+fn if_none_match() -> Value {
+    json!({
+        "name": "If-None-Match",
+        "in": "header",
+        "description": "Entity tag of a previous response",
+        "schema": { "type": "string" },
+    })
+}
+
+/// 304 response of conditional GETs.
+fn not_modified() -> Value {
+    json!({ "description": "The catalog has not changed since the given `ETag`" })
+}
+
+/// Returns the OpenAPI 3.1 document of the management API.
+pub fn spec() -> Value {
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Ark management API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Plugin management, tool execution, credentials and \
+                administration of an Ark MCP server. Errors share the `Error` \
+                shape. Requests authenticate with a bearer token (API key or \
+                access token) or the console session cookie; cookie-authenticated \
+                writes also need the `x-csrf-token` header.",
+        },
+        "servers": [{ "url": API_V1_PREFIX }],
+        "security": [{ "bearerAuth": [] }, { "sessionCookie": [] }],
+        "tags": [
+            { "name": "plugins", "description": "Plugins and tool execution" },
+            { "name": "events", "description": "Live server events" },
+            { "name": "profile", "description": "The caller's identity and console preferences" },
+            { "name": "credentials", "description": "The caller's API keys and password" },
+            { "name": "users", "description": "Local user accounts (admin)" },
+            { "name": "service-accounts", "description": "Service accounts (admin)" },
+            { "name": "admin", "description": "Audit trail, logging and usage statistics (admin)" },
+            { "name": "meta", "description": "This document" },
+        ],
+        "paths": paths(),
+        "components": components(),
+    })
+}
+
+fn paths() -> Value {
+    let mut paths = json!({
+        "/plugins": {
+            "get": {
+                "tags": ["plugins"],
+                "operationId": "listPlugins",
+                "summary": "List the plugins visible to the caller",
+                "description": "Shared plugins and the caller's own, keyed by name. \
+                    Responses carry an `ETag` that changes with the plugin catalog.",
+                "parameters": [if_none_match()],
+                "responses": {
+                    "200": json_response("Plugins by name", json!({
+                        "type": "object",
+                        "additionalProperties": schema_ref("PluginSummary"),
+                    })),
+                    "304": not_modified(),
+                    "401": shared("Unauthorized"),
+                },
+            },
+            "post": {
+                "tags": ["plugins"],
+                "operationId": "createPlugin",
+                "summary": "Register a plugin",
+                "description": "The plugin is owned by the caller. A plugin with the \
+                    same name is replaced.",
+                "requestBody": json_body(schema_ref("PluginConfig")),
+                "responses": {
+                    "201": json_response("Plugin registered", schema_ref("Message")),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "500": error("The plugin could not be loaded or registered"),
+                },
+            },
+        },
+        "/plugins/{id}": {
+            "parameters": [path_param("id", "Plugin name")],
+            "get": {
+                "tags": ["plugins"],
+                "operationId": "getPlugin",
+                "summary": "Get a plugin's tools",
+                "description": "Responses carry an `ETag` that changes with the plugin catalog.",
+                "parameters": [if_none_match()],
+                "responses": {
+                    "200": json_response("The plugin's tool set", schema_ref("ToolSet")),
+                    "304": not_modified(),
+                    "401": shared("Unauthorized"),
+                    "404": shared("NotFound"),
+                },
+            },
+            "delete": {
+                "tags": ["plugins"],
+                "operationId": "deletePlugin",
+                "summary": "Unregister a plugin",
+                "description": "Allowed for the owner, or with `plugin.delete.any`.",
+                "responses": {
+                    "204": { "description": "Plugin unregistered" },
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                },
+            },
+        },
+        "/plugins/{id}/tools/{tool_id}": {
+            "parameters": [
+                path_param("id", "Plugin name"),
+                path_param("tool_id", "Tool name"),
+            ],
+            "post": {
+                "tags": ["plugins"],
+                "operationId": "executeTool",
+                "summary": "Execute a tool",
+                "description": "The body holds the tool arguments, as described by the \
+                    tool's `inputSchema`.",
+                "requestBody": json_body(json!({ "type": "object" })),
+                "responses": {
+                    "200": json_response("The tool result", json!({ "type": "object" })),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "429": error("The caller has used up a monthly quota"),
+                    "500": error("The tool failed"),
+                },
+            },
+        },
+        "/plugins/{id}/export": {
+            "parameters": [path_param("id", "Plugin name")],
+            "get": {
+                "tags": ["plugins"],
+                "operationId": "exportPlugin",
+                "summary": "Export a plugin as a self-contained bundle",
+                "description": "The WASM module comes from the database when the plugin \
+                    was persisted, and is otherwise read again from its source.",
+                "responses": {
+                    "200": json_response("The plugin bundle", schema_ref("PluginBundle")),
+                    "400": error("The built-in plugin cannot be exported"),
+                    "401": shared("Unauthorized"),
+                    "404": shared("NotFound"),
+                    "409": error("No WASM module is available for the plugin"),
+                    "500": error("The module could not be read"),
+                },
+            },
+        },
+        "/plugins/import": {
+            "post": {
+                "tags": ["plugins"],
+                "operationId": "importPlugin",
+                "summary": "Register a plugin from an exported bundle",
+                "description": "The plugin is owned by the caller and keeps the bundle's \
+                    source URL and version information. A plugin with the same name is \
+                    replaced. Requires `plugin.create`.",
+                "requestBody": json_body(schema_ref("PluginBundle")),
+                "responses": {
+                    "201": json_response("Plugin imported", schema_ref("Message")),
+                    "400": error("The bundle is malformed or does not match its digest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "413": { "description": "The bundle exceeds the upload body limit (64 MiB by default)" },
+                    "500": error("The plugin could not be loaded or registered"),
+                },
+            },
+        },
+        "/tools": {
+            "get": {
+                "tags": ["plugins"],
+                "operationId": "searchTools",
+                "summary": "Search tools across the visible plugins",
+                "description": "Tools whose name matches come before those matching only \
+                    in their description. Credentials restricted to specific tools only \
+                    find those tools.",
+                "parameters": [query_param(
+                    "q",
+                    "Case-insensitive text matched against tool names and descriptions; \
+                        every visible tool is returned when empty",
+                    json!({ "type": "string" }),
+                )],
+                "responses": {
+                    "200": json_response("Matching tools", array_of("ToolMatch")),
+                    "401": shared("Unauthorized"),
+                },
+            },
+        },
+        "/events": {
+            "get": {
+                "tags": ["events"],
+                "operationId": "streamEvents",
+                "summary": "Stream server events",
+                "description": "Server-sent events named after the event `type`, with the \
+                    event as JSON data. Only events visible to the caller are sent. A \
+                    `lagged` event with `{\"skipped\": n}` means events were missed.",
+                "responses": {
+                    "200": {
+                        "description": "Event stream",
+                        "content": { "text/event-stream": { "schema": schema_ref("ServerEvent") } },
+                    },
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                },
+            },
+        },
+        "/executions": {
+            "get": {
+                "tags": ["plugins"],
+                "operationId": "listExecutions",
+                "summary": "List tool executions, newest first",
+                "description": "Administrators see every caller's executions, other users only \
+                    their own. Only the latest executions are kept.",
+                "parameters": [
+                    query_param("principal", "Caller global id; admin only for other callers", json!({ "type": "string" })),
+                    query_param("plugin", "Plugin name", json!({ "type": "string" })),
+                    query_param("tool", "Tool name", json!({ "type": "string" })),
+                    query_param("status", "Outcome", json!({ "type": "string", "enum": ["ok", "error"] })),
+                    query_param("since", "Earliest timestamp (RFC 3339)", json!({ "type": "string", "format": "date-time" })),
+                    query_param("until", "Latest timestamp, exclusive (RFC 3339)", json!({ "type": "string", "format": "date-time" })),
+                    query_param("before_id", "Only executions with a lower id, to page back", json!({ "type": "integer" })),
+                    query_param("limit", "Maximum number of executions", json!({
+                        "type": "integer", "minimum": 1, "maximum": 1000, "default": 100,
+                    })),
+                ],
+                "responses": {
+                    "200": json_response("Executions", array_of("ToolExecution")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/me": {
+            "get": {
+                "tags": ["profile"],
+                "operationId": "getProfile",
+                "summary": "The caller's identity, permissions and preferences",
+                "responses": {
+                    "200": json_response("Profile", schema_ref("Profile")),
+                    "401": shared("Unauthorized"),
+                },
+            },
+            "patch": {
+                "tags": ["profile"],
+                "operationId": "updatePreferences",
+                "summary": "Update the caller's display preferences",
+                "description": "JSON merge patch of the preferences: listed fields are \
+                    replaced and fields set to `null` are reset.",
+                "requestBody": json_body(schema_ref("UserPreferences")),
+                "responses": {
+                    "200": json_response("Profile with the updated preferences", schema_ref("Profile")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/me/usage": {
+            "get": {
+                "tags": ["profile"],
+                "operationId": "getUsage",
+                "summary": "The caller's monthly usage and quotas",
+                "parameters": [
+                    query_param("month", "Month (UTC) as `YYYY-MM`; the current one by default", json!({
+                        "type": "string", "pattern": "^[0-9]{4}-[0-9]{2}$",
+                    })),
+                ],
+                "responses": {
+                    "200": json_response("Usage", schema_ref("PrincipalUsage")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/me/tokens": {
+            "get": {
+                "tags": ["credentials"],
+                "operationId": "listTokens",
+                "summary": "List the caller's API keys",
+                "responses": {
+                    "200": json_response("API keys", array_of("ApiKey")),
+                    "401": shared("Unauthorized"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+            "post": {
+                "tags": ["credentials"],
+                "operationId": "createToken",
+                "summary": "Create an API key",
+                "requestBody": json_body(schema_ref("CreateTokenRequest")),
+                "responses": {
+                    "201": json_response("The key, with its token shown only once", schema_ref("NewApiKey")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/me/tokens/{id}": {
+            "parameters": [path_param("id", "API key id")],
+            "delete": {
+                "tags": ["credentials"],
+                "operationId": "revokeToken",
+                "summary": "Revoke one of the caller's API keys",
+                "responses": {
+                    "204": { "description": "Key revoked" },
+                    "401": shared("Unauthorized"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/me/password": {
+            "post": {
+                "tags": ["credentials"],
+                "operationId": "changeOwnPassword",
+                "summary": "Change the caller's local password",
+                "requestBody": json_body(schema_ref("ChangePasswordRequest")),
+                "responses": {
+                    "204": { "description": "Password changed" },
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/openapi.json": {
+            "get": {
+                "tags": ["meta"],
+                "operationId": "getOpenApi",
+                "summary": "This document",
+                "security": [],
+                "responses": {
+                    "200": json_response("OpenAPI document", json!({ "type": "object" })),
+                },
+            },
+        },
+    });
+    // Split in two to stay within the `json!` recursion limit
+    if let (Some(paths), Value::Object(admin)) = (paths.as_object_mut(), admin_paths()) {
+        paths.extend(admin);
+    }
+    paths
+}
+
+fn admin_paths() -> Value {
+    json!({
+        "/admin/users": {
+            "get": {
+                "tags": ["users"],
+                "operationId": "listUsers",
+                "summary": "List local users",
+                "responses": {
+                    "200": json_response("Users", array_of("User")),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+            "post": {
+                "tags": ["users"],
+                "operationId": "createUser",
+                "summary": "Create a local user",
+                "requestBody": json_body(schema_ref("CreateUserRequest")),
+                "responses": {
+                    "201": json_response("The new user", schema_ref("User")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "409": error("The user already exists"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/users/{username}": {
+            "parameters": [path_param("username", "Login name")],
+            "delete": {
+                "tags": ["users"],
+                "operationId": "deleteUser",
+                "summary": "Delete a local user",
+                "responses": {
+                    "204": { "description": "User deleted" },
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/users/{username}/password": {
+            "parameters": [path_param("username", "Login name")],
+            "put": {
+                "tags": ["users"],
+                "operationId": "setUserPassword",
+                "summary": "Reset a local user's password",
+                "requestBody": json_body(schema_ref("SetPasswordRequest")),
+                "responses": {
+                    "204": { "description": "Password reset" },
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/service-accounts": {
+            "get": {
+                "tags": ["service-accounts"],
+                "operationId": "listServiceAccounts",
+                "summary": "List service accounts",
+                "responses": {
+                    "200": json_response("Service accounts", array_of("ServiceAccount")),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+            "post": {
+                "tags": ["service-accounts"],
+                "operationId": "createServiceAccount",
+                "summary": "Create a service account",
+                "requestBody": json_body(schema_ref("CreateServiceAccountRequest")),
+                "responses": {
+                    "201": json_response(
+                        "The new account, with its client secret shown only once",
+                        schema_ref("NewServiceAccount"),
+                    ),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "409": error("The account already exists"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/service-accounts/{id}": {
+            "parameters": [path_param("id", "Service account id")],
+            "delete": {
+                "tags": ["service-accounts"],
+                "operationId": "deleteServiceAccount",
+                "summary": "Delete a service account and revoke its keys",
+                "responses": {
+                    "204": { "description": "Account deleted" },
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/service-accounts/{id}/secret": {
+            "parameters": [path_param("id", "Service account id")],
+            "post": {
+                "tags": ["service-accounts"],
+                "operationId": "rotateServiceAccountSecret",
+                "summary": "Generate a new client secret",
+                "responses": {
+                    "200": json_response("The new secret, shown only once", json!({
+                        "type": "object",
+                        "properties": {
+                            "client_id": { "type": "string" },
+                            "client_secret": { "type": "string" },
+                        },
+                    })),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/service-accounts/{id}/tokens": {
+            "parameters": [path_param("id", "Service account id")],
+            "get": {
+                "tags": ["service-accounts"],
+                "operationId": "listServiceAccountTokens",
+                "summary": "List a service account's API keys",
+                "responses": {
+                    "200": json_response("API keys", array_of("ApiKey")),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+            "post": {
+                "tags": ["service-accounts"],
+                "operationId": "createServiceAccountToken",
+                "summary": "Create an API key for a service account",
+                "requestBody": json_body(schema_ref("CreateTokenRequest")),
+                "responses": {
+                    "201": json_response("The key, with its token shown only once", schema_ref("NewApiKey")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/service-accounts/{id}/tokens/{key_id}": {
+            "parameters": [
+                path_param("id", "Service account id"),
+                path_param("key_id", "API key id"),
+            ],
+            "delete": {
+                "tags": ["service-accounts"],
+                "operationId": "revokeServiceAccountToken",
+                "summary": "Revoke a service account's API key",
+                "responses": {
+                    "204": { "description": "Key revoked" },
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "404": shared("NotFound"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/audit/auth-events": {
+            "get": {
+                "tags": ["admin"],
+                "operationId": "listAuthEvents",
+                "summary": "List authentication events, newest first",
+                "parameters": [
+                    query_param("event", "Event type", json!({
+                        "type": "string",
+                        "enum": ["login", "login_failed", "logout", "token_issued", "token_failed"],
+                    })),
+                    query_param("subject", "Principal global id or username", json!({ "type": "string" })),
+                    query_param("provider", "Provider kind, e.g. `local` or `oidc`", json!({ "type": "string" })),
+                    query_param("request_id", "Request that produced the event", json!({ "type": "string" })),
+                    query_param("since", "Earliest timestamp (RFC 3339)", json!({ "type": "string", "format": "date-time" })),
+                    query_param("until", "Latest timestamp, exclusive (RFC 3339)", json!({ "type": "string", "format": "date-time" })),
+                    query_param("limit", "Maximum number of events", json!({
+                        "type": "integer", "minimum": 1, "maximum": 1000, "default": 100,
+                    })),
+                ],
+                "responses": {
+                    "200": json_response("Events", array_of("AuthEvent")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/loglevel": {
+            "get": {
+                "tags": ["admin"],
+                "operationId": "getLogLevel",
+                "summary": "Get the active log filter",
+                "responses": {
+                    "200": json_response("The filter", schema_ref("LogFilter")),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": error("The log level cannot be changed at runtime"),
+                },
+            },
+            "put": {
+                "tags": ["admin"],
+                "operationId": "setLogLevel",
+                "summary": "Replace the log filter",
+                "requestBody": json_body(schema_ref("LogFilter")),
+                "responses": {
+                    "200": json_response("The new filter", schema_ref("LogFilter")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": error("The log level cannot be changed at runtime"),
+                },
+            },
+        },
+        "/admin/config": {
+            "get": {
+                "tags": ["admin"],
+                "operationId": "getConfig",
+                "summary": "Effective configuration",
+                "description": "The configuration file merged with environment and command line overrides and later reloads. Passwords, secrets and inline tokens are replaced by `********`.",
+                "responses": {
+                    "200": json_response("Configuration, in the layout of the configuration file", json!({ "type": "object" })),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": error("The server was not started from a configuration file"),
+                },
+            },
+        },
+        "/admin/reload": {
+            "post": {
+                "tags": ["admin"],
+                "operationId": "reloadConfig",
+                "summary": "Reload the configuration file",
+                "description": "Applies changes to plugins, CORS origins, log filters and rate limits; other changed settings are reported as requiring a restart.",
+                "responses": {
+                    "200": json_response("What the reload changed", schema_ref("ReloadReport")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": error("The server was not started from a configuration file"),
+                },
+            },
+        },
+        "/admin/stats": {
+            "get": {
+                "tags": ["admin"],
+                "operationId": "getStats",
+                "summary": "Usage statistics per owner and in total",
+                "responses": {
+                    "200": json_response("Statistics", json!({
+                        "type": "object",
+                        "properties": {
+                            "owners": { "type": "array", "items": {
+                                "allOf": [
+                                    schema_ref("UsageTotals"),
+                                    {
+                                        "type": "object",
+                                        "properties": {
+                                            "owner": { "type": "string" },
+                                            "usage": array_of("ToolUsage"),
+                                        },
+                                    },
+                                ],
+                            } },
+                            "totals": schema_ref("UsageTotals"),
+                        },
+                    })),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/stats/top": {
+            "get": {
+                "tags": ["admin"],
+                "operationId": "getTopUsage",
+                "summary": "Busiest and slowest tools and most active principals",
+                "parameters": [
+                    query_param("window", "Hours or days to report on, e.g. `24h` or `7d`; at most 30 days", json!({
+                        "type": "string", "pattern": "^[0-9]+[hd]$", "default": "24h",
+                    })),
+                    query_param("limit", "Entries per list", json!({
+                        "type": "integer", "minimum": 1, "maximum": 100, "default": 10,
+                    })),
+                ],
+                "responses": {
+                    "200": json_response("Top usage", schema_ref("TopUsage")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+        "/admin/usage": {
+            "get": {
+                "tags": ["admin"],
+                "operationId": "getUsageReport",
+                "summary": "Monthly usage and quotas of every principal, for chargeback",
+                "parameters": [
+                    query_param("month", "Month (UTC) as `YYYY-MM`; the current one by default", json!({
+                        "type": "string", "pattern": "^[0-9]{4}-[0-9]{2}$",
+                    })),
+                ],
+                "responses": {
+                    "200": json_response("Usage by principal", schema_ref("UsageReport")),
+                    "400": shared("BadRequest"),
+                    "401": shared("Unauthorized"),
+                    "403": shared("Forbidden"),
+                    "503": shared("NoDatabase"),
+                },
+            },
+        },
+    })
+}
+
+fn components() -> Value {
+    json!({
+        "securitySchemes": {
+            "bearerAuth": {
+                "type": "http",
+                "scheme": "bearer",
+                "description": "API key or access token",
+            },
+            "sessionCookie": { "type": "apiKey", "in": "cookie", "name": "ark_session" },
+        },
+        "responses": {
+            "BadRequest": error("The request is invalid; `additional` says why"),
+            "Unauthorized": error("Authentication required"),
+            "Forbidden": error("The caller lacks the required role or permission"),
+            "NotFound": error("Not found"),
+            "NoDatabase": error("The server runs without a database"),
+        },
+        "schemas": schemas(),
+    })
+}
+
+fn schemas() -> Value {
+    let nullable_string = json!({ "type": ["string", "null"] });
+    let timestamp = json!({ "type": "string", "format": "date-time" });
+    let mut schemas = json!({
+        "Error": {
+            "type": "object",
+            "required": ["error", "is_error", "code"],
+            "properties": {
+                "error": { "type": "string" },
+                "code": {
+                    "type": "string",
+                    "enum": ErrorCode::ALL.map(|code| code.as_str()),
+                    "description": "Machine-readable kind of the error, stable across releases",
+                },
+                "message": nullable_string,
+                "additional": { "type": ["string", "null"], "description": "Details or hints" },
+                "is_error": { "type": "boolean" },
+                "request_id": { "type": "string", "description": "Quote when reporting the error" },
+            },
+        },
+        "Message": {
+            "type": "object",
+            "properties": { "message": { "type": "string" } },
+        },
+        "Tool": {
+            "type": "object",
+            "required": ["name", "inputSchema"],
+            "properties": {
+                "name": { "type": "string" },
+                "description": nullable_string,
+                "inputSchema": { "type": "object", "description": "JSON Schema of the arguments" },
+            },
+        },
+        "PluginBundle": {
+            "type": "object",
+            "required": ["format", "name", "source_url", "sha256", "exported_utc", "insecure", "wasm"],
+            "properties": {
+                "format": { "type": "string", "const": crate::plugins::bundle::BUNDLE_FORMAT },
+                "name": { "type": "string" },
+                "source_url": { "type": "string", "description": "Original source, without credentials" },
+                "sha256": { "type": "string", "description": "Hex digest of the WASM module" },
+                "date_added_utc": { "type": ["string", "null"], "format": "date-time" },
+                "exported_utc": timestamp,
+                "insecure": { "type": "boolean" },
+                "manifest": { "type": ["object", "null"] },
+                "tools_allow": { "type": "array", "items": { "type": "string" } },
+                "tools_deny": { "type": "array", "items": { "type": "string" } },
+                "wasm": { "type": "string", "contentEncoding": "base64" },
+            },
+        },
+        "ToolMatch": {
+            "type": "object",
+            "required": ["plugin", "tool"],
+            "properties": {
+                "plugin": { "type": "string", "description": "Name of the plugin providing the tool" },
+                "tool": schema_ref("Tool"),
+            },
+        },
+        "ToolSet": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "tools": array_of("Tool"),
+            },
+        },
+        "PluginSummary": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "tools": array_of("Tool"),
+                "url": nullable_string,
+                "insecure": { "type": "boolean" },
+                "manifest": { "anyOf": [schema_ref("PluginManifest"), { "type": "null" }] },
+                "owner": { "type": "string", "description": "Absent for shared plugins" },
+            },
+        },
+        "PluginConfig": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": { "type": "string" },
+                "url": {
+                    "type": "string",
+                    "description": "file://, http(s):// or oci:// location of the plugin",
+                },
+                "config": {
+                    "type": "object",
+                    "description": "Registry credentials for oci:// plugins",
+                },
+                "insecure": { "type": "boolean", "default": false },
+                "manifest": schema_ref("PluginManifest"),
+                "tools_allow": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only tools of the plugin to expose",
+                },
+                "tools_deny": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Tools of the plugin not to expose",
+                },
+                "sha256": {
+                    "type": "string",
+                    "description": "Expected hex SHA-256 digest of the WASM module of a file:// or http(s):// plugin",
+                },
+            },
+        },
+        "PluginManifest": {
+            "type": "object",
+            "properties": {
+                "wasm": { "type": "array", "items": { "type": "string" } },
+                "memory": {
+                    "type": "object",
+                    "properties": { "max_pages": { "type": "integer", "description": "64 KiB pages" } },
+                },
+                "config": { "type": "object", "additionalProperties": { "type": "string" } },
+                "allowed_hosts": { "type": "array", "items": { "type": "string" } },
+                "allowed_paths": { "type": "object", "additionalProperties": { "type": "string" } },
+            },
+        },
+        "ServerEvent": {
+            "type": "object",
+            "required": ["type"],
+            "properties": {
+                "type": {
+                    "type": "string",
+                    "enum": [
+                        "plugin_registered",
+                        "plugin_unregistered",
+                        "tool_executed",
+                        "auth",
+                        "state_changed",
+                    ],
+                },
+            },
+            "additionalProperties": true,
+        },
+    });
+    // Split in two to stay within the `json!` recursion limit
+    if let (Some(schemas), Value::Object(admin)) = (schemas.as_object_mut(), admin_schemas()) {
+        schemas.extend(admin);
+    }
+    schemas
+}
+
+fn admin_schemas() -> Value {
+    let nullable_string = json!({ "type": ["string", "null"] });
+    let timestamp = json!({ "type": "string", "format": "date-time" });
+    json!({
+        "ApiKey": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "name": { "type": "string" },
+                "scopes": { "type": "array", "items": { "type": "string" } },
+                "created": timestamp,
+                "expires": { "type": ["string", "null"], "format": "date-time" },
+                "expired": { "type": "boolean" },
+            },
+        },
+        "NewApiKey": {
+            "allOf": [
+                schema_ref("ApiKey"),
+                { "type": "object", "properties": { "token": { "type": "string" } } },
+            ],
+        },
+        "CreateTokenRequest": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": { "type": "string", "minLength": 1, "maxLength": 128 },
+                "scopes": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "`api`, `mcp`, `tool:<name>` or `plugin:<id>`; \
+                        defaults to `api` and `mcp`",
+                },
+                "expires_in_seconds": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Defaults to 90 days, at most 365 days",
+                },
+            },
+        },
+        "Profile": {
+            "type": "object",
+            "properties": {
+                "global_id": { "type": "string" },
+                "subject": { "type": "string" },
+                "name": nullable_string,
+                "email": nullable_string,
+                "picture": nullable_string,
+                "provider": { "type": "string" },
+                "provider_kind": { "type": "string" },
+                "groups": { "type": "array", "items": { "type": "string" } },
+                "roles": { "type": "array", "items": { "type": "string" } },
+                "is_admin": { "type": "boolean" },
+                "permissions": { "type": "array", "items": { "type": "string" } },
+                "owned_plugins": { "type": "integer", "minimum": 0 },
+                "preferences": schema_ref("UserPreferences"),
+            },
+        },
+        "UserPreferences": {
+            "type": "object",
+            "properties": {
+                "theme": { "type": ["string", "null"], "enum": ["light", "dark", "system", null] },
+                "locale": { "type": ["string", "null"], "examples": ["en-US"] },
+                "time_zone": { "type": ["string", "null"], "examples": ["Europe/Bucharest"] },
+                "page_size": { "type": ["integer", "null"], "minimum": 1, "maximum": 500 },
+            },
+            "additionalProperties": false,
+        },
+        "ChangePasswordRequest": {
+            "type": "object",
+            "required": ["current_password", "new_password"],
+            "properties": {
+                "current_password": { "type": "string" },
+                "new_password": { "type": "string" },
+            },
+        },
+        "SetPasswordRequest": {
+            "type": "object",
+            "required": ["password"],
+            "properties": { "password": { "type": "string" } },
+        },
+        "User": {
+            "type": "object",
+            "properties": {
+                "username": { "type": "string" },
+                "global_id": { "type": "string" },
+                "display_name": nullable_string,
+                "email": nullable_string,
+                "groups": { "type": "array", "items": { "type": "string" } },
+                "is_admin": { "type": "boolean" },
+                "created": timestamp,
+            },
+        },
+        "CreateUserRequest": {
+            "type": "object",
+            "required": ["username", "password"],
+            "properties": {
+                "username": { "type": "string", "pattern": "^[A-Za-z0-9_.@-]{1,64}$" },
+                "password": { "type": "string" },
+                "display_name": { "type": "string" },
+                "email": { "type": "string" },
+                "groups": { "type": "array", "items": { "type": "string" } },
+                "is_admin": { "type": "boolean", "default": false },
+            },
+        },
+        "ServiceAccount": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "global_id": { "type": "string" },
+                "description": nullable_string,
+                "roles": { "type": "array", "items": { "type": "string" } },
+                "client_credentials": { "type": "boolean" },
+                "created_by": { "type": "string" },
+                "created": timestamp,
+            },
+        },
+        "NewServiceAccount": {
+            "allOf": [
+                schema_ref("ServiceAccount"),
+                { "type": "object", "properties": { "client_secret": { "type": "string" } } },
+            ],
+        },
+        "CreateServiceAccountRequest": {
+            "type": "object",
+            "required": ["id"],
+            "properties": {
+                "id": { "type": "string", "pattern": "^[a-z0-9][a-z0-9_.-]{0,63}$" },
+                "description": { "type": "string" },
+                "roles": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Defaults to `[\"User\"]`",
+                },
+                "client_credentials": {
+                    "type": "boolean",
+                    "default": true,
+                    "description": "Generate a secret for the client-credentials grant",
+                },
+            },
+        },
+        "AuthEvent": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer" },
+                "timestamp": timestamp,
+                "event": { "type": "string" },
+                "provider": nullable_string,
+                "subject": nullable_string,
+                "ip": nullable_string,
+                "user_agent": nullable_string,
+                "detail": nullable_string,
+                "request_id": nullable_string,
+            },
+        },
+        "ToolExecution": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer" },
+                "timestamp": timestamp,
+                "principal": nullable_string,
+                "plugin": { "type": "string" },
+                "tool": { "type": "string" },
+                "duration_ms": { "type": "number" },
+                "status": { "type": "string", "enum": ["ok", "error"] },
+                "error_code": {
+                    "type": ["string", "null"],
+                    "description": "The `Error` code the call failed with, if it failed",
+                },
+                "args_hash": {
+                    "type": "string",
+                    "description": "Truncated SHA-256 of the arguments, which are not stored",
+                },
+                "request_id": nullable_string,
+            },
+        },
+        "LogFilter": {
+            "type": "object",
+            "required": ["filter"],
+            "properties": {
+                "filter": {
+                    "type": "string",
+                    "description": "Directives in `RUST_LOG` syntax, e.g. `info,ark::plugins::oci=trace`",
+                },
+            },
+        },
+        "ReloadReport": {
+            "type": "object",
+            "required": ["applied", "restart_required", "errors"],
+            "properties": {
+                "applied": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Changed settings now in effect, e.g. `management_server.cors` or `plugins.time`",
+                },
+                "restart_required": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Changed settings that only take effect after a restart",
+                },
+                "errors": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Changes that could not be applied",
+                },
+            },
+        },
+        "UsageTotals": {
+            "type": "object",
+            "properties": {
+                "plugins": { "type": "integer" },
+                "tools": { "type": "integer" },
+                "calls": { "type": "integer" },
+                "errors": { "type": "integer" },
+                "error_rate": { "type": ["number", "null"] },
+                "avg_latency_ms": { "type": ["number", "null"] },
+                "active_sessions": { "type": "integer" },
+            },
+        },
+        "ToolUsage": {
+            "type": "object",
+            "properties": {
+                "plugin": { "type": "string" },
+                "tool": { "type": "string" },
+                "calls": { "type": "integer" },
+                "errors": { "type": "integer" },
+                "avg_latency_ms": { "type": ["number", "null"] },
+                "last_call": { "type": ["string", "null"], "format": "date-time" },
+            },
+        },
+        "TopUsage": {
+            "type": "object",
+            "properties": {
+                "window": { "type": "string" },
+                "since": timestamp,
+                "top_tools": { "type": "array", "items": {
+                    "type": "object",
+                    "properties": {
+                        "plugin": { "type": "string" },
+                        "tool": { "type": "string" },
+                        "calls": { "type": "integer" },
+                        "errors": { "type": "integer" },
+                    },
+                } },
+                "slowest_tools": { "type": "array", "items": {
+                    "type": "object",
+                    "properties": {
+                        "plugin": { "type": "string" },
+                        "tool": { "type": "string" },
+                        "p95_latency_ms": { "type": "number" },
+                        "calls": { "type": "integer" },
+                    },
+                } },
+                "top_principals": { "type": "array", "items": {
+                    "type": "object",
+                    "properties": {
+                        "principal": { "type": "string" },
+                        "calls": { "type": "integer" },
+                        "errors": { "type": "integer" },
+                    },
+                } },
+            },
+        },
+        "UsageCounter": {
+            "type": "object",
+            "properties": {
+                "used": { "type": "number" },
+                "quota": { "type": ["integer", "null"], "description": "Null when unlimited" },
+                "remaining": { "type": ["integer", "null"], "description": "Null when unlimited" },
+            },
+        },
+        "PrincipalUsage": {
+            "type": "object",
+            "properties": {
+                "principal": { "type": "string" },
+                "month": { "type": "string" },
+                "calls": schema_ref("UsageCounter"),
+                "compute_ms": schema_ref("UsageCounter"),
+                "bytes_returned": schema_ref("UsageCounter"),
+            },
+        },
+        "UsageReport": {
+            "type": "object",
+            "properties": {
+                "month": { "type": "string" },
+                "principals": array_of("PrincipalUsage"),
+            },
+        },
+    })
+}
+
+/// Serves the document.
+///
+/// # Endpoint
+/// `GET /api/openapi.json` (no authentication required)
+pub async fn get_openapi(extensions: Extensions) -> impl IntoResponse {
+    let start = Instant::now();
+    let mut spec = spec();
+    spec["servers"][0]["url"] = BasePath::url(&extensions, API_V1_PREFIX).into();
+    finish(Json(spec).into_response(), OPENAPI_PATH, "GET", start)
+}
+
+/// Swagger UI page rendering the document, with assets from a CDN.
+///
+/// # Endpoint
+/// `GET /admin/api-docs` when `management_server.swagger_ui` is enabled
+pub async fn swagger_ui(extensions: Extensions) -> Html<String> {
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Ark management API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({{ url: "{spec}", dom_id: "#swagger-ui", withCredentials: true }});
+  </script>
+</body>
+</html>
+"##,
+        version = SWAGGER_UI_VERSION,
+        spec = BasePath::url(&extensions, OPENAPI_PATH),
+    ))
+}
@@ -1,8 +1,16 @@
 //! Persistent storage implementation for Ark MCP server.
 //!
 //! This module provides database functionality for storing and managing:
-//! - User sessions with automatic expiry
-//! - Plugin metadata and ownership information
+//! - User sessions with automatic expiry, their principals encrypted when a
+//!   session encryption key is configured, and the encrypted IdP refresh
+//!   tokens used to renew them
+//! - Plugin metadata and ownership information, with each distinct payload
+//!   stored once and payloads over 64 KiB kept in a content-addressed blob
+//!   directory next to the database
+//! - API keys (personal access tokens), stored as token hashes
+//! - Service accounts managed by administrators
+//! - Local user accounts with argon2 password hashes
+//! - The audit trail of authentication events
 //!
 //! The database uses SQLite with secure file permissions and optimized settings
 //! for server workloads. All operations are async-compatible using blocking
@@ -11,7 +19,7 @@
 use anyhow::{Context, Result};
 use refinery::Runner;
 use refinery::embed_migrations;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior, params};
 
 // Embed compile-time migrations located under `migrations/sqlite/`.
 // This macro expands to an `embedded_migrations` module with a `runner()` helper.
@@ -23,6 +31,7 @@ use std::fs::OpenOptions;
 #[cfg(unix)]
 use std::time::Instant;
 use std::{
+    collections::BTreeMap,
     env, fs,
     path::{Path, PathBuf},
     time::Duration,
@@ -253,12 +262,71 @@ fn apply_migrations(db_path: &Path, migrations_dir: Option<&str>) -> anyhow::Res
     Ok(())
 }
 
+/// Migrations of a database file, as found by [`migration_status`].
+#[derive(Debug, Clone, Default)]
+pub struct MigrationStatus {
+    /// Applied migrations, e.g. `V3__api_keys`.
+    pub applied: Vec<String>,
+    /// Known migrations not applied yet; the next start applies them unless
+    /// `ARK_AUTO_APPLY_MIGRATIONS=false`.
+    pub pending: Vec<String>,
+}
+
+/// Compares the migrations applied to the database at `db_path` with those
+/// the server would apply (from `ARK_MIGRATIONS_DIR` or embedded), without
+/// creating or changing the file. A missing file has every migration pending.
+pub fn migration_status(db_path: &Path) -> anyhow::Result<MigrationStatus> {
+    let mut known = match env::var("ARK_MIGRATIONS_DIR") {
+        Ok(dir) if Path::new(&dir).exists() => refinery::load_sql_migrations(Path::new(&dir))
+            .with_context(|| format!("loading migrations from {}", dir))?,
+        _ => migrations::runner().get_migrations().clone(),
+    };
+    known.sort_by_key(|m| m.version());
+    let name = |m: &refinery::Migration| format!("V{}__{}", m.version(), m.name());
+
+    let mut applied = Vec::new();
+    if db_path.exists() {
+        let mut conn =
+            Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+        let has_history: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'refinery_schema_history')",
+                [],
+                |row| row.get(0),
+            )
+            .context("reading database schema")?;
+        if has_history {
+            applied = Runner::new(&[])
+                .get_applied_migrations(&mut conn)
+                .context("reading applied migrations")?;
+        }
+    }
+    let pending = known
+        .iter()
+        .filter(|m| !applied.iter().any(|a| a.version() == m.version()))
+        .map(name)
+        .collect();
+    Ok(MigrationStatus {
+        applied: applied.iter().map(name).collect(),
+        pending,
+    })
+}
+
+use sha2::{Digest, Sha256};
 use tokio::task;
 
+use crate::errors::ErrorCode;
+use crate::server::crypto::Cipher;
 use crate::utility::{set_secure_dir_permissions, set_secure_file_permissions};
 
 pub mod models;
-pub use models::{PluginRecord, SessionRecord};
+pub use models::{
+    ApiKeyRecord, AuthEventQuery, AuthEventRecord, OAuthClientMetadata, OAuthClientRecord,
+    PluginRecord, PrincipalUsageRecord, ServiceAccountRecord, SessionRecord, SessionRefreshRecord,
+    ToolCallRecord, ToolExecutionQuery, ToolExecutionRecord, ToolUsageRecord, ToolUsageRollup,
+    UserPreferences, UserRecord,
+};
 
 /// SQLite database handle for persistent storage.
 ///
@@ -267,6 +335,8 @@ pub use models::{PluginRecord, SessionRecord};
 pub struct Database {
     /// Path to the SQLite database file.
     db_path: PathBuf,
+    /// Cipher sealing the principals of stored sessions, if configured.
+    session_cipher: Option<Cipher>,
 }
 
 // Keep the impl visible for tests; silence analyzer-only dead-code warnings.
@@ -283,6 +353,7 @@ impl Database {
         ensure_parent_dir(&path)?;
         let db = Self {
             db_path: path.clone(),
+            session_cipher: None,
         };
         db.run_bootstrap_migrations()?;
 
@@ -330,6 +401,7 @@ impl Database {
         ensure_parent_dir(&path)?;
         let db = Self {
             db_path: path.clone(),
+            session_cipher: None,
         };
         db.run_bootstrap_migrations()?;
 
@@ -347,6 +419,16 @@ impl Database {
         Ok(db)
     }
 
+    /// Encrypts the principals of the sessions saved from now on with `cipher`,
+    /// and decrypts stored ones with it.
+    ///
+    /// Sessions saved without a cipher stay readable, and are encrypted the
+    /// next time they are saved.
+    pub fn with_session_cipher(mut self, cipher: Cipher) -> Self {
+        self.session_cipher = Some(cipher);
+        self
+    }
+
     /// Opens a SQLite connection with optimized settings for server workloads.
     ///
     /// Configures the connection with:
@@ -371,14 +453,24 @@ impl Database {
         Ok(conn)
     }
 
+    /// Directory of the plugin payloads too large to keep in `plugin_blobs`,
+    /// named after the database file (e.g. `ark.plugin-blobs` for `ark.db`).
+    pub fn blob_dir(&self) -> PathBuf {
+        self.db_path.with_extension("plugin-blobs")
+    }
+
     /// Runs bootstrap migrations to create the initial database schema.
     ///
     /// Creates the following tables if they don't exist:
     ///
     /// **sessions table:**
     /// - `session_id` (TEXT PRIMARY KEY) - Unique session identifier
-    /// - `principal_json` (TEXT) - Serialized user principal data
+    /// - `principal_json` (TEXT) - Serialized user principal data, sealed with
+    ///   the session cipher when one is set
     /// - `expiry_utc` (TEXT) - ISO 8601 UTC expiration timestamp
+    /// - `client_binding` (TEXT) - Hash of the client attributes the session
+    ///   is bound to, if any
+    /// - `principal_id` (TEXT) - Global id of the principal, indexed
     /// - Index on `expiry_utc` for efficient cleanup
     ///
     /// **plugins table:**
@@ -386,12 +478,19 @@ impl Database {
     /// - `plugin_id` (TEXT) - Plugin identifier (typically the plugin name)
     /// - `plugin_name` (TEXT) - Friendly plugin name (kept for clarity / future use)
     /// - `plugin_path` (TEXT) - Original plugin path/URL used to load the plugin
-    /// - `plugin_data` (BLOB) - Raw plugin payload (WASM bytes when available)
+    /// - `plugin_data` (BLOB) - Raw plugin payload of rows saved before payloads
+    ///   were deduplicated, moved to `plugin_blobs` on startup
+    /// - `plugin_digest` (TEXT) - SHA-256 of the payload in `plugin_blobs`
     /// - `metadata` (TEXT) - Serialized plugin metadata (JSON)
     /// - `date_added_utc` (TEXT) - ISO 8601 UTC creation timestamp
     /// - Composite primary key on `(owner, plugin_id)`
     /// - Indexes on `owner` and `date_added_utc`
     ///
+    /// **plugin_blobs table:**
+    /// - `digest` (TEXT PRIMARY KEY) - SHA-256 of the payload
+    /// - `data` (BLOB) - The payload, or NULL when kept in the blob directory
+    /// - `ref_count` (INTEGER) - Number of plugins using the payload
+    ///
     /// # Returns
     ///
     /// `Ok(())` if all migrations succeed.
@@ -417,9 +516,69 @@ impl Database {
         )?;
         let migrations_dir = std::env::var("ARK_MIGRATIONS_DIR").ok();
         apply_migrations(&self.db_path, migrations_dir.as_deref())?;
+        self.move_inline_plugin_payloads()
+    }
+
+    /// Moves payloads stored in `plugins.plugin_data` by earlier versions to
+    /// `plugin_blobs`, so that identical ones are stored once.
+    fn move_inline_plugin_payloads(&self) -> Result<()> {
+        let mut conn = self.open()?;
+        // Migrations from ARK_MIGRATIONS_DIR may not create the tables
+        let tables: i64 = conn.query_row(
+            r#"SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('plugins', 'plugin_blobs')"#,
+            [],
+            |row| row.get(0),
+        )?;
+        if tables < 2 {
+            return Ok(());
+        }
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let blob_dir = self.blob_dir();
+        let mut moved = 0;
+        // One row at a time, so only one payload is in memory
+        loop {
+            let row: Option<(String, String, Vec<u8>)> = tx
+                .query_row(
+                    r#"SELECT owner, plugin_id, plugin_data FROM plugins WHERE plugin_data IS NOT NULL LIMIT 1"#,
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()?;
+            let Some((owner, plugin_id, bytes)) = row else {
+                break;
+            };
+            let digest = store_blob(&tx, &blob_dir, &bytes)?;
+            tx.execute(
+                r#"UPDATE plugins SET plugin_data = NULL, plugin_digest = ?3 WHERE owner = ?1 AND plugin_id = ?2"#,
+                params![owner, plugin_id, digest],
+            )?;
+            moved += 1;
+        }
+        tx.commit()?;
+        if moved > 0 {
+            tracing::info!("Moved {} stored plugin payloads to plugin_blobs", moved);
+        }
         Ok(())
     }
 
+    /// Checks that the database file can be opened and read.
+    ///
+    /// Used by the readiness probe; never creates the file.
+    pub async fn ping_async(&self) -> Result<()> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn =
+                Connection::open_with_flags(&db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+                    .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+                row.get::<_, i64>(0)
+            })?;
+            Ok(())
+        })
+        .await?
+    }
+
     // ---------------- Async Sessions ----------------
 
     // ...removed legacy convenience `save_session_async` in favor of the
@@ -443,6 +602,9 @@ impl Database {
         let expiry_epoch = record.expiry_epoch;
         let expiry_utc_str = record.expiry_utc.to_rfc3339();
         let is_admin_flag: i64 = if record.is_admin { 1 } else { 0 };
+        let client_binding = record.client_binding.clone();
+        let principal_id = record.principal.global_id();
+        let cipher = self.session_cipher.clone();
 
         let result = task::spawn_blocking(move || -> Result<()> {
             let conn = Connection::open(&db_path)
@@ -451,23 +613,31 @@ impl Database {
             conn.pragma_update(None, "synchronous", "NORMAL").ok();
 
             let principal_json = serde_json::to_string(&principal_clone)?;
+            let principal_json = match &cipher {
+                Some(cipher) => cipher.seal(principal_json.as_bytes())?,
+                None => principal_json,
+            };
             conn.execute(
                 r#"
-                INSERT INTO sessions(session_id, principal_json, expiry_utc, expiry_epoch, is_admin)
-                VALUES(?1, ?2, ?3, ?4, ?5)
+                INSERT INTO sessions(session_id, principal_json, expiry_utc, expiry_epoch, is_admin, client_binding, principal_id)
+                VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)
                 ON CONFLICT(session_id) 
                 DO UPDATE SET 
                     principal_json = excluded.principal_json, 
                     expiry_utc = excluded.expiry_utc,
                     expiry_epoch = excluded.expiry_epoch,
-                    is_admin = excluded.is_admin
+                    is_admin = excluded.is_admin,
+                    client_binding = excluded.client_binding,
+                    principal_id = excluded.principal_id
                 "#,
                 params![
                     sid,
                     principal_json,
                     expiry_utc_str,
                     expiry_epoch,
-                    is_admin_flag
+                    is_admin_flag,
+                    client_binding,
+                    principal_id
                 ],
             )?;
             Ok(())
@@ -502,22 +672,25 @@ impl Database {
     ) -> Result<Option<models::SessionRecord>> {
         tracing::trace!("Getting session: session_id={}", session_id);
         let db_path = self.db_path.clone();
+        let cipher = self.session_cipher.clone();
 
         task::spawn_blocking(move || -> Result<Option<models::SessionRecord>> {
             let conn = Connection::open(&db_path)
                 .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
 
             let mut stmt = conn.prepare(
-                r#"SELECT session_id, principal_json, expiry_epoch, is_admin FROM sessions WHERE session_id = ?1"#,
+                r#"SELECT session_id, principal_json, expiry_epoch, is_admin, client_binding FROM sessions WHERE session_id = ?1"#,
             )?;
 
             tracing::trace!("Executing SQL: SELECT session_id, principal_json, expiry_epoch, is_admin FROM sessions WHERE session_id = {}", session_id);
-            let rec: Option<(String, String, i64, Option<i64>)> = match stmt.query_row(params![session_id], |row| {
+            type SessionRow = (String, String, i64, Option<i64>, Option<String>);
+            let rec: Option<SessionRow> = match stmt.query_row(params![session_id], |row| {
                 Ok::<_, rusqlite::Error>( (
                     row.get(0)?, // session_id
                     row.get(1)?, // principal_json
                     row.get(2)?, // expiry_epoch
                     row.get::<_, Option<i64>>(3)?, // is_admin
+                    row.get::<_, Option<String>>(4)?, // client_binding
                 ))
             }) {
                 Ok(v) => Some(v),
@@ -525,8 +698,10 @@ impl Database {
                 Err(e) => return Err(e.into()),
             };
 
-            if let Some((sid, principal_json, expiry_epoch, is_admin_opt)) = rec {
-                match models::SessionRecord::from_db_row(sid.clone(), principal_json, expiry_epoch, is_admin_opt) {
+            if let Some((sid, principal_json, expiry_epoch, is_admin_opt, client_binding)) = rec {
+                match open_principal(cipher.as_ref(), principal_json).and_then(|principal_json| {
+                    models::SessionRecord::from_db_row(sid.clone(), principal_json, expiry_epoch, is_admin_opt, client_binding)
+                }) {
                     Ok(session_record) => {
                         tracing::trace!("Session found: session_id={}, expiry_epoch={}", sid, session_record.expiry_epoch);
                         Ok(Some(session_record))
@@ -567,17 +742,23 @@ impl Database {
         let db_path = self.db_path.clone();
 
         task::spawn_blocking(move || -> Result<bool> {
-            let conn = Connection::open(&db_path)
+            let mut conn = Connection::open(&db_path)
                 .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
 
             tracing::trace!(
                 "Executing SQL: DELETE FROM sessions WHERE session_id = {}",
                 session_id
             );
-            let n = conn.execute(
+            let tx = conn.transaction()?;
+            tx.execute(
+                r#"DELETE FROM session_refresh WHERE session_id = ?1"#,
+                params![session_id],
+            )?;
+            let n = tx.execute(
                 r#"DELETE FROM sessions WHERE session_id = ?1"#,
                 params![session_id],
             )?;
+            tx.commit()?;
             let deleted = n > 0;
             tracing::trace!(
                 "Session deletion result: session_id={}, deleted={}",
@@ -589,6 +770,56 @@ impl Database {
         .await?
     }
 
+    /// Deletes the oldest active sessions of a principal beyond the newest
+    /// `keep`, along with their refresh tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal_id` - Global id of the principal
+    /// * `keep` - Number of most recently created sessions to keep
+    ///
+    /// # Returns
+    ///
+    /// The ids of the deleted sessions.
+    pub async fn evict_oldest_sessions_async(
+        &self,
+        principal_id: &str,
+        keep: usize,
+    ) -> Result<Vec<String>> {
+        let db_path = self.db_path.clone();
+        let principal_id = principal_id.to_string();
+
+        task::spawn_blocking(move || -> Result<Vec<String>> {
+            let mut conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            // Rowids grow with each insert, so they order sessions by creation
+            let evicted = tx
+                .prepare(
+                    r#"SELECT session_id FROM sessions WHERE principal_id = ?1 AND expiry_epoch > ?2
+                       ORDER BY rowid DESC LIMIT -1 OFFSET ?3"#,
+                )?
+                .query_map(
+                    params![principal_id, chrono::Utc::now().timestamp(), keep as i64],
+                    |row| row.get::<_, String>(0),
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for session_id in &evicted {
+                tx.execute(
+                    r#"DELETE FROM session_refresh WHERE session_id = ?1"#,
+                    params![session_id],
+                )?;
+                tx.execute(
+                    r#"DELETE FROM sessions WHERE session_id = ?1"#,
+                    params![session_id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(evicted)
+        })
+        .await?
+    }
+
     /// Removes all expired sessions from the database.
     ///
     /// Deletes sessions where the expiry timestamp is less than or equal to
@@ -616,12 +847,90 @@ impl Database {
                 r#"DELETE FROM sessions WHERE expiry_epoch <= ?1"#,
                 params![now_epoch],
             )?;
+            conn.execute(
+                r#"DELETE FROM session_refresh WHERE session_id NOT IN (SELECT session_id FROM sessions)"#,
+                [],
+            )?;
             tracing::trace!("Cleaned up {} expired sessions", n);
             Ok(n)
         })
         .await?
     }
 
+    /// Stores (or replaces) the refresh token of a session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection or upsert fails.
+    pub async fn save_session_refresh_async(
+        &self,
+        record: models::SessionRefreshRecord,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Saving session refresh token: session_id={}, renew_after_epoch={}",
+            record.session_id,
+            record.renew_after_epoch
+        );
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "journal_mode", "WAL").ok();
+            conn.pragma_update(None, "synchronous", "NORMAL").ok();
+
+            conn.execute(
+                r#"
+                INSERT INTO session_refresh(session_id, refresh_token_enc, renew_after_epoch)
+                VALUES(?1, ?2, ?3)
+                ON CONFLICT(session_id) DO UPDATE SET
+                    refresh_token_enc = excluded.refresh_token_enc,
+                    renew_after_epoch = excluded.renew_after_epoch
+                "#,
+                params![
+                    record.session_id,
+                    record.refresh_token_enc,
+                    record.renew_after_epoch
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Retrieves the refresh token of a session.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(record))` if the session is renewable
+    /// - `Ok(None)` if it has no refresh token
+    /// - `Err(...)` if database operation fails
+    pub async fn get_session_refresh_async(
+        &self,
+        session_id: String,
+    ) -> Result<Option<models::SessionRefreshRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Option<models::SessionRefreshRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT session_id, refresh_token_enc, renew_after_epoch FROM session_refresh WHERE session_id = ?1"#,
+            )?;
+            let mut rows = stmt.query(params![session_id])?;
+            match rows.next()? {
+                Some(row) => Ok(Some(models::SessionRefreshRecord {
+                    session_id: row.get(0)?,
+                    refresh_token_enc: row.get(1)?,
+                    renew_after_epoch: row.get(2)?,
+                })),
+                None => Ok(None),
+            }
+        })
+        .await?
+    }
+
     // ---------------- Async Plugins ----------------
 
     /// Inserts or updates plugin metadata in the database.
@@ -666,22 +975,40 @@ impl Database {
         let plugin_data = record.plugin_data.clone();
         let metadata_json = serde_json::to_string(&record.metadata)?;
         let date_added_utc = record.date_added_utc.to_rfc3339();
+        let blob_dir = self.blob_dir();
 
         let result = task::spawn_blocking(move || -> Result<()> {
-            let conn = Connection::open(&db_path)
+            let mut conn = Connection::open(&db_path)
                 .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
             conn.pragma_update(None, "journal_mode", "WAL").ok();
             conn.pragma_update(None, "synchronous", "NORMAL").ok();
 
-            conn.execute(
+            // Blobs are counted, written and removed while holding the write
+            // lock, so a blob is never removed between being written and
+            // referenced.
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let previous_digest: Option<String> = tx
+                .query_row(
+                    r#"SELECT plugin_digest FROM plugins WHERE owner = ?1 AND plugin_id = ?2"#,
+                    params![owner, plugin_id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten();
+            let plugin_digest = plugin_data
+                .map(|bytes| store_blob(&tx, &blob_dir, &bytes))
+                .transpose()?;
+
+            // A record without a payload keeps the stored one
+            tx.execute(
                 r#"
-                INSERT INTO plugins(owner, plugin_id, plugin_name, plugin_path, plugin_data, metadata, date_added_utc)
+                INSERT INTO plugins(owner, plugin_id, plugin_name, plugin_path, plugin_digest, metadata, date_added_utc)
                 VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)
                 ON CONFLICT(owner, plugin_id) 
                 DO UPDATE SET 
                     plugin_name = COALESCE(excluded.plugin_name, plugins.plugin_name),
                     plugin_path = COALESCE(excluded.plugin_path, plugins.plugin_path),
-                    plugin_data = COALESCE(excluded.plugin_data, plugins.plugin_data),
+                    plugin_digest = COALESCE(excluded.plugin_digest, plugins.plugin_digest),
                     metadata = excluded.metadata,
                     date_added_utc = excluded.date_added_utc
                 "#,
@@ -690,11 +1017,17 @@ impl Database {
                     plugin_id,
                     plugin_name,
                     plugin_path,
-                    plugin_data,
+                    plugin_digest,
                     metadata_json,
                     date_added_utc
                 ],
             )?;
+            if let Some(previous) = previous_digest
+                && plugin_digest.is_some()
+            {
+                release_blob(&tx, &blob_dir, &previous)?;
+            }
+            tx.commit()?;
             Ok(())
         })
         .await?;
@@ -735,13 +1068,14 @@ impl Database {
     ) -> Result<Option<PluginRecord>> {
         tracing::trace!("Getting plugin: owner={}, plugin_id={}", owner, plugin_id);
         let db_path = self.db_path.clone();
+        let blob_dir = self.blob_dir();
 
         task::spawn_blocking(move || -> Result<Option<PluginRecord>> {
             let conn = Connection::open(&db_path)
                 .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
 
             let mut stmt = conn.prepare(
-                r#"SELECT owner, plugin_id, plugin_name, plugin_path, metadata, date_added_utc, plugin_data FROM plugins WHERE owner = ?1 AND plugin_id = ?2"#,
+                r#"SELECT p.owner, p.plugin_id, p.plugin_name, p.plugin_path, p.metadata, p.date_added_utc, COALESCE(p.plugin_data, b.data), p.plugin_digest FROM plugins p LEFT JOIN plugin_blobs b ON b.digest = p.plugin_digest WHERE p.owner = ?1 AND p.plugin_id = ?2"#,
             )?;
 
             tracing::trace!("Executing SQL: SELECT FROM plugins WHERE owner = {} AND plugin_id = {}", owner, plugin_id);
@@ -753,6 +1087,7 @@ impl Database {
                 String,
                 String,
                 Option<Vec<u8>>,
+                Option<String>,
             );
             let rec: Option<PluginRow> = match stmt.query_row(params![owner, plugin_id], |row| {
                 Ok::<_, rusqlite::Error>((
@@ -763,13 +1098,14 @@ impl Database {
                     row.get(4)?, // metadata
                     row.get(5)?, // date_added_utc
                     row.get::<_, Option<Vec<u8>>>(6)?, // plugin_data
+                    row.get::<_, Option<String>>(7)?, // plugin_digest
                 ))
             }) {
                 Ok(v) => Some(v),
                 Err(rusqlite::Error::QueryReturnedNoRows) => None,
                 Err(e) => return Err(e.into()),
             };
-            if let Some((owner, plugin_id, plugin_name, plugin_path, metadata_json, date_added_utc_str, plugin_data)) = rec {
+            if let Some((owner, plugin_id, plugin_name, plugin_path, metadata_json, date_added_utc_str, plugin_data, plugin_digest)) = rec {
                 match PluginRecord::from_db_row(
                     owner,
                     plugin_id,
@@ -777,7 +1113,7 @@ impl Database {
                     plugin_path,
                     metadata_json,
                     date_added_utc_str,
-                    plugin_data,
+                    read_payload(&blob_dir, plugin_data, plugin_digest),
                 ) {
                     Ok(record) => Ok(Some(record)),
                     Err(e) => {
@@ -812,20 +1148,34 @@ impl Database {
     pub async fn delete_plugin_async(&self, owner: String, plugin_id: String) -> Result<bool> {
         tracing::trace!("Deleting plugin: owner={}, plugin_id={}", owner, plugin_id);
         let db_path = self.db_path.clone();
+        let blob_dir = self.blob_dir();
 
         task::spawn_blocking(move || -> Result<bool> {
-            let conn = Connection::open(&db_path)
+            let mut conn = Connection::open(&db_path)
                 .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let digest: Option<String> = tx
+                .query_row(
+                    r#"SELECT plugin_digest FROM plugins WHERE owner = ?1 AND plugin_id = ?2"#,
+                    params![owner, plugin_id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten();
 
             tracing::trace!(
                 "Executing SQL: DELETE FROM plugins WHERE owner = {} AND plugin_id = {}",
                 owner,
                 plugin_id
             );
-            let n = conn.execute(
+            let n = tx.execute(
                 r#"DELETE FROM plugins WHERE owner = ?1 AND plugin_id = ?2"#,
                 params![owner, plugin_id],
             )?;
+            if let Some(digest) = digest {
+                release_blob(&tx, &blob_dir, &digest)?;
+            }
+            tx.commit()?;
             let deleted = n > 0;
             tracing::trace!(
                 "Plugin deletion result: owner={}, plugin_id={}, deleted={}",
@@ -850,13 +1200,14 @@ impl Database {
     pub async fn list_plugins_async(&self) -> Result<Vec<PluginRecord>> {
         tracing::trace!("Listing all plugins");
         let db_path = self.db_path.clone();
+        let blob_dir = self.blob_dir();
 
         task::spawn_blocking(move || -> Result<Vec<PluginRecord>> {
             let conn = Connection::open(&db_path)
                 .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
 
             let mut stmt = conn.prepare(
-                r#"SELECT owner, plugin_id, plugin_name, plugin_path, metadata, date_added_utc, plugin_data FROM plugins ORDER BY date_added_utc DESC"#,
+                r#"SELECT p.owner, p.plugin_id, p.plugin_name, p.plugin_path, p.metadata, p.date_added_utc, COALESCE(p.plugin_data, b.data), p.plugin_digest FROM plugins p LEFT JOIN plugin_blobs b ON b.digest = p.plugin_digest ORDER BY p.date_added_utc DESC"#,
             )?;
             tracing::trace!("Executing SQL: SELECT FROM plugins ORDER BY date_added_utc DESC");
             let mut out = Vec::new();
@@ -870,6 +1221,7 @@ impl Database {
                 let metadata_json: String = row.get(4)?;
                 let date_added_utc_str: String = row.get(5)?;
                 let plugin_data: Option<Vec<u8>> = row.get(6).ok();
+                let plugin_digest: Option<String> = row.get(7).ok().flatten();
 
                 match PluginRecord::from_db_row(
                     owner.clone(),
@@ -878,7 +1230,7 @@ impl Database {
                     plugin_path,
                     metadata_json,
                     date_added_utc_str,
-                    plugin_data,
+                    read_payload(&blob_dir, plugin_data, plugin_digest),
                 ) {
                     Ok(rec) => out.push(rec),
                     Err(e) => tracing::warn!(error=%e, "Skipping malformed plugin row`{}`", plugin_id),
@@ -916,13 +1268,14 @@ impl Database {
     pub async fn list_plugins_by_owner_async(&self, owner: String) -> Result<Vec<PluginRecord>> {
         tracing::trace!("Listing plugins by owner: owner={}", owner);
         let db_path = self.db_path.clone();
+        let blob_dir = self.blob_dir();
 
         task::spawn_blocking(move || -> Result<Vec<PluginRecord>> {
             let conn = Connection::open(&db_path)
                 .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
 
             let mut stmt = conn.prepare(
-                r#"SELECT owner, plugin_id, plugin_name, plugin_path, metadata, date_added_utc, plugin_data FROM plugins WHERE owner = ?1 ORDER BY date_added_utc DESC"#,
+                r#"SELECT p.owner, p.plugin_id, p.plugin_name, p.plugin_path, p.metadata, p.date_added_utc, COALESCE(p.plugin_data, b.data), p.plugin_digest FROM plugins p LEFT JOIN plugin_blobs b ON b.digest = p.plugin_digest WHERE p.owner = ?1 ORDER BY p.date_added_utc DESC"#,
             )?;
             tracing::trace!("Executing SQL: SELECT FROM plugins WHERE owner = {} ORDER BY date_added_utc DESC", owner);
             let mut out = Vec::new();
@@ -936,6 +1289,7 @@ impl Database {
                 let metadata_json: String = row.get(4)?;
                 let date_added_utc_str: String = row.get(5)?;
                 let plugin_data: Option<Vec<u8>> = row.get(6).ok();
+                let plugin_digest: Option<String> = row.get(7).ok().flatten();
 
                 match PluginRecord::from_db_row(
                     owner.clone(),
@@ -944,7 +1298,7 @@ impl Database {
                     plugin_path,
                     metadata_json,
                     date_added_utc_str,
-                    plugin_data,
+                    read_payload(&blob_dir, plugin_data, plugin_digest),
                 ) {
                     Ok(rec) => out.push(rec),
                     Err(e) => tracing::warn!(error=%e, "Skipping malformed plugin row: {}/{}", owner, plugin_id),
@@ -955,94 +1309,1576 @@ impl Database {
         })
         .await?
     }
-}
 
-/// Resolves the default database file path.
-///
-/// Checks for the `ARK_DB_PATH` environment variable first, then falls back
-/// to platform-specific default locations:
-///
-/// - Windows: `%PROGRAMDATA%\ark\ark.db`
-/// - Unix/Linux: `/var/ark/ark.db`
-///
-/// # Returns
-///
-/// The resolved database file path.
-fn resolve_db_path() -> Result<PathBuf> {
-    if let Ok(p) = env::var("ARK_DB_PATH") {
-        return Ok(PathBuf::from(p));
-    }
+    // ---------------- Async API Keys ----------------
 
-    #[cfg(target_os = "windows")]
-    {
-        let program_data =
-            env::var("PROGRAMDATA").unwrap_or_else(|_| r"C:\ProgramData".to_string());
-        Ok(Path::new(&program_data).join("ark").join("ark.db"))
-    }
+    /// Persists a new API key record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The API key record; only the token hash is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection, serialization or insert fails
+    /// (including a duplicate `key_id` or `token_hash`).
+    pub async fn save_api_key_record_async(&self, record: models::ApiKeyRecord) -> Result<()> {
+        tracing::trace!(
+            "Saving API key record: key_id={}, owner={}",
+            record.key_id,
+            record.owner
+        );
+        let db_path = self.db_path.clone();
+        let principal_json = serde_json::to_string(&record.principal)?;
+        let scopes = record.scopes.join(" ");
+        let created_utc = record.created_utc.to_rfc3339();
+        let expiry_epoch = record.expiry_utc.map(|e| e.timestamp());
+        let key_id = record.key_id.clone();
+        let token_hash = record.token_hash.clone();
+        let owner = record.owner.clone();
+        let name = record.name.clone();
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        return Ok(PathBuf::from("/var/ark/ark.db"));
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "journal_mode", "WAL").ok();
+            conn.pragma_update(None, "synchronous", "NORMAL").ok();
+
+            conn.execute(
+                r#"
+                INSERT INTO api_keys(key_id, token_hash, owner, name, principal_json, scopes, created_utc, expiry_epoch)
+                VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+                params![
+                    key_id,
+                    token_hash,
+                    owner,
+                    name,
+                    principal_json,
+                    scopes,
+                    created_utc,
+                    expiry_epoch
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
     }
-}
 
-/// Ensures the parent directory of the given path exists with secure permissions.
-///
-/// Creates all parent directories if they don't exist and sets secure
-/// permissions using the utility functions.
-///
-/// # Arguments
-///
-/// * `path` - The file path whose parent directory should be ensured
-///
-/// # Returns
-///
-/// `Ok(())` if the directory exists or was created successfully.
-///
-/// # Errors
-///
-/// Returns an error if directory creation or permission setting fails.
-fn ensure_parent_dir(path: &Path) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("creating parent dir {}", parent.display()))?;
+    /// Retrieves an API key record by the hash of its token.
+    ///
+    /// # Arguments
+    ///
+    /// * `token_hash` - Hex-encoded SHA-256 hash of the presented token
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(record))` if a key with this hash exists (it may be expired)
+    /// - `Ok(None)` if no key matches
+    /// - `Err(...)` if database operation fails
+    pub async fn get_api_key_by_hash_async(
+        &self,
+        token_hash: String,
+    ) -> Result<Option<models::ApiKeyRecord>> {
+        let db_path = self.db_path.clone();
 
-        // Quick writability check: try to create and remove a temp file to
-        // ensure we have permission to write into this directory. This gives
-        // a clearer error earlier than relying on SQLite errors such as
-        // "attempt to write a readonly database".
-        let test_file = parent.join(".ark_write_test");
-        match std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&test_file)
-        {
-            Ok(mut f) => {
-                use std::io::Write;
-                // try write and flush a small payload then remove file
-                if let Err(e) = f.write_all(b"ok") {
-                    let _ = std::fs::remove_file(&test_file);
-                    return Err(anyhow::anyhow!(
-                        "parent dir not writable {}: {}",
-                        parent.display(),
+        task::spawn_blocking(move || -> Result<Option<models::ApiKeyRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT key_id, token_hash, owner, name, principal_json, scopes, created_utc, expiry_epoch FROM api_keys WHERE token_hash = ?1"#,
+            )?;
+            tracing::trace!("Executing SQL: SELECT FROM api_keys WHERE token_hash = <redacted>");
+            let mut rows = stmt.query(params![token_hash])?;
+            match rows.next()? {
+                Some(row) => {
+                    let key_id: String = row.get(0)?;
+                    models::ApiKeyRecord::from_db_row(
+                        key_id.clone(),
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                        row.get(6)?,
+                        row.get(7)?,
+                    )
+                    .map(Some)
+                    .map_err(|e| {
+                        tracing::warn!(error=%e, "Malformed API key row: {}", key_id);
                         e
-                    ));
+                    })
                 }
-                let _ = std::fs::remove_file(&test_file);
+                None => Ok(None),
             }
-            Err(e) => {
-                return Err(anyhow::anyhow!(
-                    "parent dir not writable {}: {}",
-                    parent.display(),
-                    e
-                ));
+        })
+        .await?
+    }
+
+    /// Lists all API key records for an owner, most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - Owner identifier to filter by
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(records)` - API keys belonging to the owner (malformed rows are skipped)
+    /// - `Err(...)` if database operation fails
+    pub async fn list_api_keys_by_owner_async(
+        &self,
+        owner: String,
+    ) -> Result<Vec<models::ApiKeyRecord>> {
+        tracing::trace!("Listing API keys by owner: owner={}", owner);
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::ApiKeyRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT key_id, token_hash, owner, name, principal_json, scopes, created_utc, expiry_epoch FROM api_keys WHERE owner = ?1 ORDER BY created_utc DESC"#,
+            )?;
+            tracing::trace!("Executing SQL: SELECT FROM api_keys WHERE owner = {} ORDER BY created_utc DESC", owner);
+            let mut out = Vec::new();
+            let mut rows = stmt.query(params![owner])?;
+
+            while let Some(row) = rows.next()? {
+                let key_id: String = row.get(0)?;
+                match models::ApiKeyRecord::from_db_row(
+                    key_id.clone(),
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                ) {
+                    Ok(rec) => out.push(rec),
+                    Err(e) => tracing::warn!(error=%e, "Skipping malformed API key row: {}", key_id),
+                }
             }
-        }
+            Ok(out)
+        })
+        .await?
+    }
 
-        // Set secure permissions on the ark directory. Failures here are
-        // non-fatal for usability in some environments, but we return the
-        // error so callers can decide. The caller (e.g., main) will log and
+    /// Deletes (revokes) an API key belonging to an owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - Owner identifier; keys of other owners are never deleted
+    /// * `key_id` - Public key identifier
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(true)` if the key was found and deleted
+    /// - `Ok(false)` if no such key exists for the owner
+    /// - `Err(...)` if database operation fails
+    pub async fn delete_api_key_async(&self, owner: String, key_id: String) -> Result<bool> {
+        tracing::trace!("Deleting API key: owner={}, key_id={}", owner, key_id);
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<bool> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let n = conn.execute(
+                r#"DELETE FROM api_keys WHERE owner = ?1 AND key_id = ?2"#,
+                params![owner, key_id],
+            )?;
+            Ok(n > 0)
+        })
+        .await?
+    }
+
+    // ---------------- Async Service Accounts ----------------
+
+    /// Creates or updates a service account record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The service account; only the client secret hash is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection, serialization or upsert fails.
+    pub async fn save_service_account_async(
+        &self,
+        record: models::ServiceAccountRecord,
+    ) -> Result<()> {
+        tracing::trace!("Saving service account: account_id={}", record.account_id);
+        let db_path = self.db_path.clone();
+        let roles_json = serde_json::to_string(&record.roles)?;
+        let created_utc = record.created_utc.to_rfc3339();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "journal_mode", "WAL").ok();
+            conn.pragma_update(None, "synchronous", "NORMAL").ok();
+
+            conn.execute(
+                r#"
+                INSERT INTO service_accounts(account_id, description, roles_json, secret_hash, created_by, created_utc)
+                VALUES(?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT(account_id) DO UPDATE SET
+                    description = excluded.description,
+                    roles_json = excluded.roles_json,
+                    secret_hash = excluded.secret_hash
+                "#,
+                params![
+                    record.account_id,
+                    record.description,
+                    roles_json,
+                    record.secret_hash,
+                    record.created_by,
+                    created_utc
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Retrieves a service account by id.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(record))` if the account exists
+    /// - `Ok(None)` if it does not
+    /// - `Err(...)` if database operation fails or the row is malformed
+    pub async fn get_service_account_async(
+        &self,
+        account_id: String,
+    ) -> Result<Option<models::ServiceAccountRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Option<models::ServiceAccountRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT account_id, description, roles_json, secret_hash, created_by, created_utc FROM service_accounts WHERE account_id = ?1"#,
+            )?;
+            tracing::trace!("Executing SQL: SELECT FROM service_accounts WHERE account_id = {}", account_id);
+            let mut rows = stmt.query(params![account_id])?;
+            match rows.next()? {
+                Some(row) => models::ServiceAccountRecord::from_db_row(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                )
+                .map(Some),
+                None => Ok(None),
+            }
+        })
+        .await?
+    }
+
+    /// Lists all service accounts ordered by id.
+    ///
+    /// Malformed rows are skipped with a warning.
+    pub async fn list_service_accounts_async(&self) -> Result<Vec<models::ServiceAccountRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::ServiceAccountRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT account_id, description, roles_json, secret_hash, created_by, created_utc FROM service_accounts ORDER BY account_id"#,
+            )?;
+            let mut out = Vec::new();
+            let mut rows = stmt.query([])?;
+
+            while let Some(row) = rows.next()? {
+                let account_id: String = row.get(0)?;
+                match models::ServiceAccountRecord::from_db_row(
+                    account_id.clone(),
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ) {
+                    Ok(rec) => out.push(rec),
+                    Err(e) => tracing::warn!(error=%e, "Skipping malformed service account row: {}", account_id),
+                }
+            }
+            Ok(out)
+        })
+        .await?
+    }
+
+    /// Deletes a service account together with all API keys issued to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - Account identifier
+    /// * `owner` - The account's global id, used to revoke its API keys
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(true)` if the account was found and deleted
+    /// - `Ok(false)` if no such account exists
+    /// - `Err(...)` if database operation fails
+    pub async fn delete_service_account_async(
+        &self,
+        account_id: String,
+        owner: String,
+    ) -> Result<bool> {
+        tracing::trace!("Deleting service account: account_id={}", account_id);
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<bool> {
+            let mut conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let tx = conn.transaction()?;
+            tx.execute(r#"DELETE FROM api_keys WHERE owner = ?1"#, params![owner])?;
+            let n = tx.execute(
+                r#"DELETE FROM service_accounts WHERE account_id = ?1"#,
+                params![account_id],
+            )?;
+            tx.commit()?;
+            Ok(n > 0)
+        })
+        .await?
+    }
+
+    /// Moves the rows of principals whose global ids start with `old_prefix`
+    /// (e.g. `oidc`) to `new_prefix` (e.g. `oidc:corp`), for a provider whose
+    /// ids became qualified by its name.
+    ///
+    /// Rows that would collide with one already under the new id are left
+    /// alone; sessions under the old ids are ended, so their users sign in
+    /// again.
+    ///
+    /// # Returns
+    ///
+    /// The number of rows moved or sessions ended.
+    pub async fn qualify_principal_ids_async(
+        &self,
+        old_prefix: String,
+        new_prefix: String,
+        provider_name: String,
+    ) -> Result<usize> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<usize> {
+            let mut conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+
+            let old = format!("{}/", old_prefix);
+            let new = format!("{}/", new_prefix);
+            let tx = conn.transaction()?;
+            let mut moved = 0;
+            for (table, column) in [
+                ("plugins", "owner"),
+                ("api_keys", "owner"),
+                ("service_accounts", "created_by"),
+                ("auth_events", "subject"),
+                ("tool_usage", "owner"),
+                ("tool_calls", "principal"),
+                ("tool_usage_rollups", "principal"),
+                ("user_preferences", "principal_id"),
+                ("tool_executions", "principal"),
+                ("principal_usage", "principal"),
+            ] {
+                moved += tx.execute(
+                    &format!(
+                        "UPDATE OR IGNORE {table} SET {column} = ?2 || substr({column}, length(?1) + 1) \
+                         WHERE substr({column}, 1, length(?1)) = ?1"
+                    ),
+                    params![old, new],
+                )?;
+            }
+            // API keys authenticate as the principal they were issued to
+            tx.execute(
+                r#"
+                UPDATE api_keys SET principal_json = json_set(principal_json, '$.provider_name', ?2)
+                WHERE substr(owner, 1, length(?1)) = ?1
+                  AND json_extract(principal_json, '$.provider_name') IS NULL
+                "#,
+                params![new, provider_name],
+            )?;
+            tx.execute(
+                r#"
+                DELETE FROM session_refresh WHERE session_id IN (
+                    SELECT session_id FROM sessions WHERE substr(principal_id, 1, length(?1)) = ?1
+                )
+                "#,
+                params![old],
+            )?;
+            moved += tx.execute(
+                r#"DELETE FROM sessions WHERE substr(principal_id, 1, length(?1)) = ?1"#,
+                params![old],
+            )?;
+            tx.commit()?;
+            Ok(moved)
+        })
+        .await?
+    }
+
+    // ---------------- Async OAuth Clients ----------------
+
+    /// Stores a dynamically registered OAuth client.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The client; only the client secret hash is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection, serialization or insert fails.
+    pub async fn save_oauth_client_async(&self, record: models::OAuthClientRecord) -> Result<()> {
+        tracing::trace!("Saving OAuth client: client_id={}", record.client_id);
+        let db_path = self.db_path.clone();
+        let metadata_json = serde_json::to_string(&record.metadata)?;
+        let created_utc = record.created_utc.to_rfc3339();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.execute(
+                r#"
+                INSERT INTO oauth_clients(client_id, secret_hash, metadata_json, created_utc)
+                VALUES(?1, ?2, ?3, ?4)
+                "#,
+                params![
+                    record.client_id,
+                    record.secret_hash,
+                    metadata_json,
+                    created_utc
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Retrieves a registered OAuth client by id.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(record))` if the client is registered
+    /// - `Ok(None)` if it is not
+    /// - `Err(...)` if database operation fails or the row is malformed
+    pub async fn get_oauth_client_async(
+        &self,
+        client_id: String,
+    ) -> Result<Option<models::OAuthClientRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Option<models::OAuthClientRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT client_id, secret_hash, metadata_json, created_utc FROM oauth_clients WHERE client_id = ?1"#,
+            )?;
+            let mut rows = stmt.query(params![client_id])?;
+            match rows.next()? {
+                Some(row) => models::OAuthClientRecord::from_db_row(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                )
+                .map(Some),
+                None => Ok(None),
+            }
+        })
+        .await?
+    }
+
+    // ---------------- Async User Preferences ----------------
+
+    /// Retrieves a principal's console preferences.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal_id` - The principal's global id.
+    ///
+    /// # Returns
+    ///
+    /// The stored preferences, or the defaults if none were saved.
+    pub async fn get_user_preferences_async(
+        &self,
+        principal_id: String,
+    ) -> Result<models::UserPreferences> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<models::UserPreferences> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            let json: Option<String> = conn
+                .query_row(
+                    "SELECT preferences_json FROM user_preferences WHERE principal_id = ?1",
+                    params![principal_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            match json {
+                Some(json) => serde_json::from_str(&json).context("parsing preferences from DB"),
+                None => Ok(models::UserPreferences::default()),
+            }
+        })
+        .await?
+    }
+
+    /// Stores a principal's console preferences, replacing any saved before.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection, serialization or upsert fails.
+    pub async fn save_user_preferences_async(
+        &self,
+        principal_id: String,
+        preferences: models::UserPreferences,
+    ) -> Result<()> {
+        let db_path = self.db_path.clone();
+        let json = serde_json::to_string(&preferences)?;
+        let updated_utc = chrono::Utc::now().to_rfc3339();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.execute(
+                r#"
+                INSERT INTO user_preferences(principal_id, preferences_json, updated_utc)
+                VALUES(?1, ?2, ?3)
+                ON CONFLICT(principal_id) DO UPDATE SET
+                    preferences_json = excluded.preferences_json,
+                    updated_utc = excluded.updated_utc
+                "#,
+                params![principal_id, json, updated_utc],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    // ---------------- Async Users ----------------
+
+    /// Creates or updates a local user.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The user record; `password_hash` must already be hashed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection, serialization or upsert fails.
+    pub async fn save_user_async(&self, record: models::UserRecord) -> Result<()> {
+        tracing::trace!("Saving user: username={}", record.username);
+        let db_path = self.db_path.clone();
+        let groups_json = serde_json::to_string(&record.groups)?;
+        let created_utc = record.created_utc.to_rfc3339();
+        let is_admin: i64 = if record.is_admin { 1 } else { 0 };
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "journal_mode", "WAL").ok();
+            conn.pragma_update(None, "synchronous", "NORMAL").ok();
+
+            conn.execute(
+                r#"
+                INSERT INTO users(username, password_hash, display_name, email, groups_json, is_admin, created_utc)
+                VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ON CONFLICT(username) DO UPDATE SET
+                    password_hash = excluded.password_hash,
+                    display_name = excluded.display_name,
+                    email = excluded.email,
+                    groups_json = excluded.groups_json,
+                    is_admin = excluded.is_admin
+                "#,
+                params![
+                    record.username,
+                    record.password_hash,
+                    record.display_name,
+                    record.email,
+                    groups_json,
+                    is_admin,
+                    created_utc
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Retrieves a local user by username.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(record))` if the user exists
+    /// - `Ok(None)` if it does not
+    /// - `Err(...)` if database operation fails or the row is malformed
+    pub async fn get_user_async(&self, username: String) -> Result<Option<models::UserRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Option<models::UserRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT username, password_hash, display_name, email, groups_json, is_admin, created_utc FROM users WHERE username = ?1"#,
+            )?;
+            tracing::trace!("Executing SQL: SELECT FROM users WHERE username = {}", username);
+            let mut rows = stmt.query(params![username])?;
+            match rows.next()? {
+                Some(row) => models::UserRecord::from_db_row(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                )
+                .map(Some),
+                None => Ok(None),
+            }
+        })
+        .await?
+    }
+
+    /// Lists all local users ordered by username.
+    ///
+    /// Malformed rows are skipped with a warning.
+    pub async fn list_users_async(&self) -> Result<Vec<models::UserRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::UserRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT username, password_hash, display_name, email, groups_json, is_admin, created_utc FROM users ORDER BY username"#,
+            )?;
+            let mut out = Vec::new();
+            let mut rows = stmt.query([])?;
+
+            while let Some(row) = rows.next()? {
+                let username: String = row.get(0)?;
+                match models::UserRecord::from_db_row(
+                    username.clone(),
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ) {
+                    Ok(rec) => out.push(rec),
+                    Err(e) => tracing::warn!(error=%e, "Skipping malformed user row: {}", username),
+                }
+            }
+            Ok(out)
+        })
+        .await?
+    }
+
+    /// Deletes a local user.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(true)` if the user was found and deleted
+    /// - `Ok(false)` if no such user exists
+    /// - `Err(...)` if database operation fails
+    pub async fn delete_user_async(&self, username: String) -> Result<bool> {
+        tracing::trace!("Deleting user: username={}", username);
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<bool> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let n = conn.execute(
+                r#"DELETE FROM users WHERE username = ?1"#,
+                params![username],
+            )?;
+            Ok(n > 0)
+        })
+        .await?
+    }
+
+    /// Appends an authentication event to the audit trail.
+    ///
+    /// # Returns
+    ///
+    /// The id assigned to the new row.
+    pub async fn save_auth_event_async(&self, record: models::AuthEventRecord) -> Result<i64> {
+        tracing::trace!(
+            "Saving auth event: event={}, subject={:?}",
+            record.event,
+            record.subject
+        );
+        let db_path = self.db_path.clone();
+        let timestamp_utc = record.timestamp_utc.to_rfc3339();
+        let timestamp_epoch = record.timestamp_utc.timestamp();
+
+        task::spawn_blocking(move || -> Result<i64> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "journal_mode", "WAL").ok();
+            conn.pragma_update(None, "synchronous", "NORMAL").ok();
+
+            conn.execute(
+                r#"
+                INSERT INTO auth_events(timestamp_utc, timestamp_epoch, event, provider, subject, ip, user_agent, detail, request_id)
+                VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                "#,
+                params![
+                    timestamp_utc,
+                    timestamp_epoch,
+                    record.event,
+                    record.provider,
+                    record.subject,
+                    record.ip,
+                    record.user_agent,
+                    record.detail,
+                    record.request_id
+                ],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await?
+    }
+
+    /// Lists authentication events matching `query`, newest first.
+    ///
+    /// Malformed rows are skipped with a warning.
+    pub async fn list_auth_events_async(
+        &self,
+        query: models::AuthEventQuery,
+    ) -> Result<Vec<models::AuthEventRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::AuthEventRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT id, timestamp_utc, event, provider, subject, ip, user_agent, detail, request_id
+                FROM auth_events
+                WHERE (?1 IS NULL OR event = ?1)
+                  AND (?2 IS NULL OR subject = ?2)
+                  AND (?3 IS NULL OR provider = ?3)
+                  AND (?4 IS NULL OR timestamp_epoch >= ?4)
+                  AND (?5 IS NULL OR timestamp_epoch < ?5)
+                  AND (?7 IS NULL OR request_id = ?7)
+                ORDER BY id DESC
+                LIMIT ?6
+                "#,
+            )?;
+            let mut out = Vec::new();
+            let mut rows = stmt.query(params![
+                query.event,
+                query.subject,
+                query.provider,
+                query.since.map(|t| t.timestamp()),
+                query.until.map(|t| t.timestamp()),
+                query.limit,
+                query.request_id
+            ])?;
+
+            while let Some(row) = rows.next()? {
+                let id: i64 = row.get(0)?;
+                match models::AuthEventRecord::from_db_row(
+                    id,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                    row.get(8)?,
+                ) {
+                    Ok(rec) => out.push(rec),
+                    Err(e) => tracing::warn!(error=%e, "Skipping malformed auth event row: {}", id),
+                }
+            }
+            Ok(out)
+        })
+        .await?
+    }
+
+    /// Deletes the authentication events older than `max_age`.
+    ///
+    /// # Returns
+    ///
+    /// The number of events deleted.
+    pub async fn purge_auth_events_async(&self, max_age: Duration) -> Result<usize> {
+        let db_path = self.db_path.clone();
+        let cutoff = chrono::Utc::now().timestamp() - max_age.as_secs() as i64;
+
+        task::spawn_blocking(move || -> Result<usize> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+            let n = conn.execute(
+                r#"DELETE FROM auth_events WHERE timestamp_epoch < ?1"#,
+                params![cutoff],
+            )?;
+            Ok(n)
+        })
+        .await?
+    }
+
+    // ---------------- Usage ----------------
+
+    /// Adds a tool call to the persisted counters of `(owner, plugin_id, tool)`
+    /// and queues it for the next rollup.
+    pub async fn record_tool_call_async(&self, record: models::ToolCallRecord) -> Result<()> {
+        tracing::trace!(
+            "Recording tool call: owner={}, plugin_id={}, tool={}, is_error={}",
+            record.owner,
+            record.plugin_id,
+            record.tool,
+            record.is_error
+        );
+        let db_path = self.db_path.clone();
+        let timestamp_epoch = record.timestamp_utc.timestamp();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let mut conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "journal_mode", "WAL").ok();
+            conn.pragma_update(None, "synchronous", "NORMAL").ok();
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+
+            let tx = conn.transaction()?;
+            tx.execute(
+                r#"
+                INSERT INTO tool_usage(owner, plugin_id, tool, calls, errors, total_latency_ms, last_call_epoch)
+                VALUES(?1, ?2, ?3, 1, ?4, ?5, ?6)
+                ON CONFLICT(owner, plugin_id, tool) DO UPDATE SET
+                    calls = calls + 1,
+                    errors = errors + excluded.errors,
+                    total_latency_ms = total_latency_ms + excluded.total_latency_ms,
+                    last_call_epoch = excluded.last_call_epoch
+                "#,
+                params![
+                    record.owner,
+                    record.plugin_id,
+                    record.tool,
+                    record.is_error as i64,
+                    record.latency_ms,
+                    timestamp_epoch
+                ],
+            )?;
+            tx.execute(
+                r#"
+                INSERT INTO tool_calls(timestamp_epoch, principal, plugin_id, tool, latency_ms, is_error)
+                VALUES(?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+                params![
+                    timestamp_epoch,
+                    record.principal,
+                    record.plugin_id,
+                    record.tool,
+                    record.latency_ms,
+                    record.is_error as i64
+                ],
+            )?;
+            if let Some(principal) = &record.principal {
+                tx.execute(
+                    r#"
+                    INSERT INTO principal_usage(principal, month, calls, compute_ms, bytes_returned)
+                    VALUES(?1, ?2, 1, ?3, ?4)
+                    ON CONFLICT(principal, month) DO UPDATE SET
+                        calls = calls + 1,
+                        compute_ms = compute_ms + excluded.compute_ms,
+                        bytes_returned = bytes_returned + excluded.bytes_returned
+                    "#,
+                    params![
+                        principal,
+                        models::PrincipalUsageRecord::month_of(record.timestamp_utc),
+                        record.latency_ms,
+                        record.bytes_returned as i64
+                    ],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Returns the usage of `principal` in `month` (`YYYY-MM`), with zero
+    /// counters if it made no calls.
+    pub async fn get_principal_usage_async(
+        &self,
+        principal: String,
+        month: String,
+    ) -> Result<models::PrincipalUsageRecord> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<models::PrincipalUsageRecord> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let usage = conn
+                .query_row(
+                    r#"
+                    SELECT calls, compute_ms, bytes_returned
+                    FROM principal_usage
+                    WHERE principal = ?1 AND month = ?2
+                    "#,
+                    params![principal, month],
+                    |row| {
+                        Ok((
+                            row.get::<_, i64>(0)?,
+                            row.get::<_, f64>(1)?,
+                            row.get::<_, i64>(2)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let (calls, compute_ms, bytes_returned) = usage.unwrap_or_default();
+            Ok(models::PrincipalUsageRecord {
+                principal,
+                month,
+                calls: calls.max(0) as u64,
+                compute_ms,
+                bytes_returned: bytes_returned.max(0) as u64,
+            })
+        })
+        .await?
+    }
+
+    /// Lists the usage of every principal that made calls in `month`
+    /// (`YYYY-MM`), heaviest compute users first.
+    pub async fn list_principal_usage_async(
+        &self,
+        month: String,
+    ) -> Result<Vec<models::PrincipalUsageRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::PrincipalUsageRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT principal, month, calls, compute_ms, bytes_returned
+                FROM principal_usage
+                WHERE month = ?1
+                ORDER BY compute_ms DESC, principal
+                "#,
+            )?;
+            let rows = stmt.query_map(params![month], |row| {
+                Ok(models::PrincipalUsageRecord {
+                    principal: row.get(0)?,
+                    month: row.get(1)?,
+                    calls: row.get::<_, i64>(2)?.max(0) as u64,
+                    compute_ms: row.get(3)?,
+                    bytes_returned: row.get::<_, i64>(4)?.max(0) as u64,
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+        })
+        .await?
+    }
+
+    /// Folds the queued tool calls into the hourly rollups and drops rollups
+    /// older than `retention`.
+    ///
+    /// # Returns
+    ///
+    /// The number of tool calls rolled up.
+    pub async fn rollup_tool_calls_async(&self, retention: Duration) -> Result<usize> {
+        let db_path = self.db_path.clone();
+        let cutoff = chrono::Utc::now().timestamp() - retention.as_secs() as i64;
+
+        task::spawn_blocking(move || -> Result<usize> {
+            let mut conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+            // (hour, plugin, tool, principal) -> (calls, errors, latency, histogram)
+            type Key = (i64, String, String, String);
+            let mut pending: BTreeMap<Key, (u64, u64, f64, Vec<u64>)> = BTreeMap::new();
+            let mut last_id = None;
+            {
+                let mut stmt = tx.prepare(
+                    r#"
+                    SELECT id, timestamp_epoch, principal, plugin_id, tool, latency_ms, is_error
+                    FROM tool_calls
+                    ORDER BY id
+                    "#,
+                )?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    last_id = Some(row.get::<_, i64>(0)?);
+                    let timestamp: i64 = row.get(1)?;
+                    let key = (
+                        timestamp - timestamp.rem_euclid(3600),
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    );
+                    let latency_ms: f64 = row.get(5)?;
+                    let entry = pending.entry(key).or_insert_with(|| {
+                        (0, 0, 0.0, vec![0; models::ROLLUP_LATENCY_BOUNDS_MS.len() + 1])
+                    });
+                    entry.0 += 1;
+                    entry.1 += u64::from(row.get::<_, i64>(6)? != 0);
+                    entry.2 += latency_ms;
+                    entry.3[models::latency_bucket(latency_ms)] += 1;
+                }
+            }
+            let mut rolled_up = 0;
+            for ((bucket, plugin_id, tool, principal), (calls, errors, latency, mut histogram)) in
+                pending
+            {
+                let existing: Option<String> = match tx.query_row(
+                    r#"
+                    SELECT latency_histogram FROM tool_usage_rollups
+                    WHERE bucket_epoch = ?1 AND plugin_id = ?2 AND tool = ?3 AND principal = ?4
+                    "#,
+                    params![bucket, plugin_id, tool, principal],
+                    |row| row.get(0),
+                ) {
+                    Ok(v) => Some(v),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                    Err(e) => return Err(e.into()),
+                };
+                if let Some(existing) = existing {
+                    let existing: Vec<u64> = serde_json::from_str(&existing)
+                        .context("parsing latency_histogram from DB")?;
+                    for (count, old) in histogram.iter_mut().zip(existing) {
+                        *count += old;
+                    }
+                }
+                tx.execute(
+                    r#"
+                    INSERT INTO tool_usage_rollups(bucket_epoch, plugin_id, tool, principal, calls, errors, total_latency_ms, latency_histogram)
+                    VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                    ON CONFLICT(bucket_epoch, plugin_id, tool, principal) DO UPDATE SET
+                        calls = calls + excluded.calls,
+                        errors = errors + excluded.errors,
+                        total_latency_ms = total_latency_ms + excluded.total_latency_ms,
+                        latency_histogram = excluded.latency_histogram
+                    "#,
+                    params![
+                        bucket,
+                        plugin_id,
+                        tool,
+                        principal,
+                        calls as i64,
+                        errors as i64,
+                        latency,
+                        serde_json::to_string(&histogram)?
+                    ],
+                )?;
+                rolled_up += calls as usize;
+            }
+            if let Some(last_id) = last_id {
+                tx.execute(r#"DELETE FROM tool_calls WHERE id <= ?1"#, params![last_id])?;
+            }
+            tx.execute(
+                r#"DELETE FROM tool_usage_rollups WHERE bucket_epoch < ?1"#,
+                params![cutoff],
+            )?;
+            tx.commit()?;
+            Ok(rolled_up)
+        })
+        .await?
+    }
+
+    /// Lists the hourly rollups of the hours starting at or after `since`.
+    ///
+    /// Malformed rows are skipped with a warning.
+    pub async fn list_tool_usage_rollups_async(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<models::ToolUsageRollup>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::ToolUsageRollup>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT bucket_epoch, plugin_id, tool, principal, calls, errors, total_latency_ms, latency_histogram
+                FROM tool_usage_rollups
+                WHERE bucket_epoch >= ?1
+                "#,
+            )?;
+            let mut rows = stmt.query(params![since.timestamp()])?;
+            let mut out = Vec::new();
+            while let Some(row) = rows.next()? {
+                let bucket: i64 = row.get(0)?;
+                let histogram: String = row.get(7)?;
+                let Some(bucket_utc) = chrono::DateTime::from_timestamp(bucket, 0) else {
+                    tracing::warn!("Skipping malformed tool usage rollup: {}", bucket);
+                    continue;
+                };
+                let latency_histogram = match serde_json::from_str(&histogram) {
+                    Ok(h) => h,
+                    Err(e) => {
+                        tracing::warn!(error=%e, "Skipping malformed tool usage rollup: {}", bucket);
+                        continue;
+                    }
+                };
+                out.push(models::ToolUsageRollup {
+                    bucket_utc,
+                    plugin_id: row.get(1)?,
+                    tool: row.get(2)?,
+                    principal: Some(row.get::<_, String>(3)?).filter(|p| !p.is_empty()),
+                    calls: row.get::<_, i64>(4)?.max(0) as u64,
+                    errors: row.get::<_, i64>(5)?.max(0) as u64,
+                    total_latency_ms: row.get(6)?,
+                    latency_histogram,
+                });
+            }
+            Ok(out)
+        })
+        .await?
+    }
+
+    /// Appends a tool call to the execution history, dropping the oldest
+    /// calls beyond the latest `keep`.
+    ///
+    /// # Returns
+    ///
+    /// The id assigned to the new row.
+    pub async fn save_tool_execution_async(
+        &self,
+        record: models::ToolExecutionRecord,
+        keep: u32,
+    ) -> Result<i64> {
+        tracing::trace!(
+            "Saving tool execution: plugin_id={}, tool={}, status={}",
+            record.plugin_id,
+            record.tool,
+            record.status()
+        );
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<i64> {
+            let mut conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "journal_mode", "WAL").ok();
+            conn.pragma_update(None, "synchronous", "NORMAL").ok();
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+
+            let tx = conn.transaction()?;
+            tx.execute(
+                r#"
+                INSERT INTO tool_executions(timestamp_utc, timestamp_epoch, principal, plugin_id, tool, duration_ms, status, error_code, args_hash, request_id)
+                VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                "#,
+                params![
+                    record.timestamp_utc.to_rfc3339(),
+                    record.timestamp_utc.timestamp(),
+                    record.principal,
+                    record.plugin_id,
+                    record.tool,
+                    record.duration_ms,
+                    record.status(),
+                    record.error_code.map(|c| c.as_str()),
+                    record.args_hash,
+                    record.request_id
+                ],
+            )?;
+            let id = tx.last_insert_rowid();
+            tx.execute(
+                r#"DELETE FROM tool_executions WHERE id <= ?1"#,
+                params![id - i64::from(keep)],
+            )?;
+            tx.commit()?;
+            Ok(id)
+        })
+        .await?
+    }
+
+    /// Lists tool executions matching `query`, newest first.
+    ///
+    /// Malformed rows are skipped with a warning.
+    pub async fn list_tool_executions_async(
+        &self,
+        query: models::ToolExecutionQuery,
+    ) -> Result<Vec<models::ToolExecutionRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::ToolExecutionRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT id, timestamp_utc, principal, plugin_id, tool, duration_ms, error_code, args_hash, request_id
+                FROM tool_executions
+                WHERE (?1 IS NULL OR principal = ?1)
+                  AND (?2 IS NULL OR plugin_id = ?2)
+                  AND (?3 IS NULL OR tool = ?3)
+                  AND (?4 IS NULL OR status = ?4)
+                  AND (?5 IS NULL OR timestamp_epoch >= ?5)
+                  AND (?6 IS NULL OR timestamp_epoch < ?6)
+                  AND (?7 IS NULL OR id < ?7)
+                ORDER BY id DESC
+                LIMIT ?8
+                "#,
+            )?;
+            let mut out = Vec::new();
+            let mut rows = stmt.query(params![
+                query.principal,
+                query.plugin_id,
+                query.tool,
+                query.is_error.map(|e| if e { "error" } else { "ok" }),
+                query.since.map(|t| t.timestamp()),
+                query.until.map(|t| t.timestamp()),
+                query.before_id,
+                query.limit
+            ])?;
+
+            while let Some(row) = rows.next()? {
+                let id: i64 = row.get(0)?;
+                let timestamp: String = row.get(1)?;
+                let Ok(timestamp_utc) = chrono::DateTime::parse_from_rfc3339(&timestamp) else {
+                    tracing::warn!("Skipping malformed tool execution row: {}", id);
+                    continue;
+                };
+                // Codes unknown to this version are reported as internal errors
+                let error_code = row
+                    .get::<_, Option<String>>(6)?
+                    .map(|c| ErrorCode::parse(&c).unwrap_or(ErrorCode::Internal));
+                out.push(models::ToolExecutionRecord {
+                    id,
+                    timestamp_utc: timestamp_utc.with_timezone(&chrono::Utc),
+                    principal: row.get(2)?,
+                    plugin_id: row.get(3)?,
+                    tool: row.get(4)?,
+                    duration_ms: row.get(5)?,
+                    error_code,
+                    args_hash: row.get(7)?,
+                    request_id: row.get(8)?,
+                });
+            }
+            Ok(out)
+        })
+        .await?
+    }
+
+    /// Takes or renews the lease on `task` for `holder` until `ttl` from
+    /// now, unless another holder has an unexpired lease on it.
+    ///
+    /// # Returns
+    ///
+    /// Whether `holder` holds the lease.
+    pub async fn acquire_lease_async(
+        &self,
+        task: &str,
+        holder: &str,
+        ttl: Duration,
+    ) -> Result<bool> {
+        let db_path = self.db_path.clone();
+        let task = task.to_string();
+        let holder = holder.to_string();
+        let now = chrono::Utc::now().timestamp();
+        let expires = now + ttl.as_secs() as i64;
+
+        task::spawn_blocking(move || -> Result<bool> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+
+            // A single statement, so two instances never both take the lease
+            let n = conn.execute(
+                r#"
+                INSERT INTO task_leases(task, holder, expires_epoch)
+                VALUES(?1, ?2, ?3)
+                ON CONFLICT(task) DO UPDATE SET
+                    holder = excluded.holder,
+                    expires_epoch = excluded.expires_epoch
+                WHERE task_leases.holder = excluded.holder OR task_leases.expires_epoch <= ?4
+                "#,
+                params![task, holder, expires, now],
+            )?;
+            Ok(n > 0)
+        })
+        .await?
+    }
+
+    /// Stores a shared MCP session with the initialize request that opened
+    /// it.
+    pub async fn save_mcp_session_async(
+        &self,
+        session_id: &str,
+        initialize_request: &str,
+    ) -> Result<()> {
+        let db_path = self.db_path.clone();
+        let session_id = session_id.to_string();
+        let initialize_request = initialize_request.to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+            conn.execute(
+                r#"
+                INSERT OR REPLACE INTO mcp_sessions(session_id, initialize_request, created_epoch, last_seen_epoch)
+                VALUES(?1, ?2, ?3, ?3)
+                "#,
+                params![session_id, initialize_request, now],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Returns the initialize request of a shared MCP session seen within
+    /// `max_idle`, marking it as seen now.
+    pub async fn touch_mcp_session_async(
+        &self,
+        session_id: &str,
+        max_idle: Duration,
+    ) -> Result<Option<String>> {
+        let db_path = self.db_path.clone();
+        let session_id = session_id.to_string();
+        let now = chrono::Utc::now().timestamp();
+        let cutoff = now - max_idle.as_secs() as i64;
+
+        task::spawn_blocking(move || -> Result<Option<String>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+            let updated = conn.execute(
+                r#"UPDATE mcp_sessions SET last_seen_epoch = ?2 WHERE session_id = ?1 AND last_seen_epoch > ?3"#,
+                params![session_id, now, cutoff],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            match conn.query_row(
+                r#"SELECT initialize_request FROM mcp_sessions WHERE session_id = ?1"#,
+                params![session_id],
+                |row| row.get(0),
+            ) {
+                Ok(request) => Ok(Some(request)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+        .await?
+    }
+
+    /// Deletes a shared MCP session.
+    pub async fn delete_mcp_session_async(&self, session_id: &str) -> Result<()> {
+        let db_path = self.db_path.clone();
+        let session_id = session_id.to_string();
+
+        task::spawn_blocking(move || -> Result<()> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+            conn.execute(
+                r#"DELETE FROM mcp_sessions WHERE session_id = ?1"#,
+                params![session_id],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Deletes the shared MCP sessions not seen within `max_idle`.
+    ///
+    /// # Returns
+    ///
+    /// The number of sessions deleted.
+    pub async fn purge_idle_mcp_sessions_async(&self, max_idle: Duration) -> Result<usize> {
+        let db_path = self.db_path.clone();
+        let cutoff = chrono::Utc::now().timestamp() - max_idle.as_secs() as i64;
+
+        task::spawn_blocking(move || -> Result<usize> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+            conn.pragma_update(None, "busy_timeout", 5000i64).ok();
+            let n = conn.execute(
+                r#"DELETE FROM mcp_sessions WHERE last_seen_epoch <= ?1"#,
+                params![cutoff],
+            )?;
+            Ok(n)
+        })
+        .await?
+    }
+
+    /// Lists the persisted tool call counters.
+    pub async fn list_tool_usage_async(&self) -> Result<Vec<models::ToolUsageRecord>> {
+        let db_path = self.db_path.clone();
+
+        task::spawn_blocking(move || -> Result<Vec<models::ToolUsageRecord>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT owner, plugin_id, tool, calls, errors, total_latency_ms, last_call_epoch
+                FROM tool_usage
+                ORDER BY owner, plugin_id, tool
+                "#,
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(models::ToolUsageRecord {
+                    owner: row.get(0)?,
+                    plugin_id: row.get(1)?,
+                    tool: row.get(2)?,
+                    calls: row.get::<_, i64>(3)?.max(0) as u64,
+                    errors: row.get::<_, i64>(4)?.max(0) as u64,
+                    total_latency_ms: row.get(5)?,
+                    last_call_utc: row
+                        .get::<_, Option<i64>>(6)?
+                        .and_then(|t| chrono::DateTime::from_timestamp(t, 0)),
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+        })
+        .await?
+    }
+
+    /// Counts unexpired sessions per principal global id.
+    ///
+    /// Malformed rows are skipped with a warning.
+    pub async fn count_active_sessions_async(&self) -> Result<BTreeMap<String, u64>> {
+        let db_path = self.db_path.clone();
+        let cipher = self.session_cipher.clone();
+        let now = chrono::Utc::now().timestamp();
+
+        task::spawn_blocking(move || -> Result<BTreeMap<String, u64>> {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("opening sqlite db at {}", db_path.display()))?;
+
+            let mut stmt = conn.prepare(
+                r#"SELECT session_id, principal_json, expiry_epoch, is_admin, client_binding FROM sessions WHERE expiry_epoch > ?1"#,
+            )?;
+            let mut rows = stmt.query(params![now])?;
+            let mut out = BTreeMap::new();
+            while let Some(row) = rows.next()? {
+                let sid: String = row.get(0)?;
+                let (expiry_epoch, is_admin, client_binding) =
+                    (row.get(2)?, row.get(3)?, row.get(4)?);
+                match open_principal(cipher.as_ref(), row.get(1)?).and_then(|principal_json| {
+                    models::SessionRecord::from_db_row(
+                        sid.clone(),
+                        principal_json,
+                        expiry_epoch,
+                        is_admin,
+                        client_binding,
+                    )
+                }) {
+                    Ok(rec) => *out.entry(rec.principal.global_id()).or_insert(0) += 1,
+                    Err(e) => tracing::warn!(error=%e, "Skipping malformed session row: {}", sid),
+                }
+            }
+            Ok(out)
+        })
+        .await?
+    }
+}
+
+/// Resolves the default database file path.
+///
+/// Checks for the `ARK_DB_PATH` environment variable first, then falls back
+/// to platform-specific default locations:
+///
+/// - Windows: `%PROGRAMDATA%\ark\ark.db`
+/// - Unix/Linux: `/var/ark/ark.db`
+///
+/// # Returns
+///
+/// The resolved database file path.
+pub fn resolve_db_path() -> Result<PathBuf> {
+    if let Ok(p) = env::var("ARK_DB_PATH") {
+        return Ok(PathBuf::from(p));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let program_data =
+            env::var("PROGRAMDATA").unwrap_or_else(|_| r"C:\ProgramData".to_string());
+        Ok(Path::new(&program_data).join("ark").join("ark.db"))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        return Ok(PathBuf::from("/var/ark/ark.db"));
+    }
+}
+
+/// Ensures the parent directory of the given path exists with secure permissions.
+///
+/// Creates all parent directories if they don't exist and sets secure
+/// permissions using the utility functions.
+///
+/// # Arguments
+///
+/// * `path` - The file path whose parent directory should be ensured
+///
+/// # Returns
+///
+/// `Ok(())` if the directory exists or was created successfully.
+///
+/// # Errors
+///
+/// Returns an error if directory creation or permission setting fails.
+fn ensure_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating parent dir {}", parent.display()))?;
+
+        // Quick writability check: try to create and remove a temp file to
+        // ensure we have permission to write into this directory. This gives
+        // a clearer error earlier than relying on SQLite errors such as
+        // "attempt to write a readonly database".
+        let test_file = parent.join(".ark_write_test");
+        match std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&test_file)
+        {
+            Ok(mut f) => {
+                use std::io::Write;
+                // try write and flush a small payload then remove file
+                if let Err(e) = f.write_all(b"ok") {
+                    let _ = std::fs::remove_file(&test_file);
+                    return Err(anyhow::anyhow!(
+                        "parent dir not writable {}: {}",
+                        parent.display(),
+                        e
+                    ));
+                }
+                let _ = std::fs::remove_file(&test_file);
+            }
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "parent dir not writable {}: {}",
+                    parent.display(),
+                    e
+                ));
+            }
+        }
+
+        // Set secure permissions on the ark directory. Failures here are
+        // non-fatal for usability in some environments, but we return the
+        // error so callers can decide. The caller (e.g., main) will log and
         // continue without persistence if permission hardening fails.
         if let Err(e) = set_secure_dir_permissions(parent) {
             return Err(anyhow::anyhow!(
@@ -1054,3 +2890,103 @@ fn ensure_parent_dir(path: &Path) -> Result<()> {
     }
     Ok(())
 }
+
+/// Returns the principal JSON of a session row, opening it with `cipher` when
+/// it was sealed. Rows saved without a cipher hold the JSON object as is.
+fn open_principal(cipher: Option<&Cipher>, stored: String) -> Result<String> {
+    if stored.starts_with('{') {
+        return Ok(stored);
+    }
+    let cipher = cipher.context("session principal is encrypted but no encryption key is set")?;
+    let json = cipher
+        .open(&stored)
+        .context("session principal cannot be decrypted (encryption key changed?)")?;
+    String::from_utf8(json).context("decrypted session principal is not UTF-8")
+}
+
+/// Plugin payloads up to this size are stored in the `plugin_blobs` table,
+/// larger ones in the blob directory.
+const INLINE_PLUGIN_MAX_BYTES: usize = 64 * 1024;
+
+/// Adds a reference to the payload `bytes`, storing it if no plugin uses it
+/// yet, and returns its SHA-256 digest.
+fn store_blob(conn: &Connection, dir: &Path, bytes: &[u8]) -> Result<String> {
+    let digest = hex::encode(Sha256::digest(bytes));
+    let referenced = conn.execute(
+        r#"UPDATE plugin_blobs SET ref_count = ref_count + 1 WHERE digest = ?1"#,
+        params![digest],
+    )?;
+    if referenced == 0 {
+        let data = if bytes.len() > INLINE_PLUGIN_MAX_BYTES {
+            write_blob(dir, &digest, bytes)?;
+            None
+        } else {
+            Some(bytes)
+        };
+        conn.execute(
+            r#"INSERT INTO plugin_blobs(digest, data, ref_count) VALUES(?1, ?2, 1)"#,
+            params![digest, data],
+        )?;
+    }
+    Ok(digest)
+}
+
+/// Drops a reference to the payload named `digest`, removing the payload once
+/// no plugin uses it.
+fn release_blob(conn: &Connection, dir: &Path, digest: &str) -> Result<()> {
+    conn.execute(
+        r#"UPDATE plugin_blobs SET ref_count = ref_count - 1 WHERE digest = ?1"#,
+        params![digest],
+    )?;
+    let removed = conn.execute(
+        r#"DELETE FROM plugin_blobs WHERE digest = ?1 AND ref_count <= 0"#,
+        params![digest],
+    )?;
+    if removed > 0 {
+        match fs::remove_file(dir.join(digest)) {
+            Ok(()) => tracing::trace!("Removed plugin blob {}", digest),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!(error=%e, "Cannot remove plugin blob {}", digest),
+        }
+    }
+    Ok(())
+}
+
+/// Writes `bytes` to the blob directory under their `digest`. A blob that
+/// already exists is left as is.
+fn write_blob(dir: &Path, digest: &str, bytes: &[u8]) -> Result<()> {
+    let path = dir.join(digest);
+    if path.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir).with_context(|| format!("creating blob dir {}", dir.display()))?;
+    set_secure_dir_permissions(dir)?;
+    // Written under a temporary name first so a reader never sees half a blob
+    let partial = dir.join(format!(".{digest}.{}", std::process::id()));
+    fs::write(&partial, bytes)
+        .with_context(|| format!("writing plugin blob {}", partial.display()))?;
+    set_secure_file_permissions(&partial)?;
+    fs::rename(&partial, &path)
+        .with_context(|| format!("writing plugin blob {}", path.display()))?;
+    Ok(())
+}
+
+/// Returns the payload of a plugin row: `data` when stored in the database,
+/// otherwise the blob named `digest`. A missing or corrupt blob is logged and
+/// yields no payload, so the plugin is loaded from its path instead.
+fn read_payload(dir: &Path, data: Option<Vec<u8>>, digest: Option<String>) -> Option<Vec<u8>> {
+    let Some(digest) = digest.filter(|_| data.is_none()) else {
+        return data;
+    };
+    match fs::read(dir.join(&digest)) {
+        Ok(bytes) if hex::encode(Sha256::digest(&bytes)) == digest => Some(bytes),
+        Ok(_) => {
+            tracing::warn!("Plugin blob {} does not match its digest", digest);
+            None
+        }
+        Err(e) => {
+            tracing::warn!(error=%e, "Cannot read plugin blob {}", digest);
+            None
+        }
+    }
+}
@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, UNIX_EPOCH};
 
+use crate::errors::ErrorCode;
+
 /// A plugin record stored in the database.
 ///
 /// Represents a plugin with its metadata, ownership, and creation timestamp.
@@ -75,6 +77,9 @@ pub struct SessionRecord {
     pub expiry_epoch: i64,
     /// Admin flag persisted for older rows or explicit overrides.
     pub is_admin: bool,
+    /// Hash of the client attributes the session is bound to, if any.
+    #[serde(default)]
+    pub client_binding: Option<String>,
 }
 
 impl SessionRecord {
@@ -87,6 +92,7 @@ impl SessionRecord {
         principal_json: String,
         expiry_epoch: i64,
         is_admin_opt: Option<i64>,
+        client_binding: Option<String>,
     ) -> Result<Self> {
         let mut principal: crate::server::auth::Principal =
             serde_json::from_str(&principal_json).context("parsing principal JSON from DB")?;
@@ -106,6 +112,525 @@ impl SessionRecord {
             expiry_utc,
             expiry_epoch,
             is_admin,
+            client_binding,
+        })
+    }
+}
+
+/// An API key (personal access token) record stored in the database.
+///
+/// Only the SHA-256 hash of the token is persisted; the plaintext token is
+/// returned to the caller exactly once at creation time. The `principal`
+/// is a snapshot of the creating user, so roles are fixed for the key's life.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    /// Public key identifier used for listing and revocation.
+    pub key_id: String,
+    /// Hex-encoded SHA-256 hash of the token.
+    pub token_hash: String,
+    /// Owner identifier (from `Principal::global_id()`).
+    pub owner: String,
+    /// Friendly name given by the user.
+    pub name: String,
+    /// Principal the key authenticates as.
+    pub principal: crate::server::auth::Principal,
+    /// Granted scopes (e.g. "api", "mcp").
+    pub scopes: Vec<String>,
+    /// UTC timestamp when the key was created.
+    pub created_utc: chrono::DateTime<chrono::Utc>,
+    /// Optional expiration time; `None` means the key does not expire.
+    pub expiry_utc: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ApiKeyRecord {
+    /// Construct an ApiKeyRecord from raw database column values.
+    ///
+    /// Parses the `principal_json`, the space-separated `scopes` column and
+    /// the optional `expiry_epoch`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_db_row(
+        key_id: String,
+        token_hash: String,
+        owner: String,
+        name: String,
+        principal_json: String,
+        scopes: String,
+        created_utc_str: String,
+        expiry_epoch: Option<i64>,
+    ) -> Result<Self> {
+        let principal: crate::server::auth::Principal =
+            serde_json::from_str(&principal_json).context("parsing principal JSON from DB")?;
+        let created_utc = chrono::DateTime::parse_from_rfc3339(&created_utc_str)
+            .context("parsing created_utc from DB")?
+            .with_timezone(&chrono::Utc);
+        let expiry_utc = expiry_epoch.map(|epoch| {
+            chrono::DateTime::<chrono::Utc>::from(UNIX_EPOCH + Duration::from_secs(epoch as u64))
+        });
+
+        Ok(ApiKeyRecord {
+            key_id,
+            token_hash,
+            owner,
+            name,
+            principal,
+            scopes: scopes.split_whitespace().map(|s| s.to_string()).collect(),
+            created_utc,
+            expiry_utc,
+        })
+    }
+
+    /// Returns true if the key has an expiry in the past.
+    pub fn is_expired(&self) -> bool {
+        self.expiry_utc
+            .is_some_and(|expiry| chrono::Utc::now() >= expiry)
+    }
+}
+
+/// A service account record stored in the database.
+///
+/// Service accounts are non-human principals created by administrators for
+/// automation (e.g. CI pipelines publishing plugins). They authenticate with
+/// the OAuth client-credentials grant (using `secret_hash`) or with API keys
+/// issued to them, and own plugins under their own global id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceAccountRecord {
+    /// Account identifier; used as the principal subject and OAuth client id.
+    pub account_id: String,
+    /// Optional free-form description.
+    pub description: Option<String>,
+    /// Roles granted to the account.
+    pub roles: Vec<crate::server::roles::Role>,
+    /// Hex-encoded SHA-256 hash of the client secret, if client credentials are enabled.
+    pub secret_hash: Option<String>,
+    /// Global id of the administrator who created the account.
+    pub created_by: String,
+    /// UTC timestamp when the account was created.
+    pub created_utc: chrono::DateTime<chrono::Utc>,
+}
+
+impl ServiceAccountRecord {
+    /// Construct a ServiceAccountRecord from raw database column values.
+    pub fn from_db_row(
+        account_id: String,
+        description: Option<String>,
+        roles_json: String,
+        secret_hash: Option<String>,
+        created_by: String,
+        created_utc_str: String,
+    ) -> Result<Self> {
+        let roles = serde_json::from_str(&roles_json).context("parsing roles JSON from DB")?;
+        let created_utc = chrono::DateTime::parse_from_rfc3339(&created_utc_str)
+            .context("parsing created_utc from DB")?
+            .with_timezone(&chrono::Utc);
+
+        Ok(ServiceAccountRecord {
+            account_id,
+            description,
+            roles,
+            secret_hash,
+            created_by,
+            created_utc,
+        })
+    }
+
+    /// Builds the principal this account authenticates as.
+    pub fn principal(&self) -> crate::server::auth::Principal {
+        crate::server::auth::Principal {
+            subject: self.account_id.clone(),
+            email: None,
+            name: self.description.clone(),
+            picture: None,
+            provider: crate::server::auth::SERVICE_ACCOUNT_PROVIDER.to_string(),
+            provider_kind: crate::server::auth::ProviderKind::Service,
+            provider_name: None,
+            tenant_id: None,
+            oid: None,
+            groups: vec![],
+            roles: self.roles.clone(),
+            is_admin: self.roles.contains(&crate::server::roles::Role::Admin),
+        }
+    }
+}
+
+/// An OAuth client registered through dynamic client registration (RFC 7591).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthClientRecord {
+    /// Client identifier issued at registration.
+    pub client_id: String,
+    /// Hex-encoded SHA-256 hash of the client secret; `None` for public clients.
+    pub secret_hash: Option<String>,
+    /// Metadata the client registered.
+    pub metadata: OAuthClientMetadata,
+    /// UTC timestamp when the client registered.
+    pub created_utc: chrono::DateTime<chrono::Utc>,
+}
+
+impl OAuthClientRecord {
+    /// Construct an OAuthClientRecord from raw database column values.
+    pub fn from_db_row(
+        client_id: String,
+        secret_hash: Option<String>,
+        metadata_json: String,
+        created_utc_str: String,
+    ) -> Result<Self> {
+        let metadata =
+            serde_json::from_str(&metadata_json).context("parsing client metadata JSON from DB")?;
+        let created_utc = chrono::DateTime::parse_from_rfc3339(&created_utc_str)
+            .context("parsing created_utc from DB")?
+            .with_timezone(&chrono::Utc);
+
+        Ok(OAuthClientRecord {
+            client_id,
+            secret_hash,
+            metadata,
+            created_utc,
         })
     }
 }
+
+/// Client metadata of a registration request (RFC 7591 section 2); other
+/// fields are ignored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OAuthClientMetadata {
+    /// Redirect URIs authorization responses may be sent to.
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// How the client authenticates at the token endpoint: `none` (public
+    /// client), `client_secret_basic` or `client_secret_post`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_method: Option<String>,
+    /// Grant types the client uses.
+    #[serde(default)]
+    pub grant_types: Vec<String>,
+    /// Response types the client uses.
+    #[serde(default)]
+    pub response_types: Vec<String>,
+    /// Human-readable name of the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    /// Home page of the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_uri: Option<String>,
+    /// Space-separated scopes the client may request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Identifier of the client software.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software_id: Option<String>,
+    /// Version of the client software.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software_version: Option<String>,
+}
+
+/// The IdP refresh token held for a renewable session.
+///
+/// Stored in its own table keyed by `session_id`; the token is sealed with
+/// [`crate::server::crypto::Cipher`] and never persisted in plaintext.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRefreshRecord {
+    /// Session identifier (matches `sessions.session_id`).
+    pub session_id: String,
+    /// Encrypted refresh token.
+    pub refresh_token_enc: String,
+    /// Epoch seconds after which the session must be renewed at the IdP
+    /// (the expiry of the access token issued alongside the refresh token).
+    pub renew_after_epoch: i64,
+}
+
+/// A local user account stored in the database.
+///
+/// Used by the `local` auth provider. Passwords are stored as argon2 PHC
+/// strings; the plaintext is never persisted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRecord {
+    /// Login name; also the principal subject.
+    pub username: String,
+    /// Argon2 password hash in PHC string format.
+    pub password_hash: String,
+    /// Optional display name.
+    pub display_name: Option<String>,
+    /// Optional email address.
+    pub email: Option<String>,
+    /// Local group memberships, matched against the provider's `groups` config.
+    pub groups: Vec<String>,
+    /// Whether the user is an administrator regardless of group membership.
+    pub is_admin: bool,
+    /// UTC timestamp when the user was created.
+    pub created_utc: chrono::DateTime<chrono::Utc>,
+}
+
+impl UserRecord {
+    /// Construct a UserRecord from raw database column values.
+    pub fn from_db_row(
+        username: String,
+        password_hash: String,
+        display_name: Option<String>,
+        email: Option<String>,
+        groups_json: String,
+        is_admin: i64,
+        created_utc_str: String,
+    ) -> Result<Self> {
+        let groups = serde_json::from_str(&groups_json).context("parsing groups JSON from DB")?;
+        let created_utc = chrono::DateTime::parse_from_rfc3339(&created_utc_str)
+            .context("parsing created_utc from DB")?
+            .with_timezone(&chrono::Utc);
+
+        Ok(UserRecord {
+            username,
+            password_hash,
+            display_name,
+            email,
+            groups,
+            is_admin: is_admin != 0,
+            created_utc,
+        })
+    }
+}
+
+/// An authentication event in the audit trail.
+///
+/// Written for sign-ins, sign-outs, token issuance and failed attempts; rows
+/// are append-only and identified by an increasing `id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthEventRecord {
+    /// Row identifier assigned by the database (ignored when saving).
+    pub id: i64,
+    /// UTC timestamp of the event.
+    pub timestamp_utc: chrono::DateTime<chrono::Utc>,
+    /// Event type (e.g. "login", "login_failed", "token_issued").
+    pub event: String,
+    /// Identity provider kind (e.g. "local", "oidc", "service").
+    pub provider: Option<String>,
+    /// Principal global id, or the username given in a failed attempt.
+    pub subject: Option<String>,
+    /// Client IP address.
+    pub ip: Option<String>,
+    /// Client `User-Agent` header.
+    pub user_agent: Option<String>,
+    /// Free-form detail such as the grant type or failure reason.
+    pub detail: Option<String>,
+    /// Id of the HTTP request that produced the event.
+    pub request_id: Option<String>,
+}
+
+impl AuthEventRecord {
+    /// Construct an AuthEventRecord from raw database column values.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_db_row(
+        id: i64,
+        timestamp_utc_str: String,
+        event: String,
+        provider: Option<String>,
+        subject: Option<String>,
+        ip: Option<String>,
+        user_agent: Option<String>,
+        detail: Option<String>,
+        request_id: Option<String>,
+    ) -> Result<Self> {
+        let timestamp_utc = chrono::DateTime::parse_from_rfc3339(&timestamp_utc_str)
+            .context("parsing timestamp_utc from DB")?
+            .with_timezone(&chrono::Utc);
+
+        Ok(AuthEventRecord {
+            id,
+            timestamp_utc,
+            event,
+            provider,
+            subject,
+            ip,
+            user_agent,
+            detail,
+            request_id,
+        })
+    }
+}
+
+/// Filters for listing authentication events; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuthEventQuery {
+    /// Only events of this type.
+    pub event: Option<String>,
+    /// Only events for this subject.
+    pub subject: Option<String>,
+    /// Only events from this provider kind.
+    pub provider: Option<String>,
+    /// Only events produced by this request.
+    pub request_id: Option<String>,
+    /// Only events at or after this time.
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only events before this time.
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Maximum number of events returned, newest first.
+    pub limit: u32,
+}
+
+/// Persisted tool call counters for one tool of one plugin owner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolUsageRecord {
+    /// Owner of the plugin providing the tool (`*/*/*` for shared plugins).
+    pub owner: String,
+    /// Plugin providing the tool.
+    pub plugin_id: String,
+    /// Tool name.
+    pub tool: String,
+    /// Number of calls.
+    pub calls: u64,
+    /// Number of calls that failed.
+    pub errors: u64,
+    /// Sum of the call latencies, in milliseconds.
+    pub total_latency_ms: f64,
+    /// Time of the latest call.
+    pub last_call_utc: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A tool call, as added to the usage counters and analytics.
+#[derive(Debug, Clone)]
+pub struct ToolCallRecord {
+    /// When the call completed.
+    pub timestamp_utc: chrono::DateTime<chrono::Utc>,
+    /// Owner of the plugin providing the tool.
+    pub owner: String,
+    /// Plugin providing the tool.
+    pub plugin_id: String,
+    /// Tool name.
+    pub tool: String,
+    /// Global id of the caller; `None` for unauthenticated calls.
+    pub principal: Option<String>,
+    /// Whether the call failed.
+    pub is_error: bool,
+    /// Call latency in milliseconds.
+    pub latency_ms: f64,
+    /// Size of the result returned to the caller, in bytes.
+    pub bytes_returned: u64,
+}
+
+/// Usage of one principal in one calendar month (UTC), as counted for
+/// quotas and chargeback.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrincipalUsageRecord {
+    /// Principal global id.
+    pub principal: String,
+    /// Month, as `YYYY-MM`.
+    pub month: String,
+    /// Number of tool calls.
+    pub calls: u64,
+    /// Sum of the call durations, in milliseconds.
+    pub compute_ms: f64,
+    /// Sum of the result sizes returned, in bytes.
+    pub bytes_returned: u64,
+}
+
+impl PrincipalUsageRecord {
+    /// The month `time` is counted in, as `YYYY-MM`.
+    pub fn month_of(time: chrono::DateTime<chrono::Utc>) -> String {
+        time.format("%Y-%m").to_string()
+    }
+}
+
+/// One tool invocation in the execution history.
+#[derive(Debug, Clone)]
+pub struct ToolExecutionRecord {
+    /// Row id, increasing with each call; 0 before the record is saved.
+    pub id: i64,
+    /// When the call completed.
+    pub timestamp_utc: chrono::DateTime<chrono::Utc>,
+    /// Global id of the caller; `None` for unauthenticated calls.
+    pub principal: Option<String>,
+    /// Plugin providing the tool.
+    pub plugin_id: String,
+    /// Tool name.
+    pub tool: String,
+    /// Call duration in milliseconds.
+    pub duration_ms: f64,
+    /// Why the call failed; `None` when it succeeded.
+    pub error_code: Option<ErrorCode>,
+    /// Truncated SHA-256 of the call's arguments, which are not stored.
+    pub args_hash: String,
+    /// Id of the request that made the call.
+    pub request_id: Option<String>,
+}
+
+impl ToolExecutionRecord {
+    /// `"ok"` or `"error"`, as stored and reported.
+    pub fn status(&self) -> &'static str {
+        match self.error_code {
+            None => "ok",
+            Some(_) => "error",
+        }
+    }
+}
+
+/// Filters for listing tool executions; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct ToolExecutionQuery {
+    /// Only calls made by this principal global id.
+    pub principal: Option<String>,
+    /// Only calls to tools of this plugin.
+    pub plugin_id: Option<String>,
+    /// Only calls to this tool.
+    pub tool: Option<String>,
+    /// Only failed (`Some(true)`) or successful (`Some(false)`) calls.
+    pub is_error: Option<bool>,
+    /// Only calls at or after this time.
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only calls before this time.
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only calls with a lower id, to page through older history.
+    pub before_id: Option<i64>,
+    /// Maximum number of calls returned, newest first.
+    pub limit: u32,
+}
+
+/// Upper bounds, in milliseconds, of the latency histogram kept with each
+/// rollup. A last bucket counts the slower calls.
+pub const ROLLUP_LATENCY_BOUNDS_MS: [f64; 14] = [
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0, 60000.0,
+    300000.0,
+];
+
+/// Index of the histogram bucket counting a call of `latency_ms`.
+pub fn latency_bucket(latency_ms: f64) -> usize {
+    ROLLUP_LATENCY_BOUNDS_MS
+        .iter()
+        .position(|bound| latency_ms <= *bound)
+        .unwrap_or(ROLLUP_LATENCY_BOUNDS_MS.len())
+}
+
+/// Calls of one tool by one principal during one hour.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolUsageRollup {
+    /// Start of the hour.
+    pub bucket_utc: chrono::DateTime<chrono::Utc>,
+    /// Plugin providing the tool.
+    pub plugin_id: String,
+    /// Tool name.
+    pub tool: String,
+    /// Global id of the caller; `None` for unauthenticated calls.
+    pub principal: Option<String>,
+    /// Number of calls.
+    pub calls: u64,
+    /// Number of calls that failed.
+    pub errors: u64,
+    /// Sum of the call latencies, in milliseconds.
+    pub total_latency_ms: f64,
+    /// Calls per latency bucket, see [`ROLLUP_LATENCY_BOUNDS_MS`].
+    pub latency_histogram: Vec<u64>,
+}
+
+/// Display preferences a user keeps for the console.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserPreferences {
+    /// Color theme: "light", "dark" or "system".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// BCP 47 language tag for dates and numbers, such as "en-US".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// IANA time zone, such as "Europe/Bucharest".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    /// Rows per page in console tables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
@@ -0,0 +1,149 @@
+//! Reverse proxies trusted to report the client's address, scheme and host.
+//!
+//! Requests from an address in `trusted_proxies` have their
+//! `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers
+//! resolved into a [`Forwarded`] extension, which client IP lookups and the
+//! URLs built for OAuth and SAML callbacks use. Once proxies are configured,
+//! these headers are removed from requests of any other peer so clients
+//! cannot spoof them.
+
+use std::net::{IpAddr, SocketAddr};
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Extensions, HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::server::metrics_auth::IpRange;
+
+const FORWARDED_FOR: &str = "x-forwarded-for";
+const FORWARDED_PROTO: &str = "x-forwarded-proto";
+const FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// What a trusted proxy reported about the original request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forwarded {
+    /// Address of the client, the nearest untrusted hop.
+    pub client: IpAddr,
+    /// Scheme the client used ("http" or "https").
+    pub proto: Option<String>,
+    /// Host the client requested, with the port if any.
+    pub host: Option<String>,
+}
+
+/// Validated form of the `trusted_proxies` setting.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<IpRange>,
+}
+
+impl TrustedProxies {
+    /// Parses addresses and CIDR ranges, rejecting malformed entries.
+    pub fn from_config(entries: &[String]) -> anyhow::Result<Self> {
+        let ranges = entries
+            .iter()
+            .map(|s| IpRange::parse(s).context("Invalid trusted_proxies entry"))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { ranges })
+    }
+
+    /// Whether no proxy is trusted.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Whether `ip` belongs to a trusted proxy.
+    pub fn trusts(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|r| r.contains(ip))
+    }
+
+    /// Resolves the forwarded headers of a request from `peer`, or `None`
+    /// when the peer is not a trusted proxy.
+    ///
+    /// `X-Forwarded-For` is read from the right, skipping trusted proxies,
+    /// since only the entries appended by them can be relied on.
+    pub fn resolve(&self, headers: &HeaderMap, peer: IpAddr) -> Option<Forwarded> {
+        if !self.trusts(peer) {
+            return None;
+        }
+        let hops: Vec<&str> = headers
+            .get_all(FORWARDED_FOR)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .collect();
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            match hop.trim().parse() {
+                Ok(ip) => client = ip,
+                Err(_) => break,
+            }
+            if !self.trusts(client) {
+                break;
+            }
+        }
+        let proto = first_value(headers, FORWARDED_PROTO)
+            .map(|p| p.to_ascii_lowercase())
+            .filter(|p| p == "http" || p == "https");
+        let host = first_value(headers, FORWARDED_HOST).map(str::to_string);
+        Some(Forwarded {
+            client,
+            proto,
+            host,
+        })
+    }
+}
+
+/// Returns the client IP reported by a trusted proxy, or else the address
+/// of the connection.
+pub fn client_ip(extensions: &Extensions) -> Option<IpAddr> {
+    match extensions.get::<Forwarded>() {
+        Some(forwarded) => Some(forwarded.client),
+        None => extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip()),
+    }
+}
+
+/// Returns the first comma-separated entry of a header, if not empty.
+fn first_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// Middleware resolving the forwarded headers of requests from trusted
+/// proxies, and dropping them from everyone else's.
+pub async fn resolve_forwarded(
+    proxies: &TrustedProxies,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    if !proxies.is_empty() {
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        match peer.and_then(|peer| proxies.resolve(req.headers(), peer)) {
+            Some(forwarded) => {
+                req.extensions_mut().insert(forwarded);
+            }
+            None => {
+                let headers = req.headers_mut();
+                for name in [FORWARDED_FOR, FORWARDED_PROTO, FORWARDED_HOST] {
+                    headers.remove(name);
+                }
+            }
+        }
+    }
+    next.run(req).await
+}
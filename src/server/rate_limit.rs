@@ -0,0 +1,232 @@
+//! Request rate limiting for the management API.
+//!
+//! Requests are sorted into classes (sign-in, plugin changes and tool
+//! execution), each with its own budget per client. Budgets are token
+//! buckets: a client may send `burst` requests at once, after which its
+//! budget refills at `per_minute`. Clients are identified by their principal
+//! when authenticated and by IP otherwise.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::config::models::{RateLimitConfig, RateLimitsConfig};
+use crate::errors::ErrorCode;
+use crate::server::auth::{AuthState, Principal};
+use crate::server::service::{StandardizedResponse, unversioned_api_path};
+
+/// How often idle buckets are dropped.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Class of endpoints sharing a request budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitClass {
+    /// Sign-in, OAuth token, client registration and device flow endpoints.
+    Auth,
+    /// Creating, deleting and importing plugins.
+    PluginMutation,
+    /// Executing tools through the management API.
+    ToolExecution,
+}
+
+impl RateLimitClass {
+    /// Returns the class of a request, or `None` for requests that are not
+    /// rate limited.
+    pub fn of(method: &Method, path: &str) -> Option<Self> {
+        let path = unversioned_api_path(path);
+        let path = path.as_ref();
+        if crate::server::authz::is_tool_execution(method, path) {
+            return Some(Self::ToolExecution);
+        }
+        let mutates = !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+        if mutates && (path == "/api/plugins" || path.starts_with("/api/plugins/")) {
+            return Some(Self::PluginMutation);
+        }
+        let sign_in = matches!(
+            path,
+            "/authorize" | "/token" | "/device_authorization" | "/device" | "/register"
+        ) || (path.starts_with("/auth/")
+            && !matches!(path, "/auth/status" | "/auth/.well-known/jwks.json"));
+        sign_in.then_some(Self::Auth)
+    }
+
+    /// Name used in metrics and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::PluginMutation => "plugin_mutation",
+            Self::ToolExecution => "tool_execution",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Budget of one class, in tokens.
+#[derive(Debug, Clone, Copy)]
+struct Budget {
+    capacity: f64,
+    per_second: f64,
+}
+
+impl Budget {
+    fn new(config: &RateLimitConfig) -> Self {
+        let per_minute = config.per_minute.max(1);
+        Self {
+            capacity: f64::from(config.burst.unwrap_or(per_minute).max(1)),
+            per_second: f64::from(per_minute) / 60.0,
+        }
+    }
+}
+
+fn budgets(config: &RateLimitsConfig) -> HashMap<RateLimitClass, Budget> {
+    [
+        (RateLimitClass::Auth, &config.auth),
+        (RateLimitClass::PluginMutation, &config.plugin_mutations),
+        (RateLimitClass::ToolExecution, &config.tool_execution),
+    ]
+    .into_iter()
+    .filter_map(|(class, limit)| Some((class, Budget::new(limit.as_ref()?))))
+    .collect()
+}
+
+/// Tracks the request budgets of clients and decides when to refuse requests.
+#[derive(Debug)]
+pub struct RateLimiter {
+    budgets: RwLock<HashMap<RateLimitClass, Budget>>,
+    buckets: Mutex<HashMap<(RateLimitClass, String), Bucket>>,
+    last_purge: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitsConfig) -> Self {
+        Self {
+            budgets: RwLock::new(budgets(config)),
+            buckets: Mutex::new(HashMap::new()),
+            last_purge: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Replaces the budgets, e.g. after a configuration reload. Clients
+    /// start over with a full budget.
+    pub fn reconfigure(&self, config: &RateLimitsConfig) {
+        *self.budgets.write().unwrap_or_else(|e| e.into_inner()) = budgets(config);
+        self.buckets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Returns whether any class is limited.
+    pub fn is_enabled(&self) -> bool {
+        !self
+            .budgets
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Takes a request from the budget of `client` for `class`. Returns how
+    /// long the client has to wait when the budget is exhausted.
+    pub fn check(&self, class: RateLimitClass, client: &str) -> Option<Duration> {
+        let budget = *self
+            .budgets
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&class)?;
+        let now = Instant::now();
+        self.purge(now);
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets
+            .entry((class, client.to_string()))
+            .or_insert(Bucket {
+                tokens: budget.capacity,
+                updated: now,
+            });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * budget.per_second).min(budget.capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / budget.per_second,
+            ))
+        }
+    }
+
+    /// Drops buckets that have refilled completely, at most once per
+    /// [`PURGE_INTERVAL`].
+    fn purge(&self, now: Instant) {
+        {
+            let mut last = self.last_purge.lock().unwrap_or_else(|e| e.into_inner());
+            if now.duration_since(*last) < PURGE_INTERVAL {
+                return;
+            }
+            *last = now;
+        }
+        let budgets = self.budgets.read().unwrap_or_else(|e| e.into_inner());
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.retain(|(class, _), bucket| {
+            budgets.get(class).is_some_and(|budget| {
+                let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens + elapsed * budget.per_second < budget.capacity
+            })
+        });
+    }
+}
+
+/// Middleware refusing requests over their class budget with 429 Too Many
+/// Requests and a `Retry-After` header.
+///
+/// Runs after authentication, so authenticated clients are limited per
+/// principal rather than per IP.
+pub async fn rate_limit(
+    req: Request<Body>,
+    next: Next,
+    limiter: Arc<RateLimiter>,
+    auth: Arc<AuthState>,
+) -> Response {
+    let Some(class) =
+        RateLimitClass::of(req.method(), req.uri().path()).filter(|_| limiter.is_enabled())
+    else {
+        return next.run(req).await;
+    };
+    let client = match req.extensions().get::<Principal>() {
+        Some(principal) => format!("principal:{}", principal.global_id()),
+        None => match auth.client_info(req.headers(), req.extensions()).ip {
+            Some(ip) => format!("ip:{ip}"),
+            None => "unknown".to_string(),
+        },
+    };
+    let Some(wait) = limiter.check(class, &client) else {
+        return next.run(req).await;
+    };
+    tracing::debug!(
+        "Rate limited {} request from {} for {:?}",
+        class.as_str(),
+        client,
+        wait
+    );
+    crate::metrics::record_rate_limited(class.as_str());
+    let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        StandardizedResponse::as_error(ErrorCode::RateLimited, "Too many requests", None),
+    )
+        .into_response()
+}
@@ -0,0 +1,329 @@
+//! Configuration hot reload.
+//!
+//! On SIGHUP, or `POST /api/admin/reload`, the configuration file is read
+//! again (with the same command line and environment overrides as at
+//! startup) and the settings that are safe to change at runtime are applied:
+//! the configured plugins, CORS origins, log filters and rate limits. Other
+//! changed settings are reported as requiring a restart and keep their
+//! current values.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::Value;
+use tower_http::cors::CorsLayer;
+
+use crate::config::{ArkConfig, ConfigError, plugins::ArkPlugin};
+use crate::server::rate_limit::RateLimiter;
+use crate::state::ArkState;
+
+/// Reads the configuration as at startup.
+pub type ConfigLoader =
+    Arc<dyn Fn() -> BoxFuture<'static, Result<ArkConfig, ConfigError>> + Send + Sync>;
+
+/// Settings that can change at runtime.
+const RELOADABLE: &[&str] = &[
+    "plugins",
+    "management_server.cors",
+    "management_server.rate_limits",
+    "mcp_server.cors",
+    "logging.filters",
+    "quotas",
+];
+
+/// Sections whose fields are compared one by one, so a change to one field
+/// does not mark the whole section as changed.
+const SECTIONS: &[&str] = &["management_server", "mcp_server", "logging"];
+
+/// Settings the servers read on every request, so a reload can change them
+/// in place.
+#[derive(Debug)]
+pub struct LiveSettings {
+    /// CORS policy of the management listener.
+    pub management_cors: Arc<RwLock<Option<CorsLayer>>>,
+    /// CORS policy of the MCP listener.
+    pub mcp_cors: Arc<RwLock<Option<CorsLayer>>>,
+    /// Rate limiter of the management API.
+    pub rate_limiter: Arc<RateLimiter>,
+}
+
+impl LiveSettings {
+    pub fn new(config: &ArkConfig) -> Self {
+        Self {
+            management_cors: Arc::new(RwLock::new(management_cors(config))),
+            mcp_cors: Arc::new(RwLock::new(mcp_cors(config))),
+            rate_limiter: Arc::new(RateLimiter::new(&rate_limits(config))),
+        }
+    }
+}
+
+fn management_cors(config: &ArkConfig) -> Option<CorsLayer> {
+    crate::server::service::management_cors(config).map(|c| c.into_layer())
+}
+
+fn mcp_cors(config: &ArkConfig) -> Option<CorsLayer> {
+    crate::server::service::mcp_cors(config).map(|c| c.into_layer())
+}
+
+fn rate_limits(config: &ArkConfig) -> crate::config::models::RateLimitsConfig {
+    config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.rate_limits.clone())
+        .unwrap_or_default()
+}
+
+/// Outcome of a reload.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ReloadReport {
+    /// Changed settings now in effect, e.g. `management_server.cors` or
+    /// `plugins.time`.
+    pub applied: Vec<String>,
+    /// Changed settings that only take effect after a restart.
+    pub restart_required: Vec<String>,
+    /// Changes that could not be applied, e.g. plugins that failed to load.
+    pub errors: Vec<String>,
+}
+
+/// Re-reads the configuration and applies what can change at runtime.
+pub struct ConfigReloader {
+    loader: ConfigLoader,
+    /// Configuration in effect. Settings that need a restart keep their
+    /// startup values here, so they are reported again on the next reload.
+    current: tokio::sync::Mutex<ArkConfig>,
+    /// Settings the servers read on every request.
+    pub live: Arc<LiveSettings>,
+}
+
+impl std::fmt::Debug for ConfigReloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigReloader")
+            .field("live", &self.live)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConfigReloader {
+    /// Creates a reloader for a server started with `config`, which
+    /// `loader` reads again on each reload.
+    pub fn new(loader: ConfigLoader, config: ArkConfig) -> Self {
+        Self {
+            loader,
+            live: Arc::new(LiveSettings::new(&config)),
+            current: tokio::sync::Mutex::new(config),
+        }
+    }
+
+    /// Configuration in effect.
+    pub async fn current(&self) -> ArkConfig {
+        self.current.lock().await.clone()
+    }
+
+    /// Reads the configuration again and applies the runtime-safe changes.
+    /// Concurrent reloads run one after the other.
+    ///
+    /// # Errors
+    /// Returns an error if the configuration cannot be read or parsed; the
+    /// running configuration is then left unchanged.
+    pub async fn reload(&self, state: &Arc<ArkState>) -> Result<ReloadReport, ConfigError> {
+        let new = (self.loader)().await?;
+        let mut current = self.current.lock().await;
+        let mut report = ReloadReport::default();
+
+        for field in changed_fields(&current, &new) {
+            if !RELOADABLE.contains(&field.as_str()) {
+                report.restart_required.push(field);
+                continue;
+            }
+            match field.as_str() {
+                "plugins" => {
+                    current.plugins =
+                        reload_plugins(state, &current.plugins, &new.plugins, &mut report).await;
+                }
+                "management_server.cors" => {
+                    set_section(
+                        &mut current.management_server,
+                        &new.management_server,
+                        |m| &mut m.cors,
+                    );
+                    *write(&self.live.management_cors) = management_cors(&current);
+                    report.applied.push(field);
+                }
+                "management_server.rate_limits" => {
+                    set_section(
+                        &mut current.management_server,
+                        &new.management_server,
+                        |m| &mut m.rate_limits,
+                    );
+                    self.live.rate_limiter.reconfigure(&rate_limits(&current));
+                    report.applied.push(field);
+                }
+                "mcp_server.cors" => {
+                    set_section(&mut current.mcp_server, &new.mcp_server, |m| &mut m.cors);
+                    *write(&self.live.mcp_cors) = mcp_cors(&current);
+                    report.applied.push(field);
+                }
+                "logging.filters" => match crate::logging::reset_filter(new.logging.as_ref()) {
+                    Ok(()) => {
+                        set_section(&mut current.logging, &new.logging, |l| &mut l.filters);
+                        report.applied.push(field);
+                    }
+                    Err(e) => report.errors.push(format!("{field}: {e}")),
+                },
+                "quotas" => {
+                    current.quotas = new.quotas.clone();
+                    state.set_quotas(current.quotas.clone().unwrap_or_default());
+                    report.applied.push(field);
+                }
+                _ => unreachable!("unhandled reloadable setting '{field}'"),
+            }
+        }
+
+        tracing::info!(
+            "Configuration reloaded: applied {:?}, restart required for {:?}",
+            report.applied,
+            report.restart_required
+        );
+        for error in &report.errors {
+            tracing::warn!("Configuration reload: {}", error);
+        }
+        Ok(report)
+    }
+}
+
+fn write<T>(lock: &RwLock<T>) -> std::sync::RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Copies one field of an optional section from `new` into `current`,
+/// creating the section with defaults if needed.
+fn set_section<S, T>(current: &mut Option<S>, new: &Option<S>, field: impl Fn(&mut S) -> &mut T)
+where
+    S: Clone + Default,
+    T: Clone,
+{
+    let mut new = new.clone().unwrap_or_default();
+    let value = field(&mut new).clone();
+    *field(current.get_or_insert_with(S::default)) = value;
+}
+
+/// Names of the settings that differ between two configurations, as
+/// `section` or `section.field`.
+fn changed_fields(old: &ArkConfig, new: &ArkConfig) -> Vec<String> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    let mut changed = Vec::new();
+    for (key, new_value) in &new {
+        let old_value = old.get(key).unwrap_or(&Value::Null);
+        if old_value == new_value {
+            continue;
+        }
+        if !SECTIONS.contains(&key.as_str()) {
+            changed.push(key.clone());
+            continue;
+        }
+        // A missing section compares as one with all fields unset
+        let field = |section: &Value, f: &str| section.get(f).cloned().unwrap_or(Value::Null);
+        let fields: std::collections::BTreeSet<String> = [old_value, new_value]
+            .into_iter()
+            .filter_map(Value::as_object)
+            .flat_map(|s| s.keys().cloned())
+            .collect();
+        changed.extend(
+            fields
+                .into_iter()
+                .filter(|f| field(old_value, f) != field(new_value, f))
+                .map(|f| format!("{key}.{f}")),
+        );
+    }
+    changed
+}
+
+/// Loads added and changed plugins and unregisters removed ones. Plugins
+/// that fail to load keep their previous version. Returns the plugins now in
+/// effect.
+async fn reload_plugins(
+    state: &Arc<ArkState>,
+    old: &[ArkPlugin],
+    new: &[ArkPlugin],
+    report: &mut ReloadReport,
+) -> Vec<ArkPlugin> {
+    let as_map = |plugins: &[ArkPlugin]| -> BTreeMap<String, Value> {
+        plugins
+            .iter()
+            .map(|p| {
+                (
+                    p.name.clone(),
+                    serde_json::to_value(p).unwrap_or(Value::Null),
+                )
+            })
+            .collect()
+    };
+    let (old_map, new_map) = (as_map(old), as_map(new));
+
+    for name in old_map.keys().filter(|n| !new_map.contains_key(*n)) {
+        match state.unregister_plugin(name).await {
+            Ok(_) => report.applied.push(format!("plugins.{name}")),
+            Err(e) => report.errors.push(format!("plugins.{name}: {e}")),
+        }
+    }
+    let mut in_effect = Vec::with_capacity(new.len());
+    for plugin in new {
+        let previous = old.iter().find(|p| p.name == plugin.name);
+        if old_map.get(&plugin.name) == new_map.get(&plugin.name) {
+            in_effect.push(plugin.clone());
+            continue;
+        }
+        let result = match crate::plugins::read_plugin_data(plugin).await {
+            Ok(result) => result,
+            Err(e) => {
+                report.errors.push(format!("plugins.{}: {e}", plugin.name));
+                in_effect.extend(previous.cloned());
+                continue;
+            }
+        };
+        // Drop the tools of the previous version before registering the new one
+        let _ = state.unregister_plugin(&plugin.name).await;
+        match state
+            .register_plugin_with_executors(plugin.clone(), result.toolset, result.executors)
+            .await
+        {
+            Ok(()) => {
+                report.applied.push(format!("plugins.{}", plugin.name));
+                in_effect.push(plugin.clone());
+            }
+            Err(e) => report.errors.push(format!("plugins.{}: {e}", plugin.name)),
+        }
+    }
+    in_effect
+}
+
+/// Reloads the configuration on every SIGHUP.
+///
+/// Must be called from within a tokio runtime.
+///
+/// # Errors
+/// Returns an error if the signal handler cannot be registered.
+#[cfg(unix)]
+pub fn spawn_signal_handler(state: Arc<ArkState>) -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hup.recv().await.is_some() {
+            let Some(reloader) = state.config_reloader() else {
+                tracing::warn!("SIGHUP: configuration reload is not available");
+                continue;
+            };
+            if let Err(e) = reloader.reload(&state).await {
+                tracing::warn!("SIGHUP: configuration not reloaded: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
@@ -1,13 +1,26 @@
 //! Role and group mapping for authentication providers.
 
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::models::Groups;
+use crate::server::auth::{IdTokenClaims, Principal};
+
+/// Claim read for group membership when the provider does not configure one.
+pub const DEFAULT_GROUPS_CLAIM: &str = "groups";
 
 /// Application-level roles used by the server's authorization checks.
 ///
-/// The list is intentionally small and can be extended to include more
-/// fine-grained roles as the authorization model grows. Use `UserRoles` to
-/// convert provider-specific group information into a vector of these roles.
+/// `Admin` and `User` are built in; additional roles are declared under
+/// `auth.roles` in the configuration and carried as `Custom`. What each role
+/// is allowed to do is decided by the permission model in
+/// [`crate::server::authz`]. Roles serialize as their plain name
+/// (`"Admin"`, `"User"`, `"operator"`), so stored sessions and service
+/// accounts keep the same format.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum Role {
     /// Administrative user. Has elevated privileges and can perform
     /// actions on all loaded plugins.
@@ -16,5 +29,275 @@ pub enum Role {
     /// authenticated identity that does not match an admin group. A user
     /// can only see and access plugins they own.
     User,
-    // Add more roles as needed
+    /// A role declared in the `auth.roles` configuration.
+    Custom(String),
+}
+
+impl Role {
+    /// Returns the role name as used in configuration and persisted records.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Admin => "Admin",
+            Role::User => "User",
+            Role::Custom(name) => name,
+        }
+    }
+}
+
+impl From<String> for Role {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "Admin" => Role::Admin,
+            "User" => Role::User,
+            _ => Role::Custom(name),
+        }
+    }
+}
+
+impl From<&str> for Role {
+    fn from(name: &str) -> Self {
+        Role::from(name.to_string())
+    }
+}
+
+impl From<Role> for String {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Custom(name) => name,
+            other => other.as_str().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One step of a group-claim JSONPath.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathStep {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Parses the JSONPath subset accepted in `groups.path`: an optional leading
+/// `$`, followed by `.key`, `.*`, `['key']`, `["key"]`, `[n]` and `[*]` steps.
+fn parse_path(path: &str) -> Result<Vec<PathStep>, String> {
+    let path = path.trim();
+    let rooted = path.starts_with('$');
+    let rest = path.strip_prefix('$').unwrap_or(path);
+    let chars: Vec<char> = rest.chars().collect();
+    let mut steps = Vec::new();
+    let mut i = 0;
+    // Allow a bare leading key ("roles" == "$.roles")
+    let mut expect_key = !rooted && !rest.is_empty() && !rest.starts_with(['.', '[']);
+    while i < chars.len() || expect_key {
+        if expect_key || chars[i] == '.' {
+            if !expect_key {
+                i += 1;
+            }
+            expect_key = false;
+            if chars.get(i) == Some(&'*') {
+                steps.push(PathStep::Wildcard);
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                i += 1;
+            }
+            if start == i {
+                return Err(format!("empty key in path '{}'", path));
+            }
+            steps.push(PathStep::Key(chars[start..i].iter().collect()));
+        } else if chars[i] == '[' {
+            let close = chars[i..]
+                .iter()
+                .position(|c| *c == ']')
+                .map(|p| p + i)
+                .ok_or_else(|| format!("unterminated '[' in path '{}'", path))?;
+            let inner: String = chars[i + 1..close].iter().collect();
+            let inner = inner.trim();
+            let step = if inner == "*" {
+                PathStep::Wildcard
+            } else if let Some(key) = inner
+                .strip_prefix('\'')
+                .and_then(|k| k.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|k| k.strip_suffix('"')))
+            {
+                PathStep::Key(key.to_string())
+            } else {
+                PathStep::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| format!("invalid index '{}' in path '{}'", inner, path))?,
+                )
+            };
+            steps.push(step);
+            i = close + 1;
+        } else {
+            return Err(format!("unexpected '{}' in path '{}'", chars[i], path));
+        }
+    }
+    Ok(steps)
+}
+
+/// Validates a `groups.path` expression.
+pub fn validate_groups_path(path: &str) -> Result<(), String> {
+    parse_path(path).map(|_| ())
+}
+
+/// Returns the values selected by `path` within `value`.
+pub fn select_json_path<'a>(value: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let mut current = vec![value];
+    for step in parse_path(path)? {
+        current = current
+            .into_iter()
+            .flat_map(|v| -> Vec<&Value> {
+                match (&step, v) {
+                    (PathStep::Key(k), Value::Object(map)) => map.get(k).into_iter().collect(),
+                    (PathStep::Index(n), Value::Array(items)) => {
+                        items.get(*n).into_iter().collect()
+                    }
+                    (PathStep::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (PathStep::Wildcard, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    Ok(current)
+}
+
+/// Collects group identifiers from a selected claim value: strings and
+/// numbers are taken as-is, arrays are flattened one level.
+fn collect_group_ids(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => out.push(s.clone()),
+        Value::Number(n) => out.push(n.to_string()),
+        Value::Array(items) => {
+            for item in items {
+                match item {
+                    Value::String(s) => out.push(s.clone()),
+                    Value::Number(n) => out.push(n.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Extracts group membership from ID token claims according to the
+/// provider's `groups.claim` / `groups.path` settings.
+///
+/// Without configuration this reads the plain `groups` claim, matching the
+/// historical behaviour.
+pub fn groups_from_claims(claims: &IdTokenClaims, config: Option<&Groups>) -> Vec<String> {
+    let claim = config
+        .and_then(|g| g.claim.as_deref())
+        .unwrap_or(DEFAULT_GROUPS_CLAIM);
+    let path = config.and_then(|g| g.path.as_deref());
+
+    let raw = match claims.raw.get(claim) {
+        Some(v) => v.clone(),
+        // Claims built without the raw payload only carry the standard claim
+        None if claim == DEFAULT_GROUPS_CLAIM => match &claims.groups {
+            Some(groups) => Value::from(groups.clone()),
+            None => return Vec::new(),
+        },
+        None => {
+            tracing::debug!("Groups claim '{}' not present in ID token", claim);
+            return Vec::new();
+        }
+    };
+
+    let selected = match path {
+        Some(path) => match select_json_path(&raw, path) {
+            Ok(values) => values,
+            Err(e) => {
+                tracing::warn!("Invalid groups path: {}", e);
+                return Vec::new();
+            }
+        },
+        None => vec![&raw],
+    };
+
+    let mut groups = Vec::new();
+    for value in selected {
+        collect_group_ids(value, &mut groups);
+    }
+    let mut seen = HashSet::new();
+    groups.retain(|group| seen.insert(group.clone()));
+    groups
+}
+
+/// Grants the roles mapped from the principal's groups in `config.role_mappings`.
+///
+/// A mapping to `Admin` also sets the admin flag.
+pub fn apply_role_mappings(principal: &mut Principal, config: &Groups) {
+    for group in &principal.groups {
+        let Some(role_names) = config.role_mappings.get(group) else {
+            continue;
+        };
+        for name in role_names {
+            let role = Role::from(name.as_str());
+            if !principal.roles.contains(&role) {
+                tracing::debug!("Group '{}' maps to role '{}'", group, role);
+                principal.roles.push(role);
+            }
+        }
+    }
+    if principal.roles.contains(&Role::Admin) {
+        principal.is_admin = true;
+    }
+}
+
+/// Assigns roles from the principal's group membership according to the
+/// provider's group settings: the admin group, then `role_mappings`.
+///
+/// Returns `false` when a users group is configured and the non-admin
+/// principal is not a member of it.
+pub fn assign_group_roles(principal: &mut Principal, groups: Option<&Groups>) -> bool {
+    let Some(groups) = groups else {
+        tracing::debug!("No groups configured, user gets User role only");
+        return true;
+    };
+    if let Some(admin_group) = &groups.admin {
+        tracing::debug!(
+            "Role assignment: user groups = {:?}, admin_group = {}, provider = {}",
+            principal.groups,
+            admin_group,
+            principal.provider
+        );
+        if principal.groups.iter().any(|g| g == admin_group) {
+            principal.roles.push(Role::Admin);
+            principal.is_admin = true;
+            tracing::debug!("User assigned Admin role");
+        } else {
+            tracing::debug!("User not in admin group, keeping User role only");
+        }
+    } else {
+        tracing::debug!("No admin group configured, user gets User role only");
+    }
+
+    // Grant roles mapped from group membership
+    apply_role_mappings(principal, groups);
+
+    // Check user group restriction for non-admin users
+    if !principal.is_admin
+        && let Some(users_group) = &groups.users
+        && !principal.groups.iter().any(|g| g == users_group)
+    {
+        tracing::warn!(
+            "Access denied: user not in required users group '{}', user groups = {:?}",
+            users_group,
+            principal.groups
+        );
+        return false;
+    }
+    true
 }
@@ -0,0 +1,744 @@
+//! SAML 2.0 service provider.
+//!
+//! Implements the SP side of Web Browser SSO: AuthnRequests go to the IdP with
+//! the HTTP-Redirect binding, responses come back to the assertion consumer
+//! service (ACS) with the HTTP-POST binding, and SP metadata is generated for
+//! registration with the IdP.
+//!
+//! A response is only accepted when the assertion, or the response enclosing
+//! it, carries a valid enveloped XML signature made with the configured IdP
+//! certificate (exclusive canonicalization, RSA-SHA256 or RSA-SHA512). The
+//! document must contain exactly one assertion, every `ID` must be unique and
+//! user data is only read from that signed assertion, which rules out
+//! signature wrapping. Encrypted assertions and unsolicited (IdP-initiated)
+//! responses are not supported.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::Write;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use roxmltree::{Document, Node, NodeId, NodeType, ParsingOptions};
+use rsa::RsaPublicKey;
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::signature::Verifier;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::config::models::{Groups, SamlConfig};
+use crate::server::auth::{Principal, ProviderKind};
+use crate::server::roles::{DEFAULT_GROUPS_CLAIM, Role};
+
+/// Path of the assertion consumer service.
+pub const ACS_PATH: &str = "/auth/saml/acs";
+
+const NS_PROTOCOL: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const NS_ASSERTION: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const NS_METADATA: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+const NS_DSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+const NS_EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+const ALG_ENVELOPED: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const ALG_RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const ALG_RSA_SHA512: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512";
+const ALG_SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+const ALG_SHA512: &str = "http://www.w3.org/2001/04/xmlenc#sha512";
+
+const BINDING_POST: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const METHOD_BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const NAMEID_UNSPECIFIED: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:unspecified";
+
+const DEFAULT_CLOCK_SKEW_SECS: u64 = 120;
+
+/// Attributes tried, in order, for the email address.
+const EMAIL_ATTRIBUTES: [&str; 3] = [
+    "email",
+    "mail",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/emailaddress",
+];
+/// Attributes tried, in order, for the display name.
+const NAME_ATTRIBUTES: [&str; 3] = [
+    "displayName",
+    "name",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/name",
+];
+
+/// Resolved SAML service provider settings, including the IdP signing key.
+#[derive(Clone, Debug)]
+pub struct ServiceProvider {
+    /// Entity ID of the IdP (expected assertion issuer).
+    pub idp_entity_id: String,
+    /// SSO endpoint of the IdP (HTTP-Redirect binding).
+    pub idp_sso_url: String,
+    /// Entity ID of this SP (expected audience).
+    pub sp_entity_id: String,
+    /// Configured ACS URL; derived from the request when unset.
+    pub acs_url: Option<String>,
+    email_attribute: Option<String>,
+    name_attribute: Option<String>,
+    clock_skew: Duration,
+    idp_key: RsaPublicKey,
+}
+
+/// The validated content of a signed SAML assertion.
+#[derive(Clone, Debug)]
+pub struct Assertion {
+    /// The subject's NameID.
+    pub name_id: String,
+    /// Attribute values by attribute name.
+    pub attributes: BTreeMap<String, Vec<String>>,
+}
+
+impl Assertion {
+    /// Returns the first value of the named attribute.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .get(name)
+            .and_then(|v| v.first())
+            .map(|s| s.as_str())
+    }
+}
+
+impl ServiceProvider {
+    /// Builds the service provider from configuration, loading and parsing
+    /// the IdP signing certificate.
+    pub fn from_config(config: &SamlConfig) -> Result<Self> {
+        if config.idp_entity_id.trim().is_empty() {
+            bail!("SAML provider idp_entity_id cannot be empty");
+        }
+        if config.sp_entity_id.trim().is_empty() {
+            bail!("SAML provider sp_entity_id cannot be empty");
+        }
+        let urls = std::iter::once(&config.idp_sso_url).chain(config.acs_url.as_ref());
+        for url in urls {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                bail!("SAML provider URLs must be valid HTTP/HTTPS URLs: {}", url);
+            }
+        }
+        let der = load_certificate(&config.idp_certificate)
+            .context("Failed to load SAML IdP certificate")?;
+        let (_, cert) = x509_parser::parse_x509_certificate(&der)
+            .map_err(|e| anyhow!("Invalid SAML IdP certificate: {}", e))?;
+        let idp_key = RsaPublicKey::from_public_key_der(cert.tbs_certificate.subject_pki.raw)
+            .map_err(|e| anyhow!("SAML IdP certificate must carry an RSA key: {}", e))?;
+
+        Ok(Self {
+            idp_entity_id: config.idp_entity_id.clone(),
+            idp_sso_url: config.idp_sso_url.clone(),
+            sp_entity_id: config.sp_entity_id.clone(),
+            acs_url: config.acs_url.clone(),
+            email_attribute: config.email_attribute.clone(),
+            name_attribute: config.name_attribute.clone(),
+            clock_skew: Duration::seconds(
+                config.clock_skew_secs.unwrap_or(DEFAULT_CLOCK_SKEW_SECS) as i64,
+            ),
+            idp_key,
+        })
+    }
+
+    /// Returns the IdP URL that starts a login, carrying a deflated
+    /// AuthnRequest with the given ID and the relay state.
+    pub fn authn_request_url(
+        &self,
+        request_id: &str,
+        acs_url: &str,
+        relay_state: &str,
+    ) -> Result<String> {
+        let request = format!(
+            r#"<samlp:AuthnRequest xmlns:samlp="{}" xmlns:saml="{}" ID="{}" Version="2.0" IssueInstant="{}" Destination="{}" AssertionConsumerServiceURL="{}" ProtocolBinding="{}"><saml:Issuer>{}</saml:Issuer><samlp:NameIDPolicy AllowCreate="true"/></samlp:AuthnRequest>"#,
+            NS_PROTOCOL,
+            NS_ASSERTION,
+            xml_escape(request_id),
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            xml_escape(&self.idp_sso_url),
+            xml_escape(acs_url),
+            BINDING_POST,
+            xml_escape(&self.sp_entity_id),
+        );
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(request.as_bytes())?;
+        let deflated = encoder.finish()?;
+
+        let separator = if self.idp_sso_url.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        Ok(format!(
+            "{}{}SAMLRequest={}&RelayState={}",
+            self.idp_sso_url,
+            separator,
+            urlencoding::encode(&STANDARD.encode(deflated)),
+            urlencoding::encode(relay_state)
+        ))
+    }
+
+    /// Renders the SP metadata document for registration with the IdP.
+    pub fn metadata(&self, acs_url: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntityDescriptor xmlns:md="{}" entityID="{}">
+  <md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="{}">
+    <md:NameIDFormat>{}</md:NameIDFormat>
+    <md:AssertionConsumerService Binding="{}" Location="{}" index="0" isDefault="true"/>
+  </md:SPSSODescriptor>
+</md:EntityDescriptor>
+"#,
+            NS_METADATA,
+            xml_escape(&self.sp_entity_id),
+            NS_PROTOCOL,
+            NAMEID_UNSPECIFIED,
+            BINDING_POST,
+            xml_escape(acs_url),
+        )
+    }
+
+    /// Validates a base64-encoded `SAMLResponse` posted to the ACS.
+    ///
+    /// `request_id` is the ID of the AuthnRequest the response must answer.
+    pub fn validate_response(
+        &self,
+        encoded: &str,
+        acs_url: &str,
+        request_id: &str,
+    ) -> Result<Assertion> {
+        self.validate_response_at(encoded, acs_url, request_id, Utc::now())
+    }
+
+    fn validate_response_at(
+        &self,
+        encoded: &str,
+        acs_url: &str,
+        request_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Assertion> {
+        let xml = String::from_utf8(decode_base64(encoded)?)
+            .context("SAML response is not valid UTF-8")?;
+        // DTDs are rejected, so there is no entity expansion
+        let doc = Document::parse_with_options(
+            &xml,
+            ParsingOptions {
+                allow_dtd: false,
+                ..ParsingOptions::default()
+            },
+        )
+        .context("SAML response is not well-formed XML")?;
+
+        let response = doc.root_element();
+        if !is_element(response, NS_PROTOCOL, "Response") {
+            bail!("Document is not a SAML Response");
+        }
+        ensure_unique_ids(&doc)?;
+
+        if response.attribute("Version") != Some("2.0") {
+            bail!("Unsupported SAML version");
+        }
+        let status = child(response, NS_PROTOCOL, "Status")
+            .and_then(|s| child(s, NS_PROTOCOL, "StatusCode"))
+            .and_then(|c| c.attribute("Value"));
+        if status != Some(STATUS_SUCCESS) {
+            bail!("IdP returned status {}", status.unwrap_or("(none)"));
+        }
+        if let Some(destination) = response.attribute("Destination")
+            && destination != acs_url
+        {
+            bail!("Response destination '{}' does not match", destination);
+        }
+        if response.attribute("InResponseTo") != Some(request_id) {
+            bail!("Response does not answer the pending AuthnRequest");
+        }
+        if let Some(issuer) = child(response, NS_ASSERTION, "Issuer")
+            && text(issuer) != self.idp_entity_id
+        {
+            bail!("Response issued by unexpected IdP '{}'", text(issuer));
+        }
+        if child(response, NS_ASSERTION, "EncryptedAssertion").is_some() {
+            bail!("Encrypted assertions are not supported");
+        }
+
+        let assertions = doc
+            .descendants()
+            .filter(|n| is_element(*n, NS_ASSERTION, "Assertion"))
+            .count();
+        let assertion = match child(response, NS_ASSERTION, "Assertion") {
+            Some(a) if assertions == 1 => a,
+            _ => bail!("Response must contain exactly one assertion"),
+        };
+
+        let response_signed = self.verify_enveloped_signature(response)?;
+        let assertion_signed = self.verify_enveloped_signature(assertion)?;
+        if !response_signed && !assertion_signed {
+            bail!("SAML assertion is not signed");
+        }
+        // The POST binding requires signed responses to name their recipient
+        if response_signed && response.attribute("Destination").is_none() {
+            bail!("Signed response has no destination");
+        }
+
+        self.read_assertion(assertion, acs_url, request_id, response_signed, now)
+    }
+
+    /// Checks the assertion's issuer, subject confirmation and conditions,
+    /// and extracts the subject and attributes.
+    ///
+    /// Unless the response is signed, the subject confirmation must name
+    /// `request_id`: otherwise a captured assertion could be wrapped in a new
+    /// unsigned response answering someone else's login.
+    fn read_assertion(
+        &self,
+        assertion: Node,
+        acs_url: &str,
+        request_id: &str,
+        response_signed: bool,
+        now: DateTime<Utc>,
+    ) -> Result<Assertion> {
+        let issuer = child(assertion, NS_ASSERTION, "Issuer").map(text);
+        if issuer.as_deref() != Some(self.idp_entity_id.as_str()) {
+            bail!("Assertion issued by unexpected IdP {:?}", issuer);
+        }
+
+        let subject = child(assertion, NS_ASSERTION, "Subject")
+            .ok_or_else(|| anyhow!("Assertion has no subject"))?;
+        let name_id = child(subject, NS_ASSERTION, "NameID")
+            .map(text)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| anyhow!("Assertion has no NameID"))?;
+
+        let confirmed = children(subject, NS_ASSERTION, "SubjectConfirmation")
+            .filter(|c| c.attribute("Method") == Some(METHOD_BEARER))
+            .filter_map(|c| child(c, NS_ASSERTION, "SubjectConfirmationData"))
+            .any(|data| {
+                data.attribute("Recipient") == Some(acs_url)
+                    && data
+                        .attribute("InResponseTo")
+                        .map_or(response_signed, |id| id == request_id)
+                    && self.within(
+                        data.attribute("NotBefore"),
+                        data.attribute("NotOnOrAfter"),
+                        true,
+                        now,
+                    )
+            });
+        if !confirmed {
+            bail!("Assertion has no valid bearer subject confirmation");
+        }
+
+        let conditions = child(assertion, NS_ASSERTION, "Conditions")
+            .ok_or_else(|| anyhow!("Assertion has no conditions"))?;
+        if !self.within(
+            conditions.attribute("NotBefore"),
+            conditions.attribute("NotOnOrAfter"),
+            false,
+            now,
+        ) {
+            bail!("Assertion is expired or not yet valid");
+        }
+        let mut restrictions = children(conditions, NS_ASSERTION, "AudienceRestriction").peekable();
+        if restrictions.peek().is_none() {
+            bail!("Assertion has no audience restriction");
+        }
+        for restriction in restrictions {
+            if !children(restriction, NS_ASSERTION, "Audience")
+                .any(|a| text(a) == self.sp_entity_id)
+            {
+                bail!("Assertion is not intended for '{}'", self.sp_entity_id);
+            }
+        }
+
+        let mut attributes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for statement in children(assertion, NS_ASSERTION, "AttributeStatement") {
+            for attribute in children(statement, NS_ASSERTION, "Attribute") {
+                let Some(name) = attribute.attribute("Name") else {
+                    continue;
+                };
+                attributes.entry(name.to_string()).or_default().extend(
+                    children(attribute, NS_ASSERTION, "AttributeValue")
+                        .map(text)
+                        .filter(|v| !v.is_empty()),
+                );
+            }
+        }
+
+        Ok(Assertion {
+            name_id,
+            attributes,
+        })
+    }
+
+    /// Checks a NotBefore / NotOnOrAfter window against `now`, allowing for
+    /// clock skew. Unparseable timestamps fail the check.
+    fn within(
+        &self,
+        not_before: Option<&str>,
+        not_on_or_after: Option<&str>,
+        require_end: bool,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let parse = |s: &str| DateTime::parse_from_rfc3339(s).map(|t| t.with_timezone(&Utc));
+        let starts_ok = match not_before.map(parse) {
+            None => true,
+            Some(Ok(t)) => now + self.clock_skew >= t,
+            Some(Err(_)) => false,
+        };
+        let ends_ok = match not_on_or_after.map(parse) {
+            None => !require_end,
+            Some(Ok(t)) => now - self.clock_skew < t,
+            Some(Err(_)) => false,
+        };
+        starts_ok && ends_ok
+    }
+
+    /// Verifies the enveloped signature of `element`, if it has one.
+    ///
+    /// Returns `Ok(false)` when the element is unsigned and an error when a
+    /// signature is present but invalid or uses unsupported algorithms.
+    fn verify_enveloped_signature(&self, element: Node) -> Result<bool> {
+        let Some(signature) = child(element, NS_DSIG, "Signature") else {
+            return Ok(false);
+        };
+        let id = element
+            .attribute("ID")
+            .ok_or_else(|| anyhow!("Signed element has no ID"))?;
+        let signed_info = child(signature, NS_DSIG, "SignedInfo")
+            .ok_or_else(|| anyhow!("Signature has no SignedInfo"))?;
+
+        let c14n = child(signed_info, NS_DSIG, "CanonicalizationMethod")
+            .filter(|m| m.attribute("Algorithm") == Some(NS_EXC_C14N))
+            .ok_or_else(|| anyhow!("Unsupported canonicalization method"))?;
+        let signature_method =
+            child(signed_info, NS_DSIG, "SignatureMethod").and_then(|m| m.attribute("Algorithm"));
+
+        let mut references = children(signed_info, NS_DSIG, "Reference");
+        let reference = match (references.next(), references.next()) {
+            (Some(r), None) => r,
+            _ => bail!("Signature must contain exactly one reference"),
+        };
+        if reference.attribute("URI") != Some(format!("#{}", id).as_str()) {
+            bail!("Signature does not reference the signed element");
+        }
+        let mut prefixes = Vec::new();
+        if let Some(transforms) = child(reference, NS_DSIG, "Transforms") {
+            for transform in children(transforms, NS_DSIG, "Transform") {
+                match transform.attribute("Algorithm") {
+                    Some(ALG_ENVELOPED) => {}
+                    Some(NS_EXC_C14N) => prefixes = inclusive_prefixes(transform),
+                    other => bail!("Unsupported signature transform {:?}", other),
+                }
+            }
+        }
+
+        let canonical = canonicalize(element, Some(signature.id()), &prefixes);
+        let digest = match child(reference, NS_DSIG, "DigestMethod")
+            .and_then(|m| m.attribute("Algorithm"))
+        {
+            Some(ALG_SHA256) => Sha256::digest(canonical.as_bytes()).to_vec(),
+            Some(ALG_SHA512) => Sha512::digest(canonical.as_bytes()).to_vec(),
+            other => bail!("Unsupported digest method {:?}", other),
+        };
+        let expected = child(reference, NS_DSIG, "DigestValue")
+            .map(|d| decode_base64(&text(d)))
+            .transpose()?;
+        if expected.as_deref() != Some(digest.as_slice()) {
+            bail!("SAML digest does not match the signed element");
+        }
+
+        let signed = canonicalize(signed_info, None, &inclusive_prefixes(c14n));
+        let value = child(signature, NS_DSIG, "SignatureValue")
+            .map(|v| decode_base64(&text(v)))
+            .transpose()?
+            .ok_or_else(|| anyhow!("Signature has no value"))?;
+        let value = Signature::try_from(value.as_slice())
+            .map_err(|_| anyhow!("Malformed signature value"))?;
+        let verified =
+            match signature_method {
+                Some(ALG_RSA_SHA256) => VerifyingKey::<Sha256>::new(self.idp_key.clone())
+                    .verify(signed.as_bytes(), &value),
+                Some(ALG_RSA_SHA512) => VerifyingKey::<Sha512>::new(self.idp_key.clone())
+                    .verify(signed.as_bytes(), &value),
+                other => bail!("Unsupported signature method {:?}", other),
+            };
+        verified.map_err(|_| anyhow!("SAML signature verification failed"))?;
+        Ok(true)
+    }
+
+    /// Builds the principal for a validated assertion.
+    ///
+    /// The NameID is the subject, so IdPs should release a persistent NameID
+    /// (or the email address). Groups are read from the attribute named by
+    /// `groups.claim` (default "groups").
+    pub fn principal(&self, assertion: &Assertion, groups: Option<&Groups>) -> Principal {
+        let pick = |configured: &Option<String>, defaults: &[&str]| match configured {
+            Some(name) => assertion.attribute(name).map(|s| s.to_string()),
+            None => defaults
+                .iter()
+                .find_map(|name| assertion.attribute(name))
+                .map(|s| s.to_string()),
+        };
+        let email = pick(&self.email_attribute, &EMAIL_ATTRIBUTES).or_else(|| {
+            assertion
+                .name_id
+                .contains('@')
+                .then(|| assertion.name_id.clone())
+        });
+        let claim = groups
+            .and_then(|g| g.claim.as_deref())
+            .unwrap_or(DEFAULT_GROUPS_CLAIM);
+        let mut group_ids = assertion.attributes.get(claim).cloned().unwrap_or_default();
+        let mut seen = HashSet::new();
+        group_ids.retain(|id| seen.insert(id.clone()));
+
+        Principal {
+            subject: assertion.name_id.clone(),
+            email,
+            name: pick(&self.name_attribute, &NAME_ATTRIBUTES),
+            picture: None,
+            provider: self.idp_entity_id.clone(),
+            provider_kind: ProviderKind::Saml,
+            provider_name: None,
+            tenant_id: None,
+            oid: None,
+            groups: group_ids,
+            roles: vec![Role::User],
+            is_admin: false,
+        }
+    }
+}
+
+/// Loads a certificate given as a PEM file path, inline PEM, or base64 DER.
+fn load_certificate(value: &str) -> Result<Vec<u8>> {
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("idp_certificate cannot be empty");
+    }
+    let text = if value.starts_with("-----BEGIN") {
+        value.to_string()
+    } else if std::path::Path::new(value).is_file() {
+        std::fs::read_to_string(value).with_context(|| format!("Failed to read {}", value))?
+    } else {
+        return decode_base64(value);
+    };
+    if !text.contains("-----BEGIN") {
+        return decode_base64(&text);
+    }
+    let pem = pem::parse(text.as_bytes())?;
+    if pem.tag != "CERTIFICATE" {
+        bail!("Expected a CERTIFICATE PEM block, found {}", pem.tag);
+    }
+    Ok(pem.contents)
+}
+
+/// Decodes standard base64, ignoring embedded whitespace.
+fn decode_base64(value: &str) -> Result<Vec<u8>> {
+    let compact: String = value.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    STANDARD
+        .decode(compact)
+        .map_err(|e| anyhow!("Invalid base64: {}", e))
+}
+
+/// Rejects documents where an `ID` value appears more than once.
+fn ensure_unique_ids(doc: &Document) -> Result<()> {
+    let mut seen = HashSet::new();
+    for id in doc.descendants().filter_map(|n| n.attribute("ID")) {
+        if !seen.insert(id) {
+            bail!("Duplicate ID '{}' in SAML response", id);
+        }
+    }
+    Ok(())
+}
+
+fn is_element(node: Node, namespace: &str, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(namespace)
+        && node.tag_name().name() == name
+}
+
+fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: &str,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| is_element(*n, namespace, name))
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: &'static str,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |n| is_element(*n, namespace, name))
+}
+
+/// Returns the trimmed text content of an element.
+fn text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Reads the `InclusiveNamespaces PrefixList` of an exclusive c14n method.
+fn inclusive_prefixes(method: Node) -> Vec<String> {
+    child(method, NS_EXC_C14N, "InclusiveNamespaces")
+        .and_then(|n| n.attribute("PrefixList"))
+        .map(|list| {
+            list.split_whitespace()
+                .map(|p| if p == "#default" { "" } else { p }.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Serializes `node` with Exclusive XML Canonicalization 1.0 (without
+/// comments), omitting the subtree `exclude` (the enveloped signature).
+///
+/// `inclusive_prefixes` are namespace prefixes from an `InclusiveNamespaces`
+/// list, which are rendered wherever they are in scope.
+pub fn canonicalize(node: Node, exclude: Option<NodeId>, inclusive_prefixes: &[String]) -> String {
+    let mut out = String::new();
+    write_canonical(
+        node,
+        &BTreeMap::new(),
+        exclude,
+        inclusive_prefixes,
+        &mut out,
+    );
+    out
+}
+
+fn write_canonical(
+    node: Node,
+    rendered: &BTreeMap<String, String>,
+    exclude: Option<NodeId>,
+    inclusive_prefixes: &[String],
+    out: &mut String,
+) {
+    // roxmltree resolves names to namespace URIs; prefixes come from the source
+    let input = node.document().input_text();
+    let qname = input[node.range().start + 1..]
+        .split(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .next()
+        .unwrap_or_default();
+
+    let mut visible: BTreeSet<&str> = BTreeSet::new();
+    visible.insert(qname.split_once(':').map(|(p, _)| p).unwrap_or(""));
+    let mut attributes = Vec::new();
+    for attribute in node.attributes() {
+        let name = &input[attribute.range_qname()];
+        if let Some((prefix, _)) = name.split_once(':')
+            && prefix != "xml"
+        {
+            visible.insert(prefix);
+        }
+        attributes.push((
+            attribute.namespace().unwrap_or(""),
+            attribute.name(),
+            name,
+            attribute.value(),
+        ));
+    }
+    visible.extend(inclusive_prefixes.iter().map(|p| p.as_str()));
+    attributes.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    out.push('<');
+    out.push_str(qname);
+    let mut scope = rendered.clone();
+    for prefix in visible {
+        let uri = if prefix.is_empty() {
+            node.default_namespace().unwrap_or("")
+        } else {
+            match node.lookup_namespace_uri(Some(prefix)) {
+                Some(uri) => uri,
+                None => continue,
+            }
+        };
+        if scope.get(prefix).map(|s| s.as_str()).unwrap_or("") == uri {
+            continue;
+        }
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        escape_attribute(uri, out);
+        out.push('"');
+        scope.insert(prefix.to_string(), uri.to_string());
+    }
+    for (_, _, name, value) in attributes {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        escape_attribute(value, out);
+        out.push('"');
+    }
+    out.push('>');
+
+    for child in node.children() {
+        if Some(child.id()) == exclude {
+            continue;
+        }
+        match child.node_type() {
+            NodeType::Element => write_canonical(child, &scope, exclude, inclusive_prefixes, out),
+            NodeType::Text => escape_text(child.text().unwrap_or_default(), out),
+            NodeType::PI => {
+                if let Some(pi) = child.pi() {
+                    out.push_str("<?");
+                    out.push_str(pi.target);
+                    if let Some(value) = pi.value {
+                        out.push(' ');
+                        out.push_str(value);
+                    }
+                    out.push_str("?>");
+                }
+            }
+            NodeType::Comment | NodeType::Root => {}
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(qname);
+    out.push('>');
+}
+
+fn escape_text(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Escapes a value for use in generated XML text or attributes.
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
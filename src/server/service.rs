@@ -4,13 +4,15 @@ use anyhow::{Context, bail};
 use axum::{
     Extension, Json, Router,
     body::Body,
-    http::Request,
+    error_handling::HandleErrorLayer,
+    extract::{ConnectInfo, DefaultBodyLimit},
+    http::{Request, StatusCode, header},
     middleware::{self, Next},
     response::{Html, IntoResponse, Redirect, Response},
-    routing::{get, post},
+    routing::{delete, get, post, put},
 };
-use hyper::server::conn::http1;
-use hyper_util::rt::TokioIo;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
 use hyper_util::service::TowerToHyperService;
 use rmcp::service::serve_server;
 use rmcp::transport::sse_server::{SseServer, SseServerConfig};
@@ -18,27 +20,67 @@ use rmcp::transport::stdio;
 use rmcp::transport::streamable_http_server::StreamableHttpServerConfig;
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::transport::streamable_http_server::tower::StreamableHttpService;
+use rustls::server::{WantsServerCert, WebPkiClientVerifier, danger::ClientCertVerifier};
+use rustls::{ConfigBuilder, ServerConfig, WantsVerifier};
 use serde::Deserialize;
 use serde_json::{Value, to_value};
-use std::{fs, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::sync::Semaphore;
 use tokio_rustls::TlsAcceptor;
-use tokio_util::sync::CancellationToken;
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
-use tracing::info;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tower::{BoxError, Layer, ServiceBuilder, ServiceExt, limit::GlobalConcurrencyLimitLayer};
+use tower_http::{
+    cors::CorsLayer, limit::RequestBodyLimitLayer, services::ServeDir, trace::TraceLayer,
+};
+use tracing::{Instrument, info};
 
 use crate::{
-    config::{ArkConfig, McpTransport},
+    config::{
+        ArkConfig, McpTransport,
+        models::{AcmeConfig, ClientAuthConfig, ClientAuthMode, TlsConfig, TlsVersion},
+    },
+    errors::{ErrorCode, StartupError},
+    logging::RequestId,
     server::{
+        acme::AcmeManager,
+        csrf,
         handlers::{
             api::{
-                create_plugin, delete_plugin, execute_plugin_tool, get_plugin_by_id, get_plugins,
+                create_plugin, delete_plugin, execute_plugin_tool, export_plugin, get_plugin_by_id,
+                get_plugins, import_plugin, search_tools,
             },
-            health::{livez, readyz},
+            audit::list_auth_events,
+            config::{get_config, reload_config},
+            events::stream_events,
+            executions::list_executions,
+            health::{livez, readyz, startupz},
+            logging::{get_log_level, set_log_level},
             oauth,
+            profile::{get_me, get_my_usage, update_me},
+            service_accounts::{
+                create_service_account, create_service_account_token, delete_service_account,
+                list_service_account_tokens, list_service_accounts, revoke_service_account_token,
+                rotate_service_account_secret,
+            },
+            stats::{get_stats, get_top_usage, get_usage_report},
+            tokens::{create_token, list_tokens, revoke_token},
+            users::{change_own_password, create_user, delete_user, list_users, set_user_password},
         },
         mcp::McpHandler,
+        mcp_sessions::SharedSessionManager,
+        rate_limit::rate_limit,
+        reload::LiveSettings,
+        systemd::{MANAGEMENT_SOCKET, MCP_SOCKET, take_listeners},
+        timeouts::{Activity, ConnectionTimeouts, TimeoutIo, track_activity},
+        watch::FileCertResolver,
     },
-    state::{ApplicationState, ArkState},
+    state::{ApplicationState, ArkState, TlsStatus},
 };
 
 /// CORS configuration for HTTP servers.
@@ -117,10 +159,12 @@ impl Cors {
             layer = layer.allow_methods(AllowMethods::any());
         }
 
-        // Expose MCP headers so browser can read them from responses
+        // Expose MCP headers, and the ETag of plugin listings, so browser
+        // can read them from responses
         layer = layer.expose_headers(ExposeHeaders::list(vec![
             axum::http::HeaderName::from_static("mcp-session-id"),
             axum::http::HeaderName::from_static("mcp-protocol-version"),
+            axum::http::header::ETAG,
         ]));
 
         // Apply credentials setting
@@ -153,23 +197,32 @@ pub struct StandardizedResponse {
     pub additional: Option<String>,
     /// Whether this response represents an error.
     pub is_error: Option<bool>,
+    /// Machine-readable kind of the error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    /// Id of the request that failed, to quote when reporting the error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl StandardizedResponse {
     /// Creates a standardized error response.
     ///
     /// # Arguments
+    /// * `code` - The machine-readable kind of the error
     /// * `error` - The error message
     /// * `additional` - Optional additional context
     ///
     /// # Returns
     /// A JSON response with error formatting
-    pub fn as_error(error: &str, additional: Option<&str>) -> Json<Value> {
+    pub fn as_error(code: ErrorCode, error: &str, additional: Option<&str>) -> Json<Value> {
         let response = StandardizedResponse {
             error: Some(error.to_string()),
             message: None,
             additional: additional.map(|s| s.to_string()),
             is_error: Some(true),
+            code: Some(code),
+            request_id: crate::logging::current_request_id(),
         };
         Json(to_value(response).unwrap())
     }
@@ -193,7 +246,7 @@ fn is_existing_file(path: &str) -> bool {
 /// in the TLS configuration. Both files must exist and be non-empty.
 ///
 /// # Arguments
-/// * `config` - Application configuration containing TLS settings
+/// * `tls` - TLS settings of the listener
 ///
 /// # Returns
 /// `Ok(TlsMaterial)` with loaded certificates and keys, or an error
@@ -201,19 +254,9 @@ fn is_existing_file(path: &str) -> bool {
 /// # Errors
 /// Returns an error if TLS is not configured, files don't exist,
 /// or reading fails
-async fn get_tls_key_material(config: &ArkConfig) -> anyhow::Result<Arc<TlsMaterial>> {
-    let tls_cert = config
-        .clone()
-        .tls
-        .unwrap_or_default()
-        .cert
-        .unwrap_or_default();
-    let tls_key = config
-        .clone()
-        .tls
-        .unwrap_or_default()
-        .key
-        .unwrap_or_default();
+async fn get_tls_key_material(tls: &TlsConfig) -> anyhow::Result<Arc<TlsMaterial>> {
+    let tls_cert = tls.cert.clone().unwrap_or_default();
+    let tls_key = tls.key.clone().unwrap_or_default();
     let use_tls = !(tls_key.is_empty() || tls_cert.is_empty());
 
     if !use_tls {
@@ -249,6 +292,187 @@ async fn get_tls_key_material(config: &ArkConfig) -> anyhow::Result<Arc<TlsMater
     }))
 }
 
+/// Application protocols offered by the TLS listeners, in order of preference.
+pub(crate) const ALPN_PROTOCOLS: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// Builds a listener's TLS acceptor from the certificate and key files,
+/// finishing `builder` (see [`tls_server_config_builder`]).
+///
+/// # Returns
+/// `Ok(None)` if no certificate and key are configured
+///
+/// # Errors
+/// Returns an error if the configured material cannot be read or parsed
+pub(crate) async fn build_tls_acceptor(
+    tls: &TlsConfig,
+    builder: ConfigBuilder<ServerConfig, WantsServerCert>,
+) -> anyhow::Result<Option<Arc<TlsAcceptor>>> {
+    if tls.cert.as_deref().unwrap_or_default().is_empty()
+        || tls.key.as_deref().unwrap_or_default().is_empty()
+    {
+        return Ok(None);
+    }
+    let material = get_tls_key_material(tls).await?;
+    let resolver = FileCertResolver::new(
+        Path::new(tls.cert.as_deref().unwrap_or_default()),
+        Path::new(tls.key.as_deref().unwrap_or_default()),
+        &material.certs,
+        &material.key,
+    )?;
+    let mut config = builder.with_cert_resolver(resolver);
+    config.alpn_protocols = ALPN_PROTOCOLS.iter().map(|p| p.to_vec()).collect();
+    Ok(Some(Arc::new(TlsAcceptor::from(Arc::new(config)))))
+}
+
+/// Starts a listener's rustls configuration with the protocol versions and
+/// cipher suites `tls` allows.
+///
+/// # Errors
+/// Returns an error for an unknown cipher suite, or when the settings leave
+/// no usable protocol version or cipher suite
+pub(crate) fn tls_server_config_builder(
+    tls: &TlsConfig,
+) -> anyhow::Result<ConfigBuilder<ServerConfig, WantsVerifier>> {
+    let min = tls.min_version.unwrap_or(TlsVersion::Tls12);
+    let max = tls.max_version.unwrap_or(TlsVersion::Tls13);
+    if min > max {
+        let name = |version| match version {
+            TlsVersion::Tls12 => "1.2",
+            TlsVersion::Tls13 => "1.3",
+        };
+        bail!(
+            "min_version {} is above max_version {}",
+            name(min),
+            name(max)
+        );
+    }
+    let versions: Vec<&'static rustls::SupportedProtocolVersion> = [
+        (TlsVersion::Tls12, &rustls::version::TLS12),
+        (TlsVersion::Tls13, &rustls::version::TLS13),
+    ]
+    .into_iter()
+    .filter(|(version, _)| (min..=max).contains(version))
+    .map(|(_, version)| version)
+    .collect();
+
+    let mut provider = rustls::crypto::aws_lc_rs::default_provider();
+    if let Some(names) = &tls.cipher_suites {
+        let supported = std::mem::take(&mut provider.cipher_suites);
+        for name in names {
+            let suite = supported
+                .iter()
+                .find(|s| format!("{:?}", s.suite()).eq_ignore_ascii_case(name))
+                .with_context(|| {
+                    format!(
+                        "Unknown cipher suite {}; supported: {}",
+                        name,
+                        supported
+                            .iter()
+                            .map(|s| format!("{:?}", s.suite()))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+            provider.cipher_suites.push(*suite);
+        }
+    }
+    ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(&versions)
+        .context("No cipher suite is usable with the allowed TLS versions")
+}
+
+/// Builds the client certificate verifier of a listener; without
+/// `client_auth` clients are not asked for certificates.
+///
+/// # Errors
+/// Returns an error if the CA bundle cannot be read or holds no usable
+/// certificate
+pub(crate) fn client_cert_verifier(
+    client_auth: Option<&ClientAuthConfig>,
+) -> anyhow::Result<Arc<dyn ClientCertVerifier>> {
+    let Some(client_auth) = client_auth else {
+        return Ok(WebPkiClientVerifier::no_client_auth());
+    };
+    let pem = fs::read(&client_auth.ca)
+        .with_context(|| format!("Failed to read client CA bundle {}", client_auth.ca))?;
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+        let cert = cert.with_context(|| format!("Failed to parse {}", client_auth.ca))?;
+        roots
+            .add(cert)
+            .with_context(|| format!("Invalid CA certificate in {}", client_auth.ca))?;
+    }
+    if roots.is_empty() {
+        bail!("No CA certificate found in {}", client_auth.ca);
+    }
+    let builder = WebPkiClientVerifier::builder(Arc::new(roots));
+    let builder = match client_auth.mode {
+        ClientAuthMode::Require => builder,
+        ClientAuthMode::Verify => builder.allow_unauthenticated(),
+    };
+    let verifier = builder
+        .build()
+        .with_context(|| format!("Invalid client CA bundle {}", client_auth.ca))?;
+    Ok(verifier)
+}
+
+/// Builds the TLS acceptor of one listener from its TLS block. Listeners
+/// with the same `acme` settings share one certificate.
+///
+/// A certificate that cannot be loaded is reported in the TLS status and the
+/// listener serves plain HTTP. Client certificate requirements and protocol
+/// restrictions are never dropped: a listener with invalid ones fails to
+/// start rather than serve without.
+async fn listener_tls_acceptor(
+    listener: &str,
+    tls: &TlsConfig,
+    client_auth: Option<&ClientAuthConfig>,
+    state: &ArkState,
+    acme_managers: &mut Vec<(AcmeConfig, Arc<AcmeManager>)>,
+) -> anyhow::Result<Option<Arc<TlsAcceptor>>> {
+    let verifier = client_cert_verifier(client_auth)
+        .with_context(|| format!("tls.client_auth.{}", listener))?;
+    let builder = tls_server_config_builder(tls)
+        .with_context(|| format!("TLS settings of the {} listener", listener))?
+        .with_client_cert_verifier(verifier);
+    let acceptor = if let Some(acme) = &tls.acme {
+        let manager = match acme_managers.iter().find(|(c, _)| c == acme) {
+            Some((_, manager)) => manager.clone(),
+            None => {
+                let manager = Arc::new(AcmeManager::new(acme)?);
+                acme_managers.push((acme.clone(), manager.clone()));
+                manager
+            }
+        };
+        Some(manager.tls_acceptor(builder))
+    } else {
+        match build_tls_acceptor(tls, builder).await {
+            Ok(acceptor) => {
+                if acceptor.is_some() && state.tls_status() == TlsStatus::Disabled {
+                    state.set_tls_status(TlsStatus::Loaded);
+                }
+                acceptor
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "TLS of the {} listener is configured but could not be loaded: {:#}",
+                    listener,
+                    e
+                );
+                state.set_tls_status(TlsStatus::Failed(format!("{}: {:#}", listener, e)));
+                None
+            }
+        }
+    };
+    if client_auth.is_some() && acceptor.is_none() {
+        bail!(
+            "tls.client_auth.{} needs TLS: set key and cert, or acme, in the same TLS block",
+            listener
+        );
+    }
+    Ok(acceptor)
+}
+
 /// Handler for Prometheus metrics endpoint.
 ///
 /// Returns metrics in Prometheus format when the `prometheus` feature is enabled.
@@ -300,18 +524,25 @@ async fn build_auth_state_and_cleanup(
                 .ok()
                 .or_else(|| ts.cert.clone());
             if let Some(k) = key_path {
-                match crate::server::signing::load_pem_signer_from_paths(&k, cert_path.as_deref()) {
+                match crate::server::signing::load_signer(ts, &k, cert_path.as_deref()) {
                     Ok(s) => Some(s),
                     Err(e) => {
                         tracing::error!("Failed to initialize PEM signer at startup: {}", e);
-                        return Err(e);
+                        return Err(signing_misconfigured(e));
                     }
                 }
             } else {
                 None
             }
         } else {
-            None
+            // Keys held in AWS KMS or Azure Key Vault
+            match crate::server::kms::load_kms_signer(ts).await {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::error!("Failed to initialize KMS signer at startup: {:#}", e);
+                    return Err(signing_misconfigured(e));
+                }
+            }
         }
     } else {
         None
@@ -327,14 +558,37 @@ async fn build_auth_state_and_cleanup(
         .await?,
     );
 
+    // Create the first local admin when requested and no users exist yet
+    if auth_state.enabled
+        && let Ok(password) = std::env::var("ARK_AUTH_LOCAL_ADMIN_PASSWORD")
+    {
+        let username =
+            std::env::var("ARK_AUTH_LOCAL_ADMIN_USER").unwrap_or_else(|_| "admin".to_string());
+        auth_state
+            .bootstrap_local_admin(&username, &password)
+            .await?;
+    }
+
     // Start cleanup tasks if auth enabled
     if auth_state.enabled {
         start_auth_cleanup_tasks(auth_state.clone());
     }
 
+    // Expose the auth state (and its role definitions) to API and MCP handlers
+    state.set_auth_state(auth_state.clone());
+
     Ok(auth_state)
 }
 
+/// Marks a token signer that failed to load as a signing misconfiguration,
+/// unless the error already says what is wrong (e.g. a key/cert mismatch).
+fn signing_misconfigured(error: anyhow::Error) -> anyhow::Error {
+    if error.downcast_ref::<StartupError>().is_some() {
+        return error;
+    }
+    StartupError::SigningMisconfigured(format!("{:#}", error)).into()
+}
+
 /// Starts periodic cleanup tasks for authentication state.
 ///
 /// # Arguments
@@ -366,7 +620,11 @@ fn start_auth_cleanup_tasks(auth_state: std::sync::Arc<crate::server::auth::Auth
                 }
             };
 
-            if let Some(database) = database {
+            if let Some(database) = database
+                && crate::server::lease::SESSION_CLEANUP
+                    .acquire(&database)
+                    .await
+            {
                 match database.cleanup_expired_sessions_async().await {
                     Ok(count) => {
                         if count > 0 {
@@ -375,6 +633,14 @@ fn start_auth_cleanup_tasks(auth_state: std::sync::Arc<crate::server::auth::Auth
                     }
                     Err(e) => tracing::warn!("Session cleanup failed: {}", e),
                 }
+                match database
+                    .purge_idle_mcp_sessions_async(crate::server::mcp_sessions::SESSION_IDLE_TTL)
+                    .await
+                {
+                    Ok(0) => {}
+                    Ok(count) => tracing::debug!("Forgot {} idle MCP sessions", count),
+                    Err(e) => tracing::warn!("MCP session cleanup failed: {}", e),
+                }
             }
         }
     });
@@ -389,21 +655,29 @@ fn start_auth_cleanup_tasks(auth_state: std::sync::Arc<crate::server::auth::Auth
 ///
 /// # Returns
 /// (Router, bool) - Router and whether API server is enabled
+///
+/// # Errors
+/// Returns an error if `management_server.metrics_auth` or
+/// `management_server.ip_filter` is invalid
 fn build_management_router(
     state: std::sync::Arc<ArkState>,
     config: &ArkConfig,
     auth_state: std::sync::Arc<crate::server::auth::AuthState>,
-) -> (Router, bool) {
+    live: &LiveSettings,
+) -> anyhow::Result<(Router, bool)> {
     let mut router = Router::new();
     let mut enable_api_server = false;
     let transport = state.get_transport();
 
-    // Add conditional routes
-    if state.is_plugin_api_enabled() {
-        router = router.nest("/api", create_api_router(state.clone()));
-        enable_api_server = true;
-    }
+    let ip_filter = config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.ip_filter.as_ref())
+        .map(crate::server::ip_filter::IpFilter::from_config)
+        .transpose()?
+        .map(Arc::new);
 
+    // Add conditional routes
     if state.is_health_api_enabled() {
         let livez_path = config
             .management_server
@@ -413,22 +687,54 @@ fn build_management_router(
             .management_server
             .as_ref()
             .and_then(|m| m.readyz.path.clone());
+        let startupz_path = config
+            .management_server
+            .as_ref()
+            .and_then(|m| m.startupz.path.clone());
         router = router.merge(create_health_router(
             state.clone(),
             &livez_path,
             &readyz_path,
+            &startupz_path,
         ));
         enable_api_server = true;
     }
 
+    // Scrapers authenticate with `metrics_auth` instead of a session, so
+    // /metrics is then added after the `check_auth` layer below
+    #[cfg(feature = "prometheus")]
+    let metrics_auth = config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.metrics_auth.as_ref())
+        .filter(|_| state.is_prometheus_api_enabled())
+        .map(crate::server::metrics_auth::MetricsAuth::from_config)
+        .transpose()?
+        .map(Arc::new);
+
     #[cfg(feature = "prometheus")]
     if state.is_prometheus_api_enabled() {
-        router = router.route("/metrics", get(metrics_handler));
+        if metrics_auth.is_none() {
+            router = router.route("/metrics", get(metrics_handler));
+        }
         enable_api_server = true;
     }
 
     if state.is_console_enabled() {
-        router = router.nest("/admin", create_console_router(state.clone()));
+        let swagger_ui = config
+            .management_server
+            .as_ref()
+            .is_some_and(|m| m.swagger_ui);
+        let console_path = config
+            .management_server
+            .as_ref()
+            .map_or_else(crate::config::defaults::default_console_path, |m| {
+                m.console_path.clone()
+            });
+        router = router.nest(
+            "/admin",
+            create_console_router(state.clone(), swagger_ui, Path::new(&console_path)),
+        );
         let transport_str = match transport {
             McpTransport::Stdio => "stdio",
             McpTransport::Sse => "sse",
@@ -436,8 +742,9 @@ fn build_management_router(
         };
         router = router.route(
             "/",
-            get(move || async move {
-                Redirect::temporary(&format!("/admin?transport={}", transport_str))
+            get(move |extensions: axum::http::Extensions| async move {
+                let path = format!("/admin?transport={}", transport_str);
+                Redirect::temporary(&BasePath::url(&extensions, &path))
             }),
         );
         enable_api_server = true;
@@ -459,47 +766,127 @@ fn build_management_router(
         router = router.merge(oauth_router);
     }
 
+    // The API router sets its own limits, so it is added after this one
+    router = limit_body(router, state.get_body_limits().api);
+    if state.is_plugin_api_enabled() {
+        router = router.nest(API_V1_PREFIX, create_api_router(state.clone()));
+        router = router.nest(
+            "/api",
+            create_api_router(state.clone()).layer(middleware::from_fn(deprecate_unversioned_api)),
+        );
+        enable_api_server = true;
+    }
+
     // Apply middleware if API server enabled
     if enable_api_server {
+        // Added before `check_auth`, so it runs after it and sees the principal.
+        // Always installed, since a reload can turn the limits on.
+        let limiter = live.rate_limiter.clone();
+        let rate_auth_state = auth_state.clone();
+        router = router.layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                rate_limit(req, next, limiter.clone(), rate_auth_state.clone())
+            },
+        ));
+
         let auth_state_clone = auth_state.clone();
-        router = router
-            .layer(middleware::from_fn(
+        router =
+            router.layer(middleware::from_fn(
                 move |req: Request<Body>, next: Next| {
                     let auth_state = auth_state_clone.clone();
                     async move {
                         crate::server::auth::check_auth(req, next, Extension(auth_state)).await
                     }
                 },
-            ))
-            .layer(middleware::from_fn(log_requests));
+            ));
+
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics_auth) = metrics_auth {
+            let auth_state = auth_state.clone();
+            router = router.route(
+                "/metrics",
+                get(metrics_handler).layer(middleware::from_fn(
+                    move |req: Request<Body>, next: Next| {
+                        let metrics_auth = metrics_auth.clone();
+                        let auth_state = auth_state.clone();
+                        async move {
+                            let ip = auth_state.client_info(req.headers(), req.extensions()).ip;
+                            match metrics_auth.deny(req.headers(), ip) {
+                                Some(denied) => denied,
+                                None => next.run(req).await,
+                            }
+                        }
+                    },
+                )),
+            );
+        }
+
+        router = router.layer(middleware::from_fn(log_requests));
+
+        // Outermost, so refused clients are dropped before anything else runs
+        if let Some(ip_filter) = ip_filter {
+            let auth_state = auth_state.clone();
+            router = router.layer(middleware::from_fn(
+                move |req: Request<Body>, next: Next| {
+                    let ip_filter = ip_filter.clone();
+                    let auth_state = auth_state.clone();
+                    async move {
+                        let ip = auth_state.client_info(req.headers(), req.extensions()).ip;
+                        match ip_filter.deny(ip, req.uri().path()) {
+                            Some(denied) => denied,
+                            None => next.run(req).await,
+                        }
+                    }
+                },
+            ));
+        }
     }
 
-    (router, enable_api_server)
+    // Routes match without the prefix; links handed to clients include it
+    if let Some(base) = config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.normalized_base_path())
+    {
+        router = Router::new()
+            .nest(&base, router)
+            .layer(Extension(BasePath(base)));
+    }
+
+    Ok((router, enable_api_server))
 }
 
 /// Builds the MCP server task based on transport.
 ///
 /// # Arguments
 /// * `transport` - MCP transport type
+/// * `shared_sessions` - Store streamable HTTP sessions in the database
 /// * `state` - Shared application state
 /// * `auth_state` - Auth state
-/// * `mcp_bind_address` - Bind address for MCP server
+/// * `listen` - Where the MCP server accepts connections
 /// * `rustls_config` - Optional TLS config
-/// * `mcp_cors` - Optional CORS config
+/// * `mcp_cors` - CORS policy, changed in place by configuration reloads
+/// * `shutdown` - Cancelled to stop accepting connections and drain
 ///
 /// # Returns
 /// Optional server task handle
+#[allow(clippy::too_many_arguments)]
 async fn build_mcp_server_task(
     transport: McpTransport,
+    shared_sessions: bool,
     state: std::sync::Arc<ArkState>,
     auth_state: std::sync::Arc<crate::server::auth::AuthState>,
-    mcp_bind_address: String,
+    listen: Listen,
     rustls_config: Option<Arc<TlsAcceptor>>,
-    mcp_cors: Option<Cors>,
+    mcp_cors: Arc<RwLock<Option<CorsLayer>>>,
+    shutdown: CancellationToken,
 ) -> Option<tokio::task::JoinHandle<()>> {
     match transport {
         McpTransport::StreamableHTTP => {
-            let mut router = create_mcp_router(state.clone());
+            let mut router = limit_body(
+                create_mcp_router(state.clone(), shared_sessions),
+                state.get_body_limits().mcp,
+            );
             if auth_state.enabled {
                 router = router
                     .merge(oauth::router(auth_state.clone()))
@@ -519,14 +906,14 @@ async fn build_mcp_server_task(
             }
             Some(tokio::spawn(async move {
                 if let Err(e) =
-                    run_server(router, mcp_bind_address, rustls_config, mcp_cors, state).await
+                    run_server(router, listen, rustls_config, mcp_cors, state, shutdown).await
                 {
                     tracing::error!("MCP server error: {:?}", e);
                 }
             }))
         }
         McpTransport::Sse => {
-            let addr: SocketAddr = resolve_bind_addr(&mcp_bind_address).await.ok()?;
+            let addr: SocketAddr = resolve_bind_addr(listen.addrs().next()?).await.ok()?;
             let sse_config = SseServerConfig {
                 bind: addr,
                 sse_path: "/sse".to_string(),
@@ -539,7 +926,7 @@ async fn build_mcp_server_task(
             let _ct = sse_server.with_service(move || McpHandler {
                 state: state_for_closure.clone(),
             });
-            let mut router = sse_router;
+            let mut router = limit_body(sse_router, state.get_body_limits().mcp);
             if auth_state.enabled {
                 router = router
                     .merge(oauth::router(auth_state.clone()))
@@ -559,7 +946,7 @@ async fn build_mcp_server_task(
             }
             Some(tokio::spawn(async move {
                 if let Err(e) =
-                    run_server(router, mcp_bind_address, rustls_config, mcp_cors, state).await
+                    run_server(router, listen, rustls_config, mcp_cors, state, shutdown).await
                 {
                     tracing::error!("MCP server error: {:?}", e);
                 }
@@ -593,6 +980,72 @@ async fn build_mcp_server_task(
     }
 }
 
+/// Resolves on Ctrl+C, or on SIGTERM as sent by init scripts and `kill` (Unix).
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("Cannot handle SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// CORS policy of the management listener, if `management_server.cors` is set.
+pub(crate) fn management_cors(config: &ArkConfig) -> Option<Cors> {
+    config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.cors.as_ref())
+        .map(|origins| Cors {
+            origins: origins.clone(),
+            allowed_headers: Some(vec![
+                axum::http::HeaderName::from_static("content-type"),
+                axum::http::HeaderName::from_static("authorization"),
+                axum::http::HeaderName::from_static("x-requested-with"),
+                axum::http::HeaderName::from_static("mcp-session-id"),
+                axum::http::HeaderName::from_static(csrf::CSRF_HEADER),
+            ]),
+            allowed_methods: Some(vec![
+                axum::http::Method::POST,
+                axum::http::Method::OPTIONS,
+                axum::http::Method::GET,
+                axum::http::Method::DELETE,
+                axum::http::Method::PATCH,
+                axum::http::Method::PUT,
+            ]),
+            allow_credentials: true,
+        })
+}
+
+/// CORS policy of the MCP listener, if `mcp_server.cors` is set.
+pub(crate) fn mcp_cors(config: &ArkConfig) -> Option<Cors> {
+    config
+        .mcp_server
+        .as_ref()
+        .and_then(|m| m.cors.as_ref())
+        .map(|origins| Cors {
+            origins: origins.clone(),
+            allowed_headers: Some(vec![
+                axum::http::HeaderName::from_static("content-type"),
+                axum::http::HeaderName::from_static("authorization"),
+                axum::http::HeaderName::from_static("x-requested-with"),
+                axum::http::HeaderName::from_static("mcp-protocol-version"),
+                axum::http::HeaderName::from_static("mcp-session-id"),
+            ]),
+            allowed_methods: Some(vec![axum::http::Method::POST, axum::http::Method::OPTIONS]),
+            allow_credentials: true,
+        })
+}
+
 /// Main entry point for starting all servers.
 ///
 /// Orchestrates server setup and shutdown.
@@ -615,26 +1068,66 @@ pub async fn start(config: &ArkConfig, state: std::sync::Arc<ArkState>) -> anyho
     // Build auth state and cleanup
     let auth_state = build_auth_state_and_cleanup(config, state.clone()).await?;
 
+    // Maintain the usage analytics rollups
+    crate::server::usage::start_rollup_task(state.clone());
+
+    // Notify webhooks of plugin changes and failing tools
+    if let Some(webhooks) = &config.webhooks {
+        crate::server::webhooks::start_webhook_task(state.clone(), webhooks);
+    }
+
+    // Settings a configuration reload can change in place
+    let live = state
+        .config_reloader()
+        .map(|r| r.live.clone())
+        .unwrap_or_else(|| Arc::new(LiveSettings::new(config)));
+
+    state.set_trusted_proxies(crate::server::proxy::TrustedProxies::from_config(
+        &config.trusted_proxies,
+    )?);
+
     // Build management router
     let (management_router, enable_api_server) =
-        build_management_router(state.clone(), config, auth_state.clone());
-
-    // TLS and CORS setup
-    let tls_key_material = get_tls_key_material(config).await;
-    let rustls_config = tls_key_material.ok().and_then(|material| {
-        let certs = rustls_pemfile::certs(&mut material.certs.as_slice())
-            .collect::<Result<Vec<_>, _>>()
-            .context("Failed to parse certificates")
-            .ok()?;
-        let key = rustls_pemfile::private_key(&mut material.key.as_slice())
-            .context("Failed to parse private key")
-            .ok()??;
-        let config = rustls::ServerConfig::builder()
-            .with_no_client_auth()
-            .with_single_cert(certs, key)
-            .ok()?;
-        Some(Arc::new(TlsAcceptor::from(Arc::new(config))))
-    });
+        build_management_router(state.clone(), config, auth_state.clone(), &live)?;
+
+    // TLS and CORS setup; each listener may replace the top-level TLS block
+    let global_tls = config.tls.clone().unwrap_or_default();
+    let management_tls_config = config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.tls.clone())
+        .unwrap_or_else(|| global_tls.clone());
+    let mcp_tls_config = config
+        .mcp_server
+        .as_ref()
+        .and_then(|m| m.tls.clone())
+        .unwrap_or(global_tls);
+    let mut acme_managers = Vec::new();
+    let management_tls = listener_tls_acceptor(
+        "management",
+        &management_tls_config,
+        management_tls_config
+            .client_auth
+            .as_ref()
+            .and_then(|c| c.management.as_ref()),
+        &state,
+        &mut acme_managers,
+    )
+    .await?;
+    let mcp_tls = listener_tls_acceptor(
+        "mcp",
+        &mcp_tls_config,
+        mcp_tls_config
+            .client_auth
+            .as_ref()
+            .and_then(|c| c.mcp.as_ref()),
+        &state,
+        &mut acme_managers,
+    )
+    .await?;
+    for (_, manager) in acme_managers {
+        manager.spawn(state.clone()).await?;
+    }
 
     let management_bind_address = config
         .management_server
@@ -649,55 +1142,33 @@ pub async fn start(config: &ArkConfig, state: std::sync::Arc<ArkState>) -> anyho
         .unwrap_or(&"127.0.0.1:3000".to_string())
         .clone();
 
-    let management_cors = config
-        .management_server
-        .as_ref()
-        .and_then(|m| m.cors.as_ref())
-        .map(|origins| Cors {
-            origins: origins.clone(),
-            allowed_headers: Some(vec![
-                axum::http::HeaderName::from_static("content-type"),
-                axum::http::HeaderName::from_static("authorization"),
-                axum::http::HeaderName::from_static("x-requested-with"),
-                axum::http::HeaderName::from_static("mcp-session-id"),
-            ]),
-            allowed_methods: Some(vec![
-                axum::http::Method::POST,
-                axum::http::Method::OPTIONS,
-                axum::http::Method::GET,
-                axum::http::Method::DELETE,
-            ]),
-            allow_credentials: true,
-        });
-
-    let mcp_cors = config
-        .mcp_server
-        .as_ref()
-        .and_then(|m| m.cors.as_ref())
-        .map(|origins| Cors {
-            origins: origins.clone(),
-            allowed_headers: Some(vec![
-                axum::http::HeaderName::from_static("content-type"),
-                axum::http::HeaderName::from_static("authorization"),
-                axum::http::HeaderName::from_static("x-requested-with"),
-                axum::http::HeaderName::from_static("mcp-protocol-version"),
-                axum::http::HeaderName::from_static("mcp-session-id"),
-            ]),
-            allowed_methods: Some(vec![axum::http::Method::POST, axum::http::Method::OPTIONS]),
-            allow_credentials: true,
-        });
+    let management_cors = live.management_cors.clone();
+    let mcp_cors = live.mcp_cors.clone();
+    let listeners = config.listeners.clone().unwrap_or_default();
+    let timeouts = ConnectionTimeouts::from_config(&listeners);
+    // Cancelled on shutdown: the listeners close and open connections drain
+    let shutdown = CancellationToken::new();
 
     // Spawn servers
     let state_clone = state.clone();
-    let rustls_clone = rustls_config.clone();
+    let management_listen = Listen {
+        name: MANAGEMENT_SOCKET,
+        addr: management_bind_address,
+        reuse_port: listeners.reuse_port,
+        timeouts,
+        max_connections: listeners.max_connections,
+        max_concurrent_requests: listeners.max_concurrent_requests,
+    };
+    let management_shutdown = shutdown.clone();
     let mut management_handle = if enable_api_server {
         Some(tokio::spawn(async move {
             if let Err(e) = run_server(
                 management_router,
-                management_bind_address,
-                rustls_clone,
+                management_listen,
+                management_tls,
                 management_cors,
                 state_clone,
+                management_shutdown,
             )
             .await
             {
@@ -709,14 +1180,26 @@ pub async fn start(config: &ArkConfig, state: std::sync::Arc<ArkState>) -> anyho
     };
 
     let state_for_mcp = state.clone();
-    let rustls_for_mcp = rustls_config.clone();
+    let shared_sessions = config
+        .mcp_server
+        .as_ref()
+        .is_some_and(|m| m.shared_sessions);
     let mut mcp_handle = build_mcp_server_task(
         state.get_transport(),
+        shared_sessions,
         state_for_mcp,
         auth_state,
-        mcp_bind_address,
-        rustls_for_mcp,
+        Listen {
+            name: MCP_SOCKET,
+            addr: mcp_bind_address,
+            reuse_port: listeners.reuse_port,
+            timeouts,
+            max_connections: listeners.max_connections,
+            max_concurrent_requests: listeners.max_concurrent_requests,
+        },
+        mcp_tls,
         mcp_cors,
+        shutdown.clone(),
     )
     .await;
 
@@ -725,7 +1208,7 @@ pub async fn start(config: &ArkConfig, state: std::sync::Arc<ArkState>) -> anyho
     let mut management_result = None;
 
     tokio::select! {
-        _ = tokio::signal::ctrl_c() => tracing::info!("Shutdown signal received"),
+        _ = shutdown_signal() => tracing::info!("Shutdown signal received"),
         res = async {
             match &mut management_handle {
                 Some(handle) => handle.await,
@@ -740,7 +1223,10 @@ pub async fn start(config: &ArkConfig, state: std::sync::Arc<ArkState>) -> anyho
         } => mcp_result = Some(res),
     }
 
-    // Handle results and abort remaining tasks
+    state.set_state(ApplicationState::Terminating);
+    shutdown.cancel();
+
+    // Handle results; remaining tasks drain, then are aborted
     if let Some(ref res) = management_result {
         match res {
             Ok(()) => tracing::debug!("Management server exited normally"),
@@ -754,33 +1240,128 @@ pub async fn start(config: &ArkConfig, state: std::sync::Arc<ArkState>) -> anyho
         }
     }
 
+    let drain = Duration::from_secs(listeners.drain_timeout_secs);
     if management_result.is_none()
         && let Some(h) = management_handle
     {
-        h.abort();
-        let _ = h.await;
+        drain_or_abort(h, drain).await;
     }
     if mcp_result.is_none()
         && let Some(h) = mcp_handle
     {
-        h.abort();
-        let _ = h.await;
+        drain_or_abort(h, drain).await;
+    }
+
+    // Short-lived runs are unlikely to have been scraped; hand the final
+    // values to the Pushgateway instead
+    if let Some(push_gateway) = config
+        .management_server
+        .as_ref()
+        .and_then(|m| m.push_gateway.as_ref())
+    {
+        match crate::metrics::handler::push_to_gateway(push_gateway).await {
+            Ok(()) => tracing::info!("Pushed final metrics to {}", push_gateway.url),
+            Err(e) => tracing::warn!("Failed to push metrics to {}: {:?}", push_gateway.url, e),
+        }
     }
 
     Ok(())
 }
 
+/// Waits up to `drain` for a server task to finish its open connections,
+/// then aborts it.
+async fn drain_or_abort(mut handle: tokio::task::JoinHandle<()>, drain: Duration) {
+    if tokio::time::timeout(drain, &mut handle).await.is_err() {
+        tracing::info!("Closing the connections still open after {:?}", drain);
+        handle.abort();
+        let _ = handle.await;
+    }
+}
+
+/// Where a server accepts connections.
+struct Listen {
+    /// Name of the sockets systemd may pass (see [`crate::server::systemd`])
+    name: &'static str,
+    /// Comma-separated addresses bound without such sockets, e.g.
+    /// "127.0.0.1:8000" or "127.0.0.1:8000,[::1]:8000"
+    addr: String,
+    /// Bind with SO_REUSEPORT (see [`crate::config::models::ListenersConfig`])
+    reuse_port: bool,
+    /// Timeouts of every accepted connection
+    timeouts: ConnectionTimeouts,
+    /// Connections kept open at once over all addresses; 0 is unlimited
+    max_connections: usize,
+    /// Requests handled at once over all addresses; 0 is unlimited
+    max_concurrent_requests: usize,
+}
+
+impl Listen {
+    /// Configured addresses.
+    fn addrs(&self) -> impl Iterator<Item = &str> {
+        self.addr
+            .split(',')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+    }
+
+    /// Binds every address, or takes the sockets systemd passed instead.
+    async fn bind(&self) -> anyhow::Result<Vec<tokio::net::TcpListener>> {
+        let listeners = take_listeners(self.name)?;
+        if !listeners.is_empty() {
+            return Ok(listeners);
+        }
+        let mut listeners = Vec::new();
+        for addr in self.addrs() {
+            let addr: SocketAddr = addr
+                .parse()
+                .with_context(|| format!("Invalid bind address {}", addr))?;
+            listeners.push(self.bind_addr(addr).await?);
+        }
+        if listeners.is_empty() {
+            bail!("No bind address for the {} listener", self.name);
+        }
+        Ok(listeners)
+    }
+
+    async fn bind_addr(&self, addr: SocketAddr) -> anyhow::Result<tokio::net::TcpListener> {
+        if !self.reuse_port {
+            return tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Cannot bind {}", addr));
+        }
+        let socket = socket2::Socket::new(
+            socket2::Domain::for_address(addr),
+            socket2::Type::STREAM,
+            Some(socket2::Protocol::TCP),
+        )?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        #[cfg(not(unix))]
+        tracing::warn!("listeners.reuse_port is only supported on Unix");
+        socket.set_nonblocking(true)?;
+        socket
+            .bind(&addr.into())
+            .with_context(|| format!("Cannot bind {}", addr))?;
+        socket.listen(1024)?;
+        Ok(tokio::net::TcpListener::from_std(socket.into())?)
+    }
+}
+
 /// Runs a single server instance with the given configuration.
 ///
-/// Binds to the specified address and serves the router, with optional TLS.
-/// Sets application state to Ready when server starts successfully.
+/// Serves the router on the sockets systemd passed for the listener, or else
+/// binds to its addresses, with optional TLS. Sets application state to Ready
+/// when server starts successfully. Once `shutdown` is cancelled no more
+/// connections are accepted and it returns when the open ones are done.
 ///
 /// # Arguments
 /// * `router` - The Axum router to serve
-/// * `addr` - Bind address as string (e.g., "127.0.0.1:8000")
+/// * `listen` - Where to accept connections
 /// * `tls_config` - Optional TLS configuration
-/// * `cors_config` - Optional CORS configuration (currently unused)
+/// * `cors` - CORS policy, changed in place by configuration reloads
 /// * `state` - Shared application state
+/// * `shutdown` - Cancelled to stop accepting connections
 ///
 /// # Returns
 /// `Ok(())` on successful server operation, or an error
@@ -789,59 +1370,262 @@ pub async fn start(config: &ArkConfig, state: std::sync::Arc<ArkState>) -> anyho
 /// Returns an error if binding fails or server encounters issues
 async fn run_server(
     router: Router,
-    addr: String,
+    listen: Listen,
     tls_acceptor: Option<Arc<TlsAcceptor>>,
-    cors_config: Option<Cors>,
+    cors: Arc<RwLock<Option<CorsLayer>>>,
     state: std::sync::Arc<ArkState>,
+    shutdown: CancellationToken,
 ) -> anyhow::Result<()> {
-    let sock_addr: SocketAddr = addr.parse()?;
-
-    // Apply CORS layer if configured
-    let app = if let Some(cors) = cors_config {
-        router.layer(cors.into_layer())
-    } else {
-        router
+    // Apply the CORS policy in effect, if configured
+    let app = router.layer(middleware::from_fn(
+        move |req: Request<Body>, next: Next| {
+            let layer = cors.read().unwrap_or_else(|e| e.into_inner()).clone();
+            async move {
+                match layer {
+                    Some(layer) => match layer.layer(next).oneshot(req).await {
+                        Ok(response) => response,
+                        Err(infallible) => match infallible {},
+                    },
+                    None => next.run(req).await,
+                }
+            }
+        },
+    ));
+
+    // Past capacity, answer at once rather than queue requests without bound
+    let app = match listen.max_concurrent_requests {
+        0 => app,
+        limit => {
+            let name = listen.name;
+            app.layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(move |_: BoxError| async move {
+                        overloaded(name)
+                    }))
+                    .load_shed()
+                    .layer(GlobalConcurrencyLimitLayer::new(limit)),
+            )
+        }
     };
 
-    // Add tracing layer for request logging
-    let app = app.layer(TraceLayer::new_for_http());
+    // Add tracing layer for request logging, inside the request id scope
+    let proxies = Arc::new(state.get_trusted_proxies());
+    let app = app
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|req: &Request<Body>| {
+                let request_id = req.extensions().get::<RequestId>().map(|id| id.0.as_str());
+                let client = crate::server::proxy::client_ip(req.extensions());
+                tracing::debug_span!(
+                    "request",
+                    method = %req.method(),
+                    uri = %req.uri(),
+                    version = ?req.version(),
+                    client = ?client,
+                    request_id,
+                )
+            }),
+        )
+        .layer(middleware::from_fn(assign_request_id))
+        // Outermost, so every layer sees the client behind a trusted proxy
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let proxies = proxies.clone();
+                async move { crate::server::proxy::resolve_forwarded(&proxies, req, next).await }
+            },
+        ));
 
-    tracing::debug!("Listening on {}", sock_addr);
+    // Every address is bound before any is served, so a failure leaves none
+    // half started
+    let listeners = listen.bind().await?;
+    state.clone().set_state(ApplicationState::Ready);
+    let connection_slots =
+        (listen.max_connections > 0).then(|| Arc::new(Semaphore::new(listen.max_connections)));
+    let serving: Vec<_> = listeners
+        .into_iter()
+        .map(|listener| {
+            serve(
+                listener,
+                app.clone(),
+                tls_acceptor.clone(),
+                &listen,
+                connection_slots.clone(),
+                shutdown.clone(),
+            )
+        })
+        .collect();
+    futures::future::try_join_all(serving).await?;
+    Ok(())
+}
 
-    let listener = tokio::net::TcpListener::bind(sock_addr).await?;
+/// Answer to requests past a listener's `max_concurrent_requests`.
+fn overloaded(listener: &str) -> Response {
+    crate::metrics::record_overloaded(listener, "request");
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, "1")],
+        StandardizedResponse::as_error(ErrorCode::Overloaded, "Server overloaded", None),
+    )
+        .into_response()
+}
 
-    if let Some(acceptor) = tls_acceptor {
-        state.clone().set_state(ApplicationState::Ready);
+/// Accept loop of one bound address, until `shutdown` is cancelled and the
+/// open connections are done.
+async fn serve(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    tls_acceptor: Option<Arc<TlsAcceptor>>,
+    listen: &Listen,
+    connection_slots: Option<Arc<Semaphore>>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let timeouts = listen.timeouts;
+    let sock_addr = listener.local_addr()?;
+    tracing::debug!("Listening on {}", sock_addr);
+    if tls_acceptor.is_some() {
         tracing::info!("Starting TLS server on https://{}", sock_addr);
+    } else {
+        tracing::info!("Starting plain HTTP server on http://{}", sock_addr);
+    }
 
-        loop {
-            let (stream, _) = listener.accept().await?;
-            let acceptor = acceptor.clone();
-            let app = app.clone();
-
-            tokio::spawn(async move {
-                let tls_stream = match acceptor.accept(stream).await {
-                    Ok(s) => s,
-                    Err(e) => {
-                        tracing::warn!("TLS accept failed: {}", e);
+    let connections = TaskTracker::new();
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown.cancelled() => break,
+        };
+        // Past capacity the connection is closed at once
+        let slot = match &connection_slots {
+            Some(slots) => match slots.clone().try_acquire_owned() {
+                Ok(slot) => Some(slot),
+                Err(_) => {
+                    tracing::debug!("Closing connection from {}: too many connections", peer);
+                    crate::metrics::record_overloaded(listen.name, "connection");
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let acceptor = tls_acceptor.clone();
+        let shutdown = shutdown.clone();
+        // Expose the peer address to handlers, as `into_make_service_with_connect_info` does
+        let app = app.clone().layer(Extension(ConnectInfo(peer)));
+
+        connections.spawn(async move {
+            let _slot = slot;
+            let activity = Arc::new(Activity::default());
+            let stream = TimeoutIo::new(stream, timeouts, activity.clone());
+            let app = app.layer(middleware::from_fn(
+                move |req: Request<Body>, next: Next| track_activity(activity.clone(), req, next),
+            ));
+            let Some(acceptor) = acceptor else {
+                serve_connection(stream, app, timeouts, true, shutdown).await;
+                return;
+            };
+            let handshake = acceptor.accept(stream);
+            let tls_stream = match timeouts.header {
+                Some(limit) => match tokio::time::timeout(limit, handshake).await {
+                    Ok(accepted) => accepted,
+                    Err(_) => {
+                        tracing::debug!("TLS handshake with {} timed out", peer);
                         return;
                     }
-                };
-                let service = TowerToHyperService::new(app);
-                let _ = http1::Builder::new()
-                    .serve_connection(TokioIo::new(tls_stream), service)
-                    .await;
-            });
-        }
-    } else {
-        state.clone().set_state(ApplicationState::Ready);
-        tracing::info!("Starting plain HTTP server on http://{}", sock_addr);
-        axum::serve(listener, app).await?;
+                },
+                None => handshake.await,
+            };
+            match tls_stream {
+                // HTTP/1.1 or HTTP/2, as negotiated with ALPN
+                Ok(tls_stream) => {
+                    serve_connection(tls_stream, app, timeouts, false, shutdown).await
+                }
+                Err(e) => tracing::warn!("TLS accept failed: {}", e),
+            }
+        });
     }
+    drop(listener);
+    connections.close();
+    connections.wait().await;
 
     Ok(())
 }
 
+/// Serves the requests of one connection, finishing the request in progress
+/// and closing once `shutdown` is cancelled.
+async fn serve_connection<I>(
+    io: I,
+    app: Router,
+    timeouts: ConnectionTimeouts,
+    http1_only: bool,
+    shutdown: CancellationToken,
+) where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(timeouts.header);
+    if http1_only {
+        builder = builder.http1_only();
+    }
+    let service = TowerToHyperService::new(app);
+    let connection = builder.serve_connection(TokioIo::new(io), service);
+    tokio::pin!(connection);
+    let result = tokio::select! {
+        result = connection.as_mut() => result,
+        _ = shutdown.cancelled() => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    };
+    if let Err(e) = result {
+        tracing::debug!("Connection closed: {}", e);
+    }
+}
+
+/// Middleware giving every request an id, returned in `x-request-id`.
+///
+/// A well-formed incoming `x-request-id` (up to 128 characters of letters,
+/// digits, `-`, `_` and `.`) is kept so ids can be followed across services;
+/// otherwise a random one is generated. Everything logged while handling the
+/// request carries the id, as do error responses and audit records; handlers
+/// running outside the request's task find it in the [`RequestId`] extension.
+pub async fn assign_request_id(mut req: Request<Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 128
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| hex::encode(rand::random::<[u8; 16]>()));
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let mut response = crate::logging::with_request_id(request_id.clone(), next.run(req)).await;
+    if let Ok(value) = axum::http::HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Header carrying the request id in both directions.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Largest body logged at trace level. Bodies that may be larger are not
+/// buffered, so logging never holds a whole upload in memory.
+const MAX_LOGGED_BODY_BYTES: u64 = 64 * 1024;
+
+/// Whether `body` is known to be small enough to be logged.
+fn is_loggable(body: &Body) -> bool {
+    axum::body::HttpBody::size_hint(body)
+        .upper()
+        .is_some_and(|n| n <= MAX_LOGGED_BODY_BYTES)
+}
+
 /// Middleware to log incoming requests and outgoing responses.
 ///
 /// Logs request method and URI on entry, response status on exit.
@@ -855,11 +1639,12 @@ async fn log_requests(req: Request<Body>, next: Next) -> Response {
     );
 
     let is_admin = req.uri().path().starts_with("/admin");
+    let log_bodies = tracing::level_enabled!(tracing::Level::TRACE) && !is_admin;
 
-    // Log request body if trace level (skip for /admin)
-    let req = if tracing::level_enabled!(tracing::Level::TRACE) && !is_admin {
+    // Log request body if trace level (skip for /admin and large bodies)
+    let req = if log_bodies && is_loggable(req.body()) {
         let (parts, body) = req.into_parts();
-        let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        let body_bytes = match axum::body::to_bytes(body, MAX_LOGGED_BODY_BYTES as usize).await {
             Ok(b) => b,
             Err(e) => {
                 tracing::warn!("Failed to read request body: {}", e);
@@ -873,15 +1658,37 @@ async fn log_requests(req: Request<Body>, next: Next) -> Response {
         }
         Request::from_parts(parts, Body::from(body_bytes))
     } else {
+        if log_bodies {
+            tracing::trace!("Request body: <not logged, may exceed {MAX_LOGGED_BODY_BYTES} bytes>");
+        }
         req
     };
 
-    let response = next.run(req).await;
-
-    // Log response body if trace level (skip for /admin)
-    let response = if tracing::level_enabled!(tracing::Level::TRACE) && !is_admin {
+    let route = req
+        .extensions()
+        .get::<axum::extract::MatchedPath>()
+        .map_or_else(|| req.uri().path().to_string(), |p| p.as_str().to_string());
+    let request_id = crate::logging::current_request_id();
+    let span = tracing::info_span!(
+        "http.request",
+        "http.request.method" = %req.method(),
+        "http.route" = route,
+        "http.response.status_code" = tracing::field::Empty,
+        request_id = request_id.as_deref()
+    );
+    crate::telemetry::set_remote_parent(&span, req.headers());
+    let response = next.run(req).instrument(span.clone()).await;
+    span.record("http.response.status_code", response.status().as_u16());
+
+    // Log response body if trace level (skip for /admin and event streams,
+    // which never end)
+    let is_stream = response
+        .headers()
+        .get(axum::http::header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"text/event-stream"));
+    let response = if log_bodies && !is_stream && is_loggable(response.body()) {
         let (parts, body) = response.into_parts();
-        let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        let body_bytes = match axum::body::to_bytes(body, MAX_LOGGED_BODY_BYTES as usize).await {
             Ok(b) => b,
             Err(e) => {
                 tracing::warn!("Failed to read response body: {}", e);
@@ -895,6 +1702,11 @@ async fn log_requests(req: Request<Body>, next: Next) -> Response {
         }
         Response::from_parts(parts, Body::from(body_bytes))
     } else {
+        if log_bodies && !is_stream {
+            tracing::trace!(
+                "Response body: <not logged, may exceed {MAX_LOGGED_BODY_BYTES} bytes>"
+            );
+        }
         response
     };
 
@@ -902,10 +1714,63 @@ async fn log_requests(req: Request<Body>, next: Next) -> Response {
     response
 }
 
+/// Path prefix of the management listener (`management_server.base_path`),
+/// added to its requests as an extension.
+#[derive(Debug, Clone)]
+pub struct BasePath(pub String);
+
+impl BasePath {
+    /// Returns the root-relative `path` under the base path of a request.
+    pub fn url(extensions: &axum::http::Extensions, path: &str) -> String {
+        match extensions.get::<BasePath>() {
+            Some(BasePath(base)) => format!("{base}{path}"),
+            None => path.to_string(),
+        }
+    }
+}
+
+/// Prefix of the current version of the management API.
+///
+/// The same routes remain reachable under the unversioned `/api` prefix for
+/// existing clients; those responses are marked deprecated.
+pub const API_V1_PREFIX: &str = "/api/v1";
+
+/// Maps a versioned management API path to its unversioned form.
+///
+/// `/api/v1/plugins` becomes `/api/plugins`; other paths are returned as is.
+/// Path-based policy (public paths, admin prefixes, tool execution) is
+/// written against the unversioned form so it applies to both prefixes.
+pub fn unversioned_api_path(path: &str) -> std::borrow::Cow<'_, str> {
+    match path.strip_prefix(API_V1_PREFIX) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("/api{rest}").into(),
+        _ => path.into(),
+    }
+}
+
+/// Middleware marking responses from the unversioned `/api` routes as deprecated.
+///
+/// Adds a `Deprecation` header and a `Link` to the `/api/v1` equivalent so
+/// clients can find the successor before the unversioned paths are removed.
+async fn deprecate_unversioned_api(req: Request<Body>, next: Next) -> Response {
+    // Nested routers see the path without the `/api` prefix
+    let successor = format!(
+        "<{}{}>; rel=\"successor-version\"",
+        API_V1_PREFIX,
+        req.uri().path()
+    );
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", axum::http::HeaderValue::from_static("true"));
+    if let Ok(value) = axum::http::HeaderValue::from_str(&successor) {
+        headers.insert(axum::http::header::LINK, value);
+    }
+    response
+}
+
 /// Creates the router for plugin management API endpoints.
 ///
 /// Includes routes for listing, creating, deleting, and executing plugins.
-/// All routes are prefixed with `/api`.
+/// The router is mounted under both [`API_V1_PREFIX`] and `/api`.
 ///
 /// # Arguments
 /// * `state` - Shared application state
@@ -914,21 +1779,82 @@ async fn log_requests(req: Request<Body>, next: Next) -> Response {
 /// Configured router with API routes
 pub fn create_api_router(state: std::sync::Arc<ArkState>) -> Router {
     tracing::debug!("Creating plugin API router");
-    Router::new()
+    let limits = state.get_body_limits();
+    let router = Router::new()
         .route("/plugins", get(get_plugins).post(create_plugin))
         .route("/plugins/{id}", get(get_plugin_by_id).delete(delete_plugin))
-        .route("/plugins/{id}/tools", post(execute_plugin_tool))
+        .route("/plugins/{id}/tools/{tool_id}", post(execute_plugin_tool))
+        .route("/plugins/{id}/export", get(export_plugin))
+        .route("/tools", get(search_tools))
+        .route("/events", get(stream_events))
+        .route("/executions", get(list_executions))
+        .route("/openapi.json", get(crate::server::openapi::get_openapi))
+        .route("/me", get(get_me).patch(update_me))
+        .route("/me/usage", get(get_my_usage))
+        .route("/me/tokens", get(list_tokens).post(create_token))
+        .route("/me/tokens/{id}", delete(revoke_token))
+        .route("/me/password", post(change_own_password))
+        .route("/admin/users", get(list_users).post(create_user))
+        .route("/admin/users/{username}", delete(delete_user))
+        .route("/admin/users/{username}/password", put(set_user_password))
+        .route(
+            "/admin/service-accounts",
+            get(list_service_accounts).post(create_service_account),
+        )
+        .route(
+            "/admin/service-accounts/{id}",
+            delete(delete_service_account),
+        )
+        .route(
+            "/admin/service-accounts/{id}/secret",
+            post(rotate_service_account_secret),
+        )
+        .route(
+            "/admin/service-accounts/{id}/tokens",
+            get(list_service_account_tokens).post(create_service_account_token),
+        )
+        .route(
+            "/admin/service-accounts/{id}/tokens/{key_id}",
+            delete(revoke_service_account_token),
+        )
+        .route("/admin/audit/auth-events", get(list_auth_events))
+        .route("/admin/loglevel", get(get_log_level).put(set_log_level))
+        .route("/admin/config", get(get_config))
+        .route("/admin/reload", post(reload_config))
+        .route("/admin/stats", get(get_stats))
+        .route("/admin/stats/top", get(get_top_usage))
+        .route("/admin/usage", get(get_usage_report));
+    // Bundle uploads get the larger upload limit instead of the API one
+    let uploads = Router::new().route("/plugins/import", post(import_plugin));
+    limit_body(router, limits.api)
+        .merge(limit_body(uploads, limits.upload))
         .with_state(state)
 }
 
+/// Caps the request bodies of the routes added to `router` so far at
+/// `limit` bytes, answering larger requests with 413 Payload Too Large.
+///
+/// This replaces axum's default 2 MiB limit on extractors, so the limit
+/// also covers services that read the body themselves.
+fn limit_body<S>(router: Router<S>, limit: usize) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(limit))
+}
+
 /// Creates the router for health check endpoints.
 ///
-/// Includes liveness and readiness probes at `/livez` and `/readyz`.
+/// Includes liveness, readiness and startup probes at `/livez`, `/readyz`
+/// and `/startupz`.
 ///
 /// # Arguments
 /// * `state` - Shared application state
 /// * `livez_path` - Optional custom liveness path
 /// * `readyz_path` - Optional custom readiness path
+/// * `startupz_path` - Optional custom startup path
 ///
 /// # Returns
 /// Configured router with health check routes
@@ -936,6 +1862,7 @@ pub fn create_health_router(
     state: std::sync::Arc<ArkState>,
     livez_path: &Option<String>,
     readyz_path: &Option<String>,
+    startupz_path: &Option<String>,
 ) -> Router {
     tracing::debug!("Creating health API router");
     Router::new()
@@ -947,49 +1874,79 @@ pub fn create_health_router(
             &readyz_path.clone().unwrap_or("/readyz".to_string()),
             get(readyz),
         )
+        .route(
+            &startupz_path.clone().unwrap_or("/startupz".to_string()),
+            get(startupz),
+        )
         .with_state(state)
 }
 
 /// Creates the router for the admin console SPA.
 ///
-/// Serves static assets and provides client-side routing fallback.
-/// Assumes static files are built to `www/dist/`.
+/// Serves static assets and provides client-side routing fallback. Assets
+/// are served pre-compressed when a `.br` or `.gz` sibling exists, and the
+/// content-hashed files under `assets/` are cached as immutable.
 ///
 /// # Arguments
 /// * `state` - Shared application state
+/// * `swagger_ui` - Whether to serve Swagger UI at `/api-docs`
+/// * `console_path` - Directory of the console build (`www/dist` by default)
 ///
 /// # Returns
 /// Configured router for admin console
-pub fn create_console_router(state: std::sync::Arc<ArkState>) -> Router {
-    tracing::debug!("Creating console router");
+pub fn create_console_router(
+    state: std::sync::Arc<ArkState>,
+    swagger_ui: bool,
+    console_path: &Path,
+) -> Router {
+    tracing::debug!("Creating console router from {}", console_path.display());
+    if !console_path.join("index.html").is_file() {
+        tracing::warn!(
+            "Admin console not found: {} has no index.html",
+            console_path.display()
+        );
+    }
+    let console_dir = Arc::new(console_path.to_path_buf());
     let transport = state.get_transport();
     let transport_str = match transport {
         McpTransport::Stdio => "stdio",
         McpTransport::Sse => "sse",
         McpTransport::StreamableHTTP => "streamable-http",
     };
-    Router::new()
-        // Serve static assets like JS, CSS, images
-        .nest_service("/assets", ServeDir::new("www/dist/assets"))
-        // Serve fonts from public/fonts (copied to dist/fonts during build)
-        .nest_service("/fonts", ServeDir::new("www/dist/fonts"))
+    let mut router = Router::new();
+    if swagger_ui {
+        router = router.route("/api-docs", get(crate::server::openapi::swagger_ui));
+    }
+    // Asset file names carry a content hash, so they never change
+    let assets = Router::new()
+        .nest_service("/assets", console_files(console_path.join("assets")))
+        .layer(middleware::map_response(|response: Response| async move {
+            with_cache_control(response, "public, max-age=31536000, immutable")
+        }));
+    // Serve fonts from public/fonts (copied to dist/fonts during build)
+    let fonts = Router::new()
+        .nest_service("/fonts", console_files(console_path.join("fonts")))
+        .layer(middleware::map_response(|response: Response| async move {
+            with_cache_control(response, "public, max-age=86400")
+        }));
+    router
+        .merge(assets)
+        .merge(fonts)
         // Serve index.html at admin root, with transport parameter redirect if missing
         .route(
             "/",
             get({
                 let transport_str_copy = transport_str;
-                move |query: axum::extract::Query<std::collections::HashMap<String, String>>| async move {
+                let console_dir = console_dir.clone();
+                move |extensions: axum::http::Extensions, query: axum::extract::Query<std::collections::HashMap<String, String>>| async move {
                     // Check if transport parameter is already present
                     if query.contains_key("transport") {
                         // Transport parameter exists, serve the SPA
-                        let index_path = PathBuf::from("www/dist/index.html");
-                        Html(
-                            std::fs::read_to_string(index_path)
-                                .unwrap_or_else(|_| "<h1>index.html not found</h1>".to_string()),
-                        ).into_response()
+                        console_index(&console_dir, &extensions).into_response()
                     } else {
                         // No transport parameter, redirect to include it
-                        Redirect::temporary(&format!("/admin?transport={}", transport_str_copy)).into_response()
+                        let path = format!("/admin?transport={}", transport_str_copy);
+                        Redirect::temporary(&BasePath::url(&extensions, &path)).into_response()
                     }
                 }
             }),
@@ -997,17 +1954,62 @@ pub fn create_console_router(state: std::sync::Arc<ArkState>) -> Router {
         // Catch-all route for SPA client-side routing (includes transport param handling)
         .route(
             "/{*path}",
-            get(|| async {
-                let index_path = PathBuf::from("www/dist/index.html");
-                Html(
-                    std::fs::read_to_string(index_path)
-                        .unwrap_or_else(|_| "<h1>index.html not found</h1>".to_string()),
-                )
+            get(move |extensions: axum::http::Extensions| async move {
+                console_index(&console_dir, &extensions)
             }),
         )
         .with_state(state)
 }
 
+/// Serves a directory of console files, preferring pre-compressed siblings.
+fn console_files(dir: PathBuf) -> ServeDir {
+    ServeDir::new(dir).precompressed_br().precompressed_gzip()
+}
+
+/// Sets the `Cache-Control` of a successful static file response.
+///
+/// `Vary: Accept-Encoding` keeps caches from mixing up the pre-compressed
+/// and plain variants.
+fn with_cache_control(mut response: Response, cache_control: &'static str) -> Response {
+    if response.status().is_success() {
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CACHE_CONTROL,
+            header::HeaderValue::from_static(cache_control),
+        );
+        headers.insert(
+            header::VARY,
+            header::HeaderValue::from_static("accept-encoding"),
+        );
+    }
+    response
+}
+
+/// Serves the console's `index.html`.
+///
+/// The console is built for `/admin/`; under a base path its asset URLs are
+/// moved below it, and `window.ARK_BASE_PATH` tells the scripts where the
+/// API and their lazily loaded assets live. The page is revalidated on every
+/// load, so a new console build takes effect at once.
+fn console_index(console_dir: &Path, extensions: &axum::http::Extensions) -> Response {
+    let index = std::fs::read_to_string(console_dir.join("index.html"))
+        .unwrap_or_else(|_| "<h1>index.html not found</h1>".to_string());
+    let index = match extensions.get::<BasePath>() {
+        Some(BasePath(base)) => {
+            let script = format!(
+                "<script>window.ARK_BASE_PATH = {};</script>\n</head>",
+                serde_json::Value::from(base.as_str())
+            );
+            index
+                .replace("\"/admin/", &format!("\"{base}/admin/"))
+                .replace("'/admin/", &format!("'{base}/admin/"))
+                .replacen("</head>", &script, 1)
+        }
+        None => index,
+    };
+    ([(header::CACHE_CONTROL, "no-cache")], Html(index)).into_response()
+}
+
 /// Creates the router for the MCP server.
 ///
 /// Uses the rmcp library to provide Model Context Protocol endpoints
@@ -1015,10 +2017,12 @@ pub fn create_console_router(state: std::sync::Arc<ArkState>) -> Router {
 ///
 /// # Arguments
 /// * `state` - Shared application state
+/// * `shared_sessions` - Store sessions in the database, so that any
+///   instance sharing it can continue them
 ///
 /// # Returns
 /// Configured router with MCP service
-fn create_mcp_router(state: std::sync::Arc<ArkState>) -> Router {
+fn create_mcp_router(state: std::sync::Arc<ArkState>, shared_sessions: bool) -> Router {
     tracing::debug!("Creating MCP router");
     // Build the rmcp streamable HTTP tower service and mount it at /mcp.
     let app_state = state.clone();
@@ -1027,8 +2031,13 @@ fn create_mcp_router(state: std::sync::Arc<ArkState>) -> Router {
             state: app_state.clone(),
         })
     };
-    let session_mgr = LocalSessionManager::default();
     let cfg = StreamableHttpServerConfig::default();
+    if shared_sessions {
+        let session_mgr = SharedSessionManager::new(state.clone());
+        let svc = StreamableHttpService::new(handler_factory, Arc::new(session_mgr), cfg);
+        return Router::new().nest_service("/mcp", svc);
+    }
+    let session_mgr = LocalSessionManager::default();
     let svc = StreamableHttpService::new(handler_factory, Arc::new(session_mgr), cfg);
 
     Router::new().nest_service("/mcp", svc)
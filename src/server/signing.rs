@@ -1,101 +1,57 @@
+use crate::config::models::TokenSigningConfig;
+use crate::errors::StartupError;
 use anyhow::{Context, Result};
-use std::sync::Arc;
-// Do not import StartupError here to avoid cross-crate path issues during
-// mixed bin/lib compilation. Return an anyhow error with a clear marker
-// string so the top-level binary can map it to a distinct exit code.
 use base64::Engine as _;
-use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_ENGINE;
-use jsonwebtoken::{EncodingKey, Header};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD as BASE64_ENGINE};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
 use pem as pem_crate;
 use sha2::{Digest, Sha256};
 use simple_asn1::{ASN1Block, from_der};
+use std::sync::Arc;
+use std::time::Duration;
 use x509_parser::parse_x509_certificate;
 
 #[derive(Clone)]
 pub struct PemSigner {
     kid: String,
+    algorithm: Algorithm,
     encoding_key: EncodingKey,
     jwk: serde_json::Value,
 }
 
+#[async_trait::async_trait]
 pub trait Signer: Send + Sync {
-    // TODO: implement this
+    /// Key id published in the JWKS and set on signed tokens.
     #[allow(dead_code)]
     fn kid(&self) -> &str;
-    fn sign(&self, header: Header, claims: &serde_json::Value) -> Result<String>;
+    /// Signs `claims`; the header's `alg` and `kid` are set from the key.
+    async fn sign(&self, header: Header, claims: &serde_json::Value) -> Result<String>;
     fn jwks(&self) -> serde_json::Value;
 }
 
 impl PemSigner {
+    /// Creates a signer from a PEM private key: RSA (PKCS#1 or PKCS#8, signing
+    /// with RS256), or PKCS#8 EC P-256 (ES256) or Ed25519 (EdDSA), as written
+    /// by `ark keygen`. The optional certificate must hold the matching public
+    /// key.
     pub fn from_pem(key_pem: &[u8], cert_pem: Option<&[u8]>) -> Result<Self> {
-        // Try to parse private key as PKCS1 or PKCS8 using the rsa crate's
-        // PEM parsers via the jsonwebtoken EncodingKey, but avoid depending
-        // on the `rsa` crate types for Windows. We still use EncodingKey to
-        // sign; extract the public modulus/exponent by parsing the PEM
-        // ourselves using simple_asn1 where necessary.
-        let encoding_key =
-            EncodingKey::from_rsa_pem(key_pem).context("failed to create encoding key from pem")?;
-
-        // Parse PEM to DER and extract modulus/exponent from private key
-        let pem = pem_crate::parse(std::str::from_utf8(key_pem).context("key pem to str")?)
-            .map_err(|e| anyhow::anyhow!("failed to parse key pem: {}", e))?;
-
-        // Parse ASN.1 structure of private key (PKCS1 or PKCS8) to extract n,e
-        let der = pem.contents;
-        let asn1 = from_der(&der).context("failed to parse private key DER ASN.1")?;
-
-        // Walk ASN.1 to locate integers for modulus and exponent
-        fn extract_n_e(blocks: &[ASN1Block]) -> Option<(Vec<u8>, Vec<u8>)> {
-            for b in blocks {
-                if let ASN1Block::Sequence(_, inner) = b {
-                    // PKCS1 private key: sequence of integers: 0, n, e, d, ...
-                    if inner.len() >= 3
-                        && let (ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)) =
-                            (&inner[1], &inner[2])
-                    {
-                        let n_bytes = n.to_signed_bytes_be();
-                        let e_bytes = e.to_signed_bytes_be();
-                        return Some((n_bytes, e_bytes));
-                    }
-                    // PKCS8 will embed algorithmIdentifier and privateKey as octet string
-                    for elem in inner.iter() {
-                        if let ASN1Block::OctetString(_, os) = elem
-                            && let Ok(nested) = from_der(os)
-                            && let Some((n, e)) = extract_n_e(&nested)
-                        {
-                            return Some((n, e));
-                        }
-                    }
-                }
+        let key_str = std::str::from_utf8(key_pem).context("key pem to str")?;
+        let key = match rcgen::KeyPair::from_pem(key_str).ok() {
+            Some(pair) if pair.algorithm() == &rcgen::PKCS_ECDSA_P256_SHA256 => {
+                curve_key(&pair, Algorithm::ES256, key_pem)?
             }
-            None
-        }
-
-        let (n_bytes, e_bytes) = extract_n_e(&asn1)
-            .ok_or_else(|| anyhow::anyhow!("failed to extract n/e from private key ASN.1"))?;
-
-        let n_b64 = BASE64_ENGINE.encode(&n_bytes);
-        let e_b64 = BASE64_ENGINE.encode(&e_bytes);
-
-        // Compute kid as SHA-256(n || e)
-        let mut hasher = Sha256::new();
-        hasher.update(&n_bytes);
-        hasher.update(&e_bytes);
-        let kid = hex::encode(hasher.finalize());
-
-        let jwk = serde_json::json!({
-            "kty": "RSA",
-            "use": "sig",
-            "alg": "RS256",
-            "kid": kid,
-            "n": n_b64,
-            "e": e_b64,
-        });
+            Some(pair) if pair.algorithm() == &rcgen::PKCS_ED25519 => {
+                curve_key(&pair, Algorithm::EdDSA, key_pem)?
+            }
+            _ => rsa_key(key_pem)?,
+        };
 
         // If a certificate was provided, parse it and ensure the public key
         // in the cert matches the private key we just parsed. This prevents
         // accidental mismatches (wrong file paths) and ensures the JWKS we
         // publish corresponds to the private key used for signing.
+        let mut cert_der = None;
         if let Some(cert_bytes) = cert_pem {
             // parse PEM wrapper (may contain additional certs)
             let pem = pem_crate::parse(std::str::from_utf8(cert_bytes).context("cert pem to str")?)
@@ -106,51 +62,204 @@ impl PemSigner {
                 .map_err(|e| anyhow::anyhow!("failed to parse x509 certificate: {:?}", e))?;
 
             let subject_pki = &cert.tbs_certificate.subject_pki.subject_public_key.data;
-
-            // Parse subject_pki DER and extract the RSA modulus/exponent
-            let subject_pki_asn = from_der(subject_pki)
-                .map_err(|_| anyhow::anyhow!("failed to parse subject_pki DER"))?;
-            // subject_pki is the DER of RSAPublicKey: SEQUENCE { INTEGER n, INTEGER e }
-            let mut pub_n = None;
-            let mut pub_e = None;
-            if subject_pki_asn.len() == 1
-                && let ASN1Block::Sequence(_, seq) = &subject_pki_asn[0]
-                && seq.len() >= 2
-                && let (ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)) = (&seq[0], &seq[1])
-            {
-                pub_n = Some(n.to_signed_bytes_be());
-                pub_e = Some(e.to_signed_bytes_be());
+            if !key.matches(subject_pki)? {
+                return Err(StartupError::KeyCertMismatch(
+                    "Certificate public key does not match provided private key".to_string(),
+                )
+                .into());
             }
+            cert_der = Some(der);
+        }
 
-            let pub_n = pub_n.ok_or_else(|| {
-                anyhow::anyhow!("failed to extract public modulus from cert subject_pki")
-            })?;
-            let pub_e = pub_e.ok_or_else(|| {
-                anyhow::anyhow!("failed to extract public exponent from cert subject_pki")
-            })?;
-
-            // Compare modulus/exponent
-            if pub_n != n_bytes || pub_e != e_bytes {
-                return Err(anyhow::anyhow!(
-                    "KeyCertMismatch: Certificate public key does not match provided private key"
-                ));
+        let mut jwk = key.jwk;
+        // With a certificate the kid is its SHA-256 thumbprint (x5t#S256), so
+        // verifiers can match tokens to the published certificate; without
+        // one it is the SHA-256 of the public key (n || e for RSA)
+        let kid = match cert_der {
+            Some(der) => {
+                let thumbprint = BASE64_ENGINE.encode(Sha256::digest(&der));
+                jwk["x5c"] = serde_json::json!([STANDARD.encode(&der)]);
+                jwk["x5t#S256"] = serde_json::json!(thumbprint);
+                thumbprint
             }
-        }
+            None => hex::encode(Sha256::digest(&key.public)),
+        };
+        jwk["kid"] = serde_json::json!(kid);
 
         Ok(PemSigner {
             kid,
-            encoding_key,
+            algorithm: key.algorithm,
+            encoding_key: key.encoding_key,
             jwk,
         })
     }
 }
 
+/// Signing key parsed from PEM, with its public half.
+struct ParsedKey {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    /// JWK of the public key, without `kid`.
+    jwk: serde_json::Value,
+    /// Public key bytes: n || e for RSA, the raw key for EC and Ed25519.
+    public: Vec<u8>,
+    /// RSA modulus and exponent, compared with certificates field by field.
+    rsa: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl ParsedKey {
+    /// Whether a certificate's subject public key is this key.
+    fn matches(&self, subject_pki: &[u8]) -> Result<bool> {
+        let Some((n_bytes, e_bytes)) = &self.rsa else {
+            return Ok(subject_pki == self.public.as_slice());
+        };
+        // Parse subject_pki DER and extract the RSA modulus/exponent
+        let subject_pki_asn = from_der(subject_pki)
+            .map_err(|_| anyhow::anyhow!("failed to parse subject_pki DER"))?;
+        // subject_pki is the DER of RSAPublicKey: SEQUENCE { INTEGER n, INTEGER e }
+        let mut pub_n = None;
+        let mut pub_e = None;
+        if subject_pki_asn.len() == 1
+            && let ASN1Block::Sequence(_, seq) = &subject_pki_asn[0]
+            && seq.len() >= 2
+            && let (ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)) = (&seq[0], &seq[1])
+        {
+            pub_n = Some(n.to_signed_bytes_be());
+            pub_e = Some(e.to_signed_bytes_be());
+        }
+
+        let pub_n = pub_n.ok_or_else(|| {
+            anyhow::anyhow!("failed to extract public modulus from cert subject_pki")
+        })?;
+        let pub_e = pub_e.ok_or_else(|| {
+            anyhow::anyhow!("failed to extract public exponent from cert subject_pki")
+        })?;
+
+        // Compare modulus/exponent
+        Ok(&pub_n == n_bytes && &pub_e == e_bytes)
+    }
+}
+
+/// EC P-256 or Ed25519 key in PKCS#8 PEM.
+fn curve_key(pair: &rcgen::KeyPair, algorithm: Algorithm, key_pem: &[u8]) -> Result<ParsedKey> {
+    let raw = pair.public_key_raw();
+    let (encoding_key, jwk) = match algorithm {
+        Algorithm::ES256 => {
+            // Uncompressed point: 0x04 || x || y
+            let (x, y) = raw
+                .get(1..)
+                .filter(|xy| raw[0] == 4 && xy.len() == 64)
+                .map(|xy| xy.split_at(32))
+                .ok_or_else(|| anyhow::anyhow!("unexpected EC public key encoding"))?;
+            (
+                EncodingKey::from_ec_pem(key_pem)
+                    .context("failed to create encoding key from pem")?,
+                serde_json::json!({
+                    "kty": "EC",
+                    "use": "sig",
+                    "alg": "ES256",
+                    "crv": "P-256",
+                    "x": BASE64_ENGINE.encode(x),
+                    "y": BASE64_ENGINE.encode(y),
+                }),
+            )
+        }
+        _ => (
+            EncodingKey::from_ed_pem(key_pem).context("failed to create encoding key from pem")?,
+            serde_json::json!({
+                "kty": "OKP",
+                "use": "sig",
+                "alg": "EdDSA",
+                "crv": "Ed25519",
+                "x": BASE64_ENGINE.encode(raw),
+            }),
+        ),
+    };
+    Ok(ParsedKey {
+        algorithm,
+        encoding_key,
+        jwk,
+        public: raw.to_vec(),
+        rsa: None,
+    })
+}
+
+/// RSA key in PKCS#1 or PKCS#8 PEM.
+fn rsa_key(key_pem: &[u8]) -> Result<ParsedKey> {
+    // Try to parse private key as PKCS1 or PKCS8 using the rsa crate's
+    // PEM parsers via the jsonwebtoken EncodingKey, but avoid depending
+    // on the `rsa` crate types for Windows. We still use EncodingKey to
+    // sign; extract the public modulus/exponent by parsing the PEM
+    // ourselves using simple_asn1 where necessary.
+    let encoding_key =
+        EncodingKey::from_rsa_pem(key_pem).context("failed to create encoding key from pem")?;
+
+    // Parse PEM to DER and extract modulus/exponent from private key
+    let pem = pem_crate::parse(std::str::from_utf8(key_pem).context("key pem to str")?)
+        .map_err(|e| anyhow::anyhow!("failed to parse key pem: {}", e))?;
+
+    // Parse ASN.1 structure of private key (PKCS1 or PKCS8) to extract n,e
+    let der = pem.contents;
+    let asn1 = from_der(&der).context("failed to parse private key DER ASN.1")?;
+
+    // Walk ASN.1 to locate integers for modulus and exponent
+    fn extract_n_e(blocks: &[ASN1Block]) -> Option<(Vec<u8>, Vec<u8>)> {
+        for b in blocks {
+            if let ASN1Block::Sequence(_, inner) = b {
+                // PKCS1 private key: sequence of integers: 0, n, e, d, ...
+                if inner.len() >= 3
+                    && let (ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)) =
+                        (&inner[1], &inner[2])
+                {
+                    let n_bytes = n.to_signed_bytes_be();
+                    let e_bytes = e.to_signed_bytes_be();
+                    return Some((n_bytes, e_bytes));
+                }
+                // PKCS8 will embed algorithmIdentifier and privateKey as octet string
+                for elem in inner.iter() {
+                    if let ASN1Block::OctetString(_, os) = elem
+                        && let Ok(nested) = from_der(os)
+                        && let Some((n, e)) = extract_n_e(&nested)
+                    {
+                        return Some((n, e));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    let (n_bytes, e_bytes) = extract_n_e(&asn1)
+        .ok_or_else(|| anyhow::anyhow!("failed to extract n/e from private key ASN.1"))?;
+
+    let n_b64 = BASE64_ENGINE.encode(&n_bytes);
+    let e_b64 = BASE64_ENGINE.encode(&e_bytes);
+
+    let mut public = n_bytes.clone();
+    public.extend_from_slice(&e_bytes);
+    Ok(ParsedKey {
+        algorithm: Algorithm::RS256,
+        encoding_key,
+        jwk: serde_json::json!({
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "n": n_b64,
+            "e": e_b64,
+        }),
+        public,
+        rsa: Some((n_bytes, e_bytes)),
+    })
+}
+
+#[async_trait::async_trait]
 impl Signer for PemSigner {
     fn kid(&self) -> &str {
         &self.kid
     }
 
-    fn sign(&self, mut header: Header, claims: &serde_json::Value) -> Result<String> {
+    async fn sign(&self, mut header: Header, claims: &serde_json::Value) -> Result<String> {
+        header.alg = self.algorithm;
         header.kid = Some(self.kid.clone());
         let token = jsonwebtoken::encode(&header, claims, &self.encoding_key)
             .context("failed to encode jwt")?;
@@ -162,15 +271,172 @@ impl Signer for PemSigner {
     }
 }
 
+/// Signs with the most recently activated of several keys, so that keys can
+/// be rotated on a schedule.
+///
+/// Every key is in the JWKS before it signs, so verifiers learn it ahead of
+/// time, and stays there for `retain` after the next key took over, so the
+/// tokens it signed can still be verified.
+pub struct RotatingSigner {
+    /// Keys and the time they start signing, in activation order.
+    keys: Vec<(PemSigner, DateTime<Utc>)>,
+    retain: chrono::Duration,
+}
+
+impl RotatingSigner {
+    /// Creates a signer starting with `current` and switching to each of
+    /// `scheduled` at its activation time.
+    pub fn new(
+        current: PemSigner,
+        scheduled: Vec<(PemSigner, DateTime<Utc>)>,
+        retain: Duration,
+    ) -> Self {
+        let mut keys = vec![(current, DateTime::<Utc>::MIN_UTC)];
+        keys.extend(scheduled);
+        keys.sort_by_key(|(_, activate_at)| *activate_at);
+        Self {
+            keys,
+            retain: chrono::Duration::from_std(retain).unwrap_or(chrono::Duration::MAX),
+        }
+    }
+
+    /// Index of the key signing at `now`.
+    fn active(&self, now: DateTime<Utc>) -> usize {
+        self.keys
+            .iter()
+            .rposition(|(_, activate_at)| *activate_at <= now)
+            .unwrap_or(0)
+    }
+
+    /// Whether key `index` is published at `now`: it was not replaced more
+    /// than `retain` ago.
+    fn published(&self, index: usize, now: DateTime<Utc>) -> bool {
+        self.keys.get(index + 1).is_none_or(|(_, next)| {
+            next.checked_add_signed(self.retain)
+                .is_none_or(|retired| now < retired)
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for RotatingSigner {
+    fn kid(&self) -> &str {
+        &self.keys[self.active(Utc::now())].0.kid
+    }
+
+    /// Signs with the active key, or with the activated and still published
+    /// key named by the header's `kid`.
+    async fn sign(&self, header: Header, claims: &serde_json::Value) -> Result<String> {
+        let now = Utc::now();
+        let active = self.active(now);
+        let selected = header.kid.as_deref().and_then(|kid| {
+            (0..=active).find(|&i| self.keys[i].0.kid == kid && self.published(i, now))
+        });
+        self.keys[selected.unwrap_or(active)]
+            .0
+            .sign(header, claims)
+            .await
+    }
+
+    fn jwks(&self) -> serde_json::Value {
+        let now = Utc::now();
+        let keys: Vec<_> = (0..self.keys.len())
+            .filter(|&i| self.published(i, now))
+            .map(|i| self.keys[i].0.jwk.clone())
+            .collect();
+        serde_json::json!({ "keys": keys })
+    }
+}
+
 pub type DynSigner = Arc<dyn Signer>;
 
 pub fn load_pem_signer_from_paths(key_path: &str, cert_path: Option<&str>) -> Result<DynSigner> {
+    Ok(Arc::new(read_pem_signer(key_path, cert_path)?))
+}
+
+/// Loads the local signing key, rotating to the keys of `config.rotation`
+/// when there are any.
+pub fn load_signer(
+    config: &TokenSigningConfig,
+    key_path: &str,
+    cert_path: Option<&str>,
+) -> Result<DynSigner> {
+    if config.rotation.is_empty() {
+        return load_pem_signer_from_paths(key_path, cert_path);
+    }
+    let current = read_pem_signer(key_path, cert_path)?;
+    let scheduled = config
+        .rotation
+        .iter()
+        .map(|k| {
+            let activate_at = DateTime::parse_from_rfc3339(&k.activate_at)
+                .with_context(|| format!("invalid activate_at of key {}", k.key))?
+                .with_timezone(&Utc);
+            let signer = read_pem_signer(&k.key, k.cert.as_deref())
+                .with_context(|| format!("load rotation key {}", k.key))?;
+            Ok((signer, activate_at))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Arc::new(RotatingSigner::new(
+        current,
+        scheduled,
+        Duration::from_secs(config.retain_seconds),
+    )))
+}
+
+fn read_pem_signer(key_path: &str, cert_path: Option<&str>) -> Result<PemSigner> {
     let key = std::fs::read(key_path).context("read private key")?;
     let cert = match cert_path {
         Some(p) => Some(std::fs::read(p).context("read cert")?),
         None => None,
     };
     let cert_ref = cert.as_deref();
-    let signer = PemSigner::from_pem(&key, cert_ref).context("create pem signer")?;
-    Ok(Arc::new(signer))
+    PemSigner::from_pem(&key, cert_ref).context("create pem signer")
+}
+
+/// Key types generated by `ark keygen`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeyAlgorithm {
+    /// EC P-256, signing with ES256.
+    Ec,
+    /// Ed25519, signing with EdDSA.
+    Ed25519,
+    /// RSA 2048-bit, signing with RS256.
+    Rsa,
+}
+
+/// Generates a signing key in the PKCS#8 PEM format [`PemSigner::from_pem`]
+/// reads and, when `certificate` gives a common name and a validity in days,
+/// a self-signed certificate for it.
+pub fn generate_key(
+    algorithm: KeyAlgorithm,
+    certificate: Option<(&str, u32)>,
+) -> Result<(String, Option<String>)> {
+    let alg = match algorithm {
+        KeyAlgorithm::Ec => &rcgen::PKCS_ECDSA_P256_SHA256,
+        KeyAlgorithm::Ed25519 => &rcgen::PKCS_ED25519,
+        KeyAlgorithm::Rsa => &rcgen::PKCS_RSA_SHA256,
+    };
+    let pair = rcgen::KeyPair::generate_for(alg).context("generate key")?;
+    let cert = match certificate {
+        Some((common_name, days)) => {
+            let mut params = rcgen::CertificateParams::default();
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, common_name);
+            let date = |d: chrono::DateTime<chrono::Utc>| {
+                use chrono::Datelike;
+                rcgen::date_time_ymd(d.year(), d.month() as u8, d.day() as u8)
+            };
+            let now = chrono::Utc::now();
+            params.not_before = date(now);
+            params.not_after = date(now + chrono::Duration::days(days.into()));
+            let cert = params
+                .self_signed(&pair)
+                .context("create self-signed certificate")?;
+            Some(cert.pem())
+        }
+        None => None,
+    };
+    Ok((pair.serialize_pem(), cert))
 }
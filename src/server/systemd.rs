@@ -0,0 +1,110 @@
+//! systemd integration: readiness notification and socket activation.
+//!
+//! With `Type=notify` the service manager learns of lifecycle transitions:
+//! `READY=1` once the listeners are up, `STOPPING=1` on shutdown and a
+//! `STATUS=` line for every state in between.
+//!
+//! With socket activation systemd binds the listening sockets and passes them
+//! in; they stay bound across restarts, so connections made while the server
+//! restarts wait instead of being refused. Sockets are matched to listeners by
+//! `FileDescriptorName=`: [`MANAGEMENT_SOCKET`] and [`MCP_SOCKET`], several
+//! sockets per name when a unit has several `ListenStream=`. A listener
+//! without a passed socket binds its configured addresses as usual.
+//!
+//! Both are no-ops when the server is not started by systemd.
+
+use crate::state::ApplicationState;
+
+/// `FileDescriptorName=` of the management listener's socket.
+pub const MANAGEMENT_SOCKET: &str = "management";
+
+/// `FileDescriptorName=` of the MCP listener's socket.
+pub const MCP_SOCKET: &str = "mcp";
+
+/// Reports a lifecycle transition to the service manager.
+pub fn notify_state(state: &ApplicationState) {
+    #[cfg(unix)]
+    {
+        use sd_notify::NotifyState;
+
+        let status = format!("{:?}", state);
+        let mut states = vec![NotifyState::Status(&status)];
+        match state {
+            ApplicationState::Ready => states.push(NotifyState::Ready),
+            ApplicationState::Terminating => states.push(NotifyState::Stopping),
+            _ => {}
+        }
+        if let Err(e) = sd_notify::notify(false, &states) {
+            tracing::debug!("systemd notification failed: {}", e);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = state;
+}
+
+/// Takes the sockets passed by systemd for the listener `name`, if any.
+///
+/// # Errors
+/// Returns an error if a socket cannot be registered with the runtime.
+pub fn take_listeners(name: &str) -> anyhow::Result<Vec<tokio::net::TcpListener>> {
+    #[cfg(unix)]
+    {
+        let fds = activated::SOCKETS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name)
+            .unwrap_or_default();
+        fds.into_iter()
+            .map(|fd| {
+                let listener = std::net::TcpListener::from(fd);
+                listener.set_nonblocking(true)?;
+                Ok(tokio::net::TcpListener::from_std(listener)?)
+            })
+            .collect()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = name;
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(unix)]
+mod activated {
+    use std::collections::HashMap;
+    use std::os::fd::{FromRawFd, OwnedFd};
+    use std::sync::{LazyLock, Mutex};
+
+    use super::{MANAGEMENT_SOCKET, MCP_SOCKET};
+
+    /// Sockets passed in and not taken yet, by name.
+    pub(super) static SOCKETS: LazyLock<Mutex<HashMap<String, Vec<OwnedFd>>>> = LazyLock::new(
+        || {
+            let mut sockets: HashMap<String, Vec<OwnedFd>> = HashMap::new();
+            // The environment is left alone: it only applies to this process
+            // (LISTEN_PID) and changing it is unsound once threads run
+            match sd_notify::listen_fds_with_names(false) {
+                Ok(fds) => {
+                    for (fd, name) in fds {
+                        // SAFETY: systemd passes these descriptors to this process
+                        // only, and they are taken exactly once
+                        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+                        if name == MANAGEMENT_SOCKET || name == MCP_SOCKET {
+                            tracing::info!("Using the '{}' socket passed by systemd", name);
+                            sockets.entry(name).or_default().push(fd);
+                        } else {
+                            tracing::warn!(
+                                "Ignoring the socket '{}' passed by systemd; set FileDescriptorName={} or {}",
+                                name,
+                                MANAGEMENT_SOCKET,
+                                MCP_SOCKET
+                            );
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Ignoring the sockets passed by systemd: {}", e),
+            }
+            Mutex::new(sockets)
+        },
+    );
+}
@@ -0,0 +1,264 @@
+//! Connection timeouts of the listeners, so that slow or stalled clients
+//! cannot hold connections open forever.
+//!
+//! Request headers are bounded by hyper (HTTP/1). Read, write and idle
+//! stalls are enforced by [`TimeoutIo`], which wraps the connection's socket:
+//! while a request is in flight, a read or write may not stall longer than
+//! the read or write timeout; once the last response has been sent, the
+//! client has the idle timeout to send another request. [`Activity`] tells
+//! the two apart, counting requests until their response bodies are done.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use futures::task::AtomicWaker;
+use hyper::body::{Body as HttpBody, Bytes, Frame, SizeHint};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::Sleep,
+};
+
+use crate::config::models::ListenersConfig;
+
+/// Timeouts applied to every connection of a listener; `None` disables one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionTimeouts {
+    /// Time to receive the headers of a request, and to complete the TLS
+    /// handshake.
+    pub header: Option<Duration>,
+    /// Longest a read may stall while a request is in flight.
+    pub read: Option<Duration>,
+    /// Longest a write may stall.
+    pub write: Option<Duration>,
+    /// Longest a connection may stay open without a request in flight.
+    pub idle: Option<Duration>,
+}
+
+impl ConnectionTimeouts {
+    /// Timeouts of the `listeners` settings, where 0 disables one.
+    pub fn from_config(config: &ListenersConfig) -> Self {
+        let secs = |s: u64| (s > 0).then(|| Duration::from_secs(s));
+        Self {
+            header: secs(config.header_timeout_secs),
+            read: secs(config.read_timeout_secs),
+            write: secs(config.write_timeout_secs),
+            idle: secs(config.idle_timeout_secs),
+        }
+    }
+}
+
+/// Requests in flight on one connection.
+#[derive(Debug, Default)]
+pub struct Activity {
+    in_flight: AtomicUsize,
+    /// Reader to wake when the connection becomes idle, so the idle timeout
+    /// starts
+    reader: AtomicWaker,
+}
+
+impl Activity {
+    fn is_busy(&self) -> bool {
+        self.in_flight.load(Ordering::Acquire) > 0
+    }
+
+    fn begin(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        InFlight(self.clone())
+    }
+}
+
+/// A request in flight, until dropped with its response body.
+struct InFlight(Arc<Activity>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.reader.wake();
+        }
+    }
+}
+
+/// Middleware counting a request as in flight on `activity` until its
+/// response body has been sent.
+pub async fn track_activity(activity: Arc<Activity>, req: Request<Body>, next: Next) -> Response {
+    let in_flight = activity.begin();
+    next.run(req).await.map(|body| {
+        Body::new(TrackedBody {
+            body,
+            _in_flight: in_flight,
+        })
+    })
+}
+
+/// Response body holding its request in flight.
+struct TrackedBody {
+    body: Body,
+    _in_flight: InFlight,
+}
+
+impl HttpBody for TrackedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+/// Socket failing with [`io::ErrorKind::TimedOut`] once a read or write
+/// stalls past its timeout.
+pub struct TimeoutIo<T> {
+    inner: T,
+    timeouts: ConnectionTimeouts,
+    activity: Arc<Activity>,
+    read_deadline: Option<Pin<Box<Sleep>>>,
+    /// Whether `read_deadline` was armed with a request in flight
+    read_busy: bool,
+    write_deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl<T> TimeoutIo<T> {
+    pub fn new(inner: T, timeouts: ConnectionTimeouts, activity: Arc<Activity>) -> Self {
+        Self {
+            inner,
+            timeouts,
+            activity,
+            read_deadline: None,
+            read_busy: false,
+            write_deadline: None,
+        }
+    }
+}
+
+/// Arms `deadline` for `limit` if not running, and fails once it passes.
+fn poll_deadline(
+    deadline: &mut Option<Pin<Box<Sleep>>>,
+    limit: Option<Duration>,
+    cx: &mut Context<'_>,
+) -> io::Result<()> {
+    let Some(limit) = limit else {
+        *deadline = None;
+        return Ok(());
+    };
+    let sleep = deadline.get_or_insert_with(|| Box::pin(tokio::time::sleep(limit)));
+    if sleep.as_mut().poll(cx).is_ready() {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "connection timed out",
+        ));
+    }
+    Ok(())
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for TimeoutIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        this.activity.reader.register(cx.waker());
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(result) => {
+                this.read_deadline = None;
+                Poll::Ready(result)
+            }
+            Poll::Pending => {
+                let busy = this.activity.is_busy();
+                // A deadline armed in the other state no longer applies
+                if busy != this.read_busy {
+                    this.read_deadline = None;
+                    this.read_busy = busy;
+                }
+                let limit = if busy {
+                    this.timeouts.read
+                } else {
+                    this.timeouts.idle
+                };
+                match poll_deadline(&mut this.read_deadline, limit, cx) {
+                    Ok(()) => Poll::Pending,
+                    Err(e) => Poll::Ready(Err(e)),
+                }
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for TimeoutIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        this.poll_write_deadline(result, cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        this.poll_write_deadline(result, cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.inner).poll_flush(cx);
+        this.poll_write_deadline(result, cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.inner).poll_shutdown(cx);
+        this.poll_write_deadline(result, cx)
+    }
+}
+
+impl<T> TimeoutIo<T> {
+    fn poll_write_deadline<R>(
+        &mut self,
+        result: Poll<io::Result<R>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<R>> {
+        match result {
+            Poll::Ready(result) => {
+                self.write_deadline = None;
+                Poll::Ready(result)
+            }
+            Poll::Pending => match poll_deadline(&mut self.write_deadline, self.timeouts.write, cx)
+            {
+                Ok(()) => Poll::Pending,
+                Err(e) => Poll::Ready(Err(e)),
+            },
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Persisted tool usage counters and analytics.
+//!
+//! Every tool call made over MCP or the plugin API is added to the
+//! `tool_usage` counters of the plugin's owner, which administrators read
+//! aggregated at `GET /api/admin/stats`. Unlike the Prometheus metrics they
+//! survive restarts. Calls are also queued in `tool_calls`, which a background
+//! task folds into hourly rollups by tool and principal for
+//! `GET /api/admin/stats/top`, on one instance at a time when several share
+//! the database (see [`crate::server::lease`]). Each call is also kept in the
+//! `tool_executions` history, the latest [`EXECUTION_HISTORY_SIZE`] of which
+//! are listed at `GET /api/executions`. Recording is best effort: a storage
+//! failure is logged and never fails the call.
+//!
+//! Calls of authenticated principals are also added to their monthly
+//! `principal_usage` counters (calls, compute time and bytes returned), shown
+//! at `GET /api/me/usage` and `GET /api/admin/usage` for chargeback, and
+//! checked against the configured quotas by [`check_quota`] before each call.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::config::models::QuotaLimits;
+use crate::errors::ErrorCode;
+use crate::server::events::ServerEvent;
+use crate::server::lease;
+use crate::server::persist::{PrincipalUsageRecord, ToolCallRecord, ToolExecutionRecord};
+use crate::state::ArkState;
+
+/// How often queued tool calls are rolled up.
+pub const ROLLUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long hourly rollups are kept.
+pub const ROLLUP_RETENTION: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// How many tool executions are kept in the history.
+pub const EXECUTION_HISTORY_SIZE: u32 = 10_000;
+
+/// A finished tool call, as recorded by [`record_tool_call`].
+#[derive(Debug, Clone, Copy)]
+pub struct ToolCall<'a> {
+    pub plugin_id: &'a str,
+    pub tool: &'a str,
+    /// Owner of the plugin, if any.
+    pub owner: Option<&'a str>,
+    /// Global id of the caller, if authenticated.
+    pub principal: Option<&'a str>,
+    /// Arguments of the call; only their hash is stored.
+    pub args: &'a Value,
+    /// Why the call failed, or `None` when it succeeded.
+    pub error: Option<ErrorCode>,
+    pub latency_ms: f64,
+    /// Size of the result returned to the caller, in bytes.
+    pub bytes_returned: u64,
+}
+
+/// A monthly quota a principal has used up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    /// The exhausted quota: "calls", "compute_ms" or "bytes_returned".
+    pub quota: &'static str,
+    /// Its limit.
+    pub limit: u64,
+    /// The month it applies to, as `YYYY-MM`.
+    pub month: String,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Monthly {} quota of {} used up for {}",
+            self.quota, self.limit, self.month
+        )
+    }
+}
+
+/// Returns the first of `limits` that `usage` has used up, if any.
+pub fn exceeded_quota(usage: &PrincipalUsageRecord, limits: QuotaLimits) -> Option<QuotaExceeded> {
+    [
+        ("calls", usage.calls as f64, limits.calls),
+        ("compute_ms", usage.compute_ms, limits.compute_ms),
+        (
+            "bytes_returned",
+            usage.bytes_returned as f64,
+            limits.bytes_returned,
+        ),
+    ]
+    .into_iter()
+    .find(|&(_, used, limit)| limit > 0 && used >= limit as f64)
+    .map(|(quota, _, limit)| QuotaExceeded {
+        quota,
+        limit,
+        month: usage.month.clone(),
+    })
+}
+
+/// Checks the monthly quotas of `principal` before a tool call.
+///
+/// Usage is only counted for authenticated principals and with a database,
+/// so other calls always pass; so do calls whose usage cannot be read. A call
+/// started before a quota is used up completes, so usage can end slightly
+/// above a quota.
+pub async fn check_quota(state: &ArkState, principal: Option<&str>) -> Result<(), QuotaExceeded> {
+    let Some(principal) = principal else {
+        return Ok(());
+    };
+    let limits = state.quota_limits(principal);
+    if limits == QuotaLimits::default() {
+        return Ok(());
+    }
+    let Some(database) = state.database.read().ok().and_then(|g| g.clone()) else {
+        return Ok(());
+    };
+    let month = PrincipalUsageRecord::month_of(chrono::Utc::now());
+    match database
+        .get_principal_usage_async(principal.to_string(), month)
+        .await
+    {
+        Ok(usage) => exceeded_quota(&usage, limits).map_or(Ok(()), Err),
+        Err(e) => {
+            tracing::warn!("Failed to read usage of {}: {}", principal, e);
+            Ok(())
+        }
+    }
+}
+
+/// Publishes a tool call to live event subscribers and adds it to the
+/// persisted counters and execution history when a database is configured.
+///
+/// Plugins without an owner are counted under the shared owner `*/*/*`.
+pub async fn record_tool_call(state: &ArkState, call: ToolCall<'_>) {
+    let owner = call.owner.unwrap_or("*/*/*").to_string();
+    let is_error = call.error.is_some();
+    state.events.publish(ServerEvent::ToolExecuted {
+        plugin: call.plugin_id.to_string(),
+        tool: call.tool.to_string(),
+        owner: owner.clone(),
+        principal: call.principal.map(str::to_string),
+        is_error,
+        latency_ms: call.latency_ms,
+    });
+    let Some(database) = state.database.read().ok().and_then(|g| g.clone()) else {
+        return;
+    };
+    let timestamp_utc = chrono::Utc::now();
+    let record = ToolCallRecord {
+        timestamp_utc,
+        owner,
+        plugin_id: call.plugin_id.to_string(),
+        tool: call.tool.to_string(),
+        principal: call.principal.map(str::to_string),
+        is_error,
+        latency_ms: call.latency_ms,
+        bytes_returned: call.bytes_returned,
+    };
+    if let Err(e) = database.record_tool_call_async(record).await {
+        tracing::warn!("Failed to record tool usage: {}", e);
+    }
+    let execution = ToolExecutionRecord {
+        id: 0,
+        timestamp_utc,
+        principal: call.principal.map(str::to_string),
+        plugin_id: call.plugin_id.to_string(),
+        tool: call.tool.to_string(),
+        duration_ms: call.latency_ms,
+        error_code: call.error,
+        args_hash: args_hash(call.args),
+        request_id: crate::logging::current_request_id(),
+    };
+    if let Err(e) = database
+        .save_tool_execution_async(execution, EXECUTION_HISTORY_SIZE)
+        .await
+    {
+        tracing::warn!("Failed to record tool execution: {}", e);
+    }
+}
+
+/// Hashes tool arguments for the execution history, so identical calls can
+/// be correlated without storing their contents.
+///
+/// Returns the first 16 hex digits of the SHA-256 of the arguments' JSON.
+pub fn args_hash(args: &Value) -> String {
+    let digest = Sha256::digest(args.to_string().as_bytes());
+    hex::encode(&digest[..8])
+}
+
+/// Starts the background task rolling up queued tool calls.
+pub fn start_rollup_task(state: Arc<ArkState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ROLLUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            // Get database reference without holding guard across await
+            let Some(database) = state.database.read().ok().and_then(|g| g.clone()) else {
+                continue;
+            };
+            // Another instance sharing the database may be rolling up
+            if !lease::USAGE_ROLLUP.acquire(&database).await {
+                continue;
+            }
+            match database.rollup_tool_calls_async(ROLLUP_RETENTION).await {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!("Rolled up {} tool calls", count),
+                Err(e) => tracing::warn!("Failed to roll up tool calls: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,232 @@
+//! Reloading on changes to the configuration and TLS files.
+//!
+//! With `watch.enabled`, the files are checked every `watch.interval_secs`:
+//! a change to the configuration file, its fragments or `watch.paths`
+//! reloads the configuration as SIGHUP does, and a change to a listener's
+//! certificate or key replaces the certificate served to new connections.
+//! Files are polled and compared by the path they resolve to as well as by
+//! size and modification time, since Kubernetes updates ConfigMap and Secret
+//! volumes by swapping a `..data` symlink rather than writing to the files.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock, Weak},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+
+use crate::config::{ArkConfig, models::WatchConfig, overlay};
+use crate::state::ArkState;
+
+/// Directory levels below a watched directory that are checked.
+const MAX_DEPTH: usize = 4;
+
+/// Certificates read from files, reloaded by the watcher.
+static CERTIFICATES: Mutex<Vec<Weak<FileCertResolver>>> = Mutex::new(Vec::new());
+
+/// Watched files with, for those that exist, the path they resolve to, their
+/// size and their modification time.
+type Fingerprint = Vec<(PathBuf, Option<(PathBuf, u64, Option<SystemTime>)>)>;
+
+/// Starts checking the files every `watch.interval_secs`.
+pub fn spawn(state: Arc<ArkState>, config_path: PathBuf, watch: &WatchConfig) {
+    let Some(reloader) = state.config_reloader() else {
+        tracing::warn!("Configuration reload is not available, files are not watched");
+        return;
+    };
+    let interval = Duration::from_secs(watch.interval_secs.max(1));
+    tokio::spawn(async move {
+        let mut last = fingerprint(&config_files(&config_path, &reloader.current().await));
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes at once
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            reload_certificates();
+            let files = fingerprint(&config_files(&config_path, &reloader.current().await));
+            if files == last {
+                continue;
+            }
+            last = files;
+            tracing::info!("Configuration files changed, reloading");
+            if let Err(e) = reloader.reload(&state).await {
+                tracing::warn!("Configuration not reloaded: {}", e);
+            }
+        }
+    });
+    tracing::info!(
+        "Watching the configuration and TLS files every {}s",
+        interval.as_secs()
+    );
+}
+
+/// Files the configuration at `path` is read from: the file itself, its
+/// includes and `config.d`, and `watch.paths`.
+fn config_files(path: &Path, config: &ArkConfig) -> Vec<PathBuf> {
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut files = vec![path.to_path_buf()];
+    files.extend(config.include.iter().map(|p| base.join(p)));
+    files.push(base.join(overlay::CONF_DIR));
+    if let Some(watch) = &config.watch {
+        files.extend(watch.paths.iter().map(|p| base.join(p)));
+    }
+    files
+}
+
+fn fingerprint(paths: &[PathBuf]) -> Fingerprint {
+    let mut files = Vec::new();
+    for path in paths {
+        collect(path, 0, &mut files);
+    }
+    files
+        .into_iter()
+        .map(|path| {
+            let state = std::fs::canonicalize(&path).ok().and_then(|resolved| {
+                let metadata = std::fs::metadata(&resolved).ok()?;
+                Some((resolved, metadata.len(), metadata.modified().ok()))
+            });
+            (path, state)
+        })
+        .collect()
+}
+
+/// Adds `path`, or the files below it if it is a directory. Hidden entries,
+/// such as the `..data` links of Kubernetes volumes, are skipped.
+fn collect(path: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            !p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'))
+        })
+        .collect();
+    entries.sort();
+    for entry in entries {
+        collect(&entry, depth + 1, files);
+    }
+}
+
+/// Reads again the certificates whose files changed.
+fn reload_certificates() {
+    let resolvers: Vec<Arc<FileCertResolver>> = {
+        let mut certificates = lock(&CERTIFICATES);
+        certificates.retain(|c| c.strong_count() > 0);
+        certificates.iter().filter_map(Weak::upgrade).collect()
+    };
+    for resolver in resolvers {
+        resolver.reload_if_changed();
+    }
+}
+
+/// Certificate of a listener read from its `tls.cert` and `tls.key` files,
+/// read again by the watcher once they change.
+#[derive(Debug)]
+pub struct FileCertResolver {
+    cert: PathBuf,
+    key: PathBuf,
+    certificate: RwLock<Arc<CertifiedKey>>,
+    /// The files as last read
+    files: Mutex<Fingerprint>,
+}
+
+impl FileCertResolver {
+    /// Creates the resolver for the PEM contents of the `cert` and `key`
+    /// files.
+    ///
+    /// # Errors
+    /// Returns an error if the certificate or key is invalid, or they do not
+    /// match.
+    pub fn new(
+        cert: &Path,
+        key: &Path,
+        cert_pem: &[u8],
+        key_pem: &[u8],
+    ) -> anyhow::Result<Arc<Self>> {
+        let files = fingerprint(&[cert.to_path_buf(), key.to_path_buf()]);
+        let resolver = Arc::new(Self {
+            cert: cert.to_path_buf(),
+            key: key.to_path_buf(),
+            certificate: RwLock::new(Arc::new(certified_key(cert_pem, key_pem)?)),
+            files: Mutex::new(files),
+        });
+        lock(&CERTIFICATES).push(Arc::downgrade(&resolver));
+        Ok(resolver)
+    }
+
+    /// Reads the files again if they changed since last read. A certificate
+    /// that fails to load leaves the previous one in use.
+    fn reload_if_changed(&self) {
+        let files = fingerprint(&[self.cert.clone(), self.key.clone()]);
+        {
+            let mut last = lock(&self.files);
+            if *last == files {
+                return;
+            }
+            *last = files;
+        }
+        let loaded = std::fs::read(&self.cert)
+            .with_context(|| format!("Failed to read cert file {}", self.cert.display()))
+            .and_then(|cert| {
+                let key = std::fs::read(&self.key)
+                    .with_context(|| format!("Failed to read key file {}", self.key.display()))?;
+                certified_key(&cert, &key)
+            });
+        match loaded {
+            Ok(certificate) => {
+                *self.certificate.write().unwrap_or_else(|e| e.into_inner()) =
+                    Arc::new(certificate);
+                tracing::info!("Reloaded TLS certificate {}", self.cert.display());
+            }
+            // Retried once the files change again, e.g. when the key of a
+            // new certificate is written
+            Err(e) => tracing::warn!(
+                "TLS certificate {} not reloaded: {:#}",
+                self.cert.display(),
+                e
+            ),
+        }
+    }
+}
+
+impl ResolvesServerCert for FileCertResolver {
+    fn resolve(&self, _hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.certificate
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        )
+    }
+}
+
+fn certified_key(cert_pem: &[u8], key_pem: &[u8]) -> anyhow::Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut &cert_pem[..])
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to parse certificates")?;
+    let key = rustls_pemfile::private_key(&mut &key_pem[..])
+        .context("Failed to parse private key")?
+        .context("No private key found")?;
+    CertifiedKey::from_der(certs, key, &rustls::crypto::aws_lc_rs::default_provider())
+        .context("Invalid certificate or private key")
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,230 @@
+//! Outbound webhooks.
+//!
+//! Plugin registrations and removals, and tools that keep failing, are posted
+//! as JSON to the endpoints configured under `webhooks`, so plugin changes can
+//! be wired into chat or inventory systems. Notifications are derived from the
+//! live [`ServerEvent`] stream:
+//!
+//! - `plugin.created` / `plugin.updated` when a plugin is registered, new or
+//!   replacing one with the same name
+//! - `plugin.deleted` when a plugin is unregistered
+//! - `tool.failing` when a tool's consecutive failed calls reach the
+//!   configured threshold; sent again only after a successful call
+//!
+//! Each body is `{"event", "timestamp", "text", "data"}`. `text` is a one-line
+//! summary, which is all Slack incoming webhooks need. With a `secret` the
+//! request carries `X-Ark-Signature: sha256=<hex HMAC-SHA256 of the body>`.
+//! Delivery is best effort: failed requests are retried a few times, then
+//! logged and dropped.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::models::{WebhookEndpointConfig, WebhooksConfig};
+use crate::server::events::ServerEvent;
+use crate::state::ArkState;
+
+/// Header carrying the HMAC signature of the body.
+pub const SIGNATURE_HEADER: &str = "X-Ark-Signature";
+/// Header carrying the event name.
+pub const EVENT_HEADER: &str = "X-Ark-Event";
+
+/// Timeout of a single delivery attempt.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Attempts per delivery, one second apart times the attempt number.
+const DELIVERY_ATTEMPTS: u32 = 3;
+
+/// Returns the `X-Ark-Signature` value for `body` signed with `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Notification derived from a server event: name, summary and data.
+type Notification = (&'static str, String, Value);
+
+/// Tracks what is needed to turn server events into notifications.
+struct Notifier {
+    /// Registered plugins, to tell new plugins from replaced ones.
+    plugins: HashSet<String>,
+    /// Consecutive failed calls by (plugin, tool).
+    failures: HashMap<(String, String), u32>,
+    failure_threshold: u32,
+}
+
+impl Notifier {
+    fn notification(&mut self, event: ServerEvent) -> Option<Notification> {
+        match event {
+            ServerEvent::PluginRegistered {
+                plugin,
+                owner,
+                tools,
+            } => {
+                let (name, verb) = if self.plugins.insert(plugin.clone()) {
+                    ("plugin.created", "registered")
+                } else {
+                    ("plugin.updated", "updated")
+                };
+                Some((
+                    name,
+                    format!("Plugin '{}' {} with {} tool(s)", plugin, verb, tools.len()),
+                    json!({ "plugin": plugin, "owner": owner, "tools": tools }),
+                ))
+            }
+            ServerEvent::PluginUnregistered { plugin, owner } => {
+                self.plugins.remove(&plugin);
+                self.failures.retain(|(p, _), _| *p != plugin);
+                Some((
+                    "plugin.deleted",
+                    format!("Plugin '{}' unregistered", plugin),
+                    json!({ "plugin": plugin, "owner": owner }),
+                ))
+            }
+            ServerEvent::ToolExecuted {
+                plugin,
+                tool,
+                owner,
+                is_error,
+                ..
+            } => {
+                let key = (plugin.clone(), tool.clone());
+                if !is_error {
+                    self.failures.remove(&key);
+                    return None;
+                }
+                let count = self.failures.entry(key).or_default();
+                *count += 1;
+                (*count == self.failure_threshold).then(|| {
+                    (
+                        "tool.failing",
+                        format!(
+                            "Tool '{}' of plugin '{}' failed {} times in a row",
+                            tool, plugin, count
+                        ),
+                        json!({
+                            "plugin": plugin,
+                            "tool": tool,
+                            "owner": owner,
+                            "consecutive_failures": *count,
+                        }),
+                    )
+                })
+            }
+            ServerEvent::Auth { .. } | ServerEvent::StateChanged { .. } => None,
+        }
+    }
+}
+
+/// Starts forwarding server events to the configured webhooks.
+///
+/// Plugins registered before this is called are treated as existing, so the
+/// plugins loaded at startup are not reported as created.
+pub fn start_webhook_task(state: Arc<ArkState>, config: &WebhooksConfig) {
+    if config.endpoints.is_empty() {
+        return;
+    }
+    let mut receiver = state.events.subscribe();
+    let endpoints = Arc::new(config.endpoints.clone());
+    let failure_threshold = config.failure_threshold.max(1);
+    tracing::info!("Sending webhooks to {} endpoint(s)", endpoints.len());
+
+    tokio::spawn(async move {
+        let plugins = {
+            let catalog = state.plugin_registry.catalog.load();
+            catalog.plugin_to_config.keys().cloned().collect()
+        };
+        drop(state);
+        let mut notifier = Notifier {
+            plugins,
+            failures: HashMap::new(),
+            failure_threshold,
+        };
+        let client = reqwest::Client::new();
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Webhooks missed {} server events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let Some((name, text, data)) = notifier.notification(event) else {
+                continue;
+            };
+            let body = json!({
+                "event": name,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "text": text,
+                "data": data,
+            })
+            .to_string();
+            for endpoint in endpoints.iter() {
+                if endpoint.events.is_empty() || endpoint.events.iter().any(|e| e == name) {
+                    tokio::spawn(deliver(
+                        client.clone(),
+                        endpoint.clone(),
+                        name,
+                        body.clone(),
+                    ));
+                }
+            }
+        }
+    });
+}
+
+/// Posts `body` to `endpoint`, retrying failed attempts.
+async fn deliver(
+    client: reqwest::Client,
+    endpoint: WebhookEndpointConfig,
+    event: &'static str,
+    body: String,
+) {
+    let signature = endpoint
+        .secret
+        .as_deref()
+        .map(|secret| sign(secret, body.as_bytes()));
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        let mut request = client
+            .post(&endpoint.url)
+            .timeout(DELIVERY_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event)
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                tracing::debug!("Delivered webhook {} to {}", event, endpoint.url);
+                return;
+            }
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt == DELIVERY_ATTEMPTS {
+            tracing::warn!(
+                "Failed to deliver webhook {} to {}: {}",
+                event,
+                endpoint.url,
+                error
+            );
+        } else {
+            tracing::debug!(
+                "Webhook {} to {} failed ({}), retrying",
+                event,
+                endpoint.url,
+                error
+            );
+            tokio::time::sleep(Duration::from_secs(attempt.into())).await;
+        }
+    }
+}
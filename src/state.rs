@@ -4,11 +4,14 @@
 /// - Maintaining the state of the server
 /// - Hosting the plugin registry
 use crate::{
-    config::models::McpTransport,
+    config::models::{BodyLimitsConfig, McpTransport, QuotaLimits, QuotasConfig},
     config::plugins::ArkPlugin,
     plugins::{ToolSet, registry::PluginRegistry},
     server::auth::AuthState,
+    server::events::{EventBus, ServerEvent},
     server::persist::Database,
+    server::proxy::TrustedProxies,
+    server::reload::ConfigReloader,
 };
 use anyhow::Result;
 use rmcp::{
@@ -17,12 +20,14 @@ use rmcp::{
 };
 
 use std::{
+    collections::BTreeMap,
     future::Future,
     pin::Pin,
     sync::{
         Arc, RwLock,
         atomic::{AtomicBool, AtomicU8, Ordering},
     },
+    time::Instant,
 };
 
 use tracing::debug;
@@ -44,6 +49,18 @@ pub enum ApplicationState {
     Terminating = 5,
 }
 
+/// Outcome of loading the configured TLS certificate and key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TlsStatus {
+    /// No certificate and key are configured.
+    #[default]
+    Disabled,
+    /// The certificate and key were loaded and are in use.
+    Loaded,
+    /// Loading failed, with the reason; servers fall back to plain HTTP.
+    Failed(String),
+}
+
 // Shared application state and registry of plugins.
 //
 // This struct holds the core state of the Ark MCP server, including
@@ -57,6 +74,10 @@ pub struct ArkState {
     pub use_json_management_responses: AtomicBool,
     /// Current application lifecycle state.
     pub state: AtomicU8,
+    /// Whether the application has reached `Ready` since it was created.
+    pub started: AtomicBool,
+    /// When the state was created, from which the uptime is counted.
+    pub created_at: Instant,
     /// Whether the health API is disabled.
     pub disable_health_api: AtomicBool,
     /// Whether the plugin management API is disabled.
@@ -67,12 +88,27 @@ pub struct ArkState {
     pub disable_console: AtomicBool,
     /// Selected MCP transport (stdio, sse, streamablehttp).
     pub transport: RwLock<McpTransport>,
+    /// Request body size limits per endpoint class.
+    pub body_limits: RwLock<BodyLimitsConfig>,
+    /// Monthly usage quotas per principal.
+    pub quotas: RwLock<QuotasConfig>,
+    /// Reverse proxies whose forwarded headers are honored.
+    pub trusted_proxies: RwLock<TrustedProxies>,
     /// Registry of all loaded plugins and their tools.
     pub plugin_registry: PluginRegistry,
     /// Authentication state (optional for testing).
     pub auth_state: RwLock<Option<Arc<AuthState>>>,
     /// Database for persistent storage (optional for testing).
     pub database: RwLock<Option<Database>>,
+    /// Plugins that failed to load, with the error (plugin name -> error).
+    pub plugin_failures: RwLock<BTreeMap<String, String>>,
+    /// Whether TLS is in use, reported by the readiness probe.
+    pub tls_status: RwLock<TlsStatus>,
+    /// Live events streamed to console clients.
+    pub events: EventBus,
+    /// Reloads the configuration at runtime (unset in tests and embedders
+    /// that do not load a configuration file).
+    pub config_reloader: RwLock<Option<Arc<ConfigReloader>>>,
 }
 
 /// Default implementation for ArkState.
@@ -86,15 +122,24 @@ impl Default for ArkState {
         Self {
             use_json_management_responses: AtomicBool::new(false),
             state: AtomicU8::new(ApplicationState::Unknown as u8),
+            started: AtomicBool::new(false),
+            created_at: Instant::now(),
             disable_plugin_api: AtomicBool::new(false),
             disable_prometheus_api: AtomicBool::new(false),
             disable_console: AtomicBool::new(false),
             disable_health_api: AtomicBool::new(false),
             transport: RwLock::new(McpTransport::Stdio),
+            body_limits: RwLock::new(BodyLimitsConfig::default()),
+            quotas: RwLock::new(QuotasConfig::default()),
+            trusted_proxies: RwLock::new(TrustedProxies::default()),
             plugin_registry: PluginRegistry::new_local(),
             server_info: Arc::new(ServerInfo::default()),
             auth_state: RwLock::new(None),
             database: RwLock::new(None),
+            plugin_failures: RwLock::new(BTreeMap::new()),
+            tls_status: RwLock::new(TlsStatus::Disabled),
+            events: EventBus::default(),
+            config_reloader: RwLock::new(None),
         }
     }
 }
@@ -114,7 +159,28 @@ impl ArkState {
     pub fn set_state(&self, value: ApplicationState) {
         let v = value as u8;
         debug!("Application state changed to {:?}", v);
-        self.state.store(v, Ordering::Relaxed);
+        let previous = self.state.swap(v, Ordering::Relaxed);
+        if v == ApplicationState::Ready as u8 {
+            self.started.store(true, Ordering::Relaxed);
+        }
+        if previous != v {
+            crate::server::systemd::notify_state(&self.get_state());
+            self.events.publish(ServerEvent::StateChanged {
+                state: format!("{:?}", self.get_state()),
+            });
+        }
+    }
+
+    /// Get application lifecycle state.
+    pub fn get_state(&self) -> ApplicationState {
+        match self.state.load(Ordering::SeqCst) {
+            1 => ApplicationState::Initializing,
+            2 => ApplicationState::LoadingPlugins,
+            3 => ApplicationState::StartingNetwork,
+            4 => ApplicationState::Ready,
+            5 => ApplicationState::Terminating,
+            _ => ApplicationState::Unknown,
+        }
     }
 
     /// Enable or disable JSON management responses.
@@ -136,6 +202,12 @@ impl ArkState {
         self.state.load(Ordering::SeqCst) >= ApplicationState::Ready as u8
     }
 
+    /// Returns true once the application has completed startup (startup check).
+    /// Unlike readiness this stays true while terminating.
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::SeqCst)
+    }
+
     /// Returns true if the token signer (if required) is ready.
     ///
     /// Logic:
@@ -252,6 +324,18 @@ impl ArkState {
         }
     }
 
+    /// Set the configuration reloader.
+    pub fn set_config_reloader(&self, reloader: Arc<ConfigReloader>) {
+        if let Ok(mut w) = self.config_reloader.write() {
+            *w = Some(reloader);
+        }
+    }
+
+    /// Get the configuration reloader, if any.
+    pub fn config_reloader(&self) -> Option<Arc<ConfigReloader>> {
+        self.config_reloader.read().ok().and_then(|g| g.clone())
+    }
+
     /// Set database for persistent storage.
     pub fn set_database(&self, database: Database) {
         if let Ok(mut w) = self.database.write() {
@@ -259,11 +343,87 @@ impl ArkState {
         }
     }
 
+    /// Record a plugin that could not be loaded.
+    pub fn record_plugin_failure(&self, plugin_name: &str, error: impl std::fmt::Display) {
+        if let Ok(mut w) = self.plugin_failures.write() {
+            w.insert(plugin_name.to_string(), error.to_string());
+        }
+    }
+
+    /// Plugins that could not be loaded, with the error.
+    pub fn plugin_failures(&self) -> BTreeMap<String, String> {
+        self.plugin_failures
+            .read()
+            .map(|r| r.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the outcome of loading TLS material.
+    pub fn set_tls_status(&self, status: TlsStatus) {
+        if let Ok(mut w) = self.tls_status.write() {
+            *w = status;
+        }
+    }
+
+    /// Get the outcome of loading TLS material.
+    pub fn tls_status(&self) -> TlsStatus {
+        self.tls_status
+            .read()
+            .map(|r| r.clone())
+            .unwrap_or_default()
+    }
+
     /// Get current transport.
     pub fn get_transport(&self) -> McpTransport {
         *self.transport.read().unwrap_or_else(|e| e.into_inner())
     }
 
+    /// Set request body size limits. Routers pick them up when built.
+    pub fn set_body_limits(&self, limits: BodyLimitsConfig) {
+        if let Ok(mut w) = self.body_limits.write() {
+            *w = limits
+        }
+    }
+
+    /// Get request body size limits.
+    pub fn get_body_limits(&self) -> BodyLimitsConfig {
+        self.body_limits
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Set the monthly usage quotas. Tool calls check them as they are made.
+    pub fn set_quotas(&self, quotas: QuotasConfig) {
+        if let Ok(mut w) = self.quotas.write() {
+            *w = quotas
+        }
+    }
+
+    /// Get the monthly usage quotas of `principal` (a global id).
+    pub fn quota_limits(&self, principal: &str) -> QuotaLimits {
+        self.quotas
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .limits_for(principal)
+    }
+
+    /// Set the reverse proxies whose forwarded headers are honored.
+    /// Listeners pick them up when started.
+    pub fn set_trusted_proxies(&self, proxies: TrustedProxies) {
+        if let Ok(mut w) = self.trusted_proxies.write() {
+            *w = proxies
+        }
+    }
+
+    /// Get the reverse proxies whose forwarded headers are honored.
+    pub fn get_trusted_proxies(&self) -> TrustedProxies {
+        self.trusted_proxies
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
     /// List tools, optionally filtered by plugin.
     ///
     /// # Arguments
@@ -293,7 +453,10 @@ impl ArkState {
             return Ok(false);
         }
 
-        catalog.plugin_to_config.remove(plugin_name);
+        let owner = catalog
+            .plugin_to_config
+            .remove(plugin_name)
+            .and_then(|cfg| cfg.owner);
         catalog.tool_to_handler.remove(plugin_name);
 
         // Remove all tools associated with this plugin
@@ -313,7 +476,12 @@ impl ArkState {
             catalog.tool_to_plugin.remove(&tool_name);
             catalog.tool_to_def.remove(&tool_name);
         }
+        catalog.generation += 1;
 
+        self.events.publish(ServerEvent::PluginUnregistered {
+            plugin: plugin_name.to_string(),
+            owner: owner.unwrap_or_else(|| "*/*/*".to_string()),
+        });
         Ok(true)
     }
 
@@ -332,7 +500,8 @@ impl ArkState {
     /// `Ok(())` on success, or an `ErrorData` if registration fails.
     ///
     /// # Details
-    /// This method updates multiple internal maps:
+    /// The tools are first renamed, changed or hidden as the plugin's
+    /// `manifest.tools` says. This method then updates multiple internal maps:
     /// - `tool_to_plugin`: Maps tool names to plugin names
     /// - `tool_to_def`: Maps tool names to tool definitions
     /// - `plugin_to_config`: Stores plugin configurations
@@ -340,9 +509,10 @@ impl ArkState {
     pub async fn register_plugin_with_executors(
         &self,
         plugin_config: ArkPlugin,
-        toolset: ToolSet,
-        executors: Vec<(String, ToolExecFn)>,
+        mut toolset: ToolSet,
+        mut executors: Vec<(String, ToolExecFn)>,
     ) -> Result<(), rmcp::ErrorData> {
+        crate::plugins::apply_tool_overrides(&plugin_config, &mut toolset, &mut executors);
         let mut catalog = self.plugin_registry.catalog.write().await;
         // Ensure plugin has an owner; default to wildcard if none
         let mut plugin_config = plugin_config;
@@ -360,6 +530,9 @@ impl ArkState {
         }
 
         // Register plugin config
+        if let Ok(mut failures) = self.plugin_failures.write() {
+            failures.remove(&plugin_config.name);
+        }
         catalog
             .plugin_to_config
             .insert(plugin_config.name.clone(), plugin_config.clone());
@@ -368,7 +541,13 @@ impl ArkState {
         for (tool_name, exec_fn) in executors {
             catalog.tool_to_handler.insert(tool_name, exec_fn);
         }
+        catalog.generation += 1;
 
+        self.events.publish(ServerEvent::PluginRegistered {
+            plugin: plugin_config.name,
+            owner: plugin_config.owner.unwrap_or_default(),
+            tools: toolset.tools.iter().map(|t| t.name.to_string()).collect(),
+        });
         Ok(())
     }
 }
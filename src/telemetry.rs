@@ -0,0 +1,156 @@
+//! # Trace Export Module
+//!
+//! MCP requests, management API calls, plugin fetches and tool executions run
+//! inside `tracing` spans. With the `otel-traces` feature those spans are
+//! exported over OTLP, configured by the standard environment variables
+//! (`OTEL_EXPORTER_OTLP_ENDPOINT`, `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`,
+//! `OTEL_EXPORTER_OTLP_HEADERS`, `OTEL_SERVICE_NAME`, ...). Setting
+//! `OTEL_TRACES_EXPORTER=none` or `OTEL_SDK_DISABLED=true` turns export off.
+//!
+//! W3C trace context (`traceparent` / `tracestate`) is honoured on incoming
+//! management API and MCP requests and sent on HTTP(S) plugin fetches, so
+//! Ark's spans join the caller's distributed trace.
+
+use axum::http::HeaderMap;
+#[cfg(feature = "otel-traces")]
+use axum::http::{HeaderName, HeaderValue};
+#[cfg(feature = "otel-traces")]
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+#[cfg(feature = "otel-traces")]
+use opentelemetry::trace::{TraceContextExt as _, TracerProvider as _};
+#[cfg(feature = "otel-traces")]
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::SdkTracerProvider};
+#[cfg(feature = "otel-traces")]
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+#[cfg(feature = "otel-traces")]
+use tracing_subscriber::{Layer, filter::Targets, registry::LookupSpan};
+
+/// Tracer provider kept for flushing buffered spans on shutdown.
+#[cfg(feature = "otel-traces")]
+static TRACER_PROVIDER: std::sync::OnceLock<SdkTracerProvider> = std::sync::OnceLock::new();
+
+/// Builds the layer exporting Ark's spans over OTLP.
+///
+/// Only spans and events from this crate at INFO or above are exported.
+///
+/// # Returns
+/// The layer, or `None` when export is disabled through the environment or
+/// the exporter cannot be built.
+#[cfg(feature = "otel-traces")]
+pub fn otel_layer<S>() -> Option<impl Layer<S>>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let disabled = std::env::var("OTEL_TRACES_EXPORTER").is_ok_and(|v| v == "none")
+        || std::env::var("OTEL_SDK_DISABLED").is_ok_and(|v| v.eq_ignore_ascii_case("true"));
+    if disabled {
+        return None;
+    }
+
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            // The subscriber is not installed yet
+            eprintln!("Failed to build OTLP span exporter: {e}");
+            return None;
+        }
+    };
+    // OTEL_SERVICE_NAME, when set, is picked up by the default resource
+    let resource = if std::env::var("OTEL_SERVICE_NAME").is_ok() {
+        Resource::builder().build()
+    } else {
+        Resource::builder()
+            .with_service_name(env!("CARGO_PKG_NAME"))
+            .build()
+    };
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    let _ = TRACER_PROVIDER.set(provider);
+
+    Some(
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(
+                Targets::new().with_target(env!("CARGO_CRATE_NAME"), tracing::Level::INFO),
+            ),
+    )
+}
+
+/// Makes `span` a child of the remote span named by the `traceparent` (and
+/// `tracestate`) headers of an incoming request.
+///
+/// Must be called before `span` is entered. No-op without the `otel-traces`
+/// feature or when the headers carry no valid trace context.
+pub fn set_remote_parent(span: &tracing::Span, headers: &HeaderMap) {
+    #[cfg(feature = "otel-traces")]
+    {
+        let cx = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+        if cx.span().span_context().is_valid() {
+            span.set_parent(cx);
+        }
+    }
+    #[cfg(not(feature = "otel-traces"))]
+    let _ = (span, headers);
+}
+
+/// `traceparent` / `tracestate` headers for an outbound request made from the
+/// current span.
+///
+/// Empty without the `otel-traces` feature or when export is disabled.
+pub fn trace_headers() -> HeaderMap {
+    #[allow(unused_mut)]
+    let mut headers = HeaderMap::new();
+    #[cfg(feature = "otel-traces")]
+    TraceContextPropagator::new().inject_context(
+        &tracing::Span::current().context(),
+        &mut HeaderInjector(&mut headers),
+    );
+    headers
+}
+
+#[cfg(feature = "otel-traces")]
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+#[cfg(feature = "otel-traces")]
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+#[cfg(feature = "otel-traces")]
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+#[cfg(feature = "otel-traces")]
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Flushes spans still buffered by the exporter.
+///
+/// No-op unless the `otel-traces` feature is enabled and export is active.
+pub fn shutdown() {
+    #[cfg(feature = "otel-traces")]
+    if let Some(provider) = TRACER_PROVIDER.get()
+        && let Err(e) = provider.shutdown()
+    {
+        tracing::warn!("Failed to flush OTLP spans: {:?}", e);
+    }
+}
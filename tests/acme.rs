@@ -0,0 +1,220 @@
+use ark::{config::models::AcmeConfig, server::acme::AcmeManager};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DOMAIN: &str = "ark.example.test";
+
+fn acme_config(server: &MockServer, cache_dir: &TempDir) -> AcmeConfig {
+    serde_json::from_value(json!({
+        "domains": [DOMAIN],
+        "email": "ops@example.test",
+        "directory": format!("{}/directory", server.uri()),
+        "challenge": "http-01",
+        "cache_dir": cache_dir.path(),
+    }))
+    .unwrap()
+}
+
+fn nonce() -> ResponseTemplate {
+    signed(200)
+}
+
+fn signed(status: u16) -> ResponseTemplate {
+    ResponseTemplate::new(status).insert_header("replay-nonce", "nonce")
+}
+
+/// Mounts a CA that issues a certificate after one pending poll of the
+/// authorization.
+async fn mount_ca(server: &MockServer) {
+    let uri = server.uri();
+    Mock::given(method("GET"))
+        .and(path("/directory"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "newNonce": format!("{uri}/new-nonce"),
+            "newAccount": format!("{uri}/new-account"),
+            "newOrder": format!("{uri}/new-order"),
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("HEAD"))
+        .and(path("/new-nonce"))
+        .respond_with(nonce())
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/new-account"))
+        .respond_with(
+            signed(201)
+                .insert_header("location", format!("{uri}/account/1"))
+                .set_body_json(json!({ "status": "valid" })),
+        )
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/new-order"))
+        .respond_with(
+            signed(201)
+                .insert_header("location", format!("{uri}/order/1"))
+                .set_body_json(json!({
+                    "status": "pending",
+                    "authorizations": [format!("{uri}/authz/1")],
+                    "finalize": format!("{uri}/order/1/finalize"),
+                })),
+        )
+        .mount(server)
+        .await;
+    let authorization = |status: &str| {
+        json!({
+            "status": status,
+            "identifier": { "type": "dns", "value": DOMAIN },
+            "challenges": [
+                { "type": "http-01", "url": format!("{uri}/chall/1"), "token": "tok", "status": status },
+                { "type": "tls-alpn-01", "url": format!("{uri}/chall/2"), "token": "tok2", "status": status },
+            ],
+        })
+    };
+    // The authorization is fetched pending before the challenge, then polled
+    Mock::given(method("POST"))
+        .and(path("/authz/1"))
+        .respond_with(nonce().set_body_json(authorization("pending")))
+        .up_to_n_times(2)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/authz/1"))
+        .respond_with(nonce().set_body_json(authorization("valid")))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chall/1"))
+        .respond_with(nonce().set_body_json(json!({ "status": "processing" })))
+        .expect(1)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/order/1/finalize"))
+        .respond_with(nonce().set_body_json(json!({ "status": "processing" })))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/order/1"))
+        .respond_with(nonce().set_body_json(json!({
+            "status": "valid",
+            "certificate": format!("{uri}/cert/1"),
+        })))
+        .mount(server)
+        .await;
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert = rcgen::CertificateParams::new(vec![DOMAIN.to_string()])
+        .unwrap()
+        .self_signed(&key)
+        .unwrap();
+    Mock::given(method("POST"))
+        .and(path("/cert/1"))
+        .respond_with(nonce().set_body_string(cert.pem()))
+        .mount(server)
+        .await;
+}
+
+fn protected_header(body: &[u8]) -> Value {
+    let jws: Value = serde_json::from_slice(body).unwrap();
+    let protected = URL_SAFE_NO_PAD
+        .decode(jws["protected"].as_str().unwrap())
+        .unwrap();
+    serde_json::from_slice(&protected).unwrap()
+}
+
+#[tokio::test]
+async fn test_acme_orders_and_caches_certificate() {
+    let server = MockServer::start().await;
+    mount_ca(&server).await;
+    let cache = TempDir::new().unwrap();
+
+    let manager = AcmeManager::new(&acme_config(&server, &cache)).unwrap();
+    assert!(!manager.has_certificate());
+    manager.ensure_certificate().await.unwrap();
+    assert!(manager.has_certificate());
+    for file in ["account.key", "cert.pem", "key.pem"] {
+        assert!(cache.path().join(file).exists(), "{file} not cached");
+    }
+
+    let requests = server.received_requests().await.unwrap();
+    let posts: Vec<_> = requests
+        .iter()
+        .filter(|r| r.method.as_str() == "POST")
+        .collect();
+    // The account is registered with its key, later requests name the account
+    let first = protected_header(&posts[0].body);
+    assert_eq!(first["alg"], "ES256");
+    assert_eq!(first["jwk"]["crv"], "P-256");
+    assert!(first.get("kid").is_none());
+    let account: Value = serde_json::from_slice(
+        &URL_SAFE_NO_PAD
+            .decode(
+                serde_json::from_slice::<Value>(&posts[0].body).unwrap()["payload"]
+                    .as_str()
+                    .unwrap(),
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(account["contact"], json!(["mailto:ops@example.test"]));
+    for post in &posts[1..] {
+        let header = protected_header(&post.body);
+        assert_eq!(header["kid"], format!("{}/account/1", server.uri()));
+        assert_eq!(
+            header["url"],
+            format!("{}{}", server.uri(), post.url.path())
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_acme_uses_cached_certificate() {
+    let server = MockServer::start().await;
+    mount_ca(&server).await;
+    let cache = TempDir::new().unwrap();
+    AcmeManager::new(&acme_config(&server, &cache))
+        .unwrap()
+        .ensure_certificate()
+        .await
+        .unwrap();
+    let ordered = server.received_requests().await.unwrap().len();
+
+    let manager = AcmeManager::new(&acme_config(&server, &cache)).unwrap();
+    assert!(manager.has_certificate());
+    manager.ensure_certificate().await.unwrap();
+    assert_eq!(server.received_requests().await.unwrap().len(), ordered);
+}
+
+#[tokio::test]
+async fn test_acme_reorders_for_changed_domains() {
+    let server = MockServer::start().await;
+    mount_ca(&server).await;
+    let cache = TempDir::new().unwrap();
+    AcmeManager::new(&acme_config(&server, &cache))
+        .unwrap()
+        .ensure_certificate()
+        .await
+        .unwrap();
+
+    let mut config = acme_config(&server, &cache);
+    config.domains.push("other.example.test".to_string());
+    let manager = AcmeManager::new(&config).unwrap();
+    assert!(!manager.has_certificate());
+}
+
+#[test]
+fn test_acme_requires_domains() {
+    let cache = TempDir::new().unwrap();
+    let config: AcmeConfig = serde_json::from_value(json!({
+        "domains": [],
+        "cache_dir": cache.path(),
+    }))
+    .unwrap();
+    let error = AcmeManager::new(&config).unwrap_err();
+    assert!(error.to_string().contains("domains"), "{error}");
+}
@@ -2,12 +2,14 @@ use std::sync::Arc;
 use tempfile::TempDir;
 
 use ark::server::auth::{self, ProviderKind};
+use ark::server::csrf;
 use ark::server::roles::Role;
 use ark::{
     config::ArkConfig,
     plugins,
     plugins::builtin::BUILTIN_PLUGIN_ID,
     server::{
+        audit::ClientInfo,
         handlers::{
             api::{
                 create_plugin, delete_plugin, execute_plugin_tool, get_plugin_by_id, get_plugins,
@@ -35,7 +37,7 @@ async fn api_builtin_plugin_behavior() {
         .expect("plugin load");
 
     // Verify builtin plugin is loaded
-    let catalog = app.plugin_registry.catalog.read().await;
+    let catalog = app.plugin_registry.catalog.load();
     assert!(catalog.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID));
     assert!(catalog.plugin_to_config.len() == 1);
 }
@@ -55,6 +57,9 @@ async fn api_external_plugins_replace_builtin() {
             insecure: false,
             manifest: None,
             owner: None,
+            tools_allow: None,
+            tools_deny: None,
+            sha256: None,
         }],
         ..Default::default()
     };
@@ -65,7 +70,7 @@ async fn api_external_plugins_replace_builtin() {
         .expect_err("plugin loading should fail");
 
     // But builtin should still be gone since we tried to load external plugins
-    let catalog = app.plugin_registry.catalog.read().await;
+    let catalog = app.plugin_registry.catalog.load();
     assert!(!catalog.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID));
 }
 
@@ -82,7 +87,7 @@ async fn api_state_validation() {
         .expect("plugin loading should succeed");
 
     // Verify builtin plugin was loaded
-    let catalog = app.plugin_registry.catalog.read().await;
+    let catalog = app.plugin_registry.catalog.load();
     assert!(catalog.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID));
 }
 
@@ -160,6 +165,9 @@ async fn test_get_plugins_filters_by_owner() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/user-a".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let p_wild = ark::config::plugins::ArkPlugin {
         name: "Wildcard".to_string(),
@@ -168,6 +176,9 @@ async fn test_get_plugins_filters_by_owner() {
         insecure: false,
         manifest: None,
         owner: Some("*/*/*".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
 
     // Register with empty tool sets
@@ -189,6 +200,7 @@ async fn test_get_plugins_filters_by_owner() {
         name: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         picture: None,
         tenant_id: None,
         oid: None,
@@ -210,10 +222,15 @@ async fn test_get_plugins_filters_by_owner() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -253,6 +270,9 @@ async fn test_get_plugin_by_id_respects_owner() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/owner-1".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let ts = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -270,6 +290,7 @@ async fn test_get_plugin_by_id_respects_owner() {
         picture: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -289,10 +310,15 @@ async fn test_get_plugin_by_id_respects_owner() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -325,6 +351,9 @@ async fn test_delete_plugin_forbidden_when_not_owner() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/owner-1".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let ts = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -341,6 +370,7 @@ async fn test_delete_plugin_forbidden_when_not_owner() {
         picture: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -360,10 +390,15 @@ async fn test_delete_plugin_forbidden_when_not_owner() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -378,6 +413,7 @@ async fn test_delete_plugin_forbidden_when_not_owner() {
 
     let request = Request::delete("/api/plugins/OwnedPlugin")
         .header("Cookie", format!("ark_session={}", session_id))
+        .header("X-CSRF-Token", csrf::token_for_session(&session_id))
         .body(Body::empty())
         .unwrap();
     let response = router.oneshot(request).await.unwrap();
@@ -397,6 +433,7 @@ async fn test_create_plugin_sets_owner() {
         picture: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -416,10 +453,15 @@ async fn test_create_plugin_sets_owner() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal.clone(), std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal.clone(),
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -441,12 +483,13 @@ async fn test_create_plugin_sets_owner() {
     let request = Request::post("/api/plugins")
         .header("content-type", "application/json")
         .header("Cookie", format!("ark_session={}", session_id))
+        .header("X-CSRF-Token", csrf::token_for_session(&session_id))
         .body(Body::from(serde_json::to_string(&payload).unwrap()))
         .unwrap();
     let response = router.clone().oneshot(request).await.unwrap();
     // Current behavior: plugin load fails for nonexistent file, returning 500 and not registering
     assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
-    let catalog = app.plugin_registry.catalog.read().await;
+    let catalog = app.plugin_registry.catalog.load();
     assert!(
         !catalog.plugin_to_config.contains_key("CreatedPlugin"),
         "Plugin should not be registered when load fails"
@@ -467,6 +510,9 @@ async fn test_execute_tool_forbidden_when_not_owner() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/owner-1".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     {
         let mut catalog = app.plugin_registry.catalog.write().await;
@@ -480,6 +526,7 @@ async fn test_execute_tool_forbidden_when_not_owner() {
         provider: "test".into(),
         picture: None,
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -499,10 +546,15 @@ async fn test_execute_tool_forbidden_when_not_owner() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -522,6 +574,7 @@ async fn test_execute_tool_forbidden_when_not_owner() {
     let request = Request::post("/api/plugins/ExecOwned/tools/t1")
         .header("content-type", "application/json")
         .header("Cookie", format!("ark_session={}", session_id))
+        .header("X-CSRF-Token", csrf::token_for_session(&session_id))
         .body(Body::from(serde_json::to_string(&payload).unwrap()))
         .unwrap();
     let response = router.oneshot(request).await.unwrap();
@@ -542,6 +595,9 @@ async fn test_get_plugins_owner_serialized_when_present() {
         insecure: false,
         manifest: None,
         owner: None,
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let toolset = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -563,6 +619,7 @@ async fn test_get_plugins_owner_serialized_when_present() {
         picture: None,
         provider: "entra".into(),
         provider_kind: ProviderKind::Microsoft,
+        provider_name: None,
         tenant_id: Some("00000000-0000-0000-0000-000000000000".into()),
         oid: Some("11111111-1111-1111-1111-111111111111".into()),
         roles: vec![Role::User],
@@ -582,10 +639,15 @@ async fn test_get_plugins_owner_serialized_when_present() {
         provider: Some("entra".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal.clone(), std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal.clone(),
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -655,6 +717,9 @@ async fn test_get_plugins_owner_omitted_when_wildcard() {
         insecure: false,
         manifest: None,
         owner: None,
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let toolset = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -696,6 +761,9 @@ async fn test_get_plugins_empty_after_external_load() {
             insecure: false,
             manifest: None,
             owner: None,
+            tools_allow: None,
+            tools_deny: None,
+            sha256: None,
         }],
         ..Default::default()
     };
@@ -762,6 +830,9 @@ async fn test_get_plugins_includes_own_and_wildcard() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/me".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     // Wildcard plugin
     let public_plugin = ark::config::plugins::ArkPlugin {
@@ -771,6 +842,9 @@ async fn test_get_plugins_includes_own_and_wildcard() {
         insecure: false,
         manifest: None,
         owner: Some("*/*/*".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let ts = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -791,6 +865,7 @@ async fn test_get_plugins_includes_own_and_wildcard() {
         picture: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -810,10 +885,15 @@ async fn test_get_plugins_includes_own_and_wildcard() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -854,6 +934,9 @@ async fn test_get_plugin_by_id_allowed_paths() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/me".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     // Wildcard plugin
     let wild = ark::config::plugins::ArkPlugin {
@@ -863,6 +946,9 @@ async fn test_get_plugin_by_id_allowed_paths() {
         insecure: false,
         manifest: None,
         owner: Some("*/*/*".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let ts = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -883,6 +969,7 @@ async fn test_get_plugin_by_id_allowed_paths() {
         picture: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -902,10 +989,15 @@ async fn test_get_plugin_by_id_allowed_paths() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -948,6 +1040,9 @@ async fn test_delete_plugin_allowed_for_owner() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/me".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let ts = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -964,6 +1059,7 @@ async fn test_delete_plugin_allowed_for_owner() {
         picture: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -983,10 +1079,15 @@ async fn test_delete_plugin_allowed_for_owner() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -1001,13 +1102,14 @@ async fn test_delete_plugin_allowed_for_owner() {
 
     let req = Request::delete("/api/plugins/ToDelete")
         .header("Cookie", format!("ark_session={}", session_id))
+        .header("X-CSRF-Token", csrf::token_for_session(&session_id))
         .body(Body::empty())
         .unwrap();
     let resp = router.clone().oneshot(req).await.unwrap();
     assert_eq!(resp.status(), StatusCode::NO_CONTENT);
 
     // Verify removed
-    let catalog = app.plugin_registry.catalog.read().await;
+    let catalog = app.plugin_registry.catalog.load();
     assert!(!catalog.plugin_to_config.contains_key("ToDelete"));
 }
 
@@ -1024,6 +1126,9 @@ async fn test_delete_plugin_forbidden_for_public() {
         insecure: false,
         manifest: None,
         owner: Some("*/*/*".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     let ts = ark::plugins::ToolSet {
         name: "tools".into(),
@@ -1041,6 +1146,7 @@ async fn test_delete_plugin_forbidden_for_public() {
         name: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -1060,10 +1166,15 @@ async fn test_delete_plugin_forbidden_for_public() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -1078,6 +1189,7 @@ async fn test_delete_plugin_forbidden_for_public() {
 
     let req = Request::delete("/api/plugins/PublicP")
         .header("Cookie", format!("ark_session={}", session_id))
+        .header("X-CSRF-Token", csrf::token_for_session(&session_id))
         .body(Body::empty())
         .unwrap();
     let resp = router.oneshot(req).await.unwrap();
@@ -1099,6 +1211,9 @@ async fn test_execute_tool_allowed_when_owner() {
         insecure: false,
         manifest: None,
         owner: Some("oidc/*/me".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     {
         let mut catalog = app.plugin_registry.catalog.write().await;
@@ -1120,6 +1235,7 @@ async fn test_execute_tool_allowed_when_owner() {
         picture: None,
         provider: "test".into(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -1139,10 +1255,15 @@ async fn test_execute_tool_allowed_when_owner() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -1162,6 +1283,7 @@ async fn test_execute_tool_allowed_when_owner() {
     let req = Request::post("/api/plugins/ExecMine/tools/t1")
         .header("content-type", "application/json")
         .header("Cookie", format!("ark_session={}", session_id))
+        .header("X-CSRF-Token", csrf::token_for_session(&session_id))
         .body(Body::from(serde_json::to_string(&payload).unwrap()))
         .unwrap();
     let resp = router.oneshot(req).await.unwrap();
@@ -1188,6 +1310,9 @@ async fn test_execute_tool_allowed_when_public() {
         insecure: false,
         manifest: None,
         owner: Some("*/*/*".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     {
         let mut catalog = app.plugin_registry.catalog.write().await;
@@ -1287,6 +1412,7 @@ async fn test_create_plugin_owner_override() {
         provider: "test".into(),
         picture: None,
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -1306,10 +1432,15 @@ async fn test_create_plugin_owner_override() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(auth_cfg).await;
     let session_id = auth_state
-        .put_session(principal.clone(), std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal.clone(),
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let auth_state = Arc::new(auth_state);
 
@@ -1327,12 +1458,13 @@ async fn test_create_plugin_owner_override() {
     let req = Request::post("/api/plugins")
         .header("content-type", "application/json")
         .header("Cookie", format!("ark_session={}", session_id))
+        .header("X-CSRF-Token", csrf::token_for_session(&session_id))
         .body(Body::from(serde_json::to_string(&payload).unwrap()))
         .unwrap();
     let resp = router.clone().oneshot(req).await.unwrap();
     // Load fails for nonexistent plugin; ensure 500 and no registration
     assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
-    let cat = app.plugin_registry.catalog.read().await;
+    let cat = app.plugin_registry.catalog.load();
     assert!(
         !cat.plugin_to_config.contains_key("OwnerOverride"),
         "Plugin should not be registered when load fails"
@@ -1533,6 +1665,9 @@ async fn test_execute_tool_handler_error() {
         insecure: false,
         manifest: None,
         owner: Some("*/*/*".into()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
     };
     {
         let mut catalog = app.plugin_registry.catalog.write().await;
@@ -0,0 +1,288 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::server::roles::Role;
+use ark::{
+    config::models::{AuthConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, Principal, ProviderKind},
+        csrf,
+        persist::{ApiKeyRecord, Database},
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    routing::any,
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn test_principal() -> Principal {
+    Principal {
+        subject: "script-user".to_string(),
+        email: Some("script@example.com".to_string()),
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+/// Builds a DB-backed auth state and a router that applies the production `check_auth`.
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("keys.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: None,
+        providers: vec![],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/api", create_api_router(app_state))
+        .route("/mcp", any(|| async { StatusCode::OK }))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+async fn session_cookie(auth_state: &AuthState) -> String {
+    let sid = auth_state
+        .put_client_session(
+            test_principal(),
+            Duration::from_secs(600),
+            &ClientInfo::default(),
+        )
+        .await;
+    format!("ark_session={}", sid)
+}
+
+async fn send(router: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn create_key(router: &Router, cookie: &str, body: Value) -> (StatusCode, Value) {
+    send(
+        router,
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/me/tokens")
+            .header(header::COOKIE, cookie)
+            .header(csrf::CSRF_HEADER, csrf_token(cookie))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await
+}
+
+fn bearer(method: Method, uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// The CSRF token the console sends along with the session cookie.
+fn csrf_token(cookie: &str) -> String {
+    csrf::token_for_session(cookie.strip_prefix("ark_session=").unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_api_key_lifecycle() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = session_cookie(&auth_state).await;
+
+    let (status, created) = create_key(&router, &cookie, json!({"name": "ci"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let token = created["token"].as_str().unwrap().to_string();
+    let key_id = created["id"].as_str().unwrap().to_string();
+    assert!(token.starts_with(auth::API_KEY_PREFIX));
+    assert_eq!(created["scopes"], json!(["api", "mcp"]));
+    assert!(created["expires"].is_string());
+
+    // The key authenticates against both the management API and MCP
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &token)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&router, bearer(Method::POST, "/mcp", &token)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Listing never exposes the secret
+    let (status, listed) = send(&router, bearer(Method::GET, "/api/me/tokens", &token)).await;
+    assert_eq!(status, StatusCode::OK);
+    let listed = listed.as_array().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], key_id);
+    assert!(listed[0].get("token").is_none());
+
+    // Revoke and verify the key no longer works
+    let (status, _) = send(
+        &router,
+        bearer(
+            Method::DELETE,
+            &format!("/api/me/tokens/{}", key_id),
+            &token,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_api_key_scopes_are_enforced() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = session_cookie(&auth_state).await;
+
+    let (_, created) = create_key(
+        &router,
+        &cookie,
+        json!({"name": "mcp-only", "scopes": ["mcp"]}),
+    )
+    .await;
+    let token = created["token"].as_str().unwrap();
+
+    let (status, _) = send(&router, bearer(Method::POST, "/mcp", token)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", token)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_api_key_cannot_mint_keys() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = session_cookie(&auth_state).await;
+    let (_, created) = create_key(&router, &cookie, json!({"name": "ci"})).await;
+    let token = created["token"].as_str().unwrap();
+
+    let (status, _) = send(
+        &router,
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/me/tokens")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({"name": "nested"}).to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_api_key_request_validation() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = session_cookie(&auth_state).await;
+
+    for body in [
+        json!({"name": ""}),
+        json!({"name": "x", "scopes": ["admin"]}),
+        json!({"name": "x", "scopes": []}),
+        json!({"name": "x", "expires_in_seconds": 0}),
+        json!({"name": "x", "expires_in_seconds": 400 * 24 * 3600}),
+    ] {
+        let (status, _) = create_key(&router, &cookie, body.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "body: {}", body);
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_and_expired_api_keys_rejected() {
+    let (router, auth_state, _tmp) = setup().await;
+
+    let (status, _) = send(
+        &router,
+        bearer(Method::GET, "/api/plugins", "ark_not-a-real-key"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Insert an already-expired key directly
+    let token = auth::generate_api_key();
+    let principal = test_principal();
+    let record = ApiKeyRecord {
+        key_id: "expired".to_string(),
+        token_hash: auth::hash_api_key(&token),
+        owner: principal.global_id(),
+        name: "old".to_string(),
+        principal,
+        scopes: vec!["api".to_string()],
+        created_utc: chrono::Utc::now() - chrono::Duration::days(2),
+        expiry_utc: Some(chrono::Utc::now() - chrono::Duration::days(1)),
+    };
+    let db = auth_state
+        .app_state
+        .database
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap();
+    db.save_api_key_record_async(record).await.unwrap();
+
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_api_keys_are_scoped_to_owner() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = session_cookie(&auth_state).await;
+    let (_, created) = create_key(&router, &cookie, json!({"name": "mine"})).await;
+    let key_id = created["id"].as_str().unwrap();
+
+    let mut other = test_principal();
+    other.subject = "someone-else".to_string();
+    let other_sid = auth_state
+        .put_client_session(other, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+
+    let (status, _) = send(
+        &router,
+        Request::builder()
+            .method(Method::DELETE)
+            .uri(format!("/api/me/tokens/{}", key_id))
+            .header(header::COOKIE, format!("ark_session={}", other_sid))
+            .header(csrf::CSRF_HEADER, csrf::token_for_session(&other_sid))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,111 @@
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use ark::config::{
+    ArkConfig, McpTransport,
+    models::{ManagementEndpointConfig, McpEndpointConfig},
+};
+use ark::server::{
+    auth::{path_requires_admin, path_requires_auth},
+    authz::is_tool_execution,
+    service::{self, unversioned_api_path},
+};
+use ark::state::{ApplicationState, ArkState};
+use axum::http::Method;
+
+fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    port
+}
+
+#[test]
+fn versioned_paths_map_to_unversioned_policy() {
+    assert_eq!(unversioned_api_path("/api/v1/plugins"), "/api/plugins");
+    assert_eq!(unversioned_api_path("/api/v1"), "/api");
+    assert_eq!(unversioned_api_path("/api/plugins"), "/api/plugins");
+    assert_eq!(unversioned_api_path("/api/v10/plugins"), "/api/v10/plugins");
+    assert_eq!(unversioned_api_path("/admin"), "/admin");
+
+    assert!(path_requires_admin("/api/v1/admin/users"));
+    assert!(path_requires_admin("/api/admin/users"));
+    assert!(!path_requires_admin("/api/v1/plugins"));
+
+    assert!(path_requires_auth("/api/v1/plugins"));
+    assert!(!path_requires_auth("/api/v1/openapi.json"));
+    assert!(!path_requires_auth("/api/openapi.json"));
+
+    assert!(is_tool_execution(
+        &Method::POST,
+        "/api/v1/plugins/p/tools/t"
+    ));
+    assert!(is_tool_execution(&Method::POST, "/api/plugins/p/tools/t"));
+    assert!(!is_tool_execution(&Method::POST, "/api/v1/plugins"));
+}
+
+/// Both prefixes serve the API; only the unversioned one is marked deprecated.
+#[tokio::test]
+async fn unversioned_api_is_deprecated_alias_of_v1() {
+    let mgmt_bind = format!("127.0.0.1:{}", free_port());
+    let cfg = ArkConfig {
+        transport: Some(McpTransport::StreamableHTTP),
+        management_server: Some(ManagementEndpointConfig {
+            bind_address: Some(mgmt_bind.clone()),
+            disable_console: true,
+            ..Default::default()
+        }),
+        mcp_server: Some(McpEndpointConfig {
+            cors: None,
+            bind_address: Some(format!("127.0.0.1:{}", free_port())),
+            tls: None,
+            shared_sessions: false,
+        }),
+        ..Default::default()
+    };
+
+    let state = Arc::new(ArkState::default());
+    state.set_state(ApplicationState::StartingNetwork);
+    cfg.apply_to_state(state.clone()).await;
+    state.set_transport(McpTransport::StreamableHTTP);
+
+    let srv_state = state.clone();
+    let cfg_clone = cfg.clone();
+    let handle = tokio::spawn(async move {
+        let _ = service::start(&cfg_clone, srv_state).await;
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let client = reqwest::Client::new();
+    let v1 = client
+        .get(format!("http://{}/api/v1/plugins", mgmt_bind))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(v1.status().as_u16(), 200);
+    assert!(v1.headers().get("deprecation").is_none());
+    let v1_body: serde_json::Value = v1.json().await.unwrap();
+
+    let legacy = client
+        .get(format!("http://{}/api/plugins", mgmt_bind))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(legacy.status().as_u16(), 200);
+    assert_eq!(legacy.headers()["deprecation"], "true");
+    assert_eq!(
+        legacy.headers()["link"],
+        "</api/v1/plugins>; rel=\"successor-version\""
+    );
+    let legacy_body: serde_json::Value = legacy.json().await.unwrap();
+    assert_eq!(v1_body, legacy_body);
+
+    let spec = client
+        .get(format!("http://{}/api/v1/openapi.json", mgmt_bind))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(spec.status().as_u16(), 200);
+
+    handle.abort();
+}
@@ -0,0 +1,396 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::{
+    config::models::{
+        AuthAuditConfig, AuthConfig, IdentityProviderConfig, LockoutConfig, SessionConfig,
+    },
+    server::{
+        audit::{ClientInfo, FailureThrottle},
+        auth::{self, AuthState},
+        csrf,
+        handlers::{oauth, session},
+        persist::{AuthEventQuery, AuthEventRecord, Database, UserRecord},
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const PASSWORD: &str = "correct horse battery";
+const USER_AGENT: &str = "audit-test/1.0";
+
+/// Builds a local-provider auth state with `/auth`, `/api`, the OAuth
+/// endpoints and the production `check_auth`. Client IPs are taken from
+/// `X-Forwarded-For`.
+async fn setup() -> (Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    let database = Database::with_path(temp_dir.path().join("audit.db")).unwrap();
+    app_state.set_database(database.clone());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        lockout: LockoutConfig {
+            backoff_base_seconds: 0,
+            trust_forwarded_for: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", PASSWORD)
+        .await
+        .unwrap();
+    database
+        .save_user_async(UserRecord {
+            username: "bob".to_string(),
+            password_hash: auth::hash_password(PASSWORD).unwrap(),
+            display_name: None,
+            email: None,
+            groups: vec![],
+            is_admin: false,
+            created_utc: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .nest("/api", create_api_router(app_state))
+        .merge(oauth::router(auth_state))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, temp_dir)
+}
+
+async fn call(router: &Router, req: Request<Body>) -> Response {
+    router.clone().oneshot(req).await.unwrap()
+}
+
+async fn body_json(resp: Response) -> Value {
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Signs in with the password form from `ip`, returning the status and the
+/// session id on success.
+async fn login(
+    router: &Router,
+    ip: &str,
+    username: &str,
+    password: &str,
+) -> (StatusCode, Option<String>) {
+    let resp = call(
+        router,
+        Request::builder()
+            .method(Method::POST)
+            .uri("/auth/login/local")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(header::USER_AGENT, USER_AGENT)
+            .header("x-forwarded-for", ip)
+            .body(Body::from(format!(
+                "username={}&password={}",
+                username,
+                urlencoding::encode(password)
+            )))
+            .unwrap(),
+    )
+    .await;
+    let session_id = resp
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|c| c.split(';').next()?.strip_prefix("ark_session="))
+        .map(str::to_string);
+    (resp.status(), session_id)
+}
+
+async fn list_events(router: &Router, session_id: &str, query: &str) -> Response {
+    call(
+        router,
+        Request::builder()
+            .uri(format!("/api/admin/audit/auth-events{}", query))
+            .header(header::COOKIE, format!("ark_session={}", session_id))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_sign_in_and_out_are_audited() {
+    let (router, _tmp) = setup().await;
+
+    let (status, _) = login(&router, "10.0.0.7", "admin", "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, session) = login(&router, "10.0.0.8", "bob", PASSWORD).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    let resp = call(
+        &router,
+        Request::builder()
+            .uri("/auth/logout")
+            .header(header::COOKIE, format!("ark_session={}", session.unwrap()))
+            .header("x-forwarded-for", "10.0.0.8")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let (_, admin) = login(&router, "10.0.0.9", "admin", PASSWORD).await;
+    let admin = admin.unwrap();
+    let events = body_json(list_events(&router, &admin, "").await).await;
+    let summary: Vec<(&str, &str)> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["event"].as_str().unwrap(), e["subject"].as_str().unwrap()))
+        .collect();
+    // Newest first
+    assert_eq!(
+        summary,
+        vec![
+            ("login", "local/*/admin"),
+            ("logout", "local/*/bob"),
+            ("login", "local/*/bob"),
+            ("login_failed", "admin"),
+        ]
+    );
+
+    let failed = body_json(list_events(&router, &admin, "?event=login_failed").await).await;
+    let failed = failed.as_array().unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["provider"], "local");
+    assert_eq!(failed[0]["ip"], "10.0.0.7");
+    assert_eq!(failed[0]["user_agent"], USER_AGENT);
+    assert_eq!(failed[0]["detail"], "invalid username or password");
+    assert!(failed[0]["timestamp"].as_str().is_some());
+
+    let bob = body_json(list_events(&router, &admin, "?subject=local/*/bob&limit=1").await).await;
+    assert_eq!(bob.as_array().unwrap().len(), 1);
+    assert_eq!(bob[0]["event"], "logout");
+    assert_eq!(bob[0]["ip"], "10.0.0.8");
+}
+
+#[tokio::test]
+async fn test_token_issuance_is_audited() {
+    let (router, _tmp) = setup().await;
+    let (_, admin) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    let admin = admin.unwrap();
+
+    let resp = call(
+        &router,
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/me/tokens")
+            .header(header::COOKIE, format!("ark_session={}", admin))
+            .header(csrf::CSRF_HEADER, csrf::token_for_session(&admin))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::USER_AGENT, USER_AGENT)
+            .header("x-forwarded-for", "10.0.0.2")
+            .body(Body::from(json!({"name": "ci"}).to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let key_id = body_json(resp).await["id"].as_str().unwrap().to_string();
+
+    // A rejected client-credentials grant and a bogus API key
+    let resp = call(
+        &router,
+        Request::builder()
+            .method(Method::POST)
+            .uri("/token")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header("x-forwarded-for", "10.0.0.3")
+            .body(Body::from(
+                "grant_type=client_credentials&client_id=robot&client_secret=nope",
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = call(
+        &router,
+        Request::builder()
+            .uri("/api/plugins")
+            .header(header::AUTHORIZATION, "Bearer ark_bogus")
+            .header("x-forwarded-for", "10.0.0.4")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let issued = body_json(list_events(&router, &admin, "?event=token_issued").await).await;
+    let issued = issued.as_array().unwrap();
+    assert_eq!(issued.len(), 1);
+    assert_eq!(issued[0]["subject"], "local/*/admin");
+    assert_eq!(issued[0]["ip"], "10.0.0.2");
+    assert_eq!(issued[0]["user_agent"], USER_AGENT);
+    assert!(issued[0]["detail"].as_str().unwrap().contains(&key_id));
+
+    let refused = body_json(list_events(&router, &admin, "?event=token_failed").await).await;
+    assert_eq!(refused[0]["provider"], "service");
+    assert_eq!(refused[0]["subject"], "robot");
+    assert_eq!(refused[0]["ip"], "10.0.0.3");
+
+    let rejected = body_json(list_events(&router, &admin, "?event=login_failed").await).await;
+    assert_eq!(rejected[0]["ip"], "10.0.0.4");
+    assert_eq!(rejected[0]["detail"], "invalid or expired API key");
+}
+
+#[tokio::test]
+async fn test_rejected_api_keys_are_recorded_once_per_client() {
+    let (router, _tmp) = setup().await;
+    for ip in ["10.0.0.4", "10.0.0.4", "10.0.0.4", "10.0.0.5"] {
+        let resp = call(
+            &router,
+            Request::builder()
+                .uri("/api/plugins")
+                .header(header::AUTHORIZATION, "Bearer ark_bogus")
+                .header("x-forwarded-for", ip)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let (_, admin) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    let rejected =
+        body_json(list_events(&router, &admin.unwrap(), "?event=login_failed").await).await;
+    let mut ips: Vec<&str> = rejected
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["ip"].as_str().unwrap())
+        .collect();
+    ips.sort_unstable();
+    assert_eq!(ips, ["10.0.0.4", "10.0.0.5"]);
+}
+
+#[tokio::test]
+async fn test_failure_throttle_counts_skipped_failures() {
+    let throttle = FailureThrottle::new(Duration::from_millis(200));
+    let client = ClientInfo {
+        ip: Some("10.0.0.4".parse().unwrap()),
+        user_agent: None,
+    };
+    assert_eq!(throttle.admit(&client), Some(0));
+    assert_eq!(throttle.admit(&client), None);
+    assert_eq!(throttle.admit(&client), None);
+    // Other clients are counted apart
+    assert_eq!(throttle.admit(&ClientInfo::default()), Some(0));
+
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(throttle.admit(&client), Some(2));
+    assert_eq!(throttle.admit(&client), None);
+}
+
+#[tokio::test]
+async fn test_old_events_are_purged() {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    let database = Database::with_path(temp_dir.path().join("audit.db")).unwrap();
+    app_state.set_database(database.clone());
+    let auth_cfg = AuthConfig {
+        audit: AuthAuditConfig { retention_days: 30 },
+        ..Default::default()
+    };
+    let auth_state = AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+        .await
+        .unwrap();
+
+    for days in [0, 29, 31] {
+        database
+            .save_auth_event_async(AuthEventRecord {
+                id: 0,
+                timestamp_utc: chrono::Utc::now() - chrono::Duration::days(days),
+                event: "login".to_string(),
+                provider: None,
+                subject: Some(format!("{days} days ago")),
+                ip: None,
+                user_agent: None,
+                detail: None,
+                request_id: None,
+            })
+            .await
+            .unwrap();
+    }
+    auth_state.cleanup().await;
+
+    let kept = database
+        .list_auth_events_async(AuthEventQuery {
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let mut subjects: Vec<&str> = kept.iter().filter_map(|e| e.subject.as_deref()).collect();
+    subjects.sort_unstable();
+    assert_eq!(subjects, ["0 days ago", "29 days ago"]);
+}
+
+#[tokio::test]
+async fn test_auth_events_require_admin() {
+    let (router, _tmp) = setup().await;
+    let (_, bob) = login(&router, "10.0.0.1", "bob", PASSWORD).await;
+    let resp = list_events(&router, &bob.unwrap(), "").await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let (_, admin) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    let admin = admin.unwrap();
+    let resp = list_events(&router, &admin, "?since=yesterday").await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = list_events(&router, &admin, "?limit=0").await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let future = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+    let resp = list_events(
+        &router,
+        &admin,
+        &format!("?since={}", urlencoding::encode(&future)),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(body_json(resp).await, json!([]));
+    let resp = list_events(
+        &router,
+        &admin,
+        &format!("?until={}", urlencoding::encode(&future)),
+    )
+    .await;
+    assert_eq!(body_json(resp).await.as_array().unwrap().len(), 2);
+}
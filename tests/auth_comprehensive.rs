@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use ark::server::audit::ClientInfo;
 use ark::server::roles::Role;
 use ark::{
     config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
@@ -26,6 +27,7 @@ fn create_test_claims(exp_offset_secs: i64, iat_offset_secs: i64) -> IdTokenClai
         tid: None,
         oid: None,
         groups: None,
+        raw: Default::default(),
     }
 }
 
@@ -41,6 +43,7 @@ async fn test_session_expiration() {
         name: Some("Test User".to_string()),
         provider: "test".to_string(),
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -50,12 +53,18 @@ async fn test_session_expiration() {
 
     // Create a session with short TTL (but long enough for database persistence)
     let ttl = Duration::from_secs(2);
-    let session_id = auth_state.put_session(principal.clone(), ttl).await;
+    let session_id = auth_state
+        .put_client_session(principal.clone(), ttl, &ClientInfo::default())
+        .await;
 
     // Wait until the session is visible, allowing the database write to settle.
     tokio::time::timeout(Duration::from_secs(2), async {
         loop {
-            if auth_state.get_session(&session_id).await.is_some() {
+            if auth_state
+                .get_client_session(&session_id, &ClientInfo::default())
+                .await
+                .is_some()
+            {
                 break;
             }
             tokio::time::sleep(Duration::from_millis(25)).await;
@@ -71,7 +80,11 @@ async fn test_session_expiration() {
     tokio::time::timeout(Duration::from_secs(2), async {
         loop {
             auth_state.cleanup().await;
-            if auth_state.get_session(&session_id).await.is_none() {
+            if auth_state
+                .get_client_session(&session_id, &ClientInfo::default())
+                .await
+                .is_none()
+            {
                 break;
             }
             tokio::time::sleep(Duration::from_millis(50)).await;
@@ -94,7 +107,9 @@ async fn test_malformed_cookie_handling() {
     ];
 
     for cookie_str in test_cases {
-        let result = auth::extract_session_user_from_cookie(&auth_state, cookie_str).await;
+        let result =
+            auth::extract_client_session_user(&auth_state, cookie_str, &ClientInfo::default())
+                .await;
         assert!(
             result.is_none(),
             "Should handle malformed cookie: {}",
@@ -294,6 +309,7 @@ async fn test_concurrent_session_access() {
         provider: "test".to_string(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -302,7 +318,11 @@ async fn test_concurrent_session_access() {
     };
 
     let session_id = auth_state
-        .put_session(principal.clone(), Duration::from_secs(60))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
 
     // Simulate concurrent access to the same session
@@ -310,8 +330,11 @@ async fn test_concurrent_session_access() {
     for _i in 0..10 {
         let auth_state_clone = auth_state.clone();
         let session_id_clone = session_id.clone();
-        let handle =
-            tokio::spawn(async move { auth_state_clone.get_session(&session_id_clone).await });
+        let handle = tokio::spawn(async move {
+            auth_state_clone
+                .get_client_session(&session_id_clone, &ClientInfo::default())
+                .await
+        });
         handles.push(handle);
     }
 
@@ -335,6 +358,7 @@ async fn test_cleanup_functionality() {
         picture: None,
         provider: "test".to_string(),
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -343,10 +367,18 @@ async fn test_cleanup_functionality() {
     };
 
     let expired_session = auth_state
-        .put_session(principal.clone(), Duration::from_millis(1))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_millis(1),
+            &ClientInfo::default(),
+        )
         .await;
     let valid_session = auth_state
-        .put_session(principal.clone(), Duration::from_secs(60))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
 
     // Add expired pending auth
@@ -359,6 +391,7 @@ async fn test_cleanup_functionality() {
                 created_at: std::time::Instant::now() - Duration::from_secs(400), // 400 seconds ago
                 redirect_to: None,
                 oauth_query: None,
+                provider: None,
             },
         );
         pending.insert(
@@ -368,6 +401,7 @@ async fn test_cleanup_functionality() {
                 created_at: std::time::Instant::now(),
                 redirect_to: None,
                 oauth_query: None,
+                provider: None,
             },
         );
     }
@@ -379,8 +413,18 @@ async fn test_cleanup_functionality() {
     auth_state.cleanup().await;
 
     // Check results
-    assert!(auth_state.get_session(&expired_session).await.is_none());
-    assert!(auth_state.get_session(&valid_session).await.is_some());
+    assert!(
+        auth_state
+            .get_client_session(&expired_session, &ClientInfo::default())
+            .await
+            .is_none()
+    );
+    assert!(
+        auth_state
+            .get_client_session(&valid_session, &ClientInfo::default())
+            .await
+            .is_some()
+    );
 
     let pending = auth_state.pending.read().await;
     assert!(!pending.contains_key("expired-state"));
@@ -461,6 +505,7 @@ async fn test_multiple_provider_resolution() {
             provider: Some(provider_name.to_string()),
             providers: providers.clone(),
             session: Some(SessionConfig::default()),
+            ..Default::default()
         };
 
         // Create app state with database for testing
@@ -519,6 +564,7 @@ async fn test_principal_global_id() {
         picture: None,
         provider: "test-provider".to_string(),
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -550,6 +596,7 @@ async fn create_test_auth_state() -> (Arc<auth::AuthState>, TempDir) {
         provider: Some("test".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     // Create app state with database for testing
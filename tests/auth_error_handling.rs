@@ -5,6 +5,7 @@ use ark::server::roles::Role;
 use ark::{
     config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
     server::{
+        audit::ClientInfo,
         auth::{self, AuthState, Principal},
         handlers,
     },
@@ -97,6 +98,7 @@ async fn test_provider_discovery_failures() {
         provider: Some("invalid-discovery".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     // Should create auth state but discovery should fail
@@ -169,6 +171,7 @@ async fn test_configuration_validation_errors() {
         provider: Some("invalid".to_string()),
         providers: vec![invalid_provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let temp_dir = TempDir::new().unwrap();
@@ -207,6 +210,7 @@ async fn test_configuration_validation_errors() {
         provider: Some("nonexistent-provider".to_string()), // Provider that doesn't exist
         providers: vec![],                                  // Empty providers list
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let temp_dir = TempDir::new().unwrap();
@@ -250,6 +254,7 @@ async fn test_configuration_validation_errors() {
         provider: Some("invalid-authority".to_string()),
         providers: vec![invalid_authority_provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let temp_dir = TempDir::new().unwrap();
@@ -309,6 +314,7 @@ async fn test_configuration_validation_errors() {
         provider: Some("incomplete".to_string()),
         providers: vec![incomplete_provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let temp_dir = TempDir::new().unwrap();
@@ -390,6 +396,7 @@ async fn test_session_timeout_edge_cases() {
         picture: None,
         provider: "test".to_string(),
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -399,11 +406,17 @@ async fn test_session_timeout_edge_cases() {
 
     // Test session with zero duration
     let zero_session = auth_state
-        .put_session(principal.clone(), Duration::from_secs(0))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_secs(0),
+            &ClientInfo::default(),
+        )
         .await;
 
     // Should immediately expire
-    let retrieved = auth_state.get_session(&zero_session).await;
+    let retrieved = auth_state
+        .get_client_session(&zero_session, &ClientInfo::default())
+        .await;
     assert!(
         retrieved.is_none(),
         "Zero-duration session should immediately expire"
@@ -411,11 +424,17 @@ async fn test_session_timeout_edge_cases() {
 
     // Test session with very long duration (but not overflow)
     let long_session = auth_state
-        .put_session(principal.clone(), Duration::from_secs(86400))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_secs(86400),
+            &ClientInfo::default(),
+        )
         .await; // 1 day
 
     // Should be retrievable
-    let retrieved = auth_state.get_session(&long_session).await;
+    let retrieved = auth_state
+        .get_client_session(&long_session, &ClientInfo::default())
+        .await;
     assert!(retrieved.is_some(), "Long-duration session should be valid");
 
     // Test concurrent cleanup operations
@@ -521,6 +540,7 @@ async fn test_provider_failover() {
         provider: Some("primary".to_string()),
         providers: providers.clone(),
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let app_state = Arc::new(ArkState::default());
@@ -539,6 +559,7 @@ async fn test_provider_failover() {
         provider: Some("secondary".to_string()),
         providers,
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let app_state = Arc::new(ArkState::default());
@@ -564,6 +585,7 @@ async fn test_memory_and_resource_cleanup() {
         picture: None,
         provider: "test".to_string(),
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -575,12 +597,13 @@ async fn test_memory_and_resource_cleanup() {
     let mut session_ids = Vec::new();
     for i in 0..1000 {
         let session_id = auth_state
-            .put_session(
+            .put_client_session(
                 Principal {
                     subject: format!("user-{}", i),
                     ..principal.clone()
                 },
                 Duration::from_millis(1), // Very short duration
+                &ClientInfo::default(),
             )
             .await;
         session_ids.push(session_id);
@@ -606,6 +629,7 @@ async fn test_memory_and_resource_cleanup() {
                     created_at: std::time::Instant::now() - Duration::from_secs(400), // Expired
                     redirect_to: None,
                     oauth_query: None,
+                    provider: None,
                 },
             );
         }
@@ -646,6 +670,7 @@ async fn create_test_auth_state() -> (Arc<AuthState>, TempDir) {
         provider: Some("test".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     // Create app state with database for testing
@@ -685,6 +710,7 @@ async fn create_test_auth_state_with_invalid_endpoint() -> (Arc<AuthState>, Temp
         provider: Some("invalid".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     // Create app state with database for testing
@@ -5,6 +5,7 @@ use ark::server::roles::Role;
 use ark::{
     config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
     server::{
+        audit::ClientInfo,
         auth::{self, AuthState, Principal},
         handlers,
     },
@@ -268,6 +269,7 @@ async fn test_session_authentication_middleware() {
         provider: "test".to_string(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -276,7 +278,7 @@ async fn test_session_authentication_middleware() {
     };
 
     let session_id = auth_state
-        .put_session(principal, Duration::from_secs(3600))
+        .put_client_session(principal, Duration::from_secs(3600), &ClientInfo::default())
         .await;
 
     let app = create_test_protected_app_with_state(auth_state.clone()).await;
@@ -373,6 +375,7 @@ async fn test_auth_status_when_disabled() {
         provider: None,
         providers: vec![],
         session: None,
+        ..Default::default()
     };
 
     let temp_dir = tempfile::tempdir().unwrap();
@@ -512,6 +515,7 @@ async fn create_test_auth_state() -> (Arc<AuthState>, tempfile::TempDir) {
         provider: Some("test".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let temp_dir = tempfile::tempdir().unwrap();
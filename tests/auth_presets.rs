@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, IdentityProviderKind, SessionConfig},
+    server::{
+        auth::{AuthState, IdentityProvider, Principal, ProviderKind},
+        handlers,
+        roles::Role,
+    },
+    state::ArkState,
+};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use tower::ServiceExt;
+
+fn preset_config(name: &str, authority: &str) -> IdentityProviderConfig {
+    IdentityProviderConfig {
+        name: name.to_string(),
+        client_id: "ark-client".to_string(),
+        authority: authority.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Names the provider type `config` selects.
+fn provider_type(config: &IdentityProviderConfig) -> &'static str {
+    match IdentityProvider::from_config(config).unwrap() {
+        IdentityProvider::Okta(_) => "okta",
+        IdentityProvider::Auth0(_) => "auth0",
+        IdentityProvider::Keycloak(_) => "keycloak",
+        IdentityProvider::Microsoft(_) => "microsoft",
+        IdentityProvider::Google(_) => "google",
+        IdentityProvider::Oidc(_) => "oidc",
+        IdentityProvider::Local(_) => "local",
+        IdentityProvider::Saml(_) => "saml",
+        IdentityProvider::Ldap(_) => "ldap",
+    }
+}
+
+#[test]
+fn test_preset_selected_by_name_prefix() {
+    let cases = [
+        ("okta", "okta"),
+        ("okta-staging", "okta"),
+        ("auth0", "auth0"),
+        ("keycloak", "keycloak"),
+        ("microsoft", "microsoft"),
+        ("corp-sso", "oidc"),
+        ("localcorp-oidc", "oidc"),
+        ("local", "local"),
+        ("saml-corp", "oidc"),
+        ("ldap-bridge", "oidc"),
+    ];
+    for (name, expected) in cases {
+        let kind = provider_type(&preset_config(name, "https://idp.test"));
+        assert_eq!(kind, expected, "provider name '{}'", name);
+    }
+}
+
+#[test]
+fn test_provider_kind_selected_by_kind() {
+    let with_kind = |name: &str, kind| IdentityProviderConfig {
+        kind: Some(kind),
+        ..preset_config(name, "https://idp.test")
+    };
+    assert_eq!(
+        provider_type(&with_kind("accounts", IdentityProviderKind::Local)),
+        "local"
+    );
+    assert_eq!(
+        provider_type(&with_kind("local", IdentityProviderKind::Local)),
+        "local"
+    );
+    assert_eq!(
+        provider_type(&with_kind("okta-eu", IdentityProviderKind::Oidc)),
+        "okta"
+    );
+    assert_eq!(
+        provider_type(&with_kind("corp-sso", IdentityProviderKind::Saml)),
+        "saml"
+    );
+    assert_eq!(
+        provider_type(&with_kind("directory", IdentityProviderKind::Ldap)),
+        "ldap"
+    );
+
+    // A provider named after a kind must be of that kind
+    for (name, kind) in [
+        ("local", IdentityProviderKind::Oidc),
+        ("oidc", IdentityProviderKind::Local),
+        ("saml", IdentityProviderKind::Oidc),
+        ("ldap", IdentityProviderKind::Local),
+    ] {
+        let err = IdentityProvider::from_config(&with_kind(name, kind))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("named after kind"), "{}", err);
+    }
+}
+
+#[test]
+fn test_okta_preset_defaults() {
+    let resolved = IdentityProvider::Okta(preset_config("okta", "dev-123.okta.com/"))
+        .resolve()
+        .expect("okta preset should resolve");
+    assert_eq!(resolved.provider_kind, ProviderKind::Okta);
+    assert_eq!(resolved.authority, "https://dev-123.okta.com");
+    assert!(resolved.scopes.contains(&"groups".to_string()));
+    assert_eq!(
+        resolved.end_session_endpoint.as_deref(),
+        Some("https://dev-123.okta.com/oauth2/v1/logout")
+    );
+
+    // Custom authorization servers serve the logout endpoint directly under /v1
+    let resolved = IdentityProvider::Okta(preset_config(
+        "okta",
+        "https://dev-123.okta.com/oauth2/default",
+    ))
+    .resolve()
+    .unwrap();
+    assert_eq!(
+        resolved.end_session_endpoint.as_deref(),
+        Some("https://dev-123.okta.com/oauth2/default/v1/logout")
+    );
+}
+
+#[test]
+fn test_auth0_preset_keeps_issuer_trailing_slash_and_audience() {
+    let mut config = preset_config("auth0", "https://tenant.eu.auth0.com");
+    config.audience = Some("https://api.example.com".to_string());
+    let resolved = IdentityProvider::Auth0(config).resolve().unwrap();
+    assert_eq!(resolved.authority, "https://tenant.eu.auth0.com/");
+    assert_eq!(
+        resolved.audience.as_deref(),
+        Some("https://api.example.com")
+    );
+    assert_eq!(
+        resolved.end_session_endpoint.as_deref(),
+        Some("https://tenant.eu.auth0.com/v2/logout")
+    );
+
+    // The audience is only forwarded for Auth0
+    let mut config = preset_config("okta", "dev-123.okta.com");
+    config.audience = Some("https://api.example.com".to_string());
+    let resolved = IdentityProvider::Okta(config).resolve().unwrap();
+    assert!(resolved.audience.is_none());
+}
+
+#[test]
+fn test_keycloak_preset_requires_realm() {
+    let err = IdentityProvider::Keycloak(preset_config("keycloak", "https://kc.example.com"))
+        .resolve()
+        .unwrap_err();
+    assert!(err.to_string().contains("realm"));
+
+    let resolved = IdentityProvider::Keycloak(preset_config(
+        "keycloak",
+        "https://kc.example.com/realms/ark/",
+    ))
+    .resolve()
+    .unwrap();
+    assert_eq!(resolved.authority, "https://kc.example.com/realms/ark");
+    assert_eq!(
+        resolved.end_session_endpoint.as_deref(),
+        Some("https://kc.example.com/realms/ark/protocol/openid-connect/logout")
+    );
+}
+
+#[test]
+fn test_preset_validation_errors() {
+    let mut config = preset_config("okta", "dev-123.okta.com");
+    config.client_id = " ".to_string();
+    assert!(IdentityProvider::Okta(config).resolve().is_err());
+
+    assert!(
+        IdentityProvider::Auth0(preset_config("auth0", "ftp://tenant.auth0.com"))
+            .resolve()
+            .is_err()
+    );
+    assert!(
+        IdentityProvider::Keycloak(preset_config("keycloak", ""))
+            .resolve()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_preset_global_ids() {
+    let mut principal = Principal {
+        subject: "user-1".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "https://dev-123.okta.com".to_string(),
+        provider_kind: ProviderKind::Okta,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    };
+    assert_eq!(principal.global_id(), "okta/*/user-1");
+    principal.provider_kind = ProviderKind::Auth0;
+    assert_eq!(principal.global_id(), "auth0/*/user-1");
+    principal.provider_kind = ProviderKind::Keycloak;
+    assert_eq!(principal.global_id(), "keycloak/*/user-1");
+}
+
+#[tokio::test]
+async fn test_auth0_logout_redirects_to_v2_logout() {
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("auth0".to_string()),
+        providers: vec![IdentityProviderConfig {
+            discovery: false,
+            ..preset_config("auth0", "tenant.auth0.com")
+        }],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state =
+        AuthState::new_with_state(&Some(auth_cfg), Arc::new(ArkState::default()), None)
+            .await
+            .unwrap();
+    let app = handlers::session::router(Arc::new(auth_state));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/logout")
+                .header(header::HOST, "ark.example.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FOUND);
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(
+        location.starts_with("https://tenant.auth0.com/v2/logout?client_id=ark-client&returnTo="),
+        "unexpected logout location: {}",
+        location
+    );
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use tempfile::TempDir;
 
+use ark::server::audit::ClientInfo;
 use ark::server::roles::Role;
 use ark::{
     config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
@@ -98,7 +99,8 @@ async fn build_test_router(auth_state: Arc<auth::AuthState>, app_state: Arc<ArkS
                 // Check session cookie
                 if let Some(cookie) = cookie_str
                     && let Some(principal) =
-                        auth::extract_session_user_from_cookie(&auth, cookie).await
+                        auth::extract_client_session_user(&auth, cookie, &ClientInfo::default())
+                            .await
                 {
                     // Check if this path requires admin privileges
                     if auth::path_requires_admin(path) && !principal.is_admin {
@@ -148,6 +150,7 @@ async fn auth_enabled_protects_and_exposes_expected_endpoints() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(Some(auth_cfg)).await;
     let auth_state = Arc::new(auth_state);
@@ -192,6 +195,7 @@ async fn auth_enabled_protects_and_exposes_expected_endpoints() {
         provider: "fake".into(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -199,7 +203,11 @@ async fn auth_enabled_protects_and_exposes_expected_endpoints() {
         groups: vec![],
     };
     let session_id = auth_state
-        .put_session(principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let cookie = format!("ark_session={}", session_id);
 
@@ -235,6 +243,7 @@ async fn metrics_endpoint_requires_admin() {
         provider: Some("fake".into()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
     let (auth_state, _temp_dir) = create_test_auth_state(Some(auth_cfg)).await;
     let auth_state = Arc::new(auth_state);
@@ -249,6 +258,7 @@ async fn metrics_endpoint_requires_admin() {
         provider: "fake".into(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -256,7 +266,11 @@ async fn metrics_endpoint_requires_admin() {
         groups: vec![],
     };
     let session_id = auth_state
-        .put_session(non_admin_principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            non_admin_principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let cookie = format!("ark_session={}", session_id);
 
@@ -279,6 +293,7 @@ async fn metrics_endpoint_requires_admin() {
         provider: "fake".into(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::Admin],
@@ -286,7 +301,11 @@ async fn metrics_endpoint_requires_admin() {
         groups: vec![],
     };
     let admin_session_id = auth_state
-        .put_session(admin_principal, std::time::Duration::from_secs(60))
+        .put_client_session(
+            admin_principal,
+            std::time::Duration::from_secs(60),
+            &ClientInfo::default(),
+        )
         .await;
     let admin_cookie = format!("ark_session={}", admin_session_id);
 
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use ark::server::audit::ClientInfo;
 use ark::server::roles::Role;
 use ark::{
     config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
@@ -206,6 +207,7 @@ async fn test_session_security() {
         provider: "test".to_string(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -215,10 +217,18 @@ async fn test_session_security() {
 
     // Create multiple sessions for same user - should get different session IDs
     let session1 = auth_state
-        .put_session(principal.clone(), Duration::from_secs(3600))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_secs(3600),
+            &ClientInfo::default(),
+        )
         .await;
     let session2 = auth_state
-        .put_session(principal.clone(), Duration::from_secs(3600))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_secs(3600),
+            &ClientInfo::default(),
+        )
         .await;
 
     assert_ne!(
@@ -234,6 +244,7 @@ async fn test_session_security() {
         provider: "test".to_string(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -242,7 +253,11 @@ async fn test_session_security() {
     };
 
     let other_session = auth_state
-        .put_session(other_principal, Duration::from_secs(3600))
+        .put_client_session(
+            other_principal,
+            Duration::from_secs(3600),
+            &ClientInfo::default(),
+        )
         .await;
 
     // Session IDs should not be predictable
@@ -254,7 +269,9 @@ async fn test_session_security() {
 
     // Test session cleanup on logout
     let _deleted = auth_state.delete_session(&session1).await;
-    let retrieved = auth_state.get_session(&session1).await;
+    let retrieved = auth_state
+        .get_client_session(&session1, &ClientInfo::default())
+        .await;
     assert!(retrieved.is_none(), "Logged out session should be removed");
 }
 
@@ -290,6 +307,7 @@ async fn test_oauth_state_security() {
                 created_at: std::time::Instant::now() - Duration::from_secs(400), // 400 seconds ago (> 300s limit)
                 redirect_to: None,
                 oauth_query: None,
+                provider: None,
             },
         );
 
@@ -301,6 +319,7 @@ async fn test_oauth_state_security() {
                 created_at: std::time::Instant::now(),
                 redirect_to: None,
                 oauth_query: None,
+                provider: None,
             },
         );
     }
@@ -380,6 +399,7 @@ async fn test_authorization_code_replay() {
                 created_at: std::time::Instant::now(),
                 redirect_to: None,
                 oauth_query: None,
+                provider: None,
             },
         );
     }
@@ -421,6 +441,7 @@ async fn test_concurrent_oauth_flows() {
                         created_at: std::time::Instant::now(),
                         redirect_to: None,
                         oauth_query: None,
+                        provider: None,
                     },
                 );
             }
@@ -528,7 +549,12 @@ async fn test_malicious_input_handling() {
     ];
 
     for (malicious_cookie, description) in malicious_cookies {
-        let result = auth::extract_session_user_from_cookie(&auth_state, malicious_cookie).await;
+        let result = auth::extract_client_session_user(
+            &auth_state,
+            malicious_cookie,
+            &ClientInfo::default(),
+        )
+        .await;
         assert!(
             result.is_none(),
             "Malicious cookie should be rejected: {} ({})",
@@ -656,6 +682,7 @@ async fn test_session_timeout_validation() {
         provider: "test".to_string(),
         picture: None,
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -665,11 +692,17 @@ async fn test_session_timeout_validation() {
 
     // Test session with zero duration
     let zero_session = auth_state
-        .put_session(principal.clone(), Duration::from_secs(0))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_secs(0),
+            &ClientInfo::default(),
+        )
         .await;
 
     // Should immediately expire
-    let retrieved = auth_state.get_session(&zero_session).await;
+    let retrieved = auth_state
+        .get_client_session(&zero_session, &ClientInfo::default())
+        .await;
     assert!(
         retrieved.is_none(),
         "Zero-duration session should immediately expire"
@@ -677,13 +710,19 @@ async fn test_session_timeout_validation() {
 
     // Test session with very short duration
     let short_session = auth_state
-        .put_session(principal.clone(), Duration::from_millis(1))
+        .put_client_session(
+            principal.clone(),
+            Duration::from_millis(1),
+            &ClientInfo::default(),
+        )
         .await;
 
     // Wait for expiration
     tokio::time::sleep(Duration::from_millis(10)).await;
 
-    let retrieved = auth_state.get_session(&short_session).await;
+    let retrieved = auth_state
+        .get_client_session(&short_session, &ClientInfo::default())
+        .await;
     assert!(
         retrieved.is_none(),
         "Short-duration session should expire quickly"
@@ -711,6 +750,7 @@ async fn create_test_auth_state() -> (Arc<AuthState>, tempfile::TempDir) {
         provider: Some("test".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     // Create a TempDir and wire a SQLite database into the ArkState so
@@ -757,6 +797,7 @@ fn create_valid_claims() -> IdTokenClaims {
         tid: None,
         oid: None,
         groups: Some(vec![]),
+        raw: Default::default(),
     }
 }
 
@@ -0,0 +1,327 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::{
+    config::models::{AuthConfig, RoleConfig, SessionConfig},
+    config::plugins::ArkPlugin,
+    plugins::ToolSet,
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, Principal, ProviderKind},
+        authz::{Authorizer, Permission},
+        csrf,
+        handlers::session,
+        persist::Database,
+        roles::Role,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    routing::get,
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn principal(subject: &str, roles: Vec<Role>) -> Principal {
+    let is_admin = roles.contains(&Role::Admin);
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles,
+        is_admin,
+    }
+}
+
+fn role(name: &str, permissions: &[&str]) -> RoleConfig {
+    RoleConfig {
+        name: name.to_string(),
+        permissions: permissions.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+/// Builds an auth state with the given role definitions, attached to the app
+/// state so API handlers see them, and a router with `/auth`, `/api` and `/metrics`.
+async fn setup(roles: Vec<RoleConfig>) -> (Router, Arc<AuthState>, Arc<ArkState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("authz.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: None,
+        providers: vec![],
+        session: Some(SessionConfig::default()),
+        roles,
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+    app_state.set_auth_state(auth_state.clone());
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .nest("/api", create_api_router(app_state.clone()))
+        .route("/metrics", get(|| async { "metrics" }))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, app_state, temp_dir)
+}
+
+async fn cookie_for(auth_state: &AuthState, principal: Principal) -> String {
+    let sid = auth_state
+        .put_client_session(principal, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+    format!("ark_session={}", sid)
+}
+
+async fn send(router: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+fn request(method: Method, uri: &str, cookie: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header(csrf::CSRF_HEADER, csrf_token(cookie))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn register_plugin(app: &ArkState, name: &str, owner: &str) {
+    let plugin = ArkPlugin {
+        name: name.to_string(),
+        url: Some("file:///nonexistent.wasm".parse().unwrap()),
+        auth: None,
+        insecure: false,
+        manifest: None,
+        owner: Some(owner.to_string()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
+    };
+    let ts = ToolSet {
+        name: "tools".into(),
+        tools: vec![],
+    };
+    app.register_plugin_with_executors(plugin, ts, vec![])
+        .await
+        .unwrap();
+}
+
+/// The CSRF token the console sends along with the session cookie.
+fn csrf_token(cookie: &str) -> String {
+    csrf::token_for_session(cookie.strip_prefix("ark_session=").unwrap_or_default())
+}
+
+#[test]
+fn test_builtin_role_permissions() {
+    let authz = Authorizer::default();
+    let user = principal("u", vec![Role::User]);
+    assert!(authz.has_permission(&user, Permission::PluginCreate));
+    assert!(authz.has_permission(&user, Permission::ToolExecute));
+    assert!(authz.has_permission(&user, Permission::ConsoleAccess));
+    assert!(!authz.has_permission(&user, Permission::PluginDeleteAny));
+    assert!(!authz.has_permission(&user, Permission::MetricsRead));
+
+    let admin = principal("a", vec![Role::User, Role::Admin]);
+    assert_eq!(authz.permissions(&admin).len(), Permission::ALL.len());
+
+    // Undefined roles grant nothing
+    let stranger = principal("s", vec![Role::from("ghost")]);
+    assert!(authz.permissions(&stranger).is_empty());
+}
+
+#[test]
+fn test_role_config_validation() {
+    let authz = Authorizer::from_config(&[
+        role("operator", &["tool.execute", "metrics.read"]),
+        role("User", &["tool.execute"]),
+    ])
+    .unwrap();
+    assert!(authz.is_defined(&Role::Custom("operator".to_string())));
+    let user = principal("u", vec![Role::User]);
+    assert!(!authz.has_permission(&user, Permission::PluginCreate));
+    let operator = principal("o", vec![Role::from("operator")]);
+    assert!(authz.has_permission(&operator, Permission::MetricsRead));
+
+    assert!(Authorizer::from_config(&[role("ops", &["plugin.launch"])]).is_err());
+    assert!(Authorizer::from_config(&[role("Admin", &[])]).is_err());
+    assert!(Authorizer::from_config(&[role("ops", &[]), role("ops", &[])]).is_err());
+    assert!(Authorizer::from_config(&[role(" ", &[])]).is_err());
+}
+
+#[test]
+fn test_roles_serialize_as_names() {
+    let roles = vec![Role::Admin, Role::User, Role::from("operator")];
+    let encoded = serde_json::to_value(&roles).unwrap();
+    assert_eq!(encoded, json!(["Admin", "User", "operator"]));
+    let decoded: Vec<Role> = serde_json::from_value(encoded).unwrap();
+    assert_eq!(decoded, roles);
+    assert_eq!(
+        serde_json::to_value(Permission::PluginDeleteAny).unwrap(),
+        json!("plugin.delete.any")
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_role_config_rejected_at_startup() {
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: None,
+        providers: vec![],
+        session: None,
+        roles: vec![role("ops", &["everything"])],
+        ..Default::default()
+    };
+    let result =
+        AuthState::new_with_state(&Some(auth_cfg), Arc::new(ArkState::default()), None).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_metrics_gated_by_permission() {
+    let (router, auth_state, _app, _tmp) = setup(vec![role("operator", &["metrics.read"])]).await;
+
+    let user = cookie_for(&auth_state, principal("u", vec![Role::User])).await;
+    let (status, _) = send(
+        &router,
+        request(Method::GET, "/metrics", &user, Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let operator = cookie_for(&auth_state, principal("o", vec![Role::from("operator")])).await;
+    let (status, _) = send(
+        &router,
+        request(Method::GET, "/metrics", &operator, Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // metrics.read does not open the admin API
+    let (status, _) = send(
+        &router,
+        request(Method::GET, "/api/admin/users", &operator, Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_plugin_permissions_enforced_in_handlers() {
+    let (router, auth_state, app, _tmp) = setup(vec![
+        role("User", &["console.access"]),
+        role("janitor", &["plugin.delete.any"]),
+    ])
+    .await;
+    register_plugin(&app, "theirs", "oidc/*/someone-else").await;
+
+    let viewer = cookie_for(&auth_state, principal("v", vec![Role::User])).await;
+    let (status, _) = send(
+        &router,
+        request(
+            Method::POST,
+            "/api/plugins",
+            &viewer,
+            json!({"name": "p", "url": "file:///nonexistent.wasm"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &router,
+        request(Method::DELETE, "/api/plugins/theirs", &viewer, Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // plugin.delete.any overrides ownership
+    let janitor = cookie_for(&auth_state, principal("j", vec![Role::from("janitor")])).await;
+    let (status, _) = send(
+        &router,
+        request(Method::DELETE, "/api/plugins/theirs", &janitor, Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_status_reports_permissions() {
+    let (router, auth_state, _app, _tmp) =
+        setup(vec![role("operator", &["metrics.read", "console.access"])]).await;
+
+    let operator = cookie_for(&auth_state, principal("o", vec![Role::from("operator")])).await;
+    let (status, body) = send(
+        &router,
+        request(Method::GET, "/auth/status", &operator, Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["permissions"],
+        json!(["metrics.read", "console.access"])
+    );
+    assert_eq!(body["user"]["roles"], json!(["operator"]));
+}
+
+#[tokio::test]
+async fn test_service_account_roles_must_be_defined() {
+    let (router, auth_state, _app, _tmp) = setup(vec![role("deployer", &["plugin.create"])]).await;
+    let admin = cookie_for(&auth_state, principal("root", vec![Role::Admin])).await;
+
+    let (status, _) = send(
+        &router,
+        request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "ci", "roles": ["nonexistent"]}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, created) = send(
+        &router,
+        request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "ci", "roles": ["deployer"]}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["roles"], json!(["deployer"]));
+}
@@ -0,0 +1,104 @@
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use reqwest::{StatusCode, header};
+use serde_json::Value;
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[tokio::test]
+async fn test_management_listener_serves_under_base_path() {
+    let dir = TempDir::new().unwrap();
+    let port = free_port();
+    std::fs::write(
+        dir.path().join("ark.yaml"),
+        format!(
+            r#"
+plugins: []
+management_server:
+  bind_address: "127.0.0.1:{port}"
+  base_path: /ark/
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:{}"
+auth:
+  enabled: true
+  provider: local
+  providers:
+    - name: local
+"#,
+            free_port()
+        ),
+    )
+    .unwrap();
+    let _server = Server(
+        Command::new(env!("CARGO_BIN_EXE_ark"))
+            .current_dir(dir.path())
+            .args(["--config-file", "ark.yaml"])
+            .env("ARK_TRANSPORT", "streamable-http")
+            .env("ARK_DB_PATH", dir.path().join("ark.db"))
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_err()
+    {
+        assert!(Instant::now() < deadline, "server did not start");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let get = |path: &str| client.get(format!("http://127.0.0.1:{port}{path}")).send();
+
+    assert_eq!(get("/ark/livez").await.unwrap().status(), StatusCode::OK);
+    assert_eq!(get("/livez").await.unwrap().status(), StatusCode::NOT_FOUND);
+
+    // Redirects and links handed to clients include the prefix
+    let resp = get("/ark/admin").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        resp.headers()[header::LOCATION],
+        "/ark/admin?transport=streamable-http"
+    );
+    let login: Value = get("/ark/auth/login").await.unwrap().json().await.unwrap();
+    assert_eq!(login["redirect"], "/ark/auth/login/local");
+    let form = get("/ark/auth/login/local")
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(form.contains(r#"action="/ark/auth/login/local""#), "{form}");
+    let spec: Value = get("/ark/api/v1/openapi.json")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(spec["servers"][0]["url"], "/ark/api/v1");
+}
@@ -23,8 +23,10 @@ fn default_addresses_when_unset_anywhere() {
         None, // no MCP CLI override
         false,
         true,
-        None, // no management disable_api override
-        None, // no management bind CLI override
+        None,  // no management disable_api override
+        None,  // no management bind CLI override
+        None,  // no profile
+        false, // not strict
     )
     .unwrap();
 
@@ -74,7 +76,9 @@ fn config_file_addresses_when_no_cli_overrides() {
         false,
         true,
         None,
-        None, // no management CLI override
+        None,  // no management CLI override
+        None,  // no profile
+        false, // not strict
     )
     .unwrap();
 
@@ -116,6 +120,8 @@ fn cli_overrides_take_precedence_over_config_file() {
         true,
         None,
         Some("127.0.0.1:6002".to_string()), // management CLI override
+        None,                               // no profile
+        false,                              // not strict
     )
     .unwrap();
 
@@ -128,3 +134,107 @@ fn cli_overrides_take_precedence_over_config_file() {
         Some("127.0.0.1:6002")
     );
 }
+
+/// Test that a list of bind addresses is accepted and kept comma-separated.
+#[test]
+fn bind_address_lists_are_joined() {
+    let tf = write_temp_config(
+        r#"
+        mcp_server:
+          bind_address: ["127.0.0.1:4555", "[::1]:4555"]
+        management_server:
+          bind_address:
+            - "127.0.0.1:4666"
+        "#,
+        "yaml",
+    );
+
+    let cfg = ArkConfig::load_with_overrides(
+        Some(tf.path().to_path_buf()),
+        McpTransport::StreamableHTTP,
+        None,
+        false,
+        true,
+        None,
+        None,
+        None,
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        cfg.mcp_server.unwrap().bind_address.as_deref(),
+        Some("127.0.0.1:4555,[::1]:4555")
+    );
+    assert_eq!(
+        cfg.management_server.unwrap().bind_address.as_deref(),
+        Some("127.0.0.1:4666")
+    );
+}
+
+/// Test that the server accepts connections on every bind address.
+#[test]
+fn server_listens_on_every_bind_address() {
+    use std::io::{Read, Write};
+    use std::time::{Duration, Instant};
+
+    let free_port = || {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    };
+    let (first, second) = (free_port(), free_port());
+    let dir = tempfile::TempDir::new().unwrap();
+    let config = dir.path().join("ark.yaml");
+    fs::write(
+        &config,
+        format!(
+            r#"
+plugins: []
+management_server:
+  bind_address: ["127.0.0.1:{first}", "127.0.0.1:{second}"]
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:{}"
+"#,
+            free_port()
+        ),
+    )
+    .unwrap();
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_ark"))
+        .args(["--config-file", config.to_str().unwrap()])
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.path().join("ark.db"))
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let livez = |port: u16| -> Option<String> {
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).ok()?;
+        stream
+            .write_all(b"GET /livez HTTP/1.0\r\nHost: localhost\r\n\r\n")
+            .ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        Some(response)
+    };
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut responses = (None, None);
+    while Instant::now() < deadline {
+        responses = (livez(first), livez(second));
+        if responses.0.is_some() && responses.1.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let _ = server.kill();
+    let _ = server.wait();
+
+    for response in [responses.0, responses.1] {
+        assert!(response.is_some_and(|r| r.contains(" 200 ")));
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use ark::config::{ArkConfig, models::BodyLimitsConfig};
+use ark::server::roles::Role;
+use ark::{
+    server::{
+        auth::{Principal, ProviderKind},
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use tower::ServiceExt;
+
+fn user() -> Principal {
+    Principal {
+        subject: "alice".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+/// POSTs a JSON string of `len` bytes, with or without a Content-Length.
+async fn post(router: &Router, uri: &str, len: usize, content_length: bool) -> StatusCode {
+    let body = format!("\"{}\"", "a".repeat(len - 2));
+    let mut req = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json");
+    if content_length {
+        req = req.header(header::CONTENT_LENGTH, len);
+    }
+    let resp = router
+        .clone()
+        .oneshot(req.body(Body::from(body)).unwrap())
+        .await
+        .unwrap();
+    resp.status()
+}
+
+#[test]
+fn test_body_limits_config() {
+    let cfg: ArkConfig = serde_yaml_ng::from_str("body_limits:\n  api: 1024\n").unwrap();
+    let limits = cfg.body_limits.unwrap();
+    assert_eq!(limits.api, 1024);
+    assert_eq!(limits.mcp, BodyLimitsConfig::default().mcp);
+    assert_eq!(limits.upload, BodyLimitsConfig::default().upload);
+}
+
+#[tokio::test]
+async fn test_oversized_bodies_are_rejected_per_endpoint_class() {
+    let state = Arc::new(ArkState::default());
+    state.set_body_limits(BodyLimitsConfig {
+        api: 1024,
+        mcp: 1024,
+        upload: 8192,
+    });
+    let router = create_api_router(state).layer(Extension(user()));
+
+    // Rejected up front when declared, or while reading otherwise
+    assert_eq!(
+        post(&router, "/plugins", 2048, true).await,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_eq!(
+        post(&router, "/plugins", 2048, false).await,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_ne!(
+        post(&router, "/plugins", 512, true).await,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+
+    // Bundle imports have their own, larger limit
+    assert_ne!(
+        post(&router, "/plugins/import", 4096, true).await,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_eq!(
+        post(&router, "/plugins/import", 16384, true).await,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_eq!(
+        post(&router, "/plugins/import", 16384, false).await,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+}
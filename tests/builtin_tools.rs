@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use ark::config::ArkConfig;
+use ark::plugins;
+use ark::plugins::builtin::BUILTIN_PLUGIN_ID;
+use ark::server::auth::{Principal, ProviderKind};
+use ark::server::authz;
+use ark::server::roles::Role;
+use ark::state::{ApplicationState, ArkState};
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+async fn builtin_state() -> Arc<ArkState> {
+    let state = Arc::new(ArkState::default());
+    state.set_state(ApplicationState::StartingNetwork);
+    plugins::load_plugins(&ArkConfig::default(), state.clone())
+        .await
+        .unwrap();
+    state
+}
+
+fn principal(is_admin: bool) -> Principal {
+    Principal {
+        subject: "tester".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![if is_admin { Role::Admin } else { Role::User }],
+        is_admin,
+    }
+}
+
+async fn call(
+    state: &ArkState,
+    caller: Option<Principal>,
+    tool: &str,
+    args: Value,
+) -> anyhow::Result<Value> {
+    authz::with_caller(caller, state.plugin_registry.call(tool, &args)).await
+}
+
+/// Serves one HTTP request on a local port, answering 204.
+async fn http_server() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf).await;
+        let _ = stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+            .await;
+    });
+    format!("http://{addr}/")
+}
+
+#[tokio::test]
+async fn test_builtin_diagnostic_tools_are_registered() {
+    let state = builtin_state().await;
+    let mut tools: Vec<String> = state
+        .get_tools(None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.name.to_string())
+        .collect();
+    tools.sort();
+    assert_eq!(
+        tools,
+        [
+            "echo",
+            "list_capabilities",
+            "ping_url",
+            "server_info",
+            "time"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_server_info_time_and_capabilities() {
+    let state = builtin_state().await;
+
+    let info = call(&state, None, "server_info", json!({})).await.unwrap();
+    assert_eq!(
+        info["structuredContent"]["version"],
+        env!("CARGO_PKG_VERSION")
+    );
+    assert_eq!(info["structuredContent"]["transport"], "stdio");
+    assert!(info["structuredContent"]["uptime_secs"].is_u64());
+
+    let time = call(&state, None, "time", json!({})).await.unwrap();
+    let utc = time["structuredContent"]["utc"].as_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(utc).is_ok(), "{utc}");
+
+    let caps = call(&state, None, "list_capabilities", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(caps["structuredContent"]["plugins"], json!(["__BUILTIN__"]));
+    assert_eq!(caps["structuredContent"]["auth"], false);
+    assert!(
+        caps["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("ping_url")
+    );
+
+    let echo = call(&state, None, "echo", json!({"message": "hi"}))
+        .await
+        .unwrap();
+    assert_eq!(echo["content"][0]["text"], "hi");
+}
+
+#[tokio::test]
+async fn test_builtin_tools_load_alongside_plugins_when_configured() {
+    // A plugin whose one tool is called "time", as a builtin tool is
+    let dir = tempfile::TempDir::new().unwrap();
+    let wat = std::fs::read_to_string("tests/testdata/warmup_plugin.wat")
+        .unwrap()
+        .replace(r#"\"name\":\"status\""#, r#"\"name\":\"time\""#)
+        .replace("(i32.const 61)", "(i32.const 59)");
+    let path = dir.path().join("clock.wat");
+    std::fs::write(&path, wat).unwrap();
+    let url = ark::config::plugins::file_path_to_url(&path).unwrap();
+
+    for always in [false, true] {
+        let config: ArkConfig = serde_json::from_value(json!({
+            "always_load_builtin": always,
+            "plugins": [{ "name": "clock", "url": url }],
+        }))
+        .unwrap();
+        let state = Arc::new(ArkState::default());
+        state.set_state(ApplicationState::StartingNetwork);
+        plugins::load_plugins(&config, state.clone()).await.unwrap();
+
+        let catalog = state.plugin_registry.catalog.load();
+        assert_eq!(
+            catalog.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID),
+            always
+        );
+        assert_eq!(catalog.tool_to_plugin.contains_key("echo"), always);
+        // The plugin's tool is not replaced by the builtin one
+        assert_eq!(catalog.tool_to_plugin["time"], "clock");
+        drop(catalog);
+        let time = call(&state, None, "time", json!({})).await.unwrap();
+        assert_eq!(time["content"][0]["text"], "cold");
+    }
+}
+
+#[tokio::test]
+async fn test_ping_url_is_restricted_to_administrators() {
+    let state = builtin_state().await;
+    let args = json!({ "url": "http://127.0.0.1:9/" });
+
+    let err = call(&state, Some(principal(false)), "ping_url", args.clone())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("administrators"), "{err}");
+    // Not reachable outside of a tool call either
+    assert!(state.plugin_registry.call("ping_url", &args).await.is_err());
+
+    let url = http_server().await;
+    let ping = call(
+        &state,
+        Some(principal(true)),
+        "ping_url",
+        json!({ "url": url }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(ping["structuredContent"]["status"], 204);
+
+    // Without authentication there is no principal, as for the management API
+    let ping = call(
+        &state,
+        None,
+        "ping_url",
+        json!({ "url": "ftp://example.com/" }),
+    )
+    .await;
+    assert!(ping.unwrap_err().to_string().contains("http or https"));
+}
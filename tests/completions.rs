@@ -0,0 +1,36 @@
+use std::process::Command;
+
+fn ark(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ark"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_completions_cover_subcommands_and_options() {
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let script = ark(&["completions", shell]);
+        for word in ["keygen", "plugin", "config-file", "strict-config"] {
+            assert!(
+                script.contains(word),
+                "{word} missing from {shell} completions"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_man_page() {
+    let page = ark(&["man"]);
+    assert!(page.contains(".TH ark 1"), "{page}");
+    // Options document their environment variables
+    assert!(page.contains("ARK_CONFIG_PATH"));
+    assert!(page.contains("completions"));
+}
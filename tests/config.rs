@@ -40,6 +40,8 @@ fn load_empty_uses_explicit_defaults() {
         true,
         None,
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -53,6 +55,7 @@ fn load_empty_uses_explicit_defaults() {
     let mgmt = cfg.management_server.clone().unwrap();
     assert_eq!(mgmt.livez.path.as_deref(), Some("/livez"));
     assert_eq!(mgmt.readyz.path.as_deref(), Some("/readyz"));
+    assert_eq!(mgmt.startupz.path.as_deref(), Some("/startupz"));
     assert_eq!(mgmt.response_type.to_lowercase(), "json");
 
     // mcp defaults present
@@ -87,6 +90,8 @@ fn load_yaml_without_mcp_block_applies_defaults() {
         true,
         None,
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -135,6 +140,8 @@ fn load_yaml_with_specific_cors() {
         true,
         Some(false),
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -180,6 +187,8 @@ fn load_yaml_with_multilevel_health_paths() {
         true,
         None,
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -235,6 +244,8 @@ fn load_yaml_with_plugins_and_tls() {
         true,
         None,
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -287,6 +298,298 @@ fn load_yaml_with_plugins_and_tls() {
     assert!(!tls.silent_insecure);
 }
 
+/// Test that TOML and JSON files are parsed according to their extension.
+#[test]
+fn load_toml_and_json_by_extension() {
+    let toml = write_temp_config(
+        r#"
+        [management_server]
+        bind_address = "127.0.0.1:4000"
+
+        [management_server.livez]
+        enabled = true
+        path = "/healthz"
+
+        [[plugins]]
+        name = "time"
+        url = "file:///path/to/time_plugin.wasm"
+
+        [plugins.manifest.config]
+        timeout_ms = "2000"
+        "#,
+        "toml",
+    );
+    let json = write_temp_config(
+        r#"{
+            "management_server": {
+                "bind_address": "127.0.0.1:4000",
+                "livez": { "enabled": true, "path": "/healthz" }
+            },
+            "plugins": [{
+                "name": "time",
+                "url": "file:///path/to/time_plugin.wasm",
+                "manifest": { "config": { "timeout_ms": "2000" } }
+            }]
+        }"#,
+        "json",
+    );
+
+    for tf in [&toml, &json] {
+        let cfg = ArkConfig::load_with_overrides(
+            Some(tf.path().to_path_buf()),
+            McpTransport::StreamableHTTP,
+            None,
+            false,
+            true,
+            None,
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        let mgmt = cfg.management_server.clone().unwrap();
+        assert_eq!(mgmt.bind_address, Some("127.0.0.1:4000".to_string()));
+        assert_eq!(mgmt.livez.path.as_deref(), Some("/healthz"));
+        // Missing sections still get explicit defaults
+        assert!(cfg.mcp_server.is_some());
+
+        assert_eq!(cfg.plugins.len(), 1);
+        let manifest = cfg.plugins[0].manifest.clone().unwrap();
+        assert_eq!(
+            manifest.config.unwrap().get("timeout_ms"),
+            Some(&"2000".to_string())
+        );
+    }
+
+    // Errors name the format of the file
+    let bad = write_temp_config("management_server = [", "toml");
+    let err = ArkConfig::load_with_overrides(
+        Some(bad.path().to_path_buf()),
+        McpTransport::Stdio,
+        None,
+        false,
+        true,
+        None,
+        None,
+        None,
+        false,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("toml error"), "{err}");
+}
+
+/// Test that included files and config.d fragments are merged over the main file.
+#[test]
+fn load_with_includes_and_config_d() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let write = |name: &str, contents: &str| {
+        let p = dir.path().join(name);
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(&p, contents).unwrap();
+        p
+    };
+    let main = write(
+        "config.yaml",
+        r#"
+        include: [teams]
+        management_server:
+          bind_address: "127.0.0.1:4000"
+          livez:
+            enabled: true
+            path: "/healthz"
+        plugins:
+        - name: base
+          url: "file:///plugins/base.wasm"
+        "#,
+    );
+    write(
+        "teams/search.yaml",
+        r#"
+        plugins:
+        - name: search
+          url: "file:///plugins/search.wasm"
+        "#,
+    );
+    write(
+        "config.d/10-billing.toml",
+        r#"
+        [[plugins]]
+        name = "billing"
+        url = "file:///plugins/billing.wasm"
+        "#,
+    );
+    write(
+        "config.d/20-override.json",
+        r#"{ "management_server": { "bind_address": "0.0.0.0:4000" } }"#,
+    );
+    write("config.d/README.md", "not a fragment");
+
+    let load = |path: &std::path::Path| {
+        ArkConfig::load_with_overrides(
+            Some(path.to_path_buf()),
+            McpTransport::StreamableHTTP,
+            None,
+            false,
+            true,
+            None,
+            None,
+            None,
+            false,
+        )
+    };
+    let cfg = load(&main).unwrap();
+
+    let names: Vec<_> = cfg.plugins.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["base", "search", "billing"]);
+    let mgmt = cfg.management_server.clone().unwrap();
+    assert_eq!(mgmt.bind_address.as_deref(), Some("0.0.0.0:4000"));
+    // Sections are merged, not replaced
+    assert_eq!(mgmt.livez.path.as_deref(), Some("/healthz"));
+
+    // Fragments are checked like the main file
+    write("config.d/30-bad.yaml", "plugins: 42");
+    let err = load(&main).unwrap_err().to_string();
+    assert!(err.contains("30-bad.yaml"), "{err}");
+    fs::remove_file(dir.path().join("config.d/30-bad.yaml")).unwrap();
+
+    let missing = write("missing.yaml", "include: [nowhere.yaml]");
+    let err = load(&missing).unwrap_err().to_string();
+    assert!(err.contains("nowhere.yaml"), "{err}");
+}
+
+/// Test that a selected profile is merged over the shared settings, below CLI overrides.
+#[test]
+fn load_with_profile() {
+    let tf = write_temp_config(
+        r#"
+        management_server:
+          bind_address: "127.0.0.1:4000"
+          livez:
+            enabled: true
+            path: "/healthz"
+        logging:
+          filters:
+            ark: info
+        profiles:
+          dev:
+            logging:
+              filters:
+                ark: debug
+          prod:
+            management_server:
+              bind_address: "0.0.0.0:4000"
+            mcp_server:
+              bind_address: "0.0.0.0:3001"
+        "#,
+        "yaml",
+    );
+    let load = |profile: Option<&str>, mcp_bind: Option<&str>| {
+        ArkConfig::load_with_overrides(
+            Some(tf.path().to_path_buf()),
+            McpTransport::StreamableHTTP,
+            mcp_bind.map(str::to_string),
+            false,
+            true,
+            None,
+            None,
+            profile.map(str::to_string),
+            false,
+        )
+    };
+
+    let shared = load(None, None).unwrap();
+    let filters = &shared.logging.as_ref().unwrap().filters;
+    assert_eq!(filters.get("ark").map(String::as_str), Some("info"));
+    assert!(shared.profiles.is_empty());
+
+    let dev = load(Some("dev"), None).unwrap();
+    let filters = &dev.logging.as_ref().unwrap().filters;
+    assert_eq!(filters.get("ark").map(String::as_str), Some("debug"));
+    assert_eq!(
+        dev.management_server.unwrap().bind_address.as_deref(),
+        Some("127.0.0.1:4000")
+    );
+
+    let prod = load(Some("prod"), Some("127.0.0.1:3002")).unwrap();
+    let mgmt = prod.management_server.unwrap();
+    assert_eq!(mgmt.bind_address.as_deref(), Some("0.0.0.0:4000"));
+    assert_eq!(mgmt.livez.path.as_deref(), Some("/healthz"));
+    // CLI overrides win over the profile
+    assert_eq!(
+        prod.mcp_server.unwrap().bind_address.as_deref(),
+        Some("127.0.0.1:3002")
+    );
+
+    let err = load(Some("qa"), None).unwrap_err().to_string();
+    assert!(
+        err.contains("profile 'qa' is not defined (defined: dev, prod)"),
+        "{err}"
+    );
+}
+
+/// Test that strict mode rejects misspelled settings, which are otherwise ignored.
+#[test]
+fn load_strict_rejects_unknown_settings() {
+    let yaml = r#"
+        managment_server:
+          bind_address: "0.0.0.0:4000"
+        mcp_server:
+          bind_adress: "0.0.0.0:3001"
+        plugins:
+          - name: time
+            url: oci://ghcr.io/example/time:v1
+            insecure_tls: true
+        profiles:
+          dev:
+            loging:
+              filters:
+                ark: debug
+        "#;
+    let load = |yaml: &str, strict: bool| {
+        let tf = write_temp_config(yaml, "yaml");
+        ArkConfig::load_with_overrides(
+            Some(tf.path().to_path_buf()),
+            McpTransport::StreamableHTTP,
+            None,
+            false,
+            true,
+            None,
+            None,
+            None,
+            strict,
+        )
+    };
+
+    // Lenient by default
+    let cfg = load(yaml, false).unwrap();
+    assert_eq!(
+        cfg.management_server.unwrap().bind_address.as_deref(),
+        Some("127.0.0.1:8000")
+    );
+
+    let err = load(yaml, true).unwrap_err().to_string();
+    for setting in [
+        "managment_server",
+        "mcp_server.bind_adress",
+        "plugins.0.insecure_tls",
+        "profiles.dev.loging",
+    ] {
+        assert!(err.contains(setting), "{setting} missing from: {err}");
+    }
+
+    // The file can ask for strict mode itself
+    let err = load(&format!("        strict: true{yaml}"), false).unwrap_err();
+    assert!(err.to_string().contains("managment_server"), "{err}");
+
+    // Known settings pass
+    load(
+        "strict: true\nmcp_server:\n  bind_address: 0.0.0.0:3001\n",
+        true,
+    )
+    .unwrap();
+}
+
 /// Test TLS configuration via environment variables overrides YAML config.
 #[test]
 fn tls_env_vars_override_config() {
@@ -315,6 +618,8 @@ fn tls_env_vars_override_config() {
         true,
         None,
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -359,6 +664,8 @@ fn auth_scopes_env_var_override() {
         true,
         None,
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -412,22 +719,53 @@ async fn mgmt_api_disabled_console_and_health_enabled_mcp_only_serves_mcp_and_he
                 path: Some("/readyz".into()),
                 enabled: true,
             },
+            startupz: ManagementPathConfig {
+                path: Some("/startupz".into()),
+                enabled: true,
+            },
             bind_address: Some(mgmt_bind.clone()),
             response_type: "json".into(),
             disable_plugin_api: true,
             disable_console: true,
+            swagger_ui: false,
             disable_health_api: false,
             cors: Some("http://localhost:3000".to_string()),
             disable_prometheus_api: false,
             disable_emit_otel: true,
+            metrics_auth: None,
+            tool_metrics_owner: None,
+            push_gateway: None,
+            statsd: None,
+            histogram_buckets: None,
+            rate_limits: None,
+            ip_filter: None,
+            tls: None,
+            base_path: None,
+            console_path: "www/dist".to_string(),
         }),
         mcp_server: Some(McpEndpointConfig {
             cors: Some("http://localhost:3000".to_string()),
             bind_address: Some(bind.clone()),
+            tls: None,
+            shared_sessions: false,
         }),
+        always_load_builtin: false,
         plugins: vec![],
         auth: None,
         token_signing: None,
+        logging: None,
+        webhooks: None,
+        body_limits: None,
+        quotas: None,
+        vault: None,
+        listeners: None,
+        watch: None,
+        wasm_engine: None,
+        plugin_downloads: None,
+        trusted_proxies: Vec::new(),
+        include: vec![],
+        profiles: Default::default(),
+        strict: false,
     };
 
     let state = Arc::new(ArkState::default());
@@ -480,6 +818,16 @@ async fn mgmt_api_disabled_console_and_health_enabled_mcp_only_serves_mcp_and_he
             .as_u16(),
         200
     );
+    assert_eq!(
+        client
+            .get(format!("{}/startupz", mgmt_base))
+            .send()
+            .await
+            .unwrap()
+            .status()
+            .as_u16(),
+        200
+    );
 
     // Management: API disabled
     assert!(
@@ -552,22 +900,53 @@ async fn console_disabled_api_and_health_enabled_mcp_only_serves_mcp() {
                 path: Some("/readyz".into()),
                 enabled: false,
             },
+            startupz: ManagementPathConfig {
+                path: Some("/startupz".into()),
+                enabled: false,
+            },
             bind_address: Some(mgmt_bind.clone()),
             response_type: "json".into(),
             disable_plugin_api: false,
             disable_console: true,
+            swagger_ui: false,
             disable_health_api: false,
             cors: Some("http://localhost:3000".to_string()),
             disable_prometheus_api: false,
             disable_emit_otel: true,
+            metrics_auth: None,
+            tool_metrics_owner: None,
+            push_gateway: None,
+            statsd: None,
+            histogram_buckets: None,
+            rate_limits: None,
+            ip_filter: None,
+            tls: None,
+            base_path: None,
+            console_path: "www/dist".to_string(),
         }),
         mcp_server: Some(McpEndpointConfig {
             cors: Some("http://localhost:3000".to_string()),
             bind_address: Some(main_bind.clone()),
+            tls: None,
+            shared_sessions: false,
         }),
+        always_load_builtin: false,
         plugins: vec![],
         auth: None,
         token_signing: None,
+        logging: None,
+        webhooks: None,
+        body_limits: None,
+        quotas: None,
+        vault: None,
+        listeners: None,
+        watch: None,
+        wasm_engine: None,
+        plugin_downloads: None,
+        trusted_proxies: Vec::new(),
+        include: vec![],
+        profiles: Default::default(),
+        strict: false,
     };
 
     let state = Arc::new(ArkState::default());
@@ -0,0 +1,64 @@
+use ark::config::{
+    ArkConfig,
+    init::{AuthPreset, InitOptions, starter_config},
+    models::McpTransport,
+};
+use clap::ValueEnum;
+
+fn parse(options: &InitOptions) -> ArkConfig {
+    let text = starter_config(options);
+    assert!(!text.contains('\r'));
+    serde_yaml_ng::from_str(&text).unwrap_or_else(|e| panic!("{e}\n{text}"))
+}
+
+#[test]
+fn test_starter_config_without_options() {
+    let config = parse(&InitOptions::default());
+    assert!(!config.auth.unwrap().enabled);
+    assert!(config.tls.is_none());
+    // The MCP listener is only configured for HTTP transports
+    assert!(config.mcp_server.is_none());
+    assert_eq!(config.plugins.len(), 1);
+    assert_eq!(
+        config.management_server.unwrap().bind_address.as_deref(),
+        Some("127.0.0.1:8000")
+    );
+}
+
+#[test]
+fn test_starter_config_for_every_provider() {
+    for &preset in AuthPreset::value_variants() {
+        for tls in [false, true] {
+            let options = InitOptions {
+                auth: Some(preset),
+                transport: McpTransport::StreamableHTTP,
+                tls,
+            };
+            let config = parse(&options);
+            let name = format!("{preset:?}/{tls}");
+
+            let auth = config.auth.unwrap();
+            assert!(auth.enabled, "{name}");
+            assert_eq!(auth.providers.len(), 1, "{name}");
+            assert_eq!(
+                auth.provider,
+                Some(auth.providers[0].name.clone()),
+                "{name}"
+            );
+            assert_eq!(auth.session.unwrap().cookie_secure, tls, "{name}");
+            assert_eq!(config.tls.is_some(), tls, "{name}");
+
+            let cors = config.mcp_server.unwrap().cors.unwrap();
+            let scheme = if tls { "https://" } else { "http://" };
+            assert!(cors.starts_with(scheme), "{name}: {cors}");
+        }
+    }
+
+    let entra = parse(&InitOptions {
+        auth: Some(AuthPreset::Entra),
+        ..Default::default()
+    });
+    let provider = &entra.auth.unwrap().providers[0];
+    assert_eq!(provider.name, "entra");
+    assert!(provider.client_secret_file.is_some());
+}
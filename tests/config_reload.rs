@@ -0,0 +1,231 @@
+use std::sync::{Arc, Mutex};
+
+use ark::config::{
+    ArkConfig,
+    models::{ManagementEndpointConfig, McpEndpointConfig, RateLimitConfig, RateLimitsConfig},
+    plugins::ArkPlugin,
+};
+use ark::server::{
+    auth::{Principal, ProviderKind},
+    rate_limit::RateLimitClass,
+    reload::ConfigReloader,
+    roles::Role,
+    service::create_api_router,
+};
+use ark::state::ArkState;
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode},
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+fn admin() -> Principal {
+    Principal {
+        subject: "root".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        is_admin: true,
+        roles: vec![Role::Admin],
+    }
+}
+
+fn sample_plugin() -> ArkPlugin {
+    let mut p = std::env::current_dir().unwrap();
+    p.push("tests/testdata/sample.wasm");
+    ArkPlugin {
+        name: "sample".to_string(),
+        url: Some(url::Url::from_file_path(p).unwrap()),
+        ..Default::default()
+    }
+}
+
+/// Reloader whose "file" is the returned config, which tests edit in place.
+fn reloader(config: ArkConfig) -> (Arc<ConfigReloader>, Arc<Mutex<ArkConfig>>) {
+    let file = Arc::new(Mutex::new(config.clone()));
+    let source = file.clone();
+    let reloader = ConfigReloader::new(
+        Arc::new(move || {
+            let config = source.lock().unwrap().clone();
+            Box::pin(async move { Ok(config) })
+        }),
+        config,
+    );
+    (Arc::new(reloader), file)
+}
+
+async fn post_reload(router: &Router) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/admin/reload")
+        .body(Body::empty())
+        .unwrap();
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_reload_applies_runtime_settings_and_reports_the_rest() {
+    let state = Arc::new(ArkState::default());
+    let (reloader, file) = reloader(ArkConfig {
+        management_server: Some(ManagementEndpointConfig::default()),
+        ..Default::default()
+    });
+
+    let report = reloader.reload(&state).await.unwrap();
+    assert!(report.applied.is_empty() && report.restart_required.is_empty());
+
+    {
+        let mut config = file.lock().unwrap();
+        let management = config.management_server.as_mut().unwrap();
+        management.bind_address = Some("0.0.0.0:9000".to_string());
+        management.rate_limits = Some(RateLimitsConfig {
+            auth: Some(RateLimitConfig {
+                per_minute: 60,
+                burst: Some(1),
+            }),
+            ..Default::default()
+        });
+        config.plugins.push(sample_plugin());
+    }
+    let report = reloader.reload(&state).await.unwrap();
+    assert_eq!(
+        report.applied,
+        vec!["management_server.rate_limits", "plugins.sample"]
+    );
+    assert_eq!(
+        report.restart_required,
+        vec!["management_server.bind_address"]
+    );
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert!(!state.get_tools(Some("sample")).await.unwrap().is_empty());
+
+    let limiter = &reloader.live.rate_limiter;
+    assert!(limiter.check(RateLimitClass::Auth, "a").is_none());
+    assert!(limiter.check(RateLimitClass::Auth, "a").is_some());
+
+    // Settings needing a restart are reported until the server restarts
+    file.lock().unwrap().plugins.clear();
+    let report = reloader.reload(&state).await.unwrap();
+    assert_eq!(report.applied, vec!["plugins.sample"]);
+    assert_eq!(
+        report.restart_required,
+        vec!["management_server.bind_address"]
+    );
+    assert!(state.get_tools(Some("sample")).await.unwrap().is_empty());
+
+    // Plugins that fail to load keep their previous version
+    file.lock().unwrap().plugins.push(ArkPlugin {
+        name: "broken".to_string(),
+        url: Some("file:///nonexistent.wasm".parse().unwrap()),
+        ..Default::default()
+    });
+    let report = reloader.reload(&state).await.unwrap();
+    assert!(report.applied.is_empty());
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].starts_with("plugins.broken:"));
+}
+
+#[tokio::test]
+async fn test_reload_endpoint() {
+    let state = Arc::new(ArkState::default());
+    let router = create_api_router(state.clone()).layer(Extension(admin()));
+
+    let (status, _) = post_reload(&router).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    let (reloader, file) = reloader(ArkConfig::default());
+    state.set_config_reloader(reloader);
+    file.lock().unwrap().mcp_server = Some(McpEndpointConfig {
+        cors: None,
+        bind_address: Some("0.0.0.0:3001".to_string()),
+        tls: None,
+        shared_sessions: false,
+    });
+    let (status, report) = post_reload(&router).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["applied"], serde_json::json!([]));
+    assert_eq!(
+        report["restart_required"],
+        serde_json::json!(["mcp_server.bind_address", "mcp_server.shared_sessions"])
+    );
+
+    // Non-admins cannot reload
+    let user = create_api_router(state).layer(Extension(Principal {
+        is_admin: false,
+        roles: vec![Role::User],
+        ..admin()
+    }));
+    let (status, _) = post_reload(&user).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_effective_config_masks_secrets() {
+    let config: ArkConfig = serde_yaml_ng::from_str(
+        r#"
+        tls:
+          key: "assets/server.key"
+          cert: "assets/server.pem"
+        auth:
+          enabled: true
+          providers:
+            - name: entra
+              client_id: "client"
+              client_secret: "s3cr3t"
+              authority: https://login.example.com/v2.0
+        webhooks:
+          endpoints:
+            - url: https://hooks.example.com/ark
+              secret: "hmac-key"
+        plugins:
+          - name: time
+            url: oci://ghcr.io/example/time:v1
+            config:
+              type: basic
+              username: robot
+              password: "hunter2"
+        "#,
+    )
+    .unwrap();
+
+    let state = Arc::new(ArkState::default());
+    let router = create_api_router(state.clone()).layer(Extension(admin()));
+    let req = || {
+        Request::builder()
+            .uri("/admin/config")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let resp = router.clone().oneshot(req()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    state.set_config_reloader(reloader(config).0);
+    let resp = router.oneshot(req()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    for secret in ["s3cr3t", "hmac-key", "hunter2"] {
+        assert!(!text.contains(secret), "{secret} leaked: {text}");
+    }
+
+    let config: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(config["auth"]["providers"][0]["client_secret"], "********");
+    assert_eq!(config["auth"]["providers"][0]["client_id"], "client");
+    assert_eq!(config["webhooks"]["endpoints"][0]["secret"], "********");
+    assert_eq!(config["plugins"][0]["config"]["password"], "********");
+    assert_eq!(config["plugins"][0]["config"]["username"], "robot");
+    // Paths to key files are not secrets
+    assert_eq!(config["tls"]["key"], "assets/server.key");
+}
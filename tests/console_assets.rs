@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use ark::{server::service::create_console_router, state::ArkState};
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{StatusCode, header},
+    response::Response,
+};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const INDEX: &str = "<html><head></head><body>external console</body></html>";
+const SCRIPT: &str = "console.log('external console');";
+
+/// Writes a console build with a gzip-compressed script next to the plain one.
+fn console_build() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join("assets")).unwrap();
+    std::fs::create_dir_all(dir.path().join("fonts")).unwrap();
+    std::fs::write(dir.path().join("index.html"), INDEX).unwrap();
+    std::fs::write(dir.path().join("assets/index-3f2a1b.js"), SCRIPT).unwrap();
+    std::fs::write(dir.path().join("assets/index-3f2a1b.js.gz"), b"gzipped").unwrap();
+    std::fs::write(dir.path().join("fonts/inter.woff2"), b"font").unwrap();
+    dir
+}
+
+async fn get(router: &Router, uri: &str, accept_encoding: Option<&str>) -> Response {
+    let mut builder = Request::builder().uri(uri);
+    if let Some(encoding) = accept_encoding {
+        builder = builder.header(header::ACCEPT_ENCODING, encoding);
+    }
+    router
+        .clone()
+        .oneshot(builder.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn header_value(resp: &Response, name: header::HeaderName) -> Option<&str> {
+    resp.headers().get(name).map(|v| v.to_str().unwrap())
+}
+
+async fn body(resp: Response) -> String {
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[tokio::test]
+async fn test_console_served_from_configured_directory() {
+    let dir = console_build();
+    let router = create_console_router(Arc::new(ArkState::default()), false, dir.path());
+
+    for uri in ["/?transport=streamable-http", "/plugins"] {
+        let resp = get(&router, uri, None).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{uri}");
+        assert_eq!(header_value(&resp, header::CACHE_CONTROL), Some("no-cache"));
+        assert_eq!(body(resp).await, INDEX);
+    }
+}
+
+#[tokio::test]
+async fn test_hashed_assets_are_immutable() {
+    let dir = console_build();
+    let router = create_console_router(Arc::new(ArkState::default()), false, dir.path());
+
+    let resp = get(&router, "/assets/index-3f2a1b.js", None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        header_value(&resp, header::CACHE_CONTROL),
+        Some("public, max-age=31536000, immutable")
+    );
+    assert_eq!(header_value(&resp, header::VARY), Some("accept-encoding"));
+    assert_eq!(body(resp).await, SCRIPT);
+
+    let resp = get(&router, "/fonts/inter.woff2", None).await;
+    assert_eq!(
+        header_value(&resp, header::CACHE_CONTROL),
+        Some("public, max-age=86400")
+    );
+
+    // Missing files are not cached
+    let resp = get(&router, "/assets/missing.js", None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert!(resp.headers().get(header::CACHE_CONTROL).is_none());
+}
+
+#[tokio::test]
+async fn test_precompressed_assets() {
+    let dir = console_build();
+    let router = create_console_router(Arc::new(ArkState::default()), false, dir.path());
+
+    let resp = get(&router, "/assets/index-3f2a1b.js", Some("gzip, br")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(header_value(&resp, header::CONTENT_ENCODING), Some("gzip"));
+    assert_eq!(body(resp).await, "gzipped");
+
+    // Without a .br sibling, brotli-only clients get the plain file
+    let resp = get(&router, "/assets/index-3f2a1b.js", Some("br")).await;
+    assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_eq!(body(resp).await, SCRIPT);
+}
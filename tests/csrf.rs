@@ -0,0 +1,331 @@
+use std::sync::Arc;
+
+use ark::{
+    config::{
+        ArkConfig,
+        models::{AuthConfig, IdentityProviderConfig, ManagementEndpointConfig, SessionConfig},
+    },
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, ProviderKind},
+        csrf,
+        handlers::session,
+        persist::Database,
+        reload::LiveSettings,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+};
+use serde_json::json;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const PASSWORD: &str = "correct horse battery";
+const CONSOLE_ORIGIN: &str = "https://console.example.com";
+
+/// Builds a local-provider auth state with `/auth`, `/api` and the production
+/// `check_auth`.
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("csrf.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", PASSWORD)
+        .await
+        .unwrap();
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .nest("/api", create_api_router(app_state))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+async fn call(router: &Router, req: Request<Body>) -> Response {
+    router.clone().oneshot(req).await.unwrap()
+}
+
+/// Returns the raw `Set-Cookie` header values.
+fn set_cookies(resp: &Response) -> Vec<String> {
+    resp.headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|v| v.to_str().unwrap().to_string())
+        .collect()
+}
+
+fn cookie_value(cookies: &[String], name: &str) -> Option<String> {
+    cookies.iter().find_map(|c| {
+        c.split(';')
+            .next()?
+            .strip_prefix(&format!("{}=", name))
+            .map(str::to_string)
+    })
+}
+
+async fn login(router: &Router) -> (String, String) {
+    let resp = call(
+        router,
+        Request::builder()
+            .method(Method::POST)
+            .uri("/auth/login/local")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(format!(
+                "username=admin&password={}",
+                urlencoding::encode(PASSWORD)
+            )))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    let cookies = set_cookies(&resp);
+    (
+        cookie_value(&cookies, "ark_session").unwrap(),
+        cookie_value(&cookies, csrf::CSRF_COOKIE).unwrap(),
+    )
+}
+
+fn create_token(session_id: &str, csrf_token: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method(Method::POST)
+        .uri("/api/me/tokens")
+        .header(
+            header::COOKIE,
+            format!(
+                "ark_session={}; {}={}",
+                session_id,
+                csrf::CSRF_COOKIE,
+                csrf::token_for_session(session_id)
+            ),
+        )
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(token) = csrf_token {
+        builder = builder.header(csrf::CSRF_HEADER, token);
+    }
+    builder
+        .body(Body::from(json!({"name": "ci"}).to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_login_issues_csrf_cookie() {
+    let (router, _auth, _tmp) = setup().await;
+    let (session_id, csrf_token) = login(&router).await;
+    assert_eq!(csrf_token, csrf::token_for_session(&session_id));
+    assert_ne!(csrf_token, session_id);
+
+    // Readable by the console, unlike the session cookie
+    let resp = call(
+        &router,
+        Request::builder()
+            .uri("/auth/status")
+            .header(header::COOKIE, format!("ark_session={}", session_id))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let cookies = set_cookies(&resp);
+    let issued = cookies
+        .iter()
+        .find(|c| c.starts_with(csrf::CSRF_COOKIE))
+        .unwrap();
+    assert!(!issued.contains("HttpOnly"));
+    assert_eq!(
+        cookie_value(&cookies, csrf::CSRF_COOKIE).unwrap(),
+        csrf_token
+    );
+
+    // Logging out expires both cookies
+    let resp = call(
+        &router,
+        Request::builder()
+            .uri("/auth/logout")
+            .header(header::COOKIE, format!("ark_session={}", session_id))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    let cookies = set_cookies(&resp);
+    assert_eq!(cookie_value(&cookies, "ark_session").unwrap(), "deleted");
+    assert_eq!(
+        cookie_value(&cookies, csrf::CSRF_COOKIE).unwrap(),
+        "deleted"
+    );
+}
+
+#[tokio::test]
+async fn test_cookie_requests_require_csrf_token() {
+    let (router, auth_state, _tmp) = setup().await;
+    let (session_id, csrf_token) = login(&router).await;
+
+    // The cookie alone, as a cross-site form or fetch would send it
+    let resp = call(&router, create_token(&session_id, None)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = call(&router, create_token(&session_id, Some("forged"))).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // Another session's token does not match
+    let principal = auth_state
+        .authenticate_password(&ProviderKind::Local, None, "admin", PASSWORD)
+        .await
+        .unwrap();
+    let other = auth_state
+        .put_client_session(
+            principal,
+            std::time::Duration::from_secs(600),
+            &ClientInfo::default(),
+        )
+        .await;
+    let resp = call(
+        &router,
+        create_token(&session_id, Some(&csrf::token_for_session(&other))),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = call(&router, create_token(&session_id, Some(&csrf_token))).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    // Reads need no token
+    let resp = call(
+        &router,
+        Request::builder()
+            .uri("/api/me/tokens")
+            .header(header::COOKIE, format!("ark_session={}", session_id))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_bearer_requests_need_no_csrf_token() {
+    let (router, _auth, _tmp) = setup().await;
+    let (session_id, csrf_token) = login(&router).await;
+    let resp = call(&router, create_token(&session_id, Some(&csrf_token))).await;
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let api_key = created["token"].as_str().unwrap();
+
+    // Scripts authenticate with a header browsers never add on their own
+    let resp = call(
+        &router,
+        Request::builder()
+            .method(Method::DELETE)
+            .uri(format!(
+                "/api/me/tokens/{}",
+                created["id"].as_str().unwrap()
+            ))
+            .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_ne!(resp.status(), StatusCode::FORBIDDEN);
+    assert_ne!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+/// Sends a CORS preflight for `method` from `CONSOLE_ORIGIN` through the
+/// management listener's CORS policy.
+async fn preflight(router: Router, method: Method) -> Response {
+    let config = ArkConfig {
+        management_server: Some(ManagementEndpointConfig {
+            cors: Some(CONSOLE_ORIGIN.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let cors = LiveSettings::new(&config)
+        .management_cors
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap();
+    call(
+        &router.layer(cors),
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api/me/tokens")
+            .header(header::ORIGIN, CONSOLE_ORIGIN)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method.as_str())
+            .header(
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                format!("content-type,{}", csrf::CSRF_HEADER),
+            )
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_cross_origin_console_may_send_csrf_token() {
+    let (router, _auth, _tmp) = setup().await;
+    let resp = preflight(router, Method::POST).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let allowed = resp
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(
+        allowed.split(',').any(|h| h.trim() == csrf::CSRF_HEADER),
+        "allowed headers: {}",
+        allowed
+    );
+}
+
+#[tokio::test]
+async fn test_cross_origin_console_may_use_write_methods() {
+    let (router, _auth, _tmp) = setup().await;
+    for method in [Method::POST, Method::DELETE, Method::PATCH, Method::PUT] {
+        let resp = preflight(router.clone(), method.clone()).await;
+        let allowed = resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(
+            allowed.split(',').any(|m| m.trim() == method.as_str()),
+            "allowed methods: {}",
+            allowed
+        );
+    }
+}
@@ -0,0 +1,112 @@
+#![cfg(unix)]
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn ark(dir: &Path, args: &[&str]) -> Command {
+    let config = dir.join("ark.yaml");
+    std::fs::write(
+        &config,
+        r#"
+plugins: []
+management_server:
+  bind_address: "127.0.0.1:0"
+mcp_server:
+  bind_address: "127.0.0.1:0"
+"#,
+    )
+    .unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ark"));
+    command
+        .args(["--config-file", config.to_str().unwrap()])
+        .args(args)
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.join("ark.db"))
+        .env_remove("ARK_DAEMON")
+        .env_remove("ARK_PID_FILE");
+    command
+}
+
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+fn alive(pid: &str) -> bool {
+    Command::new("kill")
+        .args(["-0", pid])
+        .stderr(Stdio::null())
+        .status()
+        .unwrap()
+        .success()
+}
+
+#[test]
+fn test_daemon_writes_and_removes_pid_file() {
+    let dir = TempDir::new().unwrap();
+    let pid_file = dir.path().join("ark.pid");
+
+    // The starting process returns once the daemon is up
+    let output = ark(
+        dir.path(),
+        &["--daemon", "--pid-file", pid_file.to_str().unwrap()],
+    )
+    .output()
+    .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let pid = std::fs::read_to_string(&pid_file)
+        .unwrap()
+        .trim()
+        .to_string();
+    assert!(alive(&pid));
+
+    // A second instance is refused while the first one runs
+    let second = ark(
+        dir.path(),
+        &["--daemon", "--pid-file", pid_file.to_str().unwrap()],
+    )
+    .output()
+    .unwrap();
+    assert!(!second.status.success());
+    assert!(
+        String::from_utf8_lossy(&second.stderr)
+            .contains(&format!("already running with PID {pid}"))
+    );
+
+    Command::new("kill").arg(&pid).status().unwrap();
+    assert!(wait_for(|| !pid_file.exists()), "PID file not removed");
+    assert!(wait_for(|| !alive(&pid)), "daemon still running");
+}
+
+#[test]
+fn test_daemon_startup_failure_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let output = ark(dir.path(), &["--daemon", "--profile", "missing"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("missing"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdio = ark(dir.path(), &["--daemon", "--transport", "stdio"])
+        .output()
+        .unwrap();
+    assert!(!stdio.status.success());
+    assert!(String::from_utf8_lossy(&stdio.stderr).contains("network transport"));
+}
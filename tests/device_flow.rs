@@ -0,0 +1,474 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, ProviderKind},
+        csrf,
+        handlers::{oauth, session},
+        persist::Database,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const ADMIN_PASSWORD: &str = "correct horse battery";
+
+/// Builds a local-provider auth state with a temp DB and a router with `/auth`,
+/// `/api`, the OAuth endpoints and the production `check_auth`.
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("device.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", ADMIN_PASSWORD)
+        .await
+        .unwrap();
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .nest("/api", create_api_router(app_state))
+        .merge(oauth::router(auth_state.clone()))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+async fn call(router: &Router, req: Request<Body>) -> Response {
+    router.clone().oneshot(req).await.unwrap()
+}
+
+async fn json_body(resp: Response) -> (StatusCode, Value) {
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn text_body(resp: Response) -> (StatusCode, String) {
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn location(resp: &Response) -> String {
+    resp.headers()
+        .get(header::LOCATION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+fn form(uri: &str, cookie: Option<&str>, body: String) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header(header::HOST, "ark.test")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+    if let Some(cookie) = cookie {
+        builder = builder.header(header::COOKIE, cookie);
+    }
+    builder.body(Body::from(body)).unwrap()
+}
+
+fn get(uri: &str, cookie: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder().uri(uri);
+    if let Some(cookie) = cookie {
+        builder = builder.header(header::COOKIE, cookie);
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+async fn start(router: &Router, client_id: &str, scope: &str) -> (StatusCode, Value) {
+    json_body(
+        call(
+            router,
+            form(
+                "/device_authorization",
+                None,
+                format!(
+                    "client_id={}&scope={}",
+                    client_id,
+                    urlencoding::encode(scope)
+                ),
+            ),
+        )
+        .await,
+    )
+    .await
+}
+
+async fn poll(router: &Router, client_id: &str, device_code: &str) -> (StatusCode, Value) {
+    json_body(
+        call(
+            router,
+            form(
+                "/token",
+                None,
+                format!(
+                    "grant_type={}&client_id={}&device_code={}",
+                    urlencoding::encode(oauth::DEVICE_CODE_GRANT_TYPE),
+                    client_id,
+                    device_code
+                ),
+            ),
+        )
+        .await,
+    )
+    .await
+}
+
+/// Lets the next poll through without waiting out the polling interval.
+async fn skip_interval(auth: &AuthState, device_code: &str) {
+    if let Some(device) = auth.device_codes.write().await.get_mut(device_code) {
+        device.next_poll = SystemTime::now();
+    }
+}
+
+async fn login_cookie(auth: &AuthState) -> String {
+    let principal = auth
+        .authenticate_password(&ProviderKind::Local, None, "admin", ADMIN_PASSWORD)
+        .await
+        .unwrap();
+    let sid = auth
+        .put_client_session(principal, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+    format!("ark_session={}", sid)
+}
+
+fn csrf_field(cookie: &str) -> String {
+    format!(
+        "&csrf_token={}",
+        csrf::token_for_session(cookie.strip_prefix("ark_session=").unwrap())
+    )
+}
+
+#[tokio::test]
+async fn test_device_flow_end_to_end() {
+    let (router, auth_state, _tmp) = setup().await;
+
+    let (status, started) = start(&router, "ark-cli", "api mcp").await;
+    assert_eq!(status, StatusCode::OK);
+    let device_code = started["device_code"].as_str().unwrap().to_string();
+    let user_code = started["user_code"].as_str().unwrap().to_string();
+    assert_eq!(user_code.len(), 9);
+    assert_eq!(started["verification_uri"], "https://ark.test/device");
+    assert_eq!(
+        started["verification_uri_complete"],
+        format!("https://ark.test/device?user_code={}", user_code)
+    );
+    assert_eq!(started["interval"], 5);
+
+    let (status, body) = poll(&router, "ark-cli", &device_code).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "authorization_pending");
+    let (_, body) = poll(&router, "ark-cli", &device_code).await;
+    assert_eq!(body["error"], "slow_down");
+
+    // Signed out: the verification page goes through the login form and back
+    let page = format!("/device?user_code={}", user_code);
+    let resp = call(&router, get(&page, None)).await;
+    assert!(resp.status().is_redirection());
+    let login = location(&resp);
+    assert_eq!(
+        login,
+        format!(
+            "/auth/login?mode=redirect&return_to={}",
+            urlencoding::encode(&page)
+        )
+    );
+    let resp = call(&router, get(&login, None)).await;
+    assert_eq!(
+        location(&resp),
+        format!("/auth/login/local?return_to={}", urlencoding::encode(&page))
+    );
+    let resp = call(
+        &router,
+        form(
+            "/auth/login/local",
+            None,
+            format!(
+                "username=admin&password={}&return_to={}",
+                urlencoding::encode(ADMIN_PASSWORD),
+                urlencoding::encode(&page)
+            ),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    assert_eq!(location(&resp), page);
+    let cookie = resp
+        .headers()
+        .get(header::SET_COOKIE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+
+    // User codes are matched ignoring case and dashes
+    let typed = user_code.replace('-', "").to_lowercase();
+    let (status, html) = text_body(
+        call(
+            &router,
+            get(&format!("/device?user_code={}", typed), Some(&cookie)),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(html.contains("ark-cli"));
+    assert!(html.contains(&user_code));
+
+    // The decision has to carry the CSRF token rendered into the form
+    let resp = call(
+        &router,
+        form(
+            "/device",
+            Some(&cookie),
+            format!("user_code={}&action=approve", typed),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let csrf_token = html
+        .split(r#"name="csrf_token" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    let (status, _) = text_body(
+        call(
+            &router,
+            form(
+                "/device",
+                Some(&cookie),
+                format!(
+                    "user_code={}&action=approve&csrf_token={}",
+                    typed, csrf_token
+                ),
+            ),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    skip_interval(&auth_state, &device_code).await;
+    let (status, token) = poll(&router, "ark-cli", &device_code).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(token["scope"], "api mcp");
+    assert!(token["id_token"].is_null());
+    let access_token = token["access_token"].as_str().unwrap();
+
+    // The token acts as the approving user on the management API
+    let resp = call(
+        &router,
+        Request::builder()
+            .uri("/api/me/tokens")
+            .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Device codes are single use
+    let (_, body) = poll(&router, "ark-cli", &device_code).await;
+    assert_eq!(body["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn test_device_flow_denied_and_errors() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = login_cookie(&auth_state).await;
+
+    let (status, body) = start(&router, "ark-cli", "admin").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "invalid_scope");
+
+    // Denied
+    let (_, started) = start(&router, "ark-cli", "").await;
+    let device_code = started["device_code"].as_str().unwrap().to_string();
+    let user_code = started["user_code"].as_str().unwrap().to_string();
+    let (_, body) = poll(&router, "other-client", &device_code).await;
+    assert_eq!(body["error"], "invalid_grant");
+    let resp = call(
+        &router,
+        form(
+            "/device",
+            Some(&cookie),
+            format!("user_code={}&action=deny{}", user_code, csrf_field(&cookie)),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let (_, body) = poll(&router, "ark-cli", &device_code).await;
+    assert_eq!(body["error"], "access_denied");
+
+    // A decided code cannot be approved afterwards
+    let resp = call(
+        &router,
+        form(
+            "/device",
+            Some(&cookie),
+            format!(
+                "user_code={}&action=approve{}",
+                user_code,
+                csrf_field(&cookie)
+            ),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Approving requires a session
+    let (_, started) = start(&router, "ark-cli", "").await;
+    let device_code = started["device_code"].as_str().unwrap().to_string();
+    let user_code = started["user_code"].as_str().unwrap().to_string();
+    let resp = call(
+        &router,
+        form(
+            "/device",
+            None,
+            format!("user_code={}&action=approve", user_code),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // Expired
+    auth_state
+        .device_codes
+        .write()
+        .await
+        .get_mut(&device_code)
+        .unwrap()
+        .expires_at = SystemTime::now() - Duration::from_secs(1);
+    let (_, body) = poll(&router, "ark-cli", &device_code).await;
+    assert_eq!(body["error"], "expired_token");
+    let resp = call(
+        &router,
+        get(&format!("/device?user_code={}", user_code), Some(&cookie)),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_default_scope_issues_id_token() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = login_cookie(&auth_state).await;
+
+    let (_, started) = start(&router, "ark-cli", "").await;
+    let device_code = started["device_code"].as_str().unwrap().to_string();
+    let user_code = started["user_code"].as_str().unwrap().to_string();
+    call(
+        &router,
+        form(
+            "/device",
+            Some(&cookie),
+            format!(
+                "user_code={}&action=approve{}",
+                user_code,
+                csrf_field(&cookie)
+            ),
+        ),
+    )
+    .await;
+    let (status, token) = poll(&router, "ark-cli", &device_code).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(token["scope"], "openid profile email mcp");
+    assert!(token["id_token"].is_string());
+
+    // Without the "api" scope the token is limited to MCP
+    let resp = call(
+        &router,
+        Request::builder()
+            .uri("/api/me/tokens")
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", token["access_token"].as_str().unwrap()),
+            )
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_login_return_to_must_be_local() {
+    let (router, _auth, _tmp) = setup().await;
+
+    let resp = call(
+        &router,
+        get(
+            "/auth/login?mode=redirect&return_to=%2F%2Fevil.example%2F",
+            None,
+        ),
+    )
+    .await;
+    assert_eq!(location(&resp), "/auth/login/local");
+
+    for return_to in [
+        "//evil.example/",
+        "https://evil.example/",
+        "/\\evil.example",
+    ] {
+        let resp = call(
+            &router,
+            form(
+                "/auth/login/local",
+                None,
+                format!(
+                    "username=admin&password={}&return_to={}",
+                    urlencoding::encode(ADMIN_PASSWORD),
+                    urlencoding::encode(return_to)
+                ),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        assert_eq!(location(&resp), "/", "{}", return_to);
+    }
+}
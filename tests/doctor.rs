@@ -0,0 +1,139 @@
+use ark::{
+    config::{
+        ArkConfig, ConfigError,
+        models::{AuthConfig, IdentityProviderConfig, TlsConfig},
+    },
+    doctor::{Finding, Status, diagnose},
+    server::{
+        persist::Database,
+        signing::{KeyAlgorithm, generate_key},
+    },
+};
+use serde_json::json;
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const HASH_PLUGIN: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/testdata/hash_plugin.wasm"
+);
+
+fn config(plugins: serde_json::Value, providers: Vec<IdentityProviderConfig>) -> ArkConfig {
+    let mut config: ArkConfig = serde_json::from_value(json!({ "plugins": plugins })).unwrap();
+    config.auth = Some(AuthConfig {
+        enabled: true,
+        provider: providers.first().map(|p| p.name.clone()),
+        providers,
+        session: None,
+        ..Default::default()
+    });
+    config
+}
+
+fn finding<'a>(findings: &'a [Finding], check: &str) -> &'a Finding {
+    findings
+        .iter()
+        .find(|f| f.check == check)
+        .unwrap_or_else(|| panic!("no {check} finding in {findings:?}"))
+}
+
+#[tokio::test]
+async fn test_doctor_healthy_environment() {
+    let idp = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "issuer": idp.uri() })))
+        .mount(&idp)
+        .await;
+    let dir = TempDir::new().unwrap();
+    let db = dir.path().join("ark.db");
+    Database::with_path(db.clone()).unwrap();
+
+    let config = config(
+        json!([{ "name": "hash", "url": format!("file://{HASH_PLUGIN}") }]),
+        vec![IdentityProviderConfig {
+            name: "corp".to_string(),
+            authority: idp.uri(),
+            discovery: true,
+            ..Default::default()
+        }],
+    );
+    let findings = diagnose(Ok(config), Some(db)).await;
+
+    assert!(
+        findings.iter().all(|f| f.status == Status::Ok),
+        "{findings:?}"
+    );
+    assert!(
+        finding(&findings, "migrations")
+            .message
+            .contains("none pending")
+    );
+    finding(&findings, "auth provider corp");
+    finding(&findings, "plugin hash");
+}
+
+#[tokio::test]
+async fn test_doctor_reports_problems_with_hints() {
+    let idp = MockServer::start().await;
+    let dir = TempDir::new().unwrap();
+
+    // A certificate issued for another key
+    let (key, _) = generate_key(KeyAlgorithm::Ec, None).unwrap();
+    let (_, cert) = generate_key(KeyAlgorithm::Ec, Some(("other", 30))).unwrap();
+    let key_path = dir.path().join("server.key");
+    let cert_path = dir.path().join("server.pem");
+    std::fs::write(&key_path, key).unwrap();
+    std::fs::write(&cert_path, cert.unwrap()).unwrap();
+
+    let mut config = config(
+        json!([
+            { "name": "missing", "url": "file:///nonexistent/plugin.wasm" },
+            { "name": "plain", "url": "http://plugins.example.com/plugin.wasm" },
+        ]),
+        vec![IdentityProviderConfig {
+            name: "corp".to_string(),
+            authority: idp.uri(),
+            discovery: true,
+            ..Default::default()
+        }],
+    );
+    config.tls = Some(TlsConfig {
+        key: Some(key_path.display().to_string()),
+        cert: Some(cert_path.display().to_string()),
+        ..Default::default()
+    });
+    let findings = diagnose(Ok(config), Some(dir.path().join("ark.db"))).await;
+
+    // Not created yet: the migrations run at the first start
+    assert_eq!(finding(&findings, "database").status, Status::Ok);
+    assert_eq!(finding(&findings, "migrations").status, Status::Ok);
+    for check in ["tls", "auth provider corp", "plugin missing", "plain"] {
+        let f = findings
+            .iter()
+            .find(|f| f.check.ends_with(check))
+            .unwrap_or_else(|| panic!("no {check} finding in {findings:?}"));
+        assert_eq!(f.status, Status::Failed, "{f:?}");
+        assert!(f.hint.is_some(), "{f:?}");
+    }
+    assert!(
+        finding(&findings, "auth provider corp")
+            .message
+            .contains("404")
+    );
+}
+
+#[tokio::test]
+async fn test_doctor_stops_at_configuration_errors() {
+    let dir = TempDir::new().unwrap();
+    let error = ConfigError::Parse("ark.yaml".into(), "expected a map".to_string());
+
+    let findings = diagnose(Err(error), Some(dir.path().join("ark.db"))).await;
+
+    let config = finding(&findings, "configuration");
+    assert_eq!(config.status, Status::Failed);
+    assert!(config.message.contains("expected a map"));
+    finding(&findings, "database");
+    assert!(!findings.iter().any(|f| f.check.starts_with("plugin")));
+}
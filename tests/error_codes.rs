@@ -0,0 +1,246 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use ark::{
+    config::{ConfigError, plugins::ArkPlugin},
+    errors::{ErrorCode, StartupError},
+    plugins::{ToolSet, bundle::PluginBundle},
+    server::{
+        auth::{Principal, ProviderKind},
+        roles::Role,
+        service::create_api_router,
+    },
+    state::{ArkState, DynExecFuture},
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use rmcp::{ErrorData, model::Tool};
+use serde_json::{Map, Value, json};
+use tower::ServiceExt;
+
+fn principal(subject: &str) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+/// Registers a plugin with one tool that fails with `error`.
+async fn register_failing(state: &ArkState, plugin: &str, owner: &str, error: ErrorData) {
+    let exec: ark::state::ToolExecFn = Arc::new(move |_args: Value| {
+        let error = error.clone();
+        Box::pin(async move { Err(error) }) as DynExecFuture
+    });
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: plugin.to_string(),
+                owner: Some(owner.to_string()),
+                ..Default::default()
+            },
+            ToolSet {
+                name: plugin.to_string(),
+                tools: vec![Tool {
+                    name: Cow::Owned(format!("{plugin}_tool")),
+                    title: None,
+                    description: None,
+                    input_schema: Arc::new(Map::new()),
+                    output_schema: None,
+                    annotations: None,
+                    icons: None,
+                }],
+            },
+            vec![(format!("{plugin}_tool"), exec)],
+        )
+        .await
+        .unwrap();
+}
+
+async fn call(router: &Router, method: Method, uri: &str) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{}"))
+        .unwrap();
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn test_api_errors_carry_codes() {
+    let state = Arc::new(ArkState::default());
+    let owner = principal("owner");
+    register_failing(
+        &state,
+        "trapping",
+        &owner.global_id(),
+        ErrorData::internal_error("unreachable executed", ErrorCode::WasmTrap.data()),
+    )
+    .await;
+    register_failing(
+        &state,
+        "slow",
+        &owner.global_id(),
+        ErrorData::internal_error("timed out", ErrorCode::ToolTimeout.data()),
+    )
+    .await;
+    let owner_router = create_api_router(state.clone()).layer(Extension(owner));
+    let other_router = create_api_router(state).layer(Extension(principal("other")));
+
+    for (router, method, uri, status, code) in [
+        (
+            &owner_router,
+            Method::GET,
+            "/plugins/missing",
+            StatusCode::NOT_FOUND,
+            "PLUGIN_NOT_FOUND",
+        ),
+        (
+            &owner_router,
+            Method::POST,
+            "/plugins/trapping/tools/missing",
+            StatusCode::NOT_FOUND,
+            "TOOL_NOT_FOUND",
+        ),
+        (
+            &owner_router,
+            Method::POST,
+            "/plugins/trapping/tools/trapping_tool",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "WASM_TRAP",
+        ),
+        (
+            &owner_router,
+            Method::POST,
+            "/plugins/slow/tools/slow_tool",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "TOOL_TIMEOUT",
+        ),
+        (
+            &other_router,
+            Method::POST,
+            "/plugins/trapping/tools/trapping_tool",
+            StatusCode::FORBIDDEN,
+            "OWNERSHIP_DENIED",
+        ),
+        (
+            &other_router,
+            Method::DELETE,
+            "/plugins/trapping",
+            StatusCode::FORBIDDEN,
+            "OWNERSHIP_DENIED",
+        ),
+        (
+            &owner_router,
+            Method::DELETE,
+            "/plugins/missing",
+            StatusCode::NOT_FOUND,
+            "PLUGIN_NOT_FOUND",
+        ),
+    ] {
+        let (actual, body) = call(router, method.clone(), uri).await;
+        assert_eq!(actual, status, "{method} {uri}: {body}");
+        assert_eq!(body["code"], code, "{method} {uri}");
+        assert_eq!(body["is_error"], true);
+    }
+}
+
+#[tokio::test]
+async fn test_tool_errors_are_classified() {
+    let state = ArkState::default();
+    register_failing(
+        &state,
+        "invalid",
+        "*/*/*",
+        ErrorData::invalid_params("missing argument", None),
+    )
+    .await;
+
+    let err = state
+        .plugin_registry
+        .call("invalid_tool", &json!({}))
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::InvalidRequest);
+    // The message is unchanged
+    assert!(err.to_string().starts_with("Plugin handler error"), "{err}");
+
+    let err = state
+        .plugin_registry
+        .call("missing", &json!({}))
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::ToolNotFound);
+}
+
+#[test]
+fn test_tampered_bundle_is_signature_invalid() {
+    let plugin = ArkPlugin {
+        name: "hash".to_string(),
+        url: Some("file:///plugins/hash.wasm".parse().unwrap()),
+        ..Default::default()
+    };
+    let mut bundle = PluginBundle::new(&plugin, b"\0asm module", None, None).unwrap();
+    bundle.sha256 = "0".repeat(64);
+
+    let err = bundle.unpack().unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::SignatureInvalid);
+}
+
+#[test]
+fn test_startup_errors_map_to_exit_codes() {
+    for (err, code, exit) in [
+        (
+            anyhow::Error::from(StartupError::SigningMisconfigured("missing key".into())),
+            ErrorCode::SigningMisconfigured,
+            3,
+        ),
+        (
+            anyhow::Error::from(StartupError::KeyCertMismatch("mismatch".into()))
+                .context("Failed to build auth state"),
+            ErrorCode::KeyCertMismatch,
+            4,
+        ),
+        (
+            anyhow::Error::from(ConfigError::Parse("ark.yaml".into(), "bad".into())),
+            ErrorCode::ConfigInvalid,
+            2,
+        ),
+        (
+            anyhow::anyhow!("Address already in use"),
+            ErrorCode::Internal,
+            1,
+        ),
+    ] {
+        assert_eq!(ErrorCode::of(&err), code, "{err:#}");
+        assert_eq!(code.exit_code(), exit);
+    }
+}
+
+#[test]
+fn test_codes_serialize_as_their_names() {
+    for code in ErrorCode::ALL {
+        assert_eq!(json!(code), code.as_str());
+        assert_eq!(ErrorCode::parse(code.as_str()), Some(code));
+    }
+}
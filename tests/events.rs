@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::config::plugins::ArkPlugin;
+use ark::plugins::ToolSet;
+use ark::server::roles::Role;
+use ark::{
+    server::{
+        auth::{Principal, ProviderKind},
+        events::ServerEvent,
+        service::create_api_router,
+    },
+    state::{ApplicationState, ArkState, DynExecFuture},
+};
+use axum::{
+    Extension, Router,
+    body::{Body, BodyDataStream},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use futures::StreamExt;
+use rmcp::model::Tool;
+use serde_json::{Map, Value, json};
+use tower::ServiceExt;
+
+fn user(subject: &str, roles: Vec<Role>) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        is_admin: roles.contains(&Role::Admin),
+        roles,
+    }
+}
+
+async fn register(state: &ArkState, plugin: &str, owner: &str, tool: &'static str) {
+    let exec: ark::state::ToolExecFn = Arc::new(move |_args: Value| {
+        Box::pin(async move { Ok(json!({"content": [{"type": "text", "text": tool}]})) })
+            as DynExecFuture
+    });
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: plugin.to_string(),
+                owner: Some(owner.to_string()),
+                ..Default::default()
+            },
+            ToolSet {
+                name: plugin.to_string(),
+                tools: vec![Tool {
+                    name: Cow::Borrowed(tool),
+                    title: None,
+                    description: None,
+                    input_schema: Arc::new(Map::new()),
+                    output_schema: None,
+                    annotations: None,
+                    icons: None,
+                }],
+            },
+            vec![(tool.to_string(), exec)],
+        )
+        .await
+        .unwrap();
+}
+
+async fn send(router: &Router, principal: &Principal, method: Method, uri: &str) -> StatusCode {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{}"))
+        .unwrap();
+    router
+        .clone()
+        .layer(Extension(principal.clone()))
+        .oneshot(req)
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Reads SSE messages until `count` have arrived, as (event name, data).
+async fn read_events(body: &mut BodyDataStream, count: usize) -> Vec<(String, Value)> {
+    let mut buffer = String::new();
+    let mut events = Vec::new();
+    while events.len() < count {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+            .await
+            .expect("timed out waiting for events")
+            .expect("event stream ended")
+            .unwrap();
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        while let Some(end) = buffer.find("\n\n") {
+            let message: String = buffer.drain(..end + 2).collect();
+            let mut name = String::new();
+            let mut data = Value::Null;
+            for line in message.lines() {
+                if let Some(value) = line.strip_prefix("event: ") {
+                    name = value.to_string();
+                } else if let Some(value) = line.strip_prefix("data: ") {
+                    data = serde_json::from_str(value).unwrap();
+                }
+            }
+            if !name.is_empty() {
+                events.push((name, data));
+            }
+        }
+    }
+    events
+}
+
+#[tokio::test]
+async fn test_event_stream() {
+    let state = Arc::new(ArkState::default());
+    let router = create_api_router(state.clone());
+    let alice = user("alice", vec![Role::User]);
+    let bob = user("bob", vec![Role::User]);
+
+    let req = Request::builder()
+        .uri("/events")
+        .body(Body::empty())
+        .unwrap();
+    let resp = router
+        .clone()
+        .layer(Extension(bob.clone()))
+        .oneshot(req)
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/event-stream"
+    );
+    let mut body = resp.into_body().into_data_stream();
+
+    // Alice's plugin and her call to it are not visible to Bob
+    register(&state, "private", &alice.global_id(), "secret").await;
+    assert_eq!(
+        send(
+            &router,
+            &alice,
+            Method::POST,
+            "/plugins/private/tools/secret"
+        )
+        .await,
+        StatusCode::OK
+    );
+    register(&state, "shared", "*/*/*", "echo").await;
+    assert_eq!(
+        send(&router, &bob, Method::POST, "/plugins/shared/tools/echo").await,
+        StatusCode::OK
+    );
+    state.set_state(ApplicationState::Ready);
+    assert!(state.unregister_plugin("shared").await.unwrap());
+
+    let events = read_events(&mut body, 4).await;
+    let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "plugin_registered",
+            "tool_executed",
+            "state_changed",
+            "plugin_unregistered"
+        ]
+    );
+    assert_eq!(
+        events[0].1,
+        json!({"type": "plugin_registered", "plugin": "shared", "owner": "*/*/*", "tools": ["echo"]})
+    );
+    assert_eq!(events[1].1["tool"], "echo");
+    assert_eq!(events[1].1["principal"], bob.global_id());
+    assert_eq!(events[1].1["is_error"], false);
+    assert_eq!(events[2].1["state"], "Ready");
+    assert_eq!(events[3].1["plugin"], "shared");
+}
+
+#[tokio::test]
+async fn test_event_stream_requires_console_access() {
+    let state = Arc::new(ArkState::default());
+    let router = create_api_router(state);
+    let nobody = user("nobody", vec![]);
+    assert_eq!(
+        send(&router, &nobody, Method::GET, "/events").await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[test]
+fn test_event_visibility() {
+    let admin = user("admin", vec![Role::Admin]);
+    let alice = user("alice", vec![Role::User]);
+    let bob = user("bob", vec![Role::User]);
+
+    let login = ServerEvent::Auth {
+        event: "login".to_string(),
+        provider: Some("oidc".to_string()),
+        subject: Some(alice.global_id()),
+    };
+    let call = ServerEvent::ToolExecuted {
+        plugin: "private".to_string(),
+        tool: "secret".to_string(),
+        owner: bob.global_id(),
+        principal: Some(alice.global_id()),
+        is_error: false,
+        latency_ms: 1.0,
+    };
+    for event in [&login, &call] {
+        assert!(event.is_visible_to(None));
+        assert!(event.is_visible_to(Some(&admin)));
+        assert!(event.is_visible_to(Some(&alice)));
+    }
+    assert!(!login.is_visible_to(Some(&bob)));
+    assert!(call.is_visible_to(Some(&bob)));
+    assert!(
+        ServerEvent::StateChanged {
+            state: "Ready".to_string()
+        }
+        .is_visible_to(Some(&bob))
+    );
+}
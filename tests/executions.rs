@@ -0,0 +1,279 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use ark::{
+    config::plugins::ArkPlugin,
+    errors::ErrorCode,
+    plugins::ToolSet,
+    server::{
+        auth::{Principal, ProviderKind},
+        persist::{Database, ToolExecutionQuery, ToolExecutionRecord},
+        roles::Role,
+        service::create_api_router,
+        usage::{ToolCall, args_hash, record_tool_call},
+    },
+    state::{ArkState, DynExecFuture},
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use rmcp::{ErrorData, model::Tool};
+use serde_json::{Map, Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn user(subject: &str, is_admin: bool) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: if is_admin {
+            vec![Role::Admin]
+        } else {
+            vec![Role::User]
+        },
+        is_admin,
+    }
+}
+
+fn tool(name: &str) -> Tool {
+    Tool {
+        name: Cow::Owned(name.to_string()),
+        title: None,
+        description: None,
+        input_schema: Arc::new(Map::new()),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+/// Builds the state with a shared plugin `p` whose `echo` tool succeeds and
+/// `trap` tool fails, backed by a temp DB.
+async fn setup() -> (Arc<ArkState>, Database, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Database::with_path(temp_dir.path().join("executions.db")).unwrap();
+    let state = Arc::new(ArkState::default());
+    state.set_database(db.clone());
+
+    let echo: ark::state::ToolExecFn =
+        Arc::new(|args: Value| Box::pin(async move { Ok(args) }) as DynExecFuture);
+    let trap: ark::state::ToolExecFn = Arc::new(|_args: Value| {
+        Box::pin(async move {
+            Err(ErrorData::internal_error(
+                "unreachable executed",
+                ErrorCode::WasmTrap.data(),
+            ))
+        }) as DynExecFuture
+    });
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: "p".to_string(),
+                owner: Some("*/*/*".to_string()),
+                ..Default::default()
+            },
+            ToolSet {
+                name: "p".to_string(),
+                tools: vec![tool("echo"), tool("trap")],
+            },
+            vec![("echo".to_string(), echo), ("trap".to_string(), trap)],
+        )
+        .await
+        .unwrap();
+    (state, db, temp_dir)
+}
+
+async fn send(
+    router: &Router,
+    principal: Option<&Principal>,
+    method: Method,
+    uri: &str,
+    body: Value,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let mut router = router.clone();
+    if let Some(principal) = principal {
+        router = router.layer(Extension(principal.clone()));
+    }
+    let resp = router.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn list(router: &Router, principal: &Principal, query: &str) -> (StatusCode, Value) {
+    send(
+        router,
+        Some(principal),
+        Method::GET,
+        &format!("/executions{query}"),
+        Value::Null,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_tool_calls_are_recorded() {
+    let (state, _db, _tmp) = setup().await;
+    let router = create_api_router(state);
+    let alice = user("alice", false);
+
+    for (tool, args) in [
+        ("echo", json!({"text": "hi"})),
+        ("trap", json!({})),
+        ("echo", json!({"text": "hi"})),
+    ] {
+        send(
+            &router,
+            Some(&alice),
+            Method::POST,
+            &format!("/plugins/p/tools/{tool}"),
+            args,
+        )
+        .await;
+    }
+
+    let (status, executions) = list(&router, &alice, "").await;
+    assert_eq!(status, StatusCode::OK);
+    let executions = executions.as_array().unwrap();
+    assert_eq!(executions.len(), 3);
+    // Newest first
+    let latest = &executions[0];
+    assert_eq!(latest["principal"], alice.global_id());
+    assert_eq!(latest["plugin"], "p");
+    assert_eq!(latest["tool"], "echo");
+    assert_eq!(latest["status"], "ok");
+    assert_eq!(latest["error_code"], Value::Null);
+    assert!(latest["duration_ms"].is_number());
+    assert!(latest["timestamp"].is_string());
+    // Identical arguments hash alike, without storing them
+    assert_eq!(latest["args_hash"], args_hash(&json!({"text": "hi"})));
+    assert_eq!(latest["args_hash"], executions[2]["args_hash"]);
+    assert_eq!(latest["args_hash"].as_str().unwrap().len(), 16);
+    assert!(latest["id"].as_i64() > executions[1]["id"].as_i64());
+
+    let (_, failed) = list(&router, &alice, "?status=error").await;
+    assert_eq!(failed.as_array().unwrap().len(), 1);
+    assert_eq!(failed[0]["tool"], "trap");
+    assert_eq!(failed[0]["status"], "error");
+    assert_eq!(failed[0]["error_code"], "WASM_TRAP");
+
+    let (_, echoes) = list(&router, &alice, "?plugin=p&tool=echo&limit=1").await;
+    assert_eq!(echoes.as_array().unwrap().len(), 1);
+    assert_eq!(echoes[0]["id"], latest["id"]);
+
+    // Paging continues below the last id seen
+    let (_, older) = list(&router, &alice, &format!("?before_id={}", latest["id"])).await;
+    assert_eq!(older.as_array().unwrap().len(), 2);
+    assert_eq!(older[0]["tool"], "trap");
+}
+
+#[tokio::test]
+async fn test_users_only_see_their_own_executions() {
+    let (state, _db, _tmp) = setup().await;
+    let router = create_api_router(state.clone());
+    let alice = user("alice", false);
+    let bob = user("bob", false);
+    let admin = user("admin", true);
+
+    for principal in [&alice, &bob, &bob] {
+        let global_id = principal.global_id();
+        let call = ToolCall {
+            plugin_id: "p",
+            tool: "echo",
+            owner: None,
+            principal: Some(&global_id),
+            args: &json!({}),
+            error: None,
+            latency_ms: 1.0,
+            bytes_returned: 0,
+        };
+        record_tool_call(&state, call).await;
+    }
+
+    let (status, own) = list(&router, &alice, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(own.as_array().unwrap().len(), 1);
+    assert_eq!(own[0]["principal"], alice.global_id());
+
+    let (status, body) = list(&router, &alice, &format!("?principal={}", bob.global_id())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "PERMISSION_DENIED");
+
+    let (_, all) = list(&router, &admin, "").await;
+    assert_eq!(all.as_array().unwrap().len(), 3);
+    let (_, bobs) = list(&router, &admin, &format!("?principal={}", bob.global_id())).await;
+    assert_eq!(bobs.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_history_keeps_the_latest_executions() {
+    let (_state, db, _tmp) = setup().await;
+
+    let mut ids = Vec::new();
+    for n in 0..5 {
+        let record = ToolExecutionRecord {
+            id: 0,
+            timestamp_utc: chrono::Utc::now(),
+            principal: None,
+            plugin_id: "p".to_string(),
+            tool: format!("tool{n}"),
+            duration_ms: 1.0,
+            error_code: None,
+            args_hash: args_hash(&json!({})),
+            request_id: None,
+        };
+        ids.push(db.save_tool_execution_async(record, 3).await.unwrap());
+    }
+
+    let kept = db
+        .list_tool_executions_async(ToolExecutionQuery {
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let tools: Vec<&str> = kept.iter().map(|r| r.tool.as_str()).collect();
+    assert_eq!(tools, ["tool4", "tool3", "tool2"]);
+    assert_eq!(kept[0].id, ids[4]);
+}
+
+#[tokio::test]
+async fn test_executions_errors() {
+    let (state, _db, _tmp) = setup().await;
+    let router = create_api_router(state);
+    let alice = user("alice", false);
+
+    let (status, _) = send(&router, None, Method::GET, "/executions", Value::Null).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    for query in ["?status=failed", "?limit=0", "?since=yesterday"] {
+        let (status, body) = list(&router, &alice, query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert_eq!(body["code"], "INVALID_REQUEST");
+    }
+
+    let router = create_api_router(Arc::new(ArkState::default()));
+    let (status, body) = list(&router, &alice, "").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "DATABASE_REQUIRED");
+}
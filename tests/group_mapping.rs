@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use ark::{
+    config::models::{AuthConfig, Groups, IdentityProviderConfig, RoleConfig},
+    server::{
+        auth::{AuthState, IdTokenClaims, Principal, ProviderKind},
+        roles::{self, Role},
+    },
+    state::ArkState,
+};
+use serde_json::{Value, json};
+
+/// Builds ID token claims the way `validate_id_token` does: typed fields plus the raw payload.
+fn claims(extra: Value) -> IdTokenClaims {
+    let mut payload = json!({
+        "iss": "https://idp.test",
+        "sub": "user-1",
+        "aud": "ark",
+        "exp": 0,
+        "iat": 0,
+    });
+    payload
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    let mut claims: IdTokenClaims = serde_json::from_value(payload.clone()).unwrap();
+    claims.raw = payload.as_object().unwrap().clone();
+    claims
+}
+
+fn groups_config(claim: Option<&str>, path: Option<&str>) -> Groups {
+    Groups {
+        claim: claim.map(|s| s.to_string()),
+        path: path.map(|s| s.to_string()),
+        ..Default::default()
+    }
+}
+
+fn principal(groups: &[&str]) -> Principal {
+    Principal {
+        subject: "user-1".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "https://idp.test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: groups.iter().map(|g| g.to_string()).collect(),
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+#[test]
+fn test_default_groups_claim() {
+    let c = claims(json!({"groups": ["a", "b"]}));
+    assert_eq!(roles::groups_from_claims(&c, None), vec!["a", "b"]);
+    assert_eq!(
+        roles::groups_from_claims(&c, Some(&Groups::default())),
+        vec!["a", "b"]
+    );
+
+    // Claims without the raw payload fall back to the typed field
+    let mut c = claims(json!({"groups": ["x"]}));
+    c.raw.clear();
+    assert_eq!(roles::groups_from_claims(&c, None), vec!["x"]);
+
+    // Repeated groups are listed once, wherever they appear
+    let c = claims(json!({"groups": ["a", "b", "a", "c", "b"]}));
+    assert_eq!(roles::groups_from_claims(&c, None), vec!["a", "b", "c"]);
+}
+
+#[test]
+fn test_custom_claim_and_path() {
+    // Entra app roles
+    let c = claims(json!({"roles": ["Ark.Admin"], "groups": ["ignored"]}));
+    assert_eq!(
+        roles::groups_from_claims(&c, Some(&groups_config(Some("roles"), None))),
+        vec!["Ark.Admin"]
+    );
+
+    // Keycloak realm roles
+    let c = claims(json!({"realm_access": {"roles": ["ops", "offline_access"]}}));
+    assert_eq!(
+        roles::groups_from_claims(
+            &c,
+            Some(&groups_config(Some("realm_access"), Some("$.roles")))
+        ),
+        vec!["ops", "offline_access"]
+    );
+
+    // Namespaced claim carrying objects
+    let c = claims(json!({
+        "https://ark.example.com/groups": [{"id": 7, "name": "eng"}, {"id": 9, "name": "sre"}]
+    }));
+    let cfg = groups_config(Some("https://ark.example.com/groups"), Some("$[*].name"));
+    assert_eq!(
+        roles::groups_from_claims(&c, Some(&cfg)),
+        vec!["eng", "sre"]
+    );
+    let cfg = groups_config(Some("https://ark.example.com/groups"), Some("[*]['id']"));
+    assert_eq!(roles::groups_from_claims(&c, Some(&cfg)), vec!["7", "9"]);
+
+    // Missing claim yields no groups
+    assert!(roles::groups_from_claims(&c, Some(&groups_config(Some("teams"), None))).is_empty());
+}
+
+#[test]
+fn test_non_string_groups_claim_does_not_fail_decoding() {
+    let c = claims(json!({"groups": [{"name": "eng"}]}));
+    assert!(c.groups.is_none());
+    let cfg = groups_config(None, Some("$[0].name"));
+    assert_eq!(roles::groups_from_claims(&c, Some(&cfg)), vec!["eng"]);
+}
+
+#[test]
+fn test_json_path_validation() {
+    for ok in ["$", "$.a.b", "roles", "$['a.b'][0]", "$.*", "$[*].name"] {
+        assert!(roles::validate_groups_path(ok).is_ok(), "{}", ok);
+    }
+    for bad in ["$.", "$[", "$[x]", "$..a", "$a"] {
+        assert!(roles::validate_groups_path(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn test_role_mappings() {
+    let cfg = Groups {
+        role_mappings: BTreeMap::from([
+            ("eng".to_string(), vec!["operator".to_string()]),
+            ("root".to_string(), vec!["Admin".to_string()]),
+        ]),
+        ..Default::default()
+    };
+
+    let mut p = principal(&["eng", "other"]);
+    roles::apply_role_mappings(&mut p, &cfg);
+    assert_eq!(p.roles, vec![Role::User, Role::from("operator")]);
+    assert!(!p.is_admin);
+
+    let mut p = principal(&["root"]);
+    roles::apply_role_mappings(&mut p, &cfg);
+    assert!(p.is_admin);
+    assert!(p.roles.contains(&Role::Admin));
+}
+
+async fn auth_state_with(groups: Groups, roles: Vec<RoleConfig>) -> anyhow::Result<AuthState> {
+    let cfg = AuthConfig {
+        enabled: true,
+        provider: Some("corp".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "corp".to_string(),
+            client_id: "ark".to_string(),
+            authority: "https://idp.test".to_string(),
+            discovery: false,
+            groups: Some(groups),
+            ..Default::default()
+        }],
+        session: None,
+        roles,
+        ..Default::default()
+    };
+    AuthState::new_with_state(&Some(cfg), Arc::new(ArkState::default()), None).await
+}
+
+#[tokio::test]
+async fn test_invalid_group_settings_rejected() {
+    let bad_path = groups_config(Some("realm_access"), Some("$[roles"));
+    assert!(auth_state_with(bad_path, vec![]).await.is_err());
+
+    let unknown_role = Groups {
+        role_mappings: BTreeMap::from([("eng".to_string(), vec!["operator".to_string()])]),
+        ..Default::default()
+    };
+    assert!(auth_state_with(unknown_role.clone(), vec![]).await.is_err());
+
+    let defined = vec![RoleConfig {
+        name: "operator".to_string(),
+        permissions: vec!["tool.execute".to_string()],
+    }];
+    assert!(auth_state_with(unknown_role, defined).await.is_ok());
+}
+
+#[test]
+fn test_groups_config_from_yaml() {
+    let groups: Groups = serde_yaml_ng::from_str(
+        r#"
+claim: realm_access
+path: $.roles
+role_mappings:
+  ark-admins: [Admin]
+  ark-ops: [operator]
+"#,
+    )
+    .unwrap();
+    assert_eq!(groups.claim.as_deref(), Some("realm_access"));
+    assert_eq!(groups.role_mappings["ark-ops"], vec!["operator"]);
+    assert!(groups.admin.is_none());
+}
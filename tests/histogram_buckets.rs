@@ -0,0 +1,73 @@
+use ark::config::models::{HistogramBucketsConfig, ManagementEndpointConfig};
+use ark::metrics::latency_buckets;
+
+#[test]
+fn test_histogram_buckets_config() {
+    let yaml = r#"
+histogram_buckets:
+  tool: [100, 0.5, 2500, 0.5]
+  mcp: []
+"#;
+    let cfg: ManagementEndpointConfig = serde_yaml_ng::from_str(yaml).unwrap();
+    let buckets = cfg.histogram_buckets.unwrap();
+    assert!(buckets.api.is_none());
+
+    // Sorted and deduplicated; empty lists are ignored
+    assert_eq!(
+        latency_buckets(&buckets),
+        vec![("ark_tool_latency_ms", vec![0.5, 100.0, 2500.0])]
+    );
+
+    let non_finite = HistogramBucketsConfig {
+        api: Some(vec![1.0, f64::NAN]),
+        ..Default::default()
+    };
+    assert!(latency_buckets(&non_finite).is_empty());
+}
+
+// The Prometheus recorder is process-wide, so it is only installed here.
+#[cfg(feature = "prometheus")]
+#[tokio::test]
+async fn test_prometheus_uses_configured_buckets() {
+    use http_body_util::BodyExt;
+
+    let cfg = ManagementEndpointConfig {
+        histogram_buckets: Some(HistogramBucketsConfig {
+            tool: Some(vec![0.5, 5.0, 5000.0]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    ark::metrics::init(Some(&cfg));
+    ark::metrics::record_tool_metrics("clock", "now", None, 0.2);
+    ark::metrics::record_tool_metrics("clock", "now", None, 3000.0);
+    ark::metrics::record_mcp_call("call_tool", 4.0);
+
+    let body = ark::metrics::handler::make_metrics_response()
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    let line = |prefix: &str| {
+        text.lines()
+            .find(|l| l.starts_with(prefix))
+            .unwrap_or_else(|| panic!("no {} in\n{}", prefix, text))
+            .to_string()
+    };
+    assert!(
+        line("ark_tool_latency_ms_bucket{plugin=\"clock\",tool=\"now\",le=\"0.5\"}")
+            .ends_with(" 1")
+    );
+    assert!(
+        line("ark_tool_latency_ms_bucket{plugin=\"clock\",tool=\"now\",le=\"5\"}").ends_with(" 1")
+    );
+    assert!(
+        line("ark_tool_latency_ms_bucket{plugin=\"clock\",tool=\"now\",le=\"5000\"}")
+            .ends_with(" 2")
+    );
+    // Latencies without buckets stay summaries
+    assert!(line("ark_mcp_latency_ms{transport=\"call_tool\",quantile=").contains("quantile"));
+    assert!(!text.contains("ark_mcp_latency_ms_bucket"));
+}
@@ -0,0 +1,92 @@
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use rcgen::CertifiedKey;
+use reqwest::Version;
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[tokio::test]
+async fn test_tls_listener_negotiates_http2_and_http1() {
+    let dir = TempDir::new().unwrap();
+    let CertifiedKey { cert, signing_key } =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    std::fs::write(dir.path().join("server.pem"), cert.pem()).unwrap();
+    std::fs::write(dir.path().join("server.key"), signing_key.serialize_pem()).unwrap();
+    let port = free_port();
+    std::fs::write(
+        dir.path().join("ark.yaml"),
+        format!(
+            r#"
+plugins: []
+tls:
+  key: server.key
+  cert: server.pem
+management_server:
+  bind_address: "127.0.0.1:{port}"
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:{}"
+"#,
+            free_port()
+        ),
+    )
+    .unwrap();
+    let _server = Server(
+        Command::new(env!("CARGO_BIN_EXE_ark"))
+            .current_dir(dir.path())
+            .args(["--config-file", "ark.yaml"])
+            .env("ARK_TRANSPORT", "streamable-http")
+            .env("ARK_DB_PATH", dir.path().join("ark.db"))
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_err()
+    {
+        assert!(Instant::now() < deadline, "server did not start");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let url = format!("https://localhost:{port}/livez");
+    let client = |http1_only: bool| {
+        let builder = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(cert.pem().as_bytes()).unwrap());
+        let builder = if http1_only {
+            builder.http1_only()
+        } else {
+            builder
+        };
+        builder.build().unwrap()
+    };
+
+    let response = client(false).get(&url).send().await.unwrap();
+    assert_eq!(response.version(), Version::HTTP_2);
+    assert_eq!(response.status(), 200);
+
+    let response = client(true).get(&url).send().await.unwrap();
+    assert_eq!(response.version(), Version::HTTP_11);
+    assert_eq!(response.status(), 200);
+}
@@ -43,6 +43,7 @@ async fn integration_token_and_jwks() {
         picture: None,
         provider: "local".to_string(),
         provider_kind: ark::server::auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         groups: vec![],
@@ -0,0 +1,131 @@
+use std::net::{IpAddr, TcpListener};
+use std::sync::Arc;
+
+use ark::config::{
+    ArkConfig, McpTransport,
+    models::{IpFilterConfig, ManagementEndpointConfig, McpEndpointConfig, RouteIpFilterConfig},
+};
+use ark::server::{ip_filter::IpFilter, service};
+use ark::state::{ApplicationState, ArkState};
+
+fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    port
+}
+
+fn strings(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+fn ip(s: &str) -> Option<IpAddr> {
+    Some(s.parse().unwrap())
+}
+
+#[test]
+fn test_allow_and_deny_lists() {
+    let filter = IpFilter::from_config(&IpFilterConfig {
+        allow: strings(&["10.0.0.0/8", "192.168.1.0/24", "::1"]),
+        deny: strings(&["10.66.0.0/16"]),
+        routes: vec![RouteIpFilterConfig {
+            prefix: "/api/admin/".to_string(),
+            allow: strings(&["10.1.0.0/16"]),
+            deny: vec![],
+        }],
+    })
+    .unwrap();
+
+    assert!(filter.allows(ip("10.2.3.4"), "/api/v1/plugins"));
+    assert!(filter.allows(ip("::ffff:192.168.1.7"), "/livez"));
+    assert!(filter.allows(ip("::1"), "/livez"));
+    assert!(!filter.allows(ip("172.16.0.1"), "/livez"));
+    assert!(!filter.allows(None, "/livez"));
+
+    // Deny entries win over the allow list
+    assert!(!filter.allows(ip("10.66.1.1"), "/api/v1/plugins"));
+
+    // Route rules add to the listener's, for both API prefixes
+    assert!(filter.allows(ip("10.1.2.3"), "/api/v1/admin/users"));
+    assert!(!filter.allows(ip("10.2.3.4"), "/api/v1/admin/users"));
+    assert!(!filter.allows(ip("10.2.3.4"), "/api/admin"));
+    assert!(filter.allows(ip("10.2.3.4"), "/api/administrators"));
+
+    // Without an allow list, unknown clients pass
+    let deny_only = IpFilter::from_config(&IpFilterConfig {
+        deny: strings(&["203.0.113.0/24"]),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(deny_only.allows(None, "/livez"));
+    assert!(!deny_only.allows(ip("203.0.113.9"), "/livez"));
+
+    assert!(
+        IpFilter::from_config(&IpFilterConfig {
+            allow: strings(&["10.0.0.0/33"]),
+            ..Default::default()
+        })
+        .is_err()
+    );
+    assert!(
+        IpFilter::from_config(&IpFilterConfig {
+            routes: vec![RouteIpFilterConfig {
+                prefix: "api".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_management_listener_refuses_filtered_clients() {
+    let mgmt_bind = format!("127.0.0.1:{}", free_port());
+    let cfg = ArkConfig {
+        transport: Some(McpTransport::StreamableHTTP),
+        management_server: Some(ManagementEndpointConfig {
+            bind_address: Some(mgmt_bind.clone()),
+            disable_console: true,
+            ip_filter: Some(IpFilterConfig {
+                routes: vec![RouteIpFilterConfig {
+                    prefix: "/api/admin".to_string(),
+                    allow: strings(&["10.0.0.0/8"]),
+                    deny: vec![],
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        mcp_server: Some(McpEndpointConfig {
+            cors: None,
+            bind_address: Some(format!("127.0.0.1:{}", free_port())),
+            tls: None,
+            shared_sessions: false,
+        }),
+        ..Default::default()
+    };
+
+    let state = Arc::new(ArkState::default());
+    state.set_state(ApplicationState::StartingNetwork);
+    cfg.apply_to_state(state.clone()).await;
+    state.set_transport(McpTransport::StreamableHTTP);
+
+    let srv_state = state.clone();
+    let cfg_clone = cfg.clone();
+    let handle = tokio::spawn(async move {
+        let _ = service::start(&cfg_clone, srv_state).await;
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let client = reqwest::Client::new();
+    let get = |path: &str| client.get(format!("http://{}{}", mgmt_bind, path)).send();
+    assert_eq!(get("/api/v1/plugins").await.unwrap().status().as_u16(), 200);
+    for path in ["/api/v1/admin/users", "/api/admin/users"] {
+        let refused = get(path).await.unwrap();
+        assert_eq!(refused.status().as_u16(), 403, "{path}");
+        assert_eq!(refused.text().await.unwrap(), "Address not allowed");
+    }
+
+    handle.abort();
+}
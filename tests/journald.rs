@@ -0,0 +1,25 @@
+use ark::config::ArkConfig;
+
+fn parse(yaml: &str) -> ArkConfig {
+    serde_yaml_ng::from_str(yaml).unwrap()
+}
+
+#[test]
+fn test_journald_config() {
+    let config = parse("logging:\n  journald: {}\n");
+    let journald = config.logging.unwrap().journald.unwrap();
+    assert_eq!(journald.syslog_identifier, "ark");
+
+    let config = parse("logging:\n  journald:\n    syslog_identifier: ark-mcp\n");
+    let journald = config.logging.unwrap().journald.unwrap();
+    assert_eq!(journald.syslog_identifier, "ark-mcp");
+
+    assert!(parse("logging: {}\n").logging.unwrap().journald.is_none());
+}
+
+#[test]
+fn test_journald_excludes_file() {
+    let config = parse("logging:\n  journald: {}\n  file:\n    path: ark.log\n");
+    let err = ark::logging::init(config.logging.as_ref()).unwrap_err();
+    assert!(err.to_string().contains("cannot both be set"), "{}", err);
+}
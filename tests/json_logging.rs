@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ark::config::ArkConfig;
+use ark::config::models::LogFormat;
+use serde_json::Value;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Collects everything written by the console layer.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Capture {
+    fn lines(&self) -> Vec<Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn test_json_format() {
+    let capture = Capture::default();
+    let writer = capture.clone();
+    let subscriber = tracing_subscriber::registry()
+        .with(ark::logging::output_layer(LogFormat::Json, move || writer.clone()).unwrap());
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    // Without RUST_LOG only errors pass the filter
+    tracing::error!(plugin = "hasher", attempts = 3, "Plugin load failed");
+    ark::logging::with_request_id("req-1".to_string(), async {
+        assert_eq!(ark::logging::current_request_id().as_deref(), Some("req-1"));
+        tracing::error!(ok = false, "Inside request");
+    })
+    .await;
+    assert!(ark::logging::current_request_id().is_none());
+
+    let lines = capture.lines();
+    assert_eq!(lines.len(), 2);
+
+    let first = &lines[0];
+    assert_eq!(first["level"], "ERROR");
+    assert_eq!(first["target"], "json_logging");
+    assert_eq!(first["message"], "Plugin load failed");
+    assert_eq!(first["plugin"], "hasher");
+    assert_eq!(first["attempts"], 3);
+    assert!(first.get("request_id").is_none());
+    let timestamp = first["timestamp"].as_str().unwrap();
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(timestamp).is_ok(),
+        "{}",
+        timestamp
+    );
+
+    let second = &lines[1];
+    assert_eq!(second["level"], "ERROR");
+    assert_eq!(second["message"], "Inside request");
+    assert_eq!(second["request_id"], "req-1");
+    assert_eq!(second["ok"], false);
+}
+
+#[test]
+fn test_logging_format_config() {
+    let config: ArkConfig = serde_yaml_ng::from_str("logging:\n  format: json\n").unwrap();
+    assert_eq!(config.logging.unwrap().format, LogFormat::Json);
+
+    let config: ArkConfig = serde_yaml_ng::from_str("logging: {}\n").unwrap();
+    assert_eq!(config.logging.unwrap().format, LogFormat::Compact);
+
+    let config: ArkConfig = serde_yaml_ng::from_str("transport: stdio\n").unwrap();
+    assert!(config.logging.is_none());
+
+    assert!(serde_yaml_ng::from_str::<ArkConfig>("logging:\n  format: xml\n").is_err());
+}
@@ -0,0 +1,183 @@
+use ark::{
+    config::models::{KeyVaultSigningConfig, KmsSigningConfig, TokenSigningConfig},
+    server::{kms, signing::DynSigner},
+};
+use base64::{
+    Engine as _,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use jsonwebtoken::jwk::JwkSet;
+use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey};
+use rsa::traits::PublicKeyParts;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use wiremock::matchers::{body_string_contains, header, header_regex, method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+fn private_key() -> RsaPrivateKey {
+    let pem = std::fs::read_to_string("tests/test_keys/saml_idp.key").unwrap();
+    RsaPrivateKey::from_pkcs8_pem(&pem).unwrap()
+}
+
+/// Signs the digest sent to the mocked service, as KMS or Key Vault would.
+struct SignDigest {
+    key: RsaPrivateKey,
+    kms: bool,
+}
+
+impl Respond for SignDigest {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        let digest = if self.kms {
+            assert_eq!(body["MessageType"], "DIGEST");
+            assert_eq!(body["SigningAlgorithm"], "RSASSA_PKCS1_V1_5_SHA_256");
+            STANDARD.decode(body["Message"].as_str().unwrap()).unwrap()
+        } else {
+            assert_eq!(body["alg"], "RS256");
+            URL_SAFE_NO_PAD
+                .decode(body["value"].as_str().unwrap())
+                .unwrap()
+        };
+        let signature = self
+            .key
+            .sign(Pkcs1v15Sign::new::<Sha256>(), &digest)
+            .unwrap();
+        ResponseTemplate::new(200).set_body_json(if self.kms {
+            json!({ "Signature": STANDARD.encode(signature) })
+        } else {
+            json!({ "value": URL_SAFE_NO_PAD.encode(signature) })
+        })
+    }
+}
+
+/// Signs a token and verifies it with the published JWKS.
+async fn assert_signs_verifiable_tokens(signer: &DynSigner) {
+    let claims = json!({"sub": "user1", "aud": "client", "exp": 9999999999u64});
+    let token = signer
+        .sign(jsonwebtoken::Header::default(), &claims)
+        .await
+        .expect("sign");
+    let header = jsonwebtoken::decode_header(&token).unwrap();
+    assert_eq!(header.alg, jsonwebtoken::Algorithm::RS256);
+    assert_eq!(header.kid.as_deref(), Some(signer.kid()));
+
+    let jwk_set: JwkSet = serde_json::from_value(signer.jwks()).unwrap();
+    let decoding = jsonwebtoken::DecodingKey::from_jwk(jwk_set.find(signer.kid()).unwrap())
+        .expect("decoding key");
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+    validation.set_audience(&["client"]);
+    let data = jsonwebtoken::decode::<Value>(&token, &decoding, &validation).expect("decode");
+    assert_eq!(data.claims["sub"], "user1");
+}
+
+#[tokio::test]
+async fn test_kms_signer_signs_with_the_kms_key() {
+    unsafe {
+        std::env::set_var("AWS_ACCESS_KEY_ID", "AKIDTEST");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+    }
+    let server = MockServer::start().await;
+    let key = private_key();
+    let spki = key.to_public_key().to_public_key_der().unwrap();
+    // Requests are signed with Signature Version 4
+    let authorization = r"^AWS4-HMAC-SHA256 Credential=AKIDTEST/\d{8}/eu-west-1/kms/aws4_request, SignedHeaders=content-type;host;x-amz-date;x-amz-target, Signature=[0-9a-f]{64}$";
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("x-amz-target", "TrentService.GetPublicKey"))
+        .and(header_regex("authorization", authorization))
+        .and(body_string_contains("alias/ark"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "KeyId": "arn:aws:kms:eu-west-1:111122223333:key/test",
+            "KeySpec": "RSA_2048",
+            "KeyUsage": "SIGN_VERIFY",
+            "PublicKey": STANDARD.encode(spki.as_bytes()),
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("x-amz-target", "TrentService.Sign"))
+        .and(header_regex("authorization", authorization))
+        .respond_with(SignDigest { key, kms: true })
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = TokenSigningConfig {
+        source: Some("kms".to_string()),
+        kms: Some(KmsSigningConfig {
+            key_id: "alias/ark".to_string(),
+            region: Some("eu-west-1".to_string()),
+            endpoint: Some(server.uri()),
+        }),
+        ..Default::default()
+    };
+    let signer = kms::load_kms_signer(&config).await.unwrap().unwrap();
+    assert_signs_verifiable_tokens(&signer).await;
+}
+
+#[tokio::test]
+async fn test_keyvault_signer_signs_with_the_vault_key() {
+    let server = MockServer::start().await;
+    let key = private_key();
+    Mock::given(method("POST"))
+        .and(path("/tenant-1/oauth2/v2.0/token"))
+        .and(body_string_contains("client_secret=kv-secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "kv-token",
+            "expires_in": 3600,
+        })))
+        // The access token is reused
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/keys/ark-signing"))
+        .and(header("authorization", "Bearer kv-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "key": {
+                "kid": format!("{}/keys/ark-signing/v1", server.uri()),
+                "kty": "RSA-HSM",
+                "n": URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                "e": URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }
+        })))
+        .mount(&server)
+        .await;
+    // Signing uses the key version read at startup
+    Mock::given(method("POST"))
+        .and(path("/keys/ark-signing/v1/sign"))
+        .and(header("authorization", "Bearer kv-token"))
+        .respond_with(SignDigest { key, kms: false })
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = TokenSigningConfig {
+        source: Some("keyvault".to_string()),
+        keyvault: Some(KeyVaultSigningConfig {
+            vault_url: server.uri(),
+            key_name: "ark-signing".to_string(),
+            key_version: None,
+            tenant_id: Some("tenant-1".to_string()),
+            client_id: Some("client-1".to_string()),
+            client_secret: Some("kv-secret".to_string()),
+            client_secret_file: None,
+            authority_host: Some(server.uri()),
+        }),
+        ..Default::default()
+    };
+    let signer = kms::load_kms_signer(&config).await.unwrap().unwrap();
+    assert_signs_verifiable_tokens(&signer).await;
+}
+
+#[tokio::test]
+async fn test_kms_source_requires_its_settings() {
+    let config = TokenSigningConfig {
+        source: Some("kms".to_string()),
+        ..Default::default()
+    };
+    let err = kms::load_kms_signer(&config).await.err().unwrap();
+    assert!(err.to_string().contains("token_signing.kms"));
+}
@@ -0,0 +1,456 @@
+use std::sync::{Arc, Mutex};
+
+use ark::{
+    config::models::{
+        AuthConfig, Groups, IdentityProviderConfig, IdentityProviderKind, LdapConfig,
+        LockoutConfig, SessionConfig,
+    },
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, ProviderKind},
+        handlers::session,
+        ldap::Directory,
+        persist::Database,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+const ADMINS: &str = "cn=ark-admins,ou=groups,dc=test";
+const ENG: &str = "cn=eng,ou=groups,dc=test";
+
+// ------------------------- Minimal LDAP server -------------------------
+
+/// A BER element: tag byte and content.
+struct Tlv<'a> {
+    tag: u8,
+    content: &'a [u8],
+}
+
+/// Reads one BER element, returning it and the remaining input, or `None`
+/// when the input does not hold a complete element yet.
+fn read_tlv(input: &[u8]) -> Option<(Tlv<'_>, &[u8])> {
+    let tag = *input.first()?;
+    let first = *input.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let n = first & 0x7f;
+        let bytes = input.get(2..2 + n)?;
+        (
+            bytes.iter().fold(0usize, |acc, b| acc << 8 | *b as usize),
+            2 + n,
+        )
+    };
+    let content = input.get(header..header + len)?;
+    Some((Tlv { tag, content }, &input[header + len..]))
+}
+
+fn children(mut content: &[u8]) -> Vec<Tlv<'_>> {
+    let mut out = Vec::new();
+    while let Some((tlv, rest)) = read_tlv(content) {
+        out.push(tlv);
+        content = rest;
+    }
+    out
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len = (content.len() as u32).to_be_bytes();
+        let skip = len.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (4 - skip) as u8);
+        out.extend_from_slice(&len[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn octets(value: &str) -> Vec<u8> {
+    tlv(0x04, value.as_bytes())
+}
+
+fn ldap_result(op: u8, rc: u8) -> Vec<u8> {
+    tlv(op, &[tlv(0x0a, &[rc]), octets(""), octets("")].concat())
+}
+
+fn text(tlv: &Tlv) -> String {
+    String::from_utf8_lossy(tlv.content).into_owned()
+}
+
+struct Entry {
+    dn: &'static str,
+    password: &'static str,
+    attrs: Vec<(&'static str, Vec<&'static str>)>,
+}
+
+fn directory() -> Vec<Entry> {
+    vec![
+        Entry {
+            dn: "cn=svc,dc=test",
+            password: "svc-password",
+            attrs: vec![],
+        },
+        Entry {
+            dn: "uid=alice,ou=people,dc=test",
+            password: "alice-password",
+            attrs: vec![
+                ("uid", vec!["alice"]),
+                ("mail", vec!["alice@example.com"]),
+                ("displayName", vec!["Alice"]),
+                ("memberOf", vec![ADMINS]),
+            ],
+        },
+        Entry {
+            dn: "uid=bob,ou=people,dc=test",
+            password: "bob-password",
+            attrs: vec![("uid", vec!["bob"])],
+        },
+        Entry {
+            dn: ENG,
+            password: "",
+            attrs: vec![("member", vec!["uid=alice,ou=people,dc=test"])],
+        },
+    ]
+}
+
+/// Evaluates the equality and presence filters used by the provider.
+fn matches(entry: &Entry, filter: &Tlv) -> bool {
+    match filter.tag {
+        // equalityMatch
+        0xa3 => {
+            let parts = children(filter.content);
+            let (attr, value) = (text(&parts[0]), text(&parts[1]));
+            entry.attrs.iter().any(|(name, values)| {
+                name.eq_ignore_ascii_case(&attr)
+                    && values.iter().any(|v| v.eq_ignore_ascii_case(&value))
+            })
+        }
+        // present
+        0x87 => true,
+        _ => false,
+    }
+}
+
+fn search(entries: &[Entry], request: &[u8]) -> Vec<&'static str> {
+    let parts = children(request);
+    let base = text(&parts[0]);
+    let base_scope = parts[1].content == [0];
+    entries
+        .iter()
+        .filter(|e| {
+            if base_scope {
+                e.dn.eq_ignore_ascii_case(&base)
+            } else {
+                e.dn.to_lowercase().ends_with(&base.to_lowercase()) && matches(e, &parts[6])
+            }
+        })
+        .map(|e| e.dn)
+        .collect()
+}
+
+fn entry_message(entries: &[Entry], msgid: &[u8], dn: &str) -> Vec<u8> {
+    let entry = entries.iter().find(|e| e.dn == dn).unwrap();
+    let attrs: Vec<u8> = entry
+        .attrs
+        .iter()
+        .flat_map(|(name, values)| {
+            let vals: Vec<u8> = values.iter().flat_map(|v| octets(v)).collect();
+            tlv(0x30, &[octets(name), tlv(0x31, &vals)].concat())
+        })
+        .collect();
+    tlv(
+        0x30,
+        &[
+            msgid.to_vec(),
+            tlv(0x64, &[octets(dn), tlv(0x30, &attrs)].concat()),
+        ]
+        .concat(),
+    )
+}
+
+/// Starts an in-process LDAP server and returns its URL and the log of bind
+/// attempts (DN and password).
+async fn start_server() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ldap://{}", listener.local_addr().unwrap());
+    let binds = Arc::new(Mutex::new(Vec::new()));
+    let log = binds.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let log = log.clone();
+            tokio::spawn(async move {
+                let entries = directory();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    let Ok(n) = socket.read(&mut chunk).await else {
+                        return;
+                    };
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    while let Some((message, rest)) = read_tlv(&buf) {
+                        let parts = children(message.content);
+                        let msgid = tlv(0x02, parts[0].content);
+                        let op = &parts[1];
+                        let mut reply = Vec::new();
+                        match op.tag {
+                            // BindRequest
+                            0x60 => {
+                                let fields = children(op.content);
+                                let (dn, password) = (text(&fields[1]), text(&fields[2]));
+                                log.lock().unwrap().push((dn.clone(), password.clone()));
+                                let ok = entries.iter().any(|e| {
+                                    e.dn.eq_ignore_ascii_case(&dn)
+                                        && !e.password.is_empty()
+                                        && e.password == password
+                                });
+                                let rc = if ok { 0 } else { 49 };
+                                reply.extend(tlv(
+                                    0x30,
+                                    &[msgid.clone(), ldap_result(0x61, rc)].concat(),
+                                ));
+                            }
+                            // SearchRequest
+                            0x63 => {
+                                for dn in search(&entries, op.content) {
+                                    reply.extend(entry_message(&entries, &msgid, dn));
+                                }
+                                reply.extend(tlv(
+                                    0x30,
+                                    &[msgid.clone(), ldap_result(0x65, 0)].concat(),
+                                ));
+                            }
+                            // UnbindRequest
+                            _ => return,
+                        }
+                        let consumed = buf.len() - rest.len();
+                        buf.drain(..consumed);
+                        if socket.write_all(&reply).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    (url, binds)
+}
+
+// ------------------------- Tests -------------------------
+
+fn search_config(url: &str) -> LdapConfig {
+    LdapConfig {
+        url: url.to_string(),
+        bind_dn: Some("cn=svc,dc=test".to_string()),
+        bind_password: Some("svc-password".to_string()),
+        user_base_dn: Some("ou=people,dc=test".to_string()),
+        group_base_dn: Some("ou=groups,dc=test".to_string()),
+        timeout_secs: Some(5),
+        ..Default::default()
+    }
+}
+
+async fn setup(ldap: LdapConfig, groups: Groups) -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("ldap.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("ldap".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "ldap".to_string(),
+            kind: Some(IdentityProviderKind::Ldap),
+            discovery: false,
+            groups: Some(groups),
+            ldap: Some(ldap),
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        // Logins here retry right after a wrong password
+        lockout: LockoutConfig {
+            backoff_base_seconds: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+/// Posts the login form and returns the status and session id (if any).
+async fn login(router: &Router, username: &str, password: &str) -> (StatusCode, Option<String>) {
+    let body = format!(
+        "username={}&password={}",
+        urlencoding::encode(username),
+        urlencoding::encode(password)
+    );
+    let resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/auth/login/local")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let sid = resp
+        .headers()
+        .get(header::SET_COOKIE)
+        .and_then(|c| c.to_str().ok())
+        .and_then(|c| c.split(';').next())
+        .and_then(|c| c.strip_prefix("ark_session="))
+        .map(|s| s.to_string());
+    (resp.status(), sid)
+}
+
+#[test]
+fn test_ldap_config_validation() {
+    assert!(Directory::from_config(&search_config("ldap://dir.test")).is_ok());
+    assert!(Directory::from_config(&search_config("https://dir.test")).is_err());
+
+    let mut cfg = search_config("ldap://dir.test");
+    cfg.user_base_dn = None;
+    assert!(Directory::from_config(&cfg).is_err());
+    cfg.user_dn_template = Some("uid=alice,dc=test".to_string());
+    assert!(Directory::from_config(&cfg).is_err());
+    cfg.user_dn_template = Some("uid={username},dc=test".to_string());
+    assert!(Directory::from_config(&cfg).is_ok());
+
+    let mut cfg = search_config("ldaps://dir.test");
+    cfg.bind_password = None;
+    assert!(Directory::from_config(&cfg).is_err());
+}
+
+#[tokio::test]
+async fn test_search_and_bind_login() {
+    let (url, binds) = start_server().await;
+    let groups = Groups {
+        admin: Some(ADMINS.to_string()),
+        ..Default::default()
+    };
+    let (router, auth_state, _tmp) = setup(search_config(&url), groups).await;
+
+    let (status, sid) = login(&router, "Alice", "alice-password").await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    let principal = auth_state
+        .get_client_session(&sid.unwrap(), &ClientInfo::default())
+        .await
+        .unwrap();
+    assert_eq!(principal.provider_kind, ProviderKind::Ldap);
+    assert_eq!(principal.global_id(), "ldap/*/alice");
+    assert_eq!(
+        principal.oid.as_deref(),
+        Some("uid=alice,ou=people,dc=test")
+    );
+    assert_eq!(principal.email.as_deref(), Some("alice@example.com"));
+    assert_eq!(principal.name.as_deref(), Some("Alice"));
+    // memberOf plus the group search
+    assert_eq!(principal.groups, vec![ADMINS.to_string(), ENG.to_string()]);
+    assert!(principal.is_admin);
+
+    let (status, sid) = login(&router, "bob", "bob-password").await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    let bob = auth_state
+        .get_client_session(&sid.unwrap(), &ClientInfo::default())
+        .await
+        .unwrap();
+    assert!(!bob.is_admin);
+    assert!(bob.groups.is_empty());
+
+    for (username, password) in [
+        ("alice", "wrong"),
+        ("nobody", "alice-password"),
+        ("*", "alice-password"),
+        ("alice", ""),
+    ] {
+        let (status, sid) = login(&router, username, password).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", username);
+        assert!(sid.is_none());
+    }
+    // Empty passwords never reach the directory as an unauthenticated bind
+    assert!(binds.lock().unwrap().iter().all(|(_, pw)| !pw.is_empty()));
+}
+
+#[tokio::test]
+async fn test_direct_bind_and_users_group() {
+    let (url, _binds) = start_server().await;
+    let ldap = LdapConfig {
+        url,
+        user_dn_template: Some("uid={username},ou=people,dc=test".to_string()),
+        group_base_dn: Some("ou=groups,dc=test".to_string()),
+        ..Default::default()
+    };
+    let groups = Groups {
+        users: Some(ENG.to_string()),
+        role_mappings: [(ENG.to_string(), vec!["Admin".to_string()])].into(),
+        ..Default::default()
+    };
+    let (router, auth_state, _tmp) = setup(ldap, groups).await;
+
+    let (status, sid) = login(&router, "alice", "alice-password").await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    let principal = auth_state
+        .get_client_session(&sid.unwrap(), &ClientInfo::default())
+        .await
+        .unwrap();
+    assert!(principal.is_admin, "mapped from the eng group");
+
+    // bob authenticates but is not in the required users group
+    let (status, _) = login(&router, "bob", "bob-password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_unreachable_directory_fails_closed() {
+    // Bind and drop a listener to get a port nothing listens on
+    let port = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let (router, _auth, _tmp) = setup(
+        search_config(&format!("ldap://127.0.0.1:{}", port)),
+        Groups::default(),
+    )
+    .await;
+    let (status, sid) = login(&router, "alice", "alice-password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(sid.is_none());
+}
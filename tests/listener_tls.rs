@@ -0,0 +1,139 @@
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use ark::{config::ArkConfig, doctor::diagnose};
+use rcgen::CertifiedKey;
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn write_cert(dir: &TempDir, name: &str) -> String {
+    let CertifiedKey { cert, signing_key } =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    std::fs::write(dir.path().join(format!("{name}.pem")), cert.pem()).unwrap();
+    std::fs::write(
+        dir.path().join(format!("{name}.key")),
+        signing_key.serialize_pem(),
+    )
+    .unwrap();
+    cert.pem()
+}
+
+/// Top-level TLS with one certificate, MCP with its own and the management
+/// listener in plain HTTP.
+fn config(management: u16, mcp: u16) -> String {
+    format!(
+        r#"
+plugins: []
+tls:
+  key: global.key
+  cert: global.pem
+management_server:
+  bind_address: "127.0.0.1:{management}"
+  livez:
+    enabled: true
+    path: /livez
+  tls: {{}}
+mcp_server:
+  bind_address: "127.0.0.1:{mcp}"
+  tls:
+    key: mcp.key
+    cert: mcp.pem
+"#
+    )
+}
+
+async fn wait_for_port(port: u16) {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("port {port} not listening");
+}
+
+#[tokio::test]
+async fn test_listeners_override_top_level_tls() {
+    let dir = TempDir::new().unwrap();
+    let global = write_cert(&dir, "global");
+    let mcp_cert = write_cert(&dir, "mcp");
+    let (management, mcp) = (free_port(), free_port());
+    let config_path = dir.path().join("ark.yaml");
+    std::fs::write(&config_path, config(management, mcp)).unwrap();
+    let _server = Server(
+        Command::new(env!("CARGO_BIN_EXE_ark"))
+            .current_dir(dir.path())
+            .args(["--config-file", config_path.to_str().unwrap()])
+            .env("ARK_TRANSPORT", "streamable-http")
+            .env("ARK_DB_PATH", dir.path().join("ark.db"))
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    wait_for_port(management).await;
+    wait_for_port(mcp).await;
+
+    let plain = reqwest::get(format!("http://127.0.0.1:{management}/livez"))
+        .await
+        .unwrap();
+    assert_eq!(plain.status(), 200);
+
+    let trusting = |pem: &str| {
+        reqwest::Client::builder()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(reqwest::Certificate::from_pem(pem.as_bytes()).unwrap())
+            .build()
+            .unwrap()
+    };
+    let url = format!("https://localhost:{mcp}/");
+    trusting(&mcp_cert).get(&url).send().await.unwrap();
+    // The MCP listener does not serve the top-level certificate
+    assert!(trusting(&global).get(&url).send().await.is_err());
+}
+
+#[tokio::test]
+async fn test_doctor_checks_listener_tls_blocks() {
+    let dir = TempDir::new().unwrap();
+    write_cert(&dir, "global");
+    let mut config: ArkConfig = serde_yaml_ng::from_str(&config(8000, 3000)).unwrap();
+    let mcp = config.mcp_server.as_mut().unwrap().tls.as_mut().unwrap();
+    mcp.key = Some(dir.path().join("missing.key").display().to_string());
+    mcp.cert = Some(dir.path().join("missing.pem").display().to_string());
+    let tls = config.tls.as_mut().unwrap();
+    tls.key = Some(dir.path().join("global.key").display().to_string());
+    tls.cert = Some(dir.path().join("global.pem").display().to_string());
+
+    let findings = diagnose(Ok(config), Some(dir.path().join("ark.db"))).await;
+    let status = |check: &str| {
+        findings
+            .iter()
+            .find(|f| f.check == check)
+            .unwrap_or_else(|| panic!("no {check} finding in {findings:?}"))
+            .status
+    };
+    assert_eq!(status("management_server.tls"), ark::doctor::Status::Ok);
+    assert_eq!(status("mcp_server.tls"), ark::doctor::Status::Failed);
+    // The top-level block is used by no listener
+    assert!(findings.iter().all(|f| f.check != "tls"));
+}
@@ -0,0 +1,407 @@
+use std::sync::Arc;
+
+use ark::{
+    config::models::{AuthConfig, Groups, IdentityProviderConfig, LockoutConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, ProviderKind},
+        csrf,
+        handlers::session,
+        persist::Database,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+/// Builds a local-provider auth state with a temp DB and a router with `/auth`, `/api`
+/// and the production `check_auth`.
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    setup_with_session(SessionConfig::default()).await
+}
+
+async fn setup_with_session(session: SessionConfig) -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("users.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            groups: Some(Groups {
+                admin: Some("admins".to_string()),
+                users: None,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }],
+        session: Some(session),
+        // Logins here retry right after a wrong password
+        lockout: LockoutConfig {
+            backoff_base_seconds: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", "correct horse battery")
+        .await
+        .unwrap();
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .nest("/api", create_api_router(app_state))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+async fn send(router: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+/// Posts the login form and returns the status, Location and session cookie (if any).
+async fn login(
+    router: &Router,
+    username: &str,
+    password: &str,
+    oauth_params: Option<&str>,
+) -> (StatusCode, Option<String>, Option<String>) {
+    let mut body = format!(
+        "username={}&password={}",
+        urlencoding::encode(username),
+        urlencoding::encode(password)
+    );
+    if let Some(q) = oauth_params {
+        body.push_str(&format!("&oauth_params={}", urlencoding::encode(q)));
+    }
+    let resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/auth/login/local")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let header_str = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string())
+    };
+    let cookie =
+        header_str(header::SET_COOKIE).and_then(|c| c.split(';').next().map(|s| s.to_string()));
+    (resp.status(), header_str(header::LOCATION), cookie)
+}
+
+fn json_request(method: Method, uri: &str, cookie: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header(csrf::CSRF_HEADER, csrf_token(cookie))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// The CSRF token the console sends along with the session cookie.
+fn csrf_token(cookie: &str) -> String {
+    csrf::token_for_session(cookie.strip_prefix("ark_session=").unwrap_or_default())
+}
+
+#[test]
+fn test_password_hash_roundtrip() {
+    let hash = auth::hash_password("s3cret-password").unwrap();
+    assert!(hash.starts_with("$argon2"));
+    assert!(auth::verify_password("s3cret-password", &hash));
+    assert!(!auth::verify_password("wrong-password", &hash));
+    assert!(!auth::verify_password("s3cret-password", "not-a-hash"));
+}
+
+#[tokio::test]
+async fn test_login_redirects_to_local_form() {
+    let (router, _auth, _tmp) = setup().await;
+
+    let (status, body) = send(
+        &router,
+        Request::builder()
+            .uri("/auth/login")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["redirect"], "/auth/login/local");
+
+    let resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/local")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let html = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&html).contains(r#"name="password""#));
+}
+
+#[tokio::test]
+async fn test_local_login_and_admin_bootstrap() {
+    let (router, auth_state, _tmp) = setup().await;
+
+    let (status, _, cookie) = login(&router, "admin", "wrong password", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(cookie.is_none());
+    let (status, _, _) = login(&router, "nobody", "correct horse battery", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, location, cookie) = login(&router, "admin", "correct horse battery", None).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    assert_eq!(location.as_deref(), Some("/"));
+    let cookie = cookie.unwrap();
+
+    let sid = cookie.strip_prefix("ark_session=").unwrap();
+    let principal = auth_state
+        .get_client_session(sid, &ClientInfo::default())
+        .await
+        .unwrap();
+    assert_eq!(principal.provider_kind, ProviderKind::Local);
+    assert_eq!(principal.global_id(), "local/*/admin");
+    assert!(principal.is_admin);
+
+    // Bootstrapping again is a no-op once users exist
+    auth_state
+        .bootstrap_local_admin("admin", "another password")
+        .await
+        .unwrap();
+    let (status, _, _) = login(&router, "admin", "correct horse battery", None).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+}
+
+/// Returns the `Set-Cookie` values of a successful login.
+async fn login_cookies(router: &Router) -> Vec<String> {
+    let resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/auth/login/local")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(
+                    "username=admin&password=correct%20horse%20battery",
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    resp.headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|v| v.to_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_login_cookies_follow_session_settings() {
+    let (router, _auth, _tmp) = setup().await;
+    let cookies = login_cookies(&router).await;
+    assert_eq!(cookies.len(), 2);
+    assert!(cookies[0].starts_with("ark_session="));
+    assert!(cookies[0].contains("; HttpOnly"));
+    assert!(cookies[1].starts_with("ark_csrf="));
+    assert!(!cookies[1].contains("HttpOnly"));
+    for cookie in &cookies {
+        assert!(cookie.contains("; SameSite=Lax; Max-Age=3600"), "{cookie}");
+        assert!(cookie.ends_with("; Secure"), "{cookie}");
+    }
+
+    // Plain HTTP development setups turn Secure off
+    let (router, auth_state, _tmp) = setup_with_session(SessionConfig {
+        timeout_seconds: 600,
+        cookie_secure: false,
+        same_site: "Strict".to_string(),
+        cookie_domain: Some("localhost".to_string()),
+        ..Default::default()
+    })
+    .await;
+    let cookies = login_cookies(&router).await;
+    for cookie in &cookies {
+        assert!(
+            cookie.contains("; SameSite=Strict; Max-Age=600; Domain=localhost"),
+            "{cookie}"
+        );
+        assert!(!cookie.contains("Secure"), "{cookie}");
+    }
+    let sid = cookies[0]
+        .split(';')
+        .next()
+        .and_then(|c| c.strip_prefix("ark_session="))
+        .unwrap();
+    assert!(
+        auth_state
+            .get_client_session(sid, &ClientInfo::default())
+            .await
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_login_resumes_oauth_flow() {
+    let (router, _auth, _tmp) = setup().await;
+    let (status, location, _) = login(
+        &router,
+        "admin",
+        "correct horse battery",
+        Some("response_type=code&client_id=cli"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    assert_eq!(
+        location.as_deref(),
+        Some("/authorize?response_type=code&client_id=cli")
+    );
+}
+
+#[tokio::test]
+async fn test_user_management_and_group_roles() {
+    let (router, _auth, _tmp) = setup().await;
+    let (_, _, admin) = login(&router, "admin", "correct horse battery", None).await;
+    let admin = admin.unwrap();
+
+    for (username, groups) in [("bob", json!(["admins"])), ("carol", json!([]))] {
+        let (status, created) = send(
+            &router,
+            json_request(
+                Method::POST,
+                "/api/admin/users",
+                &admin,
+                json!({"username": username, "password": "password-123", "groups": groups}),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created.get("password_hash").is_none());
+    }
+
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/users",
+            &admin,
+            json!({"username": "dave", "password": "short"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Membership of the configured admin group grants the Admin role
+    let (_, _, bob) = login(&router, "bob", "password-123", None).await;
+    let (status, users) = send(
+        &router,
+        json_request(Method::GET, "/api/admin/users", &bob.unwrap(), Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(users.as_array().unwrap().len(), 3);
+
+    let (_, _, carol) = login(&router, "carol", "password-123", None).await;
+    let carol = carol.unwrap();
+    let (status, _) = send(
+        &router,
+        json_request(Method::GET, "/api/admin/users", &carol, Value::Null),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Users can change their own password
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/me/password",
+            &carol,
+            json!({"current_password": "wrong", "new_password": "new-password-456"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/me/password",
+            &carol,
+            json!({"current_password": "password-123", "new_password": "new-password-456"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = login(&router, "carol", "password-123", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, _) = login(&router, "carol", "new-password-456", None).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+
+    // Admin can delete users, but not themselves
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::DELETE,
+            "/api/admin/users/admin",
+            &admin,
+            Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::DELETE,
+            "/api/admin/users/carol",
+            &admin,
+            Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = login(&router, "carol", "new-password-456", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
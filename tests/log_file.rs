@@ -0,0 +1,107 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use ark::config::ArkConfig;
+use ark::config::models::{LogFileConfig, LogRotation};
+use ark::logging::file::RotatingFile;
+
+fn file_config(path: &Path, rotation: LogRotation) -> LogFileConfig {
+    LogFileConfig {
+        path: path.to_path_buf(),
+        rotation,
+        max_size_bytes: None,
+        max_files: None,
+    }
+}
+
+/// Contents of the rotated files next to `path`, oldest first.
+fn archives(path: &Path) -> Vec<String> {
+    let prefix = format!("{}.", path.file_name().unwrap().to_string_lossy());
+    let mut names: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|p| {
+            p.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with(&prefix)
+        })
+        .collect();
+    names.sort();
+    names
+        .iter()
+        .map(|p| std::fs::read_to_string(p).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_size_rotation_and_retention() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("logs").join("ark.log");
+    let mut config = file_config(&path, LogRotation::Never);
+    config.max_size_bytes = Some(10);
+    config.max_files = Some(2);
+
+    let mut file = RotatingFile::new(&config).unwrap();
+    for line in ["line 1\n", "line 2\n", "line 3\n", "line 4\n"] {
+        file.write_all(line.as_bytes()).unwrap();
+    }
+    file.flush().unwrap();
+
+    // Each line would overflow the previous one, the oldest archive is gone
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "line 4\n");
+    assert_eq!(archives(&path), vec!["line 2\n", "line 3\n"]);
+}
+
+#[test]
+fn test_appends_within_period() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ark.log");
+    let config = file_config(&path, LogRotation::Daily);
+
+    RotatingFile::new(&config)
+        .unwrap()
+        .write_all(b"first\n")
+        .unwrap();
+    RotatingFile::new(&config)
+        .unwrap()
+        .write_all(b"second\n")
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    assert!(archives(&path).is_empty());
+}
+
+#[test]
+fn test_stale_file_rotated_on_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ark.log");
+    std::fs::write(&path, "yesterday\n").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(2 * 86400))
+        .unwrap();
+
+    let mut file = RotatingFile::new(&file_config(&path, LogRotation::Daily)).unwrap();
+    file.write_all(b"today\n").unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "today\n");
+    assert_eq!(archives(&path), vec!["yesterday\n"]);
+}
+
+#[test]
+fn test_log_file_config() {
+    let yaml = "logging:\n  file:\n    path: /var/log/ark/ark.log\n    max_files: 7\n";
+    let config: ArkConfig = serde_yaml_ng::from_str(yaml).unwrap();
+    let file = config.logging.unwrap().file.unwrap();
+    assert_eq!(file.path, Path::new("/var/log/ark/ark.log"));
+    assert_eq!(file.rotation, LogRotation::Daily);
+    assert_eq!(file.max_size_bytes, None);
+    assert_eq!(file.max_files, Some(7));
+
+    let yaml = "logging:\n  file:\n    path: ark.log\n    rotation: weekly\n";
+    assert!(serde_yaml_ng::from_str::<ArkConfig>(yaml).is_err());
+}
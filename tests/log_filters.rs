@@ -0,0 +1,47 @@
+use ark::config::ArkConfig;
+use ark::logging::env_filter;
+use tracing::Level;
+use tracing_subscriber::layer::SubscriberExt;
+
+fn parse(yaml: &str) -> ArkConfig {
+    serde_yaml_ng::from_str(yaml).unwrap()
+}
+
+// RUST_LOG is process-wide, so every case runs in this one test.
+#[test]
+fn test_configured_filters() {
+    // SAFETY: this test binary runs a single test, no other thread reads the environment
+    unsafe { std::env::remove_var("RUST_LOG") };
+
+    let config = parse("logging:\n  filters:\n    ark: info\n    ark::plugins::oci: trace\n");
+    let filter = env_filter(config.logging.as_ref()).unwrap();
+    let subscriber = tracing_subscriber::registry().with(filter);
+    tracing::subscriber::with_default(subscriber, || {
+        assert!(tracing::enabled!(target: "ark::plugins::oci", Level::TRACE));
+        assert!(tracing::enabled!(target: "ark::server", Level::INFO));
+        assert!(!tracing::enabled!(target: "ark::server", Level::DEBUG));
+        // Other targets keep the default of errors only
+        assert!(tracing::enabled!(target: "hyper", Level::ERROR));
+        assert!(!tracing::enabled!(target: "hyper", Level::WARN));
+    });
+
+    // RUST_LOG wins for the same target and replaces the default level
+    // SAFETY: as above
+    unsafe { std::env::set_var("RUST_LOG", "ark::plugins::oci=warn,debug") };
+    let filter = env_filter(config.logging.as_ref()).unwrap();
+    unsafe { std::env::remove_var("RUST_LOG") };
+    let subscriber = tracing_subscriber::registry().with(filter);
+    tracing::subscriber::with_default(subscriber, || {
+        assert!(tracing::enabled!(target: "ark::plugins::oci", Level::WARN));
+        assert!(!tracing::enabled!(target: "ark::plugins::oci", Level::INFO));
+        assert!(tracing::enabled!(target: "ark::server", Level::INFO));
+        assert!(!tracing::enabled!(target: "ark::server", Level::DEBUG));
+        assert!(tracing::enabled!(target: "hyper", Level::DEBUG));
+        assert!(!tracing::enabled!(target: "log", Level::INFO));
+    });
+
+    // Invalid levels are rejected rather than ignored
+    let config = parse("logging:\n  filters:\n    ark: loud\n");
+    let err = env_filter(config.logging.as_ref()).unwrap_err();
+    assert!(err.to_string().contains("ark: loud"), "{}", err);
+}
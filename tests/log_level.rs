@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use ark::{
+    server::{
+        auth::{Principal, ProviderKind},
+        roles::Role,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    http::{Method, Request, StatusCode, header},
+};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn principal(is_admin: bool) -> Principal {
+    Principal {
+        subject: "tester".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: if is_admin {
+            vec![Role::Admin]
+        } else {
+            vec![Role::User]
+        },
+        is_admin,
+    }
+}
+
+fn router(principal: Option<Principal>) -> Router {
+    let router = Router::new().nest("/api", create_api_router(Arc::new(ArkState::default())));
+    match principal {
+        Some(p) => router.layer(Extension(p)),
+        None => router,
+    }
+}
+
+async fn call(router: Router, method: Method, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri("/api/admin/loglevel")
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or(Body::empty(), |b| Body::from(b.to_string()));
+    let response = router.oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+// The subscriber is process-wide, so every case runs in this one test.
+#[tokio::test]
+async fn test_log_level_endpoint() {
+    let admin = || router(Some(principal(true)));
+
+    // Before logging is initialized the filter cannot be changed
+    let (status, _) = call(admin(), Method::GET, None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    ark::logging::init(None).unwrap();
+    let initial = ark::logging::current_filter().unwrap();
+
+    let (status, body) = call(admin(), Method::GET, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["filter"], initial);
+
+    // Only administrators may read or change the filter
+    let (status, _) = call(router(None), Method::GET, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let filter = json!({ "filter": "trace" });
+    let (status, _) = call(router(Some(principal(false))), Method::PUT, Some(filter)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(ark::logging::current_filter().unwrap(), initial);
+
+    let filter = json!({ "filter": "info,ark::plugins::oci=trace" });
+    let (status, body) = call(admin(), Method::PUT, Some(filter)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["filter"], "ark::plugins::oci=trace,info");
+    assert!(tracing::enabled!(target: "ark::plugins::oci", tracing::Level::TRACE));
+    assert!(!tracing::enabled!(target: "ark::server", tracing::Level::DEBUG));
+
+    // Invalid directives leave the filter unchanged
+    let (status, body) = call(admin(), Method::PUT, Some(json!({ "filter": "ark=loud" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "Invalid log filter");
+    assert_eq!(
+        ark::logging::current_filter().unwrap(),
+        "ark::plugins::oci=trace,info"
+    );
+
+    // Toggling verbose logging restores the previous filter
+    assert_eq!(
+        ark::logging::toggle_verbose().unwrap(),
+        ark::logging::VERBOSE_FILTER
+    );
+    assert!(tracing::enabled!(target: "ark::server", tracing::Level::TRACE));
+    assert_eq!(
+        ark::logging::toggle_verbose().unwrap(),
+        "ark::plugins::oci=trace,info"
+    );
+    assert!(!tracing::enabled!(target: "ark::server", tracing::Level::TRACE));
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, LockoutConfig, SessionConfig},
+    server::{
+        auth::{self, AuthState},
+        handlers::session,
+        persist::{Database, UserRecord},
+    },
+    state::ArkState,
+};
+use axum::{
+    Router,
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const PASSWORD: &str = "correct horse battery";
+
+/// Builds a local-provider session router with the given lockout settings and
+/// two users, "admin" and "bob".
+async fn setup(lockout: LockoutConfig) -> (Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    let database = Database::with_path(temp_dir.path().join("lockout.db")).unwrap();
+    app_state.set_database(database.clone());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        lockout,
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", PASSWORD)
+        .await
+        .unwrap();
+    database
+        .save_user_async(UserRecord {
+            username: "bob".to_string(),
+            password_hash: auth::hash_password(PASSWORD).unwrap(),
+            display_name: None,
+            email: None,
+            groups: vec![],
+            is_admin: false,
+            created_utc: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+
+    (session::router(auth_state), temp_dir)
+}
+
+async fn login(
+    router: &Router,
+    ip: &str,
+    username: &str,
+    password: &str,
+) -> (StatusCode, Option<String>) {
+    let resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/login/local")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .header("x-forwarded-for", ip)
+                .body(Body::from(format!(
+                    "username={}&password={}",
+                    username,
+                    urlencoding::encode(password)
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let retry_after = resp
+        .headers()
+        .get(header::RETRY_AFTER)
+        .map(|v| v.to_str().unwrap().to_string());
+    (resp.status(), retry_after)
+}
+
+fn lockout(
+    max_attempts: u32,
+    max_attempts_per_ip: u32,
+    backoff_base_seconds: u64,
+) -> LockoutConfig {
+    LockoutConfig {
+        max_attempts,
+        max_attempts_per_ip,
+        backoff_base_seconds,
+        lockout_seconds: 2,
+        trust_forwarded_for: true,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_failed_password_backs_off() {
+    let (router, _tmp) = setup(lockout(10, 100, 1)).await;
+
+    let (status, _) = login(&router, "10.0.0.1", "admin", "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Even the right password has to wait out the backoff
+    let (status, retry_after) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(retry_after.as_deref(), Some("1"));
+
+    // The backoff follows the username, not the IP
+    let (status, _) = login(&router, "10.0.0.2", "Admin", PASSWORD).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let (status, _) = login(&router, "10.0.0.1", "bob", PASSWORD).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (status, _) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+
+    // A successful sign-in resets the counter: the next failure waits 1s again
+    let (status, _) = login(&router, "10.0.0.1", "admin", "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (_, retry_after) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    assert_eq!(retry_after.as_deref(), Some("1"));
+}
+
+#[tokio::test]
+async fn test_username_locked_out_after_max_attempts() {
+    let (router, _tmp) = setup(lockout(3, 100, 0)).await;
+
+    for _ in 0..3 {
+        let (status, _) = login(&router, "10.0.0.1", "admin", "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, retry_after) = login(&router, "10.0.0.9", "admin", PASSWORD).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(retry_after.as_deref(), Some("2"));
+
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    let (status, _) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn test_ip_locked_out_across_usernames() {
+    let (router, _tmp) = setup(lockout(10, 3, 0)).await;
+
+    for user in ["alice", "carol", "dave"] {
+        let (status, _) = login(&router, "10.0.0.1", user, "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = login(&router, "10.0.0.1", "bob", PASSWORD).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let (status, _) = login(&router, "10.0.0.2", "bob", PASSWORD).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn test_forwarded_for_ignored_unless_trusted() {
+    let (router, _tmp) = setup(LockoutConfig {
+        trust_forwarded_for: false,
+        ..lockout(10, 2, 0)
+    })
+    .await;
+
+    // Without a trusted header or peer address there is no IP to count
+    for ip in ["10.0.0.1", "10.0.0.2", "10.0.0.3"] {
+        let (status, _) = login(&router, ip, "nobody", "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = login(&router, "10.0.0.4", "bob", PASSWORD).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn test_lockout_disabled() {
+    let (router, _tmp) = setup(LockoutConfig {
+        enabled: false,
+        ..lockout(1, 1, 1)
+    })
+    .await;
+
+    for _ in 0..3 {
+        let (status, _) = login(&router, "10.0.0.1", "admin", "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn test_oauth_callback_failures_lock_out_ip() {
+    let (router, _tmp) = setup(lockout(10, 2, 0)).await;
+    let callback = |ip: &'static str| {
+        Request::builder()
+            .uri("/callback?code=abc&state=unknown-state")
+            .header("x-forwarded-for", ip)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    for _ in 0..2 {
+        let resp = router.clone().oneshot(callback("10.0.0.1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+    let resp = router.clone().oneshot(callback("10.0.0.1")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key(header::RETRY_AFTER));
+
+    // The IP is locked out of password sign-in as well
+    let (status, _) = login(&router, "10.0.0.1", "admin", PASSWORD).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let resp = router.clone().oneshot(callback("10.0.0.2")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
@@ -35,6 +35,7 @@ async fn test_mcp_ownership_filtering() {
         provider: Some("test".to_string()),
         providers: vec![provider],
         session: Some(ark::config::models::SessionConfig::default()),
+        ..Default::default()
     };
 
     let app_state = Arc::new(ArkState::default());
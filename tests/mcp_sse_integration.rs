@@ -2,6 +2,7 @@
 use std::{sync::Arc, time::Duration};
 use tempfile::TempDir;
 
+use ark::server::audit::ClientInfo;
 use ark::server::roles::Role;
 use ark::{
     config::{
@@ -77,6 +78,7 @@ async fn create_enabled_auth_state() -> Arc<auth::AuthState> {
         provider: Some("test-provider".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let (auth_state, _temp_dir) = create_test_auth_state(Some(auth_cfg)).await;
@@ -185,6 +187,7 @@ fn create_test_user(user_id: &str) -> auth::Principal {
         name: Some(format!("Test User {}", user_id)),
         provider: "test-provider".to_string(),
         provider_kind: auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         picture: None,
@@ -310,6 +313,7 @@ async fn test_mcp_endpoint_authentication_protection() {
         provider: Some("test-provider".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let (auth_state, _temp_dir) = create_test_auth_state(Some(auth_cfg)).await;
@@ -334,7 +338,7 @@ async fn test_mcp_endpoint_authentication_protection() {
     // Test authenticated access to MCP endpoint - should be allowed
     let user1 = create_test_user("user1");
     let session_id = auth_state
-        .put_session(user1, Duration::from_secs(3600))
+        .put_client_session(user1, Duration::from_secs(3600), &ClientInfo::default())
         .await;
     let cookie = format!("ark_session={}", session_id);
 
@@ -377,6 +381,7 @@ async fn test_sse_endpoint_authentication_protection() {
         provider: Some("test-provider".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     let (auth_state, _temp_dir) = create_test_auth_state(Some(auth_cfg)).await;
@@ -399,7 +404,7 @@ async fn test_sse_endpoint_authentication_protection() {
     // Test authenticated access to SSE endpoint - should be allowed
     let user1 = create_test_user("user1");
     let session_id = auth_state
-        .put_session(user1, Duration::from_secs(3600))
+        .put_client_session(user1, Duration::from_secs(3600), &ClientInfo::default())
         .await;
     let cookie = format!("ark_session={}", session_id);
 
@@ -533,7 +538,8 @@ fn apply_auth_middleware(router: Router, auth_state: Arc<auth::AuthState>) -> Ro
                 // Check session cookie
                 if let Some(cookie) = cookie_str
                     && let Some(principal) =
-                        auth::extract_session_user_from_cookie(&auth, cookie).await
+                        auth::extract_client_session_user(&auth, cookie, &ClientInfo::default())
+                            .await
                 {
                     let mut req = req;
                     req.extensions_mut().insert(principal);
@@ -0,0 +1,182 @@
+use std::net::IpAddr;
+
+use ark::{
+    config::models::{BasicAuthConfig, ManagementEndpointConfig, MetricsAuthConfig},
+    server::metrics_auth::{IpRange, MetricsAuth},
+};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+fn ip(s: &str) -> Option<IpAddr> {
+    Some(s.parse().unwrap())
+}
+
+fn authorization(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+    headers
+}
+
+fn status(denied: Option<axum::response::Response>) -> StatusCode {
+    denied.map_or(StatusCode::OK, |resp| resp.status())
+}
+
+#[test]
+fn test_metrics_auth_parses_from_yaml() {
+    let yaml = r#"
+metrics_auth:
+  bearer_token: scrape-secret
+  basic:
+    username: prometheus
+    password: hunter2
+  allowed_ips: ["10.0.0.0/8", "::1"]
+"#;
+    let cfg: ManagementEndpointConfig = serde_yaml_ng::from_str(yaml).unwrap();
+    let metrics_auth = cfg.metrics_auth.unwrap();
+    assert_eq!(metrics_auth.bearer_token.as_deref(), Some("scrape-secret"));
+    assert_eq!(metrics_auth.basic.unwrap().username, "prometheus");
+    assert_eq!(metrics_auth.allowed_ips, vec!["10.0.0.0/8", "::1"]);
+
+    let cfg: ManagementEndpointConfig = serde_yaml_ng::from_str("response_type: json").unwrap();
+    assert!(cfg.metrics_auth.is_none());
+}
+
+#[test]
+fn test_ip_range_matching() {
+    let range = IpRange::parse("10.1.0.0/16").unwrap();
+    assert!(range.contains("10.1.200.3".parse().unwrap()));
+    assert!(!range.contains("10.2.0.1".parse().unwrap()));
+    // IPv4-mapped IPv6 peers match IPv4 ranges
+    assert!(range.contains("::ffff:10.1.0.9".parse().unwrap()));
+
+    let host = IpRange::parse("192.168.1.5").unwrap();
+    assert!(host.contains("192.168.1.5".parse().unwrap()));
+    assert!(!host.contains("192.168.1.6".parse().unwrap()));
+
+    let v6 = IpRange::parse("fd00::/8").unwrap();
+    assert!(v6.contains("fd12::1".parse().unwrap()));
+    assert!(!v6.contains("10.0.0.1".parse().unwrap()));
+
+    assert!(
+        IpRange::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap())
+    );
+    assert!(IpRange::parse("10.0.0.0/33").is_err());
+    assert!(IpRange::parse("not-an-ip").is_err());
+}
+
+#[test]
+fn test_invalid_metrics_auth_config_is_rejected() {
+    let empty = MetricsAuthConfig::default();
+    assert!(MetricsAuth::from_config(&empty).is_err());
+
+    let bad_range = MetricsAuthConfig {
+        allowed_ips: vec!["10.0.0.0/40".to_string()],
+        ..Default::default()
+    };
+    assert!(MetricsAuth::from_config(&bad_range).is_err());
+
+    let blank_token = MetricsAuthConfig {
+        bearer_token: Some(String::new()),
+        ..Default::default()
+    };
+    assert!(MetricsAuth::from_config(&blank_token).is_err());
+}
+
+#[test]
+fn test_bearer_token_scrape() {
+    let guard = MetricsAuth::from_config(&MetricsAuthConfig {
+        bearer_token: Some("scrape-secret".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let ok = guard.deny(&authorization("Bearer scrape-secret"), ip("203.0.113.7"));
+    assert_eq!(status(ok), StatusCode::OK);
+
+    let wrong = guard.deny(&authorization("Bearer scrape-secreT"), ip("203.0.113.7"));
+    assert_eq!(status(wrong), StatusCode::UNAUTHORIZED);
+
+    let missing = guard.deny(&HeaderMap::new(), None).unwrap();
+    assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        missing.headers()[header::WWW_AUTHENTICATE],
+        "Bearer realm=\"ark metrics\""
+    );
+}
+
+#[test]
+fn test_basic_auth_scrape() {
+    let guard = MetricsAuth::from_config(&MetricsAuthConfig {
+        basic: Some(BasicAuthConfig {
+            username: "prometheus".to_string(),
+            password: "hunter2".to_string(),
+            password_file: None,
+        }),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let good = format!("Basic {}", STANDARD.encode("prometheus:hunter2"));
+    assert_eq!(
+        status(guard.deny(&authorization(&good), None)),
+        StatusCode::OK
+    );
+    let bad = format!("Basic {}", STANDARD.encode("prometheus:wrong"));
+    assert_eq!(
+        status(guard.deny(&authorization(&bad), None)),
+        StatusCode::UNAUTHORIZED
+    );
+    // A bearer token is not accepted when only basic auth is configured
+    assert_eq!(
+        status(guard.deny(&authorization("Bearer hunter2"), None)),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let challenge = guard.deny(&HeaderMap::new(), None).unwrap();
+    assert_eq!(
+        challenge.headers()[header::WWW_AUTHENTICATE],
+        "Basic realm=\"ark metrics\""
+    );
+}
+
+#[test]
+fn test_ip_allowlist_scrape() {
+    let allowlist_only = MetricsAuth::from_config(&MetricsAuthConfig {
+        allowed_ips: vec!["10.0.0.0/8".to_string()],
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(
+        status(allowlist_only.deny(&HeaderMap::new(), ip("10.3.2.1"))),
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(allowlist_only.deny(&HeaderMap::new(), ip("192.168.0.1"))),
+        StatusCode::FORBIDDEN
+    );
+    // Without a known peer address the allowlist cannot be satisfied
+    assert_eq!(
+        status(allowlist_only.deny(&HeaderMap::new(), None)),
+        StatusCode::FORBIDDEN
+    );
+
+    // With a token as well, both must hold
+    let both = MetricsAuth::from_config(&MetricsAuthConfig {
+        bearer_token: Some("scrape-secret".to_string()),
+        allowed_ips: vec!["10.0.0.0/8".to_string()],
+        ..Default::default()
+    })
+    .unwrap();
+    let headers = authorization("Bearer scrape-secret");
+    assert_eq!(status(both.deny(&headers, ip("10.0.0.1"))), StatusCode::OK);
+    assert_eq!(
+        status(both.deny(&headers, ip("172.16.0.1"))),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(both.deny(&HeaderMap::new(), ip("10.0.0.1"))),
+        StatusCode::UNAUTHORIZED
+    );
+}
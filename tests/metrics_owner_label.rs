@@ -0,0 +1,62 @@
+use ark::config::models::{OwnerLabelConfig, OwnerLabelMode};
+use ark::metrics::{configure_owner_label, owner_label};
+
+// Owner labels are process-wide, so every case runs in this one test.
+#[tokio::test]
+async fn test_tool_metrics_owner_label() {
+    // Disabled by default
+    assert_eq!(owner_label(Some("local/*/alice")), None);
+
+    configure_owner_label(Some(OwnerLabelConfig {
+        mode: OwnerLabelMode::Truncate,
+        max_owners: 2,
+        max_length: 5,
+    }));
+    assert_eq!(owner_label(Some("local/*/alice")).as_deref(), Some("alice"));
+    assert_eq!(
+        owner_label(Some("aad/tenant/bob-0123456789")).as_deref(),
+        Some("bob-0")
+    );
+    // Public plugins don't count towards the cap
+    assert_eq!(owner_label(None).as_deref(), Some("public"));
+    assert_eq!(owner_label(Some("*/*/*")).as_deref(), Some("public"));
+    // The cap is reached: new owners are grouped, known ones keep their label
+    assert_eq!(owner_label(Some("local/*/carol")).as_deref(), Some("other"));
+    assert_eq!(owner_label(Some("local/*/alice")).as_deref(), Some("alice"));
+
+    // Reconfiguring starts a fresh count
+    configure_owner_label(Some(OwnerLabelConfig::default()));
+    let hashed = owner_label(Some("local/*/carol")).unwrap();
+    assert_eq!(hashed.len(), 12);
+    assert!(hashed.chars().all(|c| c.is_ascii_hexdigit()));
+    assert!(!hashed.contains("carol"));
+    assert_eq!(owner_label(Some("local/*/carol")), Some(hashed.clone()));
+
+    #[cfg(feature = "prometheus")]
+    {
+        use http_body_util::BodyExt;
+
+        ark::metrics::init(None);
+        ark::metrics::record_tool_metrics("echo", "say", Some("local/*/carol"), 3.0);
+        ark::metrics::record_tool_metrics("clock", "now", None, 1.0);
+        let body = ark::metrics::handler::make_metrics_response()
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let series = |name: &str, plugin: &str| {
+            text.lines()
+                .find(|l| l.starts_with(name) && l.contains(&format!("plugin=\"{}\"", plugin)))
+                .map(str::to_string)
+        };
+        let echo = series("ark_tool_calls_total", "echo").unwrap();
+        assert!(echo.contains(&format!("owner=\"{}\"", hashed)), "{}", echo);
+        let clock = series("ark_tool_calls_total", "clock").unwrap();
+        assert!(clock.contains("owner=\"public\""), "{}", clock);
+    }
+
+    configure_owner_label(None);
+    assert_eq!(owner_label(Some("local/*/alice")), None);
+}
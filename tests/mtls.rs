@@ -0,0 +1,202 @@
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use rcgen::{
+    BasicConstraints, CertificateParams, CertifiedIssuer, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+};
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn ca() -> CertifiedIssuer<'static, KeyPair> {
+    let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap()
+}
+
+/// Certificate and key, in one PEM, issued by `ca` for `purpose`.
+fn issue(
+    ca: &CertifiedIssuer<'static, KeyPair>,
+    name: &str,
+    purpose: ExtendedKeyUsagePurpose,
+) -> (String, String) {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+    params.extended_key_usages = vec![purpose];
+    let cert = params.signed_by(&key, ca).unwrap();
+    (cert.pem(), key.serialize_pem())
+}
+
+struct Server {
+    _dir: TempDir,
+    child: Child,
+    management: u16,
+    mcp: u16,
+    ca: CertifiedIssuer<'static, KeyPair>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn command(dir: &Path, (management, mcp): (u16, u16), client_auth: &str, tls: bool) -> Command {
+    let config = dir.join("ark.yaml");
+    let tls = if tls {
+        "  key: server.key\n  cert: server.pem\n"
+    } else {
+        ""
+    };
+    std::fs::write(
+        &config,
+        format!(
+            "plugins: []\n\
+             management_server:\n  bind_address: \"127.0.0.1:{management}\"\n  livez:\n    enabled: true\n    path: /livez\n\
+             mcp_server:\n  bind_address: \"127.0.0.1:{mcp}\"\n\
+             tls:\n{tls}  client_auth:\n{client_auth}",
+        ),
+    )
+    .unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ark"));
+    command
+        .current_dir(dir)
+        .args(["--config-file", config.to_str().unwrap()])
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.join("ark.db"));
+    command
+}
+
+/// Starts the server requiring client certificates on the management
+/// listener and verifying optional ones on the MCP listener.
+fn start_server() -> Server {
+    let dir = TempDir::new().unwrap();
+    let ca = ca();
+    let (cert, key) = issue(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth);
+    std::fs::write(dir.path().join("server.pem"), cert).unwrap();
+    std::fs::write(dir.path().join("server.key"), key).unwrap();
+    std::fs::write(dir.path().join("clients.pem"), ca.pem()).unwrap();
+
+    let (management, mcp) = (free_port(), free_port());
+    let child = command(
+        dir.path(),
+        (management, mcp),
+        "    management:\n      ca: clients.pem\n      mode: require\n    mcp:\n      ca: clients.pem\n      mode: verify\n",
+        true,
+    )
+    .stdout(Stdio::null())
+    .spawn()
+    .unwrap();
+    Server {
+        management,
+        mcp,
+        _dir: dir,
+        child,
+        ca,
+    }
+}
+
+fn client(server: &Server, identity: Option<(String, String)>) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(server.ca.pem().as_bytes()).unwrap())
+        .timeout(Duration::from_secs(5));
+    if let Some((cert, key)) = identity {
+        builder = builder
+            .identity(reqwest::Identity::from_pem(format!("{cert}{key}").as_bytes()).unwrap());
+    }
+    builder.build().unwrap()
+}
+
+async fn wait_for_tls(server: &Server) {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        if tokio::net::TcpStream::connect(("127.0.0.1", server.mcp))
+            .await
+            .is_ok()
+            && tokio::net::TcpStream::connect(("127.0.0.1", server.management))
+                .await
+                .is_ok()
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("server did not start");
+}
+
+#[tokio::test]
+async fn test_mtls_required_on_management_listener() {
+    let server = start_server();
+    wait_for_tls(&server).await;
+    let url = format!("https://localhost:{}/livez", server.management);
+
+    let identity = issue(
+        &server.ca,
+        "mesh-client",
+        ExtendedKeyUsagePurpose::ClientAuth,
+    );
+    let response = client(&server, Some(identity))
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    assert!(client(&server, None).get(&url).send().await.is_err());
+
+    // A certificate from another CA is refused
+    let other = issue(&ca(), "intruder", ExtendedKeyUsagePurpose::ClientAuth);
+    assert!(client(&server, Some(other)).get(&url).send().await.is_err());
+}
+
+#[tokio::test]
+async fn test_mtls_verify_mode_allows_anonymous_clients() {
+    let server = start_server();
+    wait_for_tls(&server).await;
+    let url = format!("https://localhost:{}/", server.mcp);
+
+    client(&server, None).get(&url).send().await.unwrap();
+    let identity = issue(
+        &server.ca,
+        "mesh-client",
+        ExtendedKeyUsagePurpose::ClientAuth,
+    );
+    client(&server, Some(identity))
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    // Presented certificates must still verify
+    let other = issue(&ca(), "intruder", ExtendedKeyUsagePurpose::ClientAuth);
+    assert!(client(&server, Some(other)).get(&url).send().await.is_err());
+}
+
+#[test]
+fn test_mtls_without_tls_fails_startup() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("clients.pem"), ca().pem()).unwrap();
+    let output = command(
+        dir.path(),
+        (free_port(), free_port()),
+        "    management:\n      ca: clients.pem\n",
+        false,
+    )
+    .output()
+    .unwrap();
+    assert!(!output.status.success());
+    let logs = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        logs.contains("tls.client_auth.management needs TLS"),
+        "{logs}"
+    );
+}
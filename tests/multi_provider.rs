@@ -0,0 +1,372 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::server::audit::ClientInfo;
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
+    server::{
+        auth::{AuthState, Principal, ProviderKind},
+        handlers::session,
+        persist::{ApiKeyRecord, Database, UserPreferences},
+        roles::Role,
+    },
+    state::ArkState,
+};
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{StatusCode, header},
+    response::Response,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+use tower::ServiceExt;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PASSWORD: &str = "correct horse battery";
+
+fn local_provider() -> IdentityProviderConfig {
+    IdentityProviderConfig {
+        name: "local".to_string(),
+        discovery: false,
+        ..Default::default()
+    }
+}
+
+fn oidc_provider(name: &str, server: &MockServer) -> IdentityProviderConfig {
+    IdentityProviderConfig {
+        name: name.to_string(),
+        client_id: format!("{}-client", name),
+        authority: server.uri(),
+        discovery: false,
+        jwks_uri: Some(format!("{}/jwks", server.uri())),
+        authorization_endpoint: Some(format!("{}/authorize", server.uri())),
+        token_endpoint: Some(format!("{}/token", server.uri())),
+        ..Default::default()
+    }
+}
+
+fn auth_config(provider: &str, providers: Vec<IdentityProviderConfig>) -> AuthConfig {
+    AuthConfig {
+        enabled: true,
+        provider: Some(provider.to_string()),
+        providers,
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    }
+}
+
+/// Builds `/auth` with `local` (the default) and the OIDC provider `corp`
+/// both active.
+async fn setup(server: &MockServer) -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("multi.db")).unwrap());
+    let cfg = auth_config(
+        "local, corp",
+        vec![oidc_provider("corp", server), local_provider()],
+    );
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", PASSWORD)
+        .await
+        .unwrap();
+    let router = Router::new().nest("/auth", session::router(auth_state.clone()));
+    (router, auth_state, temp_dir)
+}
+
+async fn get(router: &Router, uri: &str) -> Response {
+    router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header(header::HOST, "ark.example.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn body_text(resp: Response) -> String {
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+fn location(resp: &Response) -> &str {
+    resp.headers()
+        .get(header::LOCATION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_login_offers_provider_choice() {
+    let server = MockServer::start().await;
+    let (router, auth_state, _tmp) = setup(&server).await;
+    let names: Vec<&str> = auth_state
+        .providers
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["local", "corp"]);
+
+    // JSON clients get the list, and a redirect to the chooser page
+    let resp = get(&router, "/auth/login").await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = serde_json::from_str(&body_text(resp).await).unwrap();
+    assert_eq!(body["redirect"], "/auth/login?mode=redirect");
+    assert_eq!(body["providers"][0]["name"], "local");
+    assert_eq!(body["providers"][1]["kind"], "oidc");
+    assert_eq!(
+        body["providers"][1]["login"],
+        "/auth/login?provider=corp&mode=redirect"
+    );
+
+    // The chooser page keeps the rest of the query on every link
+    let resp = get(&router, "/auth/login?mode=redirect&return_to=%2Fdevice").await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let page = body_text(resp).await;
+    assert!(page.contains(
+        r#"href="/auth/login?provider=local&amp;mode=redirect&amp;return_to=%2Fdevice""#
+    ));
+    assert!(
+        page.contains(
+            r#"href="/auth/login?provider=corp&amp;mode=redirect&amp;return_to=%2Fdevice""#
+        )
+    );
+
+    // Choosing a provider starts its login
+    let resp = get(
+        &router,
+        "/auth/login?provider=local&mode=redirect&return_to=%2Fdevice",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert_eq!(
+        location(&resp),
+        "/auth/login/local?provider=local&return_to=%2Fdevice"
+    );
+    let resp = get(&router, "/auth/login?provider=corp&mode=redirect").await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert!(location(&resp).starts_with(&format!("{}/authorize?", server.uri())));
+    assert!(location(&resp).contains("client_id=corp-client"));
+
+    let resp = get(&router, "/auth/login?provider=nope").await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_callback_uses_provider_login_started_with() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("client_id=corp-client"))
+        .and(body_string_contains("code=the-code"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let (router, _auth_state, _tmp) = setup(&server).await;
+
+    let resp = get(&router, "/auth/login?provider=corp&mode=redirect").await;
+    let state = url::Url::parse(location(&resp))
+        .unwrap()
+        .query_pairs()
+        .find(|(k, _)| k == "state")
+        .map(|(_, v)| v.into_owned())
+        .unwrap();
+
+    // `local` is the default provider; the code is redeemed at `corp`
+    let resp = get(
+        &router,
+        &format!("/auth/callback?code=the-code&state={}", state),
+    )
+    .await;
+    assert!(body_text(resp).await.contains("Token Exchange Failed"));
+}
+
+#[tokio::test]
+async fn test_password_login_with_several_providers() {
+    let server = MockServer::start().await;
+    let (router, auth_state, _tmp) = setup(&server).await;
+
+    let resp = get(&router, "/auth/login/local?provider=local").await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(
+        body_text(resp)
+            .await
+            .contains(r#"<input type="hidden" name="provider" value="local">"#)
+    );
+    // `corp` has no password form
+    let resp = get(&router, "/auth/login/local?provider=corp").await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/auth/login/local")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "username=admin&password={}&provider=local",
+                    urlencoding::encode(PASSWORD)
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    let session_id = resp
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|c| c.split(';').next()?.strip_prefix("ark_session="))
+        .unwrap()
+        .to_string();
+    let principal = auth_state
+        .get_client_session(&session_id, &ClientInfo::default())
+        .await
+        .unwrap();
+    assert_eq!(principal.global_id(), "local/*/admin");
+}
+
+#[tokio::test]
+async fn test_providers_of_one_kind_keep_users_apart() {
+    let server = MockServer::start().await;
+    let app_state = Arc::new(ArkState::default());
+
+    let cfg = auth_config(
+        "corp,partner",
+        vec![
+            oidc_provider("corp", &server),
+            oidc_provider("partner", &server),
+        ],
+    );
+    let auth_state = AuthState::new_with_state(&Some(cfg), app_state.clone(), None)
+        .await
+        .unwrap();
+    assert_eq!(auth_state.providers.len(), 2);
+    let user = |provider: &str| Principal {
+        subject: "alice".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: server.uri(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: Some(provider.to_string()),
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    };
+    assert_eq!(user("corp").global_id(), "oidc:corp/*/alice");
+    assert_eq!(user("partner").global_id(), "oidc:partner/*/alice");
+    // Providers named after their kind keep unqualified ids
+    assert_eq!(user("oidc").global_id(), "oidc/*/alice");
+
+    let cfg = auth_config("corp,corp", vec![oidc_provider("corp", &server)]);
+    let err = AuthState::new_with_state(&Some(cfg), app_state.clone(), None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("listed twice"), "{}", err);
+
+    let cfg = auth_config("local,missing", vec![local_provider()]);
+    let err = AuthState::new_with_state(&Some(cfg), app_state.clone(), None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("'missing' not found"), "{}", err);
+
+    // A single name keeps working as before
+    let cfg = auth_config("corp", vec![oidc_provider("corp", &server)]);
+    let auth_state = AuthState::new_with_state(&Some(cfg), app_state, None)
+        .await
+        .unwrap();
+    assert_eq!(auth_state.providers.len(), 1);
+    assert!(auth_state.active.read().await.is_some());
+}
+
+#[tokio::test]
+async fn test_rows_move_to_qualified_ids() {
+    let server = MockServer::start().await;
+    let temp_dir = TempDir::new().unwrap();
+    let db = Database::with_path(temp_dir.path().join("multi.db")).unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(db.clone());
+
+    // Stored while `corp` was the only OIDC provider and ids were unqualified
+    let legacy = Principal {
+        subject: "alice".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: server.uri(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    };
+    let preferences = UserPreferences {
+        theme: Some("dark".to_string()),
+        ..Default::default()
+    };
+    db.save_user_preferences_async("oidc/*/alice".to_string(), preferences.clone())
+        .await
+        .unwrap();
+    db.save_api_key_record_async(ApiKeyRecord {
+        key_id: "k1".to_string(),
+        token_hash: "hash".to_string(),
+        owner: legacy.global_id(),
+        name: "ci".to_string(),
+        principal: legacy.clone(),
+        scopes: vec!["api".to_string()],
+        created_utc: chrono::Utc::now(),
+        expiry_utc: None,
+    })
+    .await
+    .unwrap();
+    let session_id = AuthState::new_with_state(&None, app_state.clone(), None)
+        .await
+        .unwrap()
+        .put_client_session(legacy, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+
+    let cfg = auth_config("corp", vec![oidc_provider("corp", &server)]);
+    let auth_state = AuthState::new_with_state(&Some(cfg), app_state, None)
+        .await
+        .unwrap();
+
+    let moved = db
+        .get_user_preferences_async("oidc:corp/*/alice".to_string())
+        .await
+        .unwrap();
+    assert_eq!(moved, preferences);
+    let key = db
+        .get_api_key_by_hash_async("hash".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.owner, "oidc:corp/*/alice");
+    assert_eq!(key.principal.global_id(), "oidc:corp/*/alice");
+    // Sessions under the old id end; the user signs in again
+    assert!(
+        auth_state
+            .get_client_session(&session_id, &ClientInfo::default())
+            .await
+            .is_none()
+    );
+}
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, OAuthServerConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{AuthState, ProviderKind},
+        handlers::oauth,
+        persist::Database,
+    },
+    state::ArkState,
+};
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    response::Response,
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const ADMIN_PASSWORD: &str = "correct horse battery";
+const REDIRECT_URI: &str = "http://127.0.0.1:33418/callback";
+/// Verifier and challenge of RFC 7636 appendix B.
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+/// Builds a local-provider auth state with a temp DB, the OAuth router and a
+/// signed-in admin's session cookie.
+async fn setup(oauth_config: OAuthServerConfig) -> (Router, String, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("pkce.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        oauth: oauth_config,
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", ADMIN_PASSWORD)
+        .await
+        .unwrap();
+    let principal = auth_state
+        .authenticate_password(&ProviderKind::Local, None, "admin", ADMIN_PASSWORD)
+        .await
+        .unwrap();
+    let sid = auth_state
+        .put_client_session(principal, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+
+    (
+        oauth::router(auth_state),
+        format!("ark_session={}", sid),
+        temp_dir,
+    )
+}
+
+async fn call(router: &Router, req: Request<Body>) -> Response {
+    router.clone().oneshot(req).await.unwrap()
+}
+
+async fn json_body(resp: Response) -> (StatusCode, Value) {
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn register(router: &Router, metadata: Value) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/register")
+        .header(header::HOST, "ark.test")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(metadata.to_string()))
+        .unwrap();
+    json_body(call(router, req).await).await
+}
+
+async fn register_public_client(router: &Router) -> String {
+    let (status, registered) = register(
+        router,
+        json!({"redirect_uris": [REDIRECT_URI], "token_endpoint_auth_method": "none"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    registered["client_id"].as_str().unwrap().to_string()
+}
+
+/// Runs `/authorize` and returns the query parameters of the redirect back
+/// to the client.
+async fn authorize(
+    router: &Router,
+    cookie: &str,
+    client_id: &str,
+    pkce: &str,
+) -> Vec<(String, String)> {
+    let req = Request::builder()
+        .uri(format!(
+            "/authorize?response_type=code&client_id={}&redirect_uri={}{}",
+            client_id,
+            urlencoding::encode(REDIRECT_URI),
+            pkce
+        ))
+        .header(header::COOKIE, cookie)
+        .body(Body::empty())
+        .unwrap();
+    let resp = call(router, req).await;
+    let location = resp.headers()[header::LOCATION].to_str().unwrap();
+    assert!(location.starts_with(REDIRECT_URI), "{location}");
+    url::Url::parse(location)
+        .unwrap()
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+fn param<'a>(query: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    query
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+async fn exchange(
+    router: &Router,
+    client_id: &str,
+    code: &str,
+    verifier: Option<&str>,
+) -> (StatusCode, Value) {
+    let mut body = format!(
+        "grant_type=authorization_code&code={}&client_id={}&redirect_uri={}",
+        code,
+        client_id,
+        urlencoding::encode(REDIRECT_URI)
+    );
+    if let Some(verifier) = verifier {
+        body.push_str(&format!("&code_verifier={}", verifier));
+    }
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/token")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body))
+        .unwrap();
+    json_body(call(router, req).await).await
+}
+
+fn s256(challenge: &str) -> String {
+    format!("&code_challenge={}&code_challenge_method=S256", challenge)
+}
+
+#[tokio::test]
+async fn test_public_client_requires_code_challenge() {
+    let (router, cookie, _tmp) = setup(OAuthServerConfig::default()).await;
+    let client_id = register_public_client(&router).await;
+
+    let query = authorize(&router, &cookie, &client_id, "").await;
+    assert_eq!(param(&query, "error"), Some("invalid_request"));
+    assert!(param(&query, "code").is_none());
+}
+
+#[tokio::test]
+async fn test_token_endpoint_checks_code_verifier() {
+    let (router, cookie, _tmp) = setup(OAuthServerConfig::default()).await;
+    let client_id = register_public_client(&router).await;
+
+    for verifier in [
+        None,
+        Some("wrong-verifier-wrong-verifier-wrong-verifier-wrong"),
+        // Right characters but too short
+        Some("dBjftJeZ4CVP"),
+    ] {
+        let query = authorize(&router, &cookie, &client_id, &s256(CHALLENGE)).await;
+        let code = param(&query, "code").unwrap();
+        let (_, body) = exchange(&router, &client_id, code, verifier).await;
+        assert_eq!(body["error"], "invalid_grant", "{verifier:?}");
+    }
+
+    let query = authorize(&router, &cookie, &client_id, &s256(CHALLENGE)).await;
+    let code = param(&query, "code").unwrap();
+    let (status, body) = exchange(&router, &client_id, code, Some(VERIFIER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["access_token"].is_string(), "{body}");
+}
+
+#[tokio::test]
+async fn test_require_pkce_applies_to_every_client() {
+    let (router, cookie, _tmp) = setup(OAuthServerConfig {
+        require_pkce: true,
+        ..Default::default()
+    })
+    .await;
+
+    let query = authorize(&router, &cookie, "preprovisioned-client", "").await;
+    assert_eq!(param(&query, "error"), Some("invalid_request"));
+
+    let query = authorize(
+        &router,
+        &cookie,
+        "preprovisioned-client",
+        &format!("&code_challenge={}&code_challenge_method=plain", VERIFIER),
+    )
+    .await;
+    assert_eq!(param(&query, "error"), Some("invalid_request"));
+
+    let query = authorize(&router, &cookie, "preprovisioned-client", &s256(CHALLENGE)).await;
+    let code = param(&query, "code").unwrap();
+    let (_, body) = exchange(&router, "preprovisioned-client", code, Some(VERIFIER)).await;
+    assert!(body["access_token"].is_string(), "{body}");
+}
+
+#[tokio::test]
+async fn test_public_clients_can_be_disabled() {
+    let (router, _cookie, _tmp) = setup(OAuthServerConfig {
+        public_clients: false,
+        ..Default::default()
+    })
+    .await;
+
+    let (status, body) = register(
+        &router,
+        json!({"redirect_uris": [REDIRECT_URI], "token_endpoint_auth_method": "none"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "invalid_client_metadata");
+
+    let req = Request::builder()
+        .uri("/.well-known/oauth-authorization-server")
+        .header(header::HOST, "ark.test")
+        .body(Body::empty())
+        .unwrap();
+    let (_, metadata) = json_body(call(&router, req).await).await;
+    assert_eq!(
+        metadata["token_endpoint_auth_methods_supported"],
+        json!(["client_secret_basic", "client_secret_post"])
+    );
+}
@@ -0,0 +1,355 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, OAuthServerConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, ProviderKind},
+        handlers::oauth,
+        persist::Database,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const ADMIN_PASSWORD: &str = "correct horse battery";
+const REDIRECT_URI: &str = "http://127.0.0.1:33418/callback";
+
+/// Builds a local-provider auth state with a temp DB and a router with the
+/// OAuth endpoints behind the production `check_auth`.
+async fn setup(oauth_config: OAuthServerConfig) -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("oauth.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        oauth: oauth_config,
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", ADMIN_PASSWORD)
+        .await
+        .unwrap();
+
+    let auth_clone = auth_state.clone();
+    let router = oauth::router(auth_state.clone()).layer(middleware::from_fn(
+        move |req: Request<Body>, next: Next| {
+            let auth_state = auth_clone.clone();
+            async move { auth::check_auth(req, next, Extension(auth_state)).await }
+        },
+    ));
+    (router, auth_state, temp_dir)
+}
+
+async fn call(router: &Router, req: Request<Body>) -> Response {
+    router.clone().oneshot(req).await.unwrap()
+}
+
+async fn json_body(resp: Response) -> (StatusCode, Value) {
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn register(router: &Router, metadata: Value) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/register")
+        .header(header::HOST, "ark.test")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(metadata.to_string()))
+        .unwrap();
+    json_body(call(router, req).await).await
+}
+
+async fn login_cookie(auth: &AuthState) -> String {
+    let principal = auth
+        .authenticate_password(&ProviderKind::Local, None, "admin", ADMIN_PASSWORD)
+        .await
+        .unwrap();
+    let sid = auth
+        .put_client_session(principal, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+    format!("ark_session={}", sid)
+}
+
+/// Runs `/authorize` for a signed-in user and returns the response;
+/// `extra_query` is appended to the query string.
+async fn authorize(
+    router: &Router,
+    cookie: &str,
+    client_id: &str,
+    redirect_uri: &str,
+    extra_query: &str,
+) -> Response {
+    let uri = format!(
+        "/authorize?response_type=code&client_id={}&redirect_uri={}&state=xyz{}",
+        client_id,
+        urlencoding::encode(redirect_uri),
+        extra_query
+    );
+    let req = Request::builder()
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .body(Body::empty())
+        .unwrap();
+    call(router, req).await
+}
+
+/// Extracts the authorization code from an `/authorize` redirect.
+fn code_from(resp: &Response) -> String {
+    let location = resp.headers()[header::LOCATION].to_str().unwrap();
+    let url = url::Url::parse(location).unwrap();
+    url.query_pairs()
+        .find(|(k, _)| k == "code")
+        .map(|(_, v)| v.into_owned())
+        .unwrap()
+}
+
+async fn exchange(router: &Router, body: String, basic: Option<(&str, &str)>) -> Response {
+    let mut builder = Request::builder()
+        .method(Method::POST)
+        .uri("/token")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+    if let Some((id, secret)) = basic {
+        let credentials = STANDARD.encode(format!("{}:{}", id, secret));
+        builder = builder.header(header::AUTHORIZATION, format!("Basic {}", credentials));
+    }
+    call(router, builder.body(Body::from(body)).unwrap()).await
+}
+
+fn code_form(code: &str, client_id: Option<&str>) -> String {
+    let mut body = format!(
+        "grant_type=authorization_code&code={}&redirect_uri={}",
+        code,
+        urlencoding::encode(REDIRECT_URI)
+    );
+    if let Some(client_id) = client_id {
+        body.push_str(&format!("&client_id={}", client_id));
+    }
+    body
+}
+
+#[tokio::test]
+async fn test_metadata_advertises_registration_endpoint() {
+    let (router, _auth, _tmp) = setup(OAuthServerConfig::default()).await;
+
+    for path in [
+        "/.well-known/oauth-authorization-server",
+        "/.well-known/oauth-authorization-server/mcp",
+    ] {
+        let req = Request::builder()
+            .uri(path)
+            .header(header::HOST, "ark.test")
+            .body(Body::empty())
+            .unwrap();
+        let (status, metadata) = json_body(call(&router, req).await).await;
+        assert_eq!(status, StatusCode::OK, "{path}");
+        assert_eq!(metadata["issuer"], "https://ark.test");
+        assert_eq!(metadata["token_endpoint"], "https://ark.test/token");
+        assert_eq!(
+            metadata["registration_endpoint"],
+            "https://ark.test/register"
+        );
+        assert_eq!(
+            metadata["code_challenge_methods_supported"],
+            json!(["S256"])
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_registered_confidential_client_flow() {
+    let (router, auth_state, _tmp) = setup(OAuthServerConfig::default()).await;
+
+    let (status, registered) = register(
+        &router,
+        json!({"redirect_uris": [REDIRECT_URI], "client_name": "Test MCP client"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let client_id = registered["client_id"].as_str().unwrap().to_string();
+    let secret = registered["client_secret"].as_str().unwrap().to_string();
+    assert_eq!(registered["client_secret_expires_at"], 0);
+    assert_eq!(
+        registered["token_endpoint_auth_method"],
+        "client_secret_basic"
+    );
+    assert_eq!(registered["grant_types"], json!(["authorization_code"]));
+    assert_eq!(registered["response_types"], json!(["code"]));
+    assert_eq!(registered["client_name"], "Test MCP client");
+
+    let cookie = login_cookie(&auth_state).await;
+
+    // Without the secret the code is refused
+    let resp = authorize(&router, &cookie, &client_id, REDIRECT_URI, "").await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    let code = code_from(&resp);
+    let (status, body) =
+        json_body(exchange(&router, code_form(&code, Some(&client_id)), None).await).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["error"], "invalid_client");
+
+    // With the secret in a Basic header it is exchanged
+    let resp = authorize(&router, &cookie, &client_id, REDIRECT_URI, "").await;
+    let code = code_from(&resp);
+    let (status, body) =
+        json_body(exchange(&router, code_form(&code, None), Some((&client_id, &secret))).await)
+            .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["access_token"].is_string());
+}
+
+#[tokio::test]
+async fn test_public_client_exchanges_code_without_secret() {
+    let (router, auth_state, _tmp) = setup(OAuthServerConfig::default()).await;
+
+    let (status, registered) = register(
+        &router,
+        json!({
+            "redirect_uris": [REDIRECT_URI],
+            "token_endpoint_auth_method": "none",
+            "grant_types": ["authorization_code", "refresh_token"]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(registered.get("client_secret").is_none());
+    let client_id = registered["client_id"].as_str().unwrap().to_string();
+
+    // Public clients prove they hold the code with PKCE instead of a secret
+    let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+    let cookie = login_cookie(&auth_state).await;
+    let resp = authorize(
+        &router,
+        &cookie,
+        &client_id,
+        REDIRECT_URI,
+        &format!("&code_challenge={}&code_challenge_method=S256", challenge),
+    )
+    .await;
+    let code = code_from(&resp);
+    let body = format!(
+        "{}&code_verifier={}",
+        code_form(&code, Some(&client_id)),
+        verifier
+    );
+    let (status, body) = json_body(exchange(&router, body, None).await).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+#[tokio::test]
+async fn test_authorize_refuses_unregistered_redirect_uri() {
+    let (router, auth_state, _tmp) = setup(OAuthServerConfig::default()).await;
+
+    let (_, registered) = register(&router, json!({"redirect_uris": [REDIRECT_URI]})).await;
+    let client_id = registered["client_id"].as_str().unwrap();
+
+    let cookie = login_cookie(&auth_state).await;
+    let resp = authorize(
+        &router,
+        &cookie,
+        client_id,
+        "https://evil.example/callback",
+        "",
+    )
+    .await;
+    assert!(resp.headers().get(header::LOCATION).is_none());
+    let (status, body) = json_body(resp).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "invalid_request");
+}
+
+#[tokio::test]
+async fn test_register_rejects_invalid_metadata() {
+    let (router, _auth, _tmp) = setup(OAuthServerConfig::default()).await;
+
+    for (metadata, error) in [
+        (json!({}), "invalid_redirect_uri"),
+        (
+            json!({"redirect_uris": ["http://example.com/cb"]}),
+            "invalid_redirect_uri",
+        ),
+        (
+            json!({"redirect_uris": ["javascript:alert(1)"]}),
+            "invalid_redirect_uri",
+        ),
+        (
+            json!({"redirect_uris": ["https://example.com/cb#frag"]}),
+            "invalid_redirect_uri",
+        ),
+        (
+            json!({"redirect_uris": [REDIRECT_URI], "token_endpoint_auth_method": "private_key_jwt"}),
+            "invalid_client_metadata",
+        ),
+        (
+            json!({"redirect_uris": [REDIRECT_URI], "grant_types": ["implicit"]}),
+            "invalid_client_metadata",
+        ),
+        (
+            json!({"redirect_uris": [REDIRECT_URI], "response_types": ["token"]}),
+            "invalid_client_metadata",
+        ),
+    ] {
+        let (status, body) = register(&router, metadata.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{metadata}");
+        assert_eq!(body["error"], error, "{metadata}");
+    }
+
+    // Native app schemes are accepted
+    let (status, _) = register(
+        &router,
+        json!({"redirect_uris": ["com.example.app:/oauth/callback", "http://localhost:8080/cb"]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_registration_can_be_disabled() {
+    let (router, _auth, _tmp) = setup(OAuthServerConfig {
+        dynamic_registration: false,
+        ..Default::default()
+    })
+    .await;
+
+    let (status, _) = register(&router, json!({"redirect_uris": [REDIRECT_URI]})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let req = Request::builder()
+        .uri("/.well-known/oauth-authorization-server")
+        .header(header::HOST, "ark.test")
+        .body(Body::empty())
+        .unwrap();
+    let (_, metadata) = json_body(call(&router, req).await).await;
+    assert!(metadata.get("registration_endpoint").is_none());
+}
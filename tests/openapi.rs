@@ -0,0 +1,164 @@
+use std::{path::Path, sync::Arc};
+
+use ark::server::roles::Role;
+use ark::{
+    server::{
+        auth::{Principal, ProviderKind, path_requires_auth},
+        openapi::{OPENAPI_PATH, spec},
+        service::{create_api_router, create_console_router},
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+fn admin() -> Principal {
+    Principal {
+        subject: "admin".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::Admin],
+        is_admin: true,
+    }
+}
+
+/// Collects every `$ref` in `value`.
+fn refs<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                match (key.as_str(), v) {
+                    ("$ref", Value::String(r)) => out.push(r),
+                    _ => refs(v, out),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| refs(v, out)),
+        _ => {}
+    }
+}
+
+#[test]
+fn test_spec_is_consistent() {
+    let doc = spec();
+    assert_eq!(doc["openapi"], "3.1.0");
+    assert_eq!(doc["servers"][0]["url"], "/api/v1");
+
+    let mut all = Vec::new();
+    refs(&doc, &mut all);
+    assert!(!all.is_empty());
+    for r in all {
+        let pointer = r.strip_prefix('#').expect("local reference");
+        assert!(doc.pointer(pointer).is_some(), "dangling reference {}", r);
+    }
+
+    let mut ids = Vec::new();
+    for (_, item) in doc["paths"].as_object().unwrap() {
+        for (method, op) in item.as_object().unwrap() {
+            if method != "parameters" {
+                ids.push(op["operationId"].as_str().expect("operationId").to_string());
+            }
+        }
+    }
+    let count = ids.len();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), count, "duplicate operationId");
+}
+
+#[tokio::test]
+async fn test_documented_operations_are_routed() {
+    let router = create_api_router(Arc::new(ArkState::default())).layer(Extension(admin()));
+    let doc = spec();
+    for (path, item) in doc["paths"].as_object().unwrap() {
+        for (method, _) in item.as_object().unwrap() {
+            if method == "parameters" {
+                continue;
+            }
+            let uri = path
+                .split('/')
+                .map(|s| if s.starts_with('{') { "x" } else { s })
+                .collect::<Vec<_>>()
+                .join("/");
+            let req = Request::builder()
+                .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+                .uri(&uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from("{}"))
+                .unwrap();
+            let resp = router.clone().oneshot(req).await.unwrap();
+            let status = resp.status();
+            assert_ne!(
+                status,
+                StatusCode::METHOD_NOT_ALLOWED,
+                "{} {}",
+                method,
+                path
+            );
+            if uri == "/events" {
+                continue;
+            }
+            // Unrouted paths get an empty 404; handlers explain theirs
+            let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            assert!(
+                status != StatusCode::NOT_FOUND || !body.is_empty(),
+                "{} {} is not routed",
+                method,
+                path
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_openapi_is_served_without_auth() {
+    assert!(!path_requires_auth(OPENAPI_PATH));
+    assert!(!path_requires_auth("/api/openapi.json"));
+    assert!(path_requires_auth("/api/plugins"));
+
+    let router = create_api_router(Arc::new(ArkState::default()));
+    let req = Request::builder()
+        .uri("/openapi.json")
+        .body(Body::empty())
+        .unwrap();
+    let resp = router.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let served: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(served, spec());
+}
+
+#[tokio::test]
+async fn test_swagger_ui_is_optional() {
+    for enabled in [true, false] {
+        let router = create_console_router(
+            Arc::new(ArkState::default()),
+            enabled,
+            Path::new("www/dist"),
+        );
+        let req = Request::builder()
+            .uri("/api-docs")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(html.contains("SwaggerUIBundle"), enabled);
+        if enabled {
+            assert!(html.contains(OPENAPI_PATH));
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use reqwest::{StatusCode, header};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child, #[allow(dead_code)] TempDir);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts ark with the given `listeners` settings, returning it with the
+/// management port once it accepts connections.
+async fn start(listeners: &str) -> (Server, u16) {
+    let dir = TempDir::new().unwrap();
+    let port = free_port();
+    std::fs::write(
+        dir.path().join("ark.yaml"),
+        format!(
+            r#"
+plugins: []
+listeners:
+{listeners}
+management_server:
+  bind_address: "127.0.0.1:{port}"
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:{}"
+"#,
+            free_port()
+        ),
+    )
+    .unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_ark"))
+        .current_dir(dir.path())
+        .args(["--config-file", "ark.yaml"])
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.path().join("ark.db"))
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let server = Server(child, dir);
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        // Probe with a request, as a bare connection could take the only slot
+        let probe = reqwest::get(format!("http://127.0.0.1:{port}/livez")).await;
+        if probe.is_ok_and(|r| r.status().is_success()) {
+            break;
+        }
+        assert!(Instant::now() < deadline, "server did not start");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    (server, port)
+}
+
+/// Opens a connection with a request whose body never arrives, keeping its
+/// handler busy.
+async fn stalled_request(port: u16) -> TcpStream {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream
+        .write_all(
+            b"POST /api/v1/plugins HTTP/1.1\r\nHost: localhost\r\n\
+              Content-Type: application/json\r\nContent-Length: 100\r\n\r\n{",
+        )
+        .await
+        .unwrap();
+    // Let the request reach its handler
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    stream
+}
+
+#[tokio::test]
+async fn test_requests_past_the_limit_get_503() {
+    let (_server, port) = start("  max_concurrent_requests: 1").await;
+    let url = format!("http://127.0.0.1:{port}/livez");
+
+    let stalled = stalled_request(port).await;
+    let resp = reqwest::get(&url).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(resp.headers().contains_key(header::RETRY_AFTER));
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "Server overloaded");
+
+    // Capacity is back once the request is done
+    drop(stalled);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(reqwest::get(&url).await.unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_connections_past_the_limit_are_closed() {
+    let (_server, port) = start("  max_connections: 1").await;
+
+    let held = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let mut refused = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let _ = refused
+        .write_all(b"GET /livez HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await;
+    let mut buf = [0u8; 64];
+    let read = tokio::time::timeout(Duration::from_secs(5), refused.read(&mut buf))
+        .await
+        .expect("connection past the limit was kept open");
+    assert!(matches!(read, Ok(0) | Err(_)), "{read:?}");
+
+    drop(held);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let url = format!("http://127.0.0.1:{port}/livez");
+    assert_eq!(reqwest::get(&url).await.unwrap().status(), StatusCode::OK);
+}
@@ -35,6 +35,7 @@ fn create_test_principal(subject: &str, provider: &str) -> Principal {
         picture: None,
         provider: provider.to_string(),
         provider_kind: ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         roles: vec![Role::User],
@@ -79,6 +80,7 @@ async fn test_database_with_path() -> Result<()> {
         expiry_utc,
         expiry_epoch,
         is_admin: principal.is_admin,
+        client_binding: None,
     };
     database.save_session_record_async(session_record).await?;
     let result = database.get_session_record_async(session_id).await?;
@@ -113,6 +115,7 @@ async fn test_session_save_and_retrieve() -> Result<()> {
         expiry_utc,
         expiry_epoch,
         is_admin: principal.is_admin,
+        client_binding: None,
     };
     database.save_session_record_async(session_record).await?;
 
@@ -136,6 +139,65 @@ async fn test_session_save_and_retrieve() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_session_principals_are_encrypted_with_a_key() -> Result<()> {
+    let (plain, temp_dir) = create_test_database().await?;
+    let db_path = temp_dir.path().join("test.db");
+    let key = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+    let database = Database::with_path(&db_path)?
+        .with_session_cipher(ark::server::crypto::Cipher::from_base64(key)?);
+    let record = |session_id: &str| {
+        let principal = create_test_principal("alice", "google");
+        let expiry_utc = Utc::now() + chrono::Duration::hours(1);
+        SessionRecord {
+            session_id: session_id.to_string(),
+            is_admin: principal.is_admin,
+            client_binding: None,
+            principal,
+            expiry_utc,
+            expiry_epoch: expiry_utc.timestamp(),
+        }
+    };
+    let stored = |session_id: &str| -> Result<String> {
+        let conn = rusqlite::Connection::open(&db_path)?;
+        Ok(conn.query_row(
+            "SELECT principal_json FROM sessions WHERE session_id = ?1",
+            [session_id],
+            |row| row.get(0),
+        )?)
+    };
+
+    database.save_session_record_async(record("sealed")).await?;
+    assert!(!stored("sealed")?.contains("alice"));
+    let rec = database
+        .get_session_record_async("sealed".to_string())
+        .await?
+        .unwrap();
+    assert_eq!(rec.principal.email.as_deref(), Some("alice@example.com"));
+
+    // Sessions saved before the key was set stay readable
+    plain.save_session_record_async(record("plain")).await?;
+    assert!(stored("plain")?.contains("alice"));
+    assert!(
+        database
+            .get_session_record_async("plain".to_string())
+            .await?
+            .is_some()
+    );
+    let counts = database.count_active_sessions_async().await?;
+    assert_eq!(counts.values().sum::<u64>(), 2);
+
+    // Without the key, encrypted sessions cannot be read
+    assert!(
+        plain
+            .get_session_record_async("sealed".to_string())
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_session_update() -> Result<()> {
     let (database, _temp_dir) = create_test_database().await?;
@@ -161,6 +223,7 @@ async fn test_session_update() -> Result<()> {
             expiry_utc,
             expiry_epoch,
             is_admin: false,
+            client_binding: None,
         })
         .await?;
 
@@ -180,6 +243,7 @@ async fn test_session_update() -> Result<()> {
             expiry_utc,
             expiry_epoch,
             is_admin: principal2.is_admin,
+            client_binding: None,
         })
         .await?;
 
@@ -218,6 +282,7 @@ async fn test_session_delete() -> Result<()> {
             expiry_utc,
             expiry_epoch,
             is_admin: false,
+            client_binding: None,
         })
         .await?;
 
@@ -269,6 +334,7 @@ async fn test_session_expiry() -> Result<()> {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: false,
+                client_binding: None,
             }
         })
         .await?;
@@ -318,6 +384,7 @@ async fn test_session_cleanup() -> Result<()> {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: principal.is_admin,
+                client_binding: None,
             })
             .await?;
     }
@@ -337,6 +404,7 @@ async fn test_session_cleanup() -> Result<()> {
             expiry_utc,
             expiry_epoch,
             is_admin: false,
+            client_binding: None,
         })
         .await?;
 
@@ -393,6 +461,7 @@ async fn test_multiple_sessions() -> Result<()> {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: false,
+                client_binding: None,
             })
             .await?;
         session_ids.push(session_id);
@@ -570,6 +639,136 @@ async fn test_plugin_delete() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_large_plugin_payloads_are_stored_as_blobs() -> Result<()> {
+    let (database, _temp_dir) = create_test_database().await?;
+    let record = |plugin_id: &str, plugin_data: Option<Vec<u8>>| PluginRecord {
+        owner: "user:tenant:blob".to_string(),
+        plugin_id: plugin_id.to_string(),
+        plugin_name: None,
+        plugin_path: None,
+        plugin_data,
+        metadata: json!({}),
+        date_added_utc: chrono::Utc::now(),
+    };
+    let large = vec![7u8; 256 * 1024];
+    let blobs = || -> Result<usize> {
+        Ok(match std::fs::read_dir(database.blob_dir()) {
+            Ok(entries) => entries.count(),
+            Err(_) => 0,
+        })
+    };
+
+    database
+        .save_plugin_record_async(record("small", Some(b"\0asm".to_vec())))
+        .await?;
+    assert_eq!(blobs()?, 0);
+    database
+        .save_plugin_record_async(record("large", Some(large.clone())))
+        .await?;
+    database
+        .save_plugin_record_async(record("copy", Some(large.clone())))
+        .await?;
+    assert_eq!(blobs()?, 1);
+
+    let owner = "user:tenant:blob".to_string();
+    let stored = database
+        .get_plugin_async(owner.clone(), "large".to_string())
+        .await?
+        .unwrap();
+    assert_eq!(stored.plugin_data.as_deref(), Some(large.as_slice()));
+    let listed = database.list_plugins_by_owner_async(owner.clone()).await?;
+    assert!(
+        listed
+            .iter()
+            .all(|p| p.plugin_data.as_ref().is_some_and(|d| !d.is_empty()))
+    );
+
+    // Saving without a payload keeps the stored blob
+    database
+        .save_plugin_record_async(record("large", None))
+        .await?;
+    let stored = database
+        .get_plugin_async(owner.clone(), "large".to_string())
+        .await?
+        .unwrap();
+    assert_eq!(stored.plugin_data.as_deref(), Some(large.as_slice()));
+
+    // The blob is removed with the last plugin referencing it
+    database
+        .delete_plugin_async(owner.clone(), "large".to_string())
+        .await?;
+    assert_eq!(blobs()?, 1);
+    database
+        .save_plugin_record_async(record("copy", Some(b"\0asm".to_vec())))
+        .await?;
+    assert_eq!(blobs()?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_identical_plugin_payloads_are_stored_once() -> Result<()> {
+    let (database, temp_dir) = create_test_database().await?;
+    let db_path = temp_dir.path().join("test.db");
+    let record = |owner: &str| PluginRecord {
+        owner: owner.to_string(),
+        plugin_id: "shared".to_string(),
+        plugin_name: None,
+        plugin_path: None,
+        plugin_data: Some(b"\0asm shared".to_vec()),
+        metadata: json!({}),
+        date_added_utc: chrono::Utc::now(),
+    };
+    let blobs = || -> Result<Vec<i64>> {
+        let conn = rusqlite::Connection::open(&db_path)?;
+        let mut stmt = conn.prepare("SELECT ref_count FROM plugin_blobs")?;
+        let counts = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(counts)
+    };
+
+    database.save_plugin_record_async(record("alice")).await?;
+    database.save_plugin_record_async(record("bob")).await?;
+    database.save_plugin_record_async(record("bob")).await?;
+    assert_eq!(blobs()?, vec![2]);
+    let stored = database
+        .get_plugin_async("bob".to_string(), "shared".to_string())
+        .await?
+        .unwrap();
+    assert_eq!(stored.plugin_data.as_deref(), Some(&b"\0asm shared"[..]));
+
+    database
+        .delete_plugin_async("alice".to_string(), "shared".to_string())
+        .await?;
+    assert_eq!(blobs()?, vec![1]);
+    database
+        .delete_plugin_async("bob".to_string(), "shared".to_string())
+        .await?;
+    assert!(blobs()?.is_empty());
+
+    // Payloads stored in the plugins table by earlier versions are moved on startup
+    {
+        let conn = rusqlite::Connection::open(&db_path)?;
+        for owner in ["alice", "bob"] {
+            conn.execute(
+                "INSERT INTO plugins(owner, plugin_id, plugin_data, metadata, date_added_utc) VALUES(?1, 'legacy', x'0061736d', '{}', ?2)",
+                rusqlite::params![owner, Utc::now().to_rfc3339()],
+            )?;
+        }
+    }
+    let database = Database::with_path(&db_path)?;
+    assert_eq!(blobs()?, vec![2]);
+    let stored = database
+        .get_plugin_async("alice".to_string(), "legacy".to_string())
+        .await?
+        .unwrap();
+    assert_eq!(stored.plugin_data.as_deref(), Some(&b"\0asm"[..]));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_plugin_list_all() -> Result<()> {
     let (database, _temp_dir) = create_test_database().await?;
@@ -706,6 +905,7 @@ async fn test_concurrent_session_operations() -> Result<()> {
                     expiry_utc,
                     expiry_epoch,
                     is_admin: false,
+                    client_binding: None,
                 }
             })
             .await?;
@@ -911,6 +1111,7 @@ async fn test_special_characters_in_data() -> Result<()> {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: principal.is_admin,
+                client_binding: None,
             }
         })
         .await?;
@@ -979,6 +1180,7 @@ async fn test_session_zero_ttl() -> Result<()> {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: principal.is_admin,
+                client_binding: None,
             }
         })
         .await?;
@@ -1019,6 +1221,7 @@ async fn test_empty_string_identifiers() -> Result<()> {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: principal.is_admin,
+                client_binding: None,
             }
         })
         .await?;
@@ -1123,6 +1326,7 @@ async fn test_database_persistence_across_instances() -> Result<()> {
                     expiry_utc,
                     expiry_epoch,
                     is_admin: principal.is_admin,
+                    client_binding: None,
                 }
             })
             .await?;
@@ -1202,6 +1406,7 @@ async fn test_cleanup_with_mixed_expiry_times() -> Result<()> {
                 expiry_utc,
                 expiry_epoch,
                 is_admin: base_principal.is_admin,
+                client_binding: None,
             })
             .await?;
     }
@@ -1235,3 +1440,24 @@ async fn test_cleanup_with_mixed_expiry_times() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_task_lease_is_held_by_one_instance() -> Result<()> {
+    let (db, _temp_dir) = create_test_database().await?;
+    let ttl = Duration::from_secs(60);
+
+    assert!(db.acquire_lease_async("cleanup", "a", ttl).await?);
+    assert!(!db.acquire_lease_async("cleanup", "b", ttl).await?);
+    // The holder renews its lease; other tasks have their own
+    assert!(db.acquire_lease_async("cleanup", "a", ttl).await?);
+    assert!(db.acquire_lease_async("rollup", "b", ttl).await?);
+
+    // An expired lease is taken over
+    assert!(
+        db.acquire_lease_async("cleanup", "a", Duration::ZERO)
+            .await?
+    );
+    assert!(db.acquire_lease_async("cleanup", "b", ttl).await?);
+    assert!(!db.acquire_lease_async("cleanup", "a", ttl).await?);
+    Ok(())
+}
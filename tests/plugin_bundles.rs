@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use ark::server::roles::Role;
+use ark::{
+    plugins::bundle::BUNDLE_FORMAT,
+    server::{
+        auth::{Principal, ProviderKind},
+        persist::Database,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn user(subject: &str, roles: Vec<Role>) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        is_admin: roles.contains(&Role::Admin),
+        roles,
+    }
+}
+
+fn sample_url() -> String {
+    let mut p = std::env::current_dir().unwrap();
+    p.push("tests/testdata/sample.wasm");
+    url::Url::from_file_path(p).unwrap().to_string()
+}
+
+async fn call(router: &Router, method: Method, uri: &str, body: Value) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_export_and_import_between_servers() {
+    let source = create_api_router(Arc::new(ArkState::default()))
+        .layer(Extension(user("alice", vec![Role::User])));
+    let (status, _) = call(
+        &source,
+        Method::POST,
+        "/plugins",
+        json!({"name": "sample", "url": sample_url()}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, bundle) = call(&source, Method::GET, "/plugins/sample/export", Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(bundle["format"], BUNDLE_FORMAT);
+    assert_eq!(bundle["name"], "sample");
+    assert_eq!(bundle["source_url"], sample_url());
+    assert_eq!(bundle["sha256"].as_str().unwrap().len(), 64);
+
+    // Other users cannot export alice's plugin
+    let other = create_api_router(Arc::new(ArkState::default()))
+        .layer(Extension(user("bob", vec![Role::User])));
+    let (status, _) = call(&other, Method::GET, "/plugins/sample/export", Value::Null).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The target server loads the plugin from the bundle alone
+    let temp_dir = TempDir::new().unwrap();
+    let target_state = Arc::new(ArkState::default());
+    target_state.set_database(Database::with_path(temp_dir.path().join("bundles.db")).unwrap());
+    let bob = user("bob", vec![Role::User]);
+    let target = create_api_router(target_state.clone()).layer(Extension(bob.clone()));
+    let (status, body) = call(&target, Method::POST, "/plugins/import", bundle.clone()).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    let (status, plugins) = call(&target, Method::GET, "/plugins", Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(plugins["sample"]["url"], sample_url());
+    assert_eq!(plugins["sample"]["owner"], bob.global_id());
+    assert!(!plugins["sample"]["tools"].as_array().unwrap().is_empty());
+
+    // Version information survives a second export
+    let db = target_state.database.read().unwrap().clone().unwrap();
+    let record = db
+        .get_plugin_async(bob.global_id(), "sample".to_string())
+        .await
+        .unwrap()
+        .expect("imported plugin is persisted");
+    assert_eq!(record.metadata["bundle"]["sha256"], bundle["sha256"]);
+    let (status, again) = call(&target, Method::GET, "/plugins/sample/export", Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(again["sha256"], bundle["sha256"]);
+    assert_eq!(again["source_url"], bundle["source_url"]);
+    assert_eq!(again["wasm"], bundle["wasm"]);
+}
+
+#[tokio::test]
+async fn test_import_rejects_invalid_bundles() {
+    let source = create_api_router(Arc::new(ArkState::default()))
+        .layer(Extension(user("alice", vec![Role::User])));
+    let (status, _) = call(
+        &source,
+        Method::POST,
+        "/plugins",
+        json!({"name": "sample", "url": sample_url()}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, bundle) = call(&source, Method::GET, "/plugins/sample/export", Value::Null).await;
+
+    let target = create_api_router(Arc::new(ArkState::default()))
+        .layer(Extension(user("bob", vec![Role::User])));
+    let mut tampered = bundle.clone();
+    tampered["sha256"] = json!("0".repeat(64));
+    let (status, body) = call(&target, Method::POST, "/plugins/import", tampered).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "Invalid plugin bundle");
+
+    let mut unknown = bundle.clone();
+    unknown["format"] = json!("ark-plugin-bundle/99");
+    let (status, _) = call(&target, Method::POST, "/plugins/import", unknown).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Importing registers a plugin, so it needs plugin.create
+    let viewer = create_api_router(Arc::new(ArkState::default())).layer(Extension(user(
+        "carol",
+        vec![Role::Custom("viewer".to_string())],
+    )));
+    let (status, _) = call(&viewer, Method::POST, "/plugins/import", bundle).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
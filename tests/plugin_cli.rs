@@ -0,0 +1,88 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+const HASH_PLUGIN: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/testdata/hash_plugin.wasm"
+);
+
+fn ark(db: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ark"))
+        .arg("plugin")
+        .args(args)
+        .arg("--database")
+        .arg(db)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_plugin_add_list_inspect_remove() {
+    let dir = TempDir::new().unwrap();
+    let db = dir.path().join("ark.db");
+
+    let list = ark(&db, &["list"]);
+    assert!(list.status.success(), "{}", stderr(&list));
+    assert!(stderr(&list).contains("No stored plugins"));
+
+    // Adding fetches the plugin and shows its tools
+    let add = ark(&db, &["add", "hash", HASH_PLUGIN]);
+    assert!(add.status.success(), "{}", stderr(&add));
+    assert!(stdout(&add).contains("sha256"), "{}", stdout(&add));
+
+    let again = ark(&db, &["add", "hash", HASH_PLUGIN]);
+    assert!(!again.status.success());
+    assert!(stderr(&again).contains("--force"), "{}", stderr(&again));
+    let forced = ark(&db, &["add", "hash", HASH_PLUGIN, "--force"]);
+    assert!(forced.status.success(), "{}", stderr(&forced));
+
+    let list = ark(&db, &["list"]);
+    let listed = stdout(&list);
+    assert!(
+        listed.contains("hash") && listed.contains("*/*/*"),
+        "{listed}"
+    );
+    assert!(listed.contains("hash_plugin.wasm"), "{listed}");
+
+    // Stored plugins are described from the stored bytes
+    let inspect = ark(&db, &["inspect", "hash"]);
+    assert!(inspect.status.success(), "{}", stderr(&inspect));
+    let shown = stdout(&inspect);
+    assert!(
+        shown.contains("(stored)") && shown.contains("base64"),
+        "{shown}"
+    );
+
+    let remove = ark(&db, &["remove", "hash"]);
+    assert!(remove.status.success(), "{}", stderr(&remove));
+    let remove = ark(&db, &["remove", "hash"]);
+    assert!(!remove.status.success());
+    assert!(stderr(&remove).contains("No stored plugin named 'hash'"));
+}
+
+#[test]
+fn test_plugin_inspect_location_does_not_store() {
+    let dir = TempDir::new().unwrap();
+    let db = dir.path().join("ark.db");
+
+    let inspect = ark(&db, &["inspect", HASH_PLUGIN]);
+    assert!(inspect.status.success(), "{}", stderr(&inspect));
+    assert!(stdout(&inspect).contains("sha512"), "{}", stdout(&inspect));
+
+    let list = ark(&db, &["list"]);
+    assert!(stderr(&list).contains("No stored plugins"));
+
+    let missing = ark(&db, &["inspect", "/nonexistent/plugin.wasm"]);
+    assert!(!missing.status.success());
+    assert!(stderr(&missing).contains("Cannot load plugin"));
+}
@@ -0,0 +1,226 @@
+use ark::config::models::PluginDownloadConfig;
+use ark::config::plugins::ArkPlugin;
+use ark::plugins::url::configure_downloads;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// The download settings are global, so tests changing them run one at a time.
+static SETTINGS: Mutex<()> = Mutex::const_new(());
+
+fn plugin(url: &str) -> ArkPlugin {
+    serde_json::from_value(serde_json::json!({
+        "name": "remote",
+        "url": url,
+        "insecure": true,
+    }))
+    .unwrap()
+}
+
+fn wasm() -> ResponseTemplate {
+    let bytes = std::fs::read("tests/testdata/warmup_plugin.wat").unwrap();
+    ResponseTemplate::new(200).set_body_bytes(bytes)
+}
+
+fn settings(retries: u32, read_timeout_ms: u64) -> PluginDownloadConfig {
+    PluginDownloadConfig {
+        retries,
+        read_timeout_ms,
+        retry_backoff_ms: 10,
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_downloads_are_retried() {
+    let _guard = SETTINGS.lock().await;
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .with_priority(1)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(wasm())
+        .mount(&server)
+        .await;
+    let url = format!("{}/plugin.wasm", server.uri());
+
+    configure_downloads(settings(2, 5_000));
+    let loaded = ark::plugins::read_plugin_data(&plugin(&url)).await.unwrap();
+    assert_eq!(loaded.toolset.tools[0].name, "status");
+
+    // Client errors are not retried
+    let missing = format!("{}/missing.wasm", server.uri());
+    server.reset().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let error = ark::plugins::read_plugin_data(&plugin(&missing))
+        .await
+        .err()
+        .unwrap();
+    assert!(format!("{error:#}").contains("404"), "{error:#}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stalled_downloads_time_out() {
+    let _guard = SETTINGS.lock().await;
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(wasm().set_delay(Duration::from_secs(30)))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let url = format!("{}/plugin.wasm", server.uri());
+
+    configure_downloads(settings(1, 200));
+    let started = Instant::now();
+    assert!(ark::plugins::read_plugin_data(&plugin(&url)).await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unchanged_plugins_are_served_from_the_cache() {
+    let _guard = SETTINGS.lock().await;
+    let cache = tempfile::TempDir::new().unwrap();
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(header("if-modified-since", "Wed, 21 Oct 2026 07:28:00 GMT"))
+        .respond_with(ResponseTemplate::new(304))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/etag.wasm"))
+        .respond_with(wasm().insert_header("etag", "\"v1\""))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/dated.wasm"))
+        .respond_with(wasm().insert_header("last-modified", "Wed, 21 Oct 2026 07:28:00 GMT"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    configure_downloads(PluginDownloadConfig {
+        cache_dir: Some(cache.path().to_path_buf()),
+        ..Default::default()
+    });
+    for name in ["etag", "dated"] {
+        let url = format!("{}/{name}.wasm", server.uri());
+        for _ in 0..2 {
+            let loaded = ark::plugins::read_plugin_data(&plugin(&url)).await.unwrap();
+            assert_eq!(loaded.toolset.tools[0].name, "status");
+        }
+    }
+    assert_eq!(std::fs::read_dir(cache.path()).unwrap().count(), 4);
+
+    // Without caching every load downloads the plugin again
+    configure_downloads(PluginDownloadConfig {
+        cache: false,
+        cache_dir: Some(cache.path().to_path_buf()),
+        ..Default::default()
+    });
+    server.reset().await;
+    Mock::given(method("GET"))
+        .respond_with(wasm().insert_header("etag", "\"v1\""))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let url = format!("{}/etag.wasm", server.uri());
+    ark::plugins::read_plugin_data(&plugin(&url)).await.unwrap();
+    configure_downloads(PluginDownloadConfig::default());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_downloads_go_through_the_configured_proxy() {
+    let _guard = SETTINGS.lock().await;
+    let proxy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/plugin.wasm"))
+        .and(header("host", "plugins.example.invalid"))
+        .respond_with(wasm())
+        .expect(1)
+        .mount(&proxy)
+        .await;
+
+    configure_downloads(PluginDownloadConfig {
+        proxy: Some(proxy.uri()),
+        ..Default::default()
+    });
+    let loaded =
+        ark::plugins::read_plugin_data(&plugin("http://plugins.example.invalid/plugin.wasm"))
+            .await
+            .unwrap();
+    assert_eq!(loaded.toolset.tools[0].name, "status");
+
+    configure_downloads(PluginDownloadConfig {
+        proxy: Some("not a url".to_string()),
+        ..Default::default()
+    });
+    let error =
+        ark::plugins::read_plugin_data(&plugin("http://plugins.example.invalid/plugin.wasm"))
+            .await
+            .err()
+            .unwrap();
+    assert!(
+        format!("{error:#}").contains("plugin_downloads.proxy"),
+        "{error:#}"
+    );
+    configure_downloads(PluginDownloadConfig::default());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oversized_plugins_are_rejected() {
+    let _guard = SETTINGS.lock().await;
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(wasm())
+        .expect(1)
+        .mount(&server)
+        .await;
+    let url = format!("{}/plugin.wasm", server.uri());
+
+    configure_downloads(PluginDownloadConfig {
+        cache: false,
+        max_plugin_bytes: 16,
+        ..Default::default()
+    });
+    let error = ark::plugins::read_plugin_data(&plugin(&url))
+        .await
+        .err()
+        .unwrap();
+    assert!(
+        format!("{error:#}").contains("plugin_downloads.max_plugin_bytes"),
+        "{error:#}"
+    );
+
+    let file = url::Url::from_file_path(
+        std::fs::canonicalize("tests/testdata/warmup_plugin.wat").unwrap(),
+    )
+    .unwrap();
+    let error = ark::plugins::read_plugin_data(&plugin(file.as_str()))
+        .await
+        .err()
+        .unwrap();
+    assert!(
+        format!("{error:#}").contains("plugin_downloads.max_plugin_bytes"),
+        "{error:#}"
+    );
+    configure_downloads(PluginDownloadConfig::default());
+}
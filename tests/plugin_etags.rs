@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use ark::config::plugins::ArkPlugin;
+use ark::plugins::ToolSet;
+use ark::server::roles::Role;
+use ark::{
+    server::{
+        auth::{Principal, ProviderKind},
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+};
+use tower::ServiceExt;
+
+fn user(subject: &str) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+async fn register(state: &ArkState, plugin: &str) {
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: plugin.to_string(),
+                ..Default::default()
+            },
+            ToolSet {
+                name: plugin.to_string(),
+                tools: vec![],
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+}
+
+/// Sends a GET, returning the status, ETag and body length.
+async fn get(
+    router: &Router,
+    uri: &str,
+    if_none_match: Option<&HeaderValue>,
+) -> (StatusCode, HeaderValue, usize) {
+    let mut req = Request::builder().uri(uri);
+    if let Some(tag) = if_none_match {
+        req = req.header(header::IF_NONE_MATCH, tag);
+    }
+    let resp = router
+        .clone()
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let etag = resp.headers().get(header::ETAG).expect("ETag").clone();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, etag, body.len())
+}
+
+#[tokio::test]
+async fn test_conditional_get_of_plugin_listing() {
+    let state = Arc::new(ArkState::default());
+    register(&state, "first").await;
+    let router = create_api_router(state.clone()).layer(Extension(user("alice")));
+
+    let (status, etag, len) = get(&router, "/plugins", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(len > 0);
+    assert!(etag.to_str().unwrap().starts_with("W/\""));
+
+    let (status, same, len) = get(&router, "/plugins", Some(&etag)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(same, etag);
+    assert_eq!(len, 0);
+
+    // Tags are per caller, since listings are filtered by owner
+    let bob = create_api_router(state.clone()).layer(Extension(user("bob")));
+    let (status, _, _) = get(&bob, "/plugins", Some(&etag)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Any change to the catalog invalidates the tag
+    register(&state, "second").await;
+    let (status, changed, _) = get(&router, "/plugins", Some(&etag)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(changed, etag);
+
+    let list = HeaderValue::from_str(&format!("\"other\", {}", changed.to_str().unwrap())).unwrap();
+    let (status, _, _) = get(&router, "/plugins", Some(&list)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn test_conditional_get_of_single_plugin() {
+    let state = Arc::new(ArkState::default());
+    register(&state, "first").await;
+    let router = create_api_router(state.clone()).layer(Extension(user("alice")));
+
+    let (status, etag, _) = get(&router, "/plugins/first", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, len) = get(&router, "/plugins/first", Some(&etag)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(len, 0);
+
+    // The listing and a single plugin never share a tag
+    let (status, _, _) = get(&router, "/plugins", Some(&etag)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    state.unregister_plugin("first").await.unwrap();
+    register(&state, "first").await;
+    let (status, _, _) = get(&router, "/plugins/first", Some(&etag)).await;
+    assert_eq!(status, StatusCode::OK);
+}
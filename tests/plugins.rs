@@ -36,6 +36,10 @@ fn overlay_manifest() -> PluginManifest {
             "/overlay".to_string(),
             PathBuf::from("/overlay/path"),
         )])),
+        fuel_limit: None,
+        scratch: None,
+        warmup: false,
+        tools: BTreeMap::new(),
     }
 }
 
@@ -52,7 +56,7 @@ async fn load_no_plugins_registers_builtin() {
     plugins::load_plugins(&cfg, app.clone())
         .await
         .expect("plugin load");
-    let defs = app.plugin_registry.catalog.read().await;
+    let defs = app.plugin_registry.catalog.load();
     assert!(
         defs.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID),
         "expected builtin plugin '{}' to be registered when no plugins are loaded",
@@ -90,7 +94,7 @@ async fn load_wasm_plugin_from_windows_file_path() {
     plugins::load_plugins(&cfg, app.clone())
         .await
         .expect("plugin load");
-    let defs = app.plugin_registry.catalog.read().await;
+    let defs = app.plugin_registry.catalog.load();
     assert!(
         defs.plugin_to_config.contains_key("sample"),
         "expected plugin 'sample' to be registered"
@@ -133,7 +137,7 @@ async fn load_wasm_plugin_from_linux_file_path() {
     plugins::load_plugins(&cfg, app.clone())
         .await
         .expect("plugin load");
-    let defs = app.plugin_registry.catalog.read().await;
+    let defs = app.plugin_registry.catalog.load();
     assert!(
         defs.plugin_to_config.contains_key("sample"),
         "expected plugin 'sample' to be registered"
@@ -210,7 +214,7 @@ async fn load_wasm_plugin_from_http_url() {
     plugins::load_plugins(&cfg, app.clone())
         .await
         .expect("plugin load");
-    let defs = app.plugin_registry.catalog.read().await;
+    let defs = app.plugin_registry.catalog.load();
     assert!(
         defs.plugin_to_config.contains_key("sample-http"),
         "expected plugin 'sample-http' to be registered"
@@ -245,7 +249,7 @@ async fn load_wasm_plugin_from_oci_registry() {
     plugins::load_plugins(&cfg, app.clone())
         .await
         .expect("plugin load");
-    let defs = app.plugin_registry.catalog.read().await;
+    let defs = app.plugin_registry.catalog.load();
     assert!(
         defs.plugin_to_config.contains_key("time"),
         "expected plugin 'time' to be registered"
@@ -258,6 +262,53 @@ async fn load_wasm_plugin_from_oci_registry() {
     );
 }
 
+#[tokio::test]
+/// Tests that file and HTTP plugins pinned to a sha256 only load when the module matches
+async fn load_wasm_plugin_with_pinned_sha256() {
+    use ark::config::plugins::{ArkPlugin, file_path_to_url};
+    use sha2::{Digest, Sha256};
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
+
+    let sample = std::fs::canonicalize(testdata_sample_path()).unwrap();
+    let bytes = std::fs::read(&sample).unwrap();
+    let digest = hex::encode(Sha256::digest(&bytes));
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(bytes))
+        .mount(&server)
+        .await;
+    let urls = [
+        file_path_to_url(&sample).unwrap(),
+        format!("{}/sample.wasm", server.uri()),
+    ];
+
+    for url in urls {
+        let plugin = |sha256: &str| -> ArkPlugin {
+            serde_json::from_value(serde_json::json!({
+                "name": "pinned",
+                "url": url,
+                "insecure": true,
+                "sha256": sha256,
+            }))
+            .unwrap()
+        };
+        assert!(plugins::read_plugin_data(&plugin(&digest)).await.is_ok());
+        assert!(
+            plugins::read_plugin_data(&plugin(&digest.to_uppercase()))
+                .await
+                .is_ok()
+        );
+        let error = plugins::read_plugin_data(&plugin(&"0".repeat(64)))
+            .await
+            .err()
+            .expect("mismatched digest is rejected");
+        assert!(
+            format!("{error:#}").contains("does not match its pinned sha256"),
+            "{error:#}"
+        );
+    }
+}
+
 fn apply_manifest_overlay(manifest: &mut Manifest, overlay: &PluginManifest) {
     if let Some(max_pages) = overlay.memory.as_ref().and_then(|memory| memory.max_pages) {
         manifest.memory.max_pages = Some(max_pages);
@@ -313,6 +364,10 @@ fn test_empty_overlay_preserves_base() {
         config: None,
         allowed_hosts: None,
         allowed_paths: None,
+        fuel_limit: None,
+        scratch: None,
+        warmup: false,
+        tools: BTreeMap::new(),
     };
     apply_manifest_overlay(&mut manifest, &overlay);
 
@@ -336,6 +391,10 @@ fn test_partial_overlay_replaces_only_specified_fields() {
         config: Some(BTreeMap::from([("D".to_string(), "4".to_string())])),
         allowed_hosts: None,
         allowed_paths: None,
+        fuel_limit: None,
+        scratch: None,
+        warmup: false,
+        tools: BTreeMap::new(),
     };
     apply_manifest_overlay(&mut manifest, &overlay);
 
@@ -349,3 +408,33 @@ fn test_partial_overlay_replaces_only_specified_fields() {
         Some(&PathBuf::from("/base/path"))
     );
 }
+
+#[tokio::test]
+/// Tests that readers keep the published snapshot while an update is in progress
+async fn catalog_reads_do_not_wait_for_updates() {
+    let cfg = ArkConfig {
+        plugins: vec![],
+        ..Default::default()
+    };
+    let app = Arc::new(ArkState::default());
+    plugins::load_plugins(&cfg, app.clone())
+        .await
+        .expect("plugin load");
+    let catalog = &app.plugin_registry.catalog;
+    let before = catalog.load();
+
+    let mut update = catalog.write().await;
+    update.plugin_to_config.remove(BUILTIN_PLUGIN_ID);
+    update.generation += 1;
+    // Not visible until the update is done, and reading does not block
+    let during = catalog.load();
+    assert!(during.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID));
+    assert_eq!(during.generation, before.generation);
+    drop(update);
+
+    let after = catalog.load();
+    assert!(!after.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID));
+    assert_eq!(after.generation, before.generation + 1);
+    // Snapshots taken earlier are unaffected
+    assert!(before.plugin_to_config.contains_key(BUILTIN_PLUGIN_ID));
+}
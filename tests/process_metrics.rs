@@ -0,0 +1,59 @@
+#![cfg(any(feature = "prometheus", feature = "otel", feature = "statsd"))]
+
+use std::collections::HashMap;
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+fn sample() -> HashMap<String, DebugValue> {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, ark::metrics::process::collect);
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| (key.key().name().to_string(), value))
+        .collect()
+}
+
+fn gauge(values: &HashMap<String, DebugValue>, name: &str) -> f64 {
+    match values.get(name) {
+        Some(DebugValue::Gauge(value)) => value.0,
+        other => panic!("{} is not a gauge: {:?}", name, other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_runtime_metrics() {
+    let parked = tokio::spawn(std::future::pending::<()>());
+    let values = sample();
+    parked.abort();
+    assert_eq!(gauge(&values, "ark_runtime_workers"), 2.0);
+    assert!(gauge(&values, "ark_runtime_alive_tasks") >= 1.0);
+    assert!(gauge(&values, "ark_runtime_global_queue_depth") >= 0.0);
+    assert!(matches!(
+        values.get("ark_runtime_worker_busy_ms_total"),
+        Some(DebugValue::Counter(_))
+    ));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_process_metrics() {
+    let values = sample();
+    assert!(gauge(&values, "ark_process_resident_memory_bytes") > 0.0);
+    assert!(
+        gauge(&values, "ark_process_virtual_memory_bytes")
+            >= gauge(&values, "ark_process_resident_memory_bytes")
+    );
+    assert!(gauge(&values, "ark_process_threads") >= 1.0);
+    let open = gauge(&values, "ark_process_open_fds");
+    assert!(open >= 3.0, "{}", open);
+    if let Some(DebugValue::Gauge(max)) = values.get("ark_process_max_fds") {
+        assert!(max.0 >= open);
+    }
+    assert!(matches!(
+        values.get("ark_process_cpu_ms_total"),
+        Some(DebugValue::Counter(_))
+    ));
+}
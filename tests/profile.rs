@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use ark::{
+    config::plugins::ArkPlugin,
+    plugins::ToolSet,
+    server::{
+        auth::{Principal, ProviderKind},
+        persist::Database,
+        roles::Role,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn principal() -> Principal {
+    Principal {
+        subject: "user-a".to_string(),
+        email: Some("a@example.com".to_string()),
+        name: Some("User A".to_string()),
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec!["engineering".to_string()],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+fn plugin(name: &str, owner: &str) -> ArkPlugin {
+    ArkPlugin {
+        name: name.to_string(),
+        url: Some("file:///nonexistent.wasm".parse().unwrap()),
+        auth: None,
+        insecure: false,
+        manifest: None,
+        owner: Some(owner.to_string()),
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
+    }
+}
+
+/// Builds the API router for `caller`, backed by a temp DB when
+/// `with_database` is set.
+async fn setup(caller: Option<Principal>, with_database: bool) -> (Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    if with_database {
+        app_state.set_database(Database::with_path(temp_dir.path().join("profile.db")).unwrap());
+    }
+    let tools = ToolSet {
+        name: "tools".into(),
+        tools: vec![],
+    };
+    for (name, owner) in [
+        ("mine", principal().global_id()),
+        ("also-mine", principal().global_id()),
+        ("public", "*/*/*".to_string()),
+    ] {
+        app_state
+            .register_plugin_with_executors(plugin(name, &owner), tools.clone(), vec![])
+            .await
+            .unwrap();
+    }
+
+    let mut router = create_api_router(app_state);
+    if let Some(caller) = caller {
+        router = router.layer(Extension(caller));
+    }
+    (router, temp_dir)
+}
+
+async fn call(router: &Router, method: Method, body: Option<Value>) -> (StatusCode, Value) {
+    let mut builder = Request::builder().method(method).uri("/me");
+    let body = match body {
+        Some(body) => {
+            builder = builder.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    let resp = router
+        .clone()
+        .oneshot(builder.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn test_get_me_describes_the_caller() {
+    let (router, _tmp) = setup(Some(principal()), true).await;
+
+    let (status, me) = call(&router, Method::GET, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["global_id"], principal().global_id());
+    assert_eq!(me["name"], "User A");
+    assert_eq!(me["provider"], "test");
+    assert_eq!(me["groups"], json!(["engineering"]));
+    assert_eq!(me["roles"], json!(["User"]));
+    assert_eq!(me["is_admin"], false);
+    assert!(
+        me["permissions"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("tool.execute"))
+    );
+    assert_eq!(me["owned_plugins"], 2);
+    assert_eq!(me["preferences"], json!({}));
+}
+
+#[tokio::test]
+async fn test_patch_me_merges_preferences() {
+    let (router, _tmp) = setup(Some(principal()), true).await;
+
+    let (status, me) = call(
+        &router,
+        Method::PATCH,
+        Some(json!({"theme": "dark", "page_size": 50})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["preferences"], json!({"theme": "dark", "page_size": 50}));
+
+    // Listed fields are replaced, null resets, others are kept
+    let (status, me) = call(
+        &router,
+        Method::PATCH,
+        Some(json!({"time_zone": "Europe/Bucharest", "page_size": null})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        me["preferences"],
+        json!({"theme": "dark", "time_zone": "Europe/Bucharest"})
+    );
+
+    let (_, me) = call(&router, Method::GET, None).await;
+    assert_eq!(
+        me["preferences"],
+        json!({"theme": "dark", "time_zone": "Europe/Bucharest"})
+    );
+}
+
+#[tokio::test]
+async fn test_patch_me_rejects_invalid_preferences() {
+    let (router, _tmp) = setup(Some(principal()), true).await;
+
+    for patch in [
+        json!({"theme": "neon"}),
+        json!({"page_size": 0}),
+        json!({"page_size": "ten"}),
+        json!({"locale": "en US"}),
+        json!({"font": "comic sans"}),
+    ] {
+        let (status, _) = call(&router, Method::PATCH, Some(patch.clone())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{patch}");
+    }
+
+    let (_, me) = call(&router, Method::GET, None).await;
+    assert_eq!(me["preferences"], json!({}));
+}
+
+#[tokio::test]
+async fn test_me_requires_authentication() {
+    let (router, _tmp) = setup(None, true).await;
+
+    let (status, _) = call(&router, Method::GET, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&router, Method::PATCH, Some(json!({"theme": "dark"}))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_preferences_need_a_database() {
+    let (router, _tmp) = setup(Some(principal()), false).await;
+
+    let (status, me) = call(&router, Method::GET, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["preferences"], json!({}));
+
+    let (status, _) = call(&router, Method::PATCH, Some(json!({"theme": "dark"}))).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
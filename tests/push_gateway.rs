@@ -0,0 +1,69 @@
+#![cfg(feature = "prometheus")]
+
+use ark::config::models::{BasicAuthConfig, ManagementEndpointConfig, PushGatewayConfig};
+use ark::metrics::handler::push_to_gateway;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use wiremock::matchers::{body_string_contains, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// The Prometheus recorder is process-wide, so every case runs in this one test.
+#[tokio::test]
+async fn test_push_final_metrics_to_gateway() {
+    let server = MockServer::start().await;
+    let config = PushGatewayConfig {
+        url: format!("{}/", server.uri()),
+        job: "ark run".to_string(),
+        instance: Some("laptop-01".to_string()),
+        basic: Some(BasicAuthConfig {
+            username: "ark".to_string(),
+            password: "s3cret".to_string(),
+            password_file: None,
+        }),
+    };
+
+    // Nothing to push before the recorder exists
+    assert!(push_to_gateway(&config).await.is_err());
+
+    ark::metrics::init(None);
+    ark::metrics::record_mcp_call("call_tool", 12.0);
+
+    Mock::given(method("PUT"))
+        .and(path("/metrics/job/ark%20run/instance/laptop-01"))
+        .and(header(
+            "authorization",
+            format!("Basic {}", STANDARD.encode("ark:s3cret")).as_str(),
+        ))
+        .and(body_string_contains("ark_mcp_calls_total"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    push_to_gateway(&config).await.unwrap();
+
+    // A rejected push is reported
+    let rejected = PushGatewayConfig {
+        url: server.uri(),
+        job: "other".to_string(),
+        instance: None,
+        basic: None,
+    };
+    Mock::given(method("PUT"))
+        .and(path("/metrics/job/other"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let err = push_to_gateway(&rejected).await.unwrap_err();
+    assert!(err.to_string().contains("400"), "{}", err);
+}
+
+#[test]
+fn test_push_gateway_config_defaults() {
+    let cfg: ManagementEndpointConfig =
+        serde_yaml_ng::from_str("push_gateway:\n  url: http://pushgateway:9091\n").unwrap();
+    let push_gateway = cfg.push_gateway.unwrap();
+    assert_eq!(push_gateway.url, "http://pushgateway:9091");
+    assert_eq!(push_gateway.job, "ark");
+    assert!(push_gateway.instance.is_none());
+    assert!(push_gateway.basic.is_none());
+}
@@ -0,0 +1,390 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+use ark::{
+    config::{
+        ArkConfig,
+        models::{QuotaLimits, QuotasConfig},
+        plugins::ArkPlugin,
+    },
+    plugins::ToolSet,
+    server::{
+        auth::{Principal, ProviderKind},
+        mcp::McpHandler,
+        persist::{Database, PrincipalUsageRecord},
+        reload::ConfigReloader,
+        roles::Role,
+        service::create_api_router,
+    },
+    state::{ArkState, DynExecFuture},
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use rmcp::model::Tool;
+use rmcp::transport::streamable_http_server::{
+    StreamableHttpServerConfig, session::local::LocalSessionManager, tower::StreamableHttpService,
+};
+use serde_json::{Map, Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn user(subject: &str, is_admin: bool) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: if is_admin {
+            vec![Role::Admin]
+        } else {
+            vec![Role::User]
+        },
+        is_admin,
+    }
+}
+
+/// Result of the `echo` tool, whose size is counted as bytes returned.
+fn echoed() -> Value {
+    json!({"content": [{"type": "text", "text": "echo"}]})
+}
+
+/// Builds the state with a shared plugin `p` whose `echo` tool returns
+/// [`echoed`], backed by a temp DB.
+async fn setup() -> (Arc<ArkState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let state = Arc::new(ArkState::default());
+    state.set_database(Database::with_path(temp_dir.path().join("quotas.db")).unwrap());
+
+    let echo: ark::state::ToolExecFn =
+        Arc::new(|_args: Value| Box::pin(async move { Ok(echoed()) }) as DynExecFuture);
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: "p".to_string(),
+                owner: Some("*/*/*".to_string()),
+                ..Default::default()
+            },
+            ToolSet {
+                name: "p".to_string(),
+                tools: vec![Tool {
+                    name: Cow::Borrowed("echo"),
+                    title: None,
+                    description: None,
+                    input_schema: Arc::new(Map::new()),
+                    output_schema: None,
+                    annotations: None,
+                    icons: None,
+                }],
+            },
+            vec![("echo".to_string(), echo)],
+        )
+        .await
+        .unwrap();
+    (state, temp_dir)
+}
+
+async fn send(
+    router: &Router,
+    principal: Option<&Principal>,
+    method: Method,
+    uri: &str,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{}"))
+        .unwrap();
+    let mut router = router.clone();
+    if let Some(principal) = principal {
+        router = router.layer(Extension(principal.clone()));
+    }
+    let resp = router.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn echo(router: &Router, principal: &Principal) -> (StatusCode, Value) {
+    send(
+        router,
+        Some(principal),
+        Method::POST,
+        "/plugins/p/tools/echo",
+    )
+    .await
+}
+
+async fn usage(router: &Router, principal: &Principal, query: &str) -> (StatusCode, Value) {
+    send(
+        router,
+        Some(principal),
+        Method::GET,
+        &format!("/me/usage{query}"),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_usage_is_counted_per_principal() {
+    let (state, _tmp) = setup().await;
+    let router = create_api_router(state);
+    let alice = user("alice", false);
+    let bob = user("bob", false);
+
+    for _ in 0..2 {
+        assert_eq!(echo(&router, &alice).await.0, StatusCode::OK);
+    }
+
+    let (status, mine) = usage(&router, &alice, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(mine["principal"], alice.global_id());
+    assert_eq!(
+        mine["month"],
+        PrincipalUsageRecord::month_of(chrono::Utc::now())
+    );
+    assert_eq!(
+        mine["calls"],
+        json!({"used": 2, "quota": null, "remaining": null})
+    );
+    assert_eq!(
+        mine["bytes_returned"]["used"],
+        2 * echoed().to_string().len()
+    );
+    assert!(mine["compute_ms"]["used"].is_number());
+
+    let (_, theirs) = usage(&router, &bob, "").await;
+    assert_eq!(theirs["calls"]["used"], 0);
+    let (_, earlier) = usage(&router, &alice, "?month=2000-01").await;
+    assert_eq!(earlier["month"], "2000-01");
+    assert_eq!(earlier["calls"]["used"], 0);
+
+    for query in ["?month=2000-13", "?month=2000-1", "?month=last"] {
+        let (status, body) = usage(&router, &alice, query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert_eq!(body["code"], "INVALID_REQUEST");
+    }
+}
+
+#[tokio::test]
+async fn test_quotas_refuse_calls_once_used_up() {
+    let (state, _tmp) = setup().await;
+    let alice = user("alice", false);
+    let bob = user("bob", false);
+    state.set_quotas(QuotasConfig {
+        default: QuotaLimits {
+            calls: 2,
+            ..Default::default()
+        },
+        principals: [(
+            bob.global_id(),
+            QuotaLimits {
+                calls: 3,
+                ..Default::default()
+            },
+        )]
+        .into(),
+    });
+    let router = create_api_router(state);
+
+    for _ in 0..2 {
+        assert_eq!(echo(&router, &alice).await.0, StatusCode::OK);
+    }
+    let (status, body) = echo(&router, &alice).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "QUOTA_EXCEEDED");
+
+    // Refused calls are not counted
+    let (_, mine) = usage(&router, &alice, "").await;
+    assert_eq!(
+        mine["calls"],
+        json!({"used": 2, "quota": 2, "remaining": 0})
+    );
+
+    // A principal's own quotas replace the default
+    for _ in 0..3 {
+        assert_eq!(echo(&router, &bob).await.0, StatusCode::OK);
+    }
+    assert_eq!(echo(&router, &bob).await.0, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_byte_quota_applies_over_mcp() {
+    let (state, _tmp) = setup().await;
+    let alice = user("alice", false);
+    // Used up by the first call
+    state.set_quotas(QuotasConfig {
+        default: QuotaLimits {
+            bytes_returned: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    let mcp_state = state.clone();
+    let mcp = StreamableHttpService::new(
+        move || -> Result<McpHandler, std::io::Error> {
+            Ok(McpHandler {
+                state: mcp_state.clone(),
+            })
+        },
+        Arc::new(LocalSessionManager::default()),
+        StreamableHttpServerConfig {
+            sse_keep_alive: None,
+            stateful_mode: false,
+        },
+    );
+    let router = Router::new()
+        .nest_service("/mcp", mcp)
+        .layer(Extension(alice.clone()));
+
+    let mut replies = Vec::new();
+    for id in 0..2 {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/mcp")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, "application/json, text/event-stream")
+            .body(Body::from(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": "tools/call",
+                    "params": {"name": "echo", "arguments": {}},
+                })
+                .to_string(),
+            ))
+            .unwrap();
+        let resp = router.clone().oneshot(req).await.unwrap();
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let data = body
+            .lines()
+            .find_map(|l| l.strip_prefix("data:"))
+            .expect("SSE data line");
+        replies.push(serde_json::from_str::<Value>(data.trim()).unwrap());
+    }
+
+    assert_eq!(replies[0]["result"]["content"][0]["text"], "echo");
+    assert!(replies[1]["result"].is_null());
+    assert_eq!(replies[1]["error"]["data"]["code"], "QUOTA_EXCEEDED");
+}
+
+#[tokio::test]
+async fn test_usage_report() {
+    let (state, _tmp) = setup().await;
+    let alice = user("alice", false);
+    let bob = user("bob", false);
+    let admin = user("admin", true);
+    state.set_quotas(QuotasConfig {
+        default: QuotaLimits {
+            calls: 10,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let router = create_api_router(state);
+
+    echo(&router, &alice).await;
+    for _ in 0..2 {
+        echo(&router, &bob).await;
+    }
+
+    let (status, report) = send(&router, Some(&admin), Method::GET, "/admin/usage").await;
+    assert_eq!(status, StatusCode::OK);
+    let mut calls: Vec<(String, Value)> = report["principals"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["principal"].as_str().unwrap().to_string(),
+                p["calls"].clone(),
+            )
+        })
+        .collect();
+    calls.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        calls,
+        vec![
+            (
+                alice.global_id(),
+                json!({"used": 1, "quota": 10, "remaining": 9})
+            ),
+            (
+                bob.global_id(),
+                json!({"used": 2, "quota": 10, "remaining": 8})
+            ),
+        ]
+    );
+
+    let (_, earlier) = send(
+        &router,
+        Some(&admin),
+        Method::GET,
+        "/admin/usage?month=2000-01",
+    )
+    .await;
+    assert_eq!(earlier, json!({"month": "2000-01", "principals": []}));
+
+    let (status, _) = send(&router, Some(&alice), Method::GET, "/admin/usage").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_usage_errors() {
+    let (state, _tmp) = setup().await;
+    let router = create_api_router(state);
+    let (status, _) = send(&router, None, Method::GET, "/me/usage").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let router = create_api_router(Arc::new(ArkState::default()));
+    let (status, body) = usage(&router, &user("alice", false), "").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "DATABASE_REQUIRED");
+}
+
+#[tokio::test]
+async fn test_quotas_reload() {
+    let state = Arc::new(ArkState::default());
+    let config = ArkConfig::default();
+    let file = Arc::new(Mutex::new(config.clone()));
+    let source = file.clone();
+    let reloader = ConfigReloader::new(
+        Arc::new(move || {
+            let config = source.lock().unwrap().clone();
+            Box::pin(async move { Ok(config) })
+        }),
+        config,
+    );
+
+    file.lock().unwrap().quotas =
+        Some(serde_yaml_ng::from_str("default:\n  calls: 100\n  compute_ms: 60000\n").unwrap());
+    let report = reloader.reload(&state).await.unwrap();
+    assert_eq!(report.applied, vec!["quotas"]);
+    assert_eq!(
+        state.quota_limits("oidc/*/alice"),
+        QuotaLimits {
+            calls: 100,
+            compute_ms: 60000,
+            bytes_returned: 0,
+        }
+    );
+
+    file.lock().unwrap().quotas = None;
+    reloader.reload(&state).await.unwrap();
+    assert_eq!(state.quota_limits("oidc/*/alice"), QuotaLimits::default());
+}
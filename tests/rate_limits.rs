@@ -0,0 +1,130 @@
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use ark::config::{
+    ArkConfig, McpTransport,
+    models::{ManagementEndpointConfig, McpEndpointConfig, RateLimitConfig, RateLimitsConfig},
+};
+use ark::server::{
+    rate_limit::{RateLimitClass, RateLimiter},
+    service,
+};
+use ark::state::{ApplicationState, ArkState};
+use axum::http::Method;
+
+fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    port
+}
+
+fn limit(per_minute: u32, burst: u32) -> Option<RateLimitConfig> {
+    Some(RateLimitConfig {
+        per_minute,
+        burst: Some(burst),
+    })
+}
+
+#[test]
+fn test_requests_are_classified_and_limited_per_client() {
+    let class = |method: Method, path: &str| RateLimitClass::of(&method, path);
+    assert_eq!(
+        class(Method::POST, "/auth/login/local"),
+        Some(RateLimitClass::Auth)
+    );
+    assert_eq!(class(Method::POST, "/token"), Some(RateLimitClass::Auth));
+    assert_eq!(class(Method::GET, "/auth/status"), None);
+    assert_eq!(
+        class(Method::POST, "/api/v1/plugins"),
+        Some(RateLimitClass::PluginMutation)
+    );
+    assert_eq!(
+        class(Method::DELETE, "/api/plugins/p"),
+        Some(RateLimitClass::PluginMutation)
+    );
+    assert_eq!(
+        class(Method::POST, "/api/v1/plugins/p/tools/t"),
+        Some(RateLimitClass::ToolExecution)
+    );
+    assert_eq!(class(Method::GET, "/api/v1/plugins"), None);
+
+    let limiter = RateLimiter::new(&RateLimitsConfig {
+        tool_execution: limit(60, 2),
+        ..Default::default()
+    });
+    assert!(limiter.is_enabled());
+    assert!(limiter.check(RateLimitClass::ToolExecution, "a").is_none());
+    assert!(limiter.check(RateLimitClass::ToolExecution, "a").is_none());
+    let wait = limiter
+        .check(RateLimitClass::ToolExecution, "a")
+        .expect("burst exhausted");
+    assert!(wait.as_secs_f64() <= 1.0);
+
+    // Budgets are per client, and unconfigured classes are not limited
+    assert!(limiter.check(RateLimitClass::ToolExecution, "b").is_none());
+    for _ in 0..10 {
+        assert!(limiter.check(RateLimitClass::PluginMutation, "a").is_none());
+    }
+    assert!(!RateLimiter::new(&RateLimitsConfig::default()).is_enabled());
+}
+
+#[tokio::test]
+async fn test_management_api_answers_429_over_budget() {
+    let mgmt_bind = format!("127.0.0.1:{}", free_port());
+    let cfg = ArkConfig {
+        transport: Some(McpTransport::StreamableHTTP),
+        management_server: Some(ManagementEndpointConfig {
+            bind_address: Some(mgmt_bind.clone()),
+            disable_console: true,
+            rate_limits: Some(RateLimitsConfig {
+                plugin_mutations: limit(1, 2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        mcp_server: Some(McpEndpointConfig {
+            cors: None,
+            bind_address: Some(format!("127.0.0.1:{}", free_port())),
+            tls: None,
+            shared_sessions: false,
+        }),
+        ..Default::default()
+    };
+
+    let state = Arc::new(ArkState::default());
+    state.set_state(ApplicationState::StartingNetwork);
+    cfg.apply_to_state(state.clone()).await;
+    state.set_transport(McpTransport::StreamableHTTP);
+
+    let srv_state = state.clone();
+    let cfg_clone = cfg.clone();
+    let handle = tokio::spawn(async move {
+        let _ = service::start(&cfg_clone, srv_state).await;
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let client = reqwest::Client::new();
+    let delete = |name: &str| {
+        client
+            .delete(format!("http://{}/api/v1/plugins/{}", mgmt_bind, name))
+            .send()
+    };
+    assert_ne!(delete("a").await.unwrap().status().as_u16(), 429);
+    assert_ne!(delete("b").await.unwrap().status().as_u16(), 429);
+    let refused = delete("c").await.unwrap();
+    assert_eq!(refused.status().as_u16(), 429);
+    assert!(refused.headers().contains_key("retry-after"));
+    let body: serde_json::Value = refused.json().await.unwrap();
+    assert_eq!(body["error"], "Too many requests");
+
+    // Reads are not limited
+    let list = client
+        .get(format!("http://{}/api/v1/plugins", mgmt_bind))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(list.status().as_u16(), 200);
+
+    handle.abort();
+}
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use ark::{
+    server::{persist::Database, service::create_health_router},
+    state::{ApplicationState, ArkState, TlsStatus},
+};
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::Value;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn router(state: Arc<ArkState>) -> Router {
+    create_health_router(state, &None, &None, &None)
+}
+
+async fn get(router: &Router, uri: &str) -> (StatusCode, String) {
+    let resp = router
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn components(router: &Router) -> (StatusCode, Value) {
+    let (status, body) = get(router, "/readyz?verbose").await;
+    let json: Value = serde_json::from_str(&body).unwrap();
+    (status, json)
+}
+
+#[tokio::test]
+async fn test_readyz_verbose_while_starting() {
+    let state = Arc::new(ArkState::default());
+    state.set_state(ApplicationState::LoadingPlugins);
+    let router = router(state);
+
+    let (status, json) = components(&router).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["status"], "not ready");
+    let c = &json["components"];
+    assert_eq!(c["lifecycle"]["status"], "starting");
+    assert_eq!(c["lifecycle"]["state"], "LoadingPlugins");
+    assert_eq!(c["signer"]["status"], "ok");
+    assert_eq!(c["database"]["status"], "disabled");
+    assert_eq!(c["plugins"]["loaded"], 0);
+    assert_eq!(c["auth"]["status"], "disabled");
+    assert_eq!(c["tls"]["status"], "disabled");
+
+    // Without `verbose` the body is unchanged
+    assert_eq!(
+        get(&router, "/readyz").await,
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready".to_string())
+    );
+    assert_eq!(
+        get(&router, "/readyz?verbose=false").await,
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_readyz_verbose_reports_components() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("ark.db");
+    let state = Arc::new(ArkState::default());
+    state.set_database(Database::with_path(&db_path).unwrap());
+    state.record_plugin_failure("broken", "invalid wasm");
+    state.set_tls_status(TlsStatus::Failed("Failed to parse private key".to_string()));
+    state.set_state(ApplicationState::Ready);
+    let router = router(state);
+
+    // Failed plugins and TLS are reported but do not affect readiness
+    let (status, json) = components(&router).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["status"], "ready");
+    let c = &json["components"];
+    assert_eq!(c["lifecycle"]["status"], "ok");
+    assert_eq!(c["database"]["status"], "ok");
+    assert_eq!(c["plugins"]["status"], "degraded");
+    assert_eq!(c["plugins"]["failed"]["broken"], "invalid wasm");
+    assert_eq!(c["tls"]["status"], "degraded");
+    assert_eq!(c["tls"]["error"], "Failed to parse private key");
+
+    // An unreadable database does
+    std::fs::remove_file(&db_path).unwrap();
+    let (status, json) = components(&router).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["components"]["database"]["status"], "error");
+    assert!(json["components"]["database"]["error"].is_string());
+    assert_eq!(
+        get(&router, "/readyz").await,
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_startupz_follows_lifecycle() {
+    let state = Arc::new(ArkState::default());
+    let router = router(state.clone());
+
+    for stage in [
+        ApplicationState::Initializing,
+        ApplicationState::LoadingPlugins,
+        ApplicationState::StartingNetwork,
+    ] {
+        state.set_state(stage);
+        assert_eq!(
+            get(&router, "/startupz").await,
+            (StatusCode::SERVICE_UNAVAILABLE, "not started".to_string())
+        );
+        // Alive throughout startup
+        assert_eq!(get(&router, "/livez").await.0, StatusCode::OK);
+    }
+
+    state.set_state(ApplicationState::Ready);
+    assert_eq!(
+        get(&router, "/startupz").await,
+        (StatusCode::OK, "started".to_string())
+    );
+
+    // Startup stays complete while shutting down
+    state.set_state(ApplicationState::Terminating);
+    assert_eq!(get(&router, "/startupz").await.0, StatusCode::OK);
+}
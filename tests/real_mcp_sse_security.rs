@@ -2,6 +2,7 @@
 /// These tests use the actual rmcp services, not placeholder routes.
 use std::{sync::Arc, time::Duration};
 
+use ark::server::audit::ClientInfo;
 use ark::server::roles::Role;
 use ark::{
     config::{
@@ -51,6 +52,7 @@ async fn create_enabled_auth_state() -> (Arc<auth::AuthState>, Arc<ArkState>, Te
         provider: Some("test-provider".to_string()),
         providers: vec![provider],
         session: Some(SessionConfig::default()),
+        ..Default::default()
     };
 
     // Create temp directory and database
@@ -73,7 +75,11 @@ async fn create_enabled_auth_state() -> (Arc<auth::AuthState>, Arc<ArkState>, Te
 async fn wait_for_session_persistence(auth_state: &Arc<auth::AuthState>, session_id: &str) {
     timeout(Duration::from_secs(2), async {
         loop {
-            if auth_state.get_session(session_id).await.is_some() {
+            if auth_state
+                .get_client_session(session_id, &ClientInfo::default())
+                .await
+                .is_some()
+            {
                 break;
             }
             sleep(Duration::from_millis(25)).await;
@@ -188,6 +194,7 @@ fn create_test_user(user_id: &str) -> auth::Principal {
         name: Some(format!("Test User {}", user_id)),
         provider: "test-provider".to_string(),
         provider_kind: auth::ProviderKind::Oidc,
+        provider_name: None,
         tenant_id: None,
         oid: None,
         picture: None,
@@ -289,7 +296,11 @@ async fn test_real_mcp_streamable_http_security() {
     // Test 2: Authenticated user should be able to access MCP endpoint
     let user1 = create_test_user("user1");
     let session_id = auth_state
-        .put_session(user1.clone(), Duration::from_secs(3600))
+        .put_client_session(
+            user1.clone(),
+            Duration::from_secs(3600),
+            &ClientInfo::default(),
+        )
         .await;
     wait_for_session_persistence(&auth_state, &session_id).await;
     let cookie = format!("ark_session={}", session_id);
@@ -377,7 +388,7 @@ async fn test_real_sse_endpoint_security() {
     // Test 2: Authenticated user should be able to access SSE endpoint
     let user1 = create_test_user("user1");
     let session_id = auth_state
-        .put_session(user1, Duration::from_secs(3600))
+        .put_client_session(user1, Duration::from_secs(3600), &ClientInfo::default())
         .await;
     wait_for_session_persistence(&auth_state, &session_id).await;
     let cookie = format!("ark_session={}", session_id);
@@ -552,7 +563,7 @@ async fn test_cross_user_tool_access_restrictions() {
     // Create session for user1
     let user1 = create_test_user("user1");
     let session_id = auth_state
-        .put_session(user1, Duration::from_secs(3600))
+        .put_client_session(user1, Duration::from_secs(3600), &ClientInfo::default())
         .await;
     wait_for_session_persistence(&auth_state, &session_id).await;
     let cookie = format!("ark_session={}", session_id);
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, LockoutConfig, SessionConfig},
+    server::{
+        auth::{self, AuthState},
+        handlers::session,
+        persist::Database,
+        service::{assign_request_id, create_api_router},
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const PASSWORD: &str = "correct horse battery";
+
+/// Builds a local-provider auth state with `/auth` and `/api` behind the
+/// production `check_auth` and request id middleware.
+async fn setup() -> (Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("rid.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        lockout: LockoutConfig {
+            backoff_base_seconds: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+    auth_state
+        .bootstrap_local_admin("admin", PASSWORD)
+        .await
+        .unwrap();
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state))
+        .nest("/api", create_api_router(app_state))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ))
+        .layer(middleware::from_fn(assign_request_id));
+    (router, temp_dir)
+}
+
+async fn login(router: &Router, request_id: &str, password: &str) -> Response {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/auth/login/local")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header("x-request-id", request_id)
+        .body(Body::from(format!(
+            "username=admin&password={}",
+            urlencoding::encode(password)
+        )))
+        .unwrap();
+    router.clone().oneshot(req).await.unwrap()
+}
+
+async fn get(router: &Router, session_id: &str, uri: &str, request_id: Option<&str>) -> Response {
+    let mut req = Request::builder()
+        .uri(uri)
+        .header(header::COOKIE, format!("ark_session={}", session_id));
+    if let Some(id) = request_id {
+        req = req.header("x-request-id", id);
+    }
+    router
+        .clone()
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn request_id(resp: &Response) -> String {
+    resp.headers()["x-request-id"].to_str().unwrap().to_string()
+}
+
+async fn body_json(resp: Response) -> Value {
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_request_id_header() {
+    let (router, _tmp) = setup().await;
+
+    // A well-formed incoming id is kept
+    let resp = login(&router, "client-req.42_a", "wrong").await;
+    assert_eq!(request_id(&resp), "client-req.42_a");
+
+    // Otherwise a random one is generated
+    let resp = login(&router, "has spaces", "wrong").await;
+    let generated = request_id(&resp);
+    assert_eq!(generated.len(), 32);
+    assert!(generated.chars().all(|c| c.is_ascii_hexdigit()));
+    let resp = login(&router, &"x".repeat(129), "wrong").await;
+    assert_ne!(request_id(&resp), generated);
+    assert_eq!(request_id(&resp).len(), 32);
+}
+
+#[tokio::test]
+async fn test_request_id_in_errors_and_audit() {
+    let (router, _tmp) = setup().await;
+    let resp = login(&router, "failed-login-1", "wrong").await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let session_id = login(&router, "good-login-1", PASSWORD)
+        .await
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|c| c.split(';').next()?.strip_prefix("ark_session="))
+        .unwrap()
+        .to_string();
+
+    // Error bodies quote the request id
+    let resp = get(
+        &router,
+        &session_id,
+        "/api/admin/audit/auth-events?limit=0",
+        Some("bad-limit-1"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(resp).await["request_id"], "bad-limit-1");
+
+    // Audit records can be looked up by request id
+    let resp = get(
+        &router,
+        &session_id,
+        "/api/admin/audit/auth-events?request_id=failed-login-1",
+        None,
+    )
+    .await;
+    let events = body_json(resp).await;
+    let events = events.as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "login_failed");
+    assert_eq!(events[0]["request_id"], "failed-login-1");
+
+    let resp = get(&router, &session_id, "/api/admin/audit/auth-events", None).await;
+    let events = body_json(resp).await;
+    assert_eq!(events[0]["event"], "login");
+    assert_eq!(events[0]["request_id"], "good-login-1");
+}
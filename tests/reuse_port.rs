@@ -0,0 +1,101 @@
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn start(dir: &Path, config: &Path) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_ark"))
+        .args(["--config-file", config.to_str().unwrap()])
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.join("ark.db"))
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+fn livez(port: u16) -> Option<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).ok()?;
+    stream
+        .write_all(b"GET /livez HTTP/1.0\r\nHost: localhost\r\n\r\n")
+        .ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    Some(response)
+}
+
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn test_new_process_binds_while_old_one_runs() {
+    let dir = TempDir::new().unwrap();
+    let (management, mcp) = (free_port(), free_port());
+    let config = dir.path().join("ark.yaml");
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+plugins: []
+listeners:
+  reuse_port: true
+  drain_timeout_secs: 5
+management_server:
+  bind_address: "127.0.0.1:{management}"
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:{mcp}"
+"#
+        ),
+    )
+    .unwrap();
+
+    let mut old = start(dir.path(), &config);
+    assert!(wait_for(
+        || livez(management).is_some_and(|r| r.contains(" 200 "))
+    ));
+
+    // The new process binds the same addresses while the old one still runs
+    let mut new = start(dir.path(), &config);
+    std::thread::sleep(Duration::from_secs(2));
+    assert!(new.try_wait().unwrap().is_none(), "new process exited");
+
+    Command::new("kill")
+        .arg(old.id().to_string())
+        .status()
+        .unwrap();
+    assert!(old.wait().unwrap().success());
+    // Only the new process listens now
+    for _ in 0..5 {
+        let response = livez(management).expect("no listener left");
+        assert!(response.contains(" 200 "), "{response}");
+    }
+
+    Command::new("kill")
+        .arg(new.id().to_string())
+        .status()
+        .unwrap();
+    assert!(new.wait().unwrap().success());
+}
@@ -0,0 +1,526 @@
+use std::io::Read;
+use std::sync::Arc;
+
+use ark::{
+    config::models::{
+        AuthConfig, Groups, IdentityProviderConfig, IdentityProviderKind, SamlConfig, SessionConfig,
+    },
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, ProviderKind},
+        handlers::session,
+        persist::Database,
+        saml::{self, ServiceProvider},
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::{Duration, SecondsFormat, Utc};
+use rsa::RsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+const IDP: &str = "https://idp.test/saml";
+const SP: &str = "https://ark.test/saml";
+const ACS: &str = "https://ark.test/auth/saml/acs";
+const NS_SAML: &str = r#" xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion""#;
+const NS_DS: &str = r#" xmlns:ds="http://www.w3.org/2000/09/xmldsig#""#;
+
+fn saml_config() -> SamlConfig {
+    SamlConfig {
+        idp_entity_id: IDP.to_string(),
+        idp_sso_url: "https://idp.test/sso".to_string(),
+        idp_certificate: "tests/test_keys/saml_idp.crt".to_string(),
+        sp_entity_id: SP.to_string(),
+        acs_url: Some(ACS.to_string()),
+        ..Default::default()
+    }
+}
+
+fn timestamp(offset: Duration) -> String {
+    (Utc::now() + offset).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Parameters of a generated assertion. Timestamps are fixed at creation so
+/// the signed and embedded forms of the assertion are identical.
+struct Claims {
+    request_id: String,
+    audience: String,
+    issued: String,
+    not_before: String,
+    expires: String,
+    groups: Vec<&'static str>,
+}
+
+impl Claims {
+    fn new(request_id: &str) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            audience: SP.to_string(),
+            issued: timestamp(Duration::zero()),
+            not_before: timestamp(Duration::minutes(-1)),
+            expires: timestamp(Duration::minutes(5)),
+            groups: vec!["ark-admins", "eng"],
+        }
+    }
+}
+
+/// Builds an assertion in canonical form, with `signature` inserted after the issuer.
+fn canonical_assertion(claims: &Claims, signature: &str) -> String {
+    let groups: String = claims
+        .groups
+        .iter()
+        .map(|g| format!("<saml:AttributeValue>{}</saml:AttributeValue>", g))
+        .collect();
+    // IdP-initiated assertions answer no request
+    let in_response_to = match claims.request_id.as_str() {
+        "" => String::new(),
+        id => format!(r#" InResponseTo="{}""#, id),
+    };
+    format!(
+        concat!(
+            r#"<saml:Assertion{ns} ID="_assertion1" IssueInstant="{now}" Version="2.0">"#,
+            r#"<saml:Issuer>{idp}</saml:Issuer>{signature}"#,
+            r#"<saml:Subject><saml:NameID>alice@example.com</saml:NameID>"#,
+            r#"<saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer">"#,
+            r#"<saml:SubjectConfirmationData{rid} NotOnOrAfter="{exp}" Recipient="{acs}"></saml:SubjectConfirmationData>"#,
+            r#"</saml:SubjectConfirmation></saml:Subject>"#,
+            r#"<saml:Conditions NotBefore="{nbf}" NotOnOrAfter="{exp}"><saml:AudienceRestriction>"#,
+            r#"<saml:Audience>{aud}</saml:Audience></saml:AudienceRestriction></saml:Conditions>"#,
+            r#"<saml:AttributeStatement>"#,
+            r#"<saml:Attribute Name="displayName"><saml:AttributeValue>Alice &amp; Co</saml:AttributeValue></saml:Attribute>"#,
+            r#"<saml:Attribute Name="groups">{groups}</saml:Attribute>"#,
+            r#"</saml:AttributeStatement></saml:Assertion>"#
+        ),
+        ns = NS_SAML,
+        now = claims.issued,
+        idp = IDP,
+        signature = signature,
+        rid = in_response_to,
+        exp = claims.expires,
+        acs = ACS,
+        nbf = claims.not_before,
+        aud = claims.audience,
+        groups = groups,
+    )
+}
+
+/// Signs the assertion with the test IdP key and returns it as embedded in a
+/// response: the namespace declaration is inherited from the response and
+/// empty elements are self-closing, so verification has to canonicalize.
+fn signed_assertion(claims: &Claims) -> String {
+    let signature = signature(&canonical_assertion(claims, ""), "_assertion1");
+    canonical_assertion(claims, &signature)
+        .replacen(NS_SAML, "", 1)
+        .replace("></saml:SubjectConfirmationData>", "/>")
+}
+
+/// Signs the response, as IdPs set to sign whole responses do.
+fn signed_response(xml: &str) -> String {
+    let doc = roxmltree::Document::parse(xml).unwrap();
+    let signature = signature(
+        &saml::canonicalize(doc.root_element(), None, &[]),
+        "_response1",
+    );
+    let issuer = xml.find("</saml:Issuer>").unwrap() + "</saml:Issuer>".len();
+    let mut signed = xml.to_string();
+    signed.insert_str(issuer, &signature);
+    signed
+}
+
+/// Returns the enveloped signature of the element with `id`, given its
+/// canonical form.
+fn signature(canonical: &str, id: &str) -> String {
+    let digest = STANDARD.encode(Sha256::digest(canonical.as_bytes()));
+    let signed_info = format!(
+        concat!(
+            r#"<ds:SignedInfo{ns}>"#,
+            r#"<ds:CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"></ds:CanonicalizationMethod>"#,
+            r#"<ds:SignatureMethod Algorithm="http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"></ds:SignatureMethod>"#,
+            r##"<ds:Reference URI="#{id}"><ds:Transforms>"##,
+            r#"<ds:Transform Algorithm="http://www.w3.org/2000/09/xmldsig#enveloped-signature"></ds:Transform>"#,
+            r#"<ds:Transform Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"></ds:Transform>"#,
+            r#"</ds:Transforms><ds:DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"></ds:DigestMethod>"#,
+            r#"<ds:DigestValue>{digest}</ds:DigestValue></ds:Reference></ds:SignedInfo>"#
+        ),
+        ns = NS_DS,
+        id = id,
+        digest = digest,
+    );
+    let key = RsaPrivateKey::from_pkcs8_pem(include_str!("test_keys/saml_idp.key")).unwrap();
+    let value = SigningKey::<Sha256>::new(key).sign(signed_info.as_bytes());
+    format!(
+        "<ds:Signature{}>{}<ds:SignatureValue>\n{}\n</ds:SignatureValue></ds:Signature>",
+        NS_DS,
+        signed_info.replace(NS_DS, ""),
+        STANDARD.encode(value.to_vec())
+    )
+}
+
+fn response(request_id: &str, assertion: &str) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n",
+            r#"<samlp:Response xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol"{ns} "#,
+            r#"ID="_response1" Version="2.0" IssueInstant="{now}" Destination="{acs}" InResponseTo="{rid}">"#,
+            r#"<saml:Issuer>{idp}</saml:Issuer>"#,
+            r#"<samlp:Status><samlp:StatusCode Value="urn:oasis:names:tc:SAML:2.0:status:Success"/></samlp:Status>"#,
+            "{assertion}</samlp:Response>"
+        ),
+        ns = NS_SAML,
+        now = timestamp(Duration::zero()),
+        acs = ACS,
+        rid = request_id,
+        idp = IDP,
+        assertion = assertion,
+    )
+}
+
+fn encoded(xml: &str) -> String {
+    STANDARD.encode(xml)
+}
+
+#[test]
+fn test_exclusive_canonicalization() {
+    let xml = r#"<a:root xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:u" b:y="2" z="1"><a:child
+        attr='x&#xA;"y"'/>text &gt; &#x3C;<b:leaf/><!-- dropped --></a:root>"#;
+    let doc = roxmltree::Document::parse(xml).unwrap();
+    assert_eq!(
+        saml::canonicalize(doc.root_element(), None, &[]),
+        concat!(
+            r#"<a:root xmlns:a="urn:a" xmlns:b="urn:b" z="1" b:y="2">"#,
+            r#"<a:child attr="x&#xA;&quot;y&quot;"></a:child>text &gt; &lt;"#,
+            r#"<b:leaf></b:leaf></a:root>"#
+        )
+    );
+
+    // A subtree renders the namespaces it uses, inherited or not
+    let child = doc.root_element().first_element_child().unwrap();
+    assert!(saml::canonicalize(child, None, &[]).starts_with(r#"<a:child xmlns:a="urn:a" attr="#));
+    let with_prefix = saml::canonicalize(child, None, &["unused".to_string()]);
+    assert!(with_prefix.starts_with(r#"<a:child xmlns:a="urn:a" xmlns:unused="urn:u" "#));
+}
+
+#[test]
+fn test_valid_response_accepted() {
+    let sp = ServiceProvider::from_config(&saml_config()).unwrap();
+    let xml = response("_req1", &signed_assertion(&Claims::new("_req1")));
+    let assertion = sp.validate_response(&encoded(&xml), ACS, "_req1").unwrap();
+    assert_eq!(assertion.name_id, "alice@example.com");
+    assert_eq!(assertion.attribute("displayName"), Some("Alice & Co"));
+    assert_eq!(assertion.attributes["groups"], vec!["ark-admins", "eng"]);
+
+    let principal = sp.principal(&assertion, None);
+    assert_eq!(principal.provider_kind, ProviderKind::Saml);
+    assert_eq!(principal.email.as_deref(), Some("alice@example.com"));
+    assert_eq!(principal.name.as_deref(), Some("Alice & Co"));
+    assert_eq!(principal.global_id(), "saml/*/alice@example.com");
+    assert_eq!(principal.groups, vec!["ark-admins", "eng"]);
+
+    // The groups attribute follows groups.claim
+    let groups = Groups {
+        claim: Some("memberOf".to_string()),
+        ..Default::default()
+    };
+    assert!(sp.principal(&assertion, Some(&groups)).groups.is_empty());
+
+    // Repeated groups are listed once, wherever they appear
+    let mut claims = Claims::new("_req2");
+    claims.groups = vec!["eng", "ark-admins", "eng"];
+    let xml = response("_req2", &signed_assertion(&claims));
+    let assertion = sp.validate_response(&encoded(&xml), ACS, "_req2").unwrap();
+    assert_eq!(
+        sp.principal(&assertion, None).groups,
+        vec!["eng", "ark-admins"]
+    );
+}
+
+#[test]
+fn test_invalid_responses_rejected() {
+    let sp = ServiceProvider::from_config(&saml_config()).unwrap();
+    let reject = |xml: String, request_id: &str| {
+        sp.validate_response(&encoded(&xml), ACS, request_id)
+            .unwrap_err()
+            .to_string()
+    };
+    let valid = signed_assertion(&Claims::new("_req1"));
+
+    // Content changed after signing
+    let tampered = valid.replace("alice@example.com", "mallory@example.com");
+    assert!(reject(response("_req1", &tampered), "_req1").contains("digest"));
+
+    // Signature value corrupted
+    let marker = "<ds:SignatureValue>\n";
+    let start = valid.find(marker).unwrap() + marker.len();
+    let mut corrupted = valid.clone();
+    let flipped = if &valid[start..start + 1] == "A" {
+        "B"
+    } else {
+        "A"
+    };
+    corrupted.replace_range(start..start + 1, flipped);
+    assert!(reject(response("_req1", &corrupted), "_req1").contains("signature"));
+
+    // Unsigned assertion
+    let unsigned = canonical_assertion(&Claims::new("_req1"), "");
+    assert!(reject(response("_req1", &unsigned), "_req1").contains("not signed"));
+
+    // Response answering another request
+    assert!(reject(response("_req1", &valid), "_req2").contains("AuthnRequest"));
+
+    // Wrong audience and expired assertions, both properly signed
+    let mut claims = Claims::new("_req1");
+    claims.audience = "https://other.test".to_string();
+    assert!(reject(response("_req1", &signed_assertion(&claims)), "_req1").contains("intended"));
+    let mut claims = Claims::new("_req1");
+    claims.expires = timestamp(Duration::minutes(-10));
+    assert!(
+        reject(response("_req1", &signed_assertion(&claims)), "_req1").contains("confirmation")
+    );
+
+    // A second (forged) assertion next to the signed one
+    let forged = unsigned.replace("_assertion1", "_assertion2");
+    let wrapped = response("_req1", &format!("{}{}", valid, forged));
+    assert!(reject(wrapped, "_req1").contains("exactly one"));
+
+    // DTDs are refused outright
+    let dtd = response("_req1", &valid).replacen("\n", "\n<!DOCTYPE r [<!ENTITY x \"y\">]>\n", 1);
+    assert!(reject(dtd, "_req1").contains("well-formed"));
+}
+
+#[test]
+fn test_signed_response_requires_destination() {
+    let sp = ServiceProvider::from_config(&saml_config()).unwrap();
+    let assertion = signed_assertion(&Claims::new("_req1"));
+    let xml = signed_response(&response("_req1", &assertion));
+    sp.validate_response(&encoded(&xml), ACS, "_req1").unwrap();
+
+    let without = response("_req1", &assertion).replace(&format!(r#" Destination="{ACS}""#), "");
+    let err = sp
+        .validate_response(&encoded(&signed_response(&without)), ACS, "_req1")
+        .unwrap_err();
+    assert!(err.to_string().contains("no destination"), "{err}");
+
+    // Without a response signature the destination is optional
+    sp.validate_response(&encoded(&without), ACS, "_req1")
+        .unwrap();
+}
+
+#[test]
+fn test_captured_assertion_cannot_be_rewrapped() {
+    let sp = ServiceProvider::from_config(&saml_config()).unwrap();
+
+    // An assertion answering another login, in an unsigned response
+    let victim = signed_assertion(&Claims::new("_victim"));
+    let xml = response("_attacker", &victim);
+    assert!(
+        sp.validate_response(&encoded(&xml), ACS, "_attacker")
+            .is_err()
+    );
+
+    // An assertion answering no login is only accepted when the IdP signs
+    // the response that binds it to the request
+    let unsolicited = signed_assertion(&Claims::new(""));
+    let xml = response("_attacker", &unsolicited);
+    let err = sp
+        .validate_response(&encoded(&xml), ACS, "_attacker")
+        .unwrap_err();
+    assert!(err.to_string().contains("subject confirmation"), "{err}");
+
+    let xml = signed_response(&response("_req1", &unsolicited));
+    sp.validate_response(&encoded(&xml), ACS, "_req1").unwrap();
+}
+
+#[test]
+fn test_invalid_config_rejected() {
+    let mut cfg = saml_config();
+    cfg.idp_certificate = "not a certificate".to_string();
+    assert!(ServiceProvider::from_config(&cfg).is_err());
+
+    let mut cfg = saml_config();
+    cfg.idp_sso_url = "idp.test/sso".to_string();
+    assert!(ServiceProvider::from_config(&cfg).is_err());
+
+    // Inline PEM works as well as a path
+    let mut cfg = saml_config();
+    cfg.idp_certificate = include_str!("test_keys/saml_idp.crt").to_string();
+    assert!(ServiceProvider::from_config(&cfg).is_ok());
+}
+
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("saml.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("saml".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "saml".to_string(),
+            kind: Some(IdentityProviderKind::Saml),
+            discovery: false,
+            groups: Some(Groups {
+                admin: Some("ark-admins".to_string()),
+                ..Default::default()
+            }),
+            saml: Some(saml_config()),
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+/// Returns the value of a query parameter in a URL.
+fn query_param(url: &str, name: &str) -> String {
+    let query = url.split_once('?').unwrap().1;
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.into_owned())
+        .unwrap()
+}
+
+async fn post_acs(
+    router: &Router,
+    saml_response: &str,
+    relay_state: &str,
+) -> axum::response::Response {
+    let body = format!(
+        "SAMLResponse={}&RelayState={}",
+        urlencoding::encode(saml_response),
+        urlencoding::encode(relay_state)
+    );
+    router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/auth/saml/acs")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_metadata_endpoint() {
+    let (router, _auth, _tmp) = setup().await;
+    let resp = router
+        .oneshot(
+            Request::builder()
+                .uri("/auth/saml/metadata")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let xml = String::from_utf8_lossy(&body);
+    assert!(xml.contains(&format!(r#"entityID="{}""#, SP)));
+    assert!(xml.contains(&format!(r#"Location="{}""#, ACS)));
+}
+
+#[tokio::test]
+async fn test_sp_initiated_login() {
+    let (router, auth_state, _tmp) = setup().await;
+
+    let resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login?mode=redirect&oauth_params=response_type%3Dcode")
+                .header(header::HOST, "ark.test")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    let location = resp.headers()[header::LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(location.starts_with("https://idp.test/sso?SAMLRequest="));
+
+    // The AuthnRequest is deflated and base64 encoded
+    let deflated = STANDARD
+        .decode(query_param(&location, "SAMLRequest"))
+        .unwrap();
+    let mut request = String::new();
+    flate2::read::DeflateDecoder::new(deflated.as_slice())
+        .read_to_string(&mut request)
+        .unwrap();
+    let doc = roxmltree::Document::parse(&request).unwrap();
+    let request_id = doc.root_element().attribute("ID").unwrap().to_string();
+    assert_eq!(
+        doc.root_element().attribute("AssertionConsumerServiceURL"),
+        Some(ACS)
+    );
+    let relay_state = query_param(&location, "RelayState");
+
+    let saml_response = encoded(&response(
+        &request_id,
+        &signed_assertion(&Claims::new(&request_id)),
+    ));
+    let resp = post_acs(&router, &saml_response, &relay_state).await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        resp.headers()[header::LOCATION],
+        "/authorize?response_type=code"
+    );
+    let cookie = resp.headers()[header::SET_COOKIE].to_str().unwrap();
+    let sid = cookie
+        .split(';')
+        .next()
+        .and_then(|c| c.strip_prefix("ark_session="))
+        .unwrap();
+    let principal = auth_state
+        .get_client_session(sid, &ClientInfo::default())
+        .await
+        .unwrap();
+    assert_eq!(principal.provider_kind, ProviderKind::Saml);
+    assert_eq!(principal.subject, "alice@example.com");
+    assert!(principal.is_admin);
+
+    // The relay state is single-use, so the response cannot be replayed
+    let resp = post_acs(&router, &saml_response, &relay_state).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_unsolicited_response_rejected() {
+    let (router, _auth, _tmp) = setup().await;
+    let saml_response = encoded(&response("_req1", &signed_assertion(&Claims::new("_req1"))));
+    let resp = post_acs(&router, &saml_response, "unknown").await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
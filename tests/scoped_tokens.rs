@@ -0,0 +1,391 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::config::plugins::ArkPlugin;
+use ark::plugins::ToolSet;
+use ark::server::roles::Role;
+use ark::{
+    config::models::{AuthConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, Principal, ProviderKind},
+        csrf,
+        handlers::oauth,
+        mcp::McpHandler,
+        service::create_api_router,
+    },
+    state::{ArkState, DynExecFuture},
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+};
+use rmcp::model::Tool;
+use rmcp::transport::streamable_http_server::{
+    StreamableHttpServerConfig, session::local::LocalSessionManager, tower::StreamableHttpService,
+};
+use serde_json::{Map, Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn user(subject: &str, is_admin: bool) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: if is_admin {
+            vec![Role::Admin]
+        } else {
+            vec![Role::User]
+        },
+        is_admin,
+    }
+}
+
+fn tool(name: &'static str) -> Tool {
+    Tool {
+        name: Cow::Borrowed(name),
+        title: None,
+        description: None,
+        input_schema: Arc::new(Map::new()),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+/// Registers a public plugin whose tools echo their own name.
+async fn register(state: &ArkState, plugin: &str, tools: &[&'static str]) {
+    let executors = tools
+        .iter()
+        .map(|&name| {
+            let exec: ark::state::ToolExecFn = Arc::new(move |_args: Value| {
+                Box::pin(async move { Ok(json!({"content": [{"type": "text", "text": name}]})) })
+                    as DynExecFuture
+            });
+            (name.to_string(), exec)
+        })
+        .collect();
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: plugin.to_string(),
+                owner: Some("*/*/*".to_string()),
+                ..Default::default()
+            },
+            ToolSet {
+                name: plugin.to_string(),
+                tools: tools.iter().map(|&name| tool(name)).collect(),
+            },
+            executors,
+        )
+        .await
+        .unwrap();
+}
+
+/// Builds the API, `/token` and a stateless `/mcp` service behind the
+/// production `check_auth`, with plugins `alpha` (tools `alpha_one`,
+/// `alpha_two`) and `beta` (tool `beta_one`).
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(
+        ark::server::persist::Database::with_path(temp_dir.path().join("scoped.db")).unwrap(),
+    );
+    register(&app_state, "alpha", &["alpha_one", "alpha_two"]).await;
+    register(&app_state, "beta", &["beta_one"]).await;
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: None,
+        providers: vec![],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+
+    let mcp_state = app_state.clone();
+    let mcp = StreamableHttpService::new(
+        move || -> Result<McpHandler, std::io::Error> {
+            Ok(McpHandler {
+                state: mcp_state.clone(),
+            })
+        },
+        Arc::new(LocalSessionManager::default()),
+        StreamableHttpServerConfig {
+            sse_keep_alive: None,
+            stateful_mode: false,
+        },
+    );
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/api", create_api_router(app_state))
+        .nest_service("/mcp", mcp)
+        .merge(oauth::router(auth_state.clone()))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+async fn cookie_for(auth_state: &AuthState, principal: Principal) -> String {
+    let sid = auth_state
+        .put_client_session(principal, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+    format!("ark_session={}", sid)
+}
+
+async fn send(router: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+fn json_request(method: Method, uri: &str, cookie: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header(
+            csrf::CSRF_HEADER,
+            csrf::token_for_session(cookie.strip_prefix("ark_session=").unwrap_or_default()),
+        )
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// Creates an API key with `scopes` for the session in `cookie`.
+async fn create_key(router: &Router, cookie: &str, scopes: Value) -> (StatusCode, Value) {
+    send(
+        router,
+        json_request(
+            Method::POST,
+            "/api/me/tokens",
+            cookie,
+            json!({"name": "scoped", "scopes": scopes}),
+        ),
+    )
+    .await
+}
+
+/// Executes a tool through the management API.
+async fn execute(router: &Router, token: &str, plugin: &str, tool: &str) -> StatusCode {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/api/plugins/{}/tools/{}", plugin, tool))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{}"))
+        .unwrap();
+    send(router, req).await.0
+}
+
+/// Sends one JSON-RPC request to `/mcp` and returns the JSON-RPC reply.
+async fn mcp(router: &Router, token: &str, method: &str, params: Value) -> Value {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/mcp")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::ACCEPT, "application/json, text/event-stream")
+        .body(Body::from(
+            json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}).to_string(),
+        ))
+        .unwrap();
+    let resp = router.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    let data = body
+        .lines()
+        .find_map(|l| l.strip_prefix("data:"))
+        .expect("SSE data line");
+    serde_json::from_str(data.trim()).unwrap()
+}
+
+#[tokio::test]
+async fn test_tool_scoped_api_key() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = cookie_for(&auth_state, user("alice", false)).await;
+
+    let (status, created) = create_key(&router, &cookie, json!(["api", "tool:alpha_one"])).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["scopes"], json!(["api", "tool:alpha_one"]));
+    let token = created["token"].as_str().unwrap().to_string();
+
+    assert_eq!(
+        execute(&router, &token, "alpha", "alpha_one").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        execute(&router, &token, "alpha", "alpha_two").await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        execute(&router, &token, "beta", "beta_one").await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Reads are still allowed; other writes are not
+    let req = Request::builder()
+        .uri("/api/plugins")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&router, req).await.0, StatusCode::OK);
+    let req = Request::builder()
+        .method(Method::DELETE)
+        .uri("/api/plugins/beta")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&router, req).await.0, StatusCode::FORBIDDEN);
+
+    // An unrestricted key may run every tool
+    let (_, created) = create_key(&router, &cookie, json!(["api"])).await;
+    let unrestricted = created["token"].as_str().unwrap();
+    assert_eq!(
+        execute(&router, unrestricted, "alpha", "alpha_two").await,
+        StatusCode::OK
+    );
+}
+
+// `tools/list` checks handlers with `block_in_place`, which needs the
+// multi-threaded runtime
+#[tokio::test(flavor = "multi_thread")]
+async fn test_plugin_scoped_api_key_over_mcp() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = cookie_for(&auth_state, user("alice", false)).await;
+    let (_, created) = create_key(&router, &cookie, json!(["mcp", "plugin:alpha"])).await;
+    let token = created["token"].as_str().unwrap().to_string();
+
+    let listed = mcp(&router, &token, "tools/list", json!({})).await;
+    let mut names: Vec<&str> = listed["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["alpha_one", "alpha_two"]);
+
+    let called = mcp(
+        &router,
+        &token,
+        "tools/call",
+        json!({"name": "alpha_two", "arguments": {}}),
+    )
+    .await;
+    assert_eq!(called["result"]["content"][0]["text"], "alpha_two");
+
+    let refused = mcp(
+        &router,
+        &token,
+        "tools/call",
+        json!({"name": "beta_one", "arguments": {}}),
+    )
+    .await;
+    assert!(refused["result"].is_null());
+    assert!(
+        refused["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("token scope")
+    );
+}
+
+#[tokio::test]
+async fn test_tool_scope_validation() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = cookie_for(&auth_state, user("alice", false)).await;
+
+    for scopes in [
+        json!(["tool:alpha_one"]),
+        json!(["api", "tool:"]),
+        json!(["api", "plugin:"]),
+        json!(["api", "tools:alpha_one"]),
+    ] {
+        let (status, _) = create_key(&router, &cookie, scopes.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "scopes: {}", scopes);
+    }
+}
+
+#[tokio::test]
+async fn test_tool_scoped_client_credentials() {
+    let (router, auth_state, _tmp) = setup().await;
+    let admin = cookie_for(&auth_state, user("root", true)).await;
+    let (status, created) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "runner"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let secret = created["client_secret"].as_str().unwrap().to_string();
+
+    let grant = |scope: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/token")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(format!(
+                "grant_type=client_credentials&client_id=runner&client_secret={}&scope={}",
+                secret,
+                urlencoding::encode(scope)
+            )))
+            .unwrap()
+    };
+    let (status, token) = send(&router, grant("plugin:beta")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(token["error"], "invalid_scope");
+
+    let (status, token) = send(&router, grant("api mcp plugin:beta")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(token["scope"], "api mcp plugin:beta");
+    let token = token["access_token"].as_str().unwrap();
+
+    assert_eq!(
+        execute(&router, token, "beta", "beta_one").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        execute(&router, token, "alpha", "alpha_one").await,
+        StatusCode::FORBIDDEN
+    );
+    let refused = mcp(
+        &router,
+        token,
+        "tools/call",
+        json!({"name": "alpha_one", "arguments": {}}),
+    )
+    .await;
+    assert!(refused["error"].is_object());
+}
@@ -0,0 +1,55 @@
+use ark::config::plugins::PluginManifest;
+use ark::plugins::wasm::WasmHandler;
+use serde_json::json;
+
+fn appender(name: &str, scratch: serde_json::Value) -> WasmHandler {
+    let bytes = std::fs::read("tests/testdata/scratch_plugin.wat").unwrap();
+    let manifest: PluginManifest = serde_json::from_value(json!({ "scratch": scratch })).unwrap();
+    WasmHandler::new(bytes, name, &Some(manifest)).unwrap()
+}
+
+/// Scratch directories of the plugin named `name`.
+fn scratch_dirs(name: &str) -> usize {
+    std::fs::read_dir(std::env::temp_dir())
+        .unwrap()
+        .flatten()
+        .filter(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .starts_with(&format!("ark-scratch-{name}-"))
+        })
+        .count()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scratch_directory_is_emptied_after_each_call() {
+    let handler = appender("scratch-empties", json!({ "max_bytes": 1000 }));
+    assert_eq!(scratch_dirs("scratch-empties"), 1);
+    let append = handler.build_executor("append");
+    // Each call appends to a file that the previous call's cleanup removed
+    for _ in 0..3 {
+        let out = append(json!({})).await.unwrap();
+        assert_eq!(out["content"][0]["text"], "0000600");
+    }
+
+    // Removed once the plugin and its executors are gone
+    drop(append);
+    drop(handler);
+    assert_eq!(scratch_dirs("scratch-empties"), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_call_writing_past_scratch_limit_fails() {
+    let append = appender("scratch-limit", json!({ "max_bytes": 500 })).build_executor("append");
+    let err = append(json!({})).await.unwrap_err();
+    assert!(err.message.contains("scratch limit"), "{}", err.message);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_plugins_without_scratch_cannot_write() {
+    let bytes = std::fs::read("tests/testdata/scratch_plugin.wat").unwrap();
+    let append = WasmHandler::new(bytes, "no-scratch", &None)
+        .unwrap()
+        .build_executor("append");
+    assert!(append(json!({})).await.is_err());
+}
@@ -0,0 +1,147 @@
+use ark::config::{ArkConfig, ConfigError, models::OciAuthentication};
+use serde_json::json;
+use tempfile::TempDir;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn parse(yaml: &str) -> ArkConfig {
+    serde_yaml_ng::from_str(yaml).unwrap()
+}
+
+#[tokio::test]
+async fn test_secrets_are_read_from_files() {
+    let dir = TempDir::new().unwrap();
+    let write = |name: &str, contents: &str| {
+        let p = dir.path().join(name);
+        std::fs::write(&p, contents).unwrap();
+        p.display().to_string()
+    };
+    let client_secret = write("client-secret", "from-file\n");
+    let webhook_secret = write("webhook-secret", "hmac-key");
+    let registry_password = write("registry-password", "  hunter2  \n");
+
+    let mut config = parse(&format!(
+        r#"
+        auth:
+          enabled: true
+          providers:
+            - name: entra
+              client_id: client
+              client_secret: inline
+              client_secret_file: "{client_secret}"
+        webhooks:
+          endpoints:
+            - url: https://hooks.example.com/ark
+              secret_file: "{webhook_secret}"
+        plugins:
+          - name: time
+            url: oci://ghcr.io/example/time:v1
+            config:
+              type: basic
+              username: robot
+              password_file: "{registry_password}"
+        "#
+    ));
+    config.resolve_secrets().await.unwrap();
+
+    let provider = &config.auth.as_ref().unwrap().providers[0];
+    assert_eq!(provider.client_secret.as_deref(), Some("from-file"));
+    let endpoint = &config.webhooks.as_ref().unwrap().endpoints[0];
+    assert_eq!(endpoint.secret.as_deref(), Some("hmac-key"));
+    match &config.plugins[0].auth {
+        Some(OciAuthentication::Basic { password, .. }) => assert_eq!(password, "hunter2"),
+        other => panic!("unexpected auth {other:?}"),
+    }
+
+    // Errors name the setting
+    let mut missing = parse(
+        r#"
+        auth:
+          enabled: true
+          providers:
+            - name: entra
+              client_secret_file: /nonexistent/secret
+        "#,
+    );
+    match missing.resolve_secrets().await {
+        Err(ConfigError::Secret(field, _)) => {
+            assert_eq!(field, "auth.providers[entra].client_secret")
+        }
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_secrets_are_read_from_vault() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/secret/data/ark"))
+        .and(header("X-Vault-Token", "root"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "data": { "client_secret": "from-vault", "token": "registry-token" },
+                "metadata": { "version": 3 },
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/kv/ark"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "bind_password": "from-kv1" }
+        })))
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let token_file = dir.path().join("vault-token");
+    std::fs::write(&token_file, "root\n").unwrap();
+
+    let config_yaml = |client_secret: &str| {
+        format!(
+            r#"
+            vault:
+              address: "{}"
+              token_file: "{}"
+            auth:
+              enabled: true
+              providers:
+                - name: entra
+                  client_secret: "{client_secret}"
+                - name: directory
+                  ldap:
+                    url: ldap://ldap.example.com
+                    bind_password: "vault:kv/ark#bind_password"
+            plugins:
+              - name: time
+                url: oci://ghcr.io/example/time:v1
+                config:
+                  type: bearer
+                  token: "vault:secret/data/ark#token"
+            "#,
+            server.uri(),
+            token_file.display()
+        )
+    };
+
+    let mut config = parse(&config_yaml("vault:secret/data/ark#client_secret"));
+    config.resolve_secrets().await.unwrap();
+    let providers = &config.auth.as_ref().unwrap().providers;
+    assert_eq!(providers[0].client_secret.as_deref(), Some("from-vault"));
+    assert_eq!(
+        providers[1].ldap.as_ref().unwrap().bind_password.as_deref(),
+        Some("from-kv1")
+    );
+    match &config.plugins[0].auth {
+        Some(OciAuthentication::Bearer { token, .. }) => assert_eq!(token, "registry-token"),
+        other => panic!("unexpected auth {other:?}"),
+    }
+
+    let mut unknown_key = parse(&config_yaml("vault:secret/data/ark#nope"));
+    let err = unknown_key.resolve_secrets().await.unwrap_err();
+    assert!(err.to_string().contains("no key 'nope'"), "{err}");
+
+    let mut unknown_path = parse(&config_yaml("vault:secret/data/other#client_secret"));
+    let err = unknown_path.resolve_secrets().await.unwrap_err();
+    assert!(err.to_string().contains("404"), "{err}");
+}
@@ -0,0 +1,417 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::server::roles::Role;
+use ark::{
+    config::models::{AuthConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, Principal, ProviderKind},
+        csrf,
+        handlers::oauth,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn user(subject: &str, is_admin: bool) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: if is_admin {
+            vec![Role::Admin]
+        } else {
+            vec![Role::User]
+        },
+        is_admin,
+    }
+}
+
+/// Builds a DB-backed auth state and a router with the API, `/token` and production `check_auth`.
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(
+        ark::server::persist::Database::with_path(temp_dir.path().join("svc.db")).unwrap(),
+    );
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: None,
+        providers: vec![],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/api", create_api_router(app_state))
+        .merge(oauth::router(auth_state.clone()))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+async fn cookie_for(auth_state: &AuthState, principal: Principal) -> String {
+    let sid = auth_state
+        .put_client_session(principal, Duration::from_secs(600), &ClientInfo::default())
+        .await;
+    format!("ark_session={}", sid)
+}
+
+async fn send(router: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+fn json_request(method: Method, uri: &str, cookie: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header(csrf::CSRF_HEADER, csrf_token(cookie))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn bearer(method: Method, uri: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn client_credentials(router: &Router, client_id: &str, secret: &str) -> (StatusCode, Value) {
+    client_credentials_with_scope(router, client_id, secret, "").await
+}
+
+async fn client_credentials_with_scope(
+    router: &Router,
+    client_id: &str,
+    secret: &str,
+    scope: &str,
+) -> (StatusCode, Value) {
+    send(
+        router,
+        Request::builder()
+            .method(Method::POST)
+            .uri("/token")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(format!(
+                "grant_type=client_credentials&client_id={}&client_secret={}&scope={}",
+                client_id, secret, scope
+            )))
+            .unwrap(),
+    )
+    .await
+}
+
+/// The CSRF token the console sends along with the session cookie.
+fn csrf_token(cookie: &str) -> String {
+    csrf::token_for_session(cookie.strip_prefix("ark_session=").unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_service_account_endpoints_require_admin() {
+    let (router, auth_state, _tmp) = setup().await;
+    let cookie = cookie_for(&auth_state, user("alice", false)).await;
+
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &cookie,
+            json!({"id": "ci"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(
+        &router,
+        Request::builder()
+            .uri("/api/admin/service-accounts")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_client_credentials_flow() {
+    let (router, auth_state, _tmp) = setup().await;
+    let admin = cookie_for(&auth_state, user("root", true)).await;
+
+    let (status, created) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "ci-publisher", "description": "CI pipeline"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["global_id"], "svc/*/ci-publisher");
+    assert_eq!(created["roles"], json!(["User"]));
+    assert_eq!(created["created_by"], "oidc/*/root");
+    let secret = created["client_secret"].as_str().unwrap().to_string();
+
+    // Duplicate ids are rejected
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "ci-publisher"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = client_credentials(&router, "ci-publisher", "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, token) = client_credentials(&router, "ci-publisher", &secret).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(token["scope"], "api mcp");
+    assert!(token["id_token"].is_null());
+    let access_token = token["access_token"].as_str().unwrap().to_string();
+
+    // Client-credentials tokens may call the management API
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &access_token)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // ... but not the admin endpoints
+    let (status, _) = send(
+        &router,
+        bearer(Method::GET, "/api/admin/service-accounts", &access_token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Service accounts cannot mint their own API keys
+    let mut req = json_request(Method::POST, "/api/me/tokens", "", json!({"name": "x"}));
+    req.headers_mut().insert(
+        header::AUTHORIZATION,
+        format!("Bearer {}", access_token).parse().unwrap(),
+    );
+    let (status, _) = send(&router, req).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Rotating the secret invalidates the old one
+    let (status, rotated) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts/ci-publisher/secret",
+            &admin,
+            Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = client_credentials(&router, "ci-publisher", &secret).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = client_credentials(
+        &router,
+        "ci-publisher",
+        rotated["client_secret"].as_str().unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_access_token_scopes_limit_endpoints() {
+    let (router, auth_state, _tmp) = setup().await;
+    let admin = cookie_for(&auth_state, user("root", true)).await;
+    let (_, created) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "ci"}),
+        ),
+    )
+    .await;
+    let secret = created["client_secret"].as_str().unwrap().to_string();
+
+    // Without the "mcp" scope the token cannot call /mcp
+    let (status, token) = client_credentials_with_scope(&router, "ci", &secret, "api").await;
+    assert_eq!(status, StatusCode::OK);
+    let api_only = token["access_token"].as_str().unwrap().to_string();
+    let (status, body) = send(&router, bearer(Method::POST, "/mcp", &api_only)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "UNAUTHENTICATED");
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &api_only)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // ... and without "api" it cannot call the management API
+    let (_, token) = client_credentials_with_scope(&router, "ci", &secret, "mcp").await;
+    let mcp_only = token["access_token"].as_str().unwrap().to_string();
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &mcp_only)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    // Past authentication, this router has no /mcp route
+    let (status, _) = send(&router, bearer(Method::POST, "/mcp", &mcp_only)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_service_account_api_keys() {
+    let (router, auth_state, _tmp) = setup().await;
+    let admin = cookie_for(&auth_state, user("root", true)).await;
+
+    let (status, created) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "deployer", "client_credentials": false}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(created.get("client_secret").is_none());
+    assert_eq!(created["client_credentials"], false);
+
+    let (status, key) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts/deployer/tokens",
+            &admin,
+            json!({"name": "pipeline"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let token = key["token"].as_str().unwrap().to_string();
+
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &token)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let record = auth_state.get_api_key(&token).await.unwrap();
+    assert_eq!(record.principal.global_id(), "svc/*/deployer");
+
+    let (status, listed) = send(
+        &router,
+        Request::builder()
+            .uri("/api/admin/service-accounts/deployer/tokens")
+            .header(header::COOKIE, &admin)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    // Deleting the account revokes its keys
+    let (status, _) = send(
+        &router,
+        Request::builder()
+            .method(Method::DELETE)
+            .uri("/api/admin/service-accounts/deployer")
+            .header(header::COOKIE, &admin)
+            .header(csrf::CSRF_HEADER, csrf_token(&admin))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&router, bearer(Method::GET, "/api/plugins", &token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_service_account_validation() {
+    let (router, auth_state, _tmp) = setup().await;
+    let admin = cookie_for(&auth_state, user("root", true)).await;
+
+    for body in [
+        json!({"id": ""}),
+        json!({"id": "Upper"}),
+        json!({"id": "-leading"}),
+        json!({"id": "has space"}),
+        json!({"id": "ok", "roles": []}),
+    ] {
+        let (status, _) = send(
+            &router,
+            json_request(
+                Method::POST,
+                "/api/admin/service-accounts",
+                &admin,
+                body.clone(),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "body: {}", body);
+    }
+
+    // Repeated roles are stored once, in the order given
+    let (status, created) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts",
+            &admin,
+            json!({"id": "dup-roles", "roles": ["User", "Admin", "User"]}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["roles"], json!(["User", "Admin"]));
+
+    let (status, _) = send(
+        &router,
+        json_request(
+            Method::POST,
+            "/api/admin/service-accounts/missing/tokens",
+            &admin,
+            json!({"name": "x"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,216 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{self, AuthState, Principal, ProviderKind},
+        csrf,
+        handlers::{oauth, session},
+        persist::{Database, SessionRecord},
+        roles::Role,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+/// Builds a local-provider auth state with sessions bound to the
+/// `User-Agent`, `/auth`, `/api`, the OAuth endpoints and the production
+/// `check_auth`.
+async fn setup() -> (Router, Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("binding.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig {
+            bind_user_agent: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state.clone(), None)
+            .await
+            .unwrap(),
+    );
+
+    let auth_clone = auth_state.clone();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state.clone()))
+        .nest("/api", create_api_router(app_state))
+        .merge(oauth::router(auth_state.clone()))
+        .layer(middleware::from_fn(
+            move |req: Request<Body>, next: Next| {
+                let auth_state = auth_clone.clone();
+                async move { auth::check_auth(req, next, Extension(auth_state)).await }
+            },
+        ));
+    (router, auth_state, temp_dir)
+}
+
+fn principal() -> Principal {
+    Principal {
+        subject: "alice".to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "local".to_string(),
+        provider_kind: ProviderKind::Local,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+/// Creates a session for `principal()` bound to the `ua-1` user agent.
+async fn bound_session(auth_state: &AuthState) -> String {
+    let client = ClientInfo {
+        ip: None,
+        user_agent: Some("ua-1".to_string()),
+    };
+    auth_state
+        .put_client_session(principal(), Duration::from_secs(3600), &client)
+        .await
+}
+
+async fn send(router: &Router, req: Request<Body>) -> Response {
+    router.clone().oneshot(req).await.unwrap()
+}
+
+fn get(uri: &str, session_id: &str, user_agent: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .header(header::COOKIE, format!("ark_session={}", session_id))
+        .header(header::USER_AGENT, user_agent)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn location(resp: &Response) -> &str {
+    resp.headers()[header::LOCATION].to_str().unwrap()
+}
+
+async fn list_tokens(router: &Router, session_id: &str, user_agent: &str) -> StatusCode {
+    let req = Request::builder()
+        .uri("/api/me/tokens")
+        .header(header::COOKIE, format!("ark_session={}", session_id))
+        .header(header::USER_AGENT, user_agent)
+        .body(Body::empty())
+        .unwrap();
+    router.clone().oneshot(req).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_bound_session_is_rejected_from_another_user_agent() {
+    let (router, auth_state, _tmp) = setup().await;
+    let session_id = bound_session(&auth_state).await;
+
+    assert_eq!(
+        list_tokens(&router, &session_id, "ua-1").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        list_tokens(&router, &session_id, "ua-2").await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let resp = send(&router, get("/auth/status", &session_id, "ua-1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = send(&router, get("/auth/status", &session_id, "ua-2")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_unbound_session_is_rejected_when_binding_is_enabled() {
+    let (router, _auth_state, tmp) = setup().await;
+    // A session created before binding was enabled
+    let expiry_utc = chrono::Utc::now() + chrono::Duration::hours(1);
+    Database::with_path(tmp.path().join("binding.db"))
+        .unwrap()
+        .save_session_record_async(SessionRecord {
+            session_id: "unbound".to_string(),
+            principal: principal(),
+            expiry_utc,
+            expiry_epoch: expiry_utc.timestamp(),
+            is_admin: false,
+            client_binding: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        list_tokens(&router, "unbound", "ua-1").await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_authorize_rejects_session_from_another_user_agent() {
+    let (router, auth_state, _tmp) = setup().await;
+    let session_id = bound_session(&auth_state).await;
+    let uri = format!(
+        "/authorize?response_type=code&client_id=test-client&redirect_uri={}&code_challenge=challenge&code_challenge_method=S256",
+        urlencoding::encode("http://localhost/callback")
+    );
+
+    let resp = send(&router, get(&uri, &session_id, "ua-1")).await;
+    assert!(
+        location(&resp).starts_with("http://localhost/callback?code="),
+        "{}",
+        location(&resp)
+    );
+
+    let resp = send(&router, get(&uri, &session_id, "ua-2")).await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    assert!(location(&resp).starts_with("/auth/login?"));
+}
+
+#[tokio::test]
+async fn test_device_page_rejects_session_from_another_user_agent() {
+    let (router, auth_state, _tmp) = setup().await;
+    let session_id = bound_session(&auth_state).await;
+
+    let resp = send(&router, get("/device", &session_id, "ua-1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = send(&router, get("/device", &session_id, "ua-2")).await;
+    assert!(location(&resp).starts_with("/auth/login?"));
+
+    let decision = |user_agent: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/device")
+            .header(header::COOKIE, format!("ark_session={}", session_id))
+            .header(header::USER_AGENT, user_agent)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(format!(
+                "user_code=ABCD-EFGH&action=approve&csrf_token={}",
+                csrf::token_for_session(&session_id)
+            )))
+            .unwrap()
+    };
+    let resp = send(&router, decision("ua-2")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{AuthState, Principal, ProviderKind},
+        persist::Database,
+        roles::Role,
+    },
+    state::ArkState,
+};
+use tempfile::TempDir;
+
+async fn setup(max_sessions_per_user: Option<usize>) -> (Arc<AuthState>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("limit.db")).unwrap());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("local".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "local".to_string(),
+            discovery: false,
+            ..Default::default()
+        }],
+        session: Some(SessionConfig {
+            max_sessions_per_user,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    (auth_state, temp_dir)
+}
+
+fn principal(subject: &str) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "local".to_string(),
+        provider_kind: ProviderKind::Local,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+async fn sign_in(auth: &AuthState, subject: &str) -> String {
+    auth.put_client_session(
+        principal(subject),
+        Duration::from_secs(3600),
+        &ClientInfo::default(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_oldest_session_is_ended_at_the_limit() {
+    let (auth, _tmp) = setup(Some(2)).await;
+    let first = sign_in(&auth, "alice").await;
+    let second = sign_in(&auth, "alice").await;
+    let bob = sign_in(&auth, "bob").await;
+    let third = sign_in(&auth, "alice").await;
+
+    assert!(
+        auth.get_client_session(&first, &ClientInfo::default())
+            .await
+            .is_none()
+    );
+    assert!(
+        auth.get_client_session(&second, &ClientInfo::default())
+            .await
+            .is_some()
+    );
+    assert!(
+        auth.get_client_session(&third, &ClientInfo::default())
+            .await
+            .is_some()
+    );
+    // Sessions of other users do not count towards the limit
+    assert!(
+        auth.get_client_session(&bob, &ClientInfo::default())
+            .await
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_sessions_are_unlimited_by_default() {
+    let (auth, _tmp) = setup(None).await;
+    let mut sessions = Vec::new();
+    for _ in 0..5 {
+        sessions.push(sign_in(&auth, "alice").await);
+    }
+    for session in &sessions {
+        assert!(
+            auth.get_client_session(session, &ClientInfo::default())
+                .await
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
+    server::{
+        audit::ClientInfo,
+        auth::{AuthState, Principal, ProviderKind, TokenResponse},
+        crypto::Cipher,
+        persist::{Database, SessionRefreshRecord},
+        roles::Role,
+    },
+    state::ArkState,
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::json;
+use tempfile::TempDir;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const KEY: [u8; 32] = [7u8; 32];
+
+async fn setup(server: &MockServer, session: SessionConfig) -> (Arc<AuthState>, Database, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    let database = Database::with_path(temp_dir.path().join("refresh.db")).unwrap();
+    app_state.set_database(database.clone());
+
+    let auth_cfg = AuthConfig {
+        enabled: true,
+        provider: Some("test".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "test".to_string(),
+            client_id: "test-client".to_string(),
+            client_secret: Some("test-secret".to_string()),
+            authority: server.uri(),
+            discovery: false,
+            jwks_uri: Some(format!("{}/jwks", server.uri())),
+            authorization_endpoint: Some(format!("{}/authorize", server.uri())),
+            token_endpoint: Some(format!("{}/token", server.uri())),
+            ..Default::default()
+        }],
+        session: Some(session),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(auth_cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    (auth_state, database, temp_dir)
+}
+
+fn session_config() -> SessionConfig {
+    SessionConfig {
+        encryption_key: Some(STANDARD.encode(KEY)),
+        ..Default::default()
+    }
+}
+
+async fn principal(auth: &AuthState) -> Principal {
+    let authority = auth.active.read().await.as_ref().unwrap().authority.clone();
+    Principal {
+        subject: "alice".to_string(),
+        email: Some("alice@example.com".to_string()),
+        name: None,
+        picture: None,
+        provider: authority,
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+fn tokens(refresh_token: Option<&str>, expires_in: u64) -> TokenResponse {
+    TokenResponse {
+        id_token: String::new(),
+        access_token: "access".to_string(),
+        refresh_token: refresh_token.map(str::to_string),
+        expires_in: Some(expires_in),
+    }
+}
+
+#[test]
+fn test_cipher_round_trip() {
+    let cipher = Cipher::from_base64(&STANDARD.encode(KEY)).unwrap();
+    let sealed = cipher.seal(b"secret").unwrap();
+    assert!(!sealed.contains("secret"));
+    assert_ne!(sealed, cipher.seal(b"secret").unwrap(), "nonce is random");
+    assert_eq!(cipher.open(&sealed).unwrap(), b"secret");
+
+    // Another key or a modified value does not open
+    assert!(Cipher::ephemeral().open(&sealed).is_err());
+    let mut bytes = STANDARD.decode(&sealed).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    assert!(cipher.open(&STANDARD.encode(bytes)).is_err());
+
+    assert!(Cipher::from_base64(&STANDARD.encode([0u8; 16])).is_err());
+    assert!(Cipher::from_base64("not base64!").is_err());
+}
+
+#[tokio::test]
+async fn test_session_without_refresh_token_lasts_an_hour() {
+    let server = MockServer::start().await;
+    let (auth, database, _tmp) = setup(&server, session_config()).await;
+
+    let (sid, ttl) = auth
+        .put_session_with_tokens(
+            principal(&auth).await,
+            &tokens(None, 300),
+            &ClientInfo::default(),
+        )
+        .await;
+    assert_eq!(ttl.as_secs(), 3600);
+    assert!(
+        database
+            .get_session_refresh_async(sid)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_session_renewed_with_refresh_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("grant_type=refresh_token"))
+        .and(body_string_contains("refresh_token=rt-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "access-2",
+            "refresh_token": "rt-2",
+            "expires_in": 3600,
+            "token_type": "Bearer"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let (auth, database, _tmp) = setup(&server, session_config()).await;
+
+    // The access token has already expired, so the first lookup renews
+    let (sid, ttl) = auth
+        .put_session_with_tokens(
+            principal(&auth).await,
+            &tokens(Some("rt-1"), 0),
+            &ClientInfo::default(),
+        )
+        .await;
+    assert_eq!(ttl.as_secs(), SessionConfig::default().max_lifetime_seconds);
+    let stored = database
+        .get_session_refresh_async(sid.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!stored.refresh_token_enc.contains("rt-1"));
+
+    let renewed = auth
+        .get_client_session(&sid, &ClientInfo::default())
+        .await
+        .expect("session renewed");
+    assert_eq!(renewed.subject, "alice");
+
+    // The rotated refresh token replaces the old one; not due again for an hour
+    let stored = database
+        .get_session_refresh_async(sid.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        auth.cipher.open(&stored.refresh_token_enc).unwrap(),
+        b"rt-2"
+    );
+    assert!(stored.renew_after_epoch > chrono::Utc::now().timestamp() + 3000);
+    assert!(
+        auth.get_client_session(&sid, &ClientInfo::default())
+            .await
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_failed_renewal_ends_session() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "invalid_grant"
+        })))
+        .mount(&server)
+        .await;
+    let (auth, database, _tmp) = setup(&server, session_config()).await;
+
+    let (sid, _) = auth
+        .put_session_with_tokens(
+            principal(&auth).await,
+            &tokens(Some("revoked"), 0),
+            &ClientInfo::default(),
+        )
+        .await;
+    assert!(
+        auth.get_client_session(&sid, &ClientInfo::default())
+            .await
+            .is_none()
+    );
+    assert!(
+        database
+            .get_session_record_async(sid.clone())
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        database
+            .get_session_refresh_async(sid)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_disabled() {
+    let server = MockServer::start().await;
+    let (auth, database, _tmp) = setup(
+        &server,
+        SessionConfig {
+            refresh: false,
+            ..session_config()
+        },
+    )
+    .await;
+    let (sid, ttl) = auth
+        .put_session_with_tokens(
+            principal(&auth).await,
+            &tokens(Some("rt"), 0),
+            &ClientInfo::default(),
+        )
+        .await;
+    assert_eq!(ttl.as_secs(), 3600);
+    assert!(
+        database
+            .get_session_refresh_async(sid)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_token_sealed_under_other_key_ends_session() {
+    let server = MockServer::start().await;
+    let (auth, database, _tmp) = setup(&server, session_config()).await;
+
+    let (sid, _) = auth
+        .put_session_with_tokens(
+            principal(&auth).await,
+            &tokens(None, 0),
+            &ClientInfo::default(),
+        )
+        .await;
+    database
+        .save_session_refresh_async(SessionRefreshRecord {
+            session_id: sid.clone(),
+            refresh_token_enc: Cipher::ephemeral().seal(b"rt").unwrap(),
+            renew_after_epoch: 0,
+        })
+        .await
+        .unwrap();
+
+    assert!(
+        auth.get_client_session(&sid, &ClientInfo::default())
+            .await
+            .is_none()
+    );
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
@@ -0,0 +1,147 @@
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts an instance using the database in `dir`, returning it with its
+/// MCP port once it accepts connections.
+async fn start(dir: &Path, name: &str) -> (Server, u16) {
+    let port = free_port();
+    let config = format!("{name}.yaml");
+    std::fs::write(
+        dir.join(&config),
+        format!(
+            r#"
+plugins: []
+management_server:
+  bind_address: "127.0.0.1:{}"
+mcp_server:
+  bind_address: "127.0.0.1:{port}"
+  shared_sessions: true
+"#,
+            free_port()
+        ),
+    )
+    .unwrap();
+    let server = Server(
+        Command::new(env!("CARGO_BIN_EXE_ark"))
+            .current_dir(dir)
+            .args(["--config-file", &config])
+            .env("ARK_TRANSPORT", "streamable-http")
+            .env("ARK_DB_PATH", dir.join("ark.db"))
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_err()
+    {
+        assert!(Instant::now() < deadline, "server did not start");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    (server, port)
+}
+
+/// Posts a JSON-RPC message to `/mcp`, returning the response with its
+/// body.
+async fn post(
+    port: u16,
+    session: Option<&str>,
+    message: Value,
+) -> (StatusCode, Option<String>, String) {
+    let mut request = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/mcp"))
+        .header("accept", "application/json, text/event-stream")
+        .json(&message);
+    if let Some(session) = session {
+        request = request.header("mcp-session-id", session);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status();
+    let session = response
+        .headers()
+        .get("mcp-session-id")
+        .map(|v| v.to_str().unwrap().to_string());
+    let body = tokio::time::timeout(Duration::from_secs(10), response.text())
+        .await
+        .expect("response did not end")
+        .unwrap();
+    (status, session, body)
+}
+
+#[tokio::test]
+async fn test_session_continues_on_another_instance() {
+    let dir = TempDir::new().unwrap();
+    let (_a, port_a) = start(dir.path(), "a").await;
+    let (_b, port_b) = start(dir.path(), "b").await;
+
+    let (status, session, _) = post(
+        port_a,
+        None,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {"name": "test", "version": "1.0"}
+            }
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let session = session.expect("no session id");
+    let (status, _, _) = post(
+        port_a,
+        Some(&session),
+        json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    // The other instance has not seen the session, but continues it
+    let (status, _, body) = post(
+        port_b,
+        Some(&session),
+        json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(
+        body.contains(r#""id":2"#) && body.contains(r#""tools""#),
+        "{body}"
+    );
+
+    // Unknown sessions are still refused
+    let (status, _, _) = post(
+        port_b,
+        Some("unknown"),
+        json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,298 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use ark::config::plugins::ArkPlugin;
+use ark::errors::ErrorCode;
+use ark::plugins::ToolSet;
+use ark::server::roles::Role;
+use ark::{
+    server::{
+        auth::{Principal, ProviderKind},
+        persist::{Database, SessionRecord},
+        service::create_api_router,
+        usage::{ROLLUP_RETENTION, ToolCall, record_tool_call},
+    },
+    state::{ArkState, DynExecFuture},
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{Method, StatusCode, header},
+};
+use rmcp::model::Tool;
+use serde_json::{Map, Value, json};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+/// Records a call to a tool of plugin `p`.
+async fn record(
+    state: &ArkState,
+    tool: &str,
+    principal: Option<&str>,
+    is_error: bool,
+    latency_ms: f64,
+) {
+    let call = ToolCall {
+        plugin_id: "p",
+        tool,
+        owner: None,
+        principal,
+        args: &json!({}),
+        error: is_error.then_some(ErrorCode::ToolFailed),
+        latency_ms,
+        bytes_returned: 0,
+    };
+    record_tool_call(state, call).await;
+}
+
+fn user(subject: &str, is_admin: bool) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: if is_admin {
+            vec![Role::Admin]
+        } else {
+            vec![Role::User]
+        },
+        is_admin,
+    }
+}
+
+fn tool(name: &'static str) -> Tool {
+    Tool {
+        name: Cow::Borrowed(name),
+        title: None,
+        description: None,
+        input_schema: Arc::new(Map::new()),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+/// Registers a plugin whose tools fail when their name ends in `_fail`.
+async fn register(state: &ArkState, plugin: &str, owner: &str, tools: &[&'static str]) {
+    let executors = tools
+        .iter()
+        .map(|&name| {
+            let exec: ark::state::ToolExecFn = Arc::new(move |_args: Value| {
+                Box::pin(async move {
+                    if name.ends_with("_fail") {
+                        Err(rmcp::ErrorData::internal_error("boom", None))
+                    } else {
+                        Ok(json!({"content": [{"type": "text", "text": name}]}))
+                    }
+                }) as DynExecFuture
+            });
+            (name.to_string(), exec)
+        })
+        .collect();
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: plugin.to_string(),
+                owner: Some(owner.to_string()),
+                ..Default::default()
+            },
+            ToolSet {
+                name: plugin.to_string(),
+                tools: tools.iter().map(|&name| tool(name)).collect(),
+            },
+            executors,
+        )
+        .await
+        .unwrap();
+}
+
+async fn send(
+    router: &Router,
+    principal: &Principal,
+    method: Method,
+    uri: &str,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{}"))
+        .unwrap();
+    let resp = router
+        .clone()
+        .layer(Extension(principal.clone()))
+        .oneshot(req)
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_stats_by_owner() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Database::with_path(temp_dir.path().join("stats.db")).unwrap();
+    let state = Arc::new(ArkState::default());
+    state.set_database(db.clone());
+
+    let alice = user("alice", true);
+    let alice_id = alice.global_id();
+    register(&state, "shared", "*/*/*", &["echo"]).await;
+    register(&state, "personal", &alice_id, &["mine_ok", "mine_fail"]).await;
+    let router = create_api_router(state.clone());
+
+    for uri in [
+        "/plugins/personal/tools/mine_ok",
+        "/plugins/personal/tools/mine_ok",
+        "/plugins/personal/tools/mine_fail",
+        "/plugins/shared/tools/echo",
+    ] {
+        send(&router, &alice, Method::POST, uri).await;
+    }
+    let expiry = chrono::Utc::now() + chrono::Duration::hours(1);
+    db.save_session_record_async(SessionRecord {
+        session_id: "s1".to_string(),
+        principal: alice.clone(),
+        expiry_utc: expiry,
+        expiry_epoch: expiry.timestamp(),
+        is_admin: true,
+        client_binding: None,
+    })
+    .await
+    .unwrap();
+
+    let (status, stats) = send(&router, &alice, Method::GET, "/admin/stats").await;
+    assert_eq!(status, StatusCode::OK);
+    let owners = stats["owners"].as_array().unwrap();
+    assert_eq!(owners.len(), 2);
+
+    let mine = owners
+        .iter()
+        .find(|o| o["owner"] == alice_id.as_str())
+        .unwrap();
+    assert_eq!(mine["plugins"], 1);
+    assert_eq!(mine["tools"], 2);
+    assert_eq!(mine["calls"], 3);
+    assert_eq!(mine["errors"], 1);
+    assert!((mine["error_rate"].as_f64().unwrap() - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(mine["active_sessions"], 1);
+    let usage = mine["usage"].as_array().unwrap();
+    let failing = usage.iter().find(|u| u["tool"] == "mine_fail").unwrap();
+    assert_eq!(failing["plugin"], "personal");
+    assert_eq!(
+        (failing["calls"].clone(), failing["errors"].clone()),
+        (json!(1), json!(1))
+    );
+    assert!(failing["last_call"].is_string());
+
+    let shared = owners.iter().find(|o| o["owner"] == "*/*/*").unwrap();
+    assert_eq!(shared["calls"], 1);
+    assert_eq!(shared["errors"], 0);
+    assert_eq!(shared["active_sessions"], 0);
+
+    let totals = &stats["totals"];
+    assert_eq!(totals["plugins"], 2);
+    assert_eq!(totals["tools"], 3);
+    assert_eq!(totals["calls"], 4);
+    assert_eq!(totals["errors"], 1);
+    assert_eq!(totals["active_sessions"], 1);
+
+    // The counters are persisted
+    let persisted = db.list_tool_usage_async().await.unwrap();
+    assert_eq!(persisted.iter().map(|r| r.calls).sum::<u64>(), 4);
+}
+
+#[tokio::test]
+async fn test_stats_requires_admin() {
+    let temp_dir = TempDir::new().unwrap();
+    let state = Arc::new(ArkState::default());
+    state.set_database(Database::with_path(temp_dir.path().join("stats.db")).unwrap());
+    let router = create_api_router(state);
+
+    let (status, _) = send(&router, &user("bob", false), Method::GET, "/admin/stats").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Without a database there is nothing to report
+    let router = create_api_router(Arc::new(ArkState::default()));
+    let (status, _) = send(&router, &user("alice", true), Method::GET, "/admin/stats").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_top_usage() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Database::with_path(temp_dir.path().join("stats.db")).unwrap();
+    let state = Arc::new(ArkState::default());
+    state.set_database(db.clone());
+    let router = create_api_router(state.clone());
+    let admin = user("admin", true);
+
+    for _ in 0..3 {
+        record(&state, "echo", Some("oidc/*/alice"), false, 2.0).await;
+    }
+    record(&state, "echo", None, false, 3.0).await;
+    record(&state, "slow", Some("oidc/*/bob"), true, 700.0).await;
+
+    let (status, top) = send(&router, &admin, Method::GET, "/admin/stats/top?window=1h").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(top["window"], "1h");
+    assert!(top["since"].is_string());
+    assert_eq!(
+        top["top_tools"],
+        json!([
+            {"plugin": "p", "tool": "echo", "calls": 4, "errors": 0},
+            {"plugin": "p", "tool": "slow", "calls": 1, "errors": 1},
+        ])
+    );
+    assert_eq!(
+        top["slowest_tools"],
+        json!([
+            {"plugin": "p", "tool": "slow", "p95_latency_ms": 1000.0, "calls": 1},
+            {"plugin": "p", "tool": "echo", "p95_latency_ms": 5.0, "calls": 4},
+        ])
+    );
+    // Unauthenticated calls are not attributed to anyone
+    assert_eq!(
+        top["top_principals"],
+        json!([
+            {"principal": "oidc/*/alice", "calls": 3, "errors": 0},
+            {"principal": "oidc/*/bob", "calls": 1, "errors": 1},
+        ])
+    );
+
+    // Calls are rolled up once and merged into the existing hour
+    assert_eq!(
+        db.rollup_tool_calls_async(ROLLUP_RETENTION).await.unwrap(),
+        0
+    );
+    record(&state, "echo", Some("oidc/*/bob"), false, 2.0).await;
+    assert_eq!(
+        db.rollup_tool_calls_async(ROLLUP_RETENTION).await.unwrap(),
+        1
+    );
+    let (_, top) = send(&router, &admin, Method::GET, "/admin/stats/top?limit=1").await;
+    assert_eq!(top["window"], "24h");
+    assert_eq!(top["top_tools"][0]["calls"], 5);
+    assert_eq!(top["top_tools"].as_array().unwrap().len(), 1);
+    assert_eq!(top["top_principals"][0]["principal"], "oidc/*/alice");
+    assert_eq!(top["top_principals"].as_array().unwrap().len(), 1);
+
+    for uri in [
+        "/admin/stats/top?window=5x",
+        "/admin/stats/top?window=31d",
+        "/admin/stats/top?window=0h",
+        "/admin/stats/top?limit=0",
+        "/admin/stats/top?limit=101",
+    ] {
+        let (status, _) = send(&router, &admin, Method::GET, uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+    }
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "statsd")]
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use ark::config::models::{ManagementEndpointConfig, StatsdConfig};
+use ark::metrics::statsd::StatsdRecorder;
+
+/// Binds a local "agent" and returns it with the matching exporter config.
+fn agent(dogstatsd: bool) -> (UdpSocket, StatsdConfig) {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let config = StatsdConfig {
+        address: socket.local_addr().unwrap().to_string(),
+        prefix: Some("ark.".to_string()),
+        dogstatsd,
+        tags: vec!["env:test".to_string()],
+    };
+    (socket, config)
+}
+
+fn receive(socket: &UdpSocket, count: usize) -> Vec<String> {
+    let mut buf = [0u8; 1024];
+    (0..count)
+        .map(|_| {
+            let n = socket.recv(&mut buf).unwrap();
+            String::from_utf8(buf[..n].to_vec()).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_dogstatsd_lines_carry_tags() {
+    let (socket, config) = agent(true);
+    let recorder = StatsdRecorder::new(&config).unwrap();
+    metrics::with_local_recorder(&recorder, || {
+        ark::metrics::record_tool_metrics("echo", "say,hi", None, 12.5);
+        metrics::gauge!("ark_queue_depth").set(-2.0);
+    });
+
+    let lines = receive(&socket, 4);
+    assert_eq!(
+        lines[0],
+        "ark.ark_tool_calls_total:1|c|#plugin:echo,tool:say_hi,env:test"
+    );
+    assert_eq!(
+        lines[1],
+        "ark.ark_tool_latency_ms:12.5|h|#plugin:echo,tool:say_hi,env:test"
+    );
+    // Negative gauges are reset first so the sign isn't read as a delta
+    assert_eq!(lines[2], "ark.ark_queue_depth:0|g|#env:test");
+    assert_eq!(lines[3], "ark.ark_queue_depth:-2|g|#env:test");
+}
+
+#[test]
+fn test_plain_statsd_drops_tags() {
+    let (socket, config) = agent(false);
+    let recorder = StatsdRecorder::new(&config).unwrap();
+    metrics::with_local_recorder(&recorder, || {
+        ark::metrics::record_mcp_call("call_tool", 3.0);
+    });
+
+    let lines = receive(&socket, 2);
+    assert_eq!(lines[0], "ark.ark_mcp_calls_total:1|c");
+    assert_eq!(lines[1], "ark.ark_mcp_latency_ms:3|ms");
+}
+
+#[test]
+fn test_statsd_config() {
+    let cfg: ManagementEndpointConfig = serde_yaml_ng::from_str("statsd: {}\n").unwrap();
+    let statsd = cfg.statsd.unwrap();
+    assert_eq!(statsd.address, "127.0.0.1:8125");
+    assert!(statsd.dogstatsd);
+    assert!(statsd.prefix.is_none());
+    assert!(statsd.tags.is_empty());
+
+    let bad = StatsdConfig {
+        address: "no-port".to_string(),
+        ..Default::default()
+    };
+    assert!(StatsdRecorder::new(&bad).is_err());
+}
@@ -0,0 +1,94 @@
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixDatagram;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+/// Reads notifications until one contains `line`, returning everything read.
+fn notified(socket: &UnixDatagram, line: &str) -> String {
+    let mut seen = String::new();
+    let mut buf = [0u8; 1024];
+    while !seen.lines().any(|l| l == line) {
+        let n = socket
+            .recv(&mut buf)
+            .unwrap_or_else(|e| panic!("no {line} after {seen:?}: {e}"));
+        seen.push_str(&String::from_utf8_lossy(&buf[..n]));
+    }
+    seen
+}
+
+#[test]
+fn test_notify_and_socket_activation() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("ark.yaml");
+    // The management address cannot be bound: the passed socket must be used
+    std::fs::write(
+        &config,
+        r#"
+plugins: []
+management_server:
+  bind_address: "192.0.2.1:9"
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:0"
+"#,
+    )
+    .unwrap();
+    let notify = UnixDatagram::bind(dir.path().join("notify.sock")).unwrap();
+    notify
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let fd = listener.as_raw_fd();
+
+    // As systemd does: the socket as descriptor 3, named, for this process
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!(
+            "exec 3<&{fd}; LISTEN_PID=$$ LISTEN_FDS=1 LISTEN_FDNAMES=management exec \"$0\" \"$@\""
+        ))
+        .arg(env!("CARGO_BIN_EXE_ark"))
+        .args(["--config-file", config.to_str().unwrap()])
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.path().join("ark.db"))
+        .env("NOTIFY_SOCKET", dir.path().join("notify.sock"))
+        .stdout(Stdio::null());
+    // SAFETY: fcntl is async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC);
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().unwrap();
+    drop(listener);
+
+    let startup = notified(&notify, "READY=1");
+    assert!(startup.contains("STATUS=LoadingPlugins"), "{startup}");
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .write_all(b"GET /livez HTTP/1.0\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.0 200"), "{response}");
+
+    Command::new("kill")
+        .arg(child.id().to_string())
+        .status()
+        .unwrap();
+    notified(&notify, "STOPPING=1");
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,74 @@
+;; Plugin with one tool, `append`, which appends 600 bytes to `out.bin` in
+;; its first preopened directory (the scratch directory) and reports the
+;; file's size. Used by tests/scratch.rs.
+(module
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_filestat_get"
+    (func $fd_filestat_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_close"
+    (func $fd_close (param i32) (result i32)))
+  (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+  (import "extism:host/env" "store_u8" (func $store_u8 (param i64 i32)))
+  (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+
+  (memory (export "memory") 1)
+
+  ;; 0: iovec { buf = 1024, len = 600 }
+  ;; 16: bytes written, 20: opened fd, 64: filestat (size at 96)
+  (data (i32.const 0) "\00\04\00\00\58\02\00\00")
+  (data (i32.const 32) "out.bin")
+  (data (i32.const 128)
+    "{\"tools\":[{\"name\":\"append\",\"inputSchema\":{\"type\":\"object\"}}]}")
+  ;; `size` is filled in by `call`, padded to 7 digits
+  (data (i32.const 256) "{\"content\":[{\"type\":\"text\",\"text\":\"0000000\"}]}")
+
+  ;; Sets the output to `len` bytes of linear memory at `ptr`.
+  (func $output (param $ptr i32) (param $len i32)
+    (local $out i64)
+    (local $i i32)
+    (local.set $out (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $out) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (call $output_set (local.get $out) (i64.extend_i32_u (local.get $len))))
+
+  (func (export "describe") (result i32)
+    (call $output (i32.const 128) (i32.const 61))
+    (i32.const 0))
+
+  (func (export "call") (result i32)
+    (local $fd i32)
+    (local $size i64)
+    (local $pos i32)
+    ;; O_CREAT, all rights, FDFLAGS_APPEND
+    (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 32) (i32.const 7)
+          (i32.const 1) (i64.const 0x3fffffff) (i64.const 0) (i32.const 1) (i32.const 20))
+      (then (return (i32.const 1))))
+    (local.set $fd (i32.load (i32.const 20)))
+    (if (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 16))
+      (then (return (i32.const 2))))
+    (if (call $fd_filestat_get (local.get $fd) (i32.const 64))
+      (then (return (i32.const 3))))
+    (drop (call $fd_close (local.get $fd)))
+    ;; Write the size as decimal digits, last digit first
+    (local.set $size (i64.load (i32.const 96)))
+    (local.set $pos (i32.const 297))
+    (block $done
+      (loop $digits
+        (br_if $done (i32.lt_u (local.get $pos) (i32.const 291)))
+        (i64.store8 (local.get $pos)
+          (i64.add (i64.const 48) (i64.rem_u (local.get $size) (i64.const 10))))
+        (local.set $size (i64.div_u (local.get $size) (i64.const 10)))
+        (local.set $pos (i32.sub (local.get $pos) (i32.const 1)))
+        (br $digits)))
+    (call $output (i32.const 256) (i32.const 46))
+    (i32.const 0))
+)
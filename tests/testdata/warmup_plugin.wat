@@ -0,0 +1,44 @@
+;; Plugin with one tool, `status`, which reports "warm" once the `warmup`
+;; export has run and "cold" before. Used by tests/warmup.rs.
+(module
+  (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+  (import "extism:host/env" "store_u8" (func $store_u8 (param i64 i32)))
+  (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+
+  (memory (export "memory") 1)
+  (global $warm (mut i32) (i32.const 0))
+
+  (data (i32.const 0)
+    "{\"tools\":[{\"name\":\"status\",\"inputSchema\":{\"type\":\"object\"}}]}")
+  (data (i32.const 128) "{\"content\":[{\"type\":\"text\",\"text\":\"cold\"}]}")
+  (data (i32.const 256) "{\"content\":[{\"type\":\"text\",\"text\":\"warm\"}]}")
+
+  ;; Sets the output to `len` bytes of linear memory at `ptr`.
+  (func $output (param $ptr i32) (param $len i32)
+    (local $out i64)
+    (local $i i32)
+    (local.set $out (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $out) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (call $output_set (local.get $out) (i64.extend_i32_u (local.get $len))))
+
+  (func (export "describe") (result i32)
+    (call $output (i32.const 0) (i32.const 61))
+    (i32.const 0))
+
+  (func (export "warmup") (result i32)
+    (global.set $warm (i32.const 1))
+    (i32.const 0))
+
+  (func (export "call") (result i32)
+    (if (global.get $warm)
+      (then (call $output (i32.const 256) (i32.const 43)))
+      (else (call $output (i32.const 128) (i32.const 43))))
+    (i32.const 0))
+)
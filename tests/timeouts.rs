@@ -0,0 +1,130 @@
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child, #[allow(dead_code)] TempDir);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts ark with the given `listeners` settings, returning it with the
+/// management port once it accepts connections.
+async fn start(listeners: &str) -> (Server, u16) {
+    let dir = TempDir::new().unwrap();
+    let port = free_port();
+    std::fs::write(
+        dir.path().join("ark.yaml"),
+        format!(
+            r#"
+plugins: []
+listeners:
+{listeners}
+management_server:
+  bind_address: "127.0.0.1:{port}"
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:{}"
+"#,
+            free_port()
+        ),
+    )
+    .unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_ark"))
+        .current_dir(dir.path())
+        .args(["--config-file", "ark.yaml"])
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.path().join("ark.db"))
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let server = Server(child, dir);
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while TcpStream::connect(("127.0.0.1", port)).await.is_err() {
+        assert!(Instant::now() < deadline, "server did not start");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    (server, port)
+}
+
+/// Reads until the server closes the connection, returning what was read,
+/// or `None` if it is still open after `limit`.
+async fn read_until_closed(stream: &mut TcpStream, limit: Duration) -> Option<String> {
+    let mut received = Vec::new();
+    tokio::time::timeout(limit, async {
+        let mut buf = [0u8; 4096];
+        loop {
+            match stream.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+            }
+        }
+    })
+    .await
+    .ok()?;
+    Some(String::from_utf8_lossy(&received).into_owned())
+}
+
+#[tokio::test]
+async fn test_incomplete_headers_are_timed_out() {
+    let (_server, port) = start("  header_timeout_secs: 1").await;
+
+    // A complete request is answered
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream
+        .write_all(b"GET /livez HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let response = read_until_closed(&mut stream, Duration::from_secs(5))
+        .await
+        .expect("connection was not closed");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+    // Headers that never end get the connection closed
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream
+        .write_all(b"GET /livez HTTP/1.1\r\nHost: localhost\r\n")
+        .await
+        .unwrap();
+    let started = Instant::now();
+    assert!(
+        read_until_closed(&mut stream, Duration::from_secs(10))
+            .await
+            .is_some(),
+        "slow client kept its connection"
+    );
+    assert!(started.elapsed() >= Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn test_idle_connections_are_closed() {
+    let (_server, port) = start("  header_timeout_secs: 0\n  idle_timeout_secs: 1").await;
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream
+        .write_all(b"GET /livez HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    // The keep-alive connection is closed once idle, after the response
+    let response = read_until_closed(&mut stream, Duration::from_secs(10))
+        .await
+        .expect("idle connection was kept open");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+}
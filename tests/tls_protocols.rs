@@ -0,0 +1,174 @@
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rcgen::CertifiedKey;
+use rustls::pki_types::ServerName;
+use tempfile::TempDir;
+use tokio_rustls::TlsConnector;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Writes a self-signed certificate and a configuration with `tls_settings`
+/// added to the top-level `tls` block, returning the certificate.
+fn setup(dir: &Path, port: u16, tls_settings: &str) -> rustls::pki_types::CertificateDer<'static> {
+    let CertifiedKey { cert, signing_key } =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    std::fs::write(dir.join("server.pem"), cert.pem()).unwrap();
+    std::fs::write(dir.join("server.key"), signing_key.serialize_pem()).unwrap();
+    std::fs::write(
+        dir.join("ark.yaml"),
+        format!(
+            "plugins: []\n\
+             management_server:\n  bind_address: \"127.0.0.1:{port}\"\n\
+             mcp_server:\n  bind_address: \"127.0.0.1:{}\"\n\
+             tls:\n  key: server.key\n  cert: server.pem\n{tls_settings}",
+            free_port()
+        ),
+    )
+    .unwrap();
+    cert.der().clone()
+}
+
+fn command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ark"));
+    command
+        .current_dir(dir)
+        .args(["--config-file", "ark.yaml"])
+        .env("ARK_TRANSPORT", "streamable-http")
+        .env("ARK_DB_PATH", dir.join("ark.db"));
+    command
+}
+
+async fn start(dir: &Path, port: u16) -> Server {
+    let server = Server(command(dir).stdout(Stdio::null()).spawn().unwrap());
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            return server;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("server did not start");
+}
+
+/// Completes a handshake offering only `versions` and `suites` (all when
+/// empty), returning the negotiated suite.
+async fn handshake(
+    port: u16,
+    cert: &rustls::pki_types::CertificateDer<'static>,
+    versions: &[&'static rustls::SupportedProtocolVersion],
+    suites: &[rustls::CipherSuite],
+) -> Result<rustls::CipherSuite, std::io::Error> {
+    let mut provider = rustls::crypto::aws_lc_rs::default_provider();
+    if !suites.is_empty() {
+        provider
+            .cipher_suites
+            .retain(|s| suites.contains(&s.suite()));
+    }
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert.clone()).unwrap();
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(versions)
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await?;
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await?;
+    Ok(stream
+        .get_ref()
+        .1
+        .negotiated_cipher_suite()
+        .unwrap()
+        .suite())
+}
+
+#[tokio::test]
+async fn test_tls13_only_refuses_tls12_clients() {
+    let dir = TempDir::new().unwrap();
+    let port = free_port();
+    let cert = setup(dir.path(), port, "  min_version: \"1.3\"\n");
+    let _server = start(dir.path(), port).await;
+
+    assert!(
+        handshake(port, &cert, &[&rustls::version::TLS12], &[])
+            .await
+            .is_err()
+    );
+    handshake(port, &cert, &[&rustls::version::TLS13], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_cipher_suites_restrict_negotiation() {
+    let dir = TempDir::new().unwrap();
+    let port = free_port();
+    let cert = setup(
+        dir.path(),
+        port,
+        "  cipher_suites:\n    - TLS13_AES_256_GCM_SHA384\n",
+    );
+    let _server = start(dir.path(), port).await;
+    let tls13 = [&rustls::version::TLS13];
+
+    assert!(
+        handshake(
+            port,
+            &cert,
+            &tls13,
+            &[rustls::CipherSuite::TLS13_AES_128_GCM_SHA256]
+        )
+        .await
+        .is_err()
+    );
+    let suite = handshake(port, &cert, &tls13, &[]).await.unwrap();
+    assert_eq!(suite, rustls::CipherSuite::TLS13_AES_256_GCM_SHA384);
+}
+
+#[test]
+fn test_invalid_tls_settings_fail_startup() {
+    for (settings, error) in [
+        (
+            "  cipher_suites: [TLS_NOT_A_SUITE]\n",
+            "Unknown cipher suite TLS_NOT_A_SUITE",
+        ),
+        (
+            "  min_version: \"1.3\"\n  max_version: \"1.2\"\n",
+            "min_version 1.3 is above max_version 1.2",
+        ),
+        (
+            "  max_version: \"1.2\"\n  cipher_suites: [TLS13_AES_256_GCM_SHA384]\n",
+            "No cipher suite is usable",
+        ),
+    ] {
+        let dir = TempDir::new().unwrap();
+        setup(dir.path(), free_port(), settings);
+        let output = command(dir.path()).output().unwrap();
+        assert!(!output.status.success(), "{settings}");
+        let logs = String::from_utf8_lossy(&output.stdout);
+        assert!(logs.contains(error), "{settings}: {logs}");
+    }
+}
@@ -0,0 +1,132 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use ark::config::plugins::ArkPlugin;
+use ark::plugins::registry::PluginHandler;
+use ark::plugins::{ToolSet, build_executors};
+use ark::state::ArkState;
+use rmcp::model::Tool;
+use serde_json::{Map, Value, json};
+
+fn tool(name: &'static str) -> Tool {
+    Tool {
+        name: Cow::Borrowed(name),
+        title: None,
+        description: Some(Cow::Borrowed("Upstream description")),
+        input_schema: Arc::new(Map::new()),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+/// Registers a plugin whose tools return the name they were called with.
+async fn register(state: &ArkState, plugin: Value) {
+    let plugin: ArkPlugin = serde_json::from_value(plugin).unwrap();
+    let toolset = ToolSet {
+        name: plugin.name.clone(),
+        tools: vec![tool("search"), tool("fetch"), tool("debug_dump")],
+    };
+    let handler: PluginHandler =
+        Arc::new(|input: Value| Box::pin(async move { Ok(input["tool"].clone()) }));
+    let executors = build_executors(&toolset, handler).await;
+    state
+        .register_plugin_with_executors(plugin, toolset, executors)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_tools_are_renamed_changed_and_hidden_as_configured() {
+    let state = ArkState::default();
+    register(
+        &state,
+        json!({
+            "name": "upstream",
+            "url": "file:///upstream.wasm",
+            "manifest": {
+                "tools": {
+                    "search": {
+                        "name": "upstream_search",
+                        "title": "Search",
+                        "description": "Searches the upstream index.",
+                        "annotations": { "readOnlyHint": true }
+                    },
+                    "debug_dump": { "hidden": true },
+                    "missing": { "hidden": true }
+                }
+            }
+        }),
+    )
+    .await;
+
+    let catalog = state.plugin_registry.catalog.load();
+    let mut names: Vec<&String> = catalog.tool_to_def.keys().collect();
+    names.sort();
+    assert_eq!(names, ["fetch", "upstream_search"]);
+    assert_eq!(catalog.tool_to_plugin["upstream_search"], "upstream");
+    assert!(!catalog.tool_to_handler.contains_key("debug_dump"));
+
+    let search = &catalog.tool_to_def["upstream_search"];
+    assert_eq!(search.title.as_deref(), Some("Search"));
+    assert_eq!(
+        search.description.as_deref(),
+        Some("Searches the upstream index.")
+    );
+    assert_eq!(
+        search.annotations.as_ref().unwrap().read_only_hint,
+        Some(true)
+    );
+    // Tools without overrides are left alone
+    assert_eq!(
+        catalog.tool_to_def["fetch"].description.as_deref(),
+        Some("Upstream description")
+    );
+    drop(catalog);
+
+    // The plugin is still called with its own name for the tool
+    let registry = &state.plugin_registry;
+    assert_eq!(
+        registry.call("upstream_search", &json!({})).await.unwrap(),
+        "search"
+    );
+    assert!(registry.call("search", &json!({})).await.is_err());
+    assert!(registry.call("debug_dump", &json!({})).await.is_err());
+}
+
+#[tokio::test]
+async fn test_only_allowed_and_not_denied_tools_are_exposed() {
+    let state = ArkState::default();
+    register(
+        &state,
+        json!({
+            "name": "upstream",
+            "url": "file:///upstream.wasm",
+            "tools_allow": ["search", "debug_dump"],
+            "tools_deny": ["debug_dump"],
+            "manifest": { "tools": { "search": { "name": "find" } } }
+        }),
+    )
+    .await;
+
+    let catalog = state.plugin_registry.catalog.load();
+    let names: Vec<&String> = catalog.tool_to_def.keys().collect();
+    assert_eq!(names, ["find"]);
+    assert_eq!(catalog.tool_to_handler.len(), 1);
+    drop(catalog);
+    assert_eq!(
+        state
+            .plugin_registry
+            .call("find", &json!({}))
+            .await
+            .unwrap(),
+        "search"
+    );
+    assert!(
+        state
+            .plugin_registry
+            .call("fetch", &json!({}))
+            .await
+            .is_err()
+    );
+}
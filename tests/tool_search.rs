@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use ark::config::plugins::ArkPlugin;
+use ark::plugins::ToolSet;
+use ark::server::roles::Role;
+use ark::{
+    server::{
+        auth::{Principal, ProviderKind},
+        authz::ToolScope,
+        service::create_api_router,
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::StatusCode,
+};
+use rmcp::model::Tool;
+use serde_json::{Map, Value};
+use tower::ServiceExt;
+
+fn user(subject: &str) -> Principal {
+    Principal {
+        subject: subject.to_string(),
+        email: None,
+        name: None,
+        picture: None,
+        provider: "test".to_string(),
+        provider_kind: ProviderKind::Oidc,
+        provider_name: None,
+        tenant_id: None,
+        oid: None,
+        groups: vec![],
+        roles: vec![Role::User],
+        is_admin: false,
+    }
+}
+
+fn tool(name: &'static str, description: &'static str) -> Tool {
+    Tool {
+        name: Cow::Borrowed(name),
+        title: None,
+        description: Some(Cow::Borrowed(description)),
+        input_schema: Arc::new(Map::new()),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+async fn register(state: &ArkState, plugin: &str, owner: &str, tools: Vec<Tool>) {
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: plugin.to_string(),
+                owner: Some(owner.to_string()),
+                ..Default::default()
+            },
+            ToolSet {
+                name: plugin.to_string(),
+                tools,
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+}
+
+/// Returns the (plugin, tool) pairs found for `uri`.
+async fn search(router: Router, uri: &str) -> Vec<(String, String)> {
+    let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let resp = router.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let results: Vec<Value> = serde_json::from_slice(&body).unwrap();
+    results
+        .iter()
+        .map(|r| {
+            (
+                r["plugin"].as_str().unwrap().to_string(),
+                r["tool"]["name"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+fn pair(plugin: &str, tool: &str) -> (String, String) {
+    (plugin.to_string(), tool.to_string())
+}
+
+#[tokio::test]
+async fn test_search_matches_names_then_descriptions() {
+    let state = Arc::new(ArkState::default());
+    register(
+        &state,
+        "hash",
+        "*/*/*",
+        vec![
+            tool("sha256", "Computes a SHA-256 digest"),
+            tool("md5", "Computes an MD5 hash"),
+        ],
+    )
+    .await;
+    register(
+        &state,
+        "time",
+        "*/*/*",
+        vec![tool("hash_time", "Returns the current time")],
+    )
+    .await;
+    let router = create_api_router(state).layer(Extension(user("alice")));
+
+    // Name matches first, then description matches, case-insensitively
+    assert_eq!(
+        search(router.clone(), "/tools?q=HASH").await,
+        vec![pair("time", "hash_time"), pair("hash", "md5")]
+    );
+    assert_eq!(
+        search(router.clone(), "/tools?q=digest").await,
+        vec![pair("hash", "sha256")]
+    );
+    assert!(search(router.clone(), "/tools?q=nothing").await.is_empty());
+    assert_eq!(search(router, "/tools").await.len(), 3);
+}
+
+#[tokio::test]
+async fn test_search_only_finds_visible_tools() {
+    let state = Arc::new(ArkState::default());
+    register(
+        &state,
+        "shared",
+        "*/*/*",
+        vec![tool("echo", "Echoes input")],
+    )
+    .await;
+    register(
+        &state,
+        "private",
+        "oidc/*/bob",
+        vec![tool("echo_secret", "Echoes a secret")],
+    )
+    .await;
+    let router = create_api_router(state);
+
+    assert_eq!(
+        search(
+            router.clone().layer(Extension(user("alice"))),
+            "/tools?q=echo"
+        )
+        .await,
+        vec![pair("shared", "echo")]
+    );
+    assert_eq!(
+        search(
+            router.clone().layer(Extension(user("bob"))),
+            "/tools?q=echo"
+        )
+        .await,
+        vec![pair("private", "echo_secret"), pair("shared", "echo")]
+    );
+
+    // A credential restricted to one tool only finds that tool
+    let scope = ToolScope::from_scopes(["api", "tool:echo_secret"]).unwrap();
+    assert_eq!(
+        search(
+            router.layer(Extension(user("bob"))).layer(Extension(scope)),
+            "/tools?q=echo"
+        )
+        .await,
+        vec![pair("private", "echo_secret")]
+    );
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "otel-traces")]
+
+use std::sync::Arc;
+
+use ark::config::ArkConfig;
+use ark::plugins;
+use ark::state::{ApplicationState, ArkState};
+use axum::http::{HeaderMap, HeaderValue};
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use wiremock::matchers::{header_regex, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_ID: &str = "00f067aa0ba902b7";
+
+fn subscriber() -> impl tracing::Subscriber + Send + Sync {
+    let tracer = SdkTracerProvider::builder().build().tracer("test");
+    tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+fn incoming() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "traceparent",
+        HeaderValue::from_str(&format!("00-{}-{}-01", TRACE_ID, PARENT_ID)).unwrap(),
+    );
+    headers.insert("tracestate", HeaderValue::from_static("vendor=abc"));
+    headers
+}
+
+#[test]
+fn test_remote_parent_is_continued() {
+    let _guard = tracing::subscriber::set_default(subscriber());
+
+    // Outside any span there is nothing to propagate
+    assert!(ark::telemetry::trace_headers().is_empty());
+
+    let span = tracing::info_span!("request");
+    ark::telemetry::set_remote_parent(&span, &incoming());
+    assert_eq!(
+        span.context().span().span_context().trace_id().to_string(),
+        TRACE_ID
+    );
+
+    let outgoing = span.in_scope(ark::telemetry::trace_headers);
+    let traceparent = outgoing["traceparent"].to_str().unwrap();
+    assert!(
+        traceparent.starts_with(&format!("00-{}-", TRACE_ID)),
+        "{}",
+        traceparent
+    );
+    assert!(!traceparent.contains(PARENT_ID), "{}", traceparent);
+    assert_eq!(outgoing["tracestate"], "vendor=abc");
+
+    // A malformed header starts a new trace instead
+    let mut bad = HeaderMap::new();
+    bad.insert("traceparent", HeaderValue::from_static("not-a-traceparent"));
+    let span = tracing::info_span!("request");
+    ark::telemetry::set_remote_parent(&span, &bad);
+    let trace_id = span.context().span().span_context().trace_id().to_string();
+    assert_ne!(trace_id, TRACE_ID);
+}
+
+#[tokio::test]
+async fn test_plugin_fetch_carries_trace_context() {
+    let _guard = tracing::subscriber::set_default(subscriber());
+    let server = MockServer::start().await;
+    let sample = std::fs::read("tests/testdata/sample.wasm").expect("read sample.wasm");
+    Mock::given(method("GET"))
+        .and(path("/sample.wasm"))
+        .and(header_regex(
+            "traceparent",
+            &format!("^00-{}-[0-9a-f]{{16}}-01$", TRACE_ID),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(sample))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let cfg: ArkConfig = serde_json::from_value(serde_json::json!({
+        "plugins": [{
+            "name": "sample-http",
+            "url": format!("{}/sample.wasm", server.uri()),
+            "insecure": true
+        }]
+    }))
+    .expect("config parse");
+    let app = Arc::new(ArkState::default());
+    app.set_state(ApplicationState::StartingNetwork);
+
+    let span = tracing::info_span!("request");
+    ark::telemetry::set_remote_parent(&span, &incoming());
+    plugins::load_plugins(&cfg, app.clone())
+        .instrument(span)
+        .await
+        .expect("plugin load");
+}
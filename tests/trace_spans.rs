@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ark::config::ArkConfig;
+use ark::config::plugins::file_path_to_url;
+use ark::plugins;
+use ark::state::{ApplicationState, ArkState};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// A span as seen by the exporter: name, fields and parent span name.
+#[derive(Debug, Clone)]
+struct Recorded {
+    name: String,
+    fields: HashMap<String, String>,
+    parent: Option<String>,
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Recorded>>>);
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+impl<S> tracing_subscriber::Layer<S> for Recorder
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let parent = ctx
+            .span(id)
+            .and_then(|span| span.parent())
+            .map(|parent| parent.name().to_string());
+        self.0.lock().unwrap().push(Recorded {
+            name: attrs.metadata().name().to_string(),
+            fields,
+            parent,
+        });
+    }
+}
+
+impl Recorder {
+    fn find(&self, name: &str) -> Recorded {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|s| s.name == name)
+            .cloned()
+            .unwrap_or_else(|| panic!("no '{}' span recorded", name))
+    }
+}
+
+#[tokio::test]
+/// Tests that plugin fetches and tool calls run in spans carrying plugin/tool attributes
+async fn plugin_fetch_and_tool_call_spans() {
+    let recorder = Recorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    let mut sample = std::env::current_dir().unwrap();
+    sample.push("tests/testdata/sample.wasm");
+    let abs = std::fs::canonicalize(&sample).unwrap();
+    let file_url = file_path_to_url(&abs.to_string_lossy()[..]).unwrap();
+    let cfg: ArkConfig = serde_json::from_value(serde_json::json!({
+        "plugins": [{ "name": "sample", "url": file_url.as_str() }]
+    }))
+    .unwrap();
+    let app = Arc::new(ArkState::default());
+    app.set_state(ApplicationState::StartingNetwork);
+    plugins::load_plugins(&cfg, app.clone()).await.unwrap();
+
+    let fetch = recorder.find("plugin.fetch");
+    assert_eq!(fetch.fields["plugin"], "sample");
+    assert!(fetch.fields["url"].ends_with("sample.wasm"));
+
+    let tool = {
+        let catalog = app.plugin_registry.catalog.load();
+        catalog
+            .tool_to_plugin
+            .iter()
+            .find(|(_, plugin)| *plugin == "sample")
+            .map(|(tool, _)| tool.clone())
+            .unwrap()
+    };
+    // The outcome doesn't matter here, only the spans around it
+    let _ = app
+        .plugin_registry
+        .call(&tool, &serde_json::json!({}))
+        .await;
+
+    let call = recorder.find("tool.call");
+    assert_eq!(call.fields["plugin"], "sample");
+    assert_eq!(call.fields["tool"], tool);
+
+    let wasm = recorder.find("wasm.execute");
+    assert_eq!(wasm.fields["tool"], tool);
+    assert_eq!(wasm.parent.as_deref(), Some("tool.call"));
+}
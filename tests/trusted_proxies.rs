@@ -0,0 +1,142 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use ark::{
+    config::models::{AuthConfig, IdentityProviderConfig, SessionConfig},
+    server::{
+        auth::AuthState,
+        handlers::session,
+        persist::Database,
+        proxy::{self, Forwarded, TrustedProxies},
+    },
+    state::ArkState,
+};
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, header},
+    middleware::{self, Next},
+};
+use tempfile::TempDir;
+use tower::ServiceExt;
+use wiremock::MockServer;
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.append(*name, value.parse().unwrap());
+    }
+    headers
+}
+
+#[test]
+fn test_resolve_trusts_only_configured_proxies() {
+    let proxies = TrustedProxies::from_config(&["10.0.0.0/8".to_string()]).unwrap();
+    let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+    let forwarded = headers(&[
+        // The leftmost entry is whatever the client sent
+        ("x-forwarded-for", "198.51.100.7, 203.0.113.9"),
+        ("x-forwarded-for", "10.0.0.5"),
+        ("x-forwarded-proto", "HTTPS"),
+        ("x-forwarded-host", "ark.example.com"),
+    ]);
+
+    assert_eq!(
+        proxies.resolve(&forwarded, ip("10.0.0.1")),
+        Some(Forwarded {
+            client: ip("203.0.113.9"),
+            proto: Some("https".to_string()),
+            host: Some("ark.example.com".to_string()),
+        })
+    );
+    assert_eq!(proxies.resolve(&forwarded, ip("192.0.2.1")), None);
+
+    // Without the header the proxy itself is the client
+    let bare = proxies.resolve(&HeaderMap::new(), ip("10.0.0.1")).unwrap();
+    assert_eq!(bare.client, ip("10.0.0.1"));
+    assert_eq!(bare.proto, None);
+
+    let err = TrustedProxies::from_config(&["10.0.0.0/33".to_string()]).unwrap_err();
+    assert!(format!("{err:#}").contains("Invalid trusted_proxies entry"));
+}
+
+/// Builds `/auth` with an OIDC provider behind the forwarded header
+/// middleware, seeing every request as coming from `peer`.
+async fn setup(server: &MockServer, peer: &str) -> (Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let app_state = Arc::new(ArkState::default());
+    app_state.set_database(Database::with_path(temp_dir.path().join("proxy.db")).unwrap());
+    let cfg = AuthConfig {
+        enabled: true,
+        provider: Some("corp".to_string()),
+        providers: vec![IdentityProviderConfig {
+            name: "corp".to_string(),
+            client_id: "corp-client".to_string(),
+            authority: server.uri(),
+            discovery: false,
+            jwks_uri: Some(format!("{}/jwks", server.uri())),
+            authorization_endpoint: Some(format!("{}/authorize", server.uri())),
+            token_endpoint: Some(format!("{}/token", server.uri())),
+            ..Default::default()
+        }],
+        session: Some(SessionConfig::default()),
+        ..Default::default()
+    };
+    let auth_state = Arc::new(
+        AuthState::new_with_state(&Some(cfg), app_state, None)
+            .await
+            .unwrap(),
+    );
+    let proxies = Arc::new(TrustedProxies::from_config(&["127.0.0.1".to_string()]).unwrap());
+    let peer: SocketAddr = peer.parse().unwrap();
+    let router = Router::new()
+        .nest("/auth", session::router(auth_state))
+        .layer(middleware::from_fn(move |req: Request, next: Next| {
+            let proxies = proxies.clone();
+            async move { proxy::resolve_forwarded(&proxies, req, next).await }
+        }))
+        .layer(Extension(ConnectInfo(peer)));
+    (router, temp_dir)
+}
+
+/// Returns the `redirect_uri` sent to the IdP when signing in.
+async fn login_redirect_uri(router: Router) -> String {
+    let resp = router
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login?mode=redirect")
+                .header(header::HOST, "10.1.2.3:8000")
+                .header("x-forwarded-proto", "http")
+                .header("x-forwarded-host", "ark.example.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let location = resp.headers()[header::LOCATION].to_str().unwrap();
+    url::Url::parse(location)
+        .unwrap()
+        .query_pairs()
+        .find(|(k, _)| k == "redirect_uri")
+        .map(|(_, v)| v.into_owned())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_callback_url_follows_trusted_proxy_headers() {
+    let server = MockServer::start().await;
+
+    let (router, _tmp) = setup(&server, "127.0.0.1:40000").await;
+    assert_eq!(
+        login_redirect_uri(router).await,
+        "http://ark.example.com/auth/callback"
+    );
+
+    // Other peers cannot redirect the callback to a host of their choosing
+    let (router, _tmp) = setup(&server, "192.0.2.1:40000").await;
+    assert_eq!(
+        login_redirect_uri(router).await,
+        "https://10.1.2.3:8000/auth/callback"
+    );
+}
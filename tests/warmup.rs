@@ -0,0 +1,54 @@
+use ark::config::plugins::{ArkPlugin, PluginManifest};
+use ark::plugins::UriHandler;
+use ark::plugins::wasm::WasmHandler;
+use serde_json::json;
+
+/// Loads the warm-up test plugin and returns what its `status` tool reports.
+async fn status(warmup: bool) -> serde_json::Value {
+    let bytes = std::fs::read("tests/testdata/warmup_plugin.wat").unwrap();
+    let manifest: PluginManifest = serde_json::from_value(json!({ "warmup": warmup })).unwrap();
+    let plugin = ArkPlugin {
+        name: "warmup".to_string(),
+        url: Some("file://tests/testdata/warmup_plugin.wat".parse().unwrap()),
+        auth: None,
+        insecure: false,
+        manifest: Some(manifest),
+        owner: None,
+        tools_allow: None,
+        tools_deny: None,
+        sha256: None,
+    };
+    let handler = WasmHandler::new(bytes, &plugin.name, &plugin.manifest).unwrap();
+    let loaded = handler.get(&plugin).await.unwrap();
+    let (_, status) = loaded
+        .executors
+        .into_iter()
+        .find(|(name, _)| name == "status")
+        .unwrap();
+    status(json!({})).await.unwrap()["content"][0]["text"].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_warmup_export_runs_when_plugin_loads() {
+    assert_eq!(status(true).await, "warm");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_plugins_loaded_from_files_are_warmed_up() {
+    let path = std::fs::canonicalize("tests/testdata/warmup_plugin.wat").unwrap();
+    let plugin: ArkPlugin = serde_json::from_value(json!({
+        "name": "warmup-file",
+        "url": ark::config::plugins::file_path_to_url(&path).unwrap(),
+        "manifest": { "warmup": true },
+    }))
+    .unwrap();
+    let loaded = ark::plugins::read_plugin_data(&plugin).await.unwrap();
+    let (_, status) = &loaded.executors[0];
+    let out = status(json!({})).await.unwrap();
+    assert_eq!(out["content"][0]["text"], "warm");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_warmup_export_is_not_called_unless_configured() {
+    assert_eq!(status(false).await, "cold");
+}
@@ -0,0 +1,32 @@
+use ark::config::models::{WasmAllocator, WasmEngineConfig};
+use ark::plugins::wasm::{WasmHandler, configure_engine};
+use serde_json::json;
+
+// The engine settings are process-wide, so every case runs in this one test.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pooling_allocator() {
+    let config: WasmEngineConfig =
+        serde_json::from_value(json!({ "allocator": "pooling" })).unwrap();
+    assert_eq!(config.allocator, WasmAllocator::Pooling);
+    assert_eq!(config.max_instances, 16);
+    assert!(!config.track_memory);
+    configure_engine(config.clone());
+
+    // Pooled plugins compute the same answer
+    let bytes = std::fs::read("tests/testdata/sample2.wasm").unwrap();
+    let sha256 = WasmHandler::new(bytes.clone(), "pooled", &None)
+        .unwrap()
+        .build_executor("sha256");
+    let out = sha256(json!({ "data": "hello" })).await.unwrap();
+    assert_eq!(
+        out["content"][0]["text"],
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+
+    // The reservation bounds each memory, so a plugin needing more fails to load
+    configure_engine(WasmEngineConfig {
+        memory_reservation: Some(64 * 1024),
+        ..config
+    });
+    assert!(WasmHandler::new(bytes, "too-small", &None).is_err());
+}
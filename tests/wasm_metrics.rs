@@ -0,0 +1,83 @@
+use ark::config::models::WasmEngineConfig;
+use ark::config::plugins::PluginManifest;
+use ark::plugins::wasm::{WasmHandler, configure_engine};
+use serde_json::json;
+
+fn hasher(name: &str, fuel_limit: Option<u64>) -> WasmHandler {
+    let bytes = std::fs::read("tests/testdata/sample2.wasm").unwrap();
+    let manifest: PluginManifest =
+        serde_json::from_value(json!({ "fuel_limit": fuel_limit })).unwrap();
+    WasmHandler::new(bytes, name, &Some(manifest)).unwrap()
+}
+
+// The metrics recorder is process-wide, so every case runs in this one test.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_wasm_resource_metrics() {
+    #[cfg(feature = "prometheus")]
+    ark::metrics::init(None);
+    configure_engine(WasmEngineConfig {
+        track_memory: true,
+        ..Default::default()
+    });
+
+    // Plugins run in tracked memory and still compute the right answer
+    let sha256 = hasher("hasher", Some(1_000_000_000)).build_executor("sha256");
+    let out = sha256(json!({ "data": "hello" })).await.unwrap();
+    assert_eq!(
+        out["content"][0]["text"],
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+
+    // Running out of fuel aborts the call
+    let starved = hasher("hasher", Some(10)).build_executor("sha256");
+    let err = starved(json!({ "data": "hello" })).await.unwrap_err();
+    assert!(err.message.contains("fuel"), "{}", err.message);
+
+    #[cfg(feature = "prometheus")]
+    {
+        use http_body_util::BodyExt;
+
+        let body = ark::metrics::handler::make_metrics_response()
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let value = |series: &str| -> f64 {
+            text.lines()
+                .find(|l| l.starts_with(series))
+                .unwrap_or_else(|| panic!("no {} in\n{}", series, text))
+                .rsplit(' ')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+        assert_eq!(
+            value("ark_wasm_instantiation_ms_count{plugin=\"hasher\"}"),
+            2.0
+        );
+        assert_eq!(
+            value("ark_wasm_memory_peak_bytes_count{plugin=\"hasher\"}"),
+            2.0
+        );
+        // At least one 64KiB page is in use
+        assert!(value("ark_wasm_memory_peak_bytes_sum{plugin=\"hasher\"}") >= 65536.0);
+        assert_eq!(
+            value("ark_wasm_fuel_consumed_count{plugin=\"hasher\"}"),
+            2.0
+        );
+        assert!(value("ark_wasm_fuel_consumed_sum{plugin=\"hasher\"}") > 10.0);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuel_limit_is_optional() {
+    let manifest: PluginManifest = serde_json::from_value(json!({})).unwrap();
+    assert!(manifest.fuel_limit.is_none());
+
+    // Without a limit calls are unmetered
+    let sha256 = hasher("unmetered", None).build_executor("sha256");
+    assert!(sha256(json!({ "data": "hello" })).await.is_ok());
+}
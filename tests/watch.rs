@@ -0,0 +1,177 @@
+#![cfg(unix)]
+
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use rcgen::CertifiedKey;
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Updates `dir` as Kubernetes updates a ConfigMap or Secret volume: the
+/// files are written to a new directory, which the `..data` link is then
+/// switched to, and each name links to its file through `..data`.
+fn publish(dir: &Path, version: u32, files: &[(&str, &str)]) {
+    let data = dir.join(format!("..{version}"));
+    std::fs::create_dir(&data).unwrap();
+    for (name, contents) in files {
+        std::fs::write(data.join(name), contents).unwrap();
+    }
+    let staged = dir.join("..data_tmp");
+    std::os::unix::fs::symlink(data.file_name().unwrap(), &staged).unwrap();
+    std::fs::rename(&staged, dir.join("..data")).unwrap();
+    for (name, _) in files {
+        let _ = std::os::unix::fs::symlink(Path::new("..data").join(name), dir.join(name));
+    }
+}
+
+fn certificate() -> (String, String) {
+    let CertifiedKey { cert, signing_key } =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    (cert.pem(), signing_key.serialize_pem())
+}
+
+fn config(management: u16, mcp: u16, cors: &str) -> String {
+    format!(
+        r#"
+plugins: []
+watch:
+  enabled: true
+  interval_secs: 1
+management_server:
+  bind_address: "127.0.0.1:{management}"
+  cors: "{cors}"
+  livez:
+    enabled: true
+    path: /livez
+mcp_server:
+  bind_address: "127.0.0.1:{mcp}"
+  tls:
+    key: secret/tls.key
+    cert: secret/tls.crt
+"#
+    )
+}
+
+fn start(dir: &TempDir) -> Server {
+    Server(
+        Command::new(env!("CARGO_BIN_EXE_ark"))
+            .current_dir(dir.path())
+            .args(["--config-file", "config/ark.yaml"])
+            .env("ARK_TRANSPORT", "streamable-http")
+            .env("ARK_DB_PATH", dir.path().join("ark.db"))
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap(),
+    )
+}
+
+async fn wait_for_port(port: u16) {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_err()
+    {
+        assert!(Instant::now() < deadline, "port {port} not listening");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Polls `check` until it holds, for up to 15 seconds.
+async fn eventually<F: Future<Output = bool>>(mut check: impl FnMut() -> F) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while Instant::now() < deadline {
+        if check().await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn test_mounted_config_and_certificate_updates_apply_without_restart() {
+    let dir = TempDir::new().unwrap();
+    let (config_dir, secret_dir) = (dir.path().join("config"), dir.path().join("secret"));
+    std::fs::create_dir(&config_dir).unwrap();
+    std::fs::create_dir(&secret_dir).unwrap();
+    let (management, mcp) = (free_port(), free_port());
+    let (old_cert, old_key) = certificate();
+    publish(
+        &config_dir,
+        1,
+        &[("ark.yaml", &config(management, mcp, "https://old.example"))],
+    );
+    publish(
+        &secret_dir,
+        1,
+        &[("tls.crt", &old_cert), ("tls.key", &old_key)],
+    );
+    let _server = start(&dir);
+    wait_for_port(management).await;
+    wait_for_port(mcp).await;
+
+    let allowed = |origin: &'static str| async move {
+        reqwest::Client::new()
+            .get(format!("http://127.0.0.1:{management}/livez"))
+            .header("Origin", origin)
+            .send()
+            .await
+            .unwrap()
+            .headers()
+            .get("access-control-allow-origin")
+            .is_some_and(|v| v == origin)
+    };
+    assert!(allowed("https://old.example").await);
+    assert!(!allowed("https://new.example").await);
+
+    publish(
+        &config_dir,
+        2,
+        &[("ark.yaml", &config(management, mcp, "https://new.example"))],
+    );
+    assert!(
+        eventually(|| allowed("https://new.example")).await,
+        "configuration change not applied"
+    );
+
+    let (new_cert, new_key) = certificate();
+    publish(
+        &secret_dir,
+        2,
+        &[("tls.crt", &new_cert), ("tls.key", &new_key)],
+    );
+    let trusted = |pem: String| async move {
+        reqwest::Client::builder()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(reqwest::Certificate::from_pem(pem.as_bytes()).unwrap())
+            .build()
+            .unwrap()
+            .get(format!("https://localhost:{mcp}/"))
+            .send()
+            .await
+            .is_ok()
+    };
+    assert!(
+        eventually(|| trusted(new_cert.clone())).await,
+        "new certificate not served"
+    );
+    assert!(!trusted(old_cert).await);
+}
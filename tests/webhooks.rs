@@ -0,0 +1,189 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark::config::models::{WebhookEndpointConfig, WebhooksConfig};
+use ark::config::plugins::ArkPlugin;
+use ark::errors::ErrorCode;
+use ark::plugins::ToolSet;
+use ark::server::{
+    usage::{ToolCall, record_tool_call},
+    webhooks,
+};
+use ark::state::ArkState;
+use hmac::{Hmac, Mac};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+async fn register(state: &ArkState, plugin: &str) {
+    state
+        .register_plugin_with_executors(
+            ArkPlugin {
+                name: plugin.to_string(),
+                ..Default::default()
+            },
+            ToolSet {
+                name: plugin.to_string(),
+                tools: vec![],
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+}
+
+/// Waits until `server` has received `count` requests on `route`.
+async fn received(server: &MockServer, route: &str, count: usize) -> Vec<Request> {
+    for _ in 0..100 {
+        let requests: Vec<Request> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.url.path() == route)
+            .collect();
+        if requests.len() >= count {
+            return requests;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("expected {} webhook requests on {}", count, route);
+}
+
+#[tokio::test]
+async fn test_webhooks_for_plugin_changes_and_failing_tools() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&server)
+        .await;
+
+    let state = Arc::new(ArkState::default());
+    register(&state, "existing").await;
+    webhooks::start_webhook_task(
+        state.clone(),
+        &WebhooksConfig {
+            endpoints: vec![
+                WebhookEndpointConfig {
+                    url: format!("{}/all", server.uri()),
+                    secret: Some("s3cret".to_string()),
+                    secret_file: None,
+                    events: vec![],
+                },
+                WebhookEndpointConfig {
+                    url: format!("{}/failing", server.uri()),
+                    secret: None,
+                    secret_file: None,
+                    events: vec!["tool.failing".to_string()],
+                },
+            ],
+            failure_threshold: 2,
+        },
+    );
+    // Let the task take its snapshot of the registered plugins
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    register(&state, "fresh").await;
+    register(&state, "existing").await;
+    // A success resets the count, so only the second run of failures notifies
+    for is_error in [true, false, true, true, true] {
+        let call = ToolCall {
+            plugin_id: "fresh",
+            tool: "flaky",
+            owner: None,
+            principal: None,
+            args: &json!({}),
+            error: is_error.then_some(ErrorCode::ToolFailed),
+            latency_ms: 1.0,
+            bytes_returned: 0,
+        };
+        record_tool_call(&state, call).await;
+    }
+    assert!(state.unregister_plugin("fresh").await.unwrap());
+
+    let all = received(&server, "/all", 4).await;
+    let mut events = Vec::new();
+    for request in &all {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        let event = body["event"].as_str().unwrap().to_string();
+        assert_eq!(request.headers["x-ark-event"], event.as_str());
+        assert!(body["text"].as_str().is_some_and(|t| !t.is_empty()));
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(&request.body);
+        let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        assert_eq!(request.headers["x-ark-signature"], expected.as_str());
+
+        match event.as_str() {
+            "plugin.created" | "plugin.deleted" => assert_eq!(body["data"]["plugin"], "fresh"),
+            "plugin.updated" => assert_eq!(body["data"]["plugin"], "existing"),
+            "tool.failing" => {
+                assert_eq!(body["data"]["tool"], "flaky");
+                assert_eq!(body["data"]["owner"], "*/*/*");
+                assert_eq!(body["data"]["consecutive_failures"], 2);
+            }
+            other => panic!("unexpected event {}", other),
+        }
+        events.push(event);
+    }
+    events.sort();
+    assert_eq!(
+        events,
+        [
+            "plugin.created",
+            "plugin.deleted",
+            "plugin.updated",
+            "tool.failing"
+        ]
+    );
+
+    let failing = received(&server, "/failing", 1).await;
+    assert!(!failing[0].headers.contains_key("x-ark-signature"));
+    // Nothing else arrives
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(received(&server, "/all", 4).await.len(), 4);
+    assert_eq!(received(&server, "/failing", 1).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_deliveries_are_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let state = Arc::new(ArkState::default());
+    webhooks::start_webhook_task(
+        state.clone(),
+        &WebhooksConfig {
+            endpoints: vec![WebhookEndpointConfig {
+                url: format!("{}/hook", server.uri()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    register(&state, "fresh").await;
+
+    let requests = received(&server, "/hook", 2).await;
+    assert_eq!(requests[0].body, requests[1].body);
+}
+
+#[test]
+fn test_sign() {
+    // RFC 4231 test case 2
+    assert_eq!(
+        webhooks::sign("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
@@ -19,7 +19,7 @@ import { ThroughputCard } from '@/components/ui/metrics/ThroughputCard'
 import { ToolCallsCard } from '@/components/ui/metrics/ToolCallsCard'
 import { ToolLatencyCard } from '@/components/ui/metrics/ToolLatencyCard'
 import { LatencyOverTimeCard } from '@/components/ui/metrics/LatencyOverTimeCard'
-import { subscribe, AuthState } from '@/lib/auth'
+import { subscribe, hasPermission, AuthState } from '@/lib/auth'
 
 type ToolItem = { name: string; description?: string; inputSchema?: any }
 type TransportMode = 'streamable-http' | 'sse'
@@ -98,6 +98,19 @@ export default function App() {
     }
   }
 
+  if (auth.authenticated && !hasPermission(auth, 'console.access')) {
+    return (
+      <div className="min-h-screen bg-background text-foreground relative flex flex-col">
+        <TitleBar />
+        <div className="mx-auto max-w-screen-2xl px-4 py-6 w-full flex-1">
+          <AlertBanner variant="destructive">
+            Your account does not have access to the console.
+          </AlertBanner>
+        </div>
+      </div>
+    )
+  }
+
   return (
     <div className="min-h-screen bg-background text-foreground relative flex flex-col">
       {/* Title Bar */}
@@ -108,7 +121,7 @@ export default function App() {
         <Tabs value={activeTab} onValueChange={(v) => setActiveTab(v)}>
           <TabsList className="mb-4">
             <TabsTrigger value="content"><Box className="ark-tab-icon" /> Content</TabsTrigger>
-            <TabsTrigger value="metrics" disabled={!auth.user?.is_admin}><ChartNoAxesCombined className="ark-tab-icon" /> Stats</TabsTrigger>
+            <TabsTrigger value="metrics" disabled={!auth.authenticated || !hasPermission(auth, 'metrics.read')}><ChartNoAxesCombined className="ark-tab-icon" /> Stats</TabsTrigger>
           </TabsList>
           <TabsContent value="content" className="m-0">
             {errorMessage ? (
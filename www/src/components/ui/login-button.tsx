@@ -1,6 +1,7 @@
 import { useEffect, useState } from 'react'
 import { User } from 'lucide-react'
 import { subscribe, refreshStatus, logout, ensureLogin, AuthState, initialStatusCheck } from '@/lib/auth'
+import { getBasePath } from '@/lib/config'
 import { Avatar, AvatarFallback, AvatarImage } from '@/components/ui/avatar'
 import { Badge } from '@/components/ui/badge'
 import {
@@ -15,7 +16,7 @@ export function LoginButton() {
     const [auth, setAuth] = useState<AuthState>({ authenticated: false })
     useEffect(() => {
         const unsub = subscribe(setAuth)
-        initialStatusCheck('')
+        initialStatusCheck()
         return unsub
     }, [])
 
@@ -110,7 +111,7 @@ export function LoginButton() {
                         <DropdownMenuSeparator />
 
                         <DropdownMenuItem
-                            onClick={() => logout('').then(() => window.location.href = '/admin')}
+                            onClick={() => logout().then(() => window.location.href = `${getBasePath()}/admin`)}
                             className="cursor-pointer"
                         >
                             Sign out
@@ -119,7 +120,7 @@ export function LoginButton() {
                 </DropdownMenu>
             ) : (
                 <button
-                    onClick={() => ensureLogin('')}
+                    onClick={() => ensureLogin()}
                     className="text-xs px-2 py-1 rounded-md border ark-border-dimmed hover:bg-accent transition-colors"
                     aria-label="Login"
                     title="Sign in with Microsoft Entra ID"
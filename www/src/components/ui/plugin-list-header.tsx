@@ -37,14 +37,14 @@ export function pluginListHeader({ onError, onRefresh, auth }: { onError?: (msg:
                 name = (seg.split("/").pop() || seg).replace(/\.[^/.]+$/, "") || "plugin"
             }
             const apiBase = getApiBase()
-            const postUrl = `${apiBase}/api/plugins`
+            const postUrl = `${apiBase}/api/v1/plugins`
             await axios.post(postUrl, { name, url: raw })
             if (import.meta.env.DEV) console.debug("Plugin POSTed from URL:", { name, url: raw })
             setUrlText("")
             onRefresh?.()
         } catch (err: any) {
             const apiBase = getApiBase()
-            const postUrl = `${apiBase}/api/plugins`
+            const postUrl = `${apiBase}/api/v1/plugins`
             const msg = `${typeof err?.message === 'string' ? err.message : 'Failed to add plugin from URL.'} (url: ${postUrl})`
             onError?.(msg)
         } finally {
@@ -112,7 +112,7 @@ export function pluginListHeader({ onError, onRefresh, auth }: { onError?: (msg:
             const absolutePath = rawPath
 
             const apiBase = getApiBase()
-            const url = `${apiBase}/api/plugins`
+            const url = `${apiBase}/api/v1/plugins`
             await axios.post(url, {
                 name: baseName,
                 url: absolutePath,
@@ -124,7 +124,7 @@ export function pluginListHeader({ onError, onRefresh, auth }: { onError?: (msg:
         } catch (err: any) {
             console.error("Failed to add plugin from local file:", err)
             const apiBase = getApiBase()
-            const url = `${apiBase}/api/plugins`
+            const url = `${apiBase}/api/v1/plugins`
             const msg = `${typeof err?.message === 'string' ? err.message : 'Failed to add plugin.'} (url: ${url})`
             onError?.(msg)
         }
@@ -43,12 +43,12 @@ export function pluginsList({ onSelectionChange, onError }: PluginsListProps = {
                 setLoading(true)
                 setError(null)
                 const apiBase = getApiBase()
-                const url = `${apiBase}/api/plugins`
+                const url = `${apiBase}/api/v1/plugins`
                 const res = await axios.get(url, {
                     headers: { 'Accept': 'application/json' },
                 })
                 if (import.meta.env.DEV) {
-                    //console.debug('GET /api/plugins →', res.status, res.data)
+                    //console.debug('GET /api/v1/plugins →', res.status, res.data)
                 }
                 const data = res.data
                 if (cancelled) return
@@ -130,10 +130,10 @@ export function pluginsList({ onSelectionChange, onError }: PluginsListProps = {
                 setItems(normalized)
             } catch (e: any) {
                 if (import.meta.env.DEV) {
-                    console.error('GET /api/plugins failed', e)
+                    console.error('GET /api/v1/plugins failed', e)
                 }
                 const apiBase = getApiBase()
-                const url = `${apiBase}/api/plugins`
+                const url = `${apiBase}/api/v1/plugins`
                 const msg = `${toMsg(e, 'Failed to load plugins')} (url: ${url})`
                 if (!cancelled) setError(msg)
                 onError?.(msg)
@@ -149,7 +149,7 @@ export function pluginsList({ onSelectionChange, onError }: PluginsListProps = {
         if (!auth.authenticated) return
         try {
             const apiBase = getApiBase()
-            const url = `${apiBase}/api/plugins`
+            const url = `${apiBase}/api/v1/plugins`
             const res = await axios.get(url, { headers: { 'Accept': 'application/json' } })
             const data = res.data
             // Reuse minimal normalization: prefer object map, else array forms
@@ -212,23 +212,33 @@ export function pluginsList({ onSelectionChange, onError }: PluginsListProps = {
             normalized.sort((a, b) => a.name.localeCompare(b.name))
             setItems(normalized)
         } catch (e) {
-            if (import.meta.env.DEV) console.error('GET /api/plugins failed on refresh', e)
+            if (import.meta.env.DEV) console.error('GET /api/v1/plugins failed on refresh', e)
         }
     }
 
+    // Reload when plugins change elsewhere, instead of polling
+    useEffect(() => {
+        if (!auth.authenticated || typeof EventSource === 'undefined') return
+        const source = new EventSource(`${getApiBase()}/api/v1/events`, { withCredentials: true })
+        for (const name of ['plugin_registered', 'plugin_unregistered', 'lagged']) {
+            source.addEventListener(name, () => { refreshPlugins() })
+        }
+        return () => source.close()
+    }, [auth.authenticated])
+
     async function handleDelete(name: string) {
         try {
             const apiBase = getApiBase()
-            const url = `${apiBase}/api/plugins/${encodeURIComponent(name)}`
+            const url = `${apiBase}/api/v1/plugins/${encodeURIComponent(name)}`
             await axios.delete(url)
         } catch (e) {
-            if (import.meta.env.DEV) console.error('DELETE /api/plugins failed', e)
+            if (import.meta.env.DEV) console.error('DELETE /api/v1/plugins failed', e)
             const err: any = e
             const status = err?.response?.status
             const statusText = err?.response?.statusText
             const detail = err?.response?.data?.message || err?.message
             const apiBase = getApiBase()
-            const url = `${apiBase}/api/plugins/${encodeURIComponent(name)}`
+            const url = `${apiBase}/api/v1/plugins/${encodeURIComponent(name)}`
             const msg = [
                 `Failed to delete plugin "${name}"`,
                 status ? `(${status}${statusText ? ` ${statusText}` : ''})` : '',
@@ -242,7 +252,7 @@ export function pluginsList({ onSelectionChange, onError }: PluginsListProps = {
         // Refresh list
         try {
             const apiBase = getApiBase()
-            const url = `${apiBase}/api/plugins`
+            const url = `${apiBase}/api/v1/plugins`
             const res = await axios.get(url, { headers: { 'Accept': 'application/json' } })
             const data = res.data
             // Reuse normalization logic by mimicking load, but inline to avoid refactor
@@ -311,13 +321,13 @@ export function pluginsList({ onSelectionChange, onError }: PluginsListProps = {
             normalized.sort((a, b) => a.name.localeCompare(b.name))
             setItems(normalized)
         } catch (e) {
-            if (import.meta.env.DEV) console.error('GET /api/plugins failed after delete', e)
+            if (import.meta.env.DEV) console.error('GET /api/v1/plugins failed after delete', e)
             const err: any = e
             const status = err?.response?.status
             const statusText = err?.response?.statusText
             const detail = err?.response?.data?.message || err?.message
             const apiBase = getApiBase()
-            const url = `${apiBase}/api/plugins`
+            const url = `${apiBase}/api/v1/plugins`
             const msg = [
                 'Failed to refresh plugins after delete',
                 status ? `(${status}${statusText ? ` ${statusText}` : ''})` : '',
@@ -2,8 +2,25 @@
 // Auto-start login when a protected fetch returns 401 with { auth: 'required' }.
 // Server handles PKCE and token exchange internally.
 
+import axios from 'axios'
+import { getBasePath } from '@/lib/config'
+
+// State-changing API calls made with the session cookie must echo the CSRF
+// token the server hands out in the (script-readable) ark_csrf cookie.
+const CSRF_COOKIE = 'ark_csrf'
+const CSRF_HEADER = 'X-CSRF-Token'
+axios.defaults.xsrfCookieName = CSRF_COOKIE
+axios.defaults.xsrfHeaderName = CSRF_HEADER
+// The API may live on another port than the console (dev server, settings override)
+axios.defaults.withXSRFToken = true
+
+export function csrfToken(): string | undefined {
+    const prefix = `${CSRF_COOKIE}=`
+    return document.cookie.split(';').map(c => c.trim()).find(c => c.startsWith(prefix))?.slice(prefix.length)
+}
+
 export interface AuthUser { subject: string; email?: string; name?: string; picture?: string; provider: string; roles?: string[]; is_admin?: boolean }
-export interface AuthState { authenticated: boolean; user?: AuthUser; auth_disabled?: boolean }
+export interface AuthState { authenticated: boolean; user?: AuthUser; auth_disabled?: boolean; permissions?: string[] }
 
 let current: AuthState = { authenticated: false }
 let listeners: Array<(s: AuthState) => void> = []
@@ -16,16 +33,16 @@ export function subscribe(listener: (s: AuthState) => void) {
     return () => { listeners = listeners.filter(l => l !== listener) }
 }
 
-export async function refreshStatus(base = '') {
+export async function refreshStatus(base = getBasePath()) {
     // Don't auto-trigger login on callback page to prevent infinite loops
-    const isCallbackPage = window.location.pathname === '/auth/callback'
+    const isCallbackPage = window.location.pathname.endsWith('/auth/callback')
 
     try {
         const res = await fetch(`${base}/auth/status`, { credentials: 'include' })
         if (res.ok) {
             const data = await res.json()
             if (data.user) {
-                current = { authenticated: true, user: data.user, auth_disabled: data.auth_disabled }
+                current = { authenticated: true, user: data.user, auth_disabled: data.auth_disabled, permissions: data.permissions }
             } else {
                 current = { authenticated: false, auth_disabled: data.auth_disabled }
             }
@@ -51,13 +68,13 @@ export async function refreshStatus(base = '') {
 }
 
 // Initial status check without auto-login trigger
-export async function initialStatusCheck(base = '') {
+export async function initialStatusCheck(base = getBasePath()) {
     try {
         const res = await fetch(`${base}/auth/status`, { credentials: 'include' })
         if (res.ok) {
             const data = await res.json()
             if (data.user) {
-                current = { authenticated: true, user: data.user, auth_disabled: data.auth_disabled }
+                current = { authenticated: true, user: data.user, auth_disabled: data.auth_disabled, permissions: data.permissions }
             } else {
                 current = { authenticated: false, auth_disabled: data.auth_disabled }
             }
@@ -70,7 +87,14 @@ export async function initialStatusCheck(base = '') {
     notify()
 }
 
-export async function logout(base = '') {
+// Whether the current user holds a permission (e.g. 'metrics.read').
+// Servers that do not report permissions are treated as granting everything.
+export function hasPermission(state: AuthState, permission: string): boolean {
+    if (state.auth_disabled || !state.permissions) return true
+    return state.permissions.includes(permission)
+}
+
+export async function logout(base = getBasePath()) {
     try {
         const res = await fetch(`${base}/auth/logout`, { credentials: 'include' })
         if (res.ok) {
@@ -87,7 +111,7 @@ export async function logout(base = '') {
     notify()
 }
 
-export async function ensureLogin(base = ''): Promise<void> {
+export async function ensureLogin(base = getBasePath()): Promise<void> {
     if (current.authenticated) return
     try {
         // Server handles PKCE and provider discovery internally
@@ -107,7 +131,11 @@ export async function ensureLogin(base = ''): Promise<void> {
 }
 
 export async function protectedFetch(input: RequestInfo | URL, init?: RequestInit & { base?: string }) {
-    const res = await fetch(input, { ...init, credentials: 'include' })
+    const headers = new Headers(init?.headers)
+    const method = (init?.method || 'GET').toUpperCase()
+    const token = csrfToken()
+    if (token && !['GET', 'HEAD', 'OPTIONS'].includes(method)) headers.set(CSRF_HEADER, token)
+    const res = await fetch(input, { ...init, headers, credentials: 'include' })
     if (res.status === 401) {
         try {
             const data = await res.clone().json()
@@ -120,4 +148,4 @@ export async function protectedFetch(input: RequestInfo | URL, init?: RequestIni
 }
 
 // Kick initial status poll (without auto-login)
-initialStatusCheck('')
+initialStatusCheck()
@@ -5,6 +5,13 @@ function ensureProtocol(hostOrUrl: string): string {
     return `${proto}//${hostOrUrl}`
 }
 
+// Path prefix the server is mounted under (management_server.base_path),
+// injected into index.html by the server; empty at the root
+export function getBasePath(): string {
+    if (typeof window === 'undefined') return ''
+    return ((window as any).ARK_BASE_PATH as string | undefined) || ''
+}
+
 export function getApiBase(): string {
     // Optional UI override from Settings
     try {
@@ -26,10 +33,10 @@ export function getApiBase(): string {
     const fromEnv = directVite || directRaw || indirect
 
     if (fromEnv && fromEnv.trim().length > 0) return ensureProtocol(fromEnv.trim())
-    // default: same as React app origin
+    // default: same as React app origin, under the server's base path
     if (typeof window !== 'undefined') {
         const { origin } = window.location
-        return origin
+        return `${origin}${getBasePath()}`
     }
     return 'http://localhost:8000'
 }
@@ -11,6 +11,19 @@ export default defineConfig(({ mode }) => {
   const mcp = process.env.VITE_ARK_SERVER_MCP || process.env.ARK_SERVER_MCP || env.VITE_ARK_SERVER_MCP || env.ARK_SERVER_MCP || ""
   return {
     base: "/admin/",
+    // The server may be mounted under a base path known only at runtime:
+    // scripts load assets below window.ARK_BASE_PATH, styles relatively.
+    // The server rewrites the URLs in index.html itself.
+    experimental: {
+      renderBuiltUrl(filename, { hostType }) {
+        if (hostType === "js") {
+          return { runtime: `(window.ARK_BASE_PATH || "") + "/admin/" + ${JSON.stringify(filename)}` }
+        }
+        if (hostType === "css") {
+          return { relative: true }
+        }
+      },
+    },
     plugins: [react(), tailwindcss()],
     resolve: {
       alias: {